@@ -1,7 +1,6 @@
 use std::convert::TryFrom;
 use std::env;
 use std::error::Error;
-use std::f32::consts::PI;
 use std::path::Path;
 use std::process::exit;
 use std::thread::sleep;
@@ -37,10 +36,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     )?;
 
     loop {
-        matrix.angle += 0.05;
-        if matrix.angle > PI * 2.0 {
-            matrix.angle = 0.0;
-        }
+        matrix.rotate(0.05);
         matrix.update();
 
         proxy.write(<AnimeDataBuffer>::try_from(&matrix)?).unwrap();
@@ -17,6 +17,30 @@ pub struct AnimeCommand {
         help = "set global base brightness value <Off, Low, Med, High>"
     )]
     pub brightness: Option<Brightness>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "set the brightness from the ambient light sensor"
+    )]
+    pub auto_brightness: Option<bool>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "use the day and night brightness schedule set in anime.ron"
+    )]
+    pub schedule: Option<bool>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "keep the current brightness until the schedule next changes"
+    )]
+    pub schedule_override: Option<bool>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "the most frames a second written to the display, 1 to 60"
+    )]
+    pub max_fps: Option<u32>,
     #[options(help = "clear the display")]
     pub clear: bool,
     #[options(
@@ -37,6 +61,19 @@ pub struct AnimeCommand {
         help = "turn the anime off when the lid is closed"
     )]
     pub off_when_lid_closed: Option<bool>,
+    #[options(
+        no_short,
+        meta = "",
+        help = "briefly show an icon <mail, chat, alert, bell, battery> or a short text"
+    )]
+    pub blip: Option<String>,
+    #[options(
+        no_short,
+        meta = "",
+        default = "3000",
+        help = "how long to show the blip in ms"
+    )]
+    pub blip_ms: u32,
     #[options(no_short, meta = "", help = "Off with his head!!!")]
     pub off_with_his_head: Option<bool>,
     #[options(command)]
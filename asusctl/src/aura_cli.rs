@@ -1,8 +1,27 @@
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use gumdrop::Options;
 use rog_aura::error::Error;
-use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, Direction, Speed};
+use rog_aura::keyboard::AuraPowerState;
+use rog_aura::palette::{Palettes, PALETTE_FILE};
+use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, Direction, PowerZones, Speed};
+use rog_platform::platform::PlatformProfile;
+
+/// The saved colours, shared with ROG Control Center
+pub fn palette_path() -> PathBuf {
+    let mut path = dirs::config_dir().unwrap_or_default();
+    path.push("rog");
+    path.push(PALETTE_FILE);
+    path
+}
+
+/// Parse a colour option given as hex or as a saved colour's name
+pub fn parse_colour(s: &str) -> Result<Colour, Error> {
+    Palettes::load(&palette_path())
+        .unwrap_or_default()
+        .parse_colour(s)
+}
 
 #[derive(Options, Debug)]
 pub struct LedPowerCommand1 {
@@ -136,7 +155,12 @@ pub struct SingleSpeedDirection {
 pub struct SingleColour {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff, mine.accent"
+    )]
     pub colour: Colour,
     #[options(
         no_long,
@@ -150,7 +174,12 @@ pub struct SingleColour {
 pub struct SingleColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff, mine.accent"
+    )]
     pub colour: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -166,9 +195,19 @@ pub struct SingleColourSpeed {
 pub struct TwoColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(no_long, meta = "", help = "set the first RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the first RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour: Colour,
-    #[options(no_long, meta = "", help = "set the second RGB value e.g, ff00ff")]
+    #[options(
+        no_long,
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the second RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour2: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -184,13 +223,33 @@ pub struct TwoColourSpeed {
 pub struct MultiZone {
     #[options(help = "print help message")]
     help: bool,
-    #[options(short = "a", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "a",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour1: Colour,
-    #[options(short = "b", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "b",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour2: Colour,
-    #[options(short = "c", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "c",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour3: Colour,
-    #[options(short = "d", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "d",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour4: Colour,
 }
 
@@ -198,13 +257,33 @@ pub struct MultiZone {
 pub struct MultiColourSpeed {
     #[options(help = "print help message")]
     help: bool,
-    #[options(short = "a", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "a",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour1: Colour,
-    #[options(short = "b", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "b",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour2: Colour,
-    #[options(short = "c", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "c",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour3: Colour,
-    #[options(short = "d", meta = "", help = "set the RGB value e.g, ff00ff")]
+    #[options(
+        short = "d",
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "set the RGB value or a saved colour e.g, ff00ff"
+    )]
     pub colour4: Colour,
     #[options(no_long, meta = "", help = "set the speed: low, med, high")]
     pub speed: Speed,
@@ -240,6 +319,246 @@ pub enum SetAuraBuiltin {
     Comet(SingleColour), // 11
     #[options(help = "set a wide vertical line zooming from left")]
     Flash(SingleColour), // 12
+    #[options(help = "pick a random colour, or shuffle on start, resume, and an interval")]
+    Random(AuraRandom),
+    #[options(help = "reinitialise a stuck keyboard controller and apply the saved state")]
+    ResetController(ResetController),
+    #[options(help = "retry writes when other RGB software such as OpenRGB holds the keyboard")]
+    Exclusive(AuraExclusive),
+    #[options(help = "show a warning effect while the battery is low")]
+    LowBattery(AuraLowBatteryCommand),
+    #[options(help = "turn the backlight off after a time without input, per profile")]
+    IdleTimeout(AuraIdleTimeoutCommand),
+    #[options(help = "set a mode, colour, and brightness when the platform profile changes")]
+    ProfileLighting(AuraProfileLightingCommand),
+    #[options(help = "set a mode, colour, and brightness at times of day or on AC or battery")]
+    Schedule(AuraScheduleCommand),
+    #[options(help = "show or edit which zones are lit at boot, awake, sleep, and shutdown")]
+    Power(AuraPowerMatrix),
+    #[options(help = "save colours by name to use in place of hex, e.g. -c mine.accent")]
+    Palette(AuraPaletteCommand),
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct ValidateLayout {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(free, help = "the layout file, e.g. g513i-per-key_US.ron")]
+    pub file: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraRandom {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "shuffle the colour when asusd starts and after resume")]
+    pub on: bool,
+    #[options(help = "stop shuffling the colour")]
+    pub off: bool,
+    #[options(
+        meta = "SECS",
+        help = "with --on, also shuffle every SECS seconds, 0 for only start and resume"
+    )]
+    pub interval: Option<u32>,
+    #[options(
+        free,
+        parse(try_from_str = "parse_colour"),
+        help = "with --on, the colours to pick from e.g. ff0000 00ff00, any colour if none"
+    )]
+    pub palette: Vec<Colour>,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct ResetController {
+    #[options(help = "print help message")]
+    pub help: bool,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraExclusive {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "retry failed writes, opening the device again")]
+    pub on: bool,
+    #[options(help = "report failed writes straight away")]
+    pub off: bool,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraLowBatteryCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "show the warning while on battery at or below the threshold")]
+    pub on: bool,
+    #[options(help = "stop showing the warning")]
+    pub off: bool,
+    #[options(meta = "PERCENT", help = "with --on, the battery charge to warn at")]
+    pub threshold: Option<u8>,
+    #[options(
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "with --on, the warning colour e.g. ff0000"
+    )]
+    pub colour: Option<Colour>,
+    #[options(help = "with --on, a steady colour in place of breathing")]
+    pub steady: bool,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraIdleTimeoutCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "",
+        help = "the platform profile to set the timeouts of, e.g. quiet"
+    )]
+    pub profile: Option<PlatformProfile>,
+    #[options(
+        meta = "SECS",
+        help = "with --profile, the timeout on AC, 0 to stay on"
+    )]
+    pub ac: Option<u32>,
+    #[options(
+        meta = "SECS",
+        help = "with --profile, the timeout on battery, 0 to stay on"
+    )]
+    pub battery: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraProfileLightingCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "",
+        help = "the platform profile to bind lighting to, e.g. quiet"
+    )]
+    pub profile: Option<PlatformProfile>,
+    #[options(help = "with --profile, bind the current mode, colours, and brightness")]
+    pub current: bool,
+    #[options(help = "with --profile, stop setting lighting for the profile")]
+    pub off: bool,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraScheduleCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        no_short,
+        meta = "WHEN",
+        help = "with --current, add a step, e.g. \"mon-fri 09:00\", \"22:00\", or @battery"
+    )]
+    pub add: Option<String>,
+    #[options(help = "with --add, use the current mode, colours, and brightness")]
+    pub current: bool,
+    #[options(no_short, meta = "WHEN", help = "remove the step for WHEN")]
+    pub remove: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraPowerMatrix {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "ZONE:STATE",
+        help = "light a zone in a system state, e.g. keyboard:sleep. Can be repeated"
+    )]
+    pub on: Vec<String>,
+    #[options(
+        meta = "ZONE:STATE",
+        help = "turn a zone off in a system state, e.g. lid:boot. Can be repeated"
+    )]
+    pub off: Vec<String>,
+    #[options(help = "edit the table one change at a time")]
+    pub interactive: bool,
+}
+
+#[derive(Debug, Clone, Default, Options)]
+pub struct AuraPaletteCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        meta = "NAME",
+        help = "save --colour as NAME, or SET.NAME to keep it in a set e.g. mine.accent"
+    )]
+    pub save: Option<String>,
+    #[options(
+        meta = "",
+        parse(try_from_str = "parse_colour"),
+        help = "with --save, the colour e.g. ff00ff"
+    )]
+    pub colour: Option<Colour>,
+    #[options(meta = "NAME", help = "remove a saved colour, or a whole set")]
+    pub remove: Option<String>,
+}
+
+/// A column of the LED power table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerStateName {
+    Boot,
+    Awake,
+    Sleep,
+    Shutdown,
+}
+
+impl PowerStateName {
+    pub const ALL: [Self; 4] = [
+        Self::Boot,
+        Self::Awake,
+        Self::Sleep,
+        Self::Shutdown,
+    ];
+
+    pub fn get(self, state: &AuraPowerState) -> bool {
+        match self {
+            Self::Boot => state.boot,
+            Self::Awake => state.awake,
+            Self::Sleep => state.sleep,
+            Self::Shutdown => state.shutdown,
+        }
+    }
+
+    pub fn set(self, state: &mut AuraPowerState, on: bool) {
+        match self {
+            Self::Boot => state.boot = on,
+            Self::Awake => state.awake = on,
+            Self::Sleep => state.sleep = on,
+            Self::Shutdown => state.shutdown = on,
+        }
+    }
+}
+
+/// Parse a `zone:state` cell of the LED power table, e.g. `rear-glow:awake`
+pub fn parse_power_cell(cell: &str) -> Result<(PowerZones, PowerStateName), String> {
+    let (zone, state) = cell
+        .split_once(':')
+        .ok_or_else(|| format!("{cell} is not in the form zone:state"))?;
+    let zone = match zone.to_lowercase().replace(['-', '_'], "").as_str() {
+        "keyboard" => PowerZones::Keyboard,
+        "logo" => PowerZones::Logo,
+        "lightbar" => PowerZones::Lightbar,
+        "lid" => PowerZones::Lid,
+        "rearglow" => PowerZones::RearGlow,
+        "ally" => PowerZones::Ally,
+        _ => {
+            return Err(format!(
+                "{zone} is not one of: keyboard, logo, lightbar, lid, rear-glow, ally"
+            ))
+        }
+    };
+    let state = match state.to_lowercase().as_str() {
+        "boot" => PowerStateName::Boot,
+        "awake" => PowerStateName::Awake,
+        "sleep" => PowerStateName::Sleep,
+        "shutdown" => PowerStateName::Shutdown,
+        _ => {
+            return Err(format!(
+                "{state} is not one of: boot, awake, sleep, shutdown"
+            ))
+        }
+    };
+    Ok((zone, state))
 }
 
 impl Default for SetAuraBuiltin {
@@ -364,6 +683,33 @@ impl From<&SetAuraBuiltin> for AuraEffect {
                 data.mode = AuraModeNum::Flash;
                 data
             }
+            SetAuraBuiltin::Random(_) => {
+                unreachable!("random is handled before any effect is set")
+            }
+            SetAuraBuiltin::ResetController(_) => {
+                unreachable!("reset-controller is handled before any effect is set")
+            }
+            SetAuraBuiltin::Exclusive(_) => {
+                unreachable!("exclusive is handled before any effect is set")
+            }
+            SetAuraBuiltin::LowBattery(_) => {
+                unreachable!("low-battery is handled before any effect is set")
+            }
+            SetAuraBuiltin::IdleTimeout(_) => {
+                unreachable!("idle-timeout is handled before any effect is set")
+            }
+            SetAuraBuiltin::ProfileLighting(_) => {
+                unreachable!("profile-lighting is handled before any effect is set")
+            }
+            SetAuraBuiltin::Schedule(_) => {
+                unreachable!("schedule is handled before any effect is set")
+            }
+            SetAuraBuiltin::Power(_) => {
+                unreachable!("power is handled before any effect is set")
+            }
+            SetAuraBuiltin::Palette(_) => {
+                unreachable!("palette is handled before any effect is set")
+            }
         }
     }
 }
@@ -0,0 +1,48 @@
+use gumdrop::Options;
+use rog_aura::{AuraEffect, LedBrightness};
+use rog_platform::asus_armoury::FirmwareAttribute;
+use rog_platform::platform::PlatformProfile;
+use serde::{Deserialize, Serialize};
+
+pub const BATCH_EXAMPLE: &str = r#"[
+    PlatformProfile(Performance),
+    ChargeLimit(80),
+    AuraBrightness(Med),
+    AuraEffect((
+        mode: Static,
+        zone: None,
+        colour1: (r: 255, g: 0, b: 0),
+        colour2: (r: 0, g: 0, b: 0),
+        speed: Med,
+        direction: Right,
+    )),
+    Attribute(PptPl1Spl, 45),
+]"#;
+
+#[derive(Options)]
+pub struct ApplyCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+
+    #[options(help = "check the file and print what would be applied")]
+    pub dry_run: bool,
+
+    #[options(help = "continue after a failed setting instead of reverting the batch")]
+    pub keep_going: bool,
+
+    #[options(free, help = "the RON file of settings to apply")]
+    pub file: Vec<String>,
+}
+
+/// One setting in a batch file. Operations are applied in the order given.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum BatchOp {
+    PlatformProfile(PlatformProfile),
+    ChargeLimit(u8),
+    /// Applied to every Aura device
+    AuraBrightness(LedBrightness),
+    /// Applied to every Aura device
+    AuraEffect(AuraEffect),
+    /// An asus-armoury firmware attribute
+    Attribute(FirmwareAttribute, i32),
+}
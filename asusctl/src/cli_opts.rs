@@ -1,11 +1,17 @@
+use std::path::PathBuf;
+
 use gumdrop::Options;
 use rog_platform::platform::PlatformProfile;
 
 use crate::anime_cli::AnimeCommand;
-use crate::aura_cli::{LedBrightness, LedPowerCommand1, LedPowerCommand2, SetAuraBuiltin};
+use crate::aura_cli::{
+    LedBrightness, LedPowerCommand1, LedPowerCommand2, SetAuraBuiltin, ValidateLayout,
+};
+use crate::batch_cli::ApplyCommand;
 use crate::fan_curve_cli::FanCurveCommand;
 use crate::scsi_cli::ScsiCommand;
 use crate::slash_cli::SlashCommand;
+use crate::watch_cli::WatchCommand;
 
 #[derive(Default, Options)]
 pub struct CliStart {
@@ -25,6 +31,20 @@ pub struct CliStart {
     pub chg_limit: Option<u8>,
     #[options(help = "Toggle one-shot battery charge to 100%")]
     pub one_shot_chg: bool,
+    #[options(
+        no_short,
+        meta = "",
+        help = "with --chg-limit, the battery to set such as BAT1 instead of the main one"
+    )]
+    pub battery: Option<String>,
+    #[options(no_short, help = "show the charge limit of each battery")]
+    pub batteries: bool,
+    #[options(
+        no_short,
+        meta = "",
+        help = "use asusd's JSON-RPC socket at this path, for systems without a system bus"
+    )]
+    pub socket: Option<PathBuf>,
     #[options(command)]
     pub command: Option<CliCommand>,
 }
@@ -37,6 +57,8 @@ pub enum CliCommand {
     AuraPowerOld(LedPowerCommand1),
     #[options(help = "Set the LED power states")]
     AuraPower(LedPowerCommand2),
+    #[options(help = "Check a keyboard layout file for problems")]
+    ValidateLayout(ValidateLayout),
     #[options(help = "Set or select platform_profile")]
     Profile(ProfileCommand),
     #[options(help = "Set, select, or modify fan curves if supported")]
@@ -56,6 +78,22 @@ pub enum CliCommand {
     Armoury(ArmouryCommand),
     #[options(name = "backlight", help = "Set screen backlight levels")]
     Backlight(BacklightCommand),
+    #[options(help = "Show time spent in each platform profile and GPU mode")]
+    Stats(StatsCommand),
+    #[options(help = "Apply a batch of settings from a RON file, reverting them all on failure")]
+    Apply(ApplyCommand),
+    #[options(help = "Save and apply keyboard, lightbar, and AniMe scenes")]
+    Scene(SceneCommand),
+    #[options(help = "Save every setting to a file, or restore them from one")]
+    Snapshot(SnapshotCommand),
+    #[options(help = "Reset settings to their defaults")]
+    Reset(ResetCommand),
+    #[options(
+        help = "Run a program on the iGPU or dGPU, e.g. `asusctl launch --gpu dgpu -- steam`"
+    )]
+    Launch(LaunchCommand),
+    #[options(help = "Print asusd's signals and property changes as they happen")]
+    Watch(WatchCommand),
 }
 
 #[derive(Debug, Clone, Options)]
@@ -92,6 +130,29 @@ pub struct LedModeCommand {
 pub struct GraphicsCommand {
     #[options(help = "print help message")]
     pub help: bool,
+    #[options(
+        no_short,
+        meta = "",
+        help = "switch the GPU MUX on the next shutdown or reboot <ultimate, optimus>"
+    )]
+    pub queue_mux: Option<String>,
+    #[options(
+        no_short,
+        help = "with --queue-mux, ask in ROG Control Center at shutdown"
+    )]
+    pub confirm: bool,
+    #[options(no_short, help = "cancel the queued GPU MUX switch")]
+    pub cancel_queued: bool,
+}
+
+#[derive(Options)]
+pub struct LaunchCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(meta = "", help = "the GPU to run the program on <igpu, dgpu>")]
+    pub gpu: Option<String>,
+    #[options(free, help = "the program and its arguments, after `--`")]
+    pub command: Vec<String>,
 }
 
 #[derive(Options, Debug)]
@@ -121,4 +182,105 @@ pub struct BacklightCommand {
         help = "Set screenpad brightness to sync with primary display"
     )]
     pub sync_screenpad_brightness: Option<bool>,
+    #[options(meta = "", help = "Turn the screenpad on or off <true/false>")]
+    pub screenpad_power: Option<bool>,
+}
+
+#[derive(Options)]
+pub struct SceneCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(command)]
+    pub command: Option<SceneAction>,
+}
+
+#[derive(Options)]
+pub enum SceneAction {
+    #[options(help = "list the saved scenes")]
+    List(SceneList),
+    #[options(help = "apply a scene by name")]
+    Apply(SceneName),
+    #[options(help = "save the current lighting as a scene")]
+    Save(SceneSave),
+    #[options(help = "delete a scene by name")]
+    Remove(SceneName),
+}
+
+#[derive(Options)]
+pub struct SceneList {
+    #[options(help = "print help message")]
+    pub help: bool,
+}
+
+#[derive(Options)]
+pub struct SceneName {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(free, help = "the scene name")]
+    pub name: Vec<String>,
+}
+
+#[derive(Options)]
+pub struct SceneSave {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "save the keyboard mode and colours")]
+    pub keyboard: bool,
+    #[options(help = "save the lightbar colour")]
+    pub lightbar: bool,
+    #[options(help = "save the AniMe animations")]
+    pub anime: bool,
+    #[options(free, help = "the scene name")]
+    pub name: Vec<String>,
+}
+
+#[derive(Options)]
+pub struct SnapshotCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(command)]
+    pub command: Option<SnapshotAction>,
+}
+
+#[derive(Options)]
+pub enum SnapshotAction {
+    #[options(help = "save the current settings as a snapshot")]
+    Save(SnapshotName),
+    #[options(help = "restore the settings of a snapshot")]
+    Restore(SnapshotName),
+}
+
+#[derive(Options)]
+pub struct SnapshotName {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(
+        free,
+        help = "the snapshot name, kept in ~/.config/rog/snapshots, or a path to a .ron file"
+    )]
+    pub name: Vec<String>,
+}
+
+#[derive(Options)]
+pub struct ResetCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "reset everything below")]
+    pub all: bool,
+    #[options(help = "reset the keyboard lighting")]
+    pub aura: bool,
+    #[options(help = "reset the platform profile settings and fan curves")]
+    pub profiles: bool,
+    #[options(help = "reset the firmware attributes to their firmware defaults")]
+    pub attributes: bool,
+    #[options(help = "reset the charge limit to 100")]
+    pub charge_limit: bool,
+}
+
+#[derive(Options)]
+pub struct StatsCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(help = "reset all usage counters")]
+    pub reset: bool,
 }
@@ -1,5 +1,6 @@
 use gumdrop::Options;
 use rog_platform::platform::PlatformProfile;
+use rog_profiles::acoustic::AcousticPreset;
 use rog_profiles::fan_curve_set::CurveData;
 use rog_profiles::FanCurvePU;
 
@@ -46,4 +47,25 @@ pub struct FanCurveCommand {
                 `--mod-profile` required. If '%' is omitted the fan range is 0-255"
     )]
     pub data: Option<CurveData>,
+
+    #[options(
+        meta = "",
+        help = "boost the fans <true/false> while the CPU is thermal throttling"
+    )]
+    pub throttle_boost: Option<bool>,
+
+    #[options(meta = "", help = "how much the throttle boost adds to the fans <1-5>")]
+    pub boost_aggressiveness: Option<u8>,
+
+    #[options(
+        meta = "",
+        help = "the highest fan speed the throttle boost may set <0-100>"
+    )]
+    pub boost_cap: Option<u8>,
+
+    #[options(
+        meta = "",
+        help = "cap the fans to a noise level <off, silent, balanced, max>"
+    )]
+    pub acoustic: Option<AcousticPreset>,
 }
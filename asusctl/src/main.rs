@@ -1,19 +1,32 @@
 use std::convert::TryFrom;
 use std::env::args;
-use std::path::Path;
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::thread::sleep;
 
 use anime_cli::{AnimeActions, AnimeCommand};
-use aura_cli::{LedPowerCommand1, LedPowerCommand2};
+use aura_cli::{
+    palette_path, parse_power_cell, AuraExclusive, AuraIdleTimeoutCommand, AuraLowBatteryCommand,
+    AuraPaletteCommand, AuraPowerMatrix, AuraProfileLightingCommand, AuraRandom,
+    AuraScheduleCommand, LedPowerCommand1, LedPowerCommand2, PowerStateName, SetAuraBuiltin,
+    ValidateLayout,
+};
+use batch_cli::{ApplyCommand, BatchOp, BATCH_EXAMPLE};
 use dmi_id::DMIID;
 use fan_curve_cli::FanCurveCommand;
 use gumdrop::{Opt, Options};
 use log::{error, info};
 use rog_anime::usb::get_anime_type;
 use rog_anime::{AnimTime, AnimeDataBuffer, AnimeDiagonal, AnimeGif, AnimeImage, AnimeType, Vec2};
-use rog_aura::keyboard::{AuraPowerState, LaptopAuraPower};
-use rog_aura::{self, AuraDeviceType, AuraEffect, PowerZones};
+use rog_aura::keyboard::{AuraPowerState, KeyLayout, LaptopAuraPower};
+use rog_aura::names::AuraNames;
+use rog_aura::palette::Palettes;
+use rog_aura::{
+    self, AuraDeviceType, AuraEffect, AuraModeNum, AuraProfileLighting, AuraScheduleStep,
+    AuraShuffle, PowerZones,
+};
 use rog_dbus::asus_armoury::AsusArmouryProxyBlocking;
 use rog_dbus::list_iface_blocking;
 use rog_dbus::scsi_aura::ScsiAuraProxyBlocking;
@@ -22,8 +35,13 @@ use rog_dbus::zbus_aura::AuraProxyBlocking;
 use rog_dbus::zbus_backlight::BacklightProxyBlocking;
 use rog_dbus::zbus_fan_curves::FanCurvesProxyBlocking;
 use rog_dbus::zbus_platform::PlatformProxyBlocking;
+use rog_dbus::zbus_scenes::ScenesProxyBlocking;
 use rog_dbus::zbus_slash::SlashProxyBlocking;
-use rog_platform::platform::{PlatformProfile, Properties};
+use rog_dbus::zbus_snapshot::SnapshotProxyBlocking;
+use rog_dbus::zbus_stats::UsageStatsProxyBlocking;
+use rog_platform::asus_armoury::FirmwareAttribute;
+use rog_platform::dgpu::{Dgpu, GpuVendor};
+use rog_platform::platform::{GpuMode, PlatformProfile, Properties};
 use rog_profiles::error::ProfileError;
 use rog_scsi::AuraMode;
 use rog_slash::SlashMode;
@@ -38,10 +56,12 @@ use crate::slash_cli::SlashCommand;
 
 mod anime_cli;
 mod aura_cli;
+mod batch_cli;
 mod cli_opts;
 mod fan_curve_cli;
 mod scsi_cli;
 mod slash_cli;
+mod watch_cli;
 
 fn main() {
     let mut logger = env_logger::Builder::new();
@@ -69,7 +89,21 @@ fn main() {
         }
     };
 
-    let conn = Connection::system().unwrap();
+    // Works on a file, so doesn't need asusd
+    if let Some(CliCommand::ValidateLayout(cmd)) = &parsed.command {
+        if let Err(err) = handle_validate_layout(cmd) {
+            println!("Error: {err}");
+        }
+        return;
+    }
+
+    if let Some(path) = &parsed.socket {
+        if !use_socket(path) {
+            return;
+        }
+    }
+
+    let conn = rog_dbus::system_connection_blocking().unwrap();
     if let Ok(platform_proxy) = PlatformProxyBlocking::new(&conn).map_err(|e| {
         check_service("asusd");
         println!("\nError: {e}\n");
@@ -118,6 +152,17 @@ fn main() {
     }
 }
 
+#[cfg(feature = "socket-api")]
+fn use_socket(path: &Path) -> bool {
+    rog_dbus::set_socket_path(path)
+}
+
+#[cfg(not(feature = "socket-api"))]
+fn use_socket(_path: &Path) -> bool {
+    println!("asusctl was built without the socket-api feature, --socket can't be used");
+    false
+}
+
 fn print_error_help(
     err: &dyn std::error::Error,
     supported_interfaces: &[String],
@@ -164,7 +209,7 @@ fn find_iface<T>(iface_name: &str) -> Result<Vec<T>, Box<dyn std::error::Error>>
 where
     T: ProxyImpl<'static> + From<zbus::Proxy<'static>>,
 {
-    let conn = zbus::blocking::Connection::system().unwrap();
+    let conn = rog_dbus::system_connection_blocking().unwrap();
     let f = zbus::blocking::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/").unwrap();
     let interfaces = f.get_managed_objects().unwrap();
     let mut paths = Vec::new();
@@ -208,25 +253,34 @@ fn do_parsed(
         Some(CliCommand::Aura(mode)) => handle_led_mode(mode)?,
         Some(CliCommand::AuraPowerOld(pow)) => handle_led_power1(pow)?,
         Some(CliCommand::AuraPower(pow)) => handle_led_power2(pow)?,
+        Some(CliCommand::ValidateLayout(cmd)) => handle_validate_layout(cmd)?,
         Some(CliCommand::Profile(cmd)) => {
             handle_throttle_profile(&conn, supported_properties, cmd)?
         }
         Some(CliCommand::FanCurve(cmd)) => {
             handle_fan_curve(&conn, cmd)?;
         }
-        Some(CliCommand::Graphics(_)) => do_gfx(),
+        Some(CliCommand::Graphics(cmd)) => do_gfx(&conn, cmd)?,
         Some(CliCommand::Anime(cmd)) => handle_anime(cmd)?,
         Some(CliCommand::Slash(cmd)) => handle_slash(cmd)?,
         Some(CliCommand::Scsi(cmd)) => handle_scsi(cmd)?,
-        Some(CliCommand::Armoury(cmd)) => handle_armoury_command(cmd)?,
+        Some(CliCommand::Armoury(cmd)) => handle_armoury_command(&conn, cmd)?,
         Some(CliCommand::Backlight(cmd)) => handle_backlight(cmd)?,
+        Some(CliCommand::Stats(cmd)) => handle_stats(&conn, cmd)?,
+        Some(CliCommand::Apply(cmd)) => handle_apply(&conn, cmd)?,
+        Some(CliCommand::Scene(cmd)) => handle_scene(&conn, cmd)?,
+        Some(CliCommand::Snapshot(cmd)) => handle_snapshot(&conn, cmd)?,
+        Some(CliCommand::Reset(cmd)) => handle_reset(&conn, cmd)?,
+        Some(CliCommand::Launch(cmd)) => handle_launch(&conn, cmd)?,
+        Some(CliCommand::Watch(cmd)) => watch_cli::watch(&conn, cmd)?,
         None => {
             if (!parsed.show_supported
                 && parsed.kbd_bright.is_none()
                 && parsed.chg_limit.is_none()
                 && !parsed.next_kbd_bright
                 && !parsed.prev_kbd_bright
-                && !parsed.one_shot_chg)
+                && !parsed.one_shot_chg
+                && !parsed.batteries)
                 || parsed.help
             {
                 println!("{}", CliStart::usage());
@@ -281,6 +335,19 @@ fn do_parsed(
                             return false;
                         }
 
+                        if command.trim().starts_with("scene")
+                            && !supported_interfaces.contains(&"xyz.ljones.Scenes".to_string())
+                        {
+                            return false;
+                        }
+
+                        if (command.trim().starts_with("snapshot")
+                            || command.trim().starts_with("reset"))
+                            && !supported_interfaces.contains(&"xyz.ljones.Snapshot".to_string())
+                        {
+                            return false;
+                        }
+
                         if command.trim().starts_with("backlight")
                             && !supported_interfaces.contains(&"xyz.ljones.Backlight".to_string())
                         {
@@ -359,10 +426,17 @@ fn do_parsed(
             let modes = aura.first().unwrap().supported_basic_modes()?;
             let zones = aura.first().unwrap().supported_basic_zones()?;
             let power = aura.first().unwrap().supported_power_zones()?;
+            let names = AuraNames::for_locale();
             println!("Supported Keyboard Brightness:\n{:#?}", bright);
-            println!("Supported Aura Modes:\n{:#?}", modes);
+            println!("Supported Aura Modes:");
+            for mode in modes {
+                println!("    {mode}: {}", names.mode(mode));
+            }
             println!("Supported Aura Zones:\n{:#?}", zones);
-            println!("Supported Aura Power Zones:\n{:#?}", power);
+            println!("Supported Aura Power Zones:");
+            for zone in power {
+                println!("    {zone:?}: {}", names.power_zone(zone));
+            }
         } else {
             println!("No aura interface found");
         }
@@ -370,7 +444,21 @@ fn do_parsed(
 
     if let Some(chg_limit) = parsed.chg_limit {
         let proxy = PlatformProxyBlocking::new(&conn)?;
-        proxy.set_charge_control_end_threshold(chg_limit)?;
+        if let Some(battery) = &parsed.battery {
+            proxy.set_battery_charge_limit(battery, chg_limit)?;
+        } else {
+            proxy.set_charge_control_end_threshold(chg_limit)?;
+        }
+    }
+
+    if parsed.batteries {
+        let proxy = PlatformProxyBlocking::new(&conn)?;
+        for battery in proxy.batteries()? {
+            match proxy.battery_charge_limit(&battery) {
+                Ok(limit) => println!("{battery}: {limit}%"),
+                Err(_) => println!("{battery}: no charge limit"),
+            }
+        }
     }
 
     if parsed.one_shot_chg {
@@ -381,18 +469,159 @@ fn do_parsed(
     Ok(())
 }
 
-fn do_gfx() {
+fn do_gfx(conn: &Connection, cmd: &GraphicsCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+    if cmd.queue_mux.is_some() || cmd.cancel_queued {
+        let proxy = PlatformProxyBlocking::new(conn)?;
+        if cmd.cancel_queued {
+            proxy.cancel_queued_gpu_mux()?;
+            println!("Cancelled the queued GPU MUX switch");
+        }
+        if let Some(mode) = &cmd.queue_mux {
+            let mode = match mode.to_lowercase().as_str() {
+                "ultimate" => GpuMode::Ultimate,
+                "optimus" => GpuMode::Optimus,
+                _ => return Err(format!("{mode} is not one of: ultimate, optimus").into()),
+            };
+            proxy.queue_gpu_mux(mode, cmd.confirm)?;
+        }
+    }
+    if let Ok(proxy) = PlatformProxyBlocking::new(conn) {
+        if let (Ok(active), Ok(pending)) = (proxy.gpu_mode_active(), proxy.gpu_mode_pending()) {
+            if active == pending {
+                println!("GPU mode: {active}");
+            } else {
+                println!("GPU mode: {active}, {pending} (pending reboot)");
+            }
+            if let Ok(requested) = proxy.gpu_mode_requested() {
+                if requested != pending {
+                    println!("Requested GPU mode {requested} was not applied by the firmware");
+                }
+            }
+        }
+        if let Ok((_, _, queued, confirm)) = proxy.pending_gpu_mux_changes() {
+            if queued != GpuMode::NotSupported {
+                let when = if confirm {
+                    "once confirmed at shutdown"
+                } else {
+                    "at shutdown"
+                };
+                println!("GPU MUX switch to {queued} queued, applied {when}");
+            }
+        }
+    }
     println!(
         "Please use supergfxctl for graphics switching. supergfxctl is the result of making \
          asusctl graphics switching generic so all laptops can use it"
     );
     println!("This command will be removed in future");
+    Ok(())
+}
+
+/// The GPU mode from supergfxd if it is running, mapped from its own modes
+fn supergfxd_gpu_mode(conn: &Connection) -> Option<GpuMode> {
+    let reply = conn
+        .call_method(
+            Some("org.supergfxctl.Daemon"),
+            "/org/supergfxctl/Gfx",
+            Some("org.supergfxctl.Daemon"),
+            "Mode",
+            &(),
+        )
+        .ok()?;
+    let mode: u32 = reply.body().deserialize().ok()?;
+    Some(match mode {
+        0 | 2 => GpuMode::Optimus,
+        1 => GpuMode::Integrated,
+        3 => GpuMode::Vfio,
+        4 => GpuMode::Egpu,
+        5 => GpuMode::Ultimate,
+        _ => GpuMode::NotSupported,
+    })
+}
+
+/// The environment `prime-run` and Mesa use to render on the dGPU
+fn dgpu_offload_env(vendor: GpuVendor, slot: &str) -> Vec<(&'static str, String)> {
+    match vendor {
+        GpuVendor::Nvidia => vec![
+            ("__NV_PRIME_RENDER_OFFLOAD", "1".to_owned()),
+            ("__GLX_VENDOR_LIBRARY_NAME", "nvidia".to_owned()),
+            ("__VK_LAYER_NV_optimus", "NVIDIA_only".to_owned()),
+        ],
+        _ => vec![(
+            "DRI_PRIME",
+            format!("pci-{}", slot.replace([':', '.'], "_")),
+        )],
+    }
+}
+
+fn handle_launch(conn: &Connection, cmd: &LaunchCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(program) = cmd.command.first().filter(|_| !cmd.help) else {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    };
+    let dgpu = match cmd.gpu.as_deref().map(str::to_lowercase).as_deref() {
+        Some("dgpu") => true,
+        Some("igpu") => false,
+        Some(gpu) => return Err(format!("{gpu} is not one of: igpu, dgpu").into()),
+        None => return Err("--gpu is required, one of: igpu, dgpu".into()),
+    };
+    let mode = supergfxd_gpu_mode(conn)
+        .or_else(|| {
+            PlatformProxyBlocking::new(conn)
+                .and_then(|proxy| proxy.gpu_mode_active())
+                .ok()
+        })
+        .unwrap_or(GpuMode::NotSupported);
+
+    let mut env = Vec::new();
+    if dgpu {
+        match (mode, Dgpu::find()) {
+            // The dGPU drives everything, nothing to offload to
+            (GpuMode::Ultimate, _) => {}
+            (GpuMode::Integrated, _) => println!(
+                "Warning: the dGPU is disabled in Integrated mode, {program} will run on the iGPU"
+            ),
+            (GpuMode::Vfio, _) => println!(
+                "Warning: the dGPU is passed to a VM in Vfio mode, {program} will run on the iGPU"
+            ),
+            (_, Ok(found)) => {
+                let vendor = found.vendor().unwrap_or(GpuVendor::Other);
+                env = dgpu_offload_env(vendor, &found.slot());
+            }
+            (_, Err(_)) => println!(
+                "Warning: the dGPU is powered off or disabled, {program} will run on the iGPU"
+            ),
+        }
+    } else {
+        if mode == GpuMode::Ultimate {
+            println!(
+                "Warning: the iGPU is not in use in Ultimate mode, {program} will run on the dGPU"
+            );
+        }
+        env = vec![
+            ("DRI_PRIME", "0".to_owned()),
+            ("__VK_LAYER_NV_optimus", "non_NVIDIA_only".to_owned()),
+        ];
+    }
+    for (key, value) in &env {
+        info!("{key}={value}");
+    }
+    let err = Command::new(program)
+        .args(&cmd.command[1..])
+        .envs(env)
+        .exec();
+    Err(format!("Could not run {program}: {err}").into())
 }
 
 fn handle_backlight(cmd: &BacklightCommand) -> Result<(), Box<dyn std::error::Error>> {
     if (cmd.screenpad_brightness.is_none()
         && cmd.screenpad_gamma.is_none()
-        && cmd.sync_screenpad_brightness.is_none())
+        && cmd.sync_screenpad_brightness.is_none()
+        && cmd.screenpad_power.is_none())
         || cmd.help
     {
         println!("Missing arg or command\n\n{}", cmd.self_usage());
@@ -400,6 +629,7 @@ fn handle_backlight(cmd: &BacklightCommand) -> Result<(), Box<dyn std::error::Er
         let backlights = find_iface::<BacklightProxyBlocking>("xyz.ljones.Backlight")?;
         for backlight in backlights {
             println!("Current screenpad settings:");
+            println!("  Power: {}", backlight.screenpad_power()?);
             println!("  Brightness: {}", backlight.screenpad_brightness()?);
             println!("  Gamma: {}", backlight.screenpad_gamma()?);
             println!(
@@ -424,20 +654,225 @@ fn handle_backlight(cmd: &BacklightCommand) -> Result<(), Box<dyn std::error::Er
         if let Some(sync) = cmd.sync_screenpad_brightness {
             backlight.set_screenpad_sync_with_primary(sync)?;
         }
+
+        if let Some(power) = cmd.screenpad_power {
+            backlight.set_screenpad_power(power)?;
+        }
     }
 
     Ok(())
 }
 
+fn format_secs(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+}
+
+fn handle_scene(conn: &Connection, cmd: &SceneCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(action) = cmd.command.as_ref() else {
+        println!("{}", cmd.self_usage());
+        if let Some(cmdlist) = SceneCommand::command_list() {
+            println!("\nCommands available:\n{cmdlist}");
+        }
+        return Ok(());
+    };
+
+    let proxy = ScenesProxyBlocking::new(conn)?;
+    match action {
+        SceneAction::List(list) => {
+            if list.help {
+                println!("{}", list.self_usage());
+                return Ok(());
+            }
+            let active = proxy.active()?;
+            let scenes = proxy.scenes()?;
+            if scenes.is_empty() {
+                println!("No scenes saved, add one with `asusctl scene save`");
+            }
+            for name in scenes {
+                let parts = proxy.scene_parts(&name)?.join(", ");
+                let marker = if name == active { "*" } else { " " };
+                println!("{marker} {name}: {parts}");
+            }
+        }
+        SceneAction::Apply(scene) => {
+            if scene.help || scene.name.is_empty() {
+                println!("{}", scene.self_usage());
+                return Ok(());
+            }
+            let name = scene.name.join(" ");
+            proxy.apply(&name)?;
+            println!("Applied scene {name}");
+        }
+        SceneAction::Save(save) => {
+            if save.help || save.name.is_empty() {
+                println!("{}", save.self_usage());
+                println!(
+                    "\nParts this laptop supports: {}",
+                    proxy.supported_parts()?.join(", ")
+                );
+                return Ok(());
+            }
+            // With no parts given, save everything this laptop supports
+            let (keyboard, lightbar, anime) = if save.keyboard || save.lightbar || save.anime {
+                (save.keyboard, save.lightbar, save.anime)
+            } else {
+                let supported = proxy.supported_parts()?;
+                let has = |part: &str| supported.iter().any(|p| p == part);
+                (has("keyboard"), has("lightbar"), has("anime"))
+            };
+            let name = save.name.join(" ");
+            proxy.capture_scene(&name, keyboard, lightbar, anime)?;
+            println!("Saved scene {name}");
+        }
+        SceneAction::Remove(scene) => {
+            if scene.help || scene.name.is_empty() {
+                println!("{}", scene.self_usage());
+                return Ok(());
+            }
+            let name = scene.name.join(" ");
+            proxy.remove_scene(&name)?;
+            println!("Removed scene {name}");
+        }
+    }
+    Ok(())
+}
+
+/// A name with a `/` or ending in `.ron` is a path, anything else is kept in
+/// `~/.config/rog/snapshots`
+fn snapshot_path(name: &str) -> PathBuf {
+    if name.contains('/') || name.ends_with(".ron") {
+        return PathBuf::from(name);
+    }
+    let mut path = dirs::config_dir().unwrap_or_default();
+    path.push("rog/snapshots");
+    path.push(format!("{name}.ron"));
+    path
+}
+
+fn handle_snapshot(
+    conn: &Connection,
+    cmd: &SnapshotCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(action) = cmd.command.as_ref() else {
+        println!("{}", cmd.self_usage());
+        if let Some(cmdlist) = SnapshotCommand::command_list() {
+            println!("\nCommands available:\n{cmdlist}");
+        }
+        return Ok(());
+    };
+
+    let proxy = SnapshotProxyBlocking::new(conn)?;
+    match action {
+        SnapshotAction::Save(snapshot) => {
+            if snapshot.help || snapshot.name.is_empty() {
+                println!("{}", snapshot.self_usage());
+                return Ok(());
+            }
+            let path = snapshot_path(&snapshot.name.join(" "));
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, proxy.snapshot()?)?;
+            println!("Saved snapshot to {}", path.display());
+        }
+        SnapshotAction::Restore(snapshot) => {
+            if snapshot.help || snapshot.name.is_empty() {
+                println!("{}", snapshot.self_usage());
+                return Ok(());
+            }
+            let path = snapshot_path(&snapshot.name.join(" "));
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+            proxy.restore_snapshot(&text)?;
+            println!("Restored snapshot from {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn handle_reset(conn: &Connection, cmd: &ResetCommand) -> Result<(), Box<dyn std::error::Error>> {
+    let parts: Vec<&str> = [
+        (cmd.aura, "aura"),
+        (cmd.profiles, "profiles"),
+        (cmd.attributes, "attributes"),
+        (cmd.charge_limit, "charge_limit"),
+    ]
+    .into_iter()
+    .filter_map(|(wanted, part)| (cmd.all || wanted).then_some(part))
+    .collect();
+    if cmd.help || parts.is_empty() {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let proxy = SnapshotProxyBlocking::new(conn)?;
+    proxy.reset_to_defaults(&parts)?;
+    println!("Reset {} to defaults", parts.join(", "));
+    Ok(())
+}
+
+fn handle_stats(conn: &Connection, cmd: &StatsCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let proxy = UsageStatsProxyBlocking::new(conn)?;
+    if cmd.reset {
+        proxy.reset()?;
+        println!("Usage stats reset");
+        return Ok(());
+    }
+
+    let profiles = proxy.profile_seconds()?;
+    let total: u64 = profiles.iter().map(|(_, s)| s).sum();
+    println!("Platform profile usage:");
+    for (profile, secs) in profiles {
+        let percent = if total > 0 { secs * 100 / total } else { 0 };
+        println!(
+            "  {:<12} {:>10} {percent:>3}%",
+            <&str>::from(profile),
+            format_secs(secs)
+        );
+    }
+
+    let modes = proxy.gpu_mode_seconds()?;
+    if !modes.is_empty() {
+        let total: u64 = modes.iter().map(|(_, s)| s).sum();
+        println!("GPU mode usage:");
+        for (mode, secs) in modes {
+            let percent = if total > 0 { secs * 100 / total } else { 0 };
+            println!(
+                "  {:<12} {:>10} {percent:>3}%",
+                mode.to_string(),
+                format_secs(secs)
+            );
+        }
+    }
+
+    let since = proxy.since()?;
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().saturating_sub(since) / 86400)
+        .unwrap_or_default();
+    println!("Tracked over the last {days} days");
+    Ok(())
+}
+
 fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
     if (cmd.command.is_none()
         && cmd.enable_display.is_none()
         && cmd.enable_powersave_anim.is_none()
         && cmd.brightness.is_none()
+        && cmd.auto_brightness.is_none()
+        && cmd.schedule.is_none()
+        && cmd.schedule_override.is_none()
+        && cmd.max_fps.is_none()
         && cmd.off_when_lid_closed.is_none()
         && cmd.off_when_suspended.is_none()
         && cmd.off_when_unplugged.is_none()
         && cmd.off_with_his_head.is_none()
+        && cmd.blip.is_none()
         && !cmd.clear)
         || cmd.help
     {
@@ -462,6 +897,18 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(bright) = cmd.brightness {
             proxy.set_brightness(bright)?;
         }
+        if let Some(enable) = cmd.auto_brightness {
+            proxy.set_auto_brightness(enable)?;
+        }
+        if let Some(enable) = cmd.schedule {
+            proxy.set_schedule_enabled(enable)?;
+        }
+        if let Some(enable) = cmd.schedule_override {
+            proxy.set_schedule_override(enable)?;
+        }
+        if let Some(max_fps) = cmd.max_fps {
+            proxy.set_max_fps(max_fps)?;
+        }
         if let Some(enable) = cmd.off_when_lid_closed {
             proxy.set_off_when_lid_closed(enable)?;
         }
@@ -474,6 +921,9 @@ fn handle_anime(cmd: &AnimeCommand) -> Result<(), Box<dyn std::error::Error>> {
         if cmd.off_with_his_head.is_some() {
             println!("Did Alice _really_ make it back from Wonderland?");
         }
+        if let Some(blip) = cmd.blip.as_deref() {
+            proxy.notify_blip(blip, cmd.blip_ms)?;
+        }
 
         let mut anime_type = get_anime_type();
         if let AnimeType::Unsupported = anime_type {
@@ -639,6 +1089,7 @@ fn handle_slash(cmd: &SlashCommand) -> Result<(), Box<dyn std::error::Error>> {
         && cmd.show_battery_warning.is_none()
         // && cmd.show_on_lid_closed.is_none()
         && cmd.mode.is_none()
+        && cmd.blip.is_none()
         && !cmd.list
         && !cmd.enable
         && !cmd.disable)
@@ -667,6 +1118,9 @@ fn handle_slash(cmd: &SlashCommand) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(slash_mode) = cmd.mode {
             proxy.set_mode(slash_mode)?;
         }
+        if let Some(blip) = cmd.blip.as_deref() {
+            proxy.notify_blip(blip, cmd.blip_ms)?;
+        }
         if let Some(show) = cmd.show_on_boot {
             proxy.set_show_on_boot(show)?;
         }
@@ -791,6 +1245,13 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
                         return true;
                     }
                 }
+                if command.trim().starts_with("random")
+                    || command.trim().starts_with("reset-controller")
+                    || command.trim().starts_with("exclusive")
+                    || command.trim().starts_with("low-battery")
+                {
+                    return true;
+                }
                 // TODO
                 // if !supported.basic_zones.is_empty() && command.trim().starts_with("multi") {
                 //     return true;
@@ -805,11 +1266,53 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
         return Ok(());
     }
 
+    // Works on a file, so doesn't need an Aura device
+    if let Some(SetAuraBuiltin::Palette(cmd)) = &mode.command {
+        return handle_aura_palette(cmd);
+    }
+
     if mode.next_mode && mode.prev_mode {
         println!("Please specify either next or previous");
         return Ok(());
     }
     let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
+    for proxy in &aura {
+        for user in proxy.conflicts().unwrap_or_default() {
+            println!("Warning: {user} also has the keyboard open and may overwrite these changes");
+        }
+    }
+    if let Some(SetAuraBuiltin::Exclusive(cmd)) = &mode.command {
+        return handle_aura_exclusive(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::Random(cmd)) = &mode.command {
+        return handle_aura_random(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::LowBattery(cmd)) = &mode.command {
+        return handle_aura_low_battery(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::IdleTimeout(cmd)) = &mode.command {
+        return handle_aura_idle_timeout(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::ProfileLighting(cmd)) = &mode.command {
+        return handle_aura_profile_lighting(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::Schedule(cmd)) = &mode.command {
+        return handle_aura_schedule(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::Power(cmd)) = &mode.command {
+        return handle_aura_power_matrix(&aura, cmd);
+    }
+    if let Some(SetAuraBuiltin::ResetController(cmd)) = &mode.command {
+        if cmd.help {
+            println!("{}", cmd.self_usage());
+            return Ok(());
+        }
+        for aura in aura {
+            aura.reset_controller()?;
+        }
+        println!("Aura controller reset");
+        return Ok(());
+    }
     if mode.next_mode {
         for aura in aura {
             let mode = aura.led_mode()?;
@@ -845,6 +1348,422 @@ fn handle_led_mode(mode: &LedModeCommand) -> Result<(), Box<dyn std::error::Erro
     Ok(())
 }
 
+fn handle_aura_exclusive(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraExclusive,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || (cmd.on && cmd.off) {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+    if !cmd.on && !cmd.off {
+        for aura in aura {
+            println!("Exclusive mode: {}", aura.exclusive_mode()?);
+        }
+        return Ok(());
+    }
+    for aura in aura {
+        aura.set_exclusive_mode(cmd.on)?;
+    }
+    println!("Exclusive mode set to {}", cmd.on);
+    Ok(())
+}
+
+fn handle_aura_random(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraRandom,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || (cmd.on && cmd.off) {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+    if !cmd.on && (cmd.interval.is_some() || !cmd.palette.is_empty()) {
+        println!("The interval and colours are only used with --on\n");
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    for aura in aura {
+        if cmd.on {
+            aura.set_shuffle(AuraShuffle {
+                enabled: true,
+                interval_secs: cmd.interval.unwrap_or_default(),
+                palette: cmd.palette.clone(),
+            })?;
+        } else if cmd.off {
+            let mut shuffle = aura.shuffle()?;
+            shuffle.enabled = false;
+            aura.set_shuffle(shuffle)?;
+        } else {
+            aura.shuffle_colour()?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_aura_low_battery(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraLowBatteryCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || (cmd.on && cmd.off) {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+    if !cmd.on && (cmd.threshold.is_some() || cmd.colour.is_some() || cmd.steady) {
+        println!("The threshold, colour, and steady options are only used with --on\n");
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    for aura in aura {
+        let mut low = aura.low_battery()?;
+        if !cmd.on && !cmd.off {
+            let c = low.effect.colour1;
+            println!(
+                "Low battery warning: {}, at {}%, {} {:02x}{:02x}{:02x}",
+                low.enabled,
+                low.threshold,
+                low.effect.mode_name(),
+                c.r,
+                c.g,
+                c.b
+            );
+            continue;
+        }
+        low.enabled = cmd.on;
+        if let Some(threshold) = cmd.threshold {
+            low.threshold = threshold.min(100);
+        }
+        if let Some(colour) = cmd.colour {
+            low.effect.colour1 = colour;
+        }
+        if cmd.on {
+            low.effect.mode = if cmd.steady {
+                AuraModeNum::Static
+            } else {
+                AuraModeNum::Breathe
+            };
+        }
+        aura.set_low_battery(low)?;
+    }
+    Ok(())
+}
+
+fn handle_aura_idle_timeout(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraIdleTimeoutCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || (cmd.profile.is_none() && (cmd.ac.is_some() || cmd.battery.is_some())) {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let describe = |secs: Option<&u32>| match secs {
+        Some(secs) if *secs > 0 => format!("off after {secs}s"),
+        _ => "always on".to_owned(),
+    };
+    for aura in aura {
+        let mut on_ac = aura.idle_timeout_on_ac()?;
+        let mut on_battery = aura.idle_timeout_on_battery()?;
+        let Some(profile) = cmd.profile else {
+            println!("Keyboard backlight idle timeouts:");
+            for profile in [
+                PlatformProfile::Quiet,
+                PlatformProfile::Balanced,
+                PlatformProfile::Performance,
+            ] {
+                println!(
+                    "  {profile:?}: AC {}, battery {}",
+                    describe(on_ac.get(&profile)),
+                    describe(on_battery.get(&profile))
+                );
+            }
+            continue;
+        };
+        if let Some(secs) = cmd.ac {
+            on_ac.insert(profile, secs);
+            aura.set_idle_timeout_on_ac(on_ac)?;
+        }
+        if let Some(secs) = cmd.battery {
+            on_battery.insert(profile, secs);
+            aura.set_idle_timeout_on_battery(on_battery)?;
+        }
+    }
+    Ok(())
+}
+
+fn handle_aura_profile_lighting(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraProfileLightingCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || (cmd.current && cmd.off) || (cmd.profile.is_none() && (cmd.current || cmd.off)) {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    for aura in aura {
+        let mut bindings = aura.profile_lighting()?;
+        let Some(profile) = cmd.profile.filter(|_| cmd.current || cmd.off) else {
+            println!("Lighting set on changing platform profile:");
+            for profile in [
+                PlatformProfile::Quiet,
+                PlatformProfile::Balanced,
+                PlatformProfile::Performance,
+            ] {
+                match bindings.get(&profile).filter(|lighting| lighting.enabled) {
+                    Some(lighting) => {
+                        let c = lighting.effect.colour1;
+                        println!(
+                            "  {profile:?}: {} {:02x}{:02x}{:02x}, brightness {:?}",
+                            lighting.effect.mode_name(),
+                            c.r,
+                            c.g,
+                            c.b,
+                            lighting.brightness
+                        );
+                    }
+                    None => println!("  {profile:?}: unchanged"),
+                }
+            }
+            continue;
+        };
+        if cmd.current {
+            bindings.insert(profile, AuraProfileLighting {
+                enabled: true,
+                effect: aura.led_mode_data()?,
+                brightness: aura.brightness()?,
+            });
+        } else if let Some(lighting) = bindings.get_mut(&profile) {
+            lighting.enabled = false;
+        }
+        aura.set_profile_lighting(bindings)?;
+    }
+    Ok(())
+}
+
+fn handle_aura_schedule(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraScheduleCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || cmd.current != cmd.add.is_some() || (cmd.add.is_some() && cmd.remove.is_some()) {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    for aura in aura {
+        if let Some(when) = &cmd.add {
+            aura.set_schedule_step(AuraScheduleStep {
+                when: when.clone(),
+                lighting: AuraProfileLighting {
+                    enabled: true,
+                    effect: aura.led_mode_data()?,
+                    brightness: aura.brightness()?,
+                },
+            })?;
+            continue;
+        }
+        if let Some(when) = &cmd.remove {
+            aura.remove_schedule_step(when)?;
+            continue;
+        }
+        let schedule = aura.schedule()?;
+        if schedule.is_empty() {
+            println!("No lighting schedule");
+            continue;
+        }
+        println!("Lighting schedule:");
+        for step in schedule {
+            let lighting = &step.lighting;
+            let c = lighting.effect.colour1;
+            println!(
+                "  {}: {} {:02x}{:02x}{:02x}, brightness {:?}{}",
+                step.when,
+                lighting.effect.mode_name(),
+                c.r,
+                c.g,
+                c.b,
+                lighting.brightness,
+                if lighting.enabled { "" } else { " (off)" }
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print the LED power table, one row per zone. `shutdown` is not settable on
+/// pre-2021 and TUF keyboards
+fn print_power_matrix(power: &LaptopAuraPower, shutdown: bool) {
+    println!(
+        "{:<12}{:<8}{:<8}{:<8}{:<8}",
+        "Zone", "Boot", "Awake", "Sleep", "Shutdown"
+    );
+    for state in &power.states {
+        let mut row = format!("{:<12}", format!("{:?}", state.zone));
+        for name in PowerStateName::ALL {
+            let cell = if name == PowerStateName::Shutdown && !shutdown {
+                "-"
+            } else if name.get(state) {
+                "on"
+            } else {
+                "off"
+            };
+            row.push_str(&format!("{cell:<8}"));
+        }
+        println!("{}", row.trim_end());
+    }
+}
+
+/// Set one cell of the LED power table, failing if the zone isn't on this
+/// device
+fn set_power_cell(power: &mut LaptopAuraPower, cell: &str, on: bool) -> Result<(), String> {
+    let (zone, name) = parse_power_cell(cell)?;
+    let state = power
+        .states
+        .iter_mut()
+        .find(|state| state.zone == zone)
+        .ok_or_else(|| format!("{zone:?} has no power states on this device"))?;
+    name.set(state, on);
+    Ok(())
+}
+
+/// Edit the LED power table from stdin. `false` if the changes were discarded
+fn edit_power_matrix(power: &mut LaptopAuraPower, shutdown: bool) -> std::io::Result<bool> {
+    println!("Enter changes as `zone:state on` or `zone:state off`, e.g. `keyboard:sleep off`");
+    println!("An empty line applies the changes, `quit` discards them\n");
+    print_power_matrix(power, shutdown);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        print!("> ");
+        std::io::stdout().flush()?;
+        if std::io::stdin().read_line(&mut line)? == 0 {
+            return Ok(true);
+        }
+        let mut words = line.split_whitespace();
+        let (cell, on) = match (words.next(), words.next()) {
+            (None, _) => return Ok(true),
+            (Some("quit"), _) => return Ok(false),
+            (Some(cell), Some("on")) => (cell, true),
+            (Some(cell), Some("off")) => (cell, false),
+            _ => {
+                println!("Expected `zone:state on` or `zone:state off`");
+                continue;
+            }
+        };
+        match set_power_cell(power, cell, on) {
+            Ok(()) => print_power_matrix(power, shutdown),
+            Err(e) => println!("{e}"),
+        }
+    }
+}
+
+fn handle_aura_power_matrix(
+    aura: &[AuraProxyBlocking],
+    cmd: &AuraPowerMatrix,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        println!(
+            "\nZones: keyboard, logo, lightbar, lid, rear-glow, ally\nStates: boot, awake, sleep, \
+             shutdown"
+        );
+        return Ok(());
+    }
+
+    for aura in aura {
+        let dev_type = aura.device_type()?;
+        if !(dev_type.is_new_laptop()
+            || dev_type.is_old_laptop()
+            || dev_type.is_tuf_laptop()
+            || dev_type.is_ally())
+        {
+            continue;
+        }
+        let shutdown = !(dev_type.is_old_laptop() || dev_type.is_tuf_laptop());
+        let mut power = aura.led_power()?;
+        let before = power.clone();
+        for (cells, on) in [
+            (&cmd.on, true),
+            (&cmd.off, false),
+        ] {
+            for cell in cells {
+                set_power_cell(&mut power, cell, on)?;
+            }
+        }
+        if cmd.interactive && !edit_power_matrix(&mut power, shutdown)? {
+            println!("Discarded the changes");
+            continue;
+        }
+        if power != before {
+            aura.set_led_power(power.clone())?;
+        }
+        if !cmd.interactive {
+            print_power_matrix(&power, shutdown);
+        }
+    }
+    Ok(())
+}
+
+fn handle_aura_palette(cmd: &AuraPaletteCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || cmd.save.is_some() != cmd.colour.is_some() {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+
+    let path = palette_path();
+    let mut palettes = Palettes::load(&path)?;
+    if let (Some(name), Some(colour)) = (&cmd.save, cmd.colour) {
+        palettes.insert(name, colour);
+    }
+    if let Some(name) = &cmd.remove {
+        if !palettes.remove(name) {
+            return Err(format!("No saved colour named {name}").into());
+        }
+    }
+    if cmd.save.is_some() || cmd.remove.is_some() {
+        palettes.save(&path)?;
+    }
+
+    let entries = palettes.entries();
+    if entries.is_empty() {
+        println!("No saved colours, save one with --save NAME --colour ff00ff");
+    }
+    for (name, c) in entries {
+        println!("{name}: {:02x}{:02x}{:02x}", c.r, c.g, c.b);
+    }
+    Ok(())
+}
+
+fn handle_validate_layout(cmd: &ValidateLayout) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(file) = cmd.file.first().filter(|_| !cmd.help) else {
+        println!("{}", cmd.self_usage());
+        println!(
+            "\nSet {} to a directory of layouts to use them in place of the installed ones",
+            rog_aura::keyboard::LAYOUT_DIR_ENV
+        );
+        return Ok(());
+    };
+
+    let layout = KeyLayout::from_file(Path::new(file))?;
+    let info = layout.layout_info(file);
+    println!(
+        "{file}: {} rows, {} LEDs, {:.1} x {:.1} keys",
+        layout.rows_ref().len(),
+        info.keys.len(),
+        info.width,
+        info.height
+    );
+    let problems = layout.validate();
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("  {problem}");
+    }
+    Err(format!("{} problems found in {file}", problems.len()).into())
+}
+
 fn handle_led_power1(power: &LedPowerCommand1) -> Result<(), Box<dyn std::error::Error>> {
     let aura = find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")?;
     for aura in aura {
@@ -1024,7 +1943,11 @@ fn handle_fan_curve(
         return Err(ProfileError::NotSupported.into());
     };
 
-    if !cmd.get_enabled && !cmd.default && cmd.mod_profile.is_none() {
+    let boost_options = cmd.throttle_boost.is_some()
+        || cmd.boost_aggressiveness.is_some()
+        || cmd.boost_cap.is_some()
+        || cmd.acoustic.is_some();
+    if !cmd.get_enabled && !cmd.default && cmd.mod_profile.is_none() && !boost_options {
         if !cmd.help {
             println!("Missing arg or command\n");
         }
@@ -1046,6 +1969,19 @@ fn handle_fan_curve(
         return Ok(());
     }
 
+    if let Some(aggressiveness) = cmd.boost_aggressiveness {
+        fan_proxy.set_throttle_boost_aggressiveness(aggressiveness)?;
+    }
+    if let Some(cap) = cmd.boost_cap {
+        fan_proxy.set_throttle_boost_cap(cap)?;
+    }
+    if let Some(enabled) = cmd.throttle_boost {
+        fan_proxy.set_throttle_boost_enabled(enabled)?;
+    }
+    if let Some(preset) = cmd.acoustic {
+        fan_proxy.set_acoustic_preset(preset)?;
+    }
+
     let plat_proxy = PlatformProxyBlocking::new(conn)?;
     if cmd.get_enabled {
         let profile = plat_proxy.platform_profile()?;
@@ -1053,6 +1989,14 @@ fn handle_fan_curve(
         for curve in curves.iter() {
             println!("{}", String::from(curve));
         }
+        println!(
+            "Throttle boost: {}, aggressiveness {}, cap {}%, active {}",
+            fan_proxy.throttle_boost_enabled()?,
+            fan_proxy.throttle_boost_aggressiveness()?,
+            fan_proxy.throttle_boost_cap()?,
+            fan_proxy.throttle_boost_active()?
+        );
+        println!("Acoustic preset: {}", fan_proxy.acoustic_preset()?);
     }
 
     if cmd.default {
@@ -1169,7 +2113,159 @@ fn print_firmware_attr(attr: &AsusArmouryProxyBlocking) -> Result<(), Box<dyn st
     Ok(())
 }
 
-fn handle_armoury_command(cmd: &ArmouryCommand) -> Result<(), Box<dyn std::error::Error>> {
+/// The value a batch operation replaced, so the batch can be reverted
+enum BatchUndo {
+    PlatformProfile(PlatformProfile),
+    ChargeLimit(u8),
+    AuraBrightness(Vec<(AuraProxyBlocking<'static>, rog_aura::LedBrightness)>),
+    AuraEffect(Vec<(AuraProxyBlocking<'static>, AuraEffect)>),
+    Attribute(AsusArmouryProxyBlocking<'static>, i32),
+}
+
+fn apply_batch_op(
+    conn: &Connection,
+    op: &BatchOp,
+) -> Result<BatchUndo, Box<dyn std::error::Error>> {
+    match op {
+        BatchOp::PlatformProfile(profile) => {
+            let proxy = PlatformProxyBlocking::new(conn)?;
+            let old = proxy.platform_profile()?;
+            proxy.set_platform_profile(*profile)?;
+            Ok(BatchUndo::PlatformProfile(old))
+        }
+        BatchOp::ChargeLimit(limit) => {
+            let proxy = PlatformProxyBlocking::new(conn)?;
+            let old = proxy.charge_control_end_threshold()?;
+            proxy.set_charge_control_end_threshold(*limit)?;
+            Ok(BatchUndo::ChargeLimit(old))
+        }
+        BatchOp::AuraBrightness(brightness) => {
+            let mut old = Vec::new();
+            for aura in find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")? {
+                let value = aura.brightness()?;
+                old.push((aura, value));
+            }
+            // All devices or none
+            let res = old
+                .iter()
+                .try_for_each(|(aura, _)| aura.set_brightness(*brightness));
+            if let Err(e) = res {
+                undo_batch_op(conn, BatchUndo::AuraBrightness(old)).ok();
+                return Err(e.into());
+            }
+            Ok(BatchUndo::AuraBrightness(old))
+        }
+        BatchOp::AuraEffect(effect) => {
+            let mut old = Vec::new();
+            for aura in find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")? {
+                let value = aura.led_mode_data()?;
+                old.push((aura, value));
+            }
+            let res = old
+                .iter()
+                .try_for_each(|(aura, _)| aura.set_led_mode_data(effect.clone()));
+            if let Err(e) = res {
+                undo_batch_op(conn, BatchUndo::AuraEffect(old)).ok();
+                return Err(e.into());
+            }
+            Ok(BatchUndo::AuraEffect(old))
+        }
+        BatchOp::Attribute(name, value) => {
+            let attr = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury")?
+                .into_iter()
+                .find(|a| a.name().ok() == Some(*name))
+                .ok_or_else(|| format!("{} is not supported", <&str>::from(*name)))?;
+            let old = attr.current_value()?;
+            attr.set_current_value(*value)?;
+            Ok(BatchUndo::Attribute(attr, old))
+        }
+    }
+}
+
+fn undo_batch_op(conn: &Connection, undo: BatchUndo) -> Result<(), Box<dyn std::error::Error>> {
+    match undo {
+        BatchUndo::PlatformProfile(profile) => {
+            PlatformProxyBlocking::new(conn)?.set_platform_profile(profile)?
+        }
+        BatchUndo::ChargeLimit(limit) => {
+            PlatformProxyBlocking::new(conn)?.set_charge_control_end_threshold(limit)?
+        }
+        BatchUndo::AuraBrightness(old) => {
+            for (aura, value) in old {
+                aura.set_brightness(value)?;
+            }
+        }
+        BatchUndo::AuraEffect(old) => {
+            for (aura, value) in old {
+                aura.set_led_mode_data(value)?;
+            }
+        }
+        BatchUndo::Attribute(attr, value) => attr.set_current_value(value)?,
+    }
+    Ok(())
+}
+
+fn handle_apply(conn: &Connection, cmd: &ApplyCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help || cmd.file.len() != 1 {
+        println!("Usage: asusctl apply [OPTIONS] <FILE>\n");
+        println!("{}", cmd.self_usage());
+        println!("\nThe file is a RON list of settings, applied in order:\n{BATCH_EXAMPLE}");
+        return Ok(());
+    }
+
+    let data = std::fs::read_to_string(&cmd.file[0])?;
+    let ops: Vec<BatchOp> = ron::from_str(&data)?;
+    if cmd.dry_run {
+        for op in &ops {
+            println!("  would apply {op:?}");
+        }
+        return Ok(());
+    }
+
+    let mut applied = Vec::new();
+    let mut failed = 0;
+    for (i, op) in ops.iter().enumerate() {
+        match apply_batch_op(conn, op) {
+            Ok(undo) => {
+                println!("  ok       {op:?}");
+                applied.push((op, undo));
+            }
+            Err(e) => {
+                println!("  FAILED   {op:?}: {e}");
+                failed += 1;
+                if cmd.keep_going {
+                    continue;
+                }
+                for (op, undo) in applied.into_iter().rev() {
+                    match undo_batch_op(conn, undo) {
+                        Ok(()) => println!("  reverted {op:?}"),
+                        Err(e) => println!("  COULD NOT REVERT {op:?}: {e}"),
+                    }
+                }
+                for op in &ops[i + 1..] {
+                    println!("  skipped  {op:?}");
+                }
+                return Err(format!(
+                    "setting {} of {} failed, the batch was reverted",
+                    i + 1,
+                    ops.len()
+                )
+                .into());
+            }
+        }
+    }
+
+    println!("Applied {} of {} settings", ops.len() - failed, ops.len());
+    if failed > 0 {
+        return Err(format!("{failed} settings failed").into());
+    }
+    Ok(())
+}
+
+fn handle_armoury_command(
+    conn: &Connection,
+    cmd: &ArmouryCommand,
+) -> Result<(), Box<dyn std::error::Error>> {
     {
         if cmd.free.is_empty() || cmd.free.len() % 2 != 0 || cmd.help {
             const USAGE: &str = "Usage: asusctl platform panel_overdrive 1 nv_dynamic_boost 5";
@@ -1192,9 +2288,35 @@ fn handle_armoury_command(cmd: &ArmouryCommand) -> Result<(), Box<dyn std::error
         }
 
         if let Ok(attr) = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury") {
+            // Several PPT limits are applied together so that a rejected value
+            // does not leave the others half applied
+            let mut ppt = Vec::new();
+            for cmd in cmd.free.chunks(2) {
+                let name = FirmwareAttribute::from(cmd[0].as_str());
+                if name.is_ppt() {
+                    let mut value: i32 = cmd[1].parse()?;
+                    if value == -1 {
+                        if let Some(attr) = attr.iter().find(|a| a.name().ok() == Some(name)) {
+                            value = attr.default_value()?;
+                        }
+                    }
+                    ppt.push((name, value));
+                }
+            }
+            let batch_ppt = ppt.len() > 1;
+            if batch_ppt {
+                PlatformProxyBlocking::new(conn)?.apply_ppt_preset(&ppt)?;
+            }
+
             for cmd in cmd.free.chunks(2) {
                 for attr in attr.iter() {
                     let name = attr.name()?;
+                    if batch_ppt && name.is_ppt() {
+                        if <&str>::from(name) == cmd[0] {
+                            print_firmware_attr(attr)?;
+                        }
+                        continue;
+                    }
                     if <&str>::from(name) == cmd[0] {
                         let mut value: i32 = cmd[1].parse()?;
                         if value == -1 {
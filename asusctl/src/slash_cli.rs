@@ -17,6 +17,19 @@ pub struct SlashCommand {
     pub mode: Option<SlashMode>,
     #[options(help = "list available animations")]
     pub list: bool,
+    #[options(
+        no_short,
+        meta = "",
+        help = "briefly play a mode, or the mode for <mail, chat, alert>"
+    )]
+    pub blip: Option<String>,
+    #[options(
+        no_short,
+        meta = "",
+        default = "3000",
+        help = "how long to play the blip in ms"
+    )]
+    pub blip_ms: u32,
 
     #[options(short = "B", meta = "", help = "Show the animation on boot")]
     pub show_on_boot: Option<bool>,
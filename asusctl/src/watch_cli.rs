@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use gumdrop::Options;
+use rog_dbus::DBUS_NAME;
+use serde_json::{json, Map, Number, Value as Json};
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::Type;
+use zbus::zvariant::{OwnedValue, Structure, Value};
+use zbus::MatchRule;
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+#[derive(Options)]
+pub struct WatchCommand {
+    #[options(help = "print help message")]
+    pub help: bool,
+    #[options(no_short, help = "print each event as a line of JSON")]
+    pub json: bool,
+}
+
+/// A signal from asusd, with a changed property being one event each
+enum Event {
+    Signal {
+        path: String,
+        interface: String,
+        member: String,
+        args: Vec<OwnedValue>,
+    },
+    Property {
+        path: String,
+        interface: String,
+        name: String,
+        value: Option<OwnedValue>,
+    },
+}
+
+impl Event {
+    fn text(&self) -> String {
+        match self {
+            Event::Signal {
+                path,
+                interface,
+                member,
+                args,
+            } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                format!("{path} {interface}.{member}({})", args.join(", "))
+            }
+            Event::Property {
+                path,
+                interface,
+                name,
+                value: Some(value),
+            } => format!("{path} {interface}.{name} = {}", &**value),
+            Event::Property {
+                path,
+                interface,
+                name,
+                value: None,
+            } => format!("{path} {interface}.{name} changed"),
+        }
+    }
+
+    fn json(&self) -> Json {
+        match self {
+            Event::Signal {
+                path,
+                interface,
+                member,
+                args,
+            } => json!({
+                "path": path,
+                "interface": interface,
+                "signal": member,
+                "args": args.iter().map(|arg| to_json(arg)).collect::<Vec<_>>(),
+            }),
+            Event::Property {
+                path,
+                interface,
+                name,
+                value,
+            } => json!({
+                "path": path,
+                "interface": interface,
+                "property": name,
+                "value": value.as_ref().map(|value| to_json(value)),
+            }),
+        }
+    }
+}
+
+/// DBus values as plain JSON: structs are arrays, and dicts are objects when
+/// their keys are strings or numbers
+fn to_json(value: &Value<'_>) -> Json {
+    match value {
+        Value::U8(v) => json!(v),
+        Value::Bool(v) => json!(v),
+        Value::I16(v) => json!(v),
+        Value::U16(v) => json!(v),
+        Value::I32(v) => json!(v),
+        Value::U32(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::U64(v) => json!(v),
+        Value::F64(v) => Number::from_f64(*v).map_or(Json::Null, Json::Number),
+        Value::Str(v) => json!(v.as_str()),
+        Value::Signature(v) => json!(v.to_string()),
+        Value::ObjectPath(v) => json!(v.as_str()),
+        Value::Value(v) => to_json(v),
+        Value::Array(v) => Json::Array(v.iter().map(to_json).collect()),
+        Value::Dict(v) => {
+            let mut map = Map::new();
+            for (key, value) in v.iter() {
+                let key = match to_json(key) {
+                    Json::String(s) => s,
+                    other => other.to_string(),
+                };
+                map.insert(key, to_json(value));
+            }
+            Json::Object(map)
+        }
+        Value::Structure(v) => Json::Array(v.fields().iter().map(to_json).collect()),
+        // File descriptors, which asusd doesn't send
+        _ => Json::Null,
+    }
+}
+
+fn events(msg: &zbus::Message) -> Vec<Event> {
+    let header = msg.header();
+    let path = header.path().map(|p| p.to_string()).unwrap_or_default();
+    let interface = header
+        .interface()
+        .map(|i| i.to_string())
+        .unwrap_or_default();
+    let member = header.member().map(|m| m.to_string()).unwrap_or_default();
+
+    if interface == PROPERTIES_INTERFACE && member == "PropertiesChanged" {
+        let Ok((interface, changed, invalidated)) =
+            msg.body()
+                .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+        else {
+            return Vec::new();
+        };
+        let mut changed: Vec<(String, OwnedValue)> = changed.into_iter().collect();
+        changed.sort_by(|a, b| a.0.cmp(&b.0));
+        return changed
+            .into_iter()
+            .map(|(name, value)| (name, Some(value)))
+            .chain(invalidated.into_iter().map(|name| (name, None)))
+            .map(|(name, value)| Event::Property {
+                path: path.clone(),
+                interface: interface.clone(),
+                name,
+                value,
+            })
+            .collect();
+    }
+
+    let args = msg
+        .body()
+        .deserialize::<Structure<'_>>()
+        .map(|s| {
+            s.into_fields()
+                .into_iter()
+                .filter_map(|v| OwnedValue::try_from(v).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    vec![
+        Event::Signal {
+            path,
+            interface,
+            member,
+            args,
+        },
+    ]
+}
+
+/// Print every signal asusd sends until interrupted. The match is on the
+/// well-known name so events carry on after asusd restarts
+pub fn watch(conn: &Connection, cmd: &WatchCommand) -> Result<(), Box<dyn std::error::Error>> {
+    if cmd.help {
+        println!("{}", cmd.self_usage());
+        return Ok(());
+    }
+    let rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender(DBUS_NAME)?
+        .build();
+    for msg in MessageIterator::for_match_rule(rule, conn, None)? {
+        for event in events(&msg?) {
+            if cmd.json {
+                println!("{}", event.json());
+            } else {
+                println!("{}", event.text());
+            }
+        }
+    }
+    Ok(())
+}
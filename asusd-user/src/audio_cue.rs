@@ -0,0 +1,85 @@
+//! Watches the PipeWire default audio devices with `pw-metadata` and flashes
+//! the keyboard when one changes, so a switch to the wrong output is noticed.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+use rog_aura::{AuraEffect, AuraModeNum};
+use rog_dbus::zbus_aura::AuraProxyBlocking;
+
+use crate::config::ConfigAudioCue;
+use crate::error::Error;
+
+const SINK_KEY: &str = "default.audio.sink";
+const SOURCE_KEY: &str = "default.audio.source";
+
+/// Get the key and value from a `pw-metadata` update line such as
+/// `update: id:0 key:'default.audio.sink' value:'{"name":"hdmi"}' type:...`
+fn parse_update(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim().strip_prefix("update:")?;
+    let (_, rest) = line.split_once("key:'")?;
+    let (key, rest) = rest.split_once('\'')?;
+    let value = rest
+        .split_once("value:'")
+        .and_then(|(_, v)| v.split_once("' type:").map(|(v, _)| v).or(Some(v)))
+        .unwrap_or_default();
+    Some((key, value))
+}
+
+fn flash(aura: &AuraProxyBlocking<'static>, cue: &ConfigAudioCue) -> zbus::Result<()> {
+    let previous = aura.led_mode_data()?;
+    aura.set_led_mode_data(AuraEffect {
+        mode: AuraModeNum::Static,
+        zone: cue.zone,
+        colour1: cue.colour,
+        ..previous.clone()
+    })?;
+    sleep(Duration::from_millis(cue.duration_ms));
+    aura.set_led_mode_data(previous)
+}
+
+/// Flash the keyboard each time the default sink or source changes. The
+/// devices found at start are not flashed for. Blocks until `pw-metadata`
+/// exits.
+pub fn watch_default_devices(
+    aura: AuraProxyBlocking<'static>,
+    cue: ConfigAudioCue,
+) -> Result<(), Error> {
+    let mut child = Command::new("pw-metadata")
+        .arg("--monitor")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let Some(stdout) = child.stdout.take() else {
+        return Ok(());
+    };
+
+    let mut sink: Option<String> = None;
+    let mut source: Option<String> = None;
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        let Some((key, value)) = parse_update(&line) else {
+            continue;
+        };
+        let (last, enabled) = match key {
+            SINK_KEY => (&mut sink, cue.on_sink),
+            SOURCE_KEY => (&mut source, cue.on_source),
+            _ => continue,
+        };
+        if last.as_deref() == Some(value) {
+            continue;
+        }
+        let first = last.is_none();
+        *last = Some(value.to_owned());
+        if enabled && !first && !value.is_empty() {
+            println!("Audio cue: {key} is now {value}");
+            flash(&aura, &cue)
+                .map_err(|e| println!("Audio cue: could not flash keyboard: {e}"))
+                .ok();
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
@@ -5,7 +5,7 @@ use config_traits::{StdConfig, StdConfigLoad};
 use rog_anime::{ActionLoader, AnimTime, AnimeType, Fade, Sequences as AnimeSequences, Vec2};
 use rog_aura::effects::{AdvancedEffects as AuraSequences, Breathe, DoomFlicker, Effect, Static};
 use rog_aura::keyboard::LedCode;
-use rog_aura::{Colour, Speed};
+use rog_aura::{AuraZone, Colour, Speed};
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
@@ -203,6 +203,67 @@ impl StdConfig for ConfigAura {
 
 impl StdConfigLoad for ConfigAura {}
 
+/// Flash the keyboard when the default audio output or input changes, such as
+/// when a dock is plugged in or HDMI audio takes over
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigAudioCue {
+    /// Flash when the default output (sink) changes
+    pub on_sink: bool,
+    /// Flash when the default input (source) changes
+    pub on_source: bool,
+    /// The zone to flash, `None` is the whole keyboard
+    pub zone: AuraZone,
+    pub colour: Colour,
+    pub duration_ms: u64,
+}
+
+impl Default for ConfigAudioCue {
+    fn default() -> Self {
+        Self {
+            on_sink: true,
+            on_source: false,
+            zone: AuraZone::None,
+            colour: Colour {
+                r: 255,
+                g: 160,
+                b: 0,
+            },
+            duration_ms: 600,
+        }
+    }
+}
+
+/// Show the track when the media player changes track, read from MPRIS
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConfigNowPlaying {
+    /// Scroll "artist - title" across the AniMe display
+    pub anime: bool,
+    /// Play `slash_mode` on the Slash ledbar, which can't show text
+    pub slash: bool,
+    /// A Slash mode name, or one of the AniMe blip icons
+    pub slash_mode: String,
+    pub slash_duration_ms: u32,
+    /// Milliseconds per LED column the text moves
+    pub scroll_step_ms: u64,
+    /// Track changes less than this far apart are not shown
+    pub min_interval_secs: u64,
+}
+
+impl Default for ConfigNowPlaying {
+    fn default() -> Self {
+        Self {
+            anime: true,
+            slash: true,
+            slash_mode: "Transmission".to_owned(),
+            slash_duration_ms: 2000,
+            scroll_step_ms: 60,
+            min_interval_secs: 10,
+        }
+    }
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 #[serde(default)]
 pub struct ConfigBase {
@@ -210,6 +271,10 @@ pub struct ConfigBase {
     pub active_anime: Option<String>,
     /// Name of active aura config file in the user config directory
     pub active_aura: Option<String>,
+    /// Flash the keyboard when the default audio device changes. Off if unset
+    pub audio_cue: Option<ConfigAudioCue>,
+    /// Show track changes on the AniMe display or Slash. Off if unset
+    pub now_playing: Option<ConfigNowPlaying>,
 }
 
 impl StdConfig for ConfigBase {
@@ -217,6 +282,8 @@ impl StdConfig for ConfigBase {
         Self {
             active_anime: Some("anime-default".to_owned()),
             active_aura: Some("aura-default".to_owned()),
+            audio_cue: None,
+            now_playing: None,
         }
     }
 
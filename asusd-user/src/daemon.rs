@@ -2,15 +2,19 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use asusd_user::audio_cue::watch_default_devices;
 use asusd_user::config::*;
 use asusd_user::ctrl_anime::{CtrlAnime, CtrlAnimeInner};
+use asusd_user::now_playing::{watch_now_playing, NowPlayingTargets};
 use config_traits::{StdConfig, StdConfigLoad};
 use rog_anime::usb::get_anime_type;
 use rog_aura::aura_detection::LedSupportData;
-use rog_aura::keyboard::KeyLayout;
+use rog_aura::keyboard::{KeyLayout, LayoutReloader, LAYOUT_DIR_ENV};
 use rog_dbus::zbus_anime::AnimeProxyBlocking;
 use rog_dbus::zbus_aura::AuraProxyBlocking;
+use rog_dbus::zbus_slash::SlashProxyBlocking;
 use rog_dbus::{list_iface_blocking, DBUS_NAME};
 use smol::Executor;
 use zbus::Connection;
@@ -41,6 +45,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let executor = Executor::new();
 
     let early_return = Arc::new(AtomicBool::new(false));
+    let anime_pause = early_return.clone();
+    let user_anime_running = config.active_anime.is_some();
     // Set up the anime data and run loop/thread
     if supported.contains(&"xyz.ljones.Anime".to_string()) {
         if let Some(cfg) = config.active_anime {
@@ -84,14 +90,52 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if let Some(cue) = config.audio_cue.clone() {
+        if supported.contains(&"xyz.ljones.Aura".to_string()) {
+            let aura_proxy_blocking = AuraProxyBlocking::new(&conn).unwrap();
+            std::thread::spawn(move || {
+                watch_default_devices(aura_proxy_blocking, cue)
+                    .map_err(|e| println!("Audio cue: could not watch audio devices: {e}"))
+                    .ok();
+            });
+        }
+    }
+
+    if let Some(now_playing) = config.now_playing.clone() {
+        let anime = supported
+            .contains(&"xyz.ljones.Anime".to_string())
+            .then(|| (AnimeProxyBlocking::new(&conn).unwrap(), get_anime_type()));
+        let slash = supported
+            .contains(&"xyz.ljones.Slash".to_string())
+            .then(|| SlashProxyBlocking::new(&conn).unwrap());
+        let targets = NowPlayingTargets {
+            anime,
+            slash,
+            anime_pause,
+            resume_main_loop: !user_anime_running,
+        };
+        std::thread::spawn(move || {
+            watch_now_playing(targets, now_playing)
+                .map_err(|e| println!("Now playing: could not watch media players: {e}"))
+                .ok();
+        });
+    }
+
     // if supported.keyboard_led.per_key_led_mode {
     if let Some(cfg) = config.active_aura {
         let mut aura_config = ConfigAura::new().set_name(cfg).load();
         // let baord_name = std::fs::read_to_string(BOARD_NAME)?;
 
         let led_support = LedSupportData::get_data("");
+        // Layouts being developed are reloaded when saved
+        let mut reloader = std::env::var_os(LAYOUT_DIR_ENV).map(|_| {
+            LayoutReloader::new(KeyLayout::layout_path(
+                &led_support.layout_name,
+                PathBuf::from(DATA_DIR),
+            ))
+        });
 
-        let layout = KeyLayout::find_layout(led_support, PathBuf::from(DATA_DIR))
+        let mut layout = KeyLayout::find_layout(led_support, PathBuf::from(DATA_DIR))
             .map_err(|e| {
                 println!("{BOARD_NAME}, {e}");
             })
@@ -100,12 +144,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let aura_proxy_blocking = AuraProxyBlocking::new(&conn).unwrap();
         executor
             .spawn(async move {
+                let mut last_poll = Instant::now();
                 loop {
+                    let poll_due = last_poll.elapsed() >= Duration::from_secs(1);
+                    if let Some(reloader) = reloader.as_mut().filter(|_| poll_due) {
+                        last_poll = Instant::now();
+                        match reloader.poll(&layout) {
+                            Some(Ok(new)) => {
+                                println!("Reloaded keyboard layout");
+                                layout = new;
+                            }
+                            Some(Err(e)) => println!("Could not reload keyboard layout: {e}"),
+                            None => {}
+                        }
+                    }
                     aura_config.aura.next_state(&layout);
                     let packets = aura_config.aura.create_packets();
 
                     aura_proxy_blocking.direct_addressing_raw(packets).unwrap();
-                    std::thread::sleep(std::time::Duration::from_millis(33));
+                    std::thread::sleep(Duration::from_millis(33));
                 }
             })
             .detach();
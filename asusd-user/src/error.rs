@@ -9,6 +9,7 @@ pub enum Error {
     ConfigLockFail,
     XdgVars,
     Anime(AnimeError),
+    Zbus(zbus::Error),
 }
 
 impl fmt::Display for Error {
@@ -20,6 +21,7 @@ impl fmt::Display for Error {
             Error::ConfigLockFail => write!(f, "Failed to lock user config"),
             Error::XdgVars => write!(f, "XDG environment vars appear unset"),
             Error::Anime(err) => write!(f, "Anime error: {}", err),
+            Error::Zbus(err) => write!(f, "Dbus error: {}", err),
         }
     }
 }
@@ -38,6 +40,12 @@ impl From<AnimeError> for Error {
     }
 }
 
+impl From<zbus::Error> for Error {
+    fn from(err: zbus::Error) -> Self {
+        Error::Zbus(err)
+    }
+}
+
 impl From<Error> for zbus::fdo::Error {
     fn from(err: Error) -> Self {
         zbus::fdo::Error::Failed(format!("Anime zbus error: {}", err))
@@ -6,4 +6,8 @@ pub mod ctrl_anime;
 
 pub mod zbus_anime;
 
+pub mod audio_cue;
+
+pub mod now_playing;
+
 pub static VERSION: &str = env!("CARGO_PKG_VERSION");
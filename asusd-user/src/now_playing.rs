@@ -0,0 +1,154 @@
+//! Watches MPRIS media players on the session bus and shows each new track
+//! briefly, scrolling "artist - title" across the AniMe display and playing a
+//! blip on the Slash.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rog_anime::{scroll_text_buffers, AnimeType};
+use rog_dbus::zbus_anime::AnimeProxyBlocking;
+use rog_dbus::zbus_slash::SlashProxyBlocking;
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::OwnedValue;
+use zbus::MatchRule;
+
+use crate::config::ConfigNowPlaying;
+use crate::error::Error;
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+/// Longer text is cut so a scroll doesn't take minutes
+const MAX_CHARS: usize = 48;
+
+/// Where the track is shown
+pub struct NowPlayingTargets {
+    pub anime: Option<(AnimeProxyBlocking<'static>, AnimeType)>,
+    pub slash: Option<SlashProxyBlocking<'static>>,
+    /// Set while scrolling to pause the user AniMe sequence, must be the same
+    /// atomic as in `CtrlAnimeInner`
+    pub anime_pause: Arc<AtomicBool>,
+    /// Restart the asusd system animations after scrolling if the builtins
+    /// are off, used when no user AniMe sequence is running
+    pub resume_main_loop: bool,
+}
+
+/// "Artist - Title" from the MPRIS `Metadata` property. `None` if there is no
+/// title.
+fn track_text(metadata: &HashMap<String, OwnedValue>) -> Option<String> {
+    let title = metadata
+        .get("xesam:title")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| String::try_from(v).ok())
+        .filter(|t| !t.trim().is_empty())?;
+    let artists = metadata
+        .get("xesam:artist")
+        .and_then(|v| v.try_clone().ok())
+        .and_then(|v| Vec::<String>::try_from(v).ok())
+        .unwrap_or_default();
+    let text = if artists.is_empty() {
+        title
+    } else {
+        format!("{} - {title}", artists.join(", "))
+    };
+    Some(text.chars().take(MAX_CHARS).collect())
+}
+
+fn scroll_on_anime(
+    anime: &AnimeProxyBlocking<'static>,
+    anime_type: AnimeType,
+    text: &str,
+    targets: &NowPlayingTargets,
+    config: &ConfigNowPlaying,
+) -> Result<(), Error> {
+    let frames = scroll_text_buffers(anime_type, text)?;
+    let builtins = anime.builtins_enabled().unwrap_or_default();
+    let was_paused = targets.anime_pause.swap(true, Ordering::SeqCst);
+    let mut result = Ok(());
+    for frame in frames {
+        if let Err(e) = anime.write(frame) {
+            result = Err(e.into());
+            break;
+        }
+        sleep(Duration::from_millis(config.scroll_step_ms));
+    }
+    targets.anime_pause.store(was_paused, Ordering::SeqCst);
+    if builtins {
+        anime.set_builtins_enabled(true)?;
+    } else if targets.resume_main_loop {
+        anime.run_main_loop(true)?;
+    }
+    result
+}
+
+fn show(text: &str, targets: &NowPlayingTargets, config: &ConfigNowPlaying) {
+    if config.slash {
+        if let Some(slash) = &targets.slash {
+            slash
+                .notify_blip(&config.slash_mode, config.slash_duration_ms)
+                .map_err(|e| println!("Now playing: could not notify Slash: {e}"))
+                .ok();
+        }
+    }
+    if config.anime {
+        if let Some((anime, anime_type)) = &targets.anime {
+            scroll_on_anime(anime, *anime_type, text, targets, config)
+                .map_err(|e| println!("Now playing: could not scroll on AniMe: {e}"))
+                .ok();
+        }
+    }
+}
+
+/// Show each track change reported by an MPRIS player. The same track is not
+/// shown twice in a row, and changes within `min_interval_secs` of the last
+/// one shown are skipped. Blocks while the session bus is connected.
+pub fn watch_now_playing(
+    targets: NowPlayingTargets,
+    config: ConfigNowPlaying,
+) -> Result<(), Error> {
+    let conn = Connection::session()?;
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(MPRIS_PATH)?
+        .arg(0, MPRIS_PLAYER)?
+        .build();
+
+    let min_interval = Duration::from_secs(config.min_interval_secs);
+    let mut last_text: Option<String> = None;
+    let mut last_shown: Option<Instant> = None;
+    for msg in MessageIterator::for_match_rule(rule, &conn, None)? {
+        let Ok(msg) = msg else {
+            continue;
+        };
+        let Ok((_, changed, _)) = msg
+            .body()
+            .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()
+        else {
+            continue;
+        };
+        let Some(text) = changed
+            .get("Metadata")
+            .and_then(|v| v.try_clone().ok())
+            .and_then(|v| HashMap::<String, OwnedValue>::try_from(v).ok())
+            .and_then(|metadata| track_text(&metadata))
+        else {
+            continue;
+        };
+        if last_text.as_deref() == Some(text.as_str()) {
+            continue;
+        }
+        last_text = Some(text.clone());
+        if last_shown.is_some_and(|t| t.elapsed() < min_interval) {
+            continue;
+        }
+        println!("Now playing: {text}");
+        show(&text, &targets, &config);
+        last_shown = Some(Instant::now());
+    }
+    Ok(())
+}
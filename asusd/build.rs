@@ -0,0 +1,483 @@
+//! Generates the API description printed by `asusd --dump-api` from the
+//! `#[interface]` blocks and the DBus enums of the workspace, so that it does
+//! not need a running daemon or the hardware an interface is for.
+//!
+//! For each source file with an interface `$OUT_DIR/api/<file>` holds an
+//! `api_description()` which that file includes, so the argument types
+//! resolve as they do in the interface. `$OUT_DIR/api/all.rs` collects them
+//! and `$OUT_DIR/api/enums.rs` lists the enums.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+use quote::ToTokens;
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Expr, Fields, FnArg, ImplItem, Item, ItemEnum, Lit, Meta, Pat, PathArguments,
+    ReturnType, Signature, Token, TraitItemFn, Type,
+};
+
+/// Crates whose enums can be sent over DBus, and their sources
+const ENUM_CRATES: &[(&str, &str)] = &[
+    ("asusd", "src"),
+    ("rog_anime", "../rog-anime/src"),
+    ("rog_aura", "../rog-aura/src"),
+    ("rog_platform", "../rog-platform/src"),
+    ("rog_profiles", "../rog-profiles/src"),
+    ("rog_scsi", "../rog-scsi/src"),
+    ("rog_slash", "../rog-slash/src"),
+];
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("reading {dir:?}: {e}"))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+fn parse_file(path: &Path) -> syn::File {
+    let source = fs::read_to_string(path).unwrap_or_else(|e| panic!("reading {path:?}: {e}"));
+    syn::parse_file(&source).unwrap_or_else(|e| panic!("parsing {path:?}: {e}"))
+}
+
+/// The items of every `#[$name(...)]` attribute
+fn attr_metas(attrs: &[Attribute], name: &str) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident(name))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .collect()
+}
+
+fn str_value(meta: &Meta, name: &str) -> Option<String> {
+    match meta {
+        Meta::NameValue(nv) if nv.path.is_ident(name) => match &nv.value {
+            Expr::Lit(lit) => match &lit.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn has_path(metas: &[Meta], name: &str) -> bool {
+    metas.iter().any(|meta| meta.path().is_ident(name))
+}
+
+/// Arguments zbus fills in itself rather than taking from the message
+fn is_injected(attrs: &[Attribute]) -> bool {
+    let metas = attr_metas(attrs, "zbus");
+    [
+        "object_server", "connection", "header", "signal_context", "signal_emitter",
+    ]
+    .iter()
+    .any(|name| {
+        metas
+            .iter()
+            .any(|meta| matches!(meta, Meta::Path(p) if p.is_ident(name)))
+    })
+}
+
+/// `snake_case` to `PascalCase`, as zbus names members
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect()
+}
+
+/// The type inside a `Result`, which zbus replies with or turns into an error
+fn unwrap_result(ty: &Type) -> &Type {
+    if let Type::Path(p) = ty {
+        if let Some(last) = p.path.segments.last() {
+            if last.ident == "Result" {
+                if let PathArguments::AngleBracketed(args) = &last.arguments {
+                    if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                        return inner;
+                    }
+                }
+            }
+        }
+    }
+    ty
+}
+
+fn signature(ty: &Type) -> String {
+    format!(
+        "<{} as ::zbus::zvariant::Type>::SIGNATURE",
+        ty.to_token_stream()
+    )
+}
+
+fn arg(name: &str, ty: &Type) -> String {
+    format!("ArgDesc::new({name:?}, {})", signature(ty))
+}
+
+fn cfgs(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .map(|attr| attr.to_token_stream().to_string() + " ")
+        .collect()
+}
+
+/// The arguments taken from the message, as `(name, type)`
+fn inputs(sig: &Signature, is_signal: bool) -> Vec<(String, Type)> {
+    let mut typed: Vec<_> = sig
+        .inputs
+        .iter()
+        .filter_map(|input| match input {
+            FnArg::Typed(pat_type) => Some(pat_type),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    if is_signal && !typed.is_empty() {
+        // The emitter
+        typed.remove(0);
+    }
+    typed
+        .into_iter()
+        .filter(|pat_type| !is_injected(&pat_type.attrs))
+        .map(|pat_type| {
+            let name = match pat_type.pat.as_ref() {
+                Pat::Ident(ident) => ident.ident.to_string(),
+                other => other.to_token_stream().to_string(),
+            };
+            (name, pat_type.ty.as_ref().clone())
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Property {
+    ty: Option<Type>,
+    writable: bool,
+    emits_changed_signal: Option<String>,
+    cfgs: String,
+}
+
+/// The `api_description()` of one source file, if it has an interface
+fn describe_interfaces(file: &syn::File) -> Option<String> {
+    let mut out = String::new();
+    for item in &file.items {
+        let Item::Impl(block) = item else {
+            continue;
+        };
+        let Some(name) = attr_metas(&block.attrs, "interface")
+            .iter()
+            .find_map(|meta| str_value(meta, "name"))
+        else {
+            continue;
+        };
+
+        let mut methods = String::new();
+        let mut signals = String::new();
+        let mut properties: BTreeMap<String, Property> = BTreeMap::new();
+        for item in &block.items {
+            // Signals have no body, which syn leaves as tokens
+            let (attrs, sig) = match item {
+                ImplItem::Fn(method) => (method.attrs.clone(), method.sig.clone()),
+                ImplItem::Verbatim(tokens) => match syn::parse2::<TraitItemFn>(tokens.clone()) {
+                    Ok(method) => (method.attrs, method.sig),
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+            let metas = attr_metas(&attrs, "zbus");
+            let fn_name = sig.ident.to_string();
+
+            if let Some(property) = metas.iter().find(|meta| meta.path().is_ident("property")) {
+                let (prop_name, is_setter) = match fn_name.strip_prefix("set_") {
+                    Some(name) if sig.inputs.len() > 1 => (name.to_owned(), true),
+                    _ => (fn_name.clone(), false),
+                };
+                let entry = properties.entry(pascal_case(&prop_name)).or_default();
+                if is_setter {
+                    entry.writable = true;
+                } else {
+                    if let ReturnType::Type(_, ty) = &sig.output {
+                        entry.ty = Some(unwrap_result(ty).clone());
+                    }
+                    entry.cfgs = cfgs(&attrs);
+                }
+                if let Meta::List(list) = property {
+                    if let Ok(nested) =
+                        list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                    {
+                        entry.emits_changed_signal = nested
+                            .iter()
+                            .find_map(|meta| str_value(meta, "emits_changed_signal"));
+                    }
+                }
+                continue;
+            }
+
+            let is_signal = has_path(&metas, "signal");
+            let member = metas
+                .iter()
+                .find_map(|meta| str_value(meta, "name"))
+                .unwrap_or_else(|| pascal_case(&fn_name));
+            let args: Vec<String> = inputs(&sig, is_signal)
+                .iter()
+                .map(|(name, ty)| arg(name, ty))
+                .collect();
+            let cfgs = cfgs(&attrs);
+            if is_signal {
+                writeln!(
+                    signals,
+                    "        {cfgs}signals.push(SignalDesc::new({member:?}, vec![{}]));",
+                    args.join(", ")
+                )
+                .unwrap();
+                continue;
+            }
+
+            let outputs: Vec<String> = match &sig.output {
+                ReturnType::Default => Vec::new(),
+                ReturnType::Type(_, ty) => match unwrap_result(ty) {
+                    Type::Tuple(tuple) => tuple.elems.iter().map(|ty| arg("", ty)).collect(),
+                    ty => vec![arg(
+                        "", ty,
+                    )],
+                },
+            };
+            writeln!(
+                methods,
+                "        {cfgs}methods.push(MethodDesc::new({member:?}, vec![{}], vec![{}]));",
+                args.join(", "),
+                outputs.join(", ")
+            )
+            .unwrap();
+        }
+
+        let mut props = String::new();
+        for (prop_name, property) in properties {
+            let ty = property
+                .ty
+                .unwrap_or_else(|| panic!("{name}.{prop_name} has no getter"));
+            writeln!(
+                props,
+                "        {}properties.push(PropertyDesc::new({prop_name:?}, {}, {}, {:?}));",
+                property.cfgs,
+                signature(&ty),
+                property.writable,
+                property.emits_changed_signal.as_deref().unwrap_or("true")
+            )
+            .unwrap();
+        }
+
+        writeln!(
+            out,
+            "    {{\n        let mut methods = Vec::new();\n{methods}        let mut properties = \
+             Vec::new();\n{props}        let mut signals = Vec::new();\n{signals}        \
+             interfaces.push(InterfaceDesc::new({name:?}, methods, properties, signals));\n    }}"
+        )
+        .unwrap();
+    }
+    if out.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "/// The interfaces of this module for `asusd --dump-api`, generated by \
+         build.rs\n#[allow(clippy::vec_init_then_push, unused_mut)]\npub(crate) fn \
+         api_description() -> Vec<crate::api_dump::InterfaceDesc> {{\n    \
+         #[allow(unused_imports)]\n    use crate::api_dump::{{ArgDesc, InterfaceDesc, MethodDesc, \
+         PropertyDesc, SignalDesc}};\n    let mut interfaces = Vec::new();\n{out}    \
+         interfaces\n}}\n"
+    ))
+}
+
+/// `crate::a::b` for `src/a/b.rs` or `src/a/b/mod.rs`
+fn module_path(relative: &Path) -> String {
+    let mut parts: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if parts.last().is_some_and(|last| last == "mod") {
+        parts.pop();
+    }
+    std::iter::once("crate".to_owned())
+        .chain(parts)
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// The signature zvariant gives a unit enum, from its `zvariant` and `repr`
+/// attributes
+fn enum_signature(attrs: &[Attribute], metas: &[Meta]) -> String {
+    if let Some(signature) = metas.iter().find_map(|meta| str_value(meta, "signature")) {
+        return signature;
+    }
+    let repr = attr_metas(attrs, "repr");
+    [
+        ("u8", "y"),
+        ("u16", "q"),
+        ("i16", "n"),
+        ("i32", "i"),
+        ("u64", "t"),
+        ("i64", "x"),
+    ]
+    .iter()
+    .find(|(ty, _)| has_path(&repr, ty))
+    .map_or("u", |(_, signature)| signature)
+    .to_owned()
+}
+
+/// `EnumDesc::new(...)` for unit enums deriving zvariant's `Type`
+fn describe_enum(krate: &str, item: &ItemEnum) -> Option<String> {
+    // Also `#[cfg_attr(feature = "dbus", derive(Type), zvariant(...))]`
+    let mut metas = Vec::new();
+    for attr in &item.attrs {
+        if attr.path().is_ident("cfg_attr") {
+            metas.extend(
+                attr_metas(std::slice::from_ref(attr), "cfg_attr")
+                    .into_iter()
+                    .skip(1),
+            );
+        } else if attr.path().is_ident("derive") || attr.path().is_ident("zvariant") {
+            metas.push(attr.meta.clone());
+        }
+    }
+    let mut derives = Vec::new();
+    let mut zvariant = Vec::new();
+    for meta in &metas {
+        if let Meta::List(list) = meta {
+            let nested = list
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()?;
+            if list.path.is_ident("derive") {
+                derives.extend(nested);
+            } else if list.path.is_ident("zvariant") {
+                zvariant.extend(nested);
+            }
+        }
+    }
+    let derives_type = derives.iter().any(|meta| {
+        meta.path()
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "Type")
+    });
+    if !derives_type
+        || item
+            .variants
+            .iter()
+            .any(|v| !matches!(v.fields, Fields::Unit))
+    {
+        return None;
+    }
+
+    let signature = enum_signature(&item.attrs, &zvariant);
+    let mut next = 0i64;
+    let mut variants = Vec::new();
+    for variant in &item.variants {
+        let name = variant.ident.to_string();
+        if let Some((_, Expr::Lit(lit))) = &variant.discriminant {
+            if let Lit::Int(int) = &lit.lit {
+                next = int.base10_parse().unwrap_or(next);
+            }
+        }
+        let value = if signature == "s" {
+            format!("{name:?}")
+        } else {
+            next.to_string()
+        };
+        variants.push(format!("VariantDesc::new({name:?}, {value})"));
+        next += 1;
+    }
+    Some(format!(
+        "        EnumDesc::new({krate:?}, {:?}, {signature:?}, vec![{}]),\n",
+        item.ident.to_string(),
+        variants.join(", ")
+    ))
+}
+
+fn collect_enums(krate: &str, items: &[Item], out: &mut String) {
+    for item in items {
+        match item {
+            Item::Enum(item) => {
+                if let Some(desc) = describe_enum(krate, item) {
+                    out.push_str(&desc);
+                }
+            }
+            Item::Mod(module) if !has_path(&attr_metas(&module.attrs, "cfg"), "test") => {
+                if let Some((_, items)) = &module.content {
+                    collect_enums(krate, items, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_if_changed(path: &Path, contents: &str) {
+    if fs::read_to_string(path).ok().as_deref() != Some(contents) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap_or_else(|e| panic!("writing {path:?}: {e}"));
+    }
+}
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap()).join("api");
+
+    let src = manifest_dir.join("src");
+    let mut files = Vec::new();
+    rust_files(&src, &mut files);
+    let mut all = String::from(
+        "/// Every interface asusd can serve, generated by build.rs\npub(crate) fn interfaces() \
+         -> Vec<InterfaceDesc> {\n    let mut interfaces = Vec::new();\n",
+    );
+    for path in &files {
+        let Some(description) = describe_interfaces(&parse_file(path)) else {
+            continue;
+        };
+        let relative = path.strip_prefix(&src).unwrap();
+        write_if_changed(&out_dir.join(relative), &description);
+        writeln!(
+            all,
+            "    interfaces.extend({}::api_description());",
+            module_path(relative)
+        )
+        .unwrap();
+    }
+    all.push_str("    interfaces\n}\n");
+    write_if_changed(&out_dir.join("all.rs"), &all);
+
+    let mut enums = String::from(
+        "/// The unit enums of the workspace that can be sent over DBus, generated by \
+         build.rs\npub(crate) fn enums() -> Vec<EnumDesc> {\n    vec![\n",
+    );
+    for (krate, dir) in ENUM_CRATES {
+        let dir = manifest_dir.join(dir);
+        println!("cargo:rerun-if-changed={}", dir.display());
+        let mut files = Vec::new();
+        rust_files(&dir, &mut files);
+        for path in &files {
+            collect_enums(krate, &parse_file(path).items, &mut enums);
+        }
+    }
+    enums.push_str("    ]\n}\n");
+    write_if_changed(&out_dir.join("enums.rs"), &enums);
+}
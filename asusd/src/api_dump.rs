@@ -0,0 +1,275 @@
+//! `asusd --dump-api json` prints the DBus API as JSON for generating
+//! bindings. It is generated by build.rs from the `#[interface]` blocks and
+//! the DBus enums, so it always matches the code and lists the interfaces of
+//! hardware that is not present.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+
+use serde::Serialize;
+use zbus::fdo::IntrospectableProxy;
+use zbus::Connection;
+use zbus_xml::Node;
+
+use crate::ctrl_capabilities::{API_VERSION, INTERFACE_VERSIONS};
+use crate::DBUS_NAME;
+
+include!(concat!(env!("OUT_DIR"), "/api/all.rs"));
+include!(concat!(env!("OUT_DIR"), "/api/enums.rs"));
+
+#[derive(Debug, Serialize)]
+pub struct ApiDump {
+    /// The asusd the dump was generated from
+    pub daemon_version: String,
+    pub api_version: u32,
+    /// Keyed by interface name
+    pub interfaces: BTreeMap<String, InterfaceDesc>,
+    pub enums: Vec<EnumDesc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InterfaceDesc {
+    #[serde(skip)]
+    pub name: String,
+    pub version: u32,
+    /// Object paths the running asusd serves the interface at, empty if it is
+    /// not running or lacks the hardware. Aura, AniMe, Slash, and SCSI paths
+    /// depend on the devices attached.
+    pub paths: Vec<String>,
+    pub methods: Vec<MethodDesc>,
+    pub properties: Vec<PropertyDesc>,
+    pub signals: Vec<SignalDesc>,
+}
+
+impl InterfaceDesc {
+    pub(crate) fn new(
+        name: &str,
+        methods: Vec<MethodDesc>,
+        properties: Vec<PropertyDesc>,
+        signals: Vec<SignalDesc>,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            version: INTERFACE_VERSIONS
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or(0, |(_, v)| *v),
+            paths: Vec::new(),
+            methods,
+            properties,
+            signals,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArgDesc {
+    pub name: String,
+    /// The DBus type signature
+    pub signature: String,
+}
+
+impl ArgDesc {
+    pub(crate) fn new(name: &str, signature: impl Display) -> Self {
+        Self {
+            name: name.to_owned(),
+            signature: signature.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MethodDesc {
+    pub name: String,
+    pub inputs: Vec<ArgDesc>,
+    pub outputs: Vec<ArgDesc>,
+}
+
+impl MethodDesc {
+    pub(crate) fn new(name: &str, inputs: Vec<ArgDesc>, outputs: Vec<ArgDesc>) -> Self {
+        Self {
+            name: name.to_owned(),
+            inputs,
+            outputs,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PropertyDesc {
+    pub name: String,
+    pub signature: String,
+    pub readable: bool,
+    pub writable: bool,
+    /// `true`, `invalidates`, `const`, or `false`
+    pub emits_changed_signal: String,
+}
+
+impl PropertyDesc {
+    pub(crate) fn new(
+        name: &str,
+        signature: impl Display,
+        writable: bool,
+        emits_changed_signal: &str,
+    ) -> Self {
+        Self {
+            name: name.to_owned(),
+            signature: signature.to_string(),
+            readable: true,
+            writable,
+            emits_changed_signal: emits_changed_signal.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SignalDesc {
+    pub name: String,
+    pub args: Vec<ArgDesc>,
+}
+
+impl SignalDesc {
+    pub(crate) fn new(name: &str, args: Vec<ArgDesc>) -> Self {
+        Self {
+            name: name.to_owned(),
+            args,
+        }
+    }
+}
+
+/// A unit enum and what each variant is sent as
+#[derive(Debug, Serialize)]
+pub struct EnumDesc {
+    /// The crate the enum is defined in
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub name: String,
+    /// `s` if sent by variant name, otherwise an integer type
+    pub signature: String,
+    pub variants: Vec<VariantDesc>,
+}
+
+impl EnumDesc {
+    fn new(krate: &str, name: &str, signature: &str, variants: Vec<VariantDesc>) -> Self {
+        Self {
+            krate: krate.to_owned(),
+            name: name.to_owned(),
+            signature: signature.to_owned(),
+            variants,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariantDesc {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+impl VariantDesc {
+    fn new(name: &str, value: impl Into<serde_json::Value>) -> Self {
+        Self {
+            name: name.to_owned(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Walk every object the running asusd exports and collect the paths of each
+/// interface
+async fn served_paths(
+    connection: &Connection,
+) -> Result<BTreeMap<String, Vec<String>>, Box<dyn Error>> {
+    let mut served: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut paths = vec!["/".to_owned()];
+    while let Some(path) = paths.pop() {
+        let xml = IntrospectableProxy::builder(connection)
+            .destination(DBUS_NAME)?
+            .path(path.as_str())?
+            .build()
+            .await?
+            .introspect()
+            .await?;
+        let node = Node::from_reader(xml.as_bytes())?;
+
+        for child in node.nodes() {
+            if let Some(name) = child.name() {
+                paths.push(format!("{}/{name}", path.trim_end_matches('/')));
+            }
+        }
+        for iface in node.interfaces() {
+            served
+                .entry(iface.name().to_string())
+                .or_default()
+                .push(path.clone());
+        }
+    }
+    Ok(served)
+}
+
+/// Describe every interface and DBus enum, with the paths the running asusd
+/// serves them at if `connection` is given and it is running
+pub async fn dump_api(connection: Option<&Connection>) -> ApiDump {
+    let mut served = match connection {
+        Some(connection) => served_paths(connection).await.unwrap_or_default(),
+        None => BTreeMap::new(),
+    };
+    let interfaces = interfaces()
+        .into_iter()
+        .map(|mut iface| {
+            iface.paths = served.remove(&iface.name).unwrap_or_default();
+            iface.paths.sort();
+            (iface.name.clone(), iface)
+        })
+        .collect();
+
+    ApiDump {
+        daemon_version: crate::VERSION.to_owned(),
+        api_version: API_VERSION,
+        interfaces,
+        enums: enums(),
+    }
+}
+
+/// Print the API in `format`, with the paths of the asusd on the system bus
+/// if it is running. Only `json` is supported.
+pub async fn print_api(format: &str) -> Result<(), Box<dyn Error>> {
+    if format != "json" {
+        return Err(format!("unsupported API dump format {format}, use json").into());
+    }
+    let connection = Connection::system().await.ok();
+    let dump = dump_api(connection.as_ref()).await;
+    println!("{}", serde_json::to_string_pretty(&dump)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::interfaces;
+    use crate::ctrl_capabilities::INTERFACE_VERSIONS;
+
+    #[test]
+    fn every_interface_dumped() {
+        let interfaces = interfaces();
+        let dumped: BTreeSet<&str> = interfaces.iter().map(|i| i.name.as_str()).collect();
+        let versioned: BTreeSet<&str> = INTERFACE_VERSIONS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(dumped, versioned);
+        assert_eq!(
+            interfaces.len(),
+            dumped.len(),
+            "an interface is dumped twice"
+        );
+        for iface in &interfaces {
+            assert!(
+                !(iface.methods.is_empty()
+                    && iface.properties.is_empty()
+                    && iface.signals.is_empty()),
+                "{} is dumped without members",
+                iface.name
+            );
+        }
+    }
+}
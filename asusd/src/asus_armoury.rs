@@ -7,12 +7,17 @@ use rog_platform::asus_armoury::{AttrValue, Attribute, FirmwareAttribute, Firmwa
 use rog_platform::platform::{PlatformProfile, RogPlatform};
 use rog_platform::power::AsusPower;
 use serde::{Deserialize, Serialize};
+use zbus::message::Header;
 use zbus::object_server::SignalEmitter;
 use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Type, Value};
 use zbus::{fdo, interface, Connection};
 
+use crate::authorization::{authorize, authorize_interactive, Action};
+use crate::boot_watchdog::{is_risky, mark_risky_applied};
 use crate::config::Config;
 use crate::error::RogError;
+use crate::hooks::{run_hooks, HookEvent};
+use crate::policy::{Lockdown, Policy};
 use crate::{Reloadable, ASUS_ZBUS_PATH};
 
 const MOD_NAME: &str = "asus_armoury";
@@ -23,7 +28,7 @@ pub struct PossibleValues {
     nums: Vec<i32>,
 }
 
-fn dbus_path_for_attr(attr_name: &str) -> OwnedObjectPath {
+pub(crate) fn dbus_path_for_attr(attr_name: &str) -> OwnedObjectPath {
     ObjectPath::from_str_unchecked(&format!("{ASUS_ZBUS_PATH}/{MOD_NAME}/{attr_name}")).into()
 }
 
@@ -62,6 +67,121 @@ impl AsusArmouryAttribute {
         Ok(())
     }
 
+    /// The value of the attribute as seen over DBus. PPT attributes give the
+    /// value stored for the active profile and power source.
+    pub async fn value(&self) -> fdo::Result<i32> {
+        if self.name().is_ppt() {
+            let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
+            let power_plugged = self
+                .power
+                .get_online()
+                .map_err(|e| {
+                    error!("Could not get power status: {e:?}");
+                    e
+                })
+                .unwrap_or_default();
+            let mut config = self.config.lock().await;
+            let tuning = config.select_tunings(power_plugged == 1, profile);
+            if let Some(tune) = tuning.group.get(&self.name()) {
+                return Ok(*tune);
+            } else if let AttrValue::Integer(i) = self.attr.default_value() {
+                return Ok(*i);
+            }
+            return Err(fdo::Error::Failed(
+                "Could not read current value".to_string(),
+            ));
+        }
+
+        if let Ok(AttrValue::Integer(i)) = self.attr.current_value() {
+            return Ok(i);
+        }
+        Err(fdo::Error::Failed(
+            "Could not read current value".to_string(),
+        ))
+    }
+
+    /// Write the attribute and store it in the config, after checking it
+    /// against the fleet policy. Callers check authorization and lockdown.
+    pub async fn write_value(&mut self, value: i32) -> fdo::Result<()> {
+        Policy::load().check_attribute(self.name(), value)?;
+        if self.name().is_ppt() {
+            let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
+            let power_plugged = self
+                .power
+                .get_online()
+                .map_err(|e| {
+                    error!("Could not get power status: {e:?}");
+                    e
+                })
+                .unwrap_or_default();
+
+            let mut config = self.config.lock().await;
+            let tuning = config.select_tunings(power_plugged == 1, profile);
+
+            if let Some(tune) = tuning.group.get_mut(&self.name()) {
+                *tune = value;
+            } else {
+                tuning.group.insert(self.name(), value);
+                debug!("Store tuning config for {} = {:?}", self.attr.name(), value);
+            }
+            if tuning.enabled {
+                self.attr
+                    .set_current_value(&AttrValue::Integer(value))
+                    .map_err(|e| {
+                        error!("Could not set value: {e:?}");
+                        e
+                    })?;
+                mark_risky_applied();
+            }
+        } else {
+            self.attr
+                .set_current_value(&AttrValue::Integer(value))
+                .map_err(|e| {
+                    error!("Could not set value: {e:?}");
+                    e
+                })?;
+            if is_risky(self.name()) {
+                mark_risky_applied();
+            }
+            if self.name().is_gpu_mode() {
+                if let Some(mode) = FirmwareAttributes::new().gpu_mode() {
+                    run_hooks(HookEvent::GpuMode {
+                        mode,
+                        reboot_pending: FirmwareAttributes::pending_reboot(),
+                    });
+                }
+            }
+
+            let has_attr = self
+                .config
+                .lock()
+                .await
+                .armoury_settings
+                .contains_key(&self.name());
+            if has_attr {
+                if let Some(setting) = self
+                    .config
+                    .lock()
+                    .await
+                    .armoury_settings
+                    .get_mut(&self.name())
+                {
+                    *setting = value
+                }
+            } else {
+                debug!("Adding config for {}", self.attr.name());
+                self.config
+                    .lock()
+                    .await
+                    .armoury_settings
+                    .insert(self.name(), value);
+                debug!("Set config for {} = {:?}", self.attr.name(), value);
+            }
+        }
+        self.config.lock().await.write();
+        Ok(())
+    }
+
     async fn watch_and_notify(
         &mut self,
         signal_ctxt: SignalEmitter<'static>,
@@ -135,6 +255,7 @@ impl crate::Reloadable for AsusArmouryAttribute {
                             e
                         })?;
                     info!("Set {} to {:?}", self.attr.name(), tune);
+                    mark_risky_applied();
                 }
             }
         }
@@ -191,8 +312,24 @@ impl AsusArmouryAttribute {
         }
     }
 
-    async fn restore_default(&self) -> fdo::Result<()> {
-        self.attr.restore_default()?;
+    async fn restore_default(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::FirmwareAttributes)?;
+        // The firmware default may be above the policy maximum
+        let default = match self.attr.default_value() {
+            AttrValue::Integer(i) => AttrValue::Integer(policy.enforce_attribute(self.name(), *i)),
+            other => other.clone(),
+        };
+        if &default == self.attr.default_value() {
+            self.attr.restore_default()?;
+        } else {
+            self.attr.set_current_value(&default)?;
+        }
         if self.name().is_ppt() {
             let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
             let power_plugged = self
@@ -207,17 +344,15 @@ impl AsusArmouryAttribute {
             let mut config = self.config.lock().await;
             let tuning = config.select_tunings(power_plugged == 1, profile);
             if let Some(tune) = tuning.group.get_mut(&self.name()) {
-                if let AttrValue::Integer(i) = self.attr.default_value() {
-                    *tune = *i;
+                if let AttrValue::Integer(i) = default {
+                    *tune = i;
                 }
             }
             if tuning.enabled {
-                self.attr
-                    .set_current_value(self.attr.default_value())
-                    .map_err(|e| {
-                        error!("Could not set value: {e:?}");
-                        e
-                    })?;
+                self.attr.set_current_value(&default).map_err(|e| {
+                    error!("Could not set value: {e:?}");
+                    e
+                })?;
             }
             config.write();
         }
@@ -258,105 +393,25 @@ impl AsusArmouryAttribute {
 
     #[zbus(property)]
     async fn current_value(&self) -> fdo::Result<i32> {
-        if self.name().is_ppt() {
-            let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
-            let power_plugged = self
-                .power
-                .get_online()
-                .map_err(|e| {
-                    error!("Could not get power status: {e:?}");
-                    e
-                })
-                .unwrap_or_default();
-            let mut config = self.config.lock().await;
-            let tuning = config.select_tunings(power_plugged == 1, profile);
-            if let Some(tune) = tuning.group.get(&self.name()) {
-                return Ok(*tune);
-            } else if let AttrValue::Integer(i) = self.attr.default_value() {
-                return Ok(*i);
-            }
-            return Err(fdo::Error::Failed(
-                "Could not read current value".to_string(),
-            ));
-        }
-
-        if let Ok(AttrValue::Integer(i)) = self.attr.current_value() {
-            return Ok(i);
-        }
-        Err(fdo::Error::Failed(
-            "Could not read current value".to_string(),
-        ))
+        self.value().await
     }
 
     #[zbus(property)]
-    async fn set_current_value(&mut self, value: i32) -> fdo::Result<()> {
-        if self.name().is_ppt() {
-            let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
-            let power_plugged = self
-                .power
-                .get_online()
-                .map_err(|e| {
-                    error!("Could not get power status: {e:?}");
-                    e
-                })
-                .unwrap_or_default();
-
-            let mut config = self.config.lock().await;
-            let tuning = config.select_tunings(power_plugged == 1, profile);
-
-            if let Some(tune) = tuning.group.get_mut(&self.name()) {
-                *tune = value;
-            } else {
-                tuning.group.insert(self.name(), value);
-                debug!("Store tuning config for {} = {:?}", self.attr.name(), value);
-            }
-            if tuning.enabled {
-                self.attr
-                    .set_current_value(&AttrValue::Integer(value))
-                    .map_err(|e| {
-                        error!("Could not set value: {e:?}");
-                        e
-                    })?;
-            }
-        } else {
-            self.attr
-                .set_current_value(&AttrValue::Integer(value))
-                .map_err(|e| {
-                    error!("Could not set value: {e:?}");
-                    e
-                })?;
-
-            let has_attr = self
-                .config
-                .lock()
-                .await
-                .armoury_settings
-                .contains_key(&self.name());
-            if has_attr {
-                if let Some(setting) = self
-                    .config
-                    .lock()
-                    .await
-                    .armoury_settings
-                    .get_mut(&self.name())
-                {
-                    *setting = value
-                }
-            } else {
-                debug!("Adding config for {}", self.attr.name());
-                self.config
-                    .lock()
-                    .await
-                    .armoury_settings
-                    .insert(self.name(), value);
-                debug!("Set config for {} = {:?}", self.attr.name(), value);
-            }
-        }
-        self.config.lock().await.write();
-        Ok(())
+    async fn set_current_value(
+        &mut self,
+        value: i32,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> fdo::Result<()> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        self.write_value(value).await
     }
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/asus_armoury.rs"));
+
 pub async fn start_attributes_zbus(
     conn: &Connection,
     platform: RogPlatform,
@@ -388,37 +443,45 @@ pub async fn set_config_or_default(
     power_plugged: bool,
     profile: PlatformProfile,
 ) {
+    let policy = Policy::load();
+    let set = |attr: &Attribute, value: &AttrValue| {
+        attr.set_current_value(value)
+            .map_err(|e| {
+                error!("Failed to set {}: {e}", attr.name());
+            })
+            .ok();
+    };
     for attr in attrs.attributes().iter() {
         let name: FirmwareAttribute = attr.name().into();
         if name.is_ppt() {
             let tuning = config.select_tunings(power_plugged, profile);
             if !tuning.enabled {
-                debug!("Tuning group is not enabled, skipping");
-                return;
+                // Values pinned by the policy apply to every profile
+                if let Some(pinned) = policy.attributes.get(&name) {
+                    set(attr, &AttrValue::Integer(*pinned));
+                } else {
+                    debug!("Tuning group is not enabled, skipping");
+                }
+                continue;
             }
 
             if let Some(tune) = tuning.group.get(&name) {
-                attr.set_current_value(&AttrValue::Integer(*tune))
-                    .map_err(|e| {
-                        error!("Failed to set {}: {e}", <&str>::from(name));
-                    })
-                    .ok();
+                set(
+                    attr,
+                    &AttrValue::Integer(policy.enforce_attribute(name, *tune)),
+                );
+                mark_risky_applied();
+            } else if let AttrValue::Integer(default) = attr.default_value() {
+                let value = policy.enforce_attribute(name, *default);
+                set(attr, &AttrValue::Integer(value));
+                tuning.group.insert(name, value);
+                info!(
+                    "Set default tuning config for {} = {:?}",
+                    <&str>::from(name),
+                    value
+                );
             } else {
-                let default = attr.default_value();
-                attr.set_current_value(default)
-                    .map_err(|e| {
-                        error!("Failed to set {}: {e}", <&str>::from(name));
-                    })
-                    .ok();
-                if let AttrValue::Integer(i) = default {
-                    tuning.group.insert(name, *i);
-                    info!(
-                        "Set default tuning config for {} = {:?}",
-                        <&str>::from(name),
-                        i
-                    );
-                    // config.write();
-                }
+                set(attr, attr.default_value());
             }
         }
     }
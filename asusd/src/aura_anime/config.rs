@@ -8,8 +8,15 @@ use rog_anime::{
 };
 use serde::{Deserialize, Serialize};
 
+use super::frame_cache::FrameCache;
+use super::pacing::MAX_FPS;
+
 const CONFIG_FILE: &str = "anime.ron";
 
+fn default_max_fps() -> u32 {
+    MAX_FPS
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct AniMeConfigCached {
     pub system: Vec<ActionData>,
@@ -19,38 +26,127 @@ pub struct AniMeConfigCached {
 }
 
 impl AniMeConfigCached {
+    /// Convert each action in `config`, reusing frames from `frames` for
+    /// files converted before
     pub fn init_from_config(
         &mut self,
         config: &AniMeConfig,
         anime_type: AnimeType,
+        frames: &FrameCache,
     ) -> Result<(), AnimeError> {
         let mut sys = Vec::with_capacity(config.system.len());
         for ani in &config.system {
-            sys.push(ActionData::from_anime_action(anime_type, ani)?);
+            sys.push(frames.load(anime_type, ani)?);
         }
         self.system = sys;
 
         let mut boot = Vec::with_capacity(config.boot.len());
         for ani in &config.boot {
-            boot.push(ActionData::from_anime_action(anime_type, ani)?);
+            boot.push(frames.load(anime_type, ani)?);
         }
         self.boot = boot;
 
         let mut wake = Vec::with_capacity(config.wake.len());
         for ani in &config.wake {
-            wake.push(ActionData::from_anime_action(anime_type, ani)?);
+            wake.push(frames.load(anime_type, ani)?);
         }
         self.wake = wake;
 
         let mut shutdown = Vec::with_capacity(config.shutdown.len());
         for ani in &config.shutdown {
-            shutdown.push(ActionData::from_anime_action(anime_type, ani)?);
+            shutdown.push(frames.load(anime_type, ani)?);
         }
         self.shutdown = shutdown;
         Ok(())
     }
 }
 
+/// Set the display brightness from the ambient light sensor, if the laptop
+/// has one
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AutoBrightness {
+    pub enabled: bool,
+    /// Pairs of a light level in lux and the brightness to use at or above it
+    pub curve: Vec<(f32, Brightness)>,
+    pub min: Brightness,
+    pub max: Brightness,
+}
+
+impl Default for AutoBrightness {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            curve: vec![
+                (0.0, Brightness::Low),
+                (50.0, Brightness::Med),
+                (1000.0, Brightness::High),
+            ],
+            min: Brightness::Low,
+            max: Brightness::High,
+        }
+    }
+}
+
+impl AutoBrightness {
+    /// The brightness for `lux` from the curve, clamped to `min` and `max`
+    pub fn level_for(&self, lux: f32) -> Brightness {
+        let level = self
+            .curve
+            .iter()
+            .filter(|(at, _)| lux >= *at)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(self.min, |(_, level)| *level);
+        let level = (level as u8).clamp(self.min as u8, (self.max as u8).max(self.min as u8));
+        Brightness::from(level)
+    }
+}
+
+/// Use one brightness in the day and another at night, such as dimming or
+/// turning the display off. Times are minutes after local midnight
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BrightnessSchedule {
+    pub enabled: bool,
+    pub day_start: u16,
+    pub night_start: u16,
+    pub day: Brightness,
+    pub night: Brightness,
+}
+
+impl Default for BrightnessSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            day_start: 7 * 60,
+            night_start: 22 * 60,
+            day: Brightness::High,
+            night: Brightness::Low,
+        }
+    }
+}
+
+impl BrightnessSchedule {
+    /// Whether `minute` after midnight falls in the day. Night may span
+    /// midnight, and if both starts are equal it is always day
+    pub fn is_day(&self, minute: u16) -> bool {
+        if self.day_start == self.night_start {
+            true
+        } else if self.day_start < self.night_start {
+            (self.day_start..self.night_start).contains(&minute)
+        } else {
+            !(self.night_start..self.day_start).contains(&minute)
+        }
+    }
+
+    /// The brightness for `minute` after local midnight
+    pub fn level_at(&self, minute: u16) -> Brightness {
+        if self.is_day(minute) {
+            self.day
+        } else {
+            self.night
+        }
+    }
+}
+
 /// Config for base system actions for the anime display
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct AniMeConfig {
@@ -68,6 +164,13 @@ pub struct AniMeConfig {
     pub off_when_suspended: bool,
     pub off_when_lid_closed: bool,
     pub brightness_on_battery: Brightness,
+    #[serde(default)]
+    pub auto_brightness: AutoBrightness,
+    #[serde(default)]
+    pub schedule: BrightnessSchedule,
+    /// Frames written faster than this are coalesced, see `FramePacer`
+    #[serde(default = "default_max_fps")]
+    pub max_fps: u32,
     pub builtin_anims: Animations,
 }
 
@@ -87,6 +190,9 @@ impl Default for AniMeConfig {
             off_when_suspended: true,
             off_when_lid_closed: true,
             brightness_on_battery: Brightness::Low,
+            auto_brightness: AutoBrightness::default(),
+            schedule: BrightnessSchedule::default(),
+            max_fps: MAX_FPS,
             builtin_anims: Animations::default(),
         }
     }
@@ -180,3 +286,53 @@ impl AniMeConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rog_anime::usb::Brightness;
+
+    use super::{AutoBrightness, BrightnessSchedule};
+
+    #[test]
+    fn auto_brightness_curve() {
+        let auto = AutoBrightness::default();
+        assert_eq!(auto.level_for(0.0), Brightness::Low);
+        assert_eq!(auto.level_for(49.9), Brightness::Low);
+        assert_eq!(auto.level_for(50.0), Brightness::Med);
+        assert_eq!(auto.level_for(20000.0), Brightness::High);
+
+        let clamped = AutoBrightness {
+            min: Brightness::Med,
+            max: Brightness::Med,
+            ..Default::default()
+        };
+        assert_eq!(clamped.level_for(0.0), Brightness::Med);
+        assert_eq!(clamped.level_for(20000.0), Brightness::Med);
+    }
+
+    #[test]
+    fn brightness_schedule() {
+        let schedule = BrightnessSchedule::default();
+        assert_eq!(schedule.level_at(6 * 60 + 59), Brightness::Low);
+        assert_eq!(schedule.level_at(7 * 60), Brightness::High);
+        assert_eq!(schedule.level_at(21 * 60 + 59), Brightness::High);
+        assert_eq!(schedule.level_at(22 * 60), Brightness::Low);
+
+        // Day across midnight, for night shifts
+        let inverted = BrightnessSchedule {
+            day_start: 20 * 60,
+            night_start: 8 * 60,
+            night: Brightness::Off,
+            ..Default::default()
+        };
+        assert_eq!(inverted.level_at(23 * 60), Brightness::High);
+        assert_eq!(inverted.level_at(60), Brightness::High);
+        assert_eq!(inverted.level_at(12 * 60), Brightness::Off);
+
+        let always_day = BrightnessSchedule {
+            night_start: 7 * 60,
+            ..Default::default()
+        };
+        assert_eq!(always_day.level_at(0), Brightness::High);
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+use rog_anime::error::AnimeError;
+use rog_anime::{ActionData, ActionLoader, AnimeType};
+
+const CACHE_DIR: &str = "/var/cache/asusd/anime/";
+/// Bump when the `ActionData` layout changes so old entries are not read
+const CACHE_VERSION: u32 = 1;
+/// The oldest entries are removed once there are more than this many
+const CACHE_MAX_ENTRIES: usize = 64;
+
+/// Converted animations stored on disk, keyed by a hash of the image file and
+/// the transform used, so a gif is only scaled and rotated once.
+///
+/// The hash is not stable between builds of asusd, which only means entries
+/// from an older build are converted again.
+#[derive(Debug, Clone)]
+pub struct FrameCache {
+    dir: PathBuf,
+}
+
+impl Default for FrameCache {
+    fn default() -> Self {
+        Self::new(PathBuf::from(CACHE_DIR))
+    }
+}
+
+impl FrameCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The cache key for `action`, or `None` if it has no file to convert
+    pub fn key(anime_type: AnimeType, action: &ActionLoader) -> Result<Option<String>, AnimeError> {
+        let (file, params) = match action {
+            ActionLoader::AsusAnimation {
+                file,
+                time,
+                brightness,
+            }
+            | ActionLoader::AsusImage {
+                file,
+                time,
+                brightness,
+            } => (file, format!("{time:?}{brightness}")),
+            ActionLoader::ImageAnimation {
+                file,
+                scale,
+                angle,
+                translation,
+                time,
+                brightness,
+            }
+            | ActionLoader::Image {
+                file,
+                scale,
+                angle,
+                translation,
+                time,
+                brightness,
+            } => (
+                file,
+                format!("{scale}{angle}{translation:?}{time:?}{brightness}"),
+            ),
+            ActionLoader::Pause(_) => return Ok(None),
+        };
+        let mut hasher = DefaultHasher::new();
+        CACHE_VERSION.hash(&mut hasher);
+        fs::read(file)?.hash(&mut hasher);
+        // The variant decides how the file is converted
+        std::mem::discriminant(action).hash(&mut hasher);
+        params.hash(&mut hasher);
+        format!("{anime_type:?}").hash(&mut hasher);
+        Ok(Some(format!("{:016x}", hasher.finish())))
+    }
+
+    /// Convert `action`, using the stored frames if it was converted before
+    pub fn load(
+        &self,
+        anime_type: AnimeType,
+        action: &ActionLoader,
+    ) -> Result<ActionData, AnimeError> {
+        let Some(key) = Self::key(anime_type, action)? else {
+            return ActionData::from_anime_action(anime_type, action);
+        };
+        let path = self.dir.join(format!("{key}.ron"));
+        if let Some(data) = Self::read(&path) {
+            debug!("AniMe frames for {key} read from cache");
+            return Ok(data);
+        }
+
+        let data = ActionData::from_anime_action(anime_type, action)?;
+        if let Err(e) = self.store(&path, &data) {
+            warn!("Could not cache AniMe frames at {path:?}: {e}");
+        }
+        Ok(data)
+    }
+
+    fn read(path: &Path) -> Option<ActionData> {
+        let text = fs::read_to_string(path).ok()?;
+        ron::from_str(&text)
+            .map_err(|e| warn!("Ignoring bad AniMe cache entry {path:?}: {e}"))
+            .ok()
+    }
+
+    fn store(&self, path: &Path, data: &ActionData) -> std::io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let text = ron::to_string(data).map_err(std::io::Error::other)?;
+        fs::write(path, text)?;
+        self.prune(CACHE_MAX_ENTRIES);
+        Ok(())
+    }
+
+    /// Remove the least recently written entries until at most `max` remain
+    fn prune(&self, max: usize) {
+        let mut entries = self.entries();
+        if entries.len() <= max {
+            return;
+        }
+        entries.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+        for path in &entries[..entries.len() - max] {
+            fs::remove_file(path)
+                .map_err(|e| warn!("Could not remove AniMe cache entry {path:?}: {e}"))
+                .ok();
+        }
+    }
+
+    fn entries(&self) -> Vec<PathBuf> {
+        fs::read_dir(&self.dir)
+            .map(|dir| {
+                dir.filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Remove every entry, returning how many were removed
+    pub fn clear(&self) -> usize {
+        self.entries()
+            .iter()
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use rog_anime::{ActionLoader, AnimTime, AnimeType};
+
+    use super::FrameCache;
+
+    fn gif_action(file: PathBuf, scale: f32) -> ActionLoader {
+        ActionLoader::ImageAnimation {
+            file,
+            scale,
+            angle: 0.0,
+            translation: Default::default(),
+            time: AnimTime::Infinite,
+            brightness: 1.0,
+        }
+    }
+
+    #[test]
+    fn key_follows_file_and_transform() {
+        let dir = std::env::temp_dir().join("asusd-frame-cache-key");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("image.gif");
+        std::fs::write(&file, b"one").unwrap();
+
+        let key = |scale| FrameCache::key(AnimeType::GA402, &gif_action(file.clone(), scale));
+        let first = key(1.0).unwrap().unwrap();
+        assert_eq!(first, key(1.0).unwrap().unwrap());
+        assert_ne!(first, key(0.5).unwrap().unwrap());
+        assert_ne!(
+            first,
+            FrameCache::key(AnimeType::GA401, &gif_action(file.clone(), 1.0))
+                .unwrap()
+                .unwrap()
+        );
+
+        std::fs::write(&file, b"two").unwrap();
+        assert_ne!(first, key(1.0).unwrap().unwrap());
+
+        let pause = ActionLoader::Pause(Default::default());
+        assert!(FrameCache::key(AnimeType::GA402, &pause).unwrap().is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn prune_and_clear() {
+        let dir = std::env::temp_dir().join("asusd-frame-cache-prune");
+        std::fs::create_dir_all(&dir).unwrap();
+        for n in 0..4 {
+            std::fs::write(dir.join(format!("{n}.ron")), "Pause((secs:0,nanos:0))").unwrap();
+        }
+        let cache = FrameCache::new(dir.clone());
+        cache.prune(2);
+        assert_eq!(cache.entries().len(), 2);
+        assert_eq!(cache.clear(), 2);
+        assert!(cache.entries().is_empty());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,36 +1,133 @@
 pub mod config;
+pub mod frame_cache;
+pub mod pacing;
 /// Implements `CtrlTask`, Reloadable, `ZbusRun`
 pub mod trait_impls;
 
 use std::convert::TryFrom;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
 use std::thread::sleep;
+use std::time::{Duration, Instant};
 
 use config_traits::StdConfig;
 use futures_util::lock::Mutex;
 use log::{debug, error, info, warn};
 use rog_anime::usb::{
-    pkt_flush, pkt_set_brightness, pkt_set_enable_display, pkt_set_enable_powersave_anim,
-    pkts_for_init, Brightness,
+    pkt_flush, pkt_set_brightness, pkt_set_builtin_animations, pkt_set_enable_display,
+    pkt_set_enable_powersave_anim, pkts_for_init, Brightness,
 };
-use rog_anime::{ActionData, AnimeDataBuffer, AnimePacketType};
+use rog_anime::{ActionData, Animations, AnimeDataBuffer, AnimePacketType};
 use rog_platform::hid_raw::HidRaw;
 use rog_platform::usb_raw::USBRaw;
+use tokio::sync::watch;
 
 use self::config::{AniMeConfig, AniMeConfigCached};
+use self::frame_cache::FrameCache;
+use self::pacing::{FramePacer, Pace, MAX_FPS};
 use crate::error::RogError;
+use crate::frame_stream::FrameStream;
 
 #[derive(Debug, Clone)]
 pub struct AniMe {
     hid: Option<Arc<Mutex<HidRaw>>>,
     usb: Option<Arc<Mutex<USBRaw>>>,
     config: Arc<Mutex<AniMeConfig>>,
-    cache: AniMeConfigCached,
+    cache: Arc<Mutex<AniMeConfigCached>>,
+    // Converted gifs and images, kept between restarts
+    frame_cache: FrameCache,
     // set to force thread to exit
     thread_exit: Arc<AtomicBool>,
     // Set to false when the thread exits
     thread_running: Arc<AtomicBool>,
+    // Blips being shown. Frames are not written while this is above zero
+    blips: Arc<AtomicUsize>,
+    // The last frame given to `write_data_buffer`, restored after a blip
+    last_frame: Arc<Mutex<Option<AnimeDataBuffer>>>,
+    // Frames written are copied to `frames` while this has a client
+    stream: FrameStream,
+    frames: Arc<watch::Sender<Vec<u8>>>,
+    // The brightness from the ambient light sensor while auto brightness is on
+    ambient_brightness: Arc<Mutex<Option<Brightness>>>,
+    // Sent when the auto brightness config changes so the sensor task can
+    // wait for it while auto brightness is off
+    auto_brightness_changed: Arc<watch::Sender<()>>,
+    // Set to hold the brightness until the schedule next changes between day
+    // and night
+    schedule_override: Arc<AtomicBool>,
+    // Keeps frame writes under `max_fps`
+    pacer: Arc<Mutex<FramePacer<AnimeDataBuffer>>>,
+}
+
+/// Held by the brightness tasks in place of [`AniMe`] so they end once the
+/// device is removed
+#[derive(Clone)]
+pub(super) struct WeakAniMe {
+    hid: Option<Weak<Mutex<HidRaw>>>,
+    usb: Option<Weak<Mutex<USBRaw>>>,
+    config: Weak<Mutex<AniMeConfig>>,
+    cache: Weak<Mutex<AniMeConfigCached>>,
+    frame_cache: FrameCache,
+    thread_exit: Weak<AtomicBool>,
+    thread_running: Weak<AtomicBool>,
+    blips: Weak<AtomicUsize>,
+    last_frame: Weak<Mutex<Option<AnimeDataBuffer>>>,
+    stream: FrameStream,
+    frames: Weak<watch::Sender<Vec<u8>>>,
+    ambient_brightness: Weak<Mutex<Option<Brightness>>>,
+    auto_brightness_changed: Weak<watch::Sender<()>>,
+    schedule_override: Weak<AtomicBool>,
+    pacer: Weak<Mutex<FramePacer<AnimeDataBuffer>>>,
+}
+
+impl WeakAniMe {
+    pub(super) fn new(anime: &AniMe) -> Self {
+        Self {
+            hid: anime.hid.as_ref().map(Arc::downgrade),
+            usb: anime.usb.as_ref().map(Arc::downgrade),
+            config: Arc::downgrade(&anime.config),
+            cache: Arc::downgrade(&anime.cache),
+            frame_cache: anime.frame_cache.clone(),
+            thread_exit: Arc::downgrade(&anime.thread_exit),
+            thread_running: Arc::downgrade(&anime.thread_running),
+            blips: Arc::downgrade(&anime.blips),
+            last_frame: Arc::downgrade(&anime.last_frame),
+            stream: anime.stream.clone(),
+            frames: Arc::downgrade(&anime.frames),
+            ambient_brightness: Arc::downgrade(&anime.ambient_brightness),
+            auto_brightness_changed: Arc::downgrade(&anime.auto_brightness_changed),
+            schedule_override: Arc::downgrade(&anime.schedule_override),
+            pacer: Arc::downgrade(&anime.pacer),
+        }
+    }
+
+    pub(super) fn upgrade(&self) -> Option<AniMe> {
+        let hid = match &self.hid {
+            Some(hid) => Some(hid.upgrade()?),
+            None => None,
+        };
+        let usb = match &self.usb {
+            Some(usb) => Some(usb.upgrade()?),
+            None => None,
+        };
+        Some(AniMe {
+            hid,
+            usb,
+            config: self.config.upgrade()?,
+            cache: self.cache.upgrade()?,
+            frame_cache: self.frame_cache.clone(),
+            thread_exit: self.thread_exit.upgrade()?,
+            thread_running: self.thread_running.upgrade()?,
+            blips: self.blips.upgrade()?,
+            last_frame: self.last_frame.upgrade()?,
+            stream: self.stream.clone(),
+            frames: self.frames.upgrade()?,
+            ambient_brightness: self.ambient_brightness.upgrade()?,
+            auto_brightness_changed: self.auto_brightness_changed.upgrade()?,
+            schedule_override: self.schedule_override.upgrade()?,
+            pacer: self.pacer.upgrade()?,
+        })
+    }
 }
 
 impl AniMe {
@@ -43,16 +140,79 @@ impl AniMe {
             hid,
             usb,
             config,
-            cache: AniMeConfigCached::default(),
+            cache: Arc::default(),
+            frame_cache: FrameCache::default(),
             thread_exit: Arc::new(AtomicBool::new(false)),
             thread_running: Arc::new(AtomicBool::new(false)),
+            blips: Arc::new(AtomicUsize::new(0)),
+            last_frame: Arc::new(Mutex::new(None)),
+            stream: FrameStream::default(),
+            frames: Arc::new(watch::Sender::new(Vec::new())),
+            ambient_brightness: Arc::new(Mutex::new(None)),
+            auto_brightness_changed: Arc::new(watch::Sender::new(())),
+            schedule_override: Arc::new(AtomicBool::new(false)),
+            pacer: Arc::new(Mutex::new(FramePacer::new(MAX_FPS))),
+        }
+    }
+
+    /// The latest frame written to the display, in the `AnimeDataBuffer`
+    /// layout, while streaming is enabled
+    pub fn subscribe_frames(&self) -> watch::Receiver<Vec<u8>> {
+        self.frames.subscribe()
+    }
+
+    /// The brightness to set on the display, from the ambient light sensor if
+    /// auto brightness is on, then from the schedule unless it is overridden,
+    /// otherwise `display_brightness`
+    pub async fn base_brightness(&self, config: &AniMeConfig) -> Brightness {
+        if config.auto_brightness.enabled {
+            if let Some(brightness) = *self.ambient_brightness.lock().await {
+                return brightness;
+            }
         }
+        if config.schedule.enabled && !self.schedule_override() {
+            return config
+                .schedule
+                .level_at(crate::local_time::minutes_since_midnight());
+        }
+        config.display_brightness
+    }
+
+    /// Write a brightness level, turning the display off for `Brightness::Off`
+    pub async fn write_brightness(&self, level: Brightness) -> Result<(), RogError> {
+        self.write_bytes(&pkt_set_brightness(level)).await?;
+        self.write_bytes(&pkt_set_enable_display(level != Brightness::Off))
+            .await
+    }
+
+    /// Hold the current brightness until the schedule next changes between
+    /// day and night
+    pub fn set_schedule_override(&self, enabled: bool) {
+        self.schedule_override.store(enabled, Ordering::Release);
+    }
+
+    pub fn schedule_override(&self) -> bool {
+        self.schedule_override.load(Ordering::Acquire)
+    }
+
+    /// Frames written to the display in the last second
+    pub async fn effective_fps(&self) -> u32 {
+        self.pacer.lock().await.fps(Instant::now())
+    }
+
+    pub async fn set_max_fps(&self, max_fps: u32) {
+        self.pacer.lock().await.set_max_fps(max_fps);
     }
 
     /// Will fail if something is already holding the config lock
     async fn do_init_cache(&mut self) {
         if let Some(mut config) = self.config.try_lock() {
-            if let Err(e) = self.cache.init_from_config(&config, config.anime_type) {
+            if let Err(e) = self
+                .cache
+                .lock()
+                .await
+                .init_from_config(&config, config.anime_type, &self.frame_cache)
+            {
                 error!(
                     "Trying to cache the Anime Config failed, will reset to default config: {e:?}"
                 );
@@ -87,8 +247,38 @@ impl AniMe {
     }
 
     /// Write only a data packet. This will modify the leds brightness using the
-    /// global brightness set in config.
-    async fn write_data_buffer(&self, mut buffer: AnimeDataBuffer) -> Result<(), RogError> {
+    /// global brightness set in config. The frame is held back while a blip is
+    /// shown, and frames written faster than `max_fps` are coalesced so only
+    /// the latest is written at the next slot.
+    async fn write_data_buffer(&self, buffer: AnimeDataBuffer) -> Result<(), RogError> {
+        *self.last_frame.lock().await = Some(buffer.clone());
+        if self.blips.load(Ordering::Acquire) > 0 {
+            return Ok(());
+        }
+        let pace = self.pacer.lock().await.offer(Instant::now(), buffer);
+        match pace {
+            Pace::Write(buffer) => self.write_frame(buffer).await,
+            Pace::Wait(wait) => {
+                let inner = self.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(wait).await;
+                    let frame = inner.pacer.lock().await.take_pending(Instant::now());
+                    if let Some(frame) = frame.filter(|_| inner.blips.load(Ordering::Acquire) == 0)
+                    {
+                        inner
+                            .write_frame(frame)
+                            .await
+                            .map_err(|e| warn!("AniMe paced frame: {e}"))
+                            .ok();
+                    }
+                });
+                Ok(())
+            }
+            Pace::Coalesced => Ok(()),
+        }
+    }
+
+    async fn write_frame(&self, mut buffer: AnimeDataBuffer) -> Result<(), RogError> {
         for led in buffer.data_mut().iter_mut() {
             let mut bright = *led as f32;
             if bright > 254.0 {
@@ -96,6 +286,9 @@ impl AniMe {
             }
             *led = bright as u8;
         }
+        if self.stream.enabled() {
+            self.frames.send_replace(buffer.data().to_vec());
+        }
         let data = AnimePacketType::try_from(buffer)?;
         for row in &data {
             self.write_bytes(row).await?;
@@ -103,6 +296,41 @@ impl AniMe {
         self.write_bytes(&pkt_flush()).await
     }
 
+    /// Show `blip` over the current content for `duration`, then put the
+    /// content back. Blips which overlap replace each other, and the content
+    /// is restored after the last one.
+    pub async fn show_blip(&self, blip: AnimeDataBuffer, duration: Duration) {
+        let (builtins, bright) = {
+            let config = self.config.lock().await;
+            (
+                config.builtin_anims_enabled,
+                self.base_brightness(&config).await,
+            )
+        };
+        if self.blips.fetch_add(1, Ordering::AcqRel) == 0 && builtins {
+            self.set_builtins_enabled(false, bright)
+                .await
+                .map_err(|e| warn!("AniMe blip: {e}"))
+                .ok();
+        }
+        self.write_frame(blip)
+            .await
+            .map_err(|e| warn!("AniMe blip: {e}"))
+            .ok();
+        tokio::time::sleep(duration).await;
+        if self.blips.fetch_sub(1, Ordering::AcqRel) > 1 {
+            return;
+        }
+        let restored = if builtins {
+            self.set_builtins_enabled(true, bright).await
+        } else if let Some(frame) = self.last_frame.lock().await.clone() {
+            self.write_frame(frame).await
+        } else {
+            Ok(())
+        };
+        restored.map_err(|e| warn!("AniMe blip: {e}")).ok();
+    }
+
     pub async fn set_builtins_enabled(
         &self,
         enabled: bool,
@@ -116,6 +344,25 @@ impl AniMe {
             .await
     }
 
+    pub async fn builtin_animations(&self) -> Animations {
+        self.config.lock().await.builtin_anims
+    }
+
+    /// Set which builtin animation is used for each stage and save it
+    pub async fn set_builtin_animations(&self, settings: Animations) -> Result<(), RogError> {
+        self.write_bytes(&pkt_set_builtin_animations(
+            settings.boot, settings.awake, settings.sleep, settings.shutdown,
+        ))
+        .await?;
+        self.write_bytes(&pkt_set_enable_powersave_anim(true))
+            .await?;
+        let mut config = self.config.lock().await;
+        config.display_enabled = true;
+        config.builtin_anims = settings;
+        config.write();
+        Ok(())
+    }
+
     /// Start an action thread. This is classed as a singleton and there should
     /// be only one running - so the thread uses atomics to signal run/exit.
     ///
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// The fastest frames are written, and the default `max_fps` in the AniMe
+/// config. Each frame is two or three USB packets and a flush, the display
+/// shows nothing new faster than this and the extra frames only fill the bus
+pub const MAX_FPS: u32 = 60;
+
+/// What to do with a frame offered to [`FramePacer::offer`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Pace<T> {
+    /// Write it now
+    Write(T),
+    /// It is held until the next slot, write [`FramePacer::take_pending`] after
+    /// this long
+    Wait(Duration),
+    /// It replaced a frame already held for the next slot
+    Coalesced,
+}
+
+/// Keeps frame writes at or under `max_fps`. A frame offered too soon after
+/// the last write is held and written at the next slot, and frames offered
+/// while one is held replace it, so a client writing too fast sees its latest
+/// frame with the ones between dropped.
+#[derive(Debug)]
+pub struct FramePacer<T> {
+    interval: Duration,
+    last_write: Option<Instant>,
+    pending: Option<T>,
+    /// When each frame in the last second was written
+    written: VecDeque<Instant>,
+}
+
+impl<T> FramePacer<T> {
+    pub fn new(max_fps: u32) -> Self {
+        Self {
+            interval: Self::interval_for(max_fps),
+            last_write: None,
+            pending: None,
+            written: VecDeque::new(),
+        }
+    }
+
+    fn interval_for(max_fps: u32) -> Duration {
+        Duration::from_secs(1) / max_fps.clamp(1, MAX_FPS)
+    }
+
+    pub fn set_max_fps(&mut self, max_fps: u32) {
+        self.interval = Self::interval_for(max_fps);
+    }
+
+    fn record_write(&mut self, now: Instant) {
+        self.last_write = Some(now);
+        self.written.push_back(now);
+        self.fps(now);
+    }
+
+    /// Offer `frame` for writing at `now`. The frame is kept by the pacer
+    /// unless it is given back in [`Pace::Write`]
+    pub fn offer(&mut self, now: Instant, frame: T) -> Pace<T> {
+        match self.last_write.map(|last| last + self.interval) {
+            Some(next) if next > now => {
+                if self.pending.replace(frame).is_some() {
+                    Pace::Coalesced
+                } else {
+                    Pace::Wait(next - now)
+                }
+            }
+            _ => {
+                self.record_write(now);
+                Pace::Write(frame)
+            }
+        }
+    }
+
+    /// The frame held for this slot, if it wasn't taken already
+    pub fn take_pending(&mut self, now: Instant) -> Option<T> {
+        let frame = self.pending.take()?;
+        self.record_write(now);
+        Some(frame)
+    }
+
+    /// Frames written in the second up to `now`
+    pub fn fps(&mut self, now: Instant) -> u32 {
+        while self
+            .written
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(1))
+        {
+            self.written.pop_front();
+        }
+        self.written.len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{FramePacer, Pace};
+
+    #[test]
+    fn coalesce_fast_writes() {
+        let start = Instant::now();
+        let ms = |ms| start + Duration::from_millis(ms);
+        let mut pacer = FramePacer::new(20);
+
+        assert_eq!(pacer.offer(ms(0), 1), Pace::Write(1));
+        assert_eq!(
+            pacer.offer(ms(10), 2),
+            Pace::Wait(Duration::from_millis(40))
+        );
+        assert_eq!(pacer.offer(ms(20), 3), Pace::Coalesced);
+        assert_eq!(pacer.take_pending(ms(50)), Some(3));
+        assert_eq!(pacer.take_pending(ms(50)), None);
+        assert_eq!(pacer.offer(ms(100), 4), Pace::Write(4));
+        assert_eq!(pacer.fps(ms(100)), 3);
+        assert_eq!(pacer.fps(ms(1060)), 1);
+    }
+
+    #[test]
+    fn max_fps_is_clamped() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(0);
+        pacer.offer(start, ());
+        assert_eq!(
+            pacer.offer(start + Duration::from_millis(500), ()),
+            Pace::Wait(Duration::from_millis(500))
+        );
+
+        pacer.set_max_fps(1000);
+        pacer.take_pending(start + Duration::from_secs(1));
+        assert!(matches!(
+            pacer.offer(start + Duration::from_millis(1010), ()),
+            Pace::Wait(_)
+        ));
+    }
+}
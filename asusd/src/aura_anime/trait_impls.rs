@@ -1,4 +1,5 @@
 use std::sync::atomic::Ordering;
+use std::time::Duration;
 
 use config_traits::StdConfig;
 use log::{debug, error, warn};
@@ -7,15 +8,27 @@ use rog_anime::usb::{
     pkt_set_brightness, pkt_set_builtin_animations, pkt_set_enable_display,
     pkt_set_enable_powersave_anim, Brightness,
 };
-use rog_anime::{Animations, AnimeDataBuffer, DeviceState};
+use rog_anime::{
+    blip_buffer, blip_icons, ActionLoader, AnimTime, Animations, AnimeDataBuffer, DeviceState,
+    Vec2, BLIP_MAX_MS,
+};
+use rog_platform::ambient_light::AmbientLight;
+use tokio::sync::watch;
+use zbus::message::Header;
 use zbus::object_server::SignalEmitter;
 use zbus::proxy::CacheProperties;
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
-use super::config::AniMeConfig;
-use super::AniMe;
+use super::config::{AniMeConfig, AutoBrightness, BrightnessSchedule};
+use super::pacing::MAX_FPS;
+use super::{AniMe, WeakAniMe};
+use crate::authorization::{authorize_interactive, Action};
 use crate::error::RogError;
+use crate::frame_stream::FrameStream;
+use crate::local_time::minutes_since_midnight;
+use crate::sleep_hooks::{HookOrder, SleepHooks};
+use crate::supervisor::Supervisor;
 use crate::Reloadable;
 
 async fn get_logind_manager<'a>() -> ManagerProxy<'a> {
@@ -42,11 +55,25 @@ impl AniMeZbus {
         mut self,
         connection: &Connection,
         path: OwnedObjectPath,
+        sleep_hooks: &SleepHooks,
+        supervisor: &Supervisor,
     ) -> Result<(), RogError> {
         // let task = zbus.clone();
         self.reload()
             .await
             .unwrap_or_else(|err| warn!("Controller error: {}", err));
+        self.register_sleep_hooks(sleep_hooks, path.as_str()).await;
+        let signal_ctxt = SignalEmitter::new(connection, path.clone())?.into_owned();
+        let name = |task: &str| format!("AniMe {}: {task}", path.as_str());
+        Self::start_frame_signal(
+            self.0.subscribe_frames(),
+            self.0.stream.clone(),
+            signal_ctxt,
+            supervisor,
+            &name("frame signal"),
+        );
+        Self::start_auto_brightness(&self.0, supervisor, &name("auto brightness"));
+        Self::start_brightness_schedule(&self.0, supervisor, &name("brightness schedule"));
         connection
             .object_server()
             .at(path.clone(), self)
@@ -60,6 +87,237 @@ impl AniMeZbus {
     }
 }
 
+impl AniMeZbus {
+    /// Send each streamed frame in the `Frame` signal to the streaming client
+    /// only, skipping any written within `FRAME_SIGNAL_INTERVAL` of the last
+    /// one sent. Ends when the device is removed.
+    fn start_frame_signal(
+        frames: watch::Receiver<Vec<u8>>,
+        stream: FrameStream,
+        signal_ctxt: SignalEmitter<'static>,
+        supervisor: &Supervisor,
+        name: &str,
+    ) {
+        supervisor.spawn(name, move || {
+            let mut frames = frames.clone();
+            let stream = stream.clone();
+            let signal_ctxt = signal_ctxt.clone();
+            async move {
+                while frames.changed().await.is_ok() {
+                    let frame = frames.borrow_and_update().clone();
+                    let Some(signal_ctxt) = stream.emitter(&signal_ctxt) else {
+                        continue;
+                    };
+                    Self::frame(&signal_ctxt, frame)
+                        .await
+                        .map_err(|e| warn!("AniMe frame signal: {e}"))
+                        .ok();
+                    tokio::time::sleep(FRAME_SIGNAL_INTERVAL).await;
+                }
+                Ok::<(), RogError>(())
+            }
+        });
+    }
+
+    /// Set the display brightness from the ambient light sensor while auto
+    /// brightness is on. A new level is used once it is read twice in a row so
+    /// the display doesn't flicker when the light is near a step in the curve.
+    /// While it is off the task waits for a change to the setting without a
+    /// timer, and it ends when the device is removed.
+    fn start_auto_brightness(inner: &AniMe, supervisor: &Supervisor, name: &str) {
+        let sensor = match AmbientLight::new() {
+            Ok(sensor) => sensor,
+            Err(e) => {
+                debug!("AniMe auto brightness unavailable: {e}");
+                return;
+            }
+        };
+        let weak = WeakAniMe::new(inner);
+        let changed = inner.auto_brightness_changed.subscribe();
+        supervisor.spawn(name, move || {
+            let weak = weak.clone();
+            let mut changed = changed.clone();
+            let sensor = sensor.clone();
+            async move {
+                let mut pending = None;
+                while let Some(inner) = weak.upgrade() {
+                    let enabled = inner.config.lock().await.auto_brightness.enabled;
+                    drop(inner);
+                    if !enabled {
+                        pending = None;
+                        if changed.changed().await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    tokio::select! {
+                        result = changed.changed() => {
+                            if result.is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+                        _ = tokio::time::sleep(AMBIENT_LIGHT_INTERVAL) => {}
+                    }
+
+                    let Some(inner) = weak.upgrade() else {
+                        break;
+                    };
+                    let (auto, display_enabled) = {
+                        let config = inner.config.lock().await;
+                        (config.auto_brightness.clone(), config.display_enabled)
+                    };
+                    if !auto.enabled {
+                        continue;
+                    }
+                    let lux = match sensor.illuminance() {
+                        Ok(lux) => lux,
+                        Err(e) => {
+                            debug!("AniMe auto brightness: {e}");
+                            continue;
+                        }
+                    };
+                    let level = auto.level_for(lux);
+                    if pending.replace(level) != Some(level) {
+                        continue;
+                    }
+                    {
+                        let mut current = inner.ambient_brightness.lock().await;
+                        if *current == Some(level) {
+                            continue;
+                        }
+                        *current = Some(level);
+                    }
+                    debug!("Ambient light is {lux} lux, setting AniMe brightness to {level:?}");
+                    if display_enabled {
+                        inner
+                            .write_bytes(&pkt_set_brightness(level))
+                            .await
+                            .map_err(|err| warn!("ctrl_anime::auto_brightness {}", err))
+                            .ok();
+                    }
+                }
+                debug!("AniMe device removed, ending the auto brightness task");
+                Ok::<(), RogError>(())
+            }
+        });
+    }
+
+    /// Set the day or night brightness from the schedule each time it changes
+    /// between the two, which also ends an override. Auto brightness takes
+    /// precedence while it is on. Ends when the device is removed.
+    fn start_brightness_schedule(inner: &AniMe, supervisor: &Supervisor, name: &str) {
+        let weak = WeakAniMe::new(inner);
+        supervisor.spawn(name, move || {
+            let weak = weak.clone();
+            async move {
+                let mut last_is_day = None;
+                while let Some(inner) = weak.upgrade() {
+                    let (schedule, auto, display_enabled) = {
+                        let config = inner.config.lock().await;
+                        (
+                            config.schedule.clone(),
+                            config.auto_brightness.enabled,
+                            config.display_enabled,
+                        )
+                    };
+                    if !schedule.enabled {
+                        last_is_day = None;
+                    } else {
+                        let is_day = schedule.is_day(minutes_since_midnight());
+                        let previous = last_is_day.replace(is_day);
+                        if previous != Some(is_day) {
+                            if previous.is_some() {
+                                inner.set_schedule_override(false);
+                            }
+                            if display_enabled && !auto && !inner.schedule_override() {
+                                let level = if is_day { schedule.day } else { schedule.night };
+                                debug!("AniMe schedule changed, setting brightness to {level:?}");
+                                inner
+                                    .write_brightness(level)
+                                    .await
+                                    .map_err(|err| warn!("ctrl_anime::brightness_schedule {}", err))
+                                    .ok();
+                            }
+                        }
+                    }
+                    drop(inner);
+                    tokio::time::sleep(SCHEDULE_INTERVAL).await;
+                }
+                debug!("AniMe device removed, ending the brightness schedule task");
+                Ok::<(), RogError>(())
+            }
+        });
+    }
+
+    /// Turn the display off before sleep if `off_when_suspended`, and back on
+    /// with the wake animation after. `owner` should be the dbus path so the
+    /// hooks can be removed with the device.
+    async fn register_sleep_hooks(&self, sleep_hooks: &SleepHooks, owner: &str) {
+        let inner = self.0.clone();
+        sleep_hooks
+            .on_pre_sleep(owner, HookOrder::Devices, move || {
+                let inner = inner.clone();
+                async move { Self::on_sleep_change(&inner, true).await }
+            })
+            .await;
+        let inner = self.0.clone();
+        sleep_hooks
+            .on_post_resume(owner, HookOrder::Devices, move || {
+                let inner = inner.clone();
+                async move { Self::on_sleep_change(&inner, false).await }
+            })
+            .await;
+    }
+
+    async fn on_sleep_change(inner: &AniMe, sleeping: bool) {
+        let config = inner.config.lock().await.clone();
+        if config.display_enabled {
+            inner.thread_exit.store(true, Ordering::Release); // ensure clean slate
+
+            inner
+                .write_bytes(&pkt_set_enable_display(
+                    !(sleeping && config.off_when_suspended),
+                ))
+                .await
+                .map_err(|err| {
+                    warn!("sleep_hooks::off_when_suspended {}", err);
+                })
+                .ok();
+
+            if config.builtin_anims_enabled {
+                inner
+                    .write_bytes(&pkt_set_enable_powersave_anim(
+                        !(sleeping && config.off_when_suspended),
+                    ))
+                    .await
+                    .map_err(|err| {
+                        warn!("sleep_hooks::off_when_suspended {}", err);
+                    })
+                    .ok();
+            } else if !sleeping && !config.builtin_anims_enabled {
+                // Run custom wake animation
+                inner
+                    .write_bytes(&pkt_set_enable_powersave_anim(false))
+                    .await
+                    .ok(); // ensure builtins are disabled
+
+                let wake = inner.cache.lock().await.wake.clone();
+                inner.run_thread(wake, true).await;
+            }
+        }
+    }
+}
+
+/// How often the ambient light sensor is read for auto brightness
+const AMBIENT_LIGHT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the time is checked for the brightness schedule
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The most `Frame` signals sent a second is the inverse of this
+const FRAME_SIGNAL_INTERVAL: Duration = Duration::from_millis(33);
+
 // None of these calls can be guarnateed to succeed unless we loop until okay
 // If the try_lock *does* succeed then any other thread trying to lock will not
 // grab it until we finish.
@@ -67,8 +325,17 @@ impl AniMeZbus {
 impl AniMeZbus {
     /// Writes a data stream of length. Will force system thread to exit until
     /// it is restarted
-    async fn write(&self, input: AnimeDataBuffer) -> zbus::fdo::Result<()> {
-        let bright = self.0.config.lock().await.display_brightness;
+    async fn write(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        input: AnimeDataBuffer,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        let bright = {
+            let config = self.0.config.lock().await;
+            self.0.base_brightness(&config).await
+        };
         if self.0.config.lock().await.builtin_anims_enabled {
             // This clears the display, causing flickers if done indiscriminately on every
             // write. Therefore, we guard it behind a config check.
@@ -82,6 +349,161 @@ impl AniMeZbus {
         Ok(())
     }
 
+    /// While set each frame written to the display is also sent in the
+    /// `Frame` signal, for previewing effects. Only root or the user of the
+    /// foreground session may set it, and it is cleared when the client which
+    /// set it leaves the bus. Not saved
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn stream_frames(&self) -> bool {
+        self.0.stream.enabled()
+    }
+
+    #[zbus(property)]
+    async fn set_stream_frames(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        self.0.stream.set_enabled(conn, &header, enabled).await
+    }
+
+    /// The most frames a second written to the display. Frames from `Write`
+    /// and animations above this are coalesced, writing only the latest at
+    /// the next slot. Capped at 60
+    #[zbus(property)]
+    async fn max_fps(&self) -> u32 {
+        self.0.config.lock().await.max_fps
+    }
+
+    #[zbus(property)]
+    async fn set_max_fps(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        max_fps: u32,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        if !(1..=MAX_FPS).contains(&max_fps) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "max_fps must be from 1 to {MAX_FPS}"
+            )));
+        }
+        let mut config = self.0.config.lock().await;
+        config.max_fps = max_fps;
+        config.write();
+        self.0.set_max_fps(max_fps).await;
+        Ok(())
+    }
+
+    /// Frames written to the display in the last second, after coalescing
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn effective_fps(&self) -> u32 {
+        self.0.effective_fps().await
+    }
+
+    /// A frame written to the display, in the same layout as `Write` takes.
+    /// Only sent while `StreamFrames` is set
+    #[zbus(signal)]
+    async fn frame(signal_ctxt: &SignalEmitter<'_>, data: Vec<u8>) -> zbus::Result<()>;
+
+    /// Briefly show an icon from `BlipIcons`, such as `mail` or `battery-low`,
+    /// or a short text over the current content, then resume it. `duration_ms`
+    /// is capped at 10 seconds. Does nothing if the display is off.
+    async fn notify_blip(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        icon_or_text: String,
+        duration_ms: u32,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        let (anime_type, display_enabled) = {
+            let config = self.0.config.lock().await;
+            (config.anime_type, config.display_enabled)
+        };
+        if !display_enabled {
+            debug!("AniMe display is off, not showing blip");
+            return Ok(());
+        }
+        let blip = blip_buffer(anime_type, &icon_or_text).map_err(RogError::from)?;
+        let duration = Duration::from_millis(duration_ms.min(BLIP_MAX_MS).into());
+        let inner = self.0.clone();
+        tokio::spawn(async move { inner.show_blip(blip, duration).await });
+        Ok(())
+    }
+
+    /// Play a gif (or show a png) scaled, rotated and moved the same way as
+    /// `asusctl anime gif`, for `loops` loops or forever if 0. The frames are
+    /// converted once and cached in `/var/cache/asusd/anime`, so playing the
+    /// same file with the same transform again is cheap.
+    #[allow(clippy::too_many_arguments)]
+    async fn play_gif(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        file: String,
+        scale: f64,
+        angle: f64,
+        translation: (f64, f64),
+        brightness: f64,
+        loops: u32,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        if !(0.0..=1.0).contains(&brightness) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "brightness must be between 0.0 and 1.0, was {brightness}"
+            )));
+        }
+        let action = ActionLoader::ImageAnimation {
+            file: file.into(),
+            scale: scale as f32,
+            angle: angle as f32,
+            translation: Vec2::new(translation.0 as f32, translation.1 as f32),
+            time: if loops == 0 {
+                AnimTime::Infinite
+            } else {
+                AnimTime::Count(loops)
+            },
+            brightness: brightness as f32,
+        };
+        let anime_type = self.0.config.lock().await.anime_type;
+        let frames = self.0.frame_cache.clone();
+        let data = tokio::task::spawn_blocking(move || frames.load(anime_type, &action))
+            .await
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?
+            .map_err(RogError::from)?;
+
+        let bright = {
+            let config = self.0.config.lock().await;
+            self.0.base_brightness(&config).await
+        };
+        if self.0.config.lock().await.builtin_anims_enabled {
+            self.0.set_builtins_enabled(false, bright).await?;
+        }
+        self.0.thread_exit.store(true, Ordering::SeqCst);
+        let inner = self.0.clone();
+        tokio::spawn(async move { inner.run_thread(vec![data], loops != 0).await });
+        Ok(())
+    }
+
+    /// Remove every converted gif from the frame cache, returning how many
+    /// were removed
+    async fn clear_frame_cache(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<u32> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Ok(self.0.frame_cache.clear() as u32)
+    }
+
+    /// The icon names `NotifyBlip` draws, anything else is shown as text
+    #[zbus(property)]
+    async fn blip_icons(&self) -> Vec<String> {
+        blip_icons().into_iter().map(str::to_owned).collect()
+    }
+
     /// Set base brightness level
     #[zbus(property)]
     async fn brightness(&self) -> Brightness {
@@ -93,7 +515,13 @@ impl AniMeZbus {
 
     /// Set base brightness level
     #[zbus(property)]
-    async fn set_brightness(&self, brightness: Brightness) {
+    async fn set_brightness(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        brightness: Brightness,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         self.0
             .write_bytes(&pkt_set_brightness(brightness))
             .await
@@ -113,6 +541,158 @@ impl AniMeZbus {
         config.display_enabled = brightness != Brightness::Off;
         config.display_brightness = brightness;
         config.write();
+        if config.schedule.enabled {
+            self.0.set_schedule_override(true);
+        }
+        Ok(())
+    }
+
+    #[zbus(property)]
+    async fn auto_brightness(&self) -> bool {
+        if let Some(config) = self.0.config.try_lock() {
+            return config.auto_brightness.enabled;
+        }
+        false
+    }
+
+    /// Set the brightness from the ambient light sensor, using the curve and
+    /// limits in `anime.ron`. Does nothing if there is no sensor
+    #[zbus(property)]
+    async fn set_auto_brightness(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        let mut config = self.0.config.lock().await;
+        config.auto_brightness.enabled = enabled;
+        config.write();
+        self.0.auto_brightness_changed.send_replace(());
+        if !enabled && self.0.ambient_brightness.lock().await.take().is_some() {
+            self.0
+                .write_bytes(&pkt_set_brightness(config.display_brightness))
+                .await
+                .map_err(|err| {
+                    warn!("ctrl_anime::set_auto_brightness {}", err);
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    #[zbus(property)]
+    async fn schedule_enabled(&self) -> bool {
+        if let Some(config) = self.0.config.try_lock() {
+            return config.schedule.enabled;
+        }
+        false
+    }
+
+    /// Use the day and night brightness from `SetSchedule`, like a night
+    /// light. Auto brightness takes precedence while it is on
+    #[zbus(property)]
+    async fn set_schedule_enabled(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        let mut config = self.0.config.lock().await;
+        config.schedule.enabled = enabled;
+        config.write();
+        self.0.set_schedule_override(false);
+        let brightness = self.0.base_brightness(&config).await;
+        if config.display_enabled {
+            self.0
+                .write_brightness(brightness)
+                .await
+                .map_err(|err| {
+                    warn!("ctrl_anime::set_schedule_enabled {}", err);
+                })
+                .ok();
+        }
+        Ok(())
+    }
+
+    /// The schedule as the minutes after local midnight that day and night
+    /// start, then the brightness for each
+    async fn schedule(&self) -> (u16, u16, Brightness, Brightness) {
+        let BrightnessSchedule {
+            day_start,
+            night_start,
+            day,
+            night,
+            ..
+        } = self.0.config.lock().await.schedule.clone();
+        (day_start, night_start, day, night)
+    }
+
+    /// Set when day and night start, in minutes after local midnight, and the
+    /// brightness for each. `Off` turns the display off
+    async fn set_schedule(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        day_start: u16,
+        night_start: u16,
+        day: Brightness,
+        night: Brightness,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        if day_start >= 24 * 60 || night_start >= 24 * 60 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "day_start and night_start must be less than 1440".to_owned(),
+            ));
+        }
+        let mut config = self.0.config.lock().await;
+        config.schedule = BrightnessSchedule {
+            enabled: config.schedule.enabled,
+            day_start,
+            night_start,
+            day,
+            night,
+        };
+        config.write();
+        if config.schedule.enabled && config.display_enabled && !self.0.schedule_override() {
+            let brightness = self.0.base_brightness(&config).await;
+            self.0.write_brightness(brightness).await?;
+        }
+        Ok(())
+    }
+
+    /// Set to keep the current brightness until the schedule next changes
+    /// between day and night, like pausing a night light until tomorrow. Also
+    /// set by changing `Brightness` while the schedule is on. Not saved
+    #[zbus(property)]
+    async fn schedule_override(&self) -> bool {
+        self.0.schedule_override()
+    }
+
+    #[zbus(property)]
+    async fn set_schedule_override(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        self.0.set_schedule_override(enabled);
+        if !enabled {
+            let config = self.0.config.lock().await;
+            if config.schedule.enabled && config.display_enabled {
+                let brightness = self.0.base_brightness(&config).await;
+                self.0
+                    .write_brightness(brightness)
+                    .await
+                    .map_err(|err| {
+                        warn!("ctrl_anime::set_schedule_override {}", err);
+                    })
+                    .ok();
+            }
+        }
+        Ok(())
     }
 
     #[zbus(property)]
@@ -126,9 +706,15 @@ impl AniMeZbus {
     /// Enable the builtin animations or not. This is quivalent to "Powersave
     /// animations" in Armory crate
     #[zbus(property)]
-    async fn set_builtins_enabled(&self, enabled: bool) {
+    async fn set_builtins_enabled(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.config.lock().await;
-        let brightness = config.display_brightness;
+        let brightness = self.0.base_brightness(&config).await;
         self.0
             .set_builtins_enabled(enabled, brightness)
             .await
@@ -158,6 +744,7 @@ impl AniMeZbus {
         if enabled {
             self.0.thread_exit.store(true, Ordering::Release);
         }
+        Ok(())
     }
 
     #[zbus(property)]
@@ -170,27 +757,21 @@ impl AniMeZbus {
 
     /// Set which builtin animation is used for each stage
     #[zbus(property)]
-    async fn set_builtin_animations(&self, settings: Animations) {
-        self.0
-            .write_bytes(&pkt_set_builtin_animations(
-                settings.boot, settings.awake, settings.sleep, settings.shutdown,
-            ))
-            .await
-            .map_err(|err| {
-                warn!("ctrl_anime::run_animation:callback {}", err);
-            })
-            .ok();
+    async fn set_builtin_animations(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        settings: Animations,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         self.0
-            .write_bytes(&pkt_set_enable_powersave_anim(true))
+            .set_builtin_animations(settings)
             .await
             .map_err(|err| {
                 warn!("ctrl_anime::run_animation:callback {}", err);
             })
             .ok();
-        let mut config = self.0.config.lock().await;
-        config.display_enabled = true;
-        config.builtin_anims = settings;
-        config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -203,7 +784,13 @@ impl AniMeZbus {
 
     /// Set whether the AniMe is enabled at all
     #[zbus(property)]
-    async fn set_enable_display(&self, enabled: bool) {
+    async fn set_enable_display(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         self.0
             .write_bytes(&pkt_set_enable_display(enabled))
             .await
@@ -214,6 +801,7 @@ impl AniMeZbus {
         let mut config = self.0.config.lock().await;
         config.display_enabled = enabled;
         config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -226,7 +814,13 @@ impl AniMeZbus {
 
     /// Set if to turn the AniMe Matrix off when external power is unplugged
     #[zbus(property)]
-    async fn set_off_when_unplugged(&self, enabled: bool) {
+    async fn set_off_when_unplugged(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let manager = get_logind_manager().await;
         let pow = manager.on_external_power().await.unwrap_or_default();
 
@@ -241,6 +835,7 @@ impl AniMeZbus {
         let mut config = self.0.config.lock().await;
         config.off_when_unplugged = enabled;
         config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -253,10 +848,17 @@ impl AniMeZbus {
 
     /// Set if to turn the AniMe Matrix off when the laptop is suspended
     #[zbus(property)]
-    async fn set_off_when_suspended(&self, enabled: bool) {
+    async fn set_off_when_suspended(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.config.lock().await;
         config.off_when_suspended = enabled;
         config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -269,7 +871,13 @@ impl AniMeZbus {
 
     /// Set if to turn the AniMe Matrix off when the lid is closed
     #[zbus(property)]
-    async fn set_off_when_lid_closed(&self, enabled: bool) {
+    async fn set_off_when_lid_closed(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let manager = get_logind_manager().await;
         let lid = manager.lid_closed().await.unwrap_or_default();
 
@@ -284,15 +892,24 @@ impl AniMeZbus {
         let mut config = self.0.config.lock().await;
         config.off_when_lid_closed = enabled;
         config.write();
+        Ok(())
     }
 
     /// The main loop is the base system set action if the user isn't running
     /// the user daemon
-    async fn run_main_loop(&self, start: bool) {
+    async fn run_main_loop(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        start: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         if start {
             self.0.thread_exit.store(true, Ordering::SeqCst);
-            self.0.run_thread(self.0.cache.system.clone(), false).await;
+            let system = self.0.cache.lock().await.system.clone();
+            self.0.run_thread(system, false).await;
         }
+        Ok(())
     }
 
     /// Get the device state as stored by asusd
@@ -302,57 +919,19 @@ impl AniMeZbus {
     }
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/aura_anime/trait_impls.rs"));
+
 impl crate::CtrlTask for AniMeZbus {
     fn zbus_path() -> &'static str {
         "ANIME_ZBUS_PATH"
     }
 
     async fn create_tasks(&self, _: SignalEmitter<'static>) -> Result<(), RogError> {
-        let inner1 = self.0.clone();
         let inner2 = self.0.clone();
         let inner3 = self.0.clone();
         let inner4 = self.0.clone();
         self.create_sys_event_tasks(
-            move |sleeping| {
-                // on_sleep
-                let inner = inner1.clone();
-                async move {
-                    let config = inner.config.lock().await.clone();
-                    if config.display_enabled {
-                        inner.thread_exit.store(true, Ordering::Release); // ensure clean slate
-
-                        inner
-                            .write_bytes(&pkt_set_enable_display(
-                                !(sleeping && config.off_when_suspended),
-                            ))
-                            .await
-                            .map_err(|err| {
-                                warn!("create_sys_event_tasks::off_when_suspended {}", err);
-                            })
-                            .ok();
-
-                        if config.builtin_anims_enabled {
-                            inner
-                                .write_bytes(&pkt_set_enable_powersave_anim(
-                                    !(sleeping && config.off_when_suspended),
-                                ))
-                                .await
-                                .map_err(|err| {
-                                    warn!("create_sys_event_tasks::off_when_suspended {}", err);
-                                })
-                                .ok();
-                        } else if !sleeping && !config.builtin_anims_enabled {
-                            // Run custom wake animation
-                            inner
-                                .write_bytes(&pkt_set_enable_powersave_anim(false))
-                                .await
-                                .ok(); // ensure builtins are disabled
-
-                            inner.run_thread(inner.cache.wake.clone(), true).await;
-                        }
-                    }
-                }
-            },
             move |shutting_down| {
                 // on_shutdown
                 let inner = inner2.clone();
@@ -363,11 +942,12 @@ impl crate::CtrlTask for AniMeZbus {
                         ..
                     } = *inner.config.lock().await;
                     if display_enabled && !builtin_anims_enabled {
-                        if shutting_down {
-                            inner.run_thread(inner.cache.shutdown.clone(), true).await;
+                        let actions = if shutting_down {
+                            inner.cache.lock().await.shutdown.clone()
                         } else {
-                            inner.run_thread(inner.cache.boot.clone(), true).await;
-                        }
+                            inner.cache.lock().await.boot.clone()
+                        };
+                        inner.run_thread(actions, true).await;
                     }
                 }
             },
@@ -408,6 +988,11 @@ impl crate::CtrlTask for AniMeZbus {
                         off_when_unplugged,
                         builtin_anims_enabled,
                         brightness_on_battery,
+                        auto_brightness:
+                            AutoBrightness {
+                                enabled: auto_brightness,
+                                ..
+                            },
                         ..
                     } = *inner.config.lock().await;
                     if off_when_unplugged {
@@ -427,7 +1012,7 @@ impl crate::CtrlTask for AniMeZbus {
                                 warn!("create_sys_event_tasks::off_when_unplugged {}", err);
                             })
                             .ok();
-                    } else {
+                    } else if !auto_brightness {
                         inner
                             .write_bytes(&pkt_set_brightness(brightness_on_battery))
                             .await
@@ -451,11 +1036,16 @@ impl crate::Reloadable for AniMeZbus {
             builtin_anims_enabled,
             builtin_anims,
             display_enabled,
-            display_brightness,
             off_when_lid_closed,
             off_when_unplugged,
+            max_fps,
             ..
         } = *self.0.config.lock().await;
+        self.0.set_max_fps(max_fps).await;
+        let display_brightness = {
+            let config = self.0.config.lock().await;
+            self.0.base_brightness(&config).await
+        };
 
         // Set builtins
         if builtin_anims_enabled {
@@ -493,13 +1083,13 @@ impl crate::Reloadable for AniMeZbus {
             return Ok(());
         }
 
-        if !builtin_anims_enabled && !self.0.cache.boot.is_empty() {
+        let action = self.0.cache.lock().await.boot.clone();
+        if !builtin_anims_enabled && !action.is_empty() {
             self.0
                 .write_bytes(&pkt_set_enable_powersave_anim(false))
                 .await
                 .ok();
 
-            let action = self.0.cache.boot.clone();
             self.0.run_thread(action, true).await;
         }
         Ok(())
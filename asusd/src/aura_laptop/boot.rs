@@ -0,0 +1,115 @@
+//! Early boot lighting. `asusd --boot-lighting` is run by
+//! `asusd-boot-lighting.service` as soon as udev has the keyboard, so the
+//! configured lighting is shown during the boot splash instead of the
+//! firmware default rainbow. asusd reads the same config when it starts and
+//! applies `current_mode` over it, so the keyboard never goes back to the
+//! firmware default in between.
+
+use std::sync::Arc;
+
+use config_traits::{StdConfig, StdConfigLoad2};
+use dmi_id::DMIID;
+use futures_util::lock::Mutex;
+use log::{debug, info, warn};
+use rog_platform::error::PlatformError;
+use rog_platform::hid_raw::HidRaw;
+
+use super::Aura;
+use crate::aura_manager::device_key;
+use crate::aura_types::DeviceHandle;
+use crate::config::Config;
+use crate::error::RogError;
+
+async fn find_laptop_auras() -> Result<Vec<Aura>, RogError> {
+    let mut auras = Vec::new();
+    let mut enumerator = udev::Enumerator::new()
+        .map_err(|err| PlatformError::Udev("enumerator failed".into(), err))?;
+    enumerator
+        .match_subsystem("hidraw")
+        .map_err(|err| PlatformError::Udev("match_subsystem failed".into(), err))?;
+
+    for device in enumerator
+        .scan_devices()
+        .map_err(|e| PlatformError::IoPath("enumerator".to_owned(), e))?
+    {
+        let Some(usb_device) = device.parent_with_subsystem_devtype("usb", "usb_device")? else {
+            continue;
+        };
+        if usb_device
+            .attribute_value("idVendor")
+            .is_none_or(|v| v != "0b05")
+        {
+            continue;
+        }
+        let Some(prod_id) = usb_device.attribute_value("idProduct") else {
+            continue;
+        };
+        let prod_id = prod_id.to_string_lossy().to_string();
+        let key = device_key(&usb_device);
+        let Ok(hidraw) = HidRaw::from_device(device) else {
+            continue;
+        };
+        if let Ok(DeviceHandle::Aura(aura)) = DeviceHandle::maybe_laptop_aura(
+            Some(Arc::new(Mutex::new(hidraw))),
+            &prod_id,
+            key.as_deref(),
+        )
+        .await
+        {
+            auras.push(aura);
+        }
+    }
+
+    if auras.is_empty() {
+        let dmi = DMIID::new().unwrap_or_default();
+        if dmi.product_name.contains("TUF") || dmi.product_family.contains("TUF") {
+            if let Ok(DeviceHandle::Aura(aura)) =
+                DeviceHandle::maybe_laptop_aura(None, "tuf", None).await
+            {
+                auras.push(aura);
+            }
+        }
+    }
+    Ok(auras)
+}
+
+async fn apply(aura: &Aura) -> Result<(), RogError> {
+    let mut config = aura.lock_config().await;
+    aura.set_power_states(&config)
+        .await
+        .map_err(|e| warn!("Boot lighting: power states: {e}"))
+        .ok();
+    aura.restore_brightness(&config)
+        .await
+        .map_err(|e| debug!("Boot lighting: brightness: {e}"))
+        .ok();
+    if let Some(effect) = config.boot_effect.clone() {
+        info!("Boot lighting: applying boot effect {:?}", effect.mode);
+        aura.write_effect_and_apply(&config, &effect).await?;
+    } else {
+        info!("Boot lighting: applying {:?}", config.current_mode);
+        aura.write_current_config_mode(&mut config).await?;
+    }
+    // asusd exits next, before a held write would be made
+    aura.flush_packets().await
+}
+
+/// Set the configured lighting on every laptop keyboard then return. Nothing
+/// is registered on DBus.
+pub async fn apply_boot_lighting() -> Result<(), RogError> {
+    if !Config::new().load().subsystems.aura {
+        info!("Boot lighting: Aura is disabled in asusd.ron");
+        return Ok(());
+    }
+    let auras = find_laptop_auras().await?;
+    if auras.is_empty() {
+        info!("Boot lighting: no laptop keyboard found");
+    }
+    for aura in &auras {
+        apply(aura)
+            .await
+            .map_err(|e| warn!("Boot lighting: {e}"))
+            .ok();
+    }
+    Ok(())
+}
@@ -1,14 +1,19 @@
 use std::collections::BTreeMap;
+use std::time::Duration;
 
 use config_traits::{StdConfig, StdConfigLoad};
 use log::{debug, info, warn};
 use rog_aura::aura_detection::LedSupportData;
 use rog_aura::keyboard::LaptopAuraPower;
 use rog_aura::{
-    AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, Direction, LedBrightness, Speed, GRADIENT,
+    AuraDeviceType, AuraEffect, AuraLowBattery, AuraModeNum, AuraProfileLighting, AuraScheduleStep,
+    AuraShuffle, AuraZone, BrightnessRestore, Colour, Direction, LedBrightness, Speed, GRADIENT,
 };
+use rog_platform::platform::PlatformProfile;
 use serde::{Deserialize, Serialize};
 
+use super::emulated::missing_modes;
+use super::shuffle::ColourRng;
 use crate::error::RogError;
 
 #[derive(Deserialize, Serialize, Default, Debug, Clone)]
@@ -22,14 +27,60 @@ pub struct AuraConfig {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub ally_fix: Option<bool>,
     pub brightness: LedBrightness,
+    /// Percent brightness of each zone, relative to the keyboard brightness.
+    /// Zones not listed are at 100
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub zone_brightness: BTreeMap<AuraZone, u8>,
+    /// Which brightness to set when asusd starts and after resume
+    #[serde(default)]
+    pub brightness_restore: BrightnessRestore,
     pub current_mode: AuraModeNum,
     pub builtins: BTreeMap<AuraModeNum, AuraEffect>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub multizone: Option<BTreeMap<AuraModeNum, Vec<AuraEffect>>>,
     pub multizone_on: bool,
     pub enabled: LaptopAuraPower,
+    /// Written by `asusd --boot-lighting` early in boot, before asusd starts
+    /// and applies `current_mode`. `None` uses `current_mode` for both
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub boot_effect: Option<AuraEffect>,
+    /// New colours for the current mode on start, resume, and an interval
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub shuffle: Option<AuraShuffle>,
+    /// Retry writes which fail while other RGB software holds the device
+    #[serde(default)]
+    pub exclusive: bool,
+    /// An effect shown in place of the current mode while the battery is low
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub low_battery: Option<AuraLowBattery>,
+    /// Set while the low battery effect is showing
+    #[serde(skip)]
+    pub low_battery_active: bool,
+    /// Seconds without input before the backlight turns off while on AC, for
+    /// each platform profile. Profiles without an entry, or 0, keep it on
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub idle_timeout_on_ac: BTreeMap<PlatformProfile, u32>,
+    /// As `idle_timeout_on_ac`, while on battery
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub idle_timeout_on_battery: BTreeMap<PlatformProfile, u32>,
+    /// Set while the backlight is off from the idle timeout
+    #[serde(skip)]
+    pub idle_off: bool,
+    /// The lighting set when the platform profile changes to each profile
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub profile_lighting: BTreeMap<PlatformProfile, AuraProfileLighting>,
+    /// Lighting set at times of day or on power source changes
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub schedule: Vec<AuraScheduleStep>,
     #[serde(skip)]
     pub per_key_mode_active: bool,
+    /// A client has a direct stream open, see `stream.rs`
+    #[serde(skip)]
+    pub direct_stream_open: bool,
+    /// Modes in `support_data` which a TUF keyboard lacks and asusd draws,
+    /// see `emulated.rs`
+    #[serde(skip)]
+    pub emulated_modes: Vec<AuraModeNum>,
 }
 
 impl StdConfig for AuraConfig {
@@ -69,14 +120,35 @@ impl AuraConfig {
             config_name: format!("aura_{prod_id}.ron"),
             ally_fix: None,
             brightness: LedBrightness::Med,
+            zone_brightness: BTreeMap::new(),
+            brightness_restore: BrightnessRestore::default(),
             current_mode: AuraModeNum::Static,
             builtins: BTreeMap::new(),
             multizone: None,
             multizone_on: false,
             enabled,
+            boot_effect: None,
+            shuffle: None,
+            exclusive: false,
+            low_battery: None,
+            low_battery_active: false,
+            idle_timeout_on_ac: BTreeMap::new(),
+            idle_timeout_on_battery: BTreeMap::new(),
+            idle_off: false,
+            profile_lighting: BTreeMap::new(),
+            schedule: Vec::new(),
             per_key_mode_active: false,
+            direct_stream_open: false,
+            emulated_modes: Vec::new(),
         };
 
+        if device_type == AuraDeviceType::LaptopKeyboardTuf {
+            config.emulated_modes = missing_modes(&config.support_data.basic_modes);
+            let modes = &mut config.support_data.basic_modes;
+            modes.extend(config.emulated_modes.iter().copied());
+            modes.sort();
+        }
+
         for n in &config.support_data.basic_modes {
             debug!("creating default for {n}");
             config
@@ -138,6 +210,73 @@ impl AuraConfig {
         }
     }
 
+    /// Make the lighting bound to `profile` the saved mode and brightness.
+    /// Returns false if there is no enabled binding for it
+    pub fn use_profile_lighting(&mut self, profile: PlatformProfile) -> bool {
+        let Some(lighting) = self
+            .profile_lighting
+            .get(&profile)
+            .filter(|lighting| lighting.enabled)
+            .cloned()
+        else {
+            return false;
+        };
+        self.use_lighting(lighting);
+        true
+    }
+
+    /// Make `lighting` the saved mode and brightness
+    pub fn use_lighting(&mut self, lighting: AuraProfileLighting) {
+        self.current_mode = lighting.effect.mode;
+        self.builtins.insert(lighting.effect.mode, lighting.effect);
+        self.multizone_on = false;
+        self.brightness = lighting.brightness;
+    }
+
+    /// How long without input before the backlight turns off for this power
+    /// source and profile, `None` to keep it on
+    pub fn idle_timeout_for(&self, on_ac: bool, profile: PlatformProfile) -> Option<Duration> {
+        let timeouts = if on_ac {
+            &self.idle_timeout_on_ac
+        } else {
+            &self.idle_timeout_on_battery
+        };
+        timeouts
+            .get(&profile)
+            .filter(|secs| **secs > 0)
+            .map(|secs| Duration::from_secs((*secs).into()))
+    }
+
+    /// The percent brightness of `zone`
+    pub fn zone_percent(&self, zone: AuraZone) -> u8 {
+        self.zone_brightness.get(&zone).copied().unwrap_or(100)
+    }
+
+    /// The effects to write for `effect` with the zone brightness applied. An
+    /// effect for the whole keyboard is split in to one per zone if any zone
+    /// is dimmed.
+    pub fn zone_effects(&self, effect: &AuraEffect) -> Vec<AuraEffect> {
+        if effect.zone != AuraZone::None {
+            return vec![effect.scaled(self.zone_percent(effect.zone))];
+        }
+        let dimmed = self
+            .support_data
+            .basic_zones
+            .iter()
+            .any(|zone| self.zone_percent(*zone) < 100);
+        if !dimmed {
+            return vec![effect.clone()];
+        }
+        self.support_data
+            .basic_zones
+            .iter()
+            .map(|zone| AuraEffect {
+                zone: *zone,
+                ..effect.scaled(self.zone_percent(*zone))
+            })
+            .collect()
+    }
+
     pub fn get_multizone(&self, aura_type: AuraModeNum) -> Option<&[AuraEffect]> {
         if let Some(multi) = &self.multizone {
             return multi.get(&aura_type).map(|v| v.as_slice());
@@ -145,6 +284,144 @@ impl AuraConfig {
         None
     }
 
+    /// The lightbar zones of a zoned keyboard, empty if the lightbar can't be
+    /// set apart from the keyboard
+    pub fn lightbar_zones(&self) -> Vec<AuraZone> {
+        self.support_data
+            .basic_zones
+            .iter()
+            .filter(|zone| matches!(zone, AuraZone::BarLeft | AuraZone::BarRight))
+            .copied()
+            .collect()
+    }
+
+    /// The logo zone of a zoned keyboard, empty if the logo can't be set apart
+    /// from the keyboard
+    pub fn logo_zones(&self) -> Vec<AuraZone> {
+        self.support_data
+            .basic_zones
+            .iter()
+            .filter(|zone| **zone == AuraZone::Logo)
+            .copied()
+            .collect()
+    }
+
+    /// The colour the lightbar currently has, `None` without lightbar zones
+    pub fn lightbar_colour(&self) -> Option<Colour> {
+        let bar = *self.lightbar_zones().first()?;
+        let zoned = self
+            .get_multizone(self.current_mode)
+            .and_then(|zones| zones.iter().find(|fx| fx.zone == bar));
+        match zoned {
+            Some(effect) if self.multizone_on => Some(effect.colour1),
+            _ => self.builtins.get(&self.current_mode).map(|fx| fx.colour1),
+        }
+    }
+
+    /// Give the lightbar zones a static `colour` and the other zones the
+    /// current mode, switching to multizone
+    pub fn set_lightbar(&mut self, colour: Colour) {
+        let mode = self.current_mode;
+        let base = self
+            .builtins
+            .get(&mode)
+            .cloned()
+            .unwrap_or_else(|| AuraEffect::default_with_mode(mode));
+        let bars = self.lightbar_zones();
+        let zones = self
+            .support_data
+            .basic_zones
+            .iter()
+            .map(|zone| {
+                if bars.contains(zone) {
+                    AuraEffect {
+                        mode: AuraModeNum::Static,
+                        zone: *zone,
+                        colour1: colour,
+                        ..Default::default()
+                    }
+                } else {
+                    AuraEffect {
+                        zone: *zone,
+                        ..base.clone()
+                    }
+                }
+            })
+            .collect();
+        self.multizone
+            .get_or_insert_with(BTreeMap::new)
+            .insert(mode, zones);
+        self.multizone_on = true;
+    }
+
+    /// Give `zones` the mode and colours of `effect` and keep the effects of
+    /// the other zones, switching to multizone. If multizone was off the
+    /// other zones start from the current mode.
+    pub fn set_zone_effect(&mut self, zones: &[AuraZone], effect: &AuraEffect) {
+        let mode = self.current_mode;
+        let mut effects = match self.get_multizone(mode) {
+            Some(set) if self.multizone_on => set.to_vec(),
+            _ => {
+                let base = self
+                    .builtins
+                    .get(&mode)
+                    .cloned()
+                    .unwrap_or_else(|| AuraEffect::default_with_mode(mode));
+                self.support_data
+                    .basic_zones
+                    .iter()
+                    .map(|zone| AuraEffect {
+                        zone: *zone,
+                        ..base.clone()
+                    })
+                    .collect()
+            }
+        };
+        for zone in zones {
+            let zoned = AuraEffect {
+                zone: *zone,
+                ..effect.clone()
+            };
+            match effects.iter_mut().find(|fx| fx.zone == *zone) {
+                Some(fx) => *fx = zoned,
+                None => effects.push(zoned),
+            }
+        }
+        self.multizone
+            .get_or_insert_with(BTreeMap::new)
+            .insert(mode, effects);
+        self.multizone_on = true;
+    }
+
+    pub fn shuffle_enabled(&self) -> bool {
+        self.shuffle.as_ref().is_some_and(|s| s.enabled)
+    }
+
+    /// Give the current mode, or each of its zones if multizone is on, new
+    /// colours picked from the shuffle palette
+    pub fn shuffle_colours(&mut self, rng: &mut ColourRng) {
+        let palette = self
+            .shuffle
+            .as_ref()
+            .map(|s| s.palette.clone())
+            .unwrap_or_default();
+        let mode = self.current_mode;
+        let effects: Vec<&mut AuraEffect> = if self.multizone_on {
+            self.multizone
+                .as_mut()
+                .and_then(|m| m.get_mut(&mode))
+                .map(|zones| zones.iter_mut().collect())
+                .unwrap_or_default()
+        } else {
+            self.builtins.get_mut(&mode).into_iter().collect()
+        };
+        for effect in effects {
+            effect.colour1 = rng.pick(&palette, effect.colour1);
+            // Two colour modes get a second, different colour
+            effect.colour2 = rng.pick(&palette, effect.colour1);
+        }
+    }
+
     /// Create a default for the `current_mode` if multizone and no config
     /// exists.
     pub fn create_multizone_default(&mut self) -> Result<(), RogError> {
@@ -173,13 +450,45 @@ impl AuraConfig {
         Ok(())
     }
 
+    /// The config file of a keyboard. `device_key` identifies one physical
+    /// device so two with the same product ID keep their own settings, `None`
+    /// is the file shared by every device with the product ID
+    fn file_name_for(prod_id: &str, device_key: Option<&str>) -> String {
+        match device_key {
+            Some(key) => format!("aura_{prod_id}_{key}.ron"),
+            None => format!("aura_{prod_id}.ron"),
+        }
+    }
+
+    /// The shared config of the product ID, if this device has no file of its
+    /// own yet. Settings made before configs were per device are kept this
+    /// way
+    fn shared_config(&self, prod_id: &str) -> Option<AuraConfig> {
+        let shared_name = Self::file_name_for(prod_id, None);
+        if shared_name == self.config_name || self.file_path().exists() {
+            return None;
+        }
+        let mut shared = self.clone();
+        shared.config_name = shared_name;
+        let loaded = shared.read_new()?;
+        info!(
+            "Starting {} from the settings in {}",
+            self.config_name, shared.config_name
+        );
+        Some(loaded)
+    }
+
     /// Reload the config from disk then verify and update it if required.
     /// Always rewrites the file to disk.
-    pub fn load_and_update_config(prod_id: &str) -> AuraConfig {
+    pub fn load_and_update_config(prod_id: &str, device_key: Option<&str>) -> AuraConfig {
         // New loads data from the DB also
         let mut config_init = AuraConfig::new(prod_id);
-        // config_init.set_filename(prod_id);
-        let mut config_loaded = config_init.clone().load();
+        config_init.config_name = Self::file_name_for(prod_id, device_key);
+        let mut config_loaded = match config_init.shared_config(prod_id) {
+            Some(shared) => shared,
+            None => config_init.clone().load(),
+        };
+        config_loaded.config_name = config_init.config_name.clone();
         // update the initialised data with what we loaded from disk
         for mode_init in &mut config_init.builtins {
             // update init values from loaded values if they exist
@@ -190,6 +499,7 @@ impl AuraConfig {
         // Then replace just incase the initialised data contains new modes added
         config_loaded.builtins = config_init.builtins;
         config_loaded.support_data = config_init.support_data;
+        config_loaded.emulated_modes = config_init.emulated_modes;
         config_loaded.led_type = config_init.led_type;
         config_loaded.ally_fix = config_init.ally_fix;
 
@@ -226,14 +536,34 @@ impl AuraConfig {
         config_loaded.write();
         config_loaded
     }
+
+    /// Put every setting back to the defaults for this keyboard. The product
+    /// ID is taken from the config file name, which is kept.
+    pub fn reset_to_defaults(&mut self) {
+        let config_name = self.config_name.clone();
+        let prod_id = config_name
+            .trim_start_matches("aura_")
+            .trim_end_matches(".ron")
+            .split('_')
+            .next()
+            .unwrap_or_default();
+        let led_type = self.led_type;
+        *self = AuraConfig::new(prod_id);
+        self.led_type = led_type;
+        self.config_name = config_name;
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use rog_aura::keyboard::AuraPowerState;
     use rog_aura::{
-        AuraEffect, AuraModeNum, AuraZone, Colour, Direction, LedBrightness, PowerZones, Speed,
+        AuraEffect, AuraModeNum, AuraProfileLighting, AuraZone, Colour, Direction, LedBrightness,
+        PowerZones, Speed,
     };
+    use rog_platform::platform::PlatformProfile;
 
     use super::AuraConfig;
 
@@ -364,6 +694,89 @@ mod tests {
         assert_eq!(sta.len(), 1);
     }
 
+    #[test]
+    fn set_lightbar_config() {
+        std::env::set_var("BOARD_NAME", "");
+        let mut config = AuraConfig::new("19b6");
+        config.support_data.basic_zones = vec![
+            AuraZone::Key1,
+            AuraZone::Key2,
+            AuraZone::BarLeft,
+        ];
+        config.set_builtin(AuraEffect {
+            mode: AuraModeNum::Breathe,
+            ..Default::default()
+        });
+
+        let blue = Colour {
+            r: 0x00,
+            g: 0x00,
+            b: 0xff,
+        };
+        config.set_lightbar(blue);
+        assert!(config.multizone_on);
+        assert_eq!(config.lightbar_colour(), Some(blue));
+
+        let zones = config.get_multizone(AuraModeNum::Breathe).unwrap();
+        assert_eq!(zones.len(), 3);
+        assert_eq!(zones[0].mode, AuraModeNum::Breathe);
+        assert_eq!(zones[2].mode, AuraModeNum::Static);
+        assert_eq!(zones[2].zone, AuraZone::BarLeft);
+
+        config.support_data.basic_zones.clear();
+        assert_eq!(config.lightbar_colour(), None);
+    }
+
+    #[test]
+    fn set_zone_effect_config() {
+        std::env::set_var("BOARD_NAME", "");
+        let mut config = AuraConfig::new("19b6");
+        config.support_data.basic_zones = vec![
+            AuraZone::Key1,
+            AuraZone::Logo,
+            AuraZone::BarLeft,
+            AuraZone::BarRight,
+        ];
+        config.set_builtin(AuraEffect {
+            mode: AuraModeNum::Static,
+            ..Default::default()
+        });
+
+        let logo = AuraEffect {
+            mode: AuraModeNum::Breathe,
+            colour1: Colour {
+                r: 0,
+                g: 0xff,
+                b: 0,
+            },
+            ..Default::default()
+        };
+        config.set_zone_effect(&[AuraZone::Logo], &logo);
+        assert!(config.multizone_on);
+        let zones = config.get_multizone(AuraModeNum::Static).unwrap();
+        assert_eq!(zones.len(), 4);
+        assert_eq!(zones[0].mode, AuraModeNum::Static);
+        assert_eq!(zones[1].zone, AuraZone::Logo);
+        assert_eq!(zones[1].mode, AuraModeNum::Breathe);
+
+        // The logo is kept when the lightbar is set after it
+        let bar = AuraEffect {
+            mode: AuraModeNum::Static,
+            colour1: Colour {
+                r: 0,
+                g: 0,
+                b: 0xff,
+            },
+            ..Default::default()
+        };
+        config.set_zone_effect(&config.lightbar_zones(), &bar);
+        let zones = config.get_multizone(AuraModeNum::Static).unwrap();
+        assert_eq!(zones[1].mode, AuraModeNum::Breathe);
+        assert_eq!(zones[2].colour1, bar.colour1);
+        assert_eq!(zones[3].zone, AuraZone::BarRight);
+        assert_eq!(zones[3].colour1, bar.colour1);
+    }
+
     #[test]
     fn verify_0x1866_g531i() {
         std::env::set_var("BOARD_NAME", "G513I");
@@ -413,4 +826,139 @@ mod tests {
             shutdown: true
         });
     }
+
+    #[test]
+    fn reset_to_defaults() {
+        std::env::set_var("BOARD_NAME", "");
+        let mut config = AuraConfig::new("19b6");
+        let defaults = config.clone();
+
+        config.brightness = LedBrightness::Off;
+        config.current_mode = AuraModeNum::Breathe;
+        config.enabled.states[0].boot = false;
+        config.reset_to_defaults();
+
+        assert_eq!(config.config_name, "aura_19b6.ron");
+        assert_eq!(config.brightness, defaults.brightness);
+        assert_eq!(config.current_mode, AuraModeNum::Static);
+        assert_eq!(config.enabled, defaults.enabled);
+        assert_eq!(config.builtins, defaults.builtins);
+    }
+
+    #[test]
+    fn per_device_file_names() {
+        std::env::set_var("BOARD_NAME", "");
+        assert_eq!(AuraConfig::file_name_for("19b6", None), "aura_19b6.ron");
+        assert_eq!(
+            AuraConfig::file_name_for("19b6", Some("1-3")),
+            "aura_19b6_1-3.ron"
+        );
+
+        let mut config = AuraConfig::new("19b6");
+        let defaults = config.clone();
+        config.config_name = AuraConfig::file_name_for("19b6", Some("1-3"));
+        config.brightness = LedBrightness::Off;
+        config.reset_to_defaults();
+        assert_eq!(config.config_name, "aura_19b6_1-3.ron");
+        assert_eq!(config.brightness, defaults.brightness);
+        assert_eq!(config.builtins, defaults.builtins);
+    }
+
+    #[test]
+    fn zone_brightness() {
+        std::env::set_var("BOARD_NAME", "");
+        let mut config = AuraConfig::new("19b6");
+        config.support_data.basic_zones = vec![
+            AuraZone::Key1,
+            AuraZone::Key2,
+        ];
+        let effect = AuraEffect {
+            colour1: Colour {
+                r: 200,
+                g: 100,
+                b: 0,
+            },
+            ..Default::default()
+        };
+        // Nothing dimmed writes the effect as it is
+        assert_eq!(config.zone_effects(&effect), vec![effect.clone()]);
+
+        config.zone_brightness.insert(AuraZone::Key2, 50);
+        let effects = config.zone_effects(&effect);
+        assert_eq!(effects.len(), 2);
+        assert_eq!(effects[0].zone, AuraZone::Key1);
+        assert_eq!(effects[0].colour1, effect.colour1);
+        assert_eq!(effects[1].zone, AuraZone::Key2);
+        assert_eq!(effects[1].colour1, Colour {
+            r: 100,
+            g: 50,
+            b: 0
+        });
+
+        let zoned = AuraEffect {
+            zone: AuraZone::Key2,
+            ..effect.clone()
+        };
+        assert_eq!(config.zone_effects(&zoned), vec![zoned.scaled(50)]);
+    }
+
+    #[test]
+    fn idle_timeout_by_profile_and_power() {
+        std::env::set_var("BOARD_NAME", "");
+        let mut config = AuraConfig::new("19b6");
+        assert_eq!(config.idle_timeout_for(false, PlatformProfile::Quiet), None);
+
+        config
+            .idle_timeout_on_battery
+            .insert(PlatformProfile::Quiet, 10);
+        config
+            .idle_timeout_on_ac
+            .insert(PlatformProfile::Performance, 0);
+        assert_eq!(
+            config.idle_timeout_for(false, PlatformProfile::Quiet),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(config.idle_timeout_for(true, PlatformProfile::Quiet), None);
+        assert_eq!(
+            config.idle_timeout_for(true, PlatformProfile::Performance),
+            None
+        );
+    }
+
+    #[test]
+    fn profile_lighting_sets_saved_mode() {
+        std::env::set_var("BOARD_NAME", "");
+        let mut config = AuraConfig::new("19b6");
+        config.current_mode = AuraModeNum::Static;
+        let red_breathe = AuraEffect {
+            mode: AuraModeNum::Breathe,
+            colour1: Colour { r: 255, g: 0, b: 0 },
+            ..Default::default()
+        };
+        config
+            .profile_lighting
+            .insert(PlatformProfile::Performance, AuraProfileLighting {
+                enabled: true,
+                effect: red_breathe.clone(),
+                brightness: LedBrightness::High,
+            });
+        config
+            .profile_lighting
+            .insert(PlatformProfile::Quiet, AuraProfileLighting {
+                enabled: false,
+                ..Default::default()
+            });
+
+        assert!(!config.use_profile_lighting(PlatformProfile::Quiet));
+        assert!(!config.use_profile_lighting(PlatformProfile::Balanced));
+        assert_eq!(config.current_mode, AuraModeNum::Static);
+
+        assert!(config.use_profile_lighting(PlatformProfile::Performance));
+        assert_eq!(config.current_mode, AuraModeNum::Breathe);
+        assert_eq!(
+            config.builtins.get(&AuraModeNum::Breathe),
+            Some(&red_breathe)
+        );
+        assert_eq!(config.brightness, LedBrightness::High);
+    }
 }
@@ -0,0 +1,363 @@
+//! Keyboard writes which skip packets the keyboard already has. Each packet
+//! which sets part of what the keyboard shows, a zone's mode or a row of
+//! per-key colours, is remembered by the part it sets, and only written again
+//! when it differs. Writes which come within `COALESCE_WINDOW` of the last are
+//! held and written together at the end of it, with each part taking its
+//! latest packet. Everything is written in full again for a change a client
+//! asks for, when exclusive mode changes, and while another process such as
+//! OpenRGB has the keyboard open, as only the periodic writes of asusd's own
+//! tasks can rely on the keyboard still having what asusd wrote.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::lock::Mutex;
+use log::warn;
+use rog_platform::hid_raw::HidRaw;
+use tokio::task::JoinHandle;
+
+use crate::error::RogError;
+
+/// Updates this close together are written as one
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(5);
+
+const BUILTIN_TYPE: u8 = 0xb3;
+const PER_KEY_TYPE: u8 = 0xbc;
+
+/// A packet to write to the keyboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Packet {
+    /// Sets part of what the keyboard shows, and is skipped if the keyboard
+    /// already has it
+    State(Vec<u8>),
+    /// Such as `LED_SET`, written only if a `State` before it in the same
+    /// update was written. A run of commits, such as `LED_SET` then
+    /// `LED_APPLY`, is written in full
+    Commit(Vec<u8>),
+}
+
+/// When to write the packets just queued
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// Write them now
+    Now,
+    /// Write them after this long, with any queued until then
+    After(Duration),
+    /// They are written by a flush already waiting
+    Queued,
+}
+
+/// The part of the keyboard state a packet sets. Builtin mode packets are per
+/// zone, and per-key packets per row
+fn slot(packet: &[u8]) -> &[u8] {
+    let len = match packet.get(1) {
+        Some(&BUILTIN_TYPE) => 3,
+        Some(&PER_KEY_TYPE) => 7,
+        _ => 2,
+    };
+    &packet[..len.min(packet.len())]
+}
+
+/// The packets last written to a keyboard, and those waiting to be
+#[derive(Debug, Default)]
+pub struct SentPackets {
+    sent: HashMap<Vec<u8>, Vec<u8>>,
+    pending: Vec<Vec<Packet>>,
+    last_flush: Option<Instant>,
+    flush_queued: bool,
+    /// The task making a held write, see `flush_after`
+    flush_task: Option<JoinHandle<()>>,
+    /// Why the last held write failed, for `Aura::flush_packets` to return
+    flush_error: Option<RogError>,
+}
+
+impl SentPackets {
+    /// Forget what was written, so that everything is written again. For
+    /// when the keyboard may have lost its state, such as after a resume
+    pub fn clear(&mut self) {
+        self.sent.clear();
+    }
+
+    /// Queue `packets`, replacing any queued packets for the same parts
+    pub fn queue(&mut self, packets: Vec<Packet>, now: Instant) -> Flush {
+        for update in &mut self.pending {
+            update.retain(|old| match old {
+                Packet::State(old) => !packets
+                    .iter()
+                    .any(|new| matches!(new, Packet::State(new) if slot(new) == slot(old))),
+                Packet::Commit(_) => true,
+            });
+        }
+        self.pending.push(packets);
+
+        if self.flush_queued {
+            return Flush::Queued;
+        }
+        match self.last_flush {
+            Some(last) if now.duration_since(last) < COALESCE_WINDOW => {
+                self.flush_queued = true;
+                Flush::After(COALESCE_WINDOW - now.duration_since(last))
+            }
+            _ => {
+                self.last_flush = Some(now);
+                Flush::Now
+            }
+        }
+    }
+
+    /// Take the queued packets which change the keyboard, in order, and
+    /// remember them as written
+    pub fn take(&mut self) -> Vec<Vec<u8>> {
+        self.flush_queued = false;
+        let mut packets = Vec::new();
+        for update in self.pending.drain(..) {
+            // Whether a `State` of the run before these commits was written
+            let mut changed = false;
+            let mut committing = false;
+            for packet in update {
+                match packet {
+                    Packet::State(packet) => {
+                        if committing {
+                            changed = false;
+                            committing = false;
+                        }
+                        if self.sent.get(slot(&packet)) == Some(&packet) {
+                            continue;
+                        }
+                        forget_replaced(&mut self.sent, &packet);
+                        self.sent.insert(slot(&packet).to_vec(), packet.clone());
+                        packets.push(packet);
+                        changed = true;
+                    }
+                    Packet::Commit(packet) => {
+                        committing = true;
+                        if changed {
+                            packets.push(packet);
+                        }
+                    }
+                }
+            }
+        }
+        packets
+    }
+
+    /// Take the error of a held write which failed since the last call
+    pub fn take_error(&mut self) -> Option<RogError> {
+        self.flush_error.take()
+    }
+
+    /// Keep the task making a held write, for `take_flush_task`
+    pub fn set_flush_task(&mut self, task: JoinHandle<()>) {
+        self.flush_task = Some(task);
+    }
+
+    /// Take the task making a held write, if one was started
+    pub fn take_flush_task(&mut self) -> Option<JoinHandle<()>> {
+        self.flush_task.take()
+    }
+}
+
+/// Forget the packets `packet` replaces on the keyboard: those of another mode
+/// type, and for a builtin mode either every zone or the all-zone packet
+fn forget_replaced(sent: &mut HashMap<Vec<u8>, Vec<u8>>, packet: &[u8]) {
+    let kind = packet.get(1);
+    let zone = packet.get(2);
+    sent.retain(|slot, _| {
+        if slot.get(1) != kind {
+            return false;
+        }
+        if kind == Some(&BUILTIN_TYPE) {
+            return zone != Some(&0) && slot.get(2) != Some(&0);
+        }
+        true
+    });
+}
+
+/// Write the queued packets which change the keyboard. On a failed write
+/// everything is forgotten, to be written in full next time
+pub async fn flush(hid_raw: &Mutex<HidRaw>, sent: &Mutex<SentPackets>) -> Result<(), RogError> {
+    let hid_raw = hid_raw.lock().await;
+    let packets = sent.lock().await.take();
+    for packet in &packets {
+        if let Err(e) = hid_raw.write_bytes(packet) {
+            sent.lock().await.clear();
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+/// Flush after `delay` for a coalesced write, whose caller has returned. A
+/// failed write is logged and kept for `SentPackets::take_error`
+pub fn flush_after(
+    delay: Duration,
+    hid_raw: Arc<Mutex<HidRaw>>,
+    sent: Arc<Mutex<SentPackets>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        if let Err(e) = flush(&hid_raw, &sent).await {
+            warn!("Could not write the keyboard LEDs: {e}");
+            sent.lock().await.flush_error = Some(e);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Flush, Packet, SentPackets, COALESCE_WINDOW};
+
+    const SET: [u8; 2] = [
+        0x5d, 0xb5,
+    ];
+    const APPLY: [u8; 2] = [
+        0x5d, 0xb4,
+    ];
+
+    fn zone(zone: u8, colour: u8) -> Vec<u8> {
+        vec![
+            0x5d, 0xb3, zone, 0, colour, 0, 0,
+        ]
+    }
+
+    fn effect(zone_packet: Vec<u8>) -> Vec<Packet> {
+        vec![
+            Packet::State(zone_packet),
+            Packet::Commit(SET.to_vec()),
+        ]
+    }
+
+    fn row(group: u8, colour: u8) -> Vec<u8> {
+        vec![
+            0x5d,
+            0xbc,
+            0,
+            1,
+            1,
+            1,
+            group << 4,
+            0x10,
+            0,
+            colour,
+        ]
+    }
+
+    /// Queue and write at once, whether or not it would be held
+    fn write(sent: &mut SentPackets, packets: Vec<Packet>) -> Vec<Vec<u8>> {
+        sent.queue(packets, Instant::now());
+        sent.take()
+    }
+
+    #[test]
+    fn unchanged_not_written() {
+        let mut sent = SentPackets::default();
+        assert_eq!(write(&mut sent, effect(zone(0, 1))), vec![
+            zone(0, 1),
+            SET.to_vec()
+        ]);
+        assert!(write(&mut sent, effect(zone(0, 1))).is_empty());
+        assert_eq!(write(&mut sent, effect(zone(0, 2))), vec![
+            zone(0, 2),
+            SET.to_vec()
+        ]);
+
+        sent.clear();
+        assert_eq!(write(&mut sent, effect(zone(0, 2))).len(), 2);
+    }
+
+    #[test]
+    fn set_and_apply() {
+        let mut sent = SentPackets::default();
+        let apply = |zone_packet| {
+            vec![
+                Packet::State(zone_packet),
+                Packet::Commit(SET.to_vec()),
+                Packet::Commit(APPLY.to_vec()),
+            ]
+        };
+        assert_eq!(write(&mut sent, apply(zone(0, 1))), vec![
+            zone(0, 1),
+            SET.to_vec(),
+            APPLY.to_vec()
+        ]);
+        assert!(write(&mut sent, apply(zone(0, 1))).is_empty());
+
+        // Only the changed zone is set and applied
+        let zones = (1..=4).flat_map(|z| apply(zone(z, 1))).collect::<Vec<_>>();
+        assert_eq!(write(&mut sent, zones.clone()).len(), 12);
+        let mut changed = zones;
+        changed[6] = Packet::State(zone(3, 2));
+        assert_eq!(write(&mut sent, changed), vec![
+            zone(3, 2),
+            SET.to_vec(),
+            APPLY.to_vec()
+        ]);
+    }
+
+    #[test]
+    fn only_changed_zones() {
+        let mut sent = SentPackets::default();
+        let zones = |colours: [u8; 4]| {
+            (1..=4)
+                .flat_map(|z| effect(zone(z, colours[z as usize - 1])))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(write(&mut sent, zones([1, 1, 1, 1])).len(), 8);
+        assert_eq!(write(&mut sent, zones([1, 2, 1, 1])), vec![
+            zone(2, 2),
+            SET.to_vec()
+        ]);
+
+        // One effect for all zones replaces them, so they're written again
+        assert_eq!(write(&mut sent, effect(zone(0, 1))).len(), 2);
+        assert_eq!(write(&mut sent, zones([1, 2, 1, 1])).len(), 8);
+    }
+
+    #[test]
+    fn per_key_rows() {
+        let mut sent = SentPackets::default();
+        let frame = |colours: [u8; 3]| {
+            (0..3)
+                .map(|g| Packet::State(row(g, colours[g as usize])))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(write(&mut sent, frame([1, 1, 1])).len(), 3);
+        assert_eq!(write(&mut sent, frame([1, 1, 2])), vec![row(
+            2, 2
+        )]);
+
+        // A builtin mode replaces per-key, and per-key the builtin mode
+        assert_eq!(write(&mut sent, effect(zone(0, 1))).len(), 2);
+        assert_eq!(write(&mut sent, frame([1, 1, 2])).len(), 3);
+        assert_eq!(write(&mut sent, effect(zone(0, 1))).len(), 2);
+    }
+
+    #[test]
+    fn coalesce_updates() {
+        let mut sent = SentPackets::default();
+        let start = Instant::now();
+        assert_eq!(sent.queue(effect(zone(0, 1)), start), Flush::Now);
+        assert_eq!(sent.take().len(), 2);
+
+        let soon = start + Duration::from_millis(1);
+        assert_eq!(
+            sent.queue(effect(zone(0, 2)), soon),
+            Flush::After(COALESCE_WINDOW - Duration::from_millis(1))
+        );
+        assert_eq!(sent.queue(effect(zone(0, 3)), soon), Flush::Queued);
+        assert_eq!(sent.queue(effect(zone(1, 3)), soon), Flush::Queued);
+        // Zone 0 is written once with its latest colour, then zone 1
+        assert_eq!(sent.take(), vec![
+            zone(0, 3),
+            SET.to_vec(),
+            zone(1, 3),
+            SET.to_vec()
+        ]);
+
+        let later = start + COALESCE_WINDOW * 2;
+        assert_eq!(sent.queue(effect(zone(1, 4)), later), Flush::Now);
+    }
+}
@@ -0,0 +1,219 @@
+//! Builtin modes drawn by asusd on TUF keyboards whose firmware lacks them.
+//! TUF keyboards are one zone set through `kbd_rgb_mode`, and most take only
+//! some of the modes. A missing mode is saved to the firmware as static
+//! `colour1`, then a task writes a new colour to it every frame while the mode
+//! is current, so TUF keyboards have the same list of modes.
+
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour, Direction, Speed};
+
+use super::config::AuraConfig;
+use super::shuffle::{hue, WeakAura};
+use super::Aura;
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+
+/// The modes which are drawn when the firmware doesn't have them
+pub const EMULATED_MODES: [AuraModeNum; 5] = [
+    AuraModeNum::Breathe,
+    AuraModeNum::RainbowCycle,
+    AuraModeNum::RainbowWave,
+    AuraModeNum::Pulse,
+    AuraModeNum::Flash,
+];
+/// Each `kbd_rgb_mode` write is a WMI call, faster isn't any smoother
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The modes to draw for a keyboard with the `hardware` modes. None if it
+/// doesn't take a static colour, which every frame is written as
+pub fn missing_modes(hardware: &[AuraModeNum]) -> Vec<AuraModeNum> {
+    if !hardware.contains(&AuraModeNum::Static) {
+        return Vec::new();
+    }
+    EMULATED_MODES
+        .into_iter()
+        .filter(|mode| !hardware.contains(mode))
+        .collect()
+}
+
+/// The length of one cycle of the effect
+fn period(speed: Speed) -> Duration {
+    match speed {
+        Speed::Low => Duration::from_secs(8),
+        Speed::Med => Duration::from_secs(4),
+        Speed::High => Duration::from_secs(2),
+    }
+}
+
+fn scale(colour: Colour, level: f32) -> Colour {
+    let level = level.clamp(0.0, 1.0);
+    Colour {
+        r: (colour.r as f32 * level).round() as u8,
+        g: (colour.g as f32 * level).round() as u8,
+        b: (colour.b as f32 * level).round() as u8,
+    }
+}
+
+/// The colour of `effect` at `elapsed` into it
+pub fn colour_at(effect: &AuraEffect, elapsed: Duration) -> Colour {
+    let period = period(effect.speed);
+    let cycles = elapsed.as_secs_f32() / period.as_secs_f32();
+    let phase = cycles.fract();
+    match effect.mode {
+        // Fade in and out of colour1 then colour2
+        AuraModeNum::Breathe => {
+            let colour = if cycles as u64 % 2 == 0 {
+                effect.colour1
+            } else {
+                effect.colour2
+            };
+            scale(colour, (PI * phase).sin().powi(2))
+        }
+        AuraModeNum::RainbowCycle | AuraModeNum::RainbowWave => {
+            let reverse = matches!(effect.direction, Direction::Left | Direction::Down);
+            let degrees = (phase * 360.0) as u32 % 360;
+            hue(if reverse { 359 - degrees } else { degrees })
+        }
+        // A quick rise and slow fall of colour1, twice a cycle
+        AuraModeNum::Pulse => {
+            let phase = (cycles * 2.0).fract();
+            scale(effect.colour1, (1.0 - phase).powi(3))
+        }
+        // colour1 on and off, four times a cycle
+        AuraModeNum::Flash => {
+            if (cycles * 4.0).fract() < 0.5 {
+                effect.colour1
+            } else {
+                Colour { r: 0, g: 0, b: 0 }
+            }
+        }
+        _ => effect.colour1,
+    }
+}
+
+/// The effect to draw now, if the current mode is a drawn one and nothing
+/// else has the keyboard
+fn drawn_effect(config: &AuraConfig) -> Option<AuraEffect> {
+    if !config.emulated_modes.contains(&config.current_mode)
+        || config.multizone_on
+        || config.low_battery_active
+        || config.idle_off
+        || config.per_key_mode_active
+        || config.direct_stream_open
+    {
+        return None;
+    }
+    config.builtins.get(&config.current_mode).cloned()
+}
+
+/// Draw the current mode while it is one the firmware lacks. Changes to the
+/// mode are picked up on the next frame.
+pub fn start_emulation_task(aura: &Aura, supervisor: &Supervisor, path: &str) {
+    let weak = WeakAura::new(aura);
+    supervisor.spawn(&format!("Aura {path}: emulated modes"), move || {
+        let weak = weak.clone();
+        async move {
+            let mut drawing: Option<(AuraEffect, Instant)> = None;
+            let mut failed = false;
+            loop {
+                tokio::time::sleep(FRAME_INTERVAL).await;
+                let Some(aura) = weak.upgrade() else {
+                    debug!("Aura device removed, ending the TUF mode task");
+                    return Ok(());
+                };
+                let Some(effect) = drawn_effect(&*aura.config.lock().await) else {
+                    drawing = None;
+                    continue;
+                };
+                // Start each newly set effect from the beginning
+                let start = match &drawing {
+                    Some((last, start)) if *last == effect => *start,
+                    _ => Instant::now(),
+                };
+                let colour = colour_at(&effect, start.elapsed());
+                drawing = Some((effect, start));
+
+                let Some(backlight) = &aura.backlight else {
+                    return Ok(());
+                };
+                let result = backlight.lock().await.set_kbd_rgb_mode(&[
+                    0, 0, colour.r, colour.g, colour.b, 0,
+                ]);
+                match result {
+                    Ok(()) => {
+                        aura.stream_zone_colours(&[(AuraZone::None, colour)]);
+                        failed = false;
+                    }
+                    Err(e) if !failed => {
+                        warn!("Could not draw the TUF keyboard mode: {e}");
+                        failed = true;
+                    }
+                    Err(_) => {}
+                }
+            }
+            #[allow(unreachable_code)]
+            Ok::<(), RogError>(())
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rog_aura::{AuraEffect, AuraModeNum, Colour, Speed};
+
+    use super::{colour_at, missing_modes};
+
+    #[test]
+    fn modes_to_draw() {
+        assert_eq!(
+            missing_modes(&[
+                AuraModeNum::Static,
+                AuraModeNum::Breathe,
+                AuraModeNum::Pulse
+            ]),
+            vec![
+                AuraModeNum::RainbowCycle,
+                AuraModeNum::RainbowWave,
+                AuraModeNum::Flash
+            ]
+        );
+        assert!(missing_modes(&[]).is_empty());
+    }
+
+    #[test]
+    fn draw_modes() {
+        let red = Colour { r: 200, g: 0, b: 0 };
+        let blue = Colour { r: 0, g: 0, b: 200 };
+        let effect = |mode| AuraEffect {
+            mode,
+            colour1: red,
+            colour2: blue,
+            speed: Speed::Med,
+            ..Default::default()
+        };
+        let ms = Duration::from_millis;
+
+        let breathe = effect(AuraModeNum::Breathe);
+        assert_eq!(colour_at(&breathe, ms(0)), Colour { r: 0, g: 0, b: 0 });
+        assert_eq!(colour_at(&breathe, ms(2000)), red);
+        assert_eq!(colour_at(&breathe, ms(6000)), blue);
+
+        let rainbow = effect(AuraModeNum::RainbowCycle);
+        assert_eq!(colour_at(&rainbow, ms(0)), Colour { r: 255, g: 0, b: 0 });
+        assert_eq!(colour_at(&rainbow, ms(1333)).g, 255);
+
+        let pulse = effect(AuraModeNum::Pulse);
+        assert_eq!(colour_at(&pulse, ms(0)), red);
+        assert!(colour_at(&pulse, ms(1000)).r < 100);
+        assert_eq!(colour_at(&pulse, ms(2000)), red);
+
+        let flash = effect(AuraModeNum::Flash);
+        assert_eq!(colour_at(&flash, ms(100)), red);
+        assert_eq!(colour_at(&flash, ms(600)), Colour { r: 0, g: 0, b: 0 });
+    }
+}
@@ -0,0 +1,187 @@
+//! Turn the keyboard backlight off after a time without input. The timeout is
+//! picked by the platform profile and power source, see
+//! [`AuraConfig::idle_timeout_for`]. Only the backlight brightness is written,
+//! so the saved brightness is what comes back on the next key press.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_platform::power::AsusPower;
+use tokio::io::AsyncReadExt;
+use tokio::sync::Notify;
+
+use super::config::AuraConfig;
+use super::shuffle::WeakAura;
+use super::Aura;
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+
+/// How often the timeout is looked up again while nothing else wakes the task,
+/// which picks up profile, power, and config changes
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The time of the last key press or touchpad movement
+struct InputActivity {
+    last: StdMutex<Instant>,
+    notify: Notify,
+}
+
+impl InputActivity {
+    fn touch(&self) {
+        if let Ok(mut last) = self.last.lock() {
+            *last = Instant::now();
+        }
+        self.notify.notify_waiters();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last
+            .lock()
+            .map(|last| last.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+/// The event nodes of keyboards, touchpads, and mice
+fn input_devices() -> Vec<PathBuf> {
+    let mut devices = Vec::new();
+    let Ok(mut enumerator) = udev::Enumerator::new() else {
+        return devices;
+    };
+    if enumerator.match_subsystem("input").is_err() {
+        return devices;
+    }
+    let Ok(scan) = enumerator.scan_devices() else {
+        return devices;
+    };
+    for device in scan {
+        let Some(node) = device.devnode() else {
+            continue;
+        };
+        if !node.to_string_lossy().starts_with("/dev/input/event") {
+            continue;
+        }
+        let is = |property| device.property_value(property).is_some_and(|v| v == "1");
+        if is("ID_INPUT_KEYBOARD") || is("ID_INPUT_TOUCHPAD") || is("ID_INPUT_MOUSE") {
+            devices.push(node.to_path_buf());
+        }
+    }
+    devices
+}
+
+/// Read `node` until it goes away, marking activity on every event. The
+/// events themselves don't matter, only that there were some.
+fn watch_input(node: PathBuf, activity: Weak<InputActivity>) {
+    tokio::spawn(async move {
+        let mut file = match tokio::fs::File::open(&node).await {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Aura idle timeout: could not open {node:?}: {e}");
+                return;
+            }
+        };
+        let mut buffer = [0; 256];
+        while let Ok(read) = file.read(&mut buffer).await {
+            let Some(activity) = activity.upgrade() else {
+                return;
+            };
+            if read == 0 {
+                break;
+            }
+            activity.touch();
+        }
+        debug!("Aura idle timeout: stopped reading {node:?}");
+    });
+}
+
+fn current_timeout(
+    config: &AuraConfig,
+    platform: Option<&RogPlatform>,
+    power: Option<&AsusPower>,
+) -> Option<Duration> {
+    let profile: PlatformProfile = platform?.get_platform_profile().ok()?.into();
+    // Without a charger to check, assume AC so the laptop behaves as a desktop
+    let on_ac = power.is_none_or(|power| power.get_online().is_ok_and(|online| online == 1));
+    config.idle_timeout_for(on_ac, profile)
+}
+
+/// Watch the input devices and turn the keyboard backlight off and on again
+/// as the idle timeout for the current profile and power source says.
+/// Changes to the config are picked up without restarting the task.
+pub fn start_idle_task(aura: &Aura, supervisor: &Supervisor, path: &str) {
+    if aura.backlight.is_none() {
+        return;
+    }
+    let devices = input_devices();
+    if devices.is_empty() {
+        debug!("No input devices to watch for the Aura idle timeout");
+        return;
+    }
+    let activity = Arc::new(InputActivity {
+        last: StdMutex::new(Instant::now()),
+        notify: Notify::new(),
+    });
+    for node in devices {
+        watch_input(node, Arc::downgrade(&activity));
+    }
+
+    let platform = RogPlatform::new().ok();
+    let power = AsusPower::new().ok();
+    let weak = WeakAura::new(aura);
+    supervisor.spawn(&format!("Aura {path}: idle timeout"), move || {
+        let weak = weak.clone();
+        let activity = activity.clone();
+        let platform = platform.clone();
+        let power = power.clone();
+        async move {
+            loop {
+                let Some(aura) = weak.upgrade() else {
+                    debug!("Aura device removed, ending the idle timeout task");
+                    return Ok(());
+                };
+                let mut config = aura.config.lock().await;
+                let timeout = current_timeout(&config, platform.as_ref(), power.as_ref());
+                let idle_for = activity.idle_for();
+                let should_be_off = timeout.is_some_and(|timeout| idle_for >= timeout);
+
+                if should_be_off != config.idle_off {
+                    let brightness = if should_be_off {
+                        info!(
+                            "No input for {}s, turning the keyboard backlight off",
+                            idle_for.as_secs()
+                        );
+                        0
+                    } else {
+                        debug!("Input again, turning the keyboard backlight back on");
+                        config.brightness.into()
+                    };
+                    match aura.set_brightness(brightness).await {
+                        Ok(()) => config.idle_off = should_be_off,
+                        Err(e) => warn!("Aura idle timeout: {e}"),
+                    }
+                }
+                let idle_off = config.idle_off;
+                drop(config);
+                drop(aura);
+
+                if idle_off {
+                    // Wake on the first input so the backlight comes on straight away
+                    tokio::select! {
+                        _ = activity.notify.notified() => {}
+                        _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                    }
+                } else {
+                    let wait = timeout.map_or(CHECK_INTERVAL, |timeout| {
+                        timeout.saturating_sub(idle_for).min(CHECK_INTERVAL)
+                    });
+                    tokio::time::sleep(wait.max(Duration::from_millis(100))).await;
+                }
+            }
+            #[allow(unreachable_code)]
+            Ok::<(), RogError>(())
+        }
+    });
+}
@@ -0,0 +1,86 @@
+//! The low battery warning, see [`AuraLowBattery`]. The warning effect is
+//! written without changing the saved mode, so putting the current mode back
+//! is the same as writing the config again.
+
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use rog_aura::AuraLowBattery;
+use rog_platform::power::AsusPower;
+
+use super::config::AuraConfig;
+use super::shuffle::WeakAura;
+use super::Aura;
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+
+/// How often the battery is checked. The charge changes slowly enough that
+/// this doesn't need to follow power supply events.
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+
+impl Aura {
+    /// Show or end the low battery effect so that it matches `warn`
+    pub async fn show_low_battery(
+        &self,
+        config: &mut AuraConfig,
+        low: &AuraLowBattery,
+        warn: bool,
+    ) -> Result<(), RogError> {
+        if warn {
+            self.write_effect_and_apply(config, &low.effect).await?;
+        } else {
+            self.write_current_config_mode(config).await?;
+        }
+        config.low_battery_active = warn;
+        Ok(())
+    }
+}
+
+/// Watch the battery and show the low battery effect while it applies.
+/// Changes to the config are picked up without restarting the task.
+pub fn start_low_battery_task(aura: &Aura, supervisor: &Supervisor, path: &str) {
+    let power = match AsusPower::new() {
+        Ok(power) => power,
+        Err(e) => {
+            debug!("No battery for the Aura low battery warning: {e}");
+            return;
+        }
+    };
+    let weak = WeakAura::new(aura);
+    supervisor.spawn(&format!("Aura {path}: low battery"), move || {
+        let weak = weak.clone();
+        let power = power.clone();
+        async move {
+            loop {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                let Some(aura) = weak.upgrade() else {
+                    debug!("Aura device removed, ending the low battery task");
+                    return Ok(());
+                };
+                let mut config = aura.config.lock().await;
+                let low = match &config.low_battery {
+                    Some(low) if low.enabled || config.low_battery_active => low.clone(),
+                    _ => continue,
+                };
+                let (Ok(online), Ok(capacity)) = (power.get_online(), power.get_capacity()) else {
+                    continue;
+                };
+                let should_warn = low.should_warn(online == 1, capacity);
+                if should_warn == config.low_battery_active {
+                    continue;
+                }
+                if should_warn {
+                    info!("Battery at {capacity}%, showing the Aura low battery effect");
+                } else {
+                    info!("Battery at {capacity}%, putting the Aura mode back");
+                }
+                aura.show_low_battery(&mut config, &low, should_warn)
+                    .await
+                    .map_err(|e| warn!("Aura low battery warning: {e}"))
+                    .ok();
+            }
+            #[allow(unreachable_code)]
+            Ok::<(), RogError>(())
+        }
+    });
+}
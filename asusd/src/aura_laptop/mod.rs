@@ -1,18 +1,41 @@
 use std::sync::Arc;
+use std::time::Instant;
 
 use config::AuraConfig;
 use config_traits::StdConfig;
+use delta::{Flush, Packet, SentPackets};
 use futures_util::lock::{Mutex, MutexGuard};
-use log::info;
-use rog_aura::keyboard::{AuraLaptopUsbPackets, LedUsbPackets};
-use rog_aura::usb::{AURA_LAPTOP_LED_APPLY, AURA_LAPTOP_LED_SET};
-use rog_aura::{AuraDeviceType, AuraEffect, LedBrightness, PowerZones, AURA_LAPTOP_LED_MSG_LEN};
-use rog_platform::hid_raw::HidRaw;
+use log::{debug, info};
+use rog_aura::keyboard::{AuraLaptopUsbPackets, LedCode, LedUsbPackets};
+use rog_aura::packets::{ally_power_packet, LED_POWER_ALL_ON};
+use rog_aura::usb::{
+    laptop_effect_packets, laptop_power_packet, tuf_effect, AURA_LAPTOP_LED_INIT,
+    AURA_LAPTOP_LED_SET,
+};
+use rog_aura::{
+    AuraDeviceInfo, AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, BrightnessRestore, Colour,
+    LedBrightness, PowerZones,
+};
+use rog_platform::hid_raw::{DeviceUser, HidRaw};
 use rog_platform::keyboard_led::KeyboardBacklight;
+use shuffle::ColourRng;
+use tokio::sync::watch;
 
 use crate::error::RogError;
+use crate::frame_stream::FrameStream;
 
+pub mod boot;
 pub mod config;
+pub mod delta;
+pub mod emulated;
+pub mod idle;
+pub mod low_battery;
+pub mod profile_lighting;
+pub mod schedule;
+/// Aura over DBus with JSON in place of structures, for scripts
+pub mod scripting;
+pub mod shuffle;
+pub mod stream;
 pub mod trait_impls;
 
 #[derive(Debug, Clone)]
@@ -20,6 +43,14 @@ pub struct Aura {
     pub hid: Option<Arc<Mutex<HidRaw>>>,
     pub backlight: Option<Arc<Mutex<KeyboardBacklight>>>,
     pub config: Arc<Mutex<AuraConfig>>,
+    /// The LED packets the keyboard has, see `delta.rs`
+    pub sent: Arc<Mutex<SentPackets>>,
+    /// The client the `Frame` signal is streamed for
+    pub stream: FrameStream,
+    /// The last frame written to the keyboard while streaming
+    pub frames: Arc<watch::Sender<AuraLaptopUsbPackets>>,
+    /// Sent when the shuffle config changes, see `shuffle.rs`
+    pub shuffle_changed: Arc<watch::Sender<()>>,
 }
 
 impl Aura {
@@ -32,18 +63,98 @@ impl Aura {
         self.config.lock().await
     }
 
+    /// Other processes with the keyboard open, such as OpenRGB. While there
+    /// are any the packets last written are forgotten, as they may have
+    /// written over them
+    pub async fn conflicts(&self) -> Vec<DeviceUser> {
+        let users = match &self.hid {
+            Some(hid_raw) => hid_raw.lock().await.users(),
+            None => Vec::new(),
+        };
+        if !users.is_empty() {
+            self.sent.lock().await.clear();
+        }
+        users
+    }
+
+    pub async fn set_exclusive(&self, exclusive: bool) {
+        if let Some(hid_raw) = &self.hid {
+            hid_raw.lock().await.set_exclusive(exclusive);
+        }
+        self.sent.lock().await.clear();
+    }
+
+    /// Forget the packets last written so the next write is in full. For
+    /// changes a client asks for, as another tool may have changed the
+    /// keyboard since
+    pub async fn resend_all(&self) {
+        self.sent.lock().await.clear();
+    }
+
+    /// The effect for the current mode
+    pub async fn current_effect(&self) -> Option<AuraEffect> {
+        let config = self.config.lock().await;
+        config.builtins.get(&config.current_mode).cloned()
+    }
+
     /// Will lock the internal config and update. If anything else has locked
     /// this in scope then a deadlock can occur.
+    ///
+    /// The brightness is only taken from the keyboard with
+    /// `BrightnessRestore::Hardware`, otherwise the saved brightness is kept.
     pub async fn update_config(&self) -> Result<(), RogError> {
         let mut config = self.config.lock().await;
-        let bright = if let Some(bl) = self.backlight.as_ref() {
-            bl.lock().await.get_brightness().unwrap_or_default()
-        } else {
-            config.brightness.into()
+        let bright = match self.backlight.as_ref() {
+            Some(bl) if config.brightness_restore == BrightnessRestore::Hardware => {
+                bl.lock().await.get_brightness().unwrap_or_default()
+            }
+            _ => config.brightness.into(),
         };
         config.read();
         config.brightness = bright.into();
         config.write();
+        self.shuffle_changed.send_replace(());
+        Ok(())
+    }
+
+    /// Set the brightness chosen by the `brightness_restore` policy, for use
+    /// when asusd starts and after resume
+    pub async fn restore_brightness(&self, config: &AuraConfig) -> Result<(), RogError> {
+        let brightness = match config.brightness_restore {
+            BrightnessRestore::Off => LedBrightness::Off,
+            BrightnessRestore::Saved | BrightnessRestore::Hardware => config.brightness,
+        };
+        info!(
+            "Restoring keyboard brightness {brightness:?} ({:?})",
+            config.brightness_restore
+        );
+        self.set_brightness(brightness.into()).await
+    }
+
+    /// Open the device again, send the init sequence, and write the saved
+    /// power states, mode, and brightness. Recovers a keyboard which stopped
+    /// responding without unplugging it or rebooting.
+    pub async fn reset_controller(&self, config: &mut AuraConfig) -> Result<(), RogError> {
+        let brightness = match self.backlight.as_ref() {
+            Some(bl) => bl.lock().await.get_brightness().ok(),
+            None => None,
+        };
+        self.sent.lock().await.clear();
+        if let Some(hid_raw) = &self.hid {
+            info!("Resetting the Aura controller");
+            let hid_raw = hid_raw.lock().await;
+            hid_raw.reopen()?;
+            for packet in AURA_LAPTOP_LED_INIT {
+                hid_raw.write_bytes(packet)?;
+            }
+        }
+        config.per_key_mode_active = false;
+        self.set_power_states(config).await?;
+        self.write_current_config_mode(config).await?;
+        if self.backlight.is_some() {
+            self.set_brightness(brightness.unwrap_or_else(|| config.brightness.into()))
+                .await?;
+        }
         Ok(())
     }
 
@@ -66,55 +177,116 @@ impl Aura {
                 config.create_multizone_default()?;
             }
 
-            if let Some(multizones) = config.multizone.as_mut() {
-                if let Some(set) = multizones.get(&mode) {
-                    for mode in set.clone() {
-                        self.write_effect_and_apply(config.led_type, &mode).await?;
-                    }
+            if let Some(set) = config.get_multizone(mode).map(|set| set.to_vec()) {
+                for mode in set {
+                    self.write_effect_and_apply(config, &mode).await?;
                 }
             }
         } else {
             let mode = config.current_mode;
             if let Some(effect) = config.builtins.get(&mode).cloned() {
-                self.write_effect_and_apply(config.led_type, &effect)
-                    .await?;
+                self.write_effect_and_apply(config, &effect).await?;
             }
         }
 
         Ok(())
     }
 
-    /// Write the AuraEffect to the device. Will lock `backlight` or `hid`.
+    /// Give the current mode new colours from the shuffle palette and write
+    /// it. The colours are not saved to disk as the next shuffle replaces them.
+    pub async fn shuffle_colours(&self, config: &mut AuraConfig) -> Result<(), RogError> {
+        config.shuffle_colours(&mut ColourRng::from_time());
+        self.write_current_config_mode(config).await
+    }
+
+    /// Write the AuraEffect to the device with the zone brightness of `config`
+    /// applied. Will lock `backlight` or `hid`.
     ///
     /// If per-key or software-mode is active it must be marked as disabled in
-    /// config.
+    /// config. Nothing is written while a direct stream is open, the mode in
+    /// `config` is written when it closes.
     pub async fn write_effect_and_apply(
         &self,
-        dev_type: AuraDeviceType,
+        config: &AuraConfig,
         mode: &AuraEffect,
     ) -> Result<(), RogError> {
-        if matches!(dev_type, AuraDeviceType::LaptopKeyboardTuf) {
+        if config.direct_stream_open {
+            debug!("Direct stream open, holding the {:?} mode", mode.mode);
+            return Ok(());
+        }
+        if matches!(config.led_type, AuraDeviceType::LaptopKeyboardTuf) {
             if let Some(platform) = &self.backlight {
-                let buf = [
-                    1, mode.mode as u8, mode.colour1.r, mode.colour1.g, mode.colour1.b,
-                    mode.speed as u8,
-                ];
-                platform.lock().await.set_kbd_rgb_mode(&buf)?;
+                // The firmware holds colour1 of a mode it lacks, and
+                // `emulated.rs` draws the mode over it
+                let effect = if config.emulated_modes.contains(&mode.mode) {
+                    AuraEffect {
+                        mode: AuraModeNum::Static,
+                        ..mode.clone()
+                    }
+                } else {
+                    mode.clone()
+                };
+                platform
+                    .lock()
+                    .await
+                    .set_kbd_rgb_mode(&tuf_effect(&effect))?;
             }
-        } else if let Some(hid_raw) = &self.hid {
-            let bytes: [u8; AURA_LAPTOP_LED_MSG_LEN] = mode.into();
-            let hid_raw = hid_raw.lock().await;
-            hid_raw.write_bytes(&bytes)?;
-            hid_raw.write_bytes(&AURA_LAPTOP_LED_SET)?;
+        } else if self.hid.is_some() {
+            // Forgets the packets last written if another tool has the device
+            self.conflicts().await;
             // Changes won't persist unless apply is set
-            hid_raw.write_bytes(&AURA_LAPTOP_LED_APPLY)?;
+            let mut packets = Vec::new();
+            for effect in config.zone_effects(mode) {
+                let [packet, set, apply] = laptop_effect_packets(&effect);
+                packets.push(Packet::State(packet.to_vec()));
+                packets.push(Packet::Commit(set.to_vec()));
+                packets.push(Packet::Commit(apply.to_vec()));
+            }
+            self.write_packets(packets).await?;
         } else {
             return Err(RogError::NoAuraKeyboard);
         }
 
+        let colours: Vec<_> = config
+            .zone_effects(mode)
+            .iter()
+            .map(|effect| (effect.zone, effect.colour1))
+            .collect();
+        self.stream_zone_colours(&colours);
         Ok(())
     }
 
+    /// Copy the per-key or zoned `packets` written to the keyboard to the
+    /// frame stream
+    pub fn stream_packets(&self, packets: &AuraLaptopUsbPackets) {
+        if self.stream.enabled() {
+            self.frames.send_replace(packets.clone());
+        }
+    }
+
+    /// Copy the colours of builtin modes written to the keyboard to the
+    /// frame stream, as a zoned frame updating only those zones. The firmware
+    /// animates builtin modes, so this is `colour1` of each.
+    pub fn stream_zone_colours(&self, colours: &[(AuraZone, Colour)]) {
+        if !self.stream.enabled() {
+            return;
+        }
+        self.frames.send_modify(|frame| {
+            let last = LedUsbPackets::from_packets(std::mem::take(frame));
+            let mut zoned = if last.zoned() {
+                last
+            } else {
+                LedUsbPackets::new_zoned(true)
+            };
+            for (zone, colour) in colours {
+                for led in zone_leds(*zone) {
+                    zoned.set(*led, colour.r, colour.g, colour.b);
+                }
+            }
+            *frame = zoned.into();
+        });
+    }
+
     pub async fn set_brightness(&self, value: u8) -> Result<(), RogError> {
         if let Some(backlight) = &self.backlight {
             backlight.lock().await.set_brightness(value)?;
@@ -138,25 +310,12 @@ impl Aura {
             let hid_raw = hid_raw.lock().await;
             if let Some(p) = config.enabled.states.first() {
                 if p.zone == PowerZones::Ally {
-                    let msg = [
-                        0x5d,
-                        0xd1,
-                        0x09,
-                        0x01,
-                        p.new_to_byte() as u8,
-                        0x0,
-                        0x0,
-                    ];
-                    hid_raw.write_bytes(&msg)?;
+                    hid_raw.write_bytes(&ally_power_packet(p.new_to_byte() as u8))?;
                     return Ok(());
                 }
             }
 
-            let bytes = config.enabled.to_bytes(config.led_type);
-            let msg = [
-                0x5d, 0xbd, 0x01, bytes[0], bytes[1], bytes[2], bytes[3],
-            ];
-            hid_raw.write_bytes(&msg)?;
+            hid_raw.write_bytes(&laptop_power_packet(&config.enabled, config.led_type))?;
         }
         Ok(())
     }
@@ -177,23 +336,27 @@ impl Aura {
         let pkt_type = effect[0][1];
         const PER_KEY_TYPE: u8 = 0xbc;
 
-        if let Some(hid_raw) = &self.hid {
-            let hid_raw = hid_raw.lock().await;
+        if self.hid.is_some() {
+            let mut packets = Vec::new();
             if pkt_type != PER_KEY_TYPE {
                 config.per_key_mode_active = false;
-                hid_raw.write_bytes(&effect[0])?;
-                hid_raw.write_bytes(&AURA_LAPTOP_LED_SET)?;
-                // hid_raw.write_bytes(&LED_APPLY)?;
+                packets.push(Packet::State(effect[0].clone()));
+                packets.push(Packet::Commit(AURA_LAPTOP_LED_SET.to_vec()));
+                // packets.push(Packet::Commit(LED_APPLY.to_vec()));
             } else {
                 if !config.per_key_mode_active {
                     let init = LedUsbPackets::get_init_msg();
-                    hid_raw.write_bytes(&init)?;
+                    packets.push(Packet::State(init.to_vec()));
                     config.per_key_mode_active = true;
                 }
                 for row in effect.iter() {
-                    hid_raw.write_bytes(row)?;
+                    packets.push(Packet::State(row.clone()));
                 }
             }
+            self.write_packets(packets).await?;
+            if pkt_type == PER_KEY_TYPE {
+                self.stream_packets(effect);
+            }
         } else if matches!(config.led_type, rog_aura::AuraDeviceType::LaptopKeyboardTuf) {
             if let Some(tuf) = &self.backlight {
                 for row in effect.iter() {
@@ -209,15 +372,77 @@ impl Aura {
         Ok(())
     }
 
+    /// Write the `packets` which change the keyboard. Those coming soon after
+    /// the last write are held for the rest of `delta::COALESCE_WINDOW` and
+    /// written with any which follow, in which case a failed write is logged
+    /// and returned by the next `flush_packets`.
+    async fn write_packets(&self, packets: Vec<Packet>) -> Result<(), RogError> {
+        let Some(hid_raw) = &self.hid else {
+            return Err(RogError::NoAuraKeyboard);
+        };
+        let mut sent = self.sent.lock().await;
+        let flush = sent.queue(packets, Instant::now());
+        match flush {
+            Flush::Now => {
+                drop(sent);
+                delta::flush(hid_raw, &self.sent).await
+            }
+            Flush::After(delay) => {
+                let task = delta::flush_after(delay, hid_raw.clone(), self.sent.clone());
+                sent.set_flush_task(task);
+                Ok(())
+            }
+            Flush::Queued => Ok(()),
+        }
+    }
+
+    /// Identify the keyboard from its USB device, or the LED class device of
+    /// a TUF keyboard
+    pub async fn device_info(&self) -> AuraDeviceInfo {
+        let device_type = self.config.lock().await.led_type;
+        let mut info = AuraDeviceInfo {
+            device_type,
+            protocol: device_type.protocol().to_owned(),
+            ..Default::default()
+        };
+        if let Some(hid_raw) = &self.hid {
+            let hid_raw = hid_raw.lock().await;
+            let usb = hid_raw.usb_identity();
+            info.vendor_id = usb.vendor_id.clone();
+            info.product_id = usb.product_id.clone();
+            info.manufacturer = usb.manufacturer.clone();
+            info.product = usb.product.clone();
+            info.firmware_version = usb.firmware_version().unwrap_or_default();
+            info.path = hid_raw.dev_node().display().to_string();
+        } else if let Some(backlight) = &self.backlight {
+            info.path = backlight.lock().await.path().display().to_string();
+        }
+        info
+    }
+
+    /// Write any packets held by `write_packets` now, after a held write
+    /// already under way. Returns the error of a held write which failed.
+    pub async fn flush_packets(&self) -> Result<(), RogError> {
+        let Some(hid_raw) = &self.hid else {
+            return Ok(());
+        };
+        let task = self.sent.lock().await.take_flush_task();
+        if let Some(task) = task {
+            task.await.ok();
+        }
+        delta::flush(hid_raw, &self.sent).await?;
+        match self.sent.lock().await.take_error() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
     pub async fn fix_ally_power(&mut self) -> Result<(), RogError> {
         if self.config.lock().await.led_type == AuraDeviceType::Ally {
             if let Some(hid_raw) = &self.hid {
                 let mut config = self.config.lock().await;
                 if config.ally_fix.is_none() {
-                    let msg = [
-                        0x5d, 0xbd, 0x01, 0xff, 0xff, 0xff, 0xff,
-                    ];
-                    hid_raw.lock().await.write_bytes(&msg)?;
+                    hid_raw.lock().await.write_bytes(&LED_POWER_ALL_ON)?;
                     info!("Reset Ally power settings to base");
                     config.ally_fix = Some(true);
                 }
@@ -227,3 +452,36 @@ impl Aura {
         Ok(())
     }
 }
+
+/// The LEDs of `zone` in a zoned frame. The lid logo has none.
+fn zone_leds(zone: AuraZone) -> &'static [LedCode] {
+    match zone {
+        AuraZone::None => &[
+            LedCode::ZonedKbLeft,
+            LedCode::ZonedKbLeftMid,
+            LedCode::ZonedKbRightMid,
+            LedCode::ZonedKbRight,
+            LedCode::LightbarLeft,
+            LedCode::LightbarLeftCorner,
+            LedCode::LightbarLeftBottom,
+            LedCode::LightbarRightBottom,
+            LedCode::LightbarRightCorner,
+            LedCode::LightbarRight,
+        ],
+        AuraZone::Key1 => &[LedCode::ZonedKbLeft],
+        AuraZone::Key2 => &[LedCode::ZonedKbLeftMid],
+        AuraZone::Key3 => &[LedCode::ZonedKbRightMid],
+        AuraZone::Key4 => &[LedCode::ZonedKbRight],
+        AuraZone::Logo => &[],
+        AuraZone::BarLeft => &[
+            LedCode::LightbarLeft,
+            LedCode::LightbarLeftCorner,
+            LedCode::LightbarLeftBottom,
+        ],
+        AuraZone::BarRight => &[
+            LedCode::LightbarRight,
+            LedCode::LightbarRightCorner,
+            LedCode::LightbarRightBottom,
+        ],
+    }
+}
@@ -0,0 +1,97 @@
+//! Set the lighting bound to a platform profile when the profile changes, see
+//! [`AuraConfig::use_profile_lighting`]. Only a change of profile sets it, so
+//! a mode picked while in a profile is kept until the next change.
+
+use futures_lite::StreamExt;
+use log::{debug, info, warn};
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+
+use super::config::AuraConfig;
+use super::shuffle::WeakAura;
+use super::Aura;
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+
+impl Aura {
+    /// Make the lighting bound to `profile` the saved mode and write it,
+    /// unless the low battery effect or the idle timeout is showing. Returns
+    /// false if `profile` has no lighting bound to it
+    pub async fn apply_profile_lighting(
+        &self,
+        config: &mut AuraConfig,
+        profile: PlatformProfile,
+    ) -> Result<bool, RogError> {
+        if !config.use_profile_lighting(profile) {
+            return Ok(false);
+        }
+        self.write_used_lighting(config).await?;
+        Ok(true)
+    }
+
+    /// Save and write the lighting put in `config` by
+    /// [`AuraConfig::use_lighting`], unless the low battery effect or the
+    /// idle timeout is showing
+    pub(super) async fn write_used_lighting(
+        &self,
+        config: &mut AuraConfig,
+    ) -> Result<(), RogError> {
+        config.write();
+        if !config.low_battery_active {
+            self.write_current_config_mode(config).await?;
+        }
+        if !config.idle_off && self.backlight.is_some() {
+            self.set_brightness(config.brightness.into()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Watch the platform profile and set the lighting bound to each profile as
+/// it becomes active. Changes to the config are picked up without restarting
+/// the task.
+pub fn start_profile_lighting_task(aura: &Aura, supervisor: &Supervisor, path: &str) {
+    let platform = match RogPlatform::new() {
+        Ok(platform) if platform.has_platform_profile() => platform,
+        _ => {
+            debug!("No platform profile to bind Aura lighting to");
+            return;
+        }
+    };
+    let weak = WeakAura::new(aura);
+    supervisor.spawn(&format!("Aura {path}: profile lighting"), move || {
+        let weak = weak.clone();
+        let platform = platform.clone();
+        async move {
+            let watch = platform.monitor_platform_profile()?;
+            let mut buffer = [0; 32];
+            let mut events = watch.into_event_stream(&mut buffer)?;
+            let current = || -> Option<PlatformProfile> {
+                Some(platform.get_platform_profile().ok()?.into())
+            };
+            let mut last = current();
+            while events.next().await.is_some() {
+                let Some(aura) = weak.upgrade() else {
+                    debug!("Aura device removed, ending the profile lighting task");
+                    return Ok(());
+                };
+                let profile = current();
+                if profile == last {
+                    continue;
+                }
+                last = profile;
+                let Some(profile) = profile else {
+                    continue;
+                };
+                let mut config = aura.config.lock().await;
+                match aura.apply_profile_lighting(&mut config, profile).await {
+                    Ok(true) => {
+                        info!("Platform profile is now {profile:?}, setting its Aura lighting")
+                    }
+                    Ok(false) => {}
+                    Err(e) => warn!("Aura profile lighting: {e}"),
+                }
+            }
+            Ok::<(), RogError>(())
+        }
+    });
+}
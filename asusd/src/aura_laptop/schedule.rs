@@ -0,0 +1,282 @@
+//! The lighting schedule, see [`AuraScheduleStep`]. A step's lighting is set
+//! when its time comes around or its event happens, and when asusd starts it
+//! sets the step whose time passed most recently. Like profile lighting the
+//! lighting becomes the saved mode, so a mode picked in between is kept until
+//! the next step.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use rog_aura::AuraScheduleStep;
+use rog_platform::power::AsusPower;
+
+use super::config::AuraConfig;
+use super::shuffle::WeakAura;
+use super::Aura;
+use crate::error::RogError;
+use crate::local_time::{minutes_since_midnight, weekday};
+use crate::supervisor::Supervisor;
+
+/// How often the time and power source are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const MINUTES_PER_DAY: u32 = 1440;
+const DAY_NAMES: [&str; 7] = [
+    "mon", "tue", "wed", "thu", "fri", "sat", "sun",
+];
+const EVERY_DAY: u8 = 0x7f;
+
+/// When a schedule step applies, parsed from [`AuraScheduleStep::when`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// A bit per day from Monday, and minutes after local midnight
+    At {
+        days: u8,
+        minute: u16,
+    },
+    OnAc,
+    OnBattery,
+}
+
+fn parse_day(s: &str) -> Option<u8> {
+    DAY_NAMES
+        .iter()
+        .position(|day| s.eq_ignore_ascii_case(day))
+        .map(|day| day as u8)
+}
+
+/// Days such as `mon-fri`, `sat,sun`, or `*` for every day
+fn parse_days(s: &str) -> Result<u8, String> {
+    if s == "*" {
+        return Ok(EVERY_DAY);
+    }
+    let mut days = 0;
+    for part in s.split(',') {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let (Some(first), Some(last)) = (parse_day(first), parse_day(last)) else {
+            return Err(format!("{part} is not a day or days such as mon-fri"));
+        };
+        // A range can go past Sunday, such as fri-mon
+        let mut day = first;
+        loop {
+            days |= 1 << day;
+            if day == last {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Ok(days)
+}
+
+/// A time such as `18:30`, as minutes after midnight
+fn parse_time(s: &str) -> Result<u16, String> {
+    let err = || format!("{s} is not a time such as 18:30");
+    let (hours, minutes) = s.split_once(':').ok_or_else(err)?;
+    let hours: u16 = hours.parse().map_err(|_| err())?;
+    let minutes: u16 = minutes.parse().map_err(|_| err())?;
+    if hours > 23 || minutes > 59 {
+        return Err(err());
+    }
+    Ok(hours * 60 + minutes)
+}
+
+impl FromStr for Trigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "@ac" => return Ok(Self::OnAc),
+            "@battery" => return Ok(Self::OnBattery),
+            _ => {}
+        }
+        let mut words = s.split_whitespace();
+        let (days, time) = match (words.next(), words.next(), words.next()) {
+            (Some(time), None, None) => (EVERY_DAY, time),
+            (Some(days), Some(time), None) => (parse_days(days)?, time),
+            _ => {
+                return Err(format!(
+                    "{s} is not a time such as 18:30 or mon-fri 09:00, or @ac or @battery"
+                ))
+            }
+        };
+        Ok(Self::At {
+            days,
+            minute: parse_time(time)?,
+        })
+    }
+}
+
+impl Trigger {
+    /// Minutes since this time last came around, looking back a week. `None`
+    /// for events
+    fn minutes_since(&self, weekday: u8, minute: u16) -> Option<u32> {
+        let Self::At { days, minute: at } = *self else {
+            return None;
+        };
+        (0..=7).find_map(|back: u32| {
+            let day = (u32::from(weekday) + 7 - back % 7) % 7;
+            let since = back * MINUTES_PER_DAY + u32::from(minute);
+            (days & (1 << day) != 0 && since >= u32::from(at)).then(|| since - u32::from(at))
+        })
+    }
+}
+
+/// The index of the time step which came around most recently. Steps which
+/// are off or don't parse are `None`
+pub fn current_step(triggers: &[Option<Trigger>], weekday: u8, minute: u16) -> Option<usize> {
+    triggers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, trigger)| Some((i, (*trigger)?.minutes_since(weekday, minute)?)))
+        .min_by_key(|(_, since)| *since)
+        .map(|(i, _)| i)
+}
+
+impl Aura {
+    /// Make a schedule step's lighting the saved mode and write it
+    async fn apply_schedule_step(
+        &self,
+        config: &mut AuraConfig,
+        step: &AuraScheduleStep,
+    ) -> Result<(), RogError> {
+        config.use_lighting(step.lighting.clone());
+        self.write_used_lighting(config).await
+    }
+}
+
+/// Follow the lighting schedule. Changes to the config are picked up without
+/// restarting the task, and a changed schedule sets its current step again.
+pub fn start_schedule_task(aura: &Aura, supervisor: &Supervisor, path: &str) {
+    let power = AsusPower::new()
+        .map_err(|e| debug!("No power supply for the Aura schedule events: {e}"))
+        .ok();
+    let weak = WeakAura::new(aura);
+    supervisor.spawn(&format!("Aura {path}: schedule"), move || {
+        let weak = weak.clone();
+        let power = power.clone();
+        async move {
+            let mut schedule: Vec<AuraScheduleStep> = Vec::new();
+            let mut last_step = None;
+            let mut last_online = None;
+            loop {
+                let Some(aura) = weak.upgrade() else {
+                    debug!("Aura device removed, ending the schedule task");
+                    return Ok(());
+                };
+                let mut config = aura.config.lock().await;
+                if config.schedule != schedule {
+                    schedule = config.schedule.clone();
+                    last_step = None;
+                }
+                let triggers: Vec<Option<Trigger>> = schedule
+                    .iter()
+                    .map(|step| {
+                        step.lighting
+                            .enabled
+                            .then(|| step.when.parse().ok())
+                            .flatten()
+                    })
+                    .collect();
+
+                let step = current_step(&triggers, weekday(), minutes_since_midnight());
+                let mut apply = step.filter(|_| step != last_step);
+                last_step = step;
+
+                let online = power
+                    .as_ref()
+                    .and_then(|power| power.get_online().ok())
+                    .map(|online| online == 1);
+                if let (Some(was), Some(now)) = (last_online, online) {
+                    if was != now {
+                        let event = if now {
+                            Trigger::OnAc
+                        } else {
+                            Trigger::OnBattery
+                        };
+                        if let Some(i) = triggers.iter().rposition(|t| *t == Some(event)) {
+                            apply = Some(i);
+                        }
+                    }
+                }
+                last_online = online;
+
+                if let Some(step) = apply.and_then(|i| schedule.get(i)) {
+                    info!("Aura schedule: setting the lighting for {}", step.when);
+                    aura.apply_schedule_step(&mut config, step)
+                        .await
+                        .map_err(|e| warn!("Aura schedule: {e}"))
+                        .ok();
+                }
+                drop(config);
+                drop(aura);
+                tokio::time::sleep(CHECK_INTERVAL).await;
+            }
+            #[allow(unreachable_code)]
+            Ok::<(), RogError>(())
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current_step, Trigger};
+
+    #[test]
+    fn parse_triggers() {
+        assert_eq!(
+            "18:30".parse(),
+            Ok(Trigger::At {
+                days: 0x7f,
+                minute: 1110
+            })
+        );
+        assert_eq!(
+            "mon-fri 09:00".parse(),
+            Ok(Trigger::At {
+                days: 0b0011111,
+                minute: 540
+            })
+        );
+        assert_eq!(
+            "sat,Sun 10:00".parse(),
+            Ok(Trigger::At {
+                days: 0b1100000,
+                minute: 600
+            })
+        );
+        assert_eq!(
+            "fri-mon 0:05".parse(),
+            Ok(Trigger::At {
+                days: 0b1110001,
+                minute: 5
+            })
+        );
+        assert_eq!("@AC".parse(), Ok(Trigger::OnAc));
+        assert_eq!(" @battery".parse(), Ok(Trigger::OnBattery));
+        assert!("24:00".parse::<Trigger>().is_err());
+        assert!("someday 10:00".parse::<Trigger>().is_err());
+        assert!("mon 10:00 extra".parse::<Trigger>().is_err());
+    }
+
+    #[test]
+    fn most_recent_step() {
+        let steps: Vec<Option<Trigger>> = [
+            "mon-fri 09:00", "18:00", "23:00", "@ac",
+        ]
+        .iter()
+        .map(|when| when.parse().ok())
+        .collect();
+        // Tuesday
+        assert_eq!(current_step(&steps, 1, 600), Some(0));
+        assert_eq!(current_step(&steps, 1, 1100), Some(1));
+        assert_eq!(current_step(&steps, 1, 1400), Some(2));
+        // Before 09:00 it is still last night's step
+        assert_eq!(current_step(&steps, 1, 60), Some(2));
+        // Saturday morning has no work hours step
+        assert_eq!(current_step(&steps, 5, 600), Some(2));
+        // Off steps are skipped
+        assert_eq!(current_step(&[None, steps[1]], 1, 600), Some(1));
+        assert_eq!(current_step(&[steps[3]], 1, 600), None);
+    }
+}
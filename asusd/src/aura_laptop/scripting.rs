@@ -0,0 +1,300 @@
+//! `xyz.ljones.AuraJson`, the Aura interface with the structured types passed
+//! as JSON strings, for scripting languages where building zvariant
+//! structures is awkward. Input is checked against the schema from `Schema`
+//! before it is applied, and the errors name the field at fault.
+
+use rog_aura::keyboard::LaptopAuraPower;
+use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Direction, PowerZones, Speed};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use zbus::fdo::Error as ZbErr;
+use zbus::message::Header;
+use zbus::object_server::ObjectServer;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{interface, Connection};
+
+use super::trait_impls::AuraZbus;
+use super::Aura;
+use crate::authorization::{authorize_interactive, Action};
+use crate::policy::{Lockdown, Policy};
+
+/// The types which can be read and written as JSON
+const LED_MODE_DATA: &str = "LedModeData";
+const LED_POWER: &str = "LedPower";
+
+fn to_values<T: serde::Serialize>(items: &[T]) -> Vec<Value> {
+    items
+        .iter()
+        .filter_map(|i| serde_json::to_value(i).ok())
+        .collect()
+}
+
+fn colour_schema() -> Value {
+    let channel = json!({ "type": "integer", "minimum": 0, "maximum": 255 });
+    json!({
+        "type": "object",
+        "properties": { "r": channel, "g": channel, "b": channel },
+        "required": ["r", "g", "b"],
+        "additionalProperties": false,
+    })
+}
+
+/// Check `value` against the part of JSON Schema used by the schemas here:
+/// `type`, `enum`, `properties`, `required`, `additionalProperties`, `items`,
+/// `minimum`, and `maximum`
+fn validate(schema: &Value, value: &Value, at: &str) -> Result<(), String> {
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!(
+                "{at}: {value} is not one of {}",
+                Value::from(allowed.clone())
+            ));
+        }
+        return Ok(());
+    }
+    match schema.get("type").and_then(Value::as_str) {
+        Some("object") => {
+            let Some(object) = value.as_object() else {
+                return Err(format!("{at}: expected an object"));
+            };
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, item) in object {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) => validate(property, item, &format!("{at}.{key}"))?,
+                    None => return Err(format!("{at}: unknown field {key}")),
+                }
+            }
+            for key in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !object.contains_key(key) {
+                    return Err(format!("{at}: missing field {key}"));
+                }
+            }
+        }
+        Some("array") => {
+            let Some(items) = value.as_array() else {
+                return Err(format!("{at}: expected an array"));
+            };
+            for (i, item) in items.iter().enumerate() {
+                validate(&schema["items"], item, &format!("{at}[{i}]"))?;
+            }
+        }
+        Some("integer") => {
+            let Some(n) = value.as_i64() else {
+                return Err(format!("{at}: expected an integer"));
+            };
+            let min = schema.get("minimum").and_then(Value::as_i64);
+            let max = schema.get("maximum").and_then(Value::as_i64);
+            if min.is_some_and(|min| n < min) || max.is_some_and(|max| n > max) {
+                return Err(format!("{at}: {n} is out of range"));
+            }
+        }
+        Some("boolean") => {
+            if !value.is_boolean() {
+                return Err(format!("{at}: expected true or false"));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Parse `json` as `T` after checking it against `schema`
+fn parse<T: DeserializeOwned>(schema: &Value, json: &str) -> Result<T, ZbErr> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| ZbErr::InvalidArgs(format!("invalid JSON: {e}")))?;
+    validate(schema, &value, "$").map_err(ZbErr::InvalidArgs)?;
+    serde_json::from_value(value).map_err(|e| ZbErr::InvalidArgs(e.to_string()))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, ZbErr> {
+    serde_json::to_string(value).map_err(|e| ZbErr::Failed(e.to_string()))
+}
+
+pub struct AuraJsonZbus {
+    aura: Aura,
+    /// The path of the `xyz.ljones.Aura` object this is beside
+    path: OwnedObjectPath,
+}
+
+impl AuraJsonZbus {
+    pub fn new(aura: Aura, path: OwnedObjectPath) -> Self {
+        Self { aura, path }
+    }
+
+    /// A JSON Schema for `name`, with the modes and zones limited to those
+    /// this device supports
+    async fn schema_for(&self, name: &str) -> Option<Value> {
+        let config = self.aura.config.lock().await;
+        let support = &config.support_data;
+        match name {
+            LED_MODE_DATA => {
+                let mut modes = to_values(&support.basic_modes);
+                if modes.is_empty() {
+                    modes = to_values(&[AuraModeNum::Static]);
+                }
+                let mut zones = to_values(&support.basic_zones);
+                zones.extend(to_values(&[AuraZone::None]));
+                Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "mode": { "enum": modes },
+                        "zone": { "enum": zones },
+                        "colour1": colour_schema(),
+                        "colour2": colour_schema(),
+                        "speed": { "enum": to_values(&[Speed::Low, Speed::Med, Speed::High]) },
+                        "direction": {
+                            "enum": to_values(&[
+                                Direction::Right,
+                                Direction::Left,
+                                Direction::Up,
+                                Direction::Down,
+                            ])
+                        },
+                    },
+                    "required": ["mode", "zone", "colour1", "colour2", "speed", "direction"],
+                    "additionalProperties": false,
+                }))
+            }
+            LED_POWER => {
+                let mut power_zones = to_values(&support.power_zones);
+                if power_zones.is_empty() {
+                    power_zones = to_values(&[PowerZones::Keyboard]);
+                }
+                Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "states": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "zone": { "enum": power_zones },
+                                    "boot": { "type": "boolean" },
+                                    "awake": { "type": "boolean" },
+                                    "sleep": { "type": "boolean" },
+                                    "shutdown": { "type": "boolean" },
+                                },
+                                "required": ["zone", "boot", "awake", "sleep", "shutdown"],
+                                "additionalProperties": false,
+                            },
+                        },
+                    },
+                    "required": ["states"],
+                    "additionalProperties": false,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    async fn parse<T: DeserializeOwned>(&self, name: &str, json: &str) -> Result<T, ZbErr> {
+        let schema = self
+            .schema_for(name)
+            .await
+            .ok_or_else(|| ZbErr::InvalidArgs(format!("no schema for {name}")))?;
+        parse(&schema, json)
+    }
+}
+
+#[interface(name = "xyz.ljones.AuraJson")]
+impl AuraJsonZbus {
+    /// The JSON Schema for `LedModeData` or `LedPower`
+    async fn schema(&self, name: &str) -> Result<String, ZbErr> {
+        let schema = self.schema_for(name).await.ok_or_else(|| {
+            ZbErr::InvalidArgs(format!("{name} is not one of {LED_MODE_DATA}, {LED_POWER}"))
+        })?;
+        to_json(&schema)
+    }
+
+    /// The current effect, as in `LedModeData` on `xyz.ljones.Aura`
+    async fn led_mode_data(&self) -> Result<String, ZbErr> {
+        let config = self.aura.config.lock().await;
+        let effect = config
+            .builtins
+            .get(&config.current_mode)
+            .ok_or_else(|| ZbErr::Failed("Could not get the current effect".into()))?;
+        to_json(effect)
+    }
+
+    /// Set the effect from JSON matching `Schema("LedModeData")`
+    async fn set_led_mode_data(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        json: &str,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let effect: AuraEffect = self.parse(LED_MODE_DATA, json).await?;
+        let iface = server.interface::<_, AuraZbus>(&self.path).await?;
+        iface.get().await.set_effect(effect).await?;
+        iface
+            .get()
+            .await
+            .led_mode_data_changed(iface.signal_emitter())
+            .await?;
+        Ok(())
+    }
+
+    /// The power states of each zone, as in `LedPower` on `xyz.ljones.Aura`
+    async fn led_power(&self) -> Result<String, ZbErr> {
+        to_json(&self.aura.config.lock().await.enabled)
+    }
+
+    /// Set zone power states from JSON matching `Schema("LedPower")`. Zones
+    /// not listed are left as they are.
+    async fn set_led_power(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        json: &str,
+        #[zbus(object_server)] server: &ObjectServer,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let power: LaptopAuraPower = self.parse(LED_POWER, json).await?;
+        let iface = server.interface::<_, AuraZbus>(&self.path).await?;
+        iface.get().await.set_power(power).await?;
+        iface
+            .get()
+            .await
+            .led_power_changed(iface.signal_emitter())
+            .await?;
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/aura_laptop/scripting.rs"));
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{colour_schema, validate};
+
+    #[test]
+    fn validate_colour() {
+        let schema = colour_schema();
+        assert!(validate(&schema, &json!({ "r": 1, "g": 2, "b": 3 }), "$").is_ok());
+        assert_eq!(
+            validate(&schema, &json!({ "r": 1, "g": 2, "b": 300 }), "$"),
+            Err("$.b: 300 is out of range".to_owned())
+        );
+        assert_eq!(
+            validate(&schema, &json!({ "r": 1, "g": 2 }), "$"),
+            Err("$: missing field b".to_owned())
+        );
+        assert_eq!(
+            validate(&schema, &json!({ "r": 1, "g": 2, "b": 3, "a": 4 }), "$"),
+            Err("$: unknown field a".to_owned())
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Random colours for the current mode, see [`AuraShuffle`]. The colours are
+//! picked daemon-side so the shuffle also happens on start and resume without
+//! a client running.
+
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_util::lock::Mutex;
+use log::{debug, warn};
+use rog_aura::keyboard::AuraLaptopUsbPackets;
+use rog_aura::{AuraShuffle, Colour};
+use rog_platform::hid_raw::HidRaw;
+use rog_platform::keyboard_led::KeyboardBacklight;
+use tokio::sync::watch;
+
+use super::config::AuraConfig;
+use super::delta::SentPackets;
+use super::Aura;
+use crate::error::RogError;
+use crate::frame_stream::FrameStream;
+use crate::supervisor::Supervisor;
+
+/// Shorter intervals are treated as this
+pub const MIN_INTERVAL_SECS: u32 = 10;
+
+/// xorshift64*, seeded from the time. Plenty for picking colours.
+pub struct ColourRng(u64);
+
+impl ColourRng {
+    pub fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Self(seed.max(1))
+    }
+
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A colour from `palette` other than `current` if there is a choice, or
+    /// a fully saturated colour of random hue if the palette is empty
+    pub fn pick(&mut self, palette: &[Colour], current: Colour) -> Colour {
+        if palette.is_empty() {
+            return hue((self.next() % 360) as u32);
+        }
+        let choices: Vec<&Colour> = palette.iter().filter(|c| **c != current).collect();
+        if choices.is_empty() {
+            return palette[0];
+        }
+        *choices[(self.next() % choices.len() as u64) as usize]
+    }
+}
+
+/// The fully saturated colour at `hue` degrees
+pub(super) fn hue(hue: u32) -> Colour {
+    let x = ((hue % 60) * 255 / 60) as u8;
+    let (r, g, b) = match hue / 60 {
+        0 => (255, x, 0),
+        1 => (255 - x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, 255 - x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, 255 - x),
+    };
+    Colour { r, g, b }
+}
+
+/// Held by the interval task in place of [`Aura`] so the task ends once the
+/// device is removed
+#[derive(Clone)]
+pub(super) struct WeakAura {
+    hid: Option<Weak<Mutex<HidRaw>>>,
+    backlight: Option<Weak<Mutex<KeyboardBacklight>>>,
+    config: Weak<Mutex<AuraConfig>>,
+    sent: Weak<Mutex<SentPackets>>,
+    stream: FrameStream,
+    frames: Weak<watch::Sender<AuraLaptopUsbPackets>>,
+    shuffle_changed: Weak<watch::Sender<()>>,
+}
+
+impl WeakAura {
+    pub(super) fn new(aura: &Aura) -> Self {
+        Self {
+            hid: aura.hid.as_ref().map(Arc::downgrade),
+            backlight: aura.backlight.as_ref().map(Arc::downgrade),
+            config: Arc::downgrade(&aura.config),
+            sent: Arc::downgrade(&aura.sent),
+            stream: aura.stream.clone(),
+            frames: Arc::downgrade(&aura.frames),
+            shuffle_changed: Arc::downgrade(&aura.shuffle_changed),
+        }
+    }
+
+    pub(super) fn upgrade(&self) -> Option<Aura> {
+        let hid = match &self.hid {
+            Some(hid) => Some(hid.upgrade()?),
+            None => None,
+        };
+        let backlight = match &self.backlight {
+            Some(backlight) => Some(backlight.upgrade()?),
+            None => None,
+        };
+        Some(Aura {
+            hid,
+            backlight,
+            config: self.config.upgrade()?,
+            sent: self.sent.upgrade()?,
+            stream: self.stream.clone(),
+            frames: self.frames.upgrade()?,
+            shuffle_changed: self.shuffle_changed.upgrade()?,
+        })
+    }
+}
+
+/// The time between shuffles, `None` while the shuffle is off
+fn shuffle_interval(shuffle: Option<&AuraShuffle>) -> Option<Duration> {
+    match shuffle {
+        Some(AuraShuffle {
+            enabled: true,
+            interval_secs,
+            ..
+        }) if *interval_secs > 0 => Some(Duration::from_secs(
+            (*interval_secs).max(MIN_INTERVAL_SECS) as u64,
+        )),
+        _ => None,
+    }
+}
+
+/// Pick a new colour every `interval_secs` of the shuffle config while it is
+/// enabled. While it is off the task waits for [`Aura::shuffle_changed`]
+/// without a timer, and a change starts the interval again.
+pub fn start_interval_task(aura: &Aura, supervisor: &Supervisor, path: &str) {
+    let weak = WeakAura::new(aura);
+    let changed = aura.shuffle_changed.subscribe();
+    supervisor.spawn(&format!("Aura {path}: colour shuffle"), move || {
+        let weak = weak.clone();
+        let mut changed = changed.clone();
+        async move {
+            loop {
+                let Some(aura) = weak.upgrade() else {
+                    debug!("Aura device removed, ending the colour shuffle task");
+                    return Ok(());
+                };
+                let interval = shuffle_interval(aura.config.lock().await.shuffle.as_ref());
+                drop(aura);
+
+                let Some(interval) = interval else {
+                    // Ends once the device is removed
+                    if changed.changed().await.is_err() {
+                        return Ok(());
+                    }
+                    continue;
+                };
+                tokio::select! {
+                    result = changed.changed() => {
+                        if result.is_err() {
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                    _ = tokio::time::sleep(interval) => {}
+                }
+
+                let Some(aura) = weak.upgrade() else {
+                    return Ok(());
+                };
+                let mut config = aura.config.lock().await;
+                if config.low_battery_active {
+                    continue;
+                }
+                aura.shuffle_colours(&mut config)
+                    .await
+                    .map_err(|e| warn!("Aura colour shuffle: {e}"))
+                    .ok();
+            }
+            #[allow(unreachable_code)]
+            Ok::<(), RogError>(())
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rog_aura::{AuraShuffle, Colour};
+
+    use super::{shuffle_interval, ColourRng};
+
+    #[test]
+    fn pick_from_palette() {
+        let red = Colour { r: 255, g: 0, b: 0 };
+        let blue = Colour { r: 0, g: 0, b: 255 };
+        let mut rng = ColourRng::new(42);
+        for _ in 0..20 {
+            assert_eq!(rng.pick(&[red, blue], red), blue);
+        }
+        assert_eq!(rng.pick(&[red], red), red);
+
+        let any = rng.pick(&[], red);
+        assert!([any.r, any.g, any.b].contains(&255));
+    }
+
+    #[test]
+    fn interval_only_while_enabled() {
+        let mut shuffle = AuraShuffle {
+            enabled: true,
+            interval_secs: 3,
+            palette: Vec::new(),
+        };
+        assert_eq!(
+            shuffle_interval(Some(&shuffle)),
+            Some(Duration::from_secs(10))
+        );
+        shuffle.interval_secs = 0;
+        assert_eq!(shuffle_interval(Some(&shuffle)), None);
+        shuffle.interval_secs = 60;
+        shuffle.enabled = false;
+        assert_eq!(shuffle_interval(Some(&shuffle)), None);
+        assert_eq!(shuffle_interval(None), None);
+    }
+}
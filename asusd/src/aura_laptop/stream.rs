@@ -0,0 +1,161 @@
+//! Direct per-key frames streamed over a socket, for effect engines which
+//! write faster than a DBus call per frame allows. A frame is the rows of a
+//! per-key or zoned `LedUsbPackets`, the same packets `DirectAddressingRaw`
+//! takes, written back to back. Frames are written as fast as the keyboard
+//! takes them, and a client writing faster has the frames in between dropped.
+//! Mode changes while the stream is open are saved but not written, and when
+//! the client closes its end the saved builtin mode is written again.
+
+use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixStream as StdUnixStream;
+
+use log::{debug, info, warn};
+use rog_aura::keyboard::AuraLaptopUsbPackets;
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixStream;
+
+use super::shuffle::WeakAura;
+use super::Aura;
+use crate::error::RogError;
+
+/// The length of each packet row in a frame
+pub const ROW_LEN: usize = 64;
+/// The rows of a per-key frame, zoned keyboards take one
+pub const MAX_ROWS: u32 = 11;
+const PER_KEY_HEADER: [u8; 2] = [
+    0x5d, 0xbc,
+];
+
+/// Splits the bytes read from the stream into frames
+#[derive(Debug)]
+pub struct FrameReader {
+    frame_len: usize,
+    buf: Vec<u8>,
+}
+
+impl FrameReader {
+    pub fn new(rows: u32) -> Self {
+        Self {
+            frame_len: rows as usize * ROW_LEN,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Add bytes read from the stream. Gives the last whole frame in them,
+    /// older whole frames are dropped and a partial frame is kept for the
+    /// next read
+    pub fn push(&mut self, bytes: &[u8]) -> Option<Vec<u8>> {
+        self.buf.extend_from_slice(bytes);
+        let whole = self.buf.len() / self.frame_len;
+        if whole == 0 {
+            return None;
+        }
+        let rest = self.buf.split_off(whole * self.frame_len);
+        let frame = self.buf.split_off((whole - 1) * self.frame_len);
+        self.buf = rest;
+        Some(frame)
+    }
+}
+
+/// The frame as packets, `None` if a row isn't a direct mode packet
+pub fn frame_packets(frame: &[u8]) -> Option<AuraLaptopUsbPackets> {
+    frame
+        .chunks(ROW_LEN)
+        .map(|row| row.starts_with(&PER_KEY_HEADER).then(|| row.to_vec()))
+        .collect()
+}
+
+impl Aura {
+    /// Write the saved mode again after direct mode
+    async fn end_direct_stream(&self) -> Result<(), RogError> {
+        let mut config = self.config.lock().await;
+        config.direct_stream_open = false;
+        config.per_key_mode_active = false;
+        self.write_current_config_mode(&mut config).await
+    }
+}
+
+/// Open a stream for frames of `rows` packet rows and give back the client's
+/// end. Only one stream is open at a time, which the caller checks and marks
+/// in `AuraConfig::direct_stream_open`.
+pub fn open_direct_stream(aura: &Aura, rows: u32) -> Result<OwnedFd, RogError> {
+    let (ours, theirs) = StdUnixStream::pair()?;
+    ours.set_nonblocking(true)?;
+    let mut stream = UnixStream::from_std(ours)?;
+    let weak = WeakAura::new(aura);
+    tokio::spawn(async move {
+        info!("Aura direct stream opened");
+        let mut reader = FrameReader::new(rows);
+        let mut buf = vec![0u8; MAX_ROWS as usize * ROW_LEN * 4];
+        loop {
+            let read = match stream.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) => {
+                    warn!("Aura direct stream: {e}");
+                    break;
+                }
+            };
+            let Some(frame) = reader.push(&buf[..read]) else {
+                continue;
+            };
+            let Some(packets) = frame_packets(&frame) else {
+                warn!("Aura direct stream: a frame had a row which isn't a direct mode packet");
+                break;
+            };
+            let Some(aura) = weak.upgrade() else {
+                debug!("Aura device removed, closing the direct stream");
+                return;
+            };
+            let mut config = aura.config.lock().await;
+            if let Err(e) = aura.write_effect_block(&mut config, &packets).await {
+                warn!("Aura direct stream: {e}");
+                break;
+            }
+        }
+        info!("Aura direct stream closed, writing the saved mode");
+        if let Some(aura) = weak.upgrade() {
+            aura.end_direct_stream()
+                .await
+                .map_err(|e| warn!("Aura direct stream: {e}"))
+                .ok();
+        }
+    });
+    Ok(theirs.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame_packets, FrameReader, ROW_LEN};
+
+    #[test]
+    fn keep_latest_frame() {
+        let mut reader = FrameReader::new(2);
+        let frame = |n: u8| vec![n; 2 * ROW_LEN];
+
+        assert_eq!(reader.push(&frame(1)[..100]), None);
+        assert_eq!(reader.push(&frame(1)[100..]), Some(frame(1)));
+
+        let mut bytes = [
+            frame(2),
+            frame(3),
+            frame(4),
+        ]
+        .concat();
+        bytes.truncate(2 * ROW_LEN * 2 + 10);
+        assert_eq!(reader.push(&bytes), Some(frame(3)));
+        assert_eq!(reader.push(&frame(4)[10..]), Some(frame(4)));
+        assert_eq!(reader.push(&[]), None);
+    }
+
+    #[test]
+    fn only_direct_packets() {
+        let mut frame = vec![0u8; 2 * ROW_LEN];
+        frame[0] = 0x5d;
+        frame[1] = 0xbc;
+        assert_eq!(frame_packets(&frame), None);
+        frame[ROW_LEN] = 0x5d;
+        frame[ROW_LEN + 1] = 0xbc;
+        assert_eq!(frame_packets(&frame).map(|rows| rows.len()), Some(2));
+    }
+}
@@ -1,18 +1,45 @@
 use std::collections::BTreeMap;
+use std::time::Duration;
 
 use config_traits::StdConfig;
 use log::{debug, error, info, warn};
-use rog_aura::keyboard::{AuraLaptopUsbPackets, LaptopAuraPower};
-use rog_aura::{AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, LedBrightness, PowerZones};
+use rog_aura::keyboard::{AuraLaptopUsbPackets, KeyLayout, LaptopAuraPower, LayoutInfo};
+use rog_aura::{
+    AuraDeviceInfo, AuraDeviceType, AuraEffect, AuraLowBattery, AuraModeNum, AuraProfileLighting,
+    AuraScheduleStep, AuraShuffle, AuraZone, BrightnessRestore, Colour, LedBrightness, PowerZones,
+};
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use tokio::sync::watch;
 use zbus::fdo::Error as ZbErr;
+use zbus::message::Header;
 use zbus::object_server::SignalEmitter;
-use zbus::zvariant::OwnedObjectPath;
+use zbus::zvariant::{OwnedFd, OwnedObjectPath};
 use zbus::{interface, Connection};
 
+use super::emulated::start_emulation_task;
+use super::idle::start_idle_task;
+use super::low_battery::start_low_battery_task;
+use super::profile_lighting::start_profile_lighting_task;
+use super::schedule::{start_schedule_task, Trigger};
+use super::scripting::AuraJsonZbus;
+use super::shuffle::{self, ColourRng};
+use super::stream::{open_direct_stream, MAX_ROWS};
 use super::Aura;
+use crate::authorization::{authorize, authorize_interactive, Action};
 use crate::error::RogError;
+use crate::frame_stream::FrameStream;
+use crate::hooks::{run_hooks, HookEvent};
+use crate::policy::{Lockdown, Policy};
+use crate::sleep_hooks::{HookOrder, SleepHooks};
+use crate::supervisor::Supervisor;
 use crate::{CtrlTask, Reloadable};
 
+/// Where the keyboard layout files are installed
+const LAYOUT_DATA_DIR: &str = "/usr/share/rog-gui/";
+
+/// The most `Frame` signals sent a second is the inverse of this
+const FRAME_SIGNAL_INTERVAL: Duration = Duration::from_millis(33);
+
 pub const AURA_ZBUS_NAME: &str = "Aura";
 pub const AURA_ZBUS_PATH: &str = "/xyz/ljones";
 
@@ -29,24 +56,260 @@ impl AuraZbus {
         connection: &Connection,
         // _signal_ctx: SignalEmitter<'static>,
         path: OwnedObjectPath,
+        sleep_hooks: &SleepHooks,
+        supervisor: &Supervisor,
     ) -> Result<(), RogError> {
         // let task = zbus.clone();
         // let signal_ctx = signal_ctx.clone();
+        let exclusive = self.0.config.lock().await.exclusive;
+        self.0.set_exclusive(exclusive).await;
+        for user in self.0.conflicts().await {
+            warn!("{user} also has the Aura device at {path} open, it may overwrite LED changes");
+        }
         self.reload()
             .await
             .unwrap_or_else(|err| warn!("Controller error: {}", err));
+        self.register_sleep_hooks(sleep_hooks, path.as_str()).await;
+        shuffle::start_interval_task(&self.0, supervisor, path.as_str());
+        start_low_battery_task(&self.0, supervisor, path.as_str());
+        start_idle_task(&self.0, supervisor, path.as_str());
+        start_profile_lighting_task(&self.0, supervisor, path.as_str());
+        start_schedule_task(&self.0, supervisor, path.as_str());
+        if !self.0.config.lock().await.emulated_modes.is_empty() {
+            start_emulation_task(&self.0, supervisor, path.as_str());
+        }
+        Self::start_frame_signal(
+            self.0.frames.subscribe(),
+            self.0.stream.clone(),
+            SignalEmitter::new(connection, path.clone())?.into_owned(),
+            supervisor,
+            &format!("Aura {path}: frame signal"),
+        );
+        let json = AuraJsonZbus::new(self.0.clone(), path.clone());
         connection
             .object_server()
             .at(path.clone(), self)
             .await
             .map_err(|e| error!("Couldn't add server at path: {path}, {e:?}"))
             .ok();
+        connection
+            .object_server()
+            .at(path.clone(), json)
+            .await
+            .map_err(|e| error!("Couldn't add AuraJson at path: {path}, {e:?}"))
+            .ok();
         // TODO: skip this until we keep handles to tasks so they can be killed
         // task.create_tasks(signal_ctx).await
         Ok(())
     }
 }
 
+impl AuraZbus {
+    /// Send each streamed frame in the `Frame` signal to the streaming client
+    /// only, skipping any written within `FRAME_SIGNAL_INTERVAL` of the last
+    /// one sent. Ends when the device is removed.
+    fn start_frame_signal(
+        frames: watch::Receiver<AuraLaptopUsbPackets>,
+        stream: FrameStream,
+        signal_ctxt: SignalEmitter<'static>,
+        supervisor: &Supervisor,
+        name: &str,
+    ) {
+        supervisor.spawn(name, move || {
+            let mut frames = frames.clone();
+            let stream = stream.clone();
+            let signal_ctxt = signal_ctxt.clone();
+            async move {
+                while frames.changed().await.is_ok() {
+                    let frame = frames.borrow_and_update().clone();
+                    let Some(signal_ctxt) = stream.emitter(&signal_ctxt) else {
+                        continue;
+                    };
+                    Self::frame(&signal_ctxt, frame)
+                        .await
+                        .map_err(|e| warn!("Aura frame signal: {e}"))
+                        .ok();
+                    tokio::time::sleep(FRAME_SIGNAL_INTERVAL).await;
+                }
+                Ok::<(), RogError>(())
+            }
+        });
+    }
+
+    /// Apply and save `effect` if the mode and zone are supported. The
+    /// `LedModeData` property, also used by [`AuraJsonZbus`].
+    pub(crate) async fn set_effect(&self, effect: AuraEffect) -> Result<(), ZbErr> {
+        // A zone keeps its own mode on keyboards which can show one per zone
+        if effect.zone != AuraZone::None && self.0.config.lock().await.support_data.per_zone_modes {
+            return self.apply_zone_effects(vec![effect]).await;
+        }
+        self.0.resend_all().await;
+        let mut config = self.0.config.lock().await;
+        if !config.support_data.basic_modes.contains(&effect.mode)
+            || effect.zone != AuraZone::None
+                && !config.support_data.basic_zones.contains(&effect.zone)
+        {
+            return Err(ZbErr::NotSupported(format!(
+                "The Aura effect is not supported: {effect:?}"
+            )));
+        }
+
+        self.0.write_effect_and_apply(&config, &effect).await?;
+        if config.brightness == LedBrightness::Off {
+            config.brightness = LedBrightness::Med;
+        }
+        self.0.set_brightness(config.brightness.into()).await?;
+        run_hooks(HookEvent::Aura(effect.clone()));
+        config.set_builtin(effect);
+        config.write();
+
+        Ok(())
+    }
+
+    /// Apply and save the power states of the zones in `options`. The
+    /// `LedPower` property, also used by [`AuraJsonZbus`].
+    pub(crate) async fn set_power(&self, options: LaptopAuraPower) -> Result<(), ZbErr> {
+        let mut config = self.0.config.lock().await;
+        for opt in options.states {
+            let zone = opt.zone;
+            for config in config.enabled.states.iter_mut() {
+                if config.zone == zone {
+                    *config = opt;
+                }
+            }
+        }
+        config.write();
+        Ok(self.0.set_power_states(&config).await.map_err(|e| {
+            warn!("{}", e);
+            e
+        })?)
+    }
+
+    /// Apply and save `mode` and `colour` on `zones` only, for the logo and
+    /// lightbar methods. `name` is used in the error if the keyboard can't
+    /// set the zones apart.
+    async fn set_zones(
+        &self,
+        zones: Vec<AuraZone>,
+        mode: AuraModeNum,
+        colour: Colour,
+        name: &str,
+    ) -> Result<(), ZbErr> {
+        self.0.resend_all().await;
+        let mut config = self.0.config.lock().await;
+        if zones.is_empty() {
+            return Err(ZbErr::NotSupported(format!(
+                "The {name} can't be set apart from the keyboard"
+            )));
+        }
+        if !config.support_data.basic_modes.contains(&mode) {
+            return Err(ZbErr::NotSupported(format!(
+                "The Aura mode is not supported: {mode:?}"
+            )));
+        }
+        let effect = AuraEffect {
+            mode,
+            colour1: colour,
+            ..AuraEffect::default_with_mode(mode)
+        };
+        config.set_zone_effect(&zones, &effect);
+        config.write();
+        if !config.low_battery_active {
+            self.0.write_current_config_mode(&mut config).await?;
+        }
+        Ok(())
+    }
+
+    /// Apply and save `effects` on their zones, keeping the effects of the
+    /// other zones and switching to multizone. The `SetZoneEffects` method
+    async fn apply_zone_effects(&self, effects: Vec<AuraEffect>) -> Result<(), ZbErr> {
+        self.0.resend_all().await;
+        let mut config = self.0.config.lock().await;
+        config
+            .support_data
+            .check_zone_effects(&effects)
+            .map_err(|e| ZbErr::NotSupported(e.to_string()))?;
+        for effect in &effects {
+            config.set_zone_effect(&[effect.zone], effect);
+        }
+        if !config.low_battery_active {
+            self.0.write_current_config_mode(&mut config).await?;
+        }
+        if config.brightness == LedBrightness::Off {
+            config.brightness = LedBrightness::Med;
+        }
+        self.0.set_brightness(config.brightness.into()).await?;
+        for effect in effects {
+            run_hooks(HookEvent::Aura(effect));
+        }
+        config.write();
+        Ok(())
+    }
+
+    /// Turn the lights of a power zone on or off in every state
+    async fn set_power_zone_enabled(&self, zone: PowerZones, enabled: bool) -> Result<(), ZbErr> {
+        let mut config = self.0.config.lock().await;
+        let Some(state) = config.enabled.states.iter_mut().find(|s| s.zone == zone) else {
+            return Err(ZbErr::NotSupported(format!(
+                "The keyboard has no power zone {zone:?}"
+            )));
+        };
+        state.boot = enabled;
+        state.awake = enabled;
+        state.sleep = enabled;
+        state.shutdown = enabled;
+        config.write();
+        Ok(self.0.set_power_states(&config).await?)
+    }
+
+    /// Save the brightness before sleep, restore brightness and mode after.
+    /// `owner` should be the dbus path so the hooks can be removed with the
+    /// device.
+    async fn register_sleep_hooks(&self, sleep_hooks: &SleepHooks, owner: &str) {
+        let inner = self.0.clone();
+        sleep_hooks
+            .on_pre_sleep(owner, HookOrder::Devices, move || {
+                let inner = inner.clone();
+                async move {
+                    inner
+                        .update_config()
+                        .await
+                        .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                        .ok();
+                }
+            })
+            .await;
+
+        let inner = self.0.clone();
+        sleep_hooks
+            .on_post_resume(owner, HookOrder::Devices, move || {
+                let inner = inner.clone();
+                async move {
+                    info!("CtrlKbdLedTask reloading brightness and modes");
+                    // The keyboard may have lost what it was sent
+                    inner.sent.lock().await.clear();
+                    let mut config = inner.config.lock().await;
+                    if config.shuffle_enabled() {
+                        config.shuffle_colours(&mut ColourRng::from_time());
+                    }
+                    if inner.backlight.is_some() {
+                        inner
+                            .restore_brightness(&config)
+                            .await
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
+                    }
+                    inner
+                        .write_current_config_mode(&mut config)
+                        .await
+                        .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                        .ok();
+                }
+            })
+            .await;
+    }
+}
+
 /// The main interface for changing, reading, or notfying
 ///
 /// LED commands are split between Brightness, Modes, Per-Key
@@ -69,13 +332,244 @@ impl AuraZbus {
 
     /// Set the keyboard brightness level (0-3)
     #[zbus(property)]
-    async fn set_brightness(&mut self, brightness: LedBrightness) -> Result<(), ZbErr> {
+    async fn set_brightness(
+        &mut self,
+        brightness: LedBrightness,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        if let Some(bl) = self.0.backlight.as_ref() {
+            bl.lock().await.set_brightness(brightness.into())?;
+            // Saved for `BrightnessRestore::Saved`
+            let mut config = self.0.config.lock().await;
+            config.brightness = brightness;
+            config.write();
+            return Ok(());
+        }
+        Err(ZbErr::Failed("No sysfs brightness control".to_string()))
+    }
+
+    /// The keyboard brightness as a percentage, spread over the raw levels on
+    /// a perceptual curve so 50% looks half as bright. Reads back the
+    /// percentage of the level in use, which may differ from the one set.
+    #[zbus(property)]
+    async fn brightness_percent(&self) -> Result<u8, ZbErr> {
+        let curve = self.0.config.lock().await.support_data.brightness_curve();
+        if let Some(bl) = self.0.backlight.as_ref() {
+            let bl = bl.lock().await;
+            let max_level = bl.get_max_brightness().unwrap_or(3);
+            return Ok(curve.percent(bl.get_brightness()?, max_level));
+        }
+        Err(ZbErr::Failed("No sysfs brightness control".to_string()))
+    }
+
+    #[zbus(property)]
+    async fn set_brightness_percent(
+        &mut self,
+        percent: u8,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let curve = self.0.config.lock().await.support_data.brightness_curve();
         if let Some(bl) = self.0.backlight.as_ref() {
-            return Ok(bl.lock().await.set_brightness(brightness.into())?);
+            let level = {
+                let bl = bl.lock().await;
+                let level = curve.level(percent, bl.get_max_brightness().unwrap_or(3));
+                bl.set_brightness(level)?;
+                level
+            };
+            // Saved for `BrightnessRestore::Saved`
+            let mut config = self.0.config.lock().await;
+            config.brightness = level.into();
+            config.write();
+            return Ok(());
         }
         Err(ZbErr::Failed("No sysfs brightness control".to_string()))
     }
 
+    /// The percent brightness of each zone of a multizone keyboard or
+    /// lightbar, relative to the keyboard brightness. Applied to the colours
+    /// of the effects written, so modes with their own colours are not
+    /// dimmed. Empty if the keyboard has no zones.
+    #[zbus(property)]
+    async fn zone_brightness(&self) -> Vec<(AuraZone, u8)> {
+        let config = self.0.config.lock().await;
+        config
+            .support_data
+            .basic_zones
+            .iter()
+            .map(|zone| (*zone, config.zone_percent(*zone)))
+            .collect()
+    }
+
+    #[zbus(property)]
+    async fn set_zone_brightness(
+        &mut self,
+        zones: Vec<(AuraZone, u8)>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.0.resend_all().await;
+        let mut config = self.0.config.lock().await;
+        for (zone, percent) in &zones {
+            if !config.support_data.basic_zones.contains(zone) {
+                return Err(ZbErr::NotSupported(format!(
+                    "The keyboard has no zone {zone:?}"
+                )));
+            }
+            if *percent > 100 {
+                return Err(ZbErr::InvalidArgs(format!(
+                    "Zone brightness must be 0-100, got {percent}"
+                )));
+            }
+        }
+        for (zone, percent) in zones {
+            if percent == 100 {
+                config.zone_brightness.remove(&zone);
+            } else {
+                config.zone_brightness.insert(zone, percent);
+            }
+        }
+        config.write();
+        if !config.low_battery_active {
+            self.0.write_current_config_mode(&mut config).await?;
+        }
+        Ok(())
+    }
+
+    /// Set the mode and colour of the lid logo only, keeping the keyboard and
+    /// lightbar as they are. Switches the current mode to multizone.
+    async fn set_logo(
+        &self,
+        mode: AuraModeNum,
+        colour: Colour,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let zones = self.0.config.lock().await.logo_zones();
+        self.set_zones(zones, mode, colour, "logo").await?;
+        self.led_mode_data_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
+    /// Set the mode and colour of the lightbar only, keeping the keyboard and
+    /// logo as they are. Switches the current mode to multizone.
+    async fn set_lightbar(
+        &self,
+        mode: AuraModeNum,
+        colour: Colour,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let zones = self.0.config.lock().await.lightbar_zones();
+        self.set_zones(zones, mode, colour, "lightbar").await?;
+        self.led_mode_data_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
+    /// Turn the lid logo on or off in every power state. The same as setting
+    /// the logo zone of `LedPower`.
+    async fn set_logo_enabled(
+        &self,
+        enabled: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.set_power_zone_enabled(PowerZones::Logo, enabled)
+            .await?;
+        self.led_power_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
+    /// Turn the lightbar on or off in every power state. The same as setting
+    /// the lightbar zone of `LedPower`.
+    async fn set_lightbar_enabled(
+        &self,
+        enabled: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.set_power_zone_enabled(PowerZones::Lightbar, enabled)
+            .await?;
+        self.led_power_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
+    /// Other processes with the device open, such as OpenRGB or rogauracore,
+    /// as "name (pid N)". They can overwrite the mode set here or make writes
+    /// fail.
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn conflicts(&self) -> Vec<String> {
+        self.0
+            .conflicts()
+            .await
+            .iter()
+            .map(|user| user.to_string())
+            .collect()
+    }
+
+    /// Retry writes which fail while other RGB software holds the device,
+    /// opening it again each time
+    #[zbus(property)]
+    async fn exclusive_mode(&self) -> bool {
+        self.0.config.lock().await.exclusive
+    }
+
+    #[zbus(property)]
+    async fn set_exclusive_mode(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        exclusive: bool,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.0.set_exclusive(exclusive).await;
+        let mut config = self.0.config.lock().await;
+        config.exclusive = exclusive;
+        config.write();
+        Ok(())
+    }
+
+    /// Which brightness is set when asusd starts and after resume
+    #[zbus(property)]
+    async fn brightness_restore(&self) -> BrightnessRestore {
+        self.0.config.lock().await.brightness_restore
+    }
+
+    #[zbus(property)]
+    async fn set_brightness_restore(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        restore: BrightnessRestore,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        config.brightness_restore = restore;
+        config.write();
+        Ok(())
+    }
+
     /// Total levels of brightness available
     #[zbus(property)]
     async fn supported_brightness(&self) -> Vec<LedBrightness> {
@@ -100,12 +594,67 @@ impl AuraZbus {
         Ok(config.support_data.basic_zones.clone())
     }
 
+    /// True if each zone of `SupportedBasicZones` can have a different mode
+    /// with `SetZoneEffects`, for clients to show a per-zone editor. Otherwise
+    /// the zones can only differ in colour
+    #[zbus(property)]
+    async fn per_zone_modes(&self) -> bool {
+        self.0.config.lock().await.support_data.per_zone_modes
+    }
+
+    /// The effect of each zone while the keyboard is in multizone, empty
+    /// while one effect is on the whole keyboard
+    #[zbus(property)]
+    async fn zone_effects(&self) -> Vec<AuraEffect> {
+        let config = self.0.config.lock().await;
+        if !config.multizone_on {
+            return Vec::new();
+        }
+        config
+            .get_multizone(config.current_mode)
+            .map(<[AuraEffect]>::to_vec)
+            .unwrap_or_default()
+    }
+
+    /// Set the effect of each zone given, keeping the others. The effects
+    /// must all have the same mode unless `PerZoneModes` is true
+    async fn set_zone_effects(
+        &self,
+        effects: Vec<AuraEffect>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.apply_zone_effects(effects).await?;
+        self.zone_effects_changed(&ctxt).await.ok();
+        self.led_mode_data_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
     #[zbus(property)]
     async fn supported_power_zones(&self) -> Result<Vec<PowerZones>, ZbErr> {
         let config = self.0.config.lock().await;
         Ok(config.support_data.power_zones.clone())
     }
 
+    /// The physical layout of the keyboard LEDs: the LED name, row, position
+    /// and size of each key, and the zones if the keyboard is zoned
+    async fn keyboard_layout(&self) -> Result<LayoutInfo, ZbErr> {
+        let support_data = self.0.config.lock().await.support_data.clone();
+        let name = support_data.layout_name.clone();
+        let layout = KeyLayout::find_layout(support_data, LAYOUT_DATA_DIR.into())
+            .map_err(|e| ZbErr::Failed(format!("Could not load layout {name}: {e:?}")))?;
+        Ok(layout.layout_info(&name))
+    }
+
+    /// The USB IDs, firmware version, and packet protocol of the keyboard, to
+    /// help triage model specific issues
+    async fn device_info(&self) -> AuraDeviceInfo {
+        self.0.device_info().await
+    }
+
     /// The current mode data
     #[zbus(property)]
     async fn led_mode(&self) -> Result<AuraModeNum, ZbErr> {
@@ -124,7 +673,15 @@ impl AuraZbus {
     /// On success the aura config file is read to refresh cached values, then
     /// the effect is stored and config written to disk.
     #[zbus(property)]
-    async fn set_led_mode(&mut self, num: AuraModeNum) -> Result<(), ZbErr> {
+    async fn set_led_mode(
+        &mut self,
+        num: AuraModeNum,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.0.resend_all().await;
         let mut config = self.0.config.lock().await;
         config.current_mode = num;
         self.0.write_current_config_mode(&mut config).await?;
@@ -132,6 +689,9 @@ impl AuraZbus {
             config.brightness = LedBrightness::Med;
         }
         self.0.set_brightness(config.brightness.into()).await?;
+        if let Some(effect) = config.builtins.get(&num) {
+            run_hooks(HookEvent::Aura(effect.clone()));
+        }
         config.write();
         Ok(())
     }
@@ -156,34 +716,269 @@ impl AuraZbus {
     /// On success the aura config file is read to refresh cached values, then
     /// the effect is stored and config written to disk.
     #[zbus(property)]
-    async fn set_led_mode_data(&mut self, effect: AuraEffect) -> Result<(), ZbErr> {
+    async fn set_led_mode_data(
+        &mut self,
+        effect: AuraEffect,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.set_effect(effect).await
+    }
+
+    /// Get the data set for every mode available
+    async fn all_mode_data(&self) -> BTreeMap<AuraModeNum, AuraEffect> {
+        let config = self.0.config.lock().await;
+        config.builtins.clone()
+    }
+
+    /// New random colours for the current mode when asusd starts, after
+    /// resume, and optionally on an interval
+    #[zbus(property)]
+    async fn shuffle(&self) -> AuraShuffle {
+        let config = self.0.config.lock().await;
+        config.shuffle.clone().unwrap_or_default()
+    }
+
+    /// Turning the shuffle on also picks a new colour
+    #[zbus(property)]
+    async fn set_shuffle(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        shuffle: AuraShuffle,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
         let mut config = self.0.config.lock().await;
-        if !config.support_data.basic_modes.contains(&effect.mode)
-            || effect.zone != AuraZone::None
-                && !config.support_data.basic_zones.contains(&effect.zone)
+        let enabled = shuffle.enabled;
+        config.shuffle = Some(shuffle);
+        config.write();
+        self.0.shuffle_changed.send_replace(());
+        if enabled {
+            self.0.shuffle_colours(&mut config).await?;
+        }
+        Ok(())
+    }
+
+    /// An effect shown in place of the current mode while on battery and the
+    /// charge is at or below the threshold
+    #[zbus(property)]
+    async fn low_battery(&self) -> AuraLowBattery {
+        let config = self.0.config.lock().await;
+        config.low_battery.clone().unwrap_or_default()
+    }
+
+    /// Turning the warning off while it shows puts the current mode back
+    #[zbus(property)]
+    async fn set_low_battery(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        low: AuraLowBattery,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        if !config.support_data.basic_modes.contains(&low.effect.mode) {
+            return Err(ZbErr::NotSupported(format!(
+                "The Aura effect is not supported: {:?}",
+                low.effect
+            )));
+        }
+        config.low_battery = Some(low.clone());
+        config.write();
+        if config.low_battery_active {
+            self.0
+                .show_low_battery(&mut config, &low, low.enabled)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Seconds without input before the keyboard backlight turns off while on
+    /// AC, for each platform profile. Profiles without an entry, or 0, keep
+    /// it on
+    #[zbus(property)]
+    async fn idle_timeout_on_ac(&self) -> BTreeMap<PlatformProfile, u32> {
+        self.0.config.lock().await.idle_timeout_on_ac.clone()
+    }
+
+    #[zbus(property)]
+    async fn set_idle_timeout_on_ac(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        timeouts: BTreeMap<PlatformProfile, u32>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        config.idle_timeout_on_ac = timeouts;
+        config.write();
+        Ok(())
+    }
+
+    /// As `IdleTimeoutOnAc`, while on battery
+    #[zbus(property)]
+    async fn idle_timeout_on_battery(&self) -> BTreeMap<PlatformProfile, u32> {
+        self.0.config.lock().await.idle_timeout_on_battery.clone()
+    }
+
+    #[zbus(property)]
+    async fn set_idle_timeout_on_battery(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        timeouts: BTreeMap<PlatformProfile, u32>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        config.idle_timeout_on_battery = timeouts;
+        config.write();
+        Ok(())
+    }
+
+    /// The mode, colour, and brightness set when the platform profile changes
+    /// to each profile. A binding changed for the active profile is set
+    /// straight away.
+    #[zbus(property)]
+    async fn profile_lighting(&self) -> BTreeMap<PlatformProfile, AuraProfileLighting> {
+        self.0.config.lock().await.profile_lighting.clone()
+    }
+
+    #[zbus(property)]
+    async fn set_profile_lighting(
+        &mut self,
+        bindings: BTreeMap<PlatformProfile, AuraProfileLighting>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        for (profile, lighting) in &bindings {
+            if !config
+                .support_data
+                .basic_modes
+                .contains(&lighting.effect.mode)
+            {
+                return Err(ZbErr::NotSupported(format!(
+                    "The Aura mode for {profile:?} is not supported: {:?}",
+                    lighting.effect.mode
+                )));
+            }
+        }
+        let active: Option<PlatformProfile> = RogPlatform::new()
+            .and_then(|platform| platform.get_platform_profile())
+            .ok()
+            .map(Into::into);
+        let changed =
+            active.filter(|active| config.profile_lighting.get(active) != bindings.get(active));
+        config.profile_lighting = bindings;
+        config.write();
+        if let Some(active) = changed {
+            self.0.apply_profile_lighting(&mut config, active).await?;
+        }
+        Ok(())
+    }
+
+    /// The lighting schedule, steps which set a mode, colour, and brightness
+    /// at a time of day or when the power source changes
+    #[zbus(property)]
+    async fn schedule(&self) -> Vec<AuraScheduleStep> {
+        self.0.config.lock().await.schedule.clone()
+    }
+
+    /// Add a schedule step, or replace the step with the same `when`. The
+    /// schedule's current step is set again
+    async fn set_schedule_step(
+        &mut self,
+        step: AuraScheduleStep,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        step.when.parse::<Trigger>().map_err(ZbErr::InvalidArgs)?;
+        let mut config = self.0.config.lock().await;
+        if !config
+            .support_data
+            .basic_modes
+            .contains(&step.lighting.effect.mode)
         {
             return Err(ZbErr::NotSupported(format!(
-                "The Aura effect is not supported: {effect:?}"
+                "The Aura mode for {} is not supported: {:?}",
+                step.when, step.lighting.effect.mode
             )));
         }
+        match config.schedule.iter_mut().find(|s| s.when == step.when) {
+            Some(existing) => *existing = step,
+            None => config.schedule.push(step),
+        }
+        config.write();
+        drop(config);
+        self.schedule_changed(&ctxt).await.ok();
+        Ok(())
+    }
 
-        self.0
-            .write_effect_and_apply(config.led_type, &effect)
-            .await?;
-        if config.brightness == LedBrightness::Off {
-            config.brightness = LedBrightness::Med;
+    /// Remove the schedule step with this `when`
+    async fn remove_schedule_step(
+        &mut self,
+        when: String,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        let len = config.schedule.len();
+        config.schedule.retain(|s| s.when != when);
+        if config.schedule.len() == len {
+            return Err(ZbErr::InvalidArgs(format!(
+                "There is no schedule step for {when}"
+            )));
         }
-        self.0.set_brightness(config.brightness.into()).await?;
-        config.set_builtin(effect);
         config.write();
+        drop(config);
+        self.schedule_changed(&ctxt).await.ok();
+        Ok(())
+    }
 
+    /// Give the current mode a new random colour now, from the shuffle
+    /// palette if one is set. Works with the shuffle off.
+    async fn shuffle_colour(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.0.resend_all().await;
+        let mut config = self.0.config.lock().await;
+        self.0.shuffle_colours(&mut config).await?;
+        drop(config);
+        self.led_mode_data_changed(&ctxt).await.ok();
         Ok(())
     }
 
-    /// Get the data set for every mode available
-    async fn all_mode_data(&self) -> BTreeMap<AuraModeNum, AuraEffect> {
-        let config = self.0.config.lock().await;
-        config.builtins.clone()
+    /// Send the controller init sequence and write the saved state again, to
+    /// recover a keyboard stuck after resume
+    async fn reset_controller(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.0.config.lock().await;
+        self.0.reset_controller(&mut config).await?;
+        Ok(())
     }
 
     // As property doesn't work for AuraPowerDev (complexity of serialization?)
@@ -198,95 +993,116 @@ impl AuraZbus {
     ///
     /// For Modern ROG devices the "enabled" flag is ignored.
     #[zbus(property)]
-    async fn set_led_power(&mut self, options: LaptopAuraPower) -> Result<(), ZbErr> {
-        let mut config = self.0.config.lock().await;
-        for opt in options.states {
-            let zone = opt.zone;
-            for config in config.enabled.states.iter_mut() {
-                if config.zone == zone {
-                    *config = opt;
-                }
-            }
-        }
-        config.write();
-        Ok(self.0.set_power_states(&config).await.map_err(|e| {
-            warn!("{}", e);
-            e
-        })?)
+    async fn set_led_power(
+        &mut self,
+        options: LaptopAuraPower,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.set_power(options).await
     }
 
     /// On machine that have some form of either per-key keyboard or per-zone
     /// this can be used to write custom effects over dbus. The input is a
     /// nested `Vec<Vec<8>>` where `Vec<u8>` is a raw USB packet
-    async fn direct_addressing_raw(&self, data: AuraLaptopUsbPackets) -> Result<(), ZbErr> {
+    async fn direct_addressing_raw(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        data: AuraLaptopUsbPackets,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.0.resend_all().await;
         let mut config = self.0.config.lock().await;
         self.0.write_effect_block(&mut config, &data).await?;
         Ok(())
     }
+
+    /// Open a stream for direct mode frames of `rows` 64 byte packet rows,
+    /// such as the rows of `DirectAddressingRaw`. Frames written to the socket
+    /// are written to the keyboard as fast as it takes them, and the saved
+    /// mode comes back when the socket is closed. One stream is open at a time
+    async fn open_direct_stream(
+        &self,
+        rows: u32,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<OwnedFd, ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        if !(1..=MAX_ROWS).contains(&rows) {
+            return Err(ZbErr::InvalidArgs(format!(
+                "A frame is 1 to {MAX_ROWS} rows, not {rows}"
+            )));
+        }
+        let mut config = self.0.config.lock().await;
+        if config.direct_stream_open {
+            return Err(ZbErr::Failed("A direct stream is already open".to_string()));
+        }
+        let fd = open_direct_stream(&self.0, rows)?;
+        config.direct_stream_open = true;
+        Ok(fd.into())
+    }
+
+    /// While set each frame written to the keyboard is also sent in the
+    /// `Frame` signal, for previewing effects. Only root or the user of the
+    /// foreground session may set it, and it is cleared when the client which
+    /// set it leaves the bus. Not saved
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn stream_frames(&self) -> bool {
+        self.0.stream.enabled()
+    }
+
+    #[zbus(property)]
+    async fn set_stream_frames(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> Result<(), ZbErr> {
+        self.0.stream.set_enabled(conn, &header, enabled).await
+    }
+
+    /// The LEDs written to the keyboard, as the packets `DirectAddressingRaw`
+    /// takes. A builtin mode is sent as a zoned frame of the `colour1` of each
+    /// zone, as the firmware animates it. Only sent while `StreamFrames` is set
+    #[zbus(signal)]
+    async fn frame(signal_ctxt: &SignalEmitter<'_>, data: AuraLaptopUsbPackets)
+        -> zbus::Result<()>;
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/aura_laptop/trait_impls.rs"));
+
 impl CtrlTask for AuraZbus {
     fn zbus_path() -> &'static str {
         "/xyz/ljones"
     }
 
     async fn create_tasks(&self, _: SignalEmitter<'static>) -> Result<(), RogError> {
-        let inner1 = self.0.clone();
         let inner3 = self.0.clone();
         self.create_sys_event_tasks(
-            move |sleeping| {
-                let inner1 = inner1.clone();
-                // unwrap as we want to bomb out of the task
+            move |shutting_down| {
+                let inner3 = inner3.clone();
                 async move {
-                    if !sleeping {
-                        info!("CtrlKbdLedTask reloading brightness and modes");
-                        if let Some(backlight) = &inner1.backlight {
-                            backlight
-                                .lock()
-                                .await
-                                .set_brightness(inner1.config.lock().await.brightness.into())
-                                .map_err(|e| {
-                                    error!("CtrlKbdLedTask: {e}");
-                                    e
-                                })
-                                .unwrap();
-                        }
-                        let mut config = inner1.config.lock().await;
-                        inner1
-                            .write_current_config_mode(&mut config)
-                            .await
-                            .map_err(|e| {
-                                error!("CtrlKbdLedTask: {e}");
-                                e
-                            })
-                            .unwrap();
-                    } else if sleeping {
-                        inner1
+                    if shutting_down {
+                        // Keep the brightness for the next start
+                        inner3
                             .update_config()
                             .await
-                            .map_err(|e| {
-                                error!("CtrlKbdLedTask: {e}");
-                                e
-                            })
-                            .unwrap();
-                    }
-                }
-            },
-            move |_shutting_down| {
-                let inner3 = inner3.clone();
-                async move {
-                    info!("CtrlKbdLedTask reloading brightness and modes");
-                    if let Some(backlight) = &inner3.backlight {
-                        // unwrap as we want to bomb out of the task
-                        backlight
-                            .lock()
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
+                    } else if inner3.backlight.is_some() {
+                        info!("CtrlKbdLedTask reloading brightness");
+                        let config = inner3.config.lock().await;
+                        inner3
+                            .restore_brightness(&config)
                             .await
-                            .set_brightness(inner3.config.lock().await.brightness.into())
-                            .map_err(|e| {
-                                error!("CtrlKbdLedTask: {e}");
-                                e
-                            })
-                            .unwrap();
+                            .map_err(|e| error!("CtrlKbdLedTask: {e}"))
+                            .ok();
                     }
                 }
             },
@@ -331,7 +1147,22 @@ impl Reloadable for AuraZbus {
         self.0.fix_ally_power().await?;
         debug!("reloading keyboard mode");
         let mut config = self.0.lock_config().await;
+        if config.shuffle_enabled() {
+            config.shuffle_colours(&mut ColourRng::from_time());
+        }
+        // The low battery task shows the warning again if it still applies
+        config.low_battery_active = false;
+        // The brightness is restored below, the idle task turns it off again
+        config.idle_off = false;
         self.0.write_current_config_mode(&mut config).await?;
+        if self.0.backlight.is_some() {
+            debug!("reloading brightness");
+            self.0
+                .restore_brightness(&config)
+                .await
+                .map_err(|err| warn!("{err}"))
+                .ok();
+        }
         debug!("reloading power states");
         self.0
             .set_power_states(&config)
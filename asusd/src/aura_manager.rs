@@ -11,18 +11,26 @@ use futures_lite::future::block_on;
 use futures_util::lock::Mutex;
 use log::{debug, error, info, warn};
 use mio::{Events, Interest, Poll, Token};
+use rog_aura::AuraEffect;
 use rog_platform::error::PlatformError;
 use rog_platform::hid_raw::HidRaw;
+use rog_platform::simulate;
 use udev::{Device, MonitorBuilder};
 use zbus::zvariant::{ObjectPath, OwnedObjectPath};
 use zbus::Connection;
 
 use crate::aura_anime::trait_impls::AniMeZbus;
+use crate::aura_laptop::scripting::AuraJsonZbus;
 use crate::aura_laptop::trait_impls::AuraZbus;
 use crate::aura_scsi::trait_impls::ScsiZbus;
 use crate::aura_slash::trait_impls::SlashZbus;
+use crate::aura_sync::AuraSync;
 use crate::aura_types::DeviceHandle;
+use crate::config::Subsystems;
 use crate::error::RogError;
+use crate::scenes::Scenes;
+use crate::sleep_hooks::SleepHooks;
+use crate::supervisor::Supervisor;
 use crate::ASUS_ZBUS_PATH;
 
 const MOD_NAME: &str = "aura";
@@ -52,6 +60,21 @@ pub fn filename_partial(parent: &Device) -> Option<OwnedObjectPath> {
     None
 }
 
+/// Identifies one physical device for its config file: the USB serial if it
+/// has one, or else the USB port it is on
+pub fn device_key(usb_device: &Device) -> Option<String> {
+    let key = usb_device
+        .attribute_value("serial")
+        .map(|serial| serial.to_string_lossy().trim().to_owned())
+        .filter(|serial| !serial.is_empty())
+        .unwrap_or_else(|| usb_device.sysname().to_string_lossy().into_owned());
+    let key: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    (!key.is_empty()).then_some(key)
+}
+
 fn dbus_path_for_dev(parent: &Device) -> Option<OwnedObjectPath> {
     if let Some(filename) = filename_partial(parent) {
         return Some(
@@ -66,6 +89,14 @@ fn dbus_path_for_tuf() -> OwnedObjectPath {
     ObjectPath::from_str_unchecked(&format!("{ASUS_ZBUS_PATH}/{MOD_NAME}/tuf")).into()
 }
 
+fn dbus_path_for_simulated() -> OwnedObjectPath {
+    ObjectPath::from_str_unchecked(&format!(
+        "{ASUS_ZBUS_PATH}/{MOD_NAME}/{}_sim",
+        simulate::SIM_AURA_PROD_ID
+    ))
+    .into()
+}
+
 fn dbus_path_for_slash() -> OwnedObjectPath {
     ObjectPath::from_str_unchecked(&format!("{ASUS_ZBUS_PATH}/{MOD_NAME}/slash")).into()
 }
@@ -90,20 +121,27 @@ fn dev_prop_matches(dev: &Device, prop: &str, value: &str) -> bool {
 /// Each controller within should track its dbus path so it can be removed if
 /// required.
 pub struct AsusDevice {
-    device: DeviceHandle,
-    dbus_path: OwnedObjectPath,
+    pub(crate) device: DeviceHandle,
+    pub(crate) dbus_path: OwnedObjectPath,
 }
 
 pub struct DeviceManager {
     _dbus_connection: Connection,
+    devices: Arc<Mutex<Vec<AsusDevice>>>,
 }
 
 impl DeviceManager {
     async fn init_hid_devices(
         connection: &Connection,
         device: Device,
+        sleep_hooks: &SleepHooks,
+        subsystems: Subsystems,
+        supervisor: &Supervisor,
     ) -> Result<Vec<AsusDevice>, RogError> {
         let mut devices = Vec::new();
+        if !(subsystems.aura || subsystems.anime || subsystems.slash) {
+            return Ok(devices);
+        }
         if let Some(usb_device) = device.parent_with_subsystem_devtype("usb", "usb_device")? {
             if let Some(usb_id) = usb_device.attribute_value("idProduct") {
                 if let Some(vendor_id) = usb_device.attribute_value("idVendor") {
@@ -116,61 +154,99 @@ impl DeviceManager {
                     // 1. Generate an interface path
                     // 2. Create the device
                     // Use the top-level endpoint, not the parent
-                    if let Ok(hidraw) = HidRaw::from_device(device) {
+                    let dev_node = device
+                        .devnode()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let hidraw = match HidRaw::from_device(device) {
+                        Ok(hidraw) => {
+                            supervisor.set_warning(&dev_node, None).await;
+                            Ok(hidraw)
+                        }
+                        Err(e @ PlatformError::HidPermission(..)) => {
+                            // Retried when udev reports the permissions changed
+                            warn!("{e}");
+                            supervisor.set_warning(&dev_node, Some(e.to_string())).await;
+                            Err(e)
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Ok(hidraw) = hidraw {
                         debug!("Testing device {usb_id:?}");
                         let dev = Arc::new(Mutex::new(hidraw));
                         // SLASH DEVICE
-                        if let Ok(dev_type) = DeviceHandle::new_slash_hid(
-                            dev.clone(),
-                            usb_id.to_str().unwrap_or_default(),
-                        )
-                        .await
-                        {
-                            if let DeviceHandle::Slash(slash) = dev_type.clone() {
-                                let path =
-                                    dbus_path_for_dev(&usb_device).unwrap_or(dbus_path_for_slash());
-                                let ctrl = SlashZbus::new(slash);
-                                ctrl.start_tasks(connection, path.clone()).await.unwrap();
-                                devices.push(AsusDevice {
-                                    device: dev_type,
-                                    dbus_path: path,
-                                });
+                        if subsystems.slash {
+                            if let Ok(dev_type) = DeviceHandle::new_slash_hid(
+                                dev.clone(),
+                                usb_id.to_str().unwrap_or_default(),
+                            )
+                            .await
+                            {
+                                if let DeviceHandle::Slash(slash) = dev_type.clone() {
+                                    let path = dbus_path_for_dev(&usb_device)
+                                        .unwrap_or(dbus_path_for_slash());
+                                    let ctrl = SlashZbus::new(slash);
+                                    ctrl.start_tasks(connection, path.clone()).await.unwrap();
+                                    devices.push(AsusDevice {
+                                        device: dev_type,
+                                        dbus_path: path,
+                                    });
+                                }
                             }
                         }
                         // ANIME MATRIX DEVICE
-                        if let Ok(dev_type) = DeviceHandle::maybe_anime_hid(
-                            dev.clone(),
-                            usb_id.to_str().unwrap_or_default(),
-                        )
-                        .await
-                        {
-                            if let DeviceHandle::AniMe(anime) = dev_type.clone() {
-                                let path =
-                                    dbus_path_for_dev(&usb_device).unwrap_or(dbus_path_for_anime());
-                                let ctrl = AniMeZbus::new(anime);
-                                ctrl.start_tasks(connection, path.clone()).await.unwrap();
-                                devices.push(AsusDevice {
-                                    device: dev_type,
-                                    dbus_path: path,
-                                });
+                        if subsystems.anime {
+                            if let Ok(dev_type) = DeviceHandle::maybe_anime_hid(
+                                dev.clone(),
+                                usb_id.to_str().unwrap_or_default(),
+                            )
+                            .await
+                            {
+                                if let DeviceHandle::AniMe(anime) = dev_type.clone() {
+                                    let path = dbus_path_for_dev(&usb_device)
+                                        .unwrap_or(dbus_path_for_anime());
+                                    let ctrl = AniMeZbus::new(anime);
+                                    ctrl.start_tasks(
+                                        connection,
+                                        path.clone(),
+                                        sleep_hooks,
+                                        supervisor,
+                                    )
+                                    .await
+                                    .unwrap();
+                                    devices.push(AsusDevice {
+                                        device: dev_type,
+                                        dbus_path: path,
+                                    });
+                                }
                             }
                         }
                         // AURA LAPTOP DEVICE
-                        if let Ok(dev_type) = DeviceHandle::maybe_laptop_aura(
-                            Some(dev),
-                            usb_id.to_str().unwrap_or_default(),
-                        )
-                        .await
-                        {
-                            if let DeviceHandle::Aura(aura) = dev_type.clone() {
-                                let path =
-                                    dbus_path_for_dev(&usb_device).unwrap_or(dbus_path_for_tuf());
-                                let ctrl = AuraZbus::new(aura);
-                                ctrl.start_tasks(connection, path.clone()).await.unwrap();
-                                devices.push(AsusDevice {
-                                    device: dev_type,
-                                    dbus_path: path,
-                                });
+                        if subsystems.aura {
+                            if let Ok(dev_type) = DeviceHandle::maybe_laptop_aura(
+                                Some(dev),
+                                usb_id.to_str().unwrap_or_default(),
+                                device_key(&usb_device).as_deref(),
+                            )
+                            .await
+                            {
+                                if let DeviceHandle::Aura(aura) = dev_type.clone() {
+                                    let path = dbus_path_for_dev(&usb_device)
+                                        .unwrap_or(dbus_path_for_tuf());
+                                    let ctrl = AuraZbus::new(aura);
+                                    ctrl.start_tasks(
+                                        connection,
+                                        path.clone(),
+                                        sleep_hooks,
+                                        supervisor,
+                                    )
+                                    .await
+                                    .unwrap();
+                                    devices.push(AsusDevice {
+                                        device: dev_type,
+                                        dbus_path: path,
+                                    });
+                                }
                             }
                         }
                     }
@@ -181,11 +257,25 @@ impl DeviceManager {
     }
 
     /// To be called on daemon startup
-    async fn init_all_hid(connection: &Connection) -> Result<Vec<AsusDevice>, RogError> {
+    async fn init_all_hid(
+        connection: &Connection,
+        sleep_hooks: &SleepHooks,
+        subsystems: Subsystems,
+        supervisor: &Supervisor,
+    ) -> Result<Vec<AsusDevice>, RogError> {
         // track and ensure we use only one hidraw per prod_id
         // let mut interfaces = HashSet::new();
         let mut devices: Vec<AsusDevice> = Vec::new();
 
+        if let Some(path) = simulate::path(simulate::AURA_HIDRAW) {
+            if subsystems.aura {
+                devices.extend(
+                    Self::init_simulated_aura(connection, &path, sleep_hooks, supervisor).await?,
+                );
+            }
+            return Ok(devices);
+        }
+
         let mut enumerator = udev::Enumerator::new().map_err(|err| {
             warn!("{}", err);
             PlatformError::Udev("enumerator failed".into(), err)
@@ -200,12 +290,46 @@ impl DeviceManager {
             .scan_devices()
             .map_err(|e| PlatformError::IoPath("enumerator".to_owned(), e))?
         {
-            devices.append(&mut Self::init_hid_devices(connection, device).await?);
+            devices.append(
+                &mut Self::init_hid_devices(
+                    connection, device, sleep_hooks, subsystems, supervisor,
+                )
+                .await?,
+            );
         }
 
         Ok(devices)
     }
 
+    /// The laptop keyboard of the simulated hardware, see
+    /// [`rog_platform::simulate`]
+    async fn init_simulated_aura(
+        connection: &Connection,
+        path: &std::path::Path,
+        sleep_hooks: &SleepHooks,
+        supervisor: &Supervisor,
+    ) -> Result<Option<AsusDevice>, RogError> {
+        let hidraw = HidRaw::simulated(path, simulate::SIM_AURA_PROD_ID)?;
+        let dev_type = DeviceHandle::maybe_laptop_aura(
+            Some(Arc::new(Mutex::new(hidraw))),
+            simulate::SIM_AURA_PROD_ID,
+            None,
+        )
+        .await?;
+        if let DeviceHandle::Aura(aura) = dev_type.clone() {
+            info!("Simulating a laptop keyboard, writes go to {path:?}");
+            let path = dbus_path_for_simulated();
+            let ctrl = AuraZbus::new(aura);
+            ctrl.start_tasks(connection, path.clone(), sleep_hooks, supervisor)
+                .await?;
+            return Ok(Some(AsusDevice {
+                device: dev_type,
+                dbus_path: path,
+            }));
+        }
+        Ok(None)
+    }
+
     async fn init_scsi(
         connection: &Connection,
         device: &Device,
@@ -275,16 +399,37 @@ impl DeviceManager {
         Ok(devices)
     }
 
-    pub async fn find_all_devices(connection: &Connection) -> Vec<AsusDevice> {
+    /// The current effect of the laptop keyboard, if there is one, for
+    /// external devices set to follow it
+    async fn keyboard_effect(devices: &Mutex<Vec<AsusDevice>>) -> Option<AuraEffect> {
+        for dev in devices.lock().await.iter() {
+            if let DeviceHandle::Aura(aura) = &dev.device {
+                // An Ally or other Aura device is not the keyboard
+                if aura.config.lock().await.led_type.is_laptop_keyboard() {
+                    return aura.current_effect().await;
+                }
+            }
+        }
+        None
+    }
+
+    pub async fn find_all_devices(
+        connection: &Connection,
+        sleep_hooks: &SleepHooks,
+        subsystems: Subsystems,
+        supervisor: &Supervisor,
+    ) -> Vec<AsusDevice> {
         let mut devices: Vec<AsusDevice> = Vec::new();
         // HID first, always
-        if let Ok(devs) = &mut Self::init_all_hid(connection).await {
+        if let Ok(devs) =
+            &mut Self::init_all_hid(connection, sleep_hooks, subsystems, supervisor).await
+        {
             devices.append(devs);
         }
         // USB after, need to check if HID picked something up and if so, skip it
-        let mut do_anime = true;
-        let mut do_slash = true;
-        let mut do_kb_backlight = true;
+        let mut do_anime = subsystems.anime;
+        let mut do_slash = subsystems.slash;
+        let mut do_kb_backlight = subsystems.aura;
         for dev in devices.iter() {
             if matches!(dev.device, DeviceHandle::Slash(_)) {
                 do_slash = false;
@@ -320,7 +465,7 @@ impl DeviceManager {
                     let path = dbus_path_for_anime();
                     let ctrl = AniMeZbus::new(anime);
                     if ctrl
-                        .start_tasks(connection, path.clone())
+                        .start_tasks(connection, path.clone(), sleep_hooks, supervisor)
                         .await
                         .map_err(|e| error!("Failed to start tasks: {e:?}, not adding this device"))
                         .is_ok()
@@ -347,11 +492,13 @@ impl DeviceManager {
             );
             if product_name.contains("TUF") || product_family.contains("TUF") {
                 info!("TUF laptop, try using sysfs backlight control");
-                if let Ok(dev_type) = DeviceHandle::maybe_laptop_aura(None, "tuf").await {
+                if let Ok(dev_type) = DeviceHandle::maybe_laptop_aura(None, "tuf", None).await {
                     if let DeviceHandle::Aura(aura) = dev_type.clone() {
                         let path = dbus_path_for_tuf();
                         let ctrl = AuraZbus::new(aura);
-                        ctrl.start_tasks(connection, path.clone()).await.unwrap();
+                        ctrl.start_tasks(connection, path.clone(), sleep_hooks, supervisor)
+                            .await
+                            .unwrap();
                         devices.push(AsusDevice {
                             device: dev_type,
                             dbus_path: path,
@@ -361,20 +508,48 @@ impl DeviceManager {
             }
         }
 
-        if let Ok(devs) = &mut Self::init_all_scsi(connection).await {
-            devices.append(devs);
+        if subsystems.aura {
+            if let Ok(devs) = &mut Self::init_all_scsi(connection).await {
+                devices.append(devs);
+            }
         }
 
         devices
     }
 
-    pub async fn new(connection: Connection) -> Result<Self, RogError> {
+    /// Find and add the Aura, AniMe, Slash, and SCSI devices, and watch for
+    /// them being plugged in or removed. Those disabled in `subsystems` are
+    /// left alone. hidraw nodes which couldn't be opened are reported on
+    /// `xyz.ljones.Health` and tried again when udev changes them.
+    pub async fn new(
+        connection: Connection,
+        sleep_hooks: SleepHooks,
+        subsystems: Subsystems,
+        supervisor: Supervisor,
+    ) -> Result<Self, RogError> {
         let conn_copy = connection.clone();
-        let devices = Self::find_all_devices(&conn_copy).await;
+        let devices =
+            Self::find_all_devices(&conn_copy, &sleep_hooks, subsystems, &supervisor).await;
         info!("Found {} valid devices on startup", devices.len());
         let devices = Arc::new(Mutex::new(devices));
+        let sync = AuraSync::new(devices.clone());
+        if subsystems.aura {
+            sync.clone()
+                .start(&connection)
+                .await
+                .map_err(|e| error!("Couldn't add Aura Sync: {e:?}"))
+                .ok();
+        }
+        if subsystems.aura || subsystems.anime {
+            Scenes::new(devices.clone())
+                .start(&connection)
+                .await
+                .map_err(|e| error!("Couldn't add Scenes: {e:?}"))
+                .ok();
+        }
         let manager = Self {
             _dbus_connection: connection,
+            devices: devices.clone(),
         };
 
         // TODO: The /sysfs/ LEDs don't cause events, so they need to be manually
@@ -408,6 +583,9 @@ impl DeviceManager {
 
                     let devices = devices.clone();
                     let conn_copy = conn_copy.clone();
+                    let sleep_hooks = sleep_hooks.clone();
+                    let supervisor = supervisor.clone();
+                    let sync = sync.clone();
                     block_on(async move {
                         // SCSCI devs
                         if subsys == "block" {
@@ -442,7 +620,7 @@ impl DeviceManager {
                                             .await?;
                                     }
                                 }
-                            } else if action == "add" {
+                            } else if action == "add" && subsystems.aura {
                                 let evdev = event.device();
                                 if let Some(serial) = evdev.property_value("ID_SERIAL_SHORT") {
                                     let serial = serial.to_string_lossy().to_string();
@@ -450,6 +628,19 @@ impl DeviceManager {
                                     if let Some(new_devs) =
                                         Self::init_scsi(&conn_copy, &evdev, path).await
                                     {
+                                        if let DeviceHandle::Scsi(scsi) = &new_devs.device {
+                                            if let Some(effect) =
+                                                Self::keyboard_effect(&devices).await
+                                            {
+                                                scsi.follow_keyboard(&effect)
+                                                    .await
+                                                    .map_err(|e| {
+                                                        error!("Couldn't follow keyboard: {e:?}")
+                                                    })
+                                                    .ok();
+                                            }
+                                        }
+                                        sync.device_added(&new_devs).await;
                                         devices.lock().await.append(&mut vec![new_devs]);
                                     }
                                 }
@@ -484,8 +675,13 @@ impl DeviceManager {
                                         for index in removals.iter().rev() {
                                             let dev = devices.lock().await.remove(*index);
                                             let path = path.clone();
+                                            sleep_hooks.remove(path.as_str()).await;
                                             let res = match dev.device {
                                                 DeviceHandle::Aura(_) => {
+                                                    conn_copy
+                                                        .object_server()
+                                                        .remove::<AuraJsonZbus, _>(&path)
+                                                        .await?;
                                                     conn_copy
                                                         .object_server()
                                                         .remove::<AuraZbus, _>(&path)
@@ -514,13 +710,27 @@ impl DeviceManager {
                                             info!("AuraManager removed: {path:?}, {res}");
                                         }
                                     }
-                                } else if action == "add" {
+                                } else if action == "add" || action == "change" {
                                     let evdev = event.device();
-                                    if let Ok(mut new_devs) =
-                                        Self::init_hid_devices(&conn_copy, evdev)
-                                            .await
-                                            .map_err(|e| error!("Couldn't add new device: {e:?}"))
+                                    // A change is only of interest if the node
+                                    // couldn't be opened before, such as after
+                                    // the udev rules were fixed
+                                    let node = evdev
+                                        .devnode()
+                                        .map(|n| n.to_string_lossy().to_string())
+                                        .unwrap_or_default();
+                                    if action == "change" && !supervisor.has_warning(&node).await {
+                                        return Ok(());
+                                    }
+                                    if let Ok(mut new_devs) = Self::init_hid_devices(
+                                        &conn_copy, evdev, &sleep_hooks, subsystems, &supervisor,
+                                    )
+                                    .await
+                                    .map_err(|e| error!("Couldn't add new device: {e:?}"))
                                     {
+                                        for dev in &new_devs {
+                                            sync.device_added(dev).await;
+                                        }
                                         devices.lock().await.append(&mut new_devs);
                                     }
                                 };
@@ -538,4 +748,10 @@ impl DeviceManager {
         });
         Ok(manager)
     }
+
+    /// The devices found so far, kept up to date as they are plugged in and
+    /// removed
+    pub fn devices(&self) -> Arc<Mutex<Vec<AsusDevice>>> {
+        self.devices.clone()
+    }
 }
@@ -13,6 +13,9 @@ pub struct ScsiConfig {
     #[serde(skip)]
     pub dev_type: AuraDeviceType,
     pub enabled: bool,
+    /// Take the laptop keyboard's current mode and colours when plugged in
+    #[serde(default)]
+    pub follow_keyboard: bool,
     pub current_mode: AuraMode,
     pub modes: BTreeMap<AuraMode, AuraEffect>,
 }
@@ -32,6 +35,7 @@ impl Default for ScsiConfig {
     fn default() -> Self {
         ScsiConfig {
             enabled: true,
+            follow_keyboard: false,
             current_mode: AuraMode::Static,
             dev_type: AuraDeviceType::ScsiExtDisk,
             modes: BTreeMap::from([
@@ -1,14 +1,60 @@
 use std::sync::Arc;
 
 use config::ScsiConfig;
+use config_traits::StdConfig;
 use futures_util::lock::{Mutex, MutexGuard};
-use rog_scsi::{AuraEffect, Device, Task};
+use log::info;
+use rog_aura::{AuraModeNum, Direction as AuraDirection, Speed as AuraSpeed};
+use rog_scsi::{AuraEffect, AuraMode, Colour, Device, Direction, Speed, Task};
 
 use crate::error::RogError;
 
 pub mod config;
 pub mod trait_impls;
 
+/// Map a laptop keyboard effect to the closest effect the SCSI device has.
+/// Keyboard modes which have no equivalent fall back to one that looks alike.
+pub fn effect_from_keyboard(effect: &rog_aura::AuraEffect) -> AuraEffect {
+    let mode = match effect.mode {
+        AuraModeNum::Static => AuraMode::Static,
+        AuraModeNum::Breathe | AuraModeNum::Pulse => AuraMode::Breathe,
+        AuraModeNum::RainbowCycle => AuraMode::RainbowCycle,
+        AuraModeNum::RainbowWave => AuraMode::RainbowWave,
+        AuraModeNum::Star | AuraModeNum::Rain => AuraMode::RandomFlicker,
+        AuraModeNum::Highlight | AuraModeNum::Ripple => AuraMode::ChaseFade,
+        AuraModeNum::Laser | AuraModeNum::Comet => AuraMode::Chase,
+        AuraModeNum::Flash => AuraMode::Flashing,
+    };
+    let speed = match effect.speed {
+        AuraSpeed::Low => Speed::Slow,
+        AuraSpeed::Med => Speed::Med,
+        AuraSpeed::High => Speed::Fast,
+    };
+    let direction = match effect.direction {
+        AuraDirection::Left | AuraDirection::Down => Direction::Reverse,
+        AuraDirection::Right | AuraDirection::Up => Direction::Forward,
+    };
+    let colour1 = Colour {
+        r: effect.colour1.r,
+        g: effect.colour1.g,
+        b: effect.colour1.b,
+    };
+    let colour2 = Colour {
+        r: effect.colour2.r,
+        g: effect.colour2.g,
+        b: effect.colour2.b,
+    };
+    AuraEffect {
+        mode,
+        speed,
+        direction,
+        colour1,
+        colour2,
+        colour3: colour1,
+        colour4: colour2,
+    }
+}
+
 #[derive(Clone)]
 pub struct ScsiAura {
     device: Arc<Mutex<Device>>,
@@ -42,4 +88,28 @@ impl ScsiAura {
         }
         Ok(())
     }
+
+    /// Apply the keyboard's current effect if this device is set to follow
+    /// the keyboard. The effect is stored so it is also restored on the next
+    /// start.
+    pub async fn follow_keyboard(&self, effect: &rog_aura::AuraEffect) -> Result<(), RogError> {
+        if !self.config.lock().await.follow_keyboard {
+            return Ok(());
+        }
+        info!("SCSI: following keyboard with {:?}", effect.mode);
+        self.apply_keyboard_effect(effect).await
+    }
+
+    /// Write and store the closest match to a keyboard effect
+    pub async fn apply_keyboard_effect(
+        &self,
+        effect: &rog_aura::AuraEffect,
+    ) -> Result<(), RogError> {
+        let mut config = self.config.lock().await;
+        let effect = effect_from_keyboard(effect);
+        self.write_effect(&effect).await?;
+        config.save_effect(effect);
+        config.write();
+        Ok(())
+    }
 }
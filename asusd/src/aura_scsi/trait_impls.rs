@@ -5,10 +5,12 @@ use log::error;
 use rog_aura::AuraDeviceType;
 use rog_scsi::{AuraEffect, AuraMode};
 use zbus::fdo::Error as ZbErr;
+use zbus::message::Header;
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
 use super::ScsiAura;
+use crate::authorization::{authorize, authorize_interactive, Action};
 use crate::error::RogError;
 
 #[derive(Clone)]
@@ -51,10 +53,37 @@ impl ScsiZbus {
 
     /// Set enabled true or false
     #[zbus(property)]
-    async fn set_enabled(&self, enabled: bool) {
+    async fn set_enabled(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         config.enabled = enabled;
         config.write();
+        Ok(())
+    }
+
+    /// Take the laptop keyboard's current mode and colours when plugged in
+    #[zbus(property)]
+    async fn follow_keyboard(&self) -> bool {
+        self.0.lock_config().await.follow_keyboard
+    }
+
+    #[zbus(property)]
+    async fn set_follow_keyboard(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        follow: bool,
+    ) -> Result<(), ZbErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        let mut config = self.0.lock_config().await;
+        config.follow_keyboard = follow;
+        config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -64,7 +93,13 @@ impl ScsiZbus {
     }
 
     #[zbus(property)]
-    async fn set_led_mode(&self, mode: AuraMode) -> Result<(), zbus::Error> {
+    async fn set_led_mode(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        mode: AuraMode,
+    ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         if let Some(effect) = config.get_effect(mode) {
             self.0
@@ -99,7 +134,13 @@ impl ScsiZbus {
     /// On success the aura config file is read to refresh cached values, then
     /// the effect is stored and config written to disk.
     #[zbus(property)]
-    async fn set_led_mode_data(&mut self, effect: AuraEffect) -> Result<(), ZbErr> {
+    async fn set_led_mode_data(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        effect: AuraEffect,
+    ) -> Result<(), ZbErr> {
+        authorize(conn, &header, Action::Lighting).await?;
         self.0.write_effect(&effect).await?;
 
         let mut config = self.0.config.lock().await;
@@ -114,3 +155,6 @@ impl ScsiZbus {
         config.modes.clone()
     }
 }
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/aura_scsi/trait_impls.rs"));
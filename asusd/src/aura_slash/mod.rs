@@ -1,10 +1,14 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use config::SlashConfig;
 use futures_util::lock::{Mutex, MutexGuard};
+use log::warn;
 use rog_platform::hid_raw::HidRaw;
 use rog_platform::usb_raw::USBRaw;
 use rog_slash::usb::{slash_pkt_enable, slash_pkt_init, slash_pkt_options, slash_pkt_set_mode};
+use rog_slash::SlashMode;
 
 use crate::error::RogError;
 
@@ -16,6 +20,8 @@ pub struct Slash {
     hid: Option<Arc<Mutex<HidRaw>>>,
     usb: Option<Arc<Mutex<USBRaw>>>,
     config: Arc<Mutex<SlashConfig>>,
+    // Blips being shown, the configured mode is restored after the last
+    blips: Arc<AtomicUsize>,
 }
 
 impl Slash {
@@ -24,7 +30,12 @@ impl Slash {
         usb: Option<Arc<Mutex<USBRaw>>>,
         config: Arc<Mutex<SlashConfig>>,
     ) -> Self {
-        Self { hid, usb, config }
+        Self {
+            hid,
+            usb,
+            config,
+            blips: Arc::new(AtomicUsize::new(0)),
+        }
     }
 
     pub async fn lock_config(&self) -> MutexGuard<SlashConfig> {
@@ -40,6 +51,26 @@ impl Slash {
         Ok(())
     }
 
+    /// Play `mode` for `duration`, then go back to the configured mode. The
+    /// mode is not saved to the device.
+    pub async fn show_blip(&self, mode: SlashMode, duration: Duration) {
+        let slash_type = self.config.lock().await.slash_type;
+        self.blips.fetch_add(1, Ordering::AcqRel);
+        self.write_bytes(&slash_pkt_set_mode(slash_type, mode)[1])
+            .await
+            .map_err(|e| warn!("Slash blip: {e}"))
+            .ok();
+        tokio::time::sleep(duration).await;
+        if self.blips.fetch_sub(1, Ordering::AcqRel) > 1 {
+            return;
+        }
+        let mode = self.config.lock().await.display_mode;
+        self.write_bytes(&slash_pkt_set_mode(slash_type, mode)[1])
+            .await
+            .map_err(|e| warn!("Slash blip: {e}"))
+            .ok();
+    }
+
     /// Initialise the device if required. Locks the internal config so be wary
     /// of deadlocks.
     pub async fn do_initialization(&self) -> Result<(), RogError> {
@@ -1,15 +1,20 @@
+use std::time::Duration;
+
 use config_traits::StdConfig;
 use log::{debug, error, warn};
+use rog_anime::BLIP_MAX_MS;
 use rog_slash::usb::{
     slash_pkt_battery_saver, slash_pkt_boot, slash_pkt_enable, slash_pkt_lid_closed,
     slash_pkt_low_battery, slash_pkt_options, slash_pkt_save, slash_pkt_set_mode,
     slash_pkt_shutdown, slash_pkt_sleep,
 };
 use rog_slash::{DeviceState, SlashMode};
+use zbus::message::Header;
 use zbus::zvariant::OwnedObjectPath;
 use zbus::{interface, Connection};
 
 use super::Slash;
+use crate::authorization::{authorize_interactive, Action};
 use crate::error::RogError;
 use crate::Reloadable;
 
@@ -51,7 +56,13 @@ impl SlashZbus {
 
     /// Set enabled true or false
     #[zbus(property)]
-    async fn set_enabled(&self, enabled: bool) {
+    async fn set_enabled(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         let brightness = if enabled && config.brightness == 0 {
             0x88
@@ -81,6 +92,7 @@ impl SlashZbus {
         config.enabled = enabled;
         config.brightness = brightness;
         config.write();
+        Ok(())
     }
 
     /// Get brightness level
@@ -92,7 +104,13 @@ impl SlashZbus {
 
     /// Set brightness level
     #[zbus(property)]
-    async fn set_brightness(&self, brightness: u8) {
+    async fn set_brightness(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        brightness: u8,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         let enabled = brightness > 0;
         self.0
@@ -111,6 +129,7 @@ impl SlashZbus {
         config.enabled = enabled;
         config.brightness = brightness;
         config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -121,7 +140,13 @@ impl SlashZbus {
 
     /// Set interval between slash animations (0-255)
     #[zbus(property)]
-    async fn set_interval(&self, interval: u8) {
+    async fn set_interval(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        interval: u8,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_options(
@@ -135,6 +160,7 @@ impl SlashZbus {
 
         config.display_interval = interval;
         config.write();
+        Ok(())
     }
 
     #[zbus(property)]
@@ -145,7 +171,13 @@ impl SlashZbus {
 
     /// Set interval between slash animations (0-255)
     #[zbus(property)]
-    async fn set_mode(&self, mode: SlashMode) -> zbus::Result<()> {
+    async fn set_mode(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        mode: SlashMode,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
 
         let command_packets = slash_pkt_set_mode(config.slash_type, mode);
@@ -160,6 +192,29 @@ impl SlashZbus {
         Ok(())
     }
 
+    /// Briefly play a mode for a desktop notification, then go back to the
+    /// configured mode. `icon_or_text` may name a mode, or one of the AniMe
+    /// blip icons. `duration_ms` is capped at 10 seconds. Does nothing if the
+    /// Slash is off.
+    async fn notify_blip(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        icon_or_text: String,
+        duration_ms: u32,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        if !self.0.lock_config().await.enabled {
+            debug!("Slash is off, not showing blip");
+            return Ok(());
+        }
+        let mode = SlashMode::for_blip(&icon_or_text);
+        let duration = Duration::from_millis(duration_ms.min(BLIP_MAX_MS).into());
+        let inner = self.0.clone();
+        tokio::spawn(async move { inner.show_blip(mode, duration).await });
+        Ok(())
+    }
+
     /// Get the device state as stored by asusd
     // #[zbus(property)]
     async fn device_state(&self) -> DeviceState {
@@ -174,7 +229,13 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn set_show_on_boot(&self, enable: bool) -> zbus::Result<()> {
+    async fn set_show_on_boot(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enable: bool,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_boot(config.slash_type, enable))
@@ -191,7 +252,13 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn set_show_on_sleep(&self, enable: bool) -> zbus::Result<()> {
+    async fn set_show_on_sleep(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enable: bool,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_sleep(config.slash_type, enable))
@@ -208,7 +275,13 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn set_show_on_shutdown(&self, enable: bool) -> zbus::Result<()> {
+    async fn set_show_on_shutdown(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enable: bool,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_shutdown(config.slash_type, enable))
@@ -225,7 +298,13 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn set_show_on_battery(&self, enable: bool) -> zbus::Result<()> {
+    async fn set_show_on_battery(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enable: bool,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_battery_saver(config.slash_type, enable))
@@ -242,7 +321,13 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn set_show_battery_warning(&self, enable: bool) -> zbus::Result<()> {
+    async fn set_show_battery_warning(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enable: bool,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_low_battery(config.slash_type, enable))
@@ -259,7 +344,13 @@ impl SlashZbus {
     }
 
     #[zbus(property)]
-    async fn set_show_on_lid_closed(&self, enable: bool) -> zbus::Result<()> {
+    async fn set_show_on_lid_closed(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enable: bool,
+    ) -> zbus::Result<()> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let mut config = self.0.lock_config().await;
         self.0
             .write_bytes(&slash_pkt_lid_closed(config.slash_type, enable))
@@ -273,6 +364,9 @@ impl SlashZbus {
     }
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/aura_slash/trait_impls.rs"));
+
 impl Reloadable for SlashZbus {
     async fn reload(&mut self) -> Result<(), RogError> {
         debug!("reloading slash settings");
@@ -0,0 +1,244 @@
+//! Aura Sync: a group of Aura devices, such as the laptop keyboard and
+//! external SCSI controllers, which are given the same builtin mode and
+//! colours together. Members are stored in `/etc/asusd/aura_sync.ron` by
+//! object path, and a member plugged in later is given the group's effect.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use futures_util::future::join_all;
+use futures_util::lock::Mutex;
+use log::{info, warn};
+use rog_aura::{AuraEffect, AuraZone};
+use serde::{Deserialize, Serialize};
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{interface, Connection};
+
+use crate::aura_manager::AsusDevice;
+use crate::aura_types::DeviceHandle;
+use crate::authorization::{authorize, authorize_interactive, Action};
+use crate::ctrl_capabilities::register_controller;
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+use crate::CONFIG_PATH_BASE;
+
+pub const AURA_SYNC_PATH: &str = "/xyz/ljones/aura_sync";
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct AuraSyncConfig {
+    /// Object paths of the devices in the group
+    pub members: Vec<String>,
+    /// The effect last set on the group
+    pub effect: Option<AuraEffect>,
+}
+
+impl StdConfig for AuraSyncConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "aura_sync.ron".to_owned()
+    }
+
+    fn config_dir() -> PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for AuraSyncConfig {}
+
+/// Write `effect` to one device and store it as that device's current mode
+async fn apply_to(device: &DeviceHandle, effect: &AuraEffect) -> Result<(), RogError> {
+    match device {
+        DeviceHandle::Aura(aura) => {
+            let mut config = aura.config.lock().await;
+            if !config.support_data.basic_modes.contains(&effect.mode) {
+                return Err(RogError::AuraEffectNotSupported);
+            }
+            aura.write_effect_and_apply(&config, effect).await?;
+            config.set_builtin(effect.clone());
+            config.write();
+            Ok(())
+        }
+        DeviceHandle::Scsi(scsi) => scsi.apply_keyboard_effect(effect).await,
+        _ => Err(RogError::AuraEffectNotSupported),
+    }
+}
+
+fn can_sync(device: &DeviceHandle) -> bool {
+    matches!(device, DeviceHandle::Aura(_) | DeviceHandle::Scsi(_))
+}
+
+#[derive(Clone)]
+pub struct AuraSync {
+    devices: Arc<Mutex<Vec<AsusDevice>>>,
+    config: Arc<Mutex<AuraSyncConfig>>,
+}
+
+impl AuraSync {
+    pub fn new(devices: Arc<Mutex<Vec<AsusDevice>>>) -> Self {
+        Self {
+            devices,
+            config: Arc::new(Mutex::new(AuraSyncConfig::new().load())),
+        }
+    }
+
+    pub async fn start(self, connection: &Connection) -> Result<(), RogError> {
+        connection.object_server().at(AURA_SYNC_PATH, self).await?;
+        register_controller("xyz.ljones.AuraSync", AURA_SYNC_PATH);
+        Ok(())
+    }
+
+    /// Write `effect` to every member which is plugged in, all at once.
+    /// Returns the members which failed.
+    async fn apply(&self, effect: &AuraEffect) -> Vec<(String, RogError)> {
+        let members = self.config.lock().await.members.clone();
+        let targets: Vec<(String, DeviceHandle)> = self
+            .devices
+            .lock()
+            .await
+            .iter()
+            .filter(|dev| members.contains(&dev.dbus_path.to_string()))
+            .map(|dev| (dev.dbus_path.to_string(), dev.device.clone()))
+            .collect();
+        join_all(
+            targets.iter().map(|(path, device)| async move {
+                (path.clone(), apply_to(device, effect).await)
+            }),
+        )
+        .await
+        .into_iter()
+        .filter_map(|(path, result)| result.err().map(|e| (path, e)))
+        .collect()
+    }
+
+    /// Store `effect` as the group's and write it to every member
+    async fn set_group_effect(&self, effect: AuraEffect) -> Result<(), FdoErr> {
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let effect = AuraEffect {
+            zone: AuraZone::None,
+            ..effect
+        };
+        {
+            let mut config = self.config.lock().await;
+            config.effect = Some(effect.clone());
+            config.write();
+        }
+        let failed = self.apply(&effect).await;
+        if failed.is_empty() {
+            return Ok(());
+        }
+        let failed: Vec<String> = failed.iter().map(|(p, e)| format!("{p}: {e}")).collect();
+        Err(FdoErr::Failed(format!(
+            "Could not set the effect on {}",
+            failed.join(", ")
+        )))
+    }
+
+    /// Give a device which was plugged in the group's effect if it is a
+    /// member
+    pub async fn device_added(&self, device: &AsusDevice) {
+        let config = self.config.lock().await;
+        let Some(effect) = config.effect.as_ref() else {
+            return;
+        };
+        if !config.members.contains(&device.dbus_path.to_string()) {
+            return;
+        }
+        info!(
+            "Aura Sync: applying the group effect to {}",
+            device.dbus_path
+        );
+        apply_to(&device.device, effect)
+            .await
+            .map_err(|e| warn!("Aura Sync: {}: {e}", device.dbus_path))
+            .ok();
+    }
+}
+
+#[interface(name = "xyz.ljones.AuraSync")]
+impl AuraSync {
+    /// The devices which can be in the group, the keyboard and SCSI Aura
+    /// devices which are plugged in
+    #[zbus(property)]
+    async fn devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices
+            .lock()
+            .await
+            .iter()
+            .filter(|dev| can_sync(&dev.device))
+            .map(|dev| dev.dbus_path.clone())
+            .collect()
+    }
+
+    /// The devices in the group, including any which are not plugged in
+    #[zbus(property)]
+    async fn members(&self) -> Vec<OwnedObjectPath> {
+        self.config
+            .lock()
+            .await
+            .members
+            .iter()
+            .filter_map(|m| OwnedObjectPath::try_from(m.as_str()).ok())
+            .collect()
+    }
+
+    #[zbus(property)]
+    async fn set_members(
+        &mut self,
+        members: Vec<OwnedObjectPath>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let mut config = self.config.lock().await;
+        config.members = members.iter().map(|m| m.to_string()).collect();
+        config.write();
+        Ok(())
+    }
+
+    /// The effect last set on the group
+    #[zbus(property)]
+    async fn effect(&self) -> AuraEffect {
+        self.config.lock().await.effect.clone().unwrap_or_default()
+    }
+
+    /// Set the mode and colours of every member together. Members which are
+    /// not plugged in are given it when they are. The effect covers the whole
+    /// of each device so any zone is dropped. Fails if any member could not
+    /// be set, such as a keyboard without the mode, after setting the others.
+    async fn set_effect(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        effect: AuraEffect,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        self.set_group_effect(effect).await
+    }
+
+    /// Write the group effect to every member again, such as after one was
+    /// changed on its own interface
+    async fn resync(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let Some(effect) = self.config.lock().await.effect.clone() else {
+            return Ok(());
+        };
+        self.set_group_effect(effect).await
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/aura_sync.rs"));
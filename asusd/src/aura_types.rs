@@ -13,16 +13,19 @@ use rog_platform::usb_raw::USBRaw;
 use rog_scsi::{open_device, ScsiType};
 use rog_slash::error::SlashError;
 use rog_slash::SlashType;
+use tokio::sync::watch;
 
 use crate::aura_anime::config::AniMeConfig;
 use crate::aura_anime::AniMe;
 use crate::aura_laptop::config::AuraConfig;
+use crate::aura_laptop::delta::SentPackets;
 use crate::aura_laptop::Aura;
 use crate::aura_scsi::config::ScsiConfig;
 use crate::aura_scsi::ScsiAura;
 use crate::aura_slash::config::SlashConfig;
 use crate::aura_slash::Slash;
 use crate::error::RogError;
+use crate::frame_stream::FrameStream;
 
 pub enum _DeviceHandle {
     /// The AniMe devices require USBRaw as they are not HID devices
@@ -172,9 +175,12 @@ impl DeviceHandle {
         Ok(Self::Scsi(scsi))
     }
 
+    /// `device_key` is from [`crate::aura_manager::device_key`], `None` for
+    /// devices found without USB such as TUF keyboards
     pub async fn maybe_laptop_aura(
         device: Option<Arc<Mutex<HidRaw>>>,
         prod_id: &str,
+        device_key: Option<&str>,
     ) -> Result<Self, RogError> {
         debug!("Testing for laptop aura");
         let aura_type = AuraDeviceType::from(prod_id);
@@ -196,12 +202,16 @@ impl DeviceHandle {
                 Some(Arc::new(Mutex::new(k)))
             });
 
-        let mut config = AuraConfig::load_and_update_config(prod_id);
+        let mut config = AuraConfig::load_and_update_config(prod_id, device_key);
         config.led_type = aura_type;
         let aura = Aura {
             hid: device,
             backlight,
             config: Arc::new(Mutex::new(config)),
+            sent: Arc::new(Mutex::new(SentPackets::default())),
+            stream: FrameStream::default(),
+            frames: Arc::new(watch::Sender::new(Vec::new())),
+            shuffle_changed: Arc::new(watch::Sender::new(())),
         };
         aura.do_initialization().await?;
         Ok(Self::Aura(aura))
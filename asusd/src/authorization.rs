@@ -0,0 +1,179 @@
+//! Polkit checks for changes made over DBus. Changes are grouped into
+//! lighting, power, and firmware tuning so that an administrator can, for
+//! example, let any user change the keyboard but require a password to write
+//! PPT or TGP limits. The defaults in `xyz.ljones.asusd.policy` allow
+//! everything, leaving the DBus policy as the only limit.
+//!
+//! If polkit isn't running the DBus policy is the only check, as before.
+
+use std::collections::HashMap;
+
+use log::{debug, warn};
+use zbus::fdo::{self, DBusProxy};
+use zbus::message::Header;
+use zbus::names::BusName;
+use zbus::zvariant::Value;
+use zbus::{proxy, Connection};
+
+/// Allow the polkit agent to ask for a password
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+/// Polkit check flags: ask nothing of the user
+const NO_USER_INTERACTION: u32 = 0;
+
+/// The kind of change a DBus caller is making
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Keyboard, lightbar, AniMe, and Slash LEDs, and the screen backlights
+    Lighting,
+    /// Platform profiles, fan curves, and charge control
+    Power,
+    /// PPT, TGP, and other firmware attributes which can harm the hardware
+    FirmwareAttributes,
+}
+
+impl Action {
+    /// The polkit action id
+    pub const fn id(&self) -> &'static str {
+        match self {
+            Self::Lighting => "xyz.ljones.asusd.lighting",
+            Self::Power => "xyz.ljones.asusd.power",
+            Self::FirmwareAttributes => "xyz.ljones.asusd.firmware-attributes",
+        }
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    /// CheckAuthorization method
+    fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// Check that the sender of the message with `header` may make changes of
+/// kind `action`. Root and asusd itself are always allowed.
+///
+/// Polkit is not allowed to ask for a password, as a `&mut self` call holds
+/// its whole interface until it returns. A change which needs one is refused
+/// unless an earlier prompt is kept, such as with `AUTH_ADMIN_KEEP`.
+pub async fn authorize(conn: &Connection, header: &Header<'_>, action: Action) -> fdo::Result<()> {
+    check(conn, header, action, NO_USER_INTERACTION).await
+}
+
+/// As [`authorize`], letting polkit ask for a password. Only for `&self`
+/// calls, which other calls can run alongside.
+pub async fn authorize_interactive(
+    conn: &Connection,
+    header: &Header<'_>,
+    action: Action,
+) -> fdo::Result<()> {
+    check(conn, header, action, ALLOW_USER_INTERACTION).await
+}
+
+async fn check(
+    conn: &Connection,
+    header: &Header<'_>,
+    action: Action,
+    flags: u32,
+) -> fdo::Result<()> {
+    let Some(sender) = header.sender() else {
+        return Ok(());
+    };
+    let dbus = DBusProxy::new(conn).await?;
+    if dbus
+        .get_connection_unix_user(BusName::Unique(sender.to_owned()))
+        .await
+        .is_ok_and(|uid| uid == 0)
+    {
+        return Ok(());
+    }
+    let subject = (
+        "system-bus-name",
+        HashMap::from([("name", Value::from(sender.as_str()))]),
+    );
+    check_subject(conn, &subject, sender.as_str(), action, flags).await
+}
+
+/// Check that process `pid` of user `uid`, a client of the socket API, may
+/// make changes of kind `action`. Root is always allowed.
+///
+/// `start_time` is the process's start time from `/proc/<pid>/stat`, read
+/// when the client connected. Polkit checks it against the process holding
+/// `pid` now, so a pid reused by another process is refused.
+pub async fn authorize_process(
+    conn: &Connection,
+    pid: u32,
+    start_time: u64,
+    uid: u32,
+    action: Action,
+) -> fdo::Result<()> {
+    if uid == 0 {
+        return Ok(());
+    }
+    let subject = (
+        "unix-process",
+        HashMap::from([
+            ("pid", Value::from(pid)),
+            ("start-time", Value::from(start_time)),
+            ("uid", Value::from(uid as i32)),
+        ]),
+    );
+    check_subject(
+        conn,
+        &subject,
+        &format!("pid {pid}"),
+        action,
+        ALLOW_USER_INTERACTION,
+    )
+    .await
+}
+
+async fn check_subject(
+    conn: &Connection,
+    subject: &(&str, HashMap<&str, Value<'_>>),
+    who: &str,
+    action: Action,
+    flags: u32,
+) -> fdo::Result<()> {
+    let authority = AuthorityProxy::new(conn).await?;
+    match authority
+        .check_authorization(subject, action.id(), HashMap::new(), flags, "")
+        .await
+    {
+        Ok((true, ..)) => Ok(()),
+        Ok((false, true, _)) if flags == NO_USER_INTERACTION => {
+            warn!("{who} must authenticate for {}", action.id());
+            Err(fdo::Error::InteractiveAuthorizationRequired(format!(
+                "Authentication is required for {}",
+                action.id()
+            )))
+        }
+        Ok((false, ..)) => {
+            warn!("{who} is not authorized for {}", action.id());
+            Err(fdo::Error::AccessDenied(format!(
+                "Not authorized for {}",
+                action.id()
+            )))
+        }
+        Err(zbus::Error::MethodError(name, ..))
+            if name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown" =>
+        {
+            debug!("No polkit, allowing {}", action.id());
+            Ok(())
+        }
+        Err(e) => Err(fdo::Error::Failed(format!(
+            "Could not check {} with polkit: {e}",
+            action.id()
+        ))),
+    }
+}
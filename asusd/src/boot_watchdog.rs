@@ -0,0 +1,247 @@
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use futures_util::lock::Mutex;
+use log::{debug, error, info, warn};
+use rog_platform::asus_armoury::FirmwareAttribute;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize_interactive, Action};
+use crate::config::Config;
+use crate::error::RogError;
+use crate::{CtrlTask, ASUS_ZBUS_PATH, CONFIG_PATH_BASE};
+
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+const UPTIME_PATH: &str = "/proc/uptime";
+/// Risky settings are considered safe once the machine has stayed up this
+/// long after they were applied
+const STABLE_AFTER: Duration = Duration::from_secs(300);
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The id of the current boot, changes on every boot
+pub(crate) fn boot_id() -> String {
+    read_to_string(BOOT_ID_PATH)
+        .map(|s| s.trim().to_owned())
+        .unwrap_or_default()
+}
+
+fn uptime_secs() -> u64 {
+    read_to_string(UPTIME_PATH)
+        .ok()
+        .and_then(|s| s.split('.').next()?.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Settings which can leave the machine unable to boot or stay up if set
+/// badly
+pub fn is_risky(name: FirmwareAttribute) -> bool {
+    name.is_ppt() || name == FirmwareAttribute::DgpuTgp
+}
+
+/// Persistent record of risky settings applied in a boot which has not yet
+/// been confirmed stable
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct BootWatchdog {
+    /// The boot the risky settings were applied in
+    pub boot_id: String,
+    /// Set while risky settings are applied and not yet confirmed stable
+    pub pending: bool,
+    /// Uptime in seconds when risky settings were last applied
+    pub marked_uptime: u64,
+    /// Set when risky settings were reverted after a failed boot
+    pub reverted: bool,
+}
+
+impl StdConfig for BootWatchdog {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "boot_watchdog.ron".to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for BootWatchdog {}
+
+/// Record that risky settings were just applied. If this boot does not
+/// reach `STABLE_AFTER` of uptime past this point, or shut down cleanly, the
+/// next boot reverts them.
+pub fn mark_risky_applied() {
+    let mut watchdog = BootWatchdog::new().load();
+    watchdog.boot_id = boot_id();
+    watchdog.pending = true;
+    watchdog.marked_uptime = uptime_secs();
+    watchdog.write();
+}
+
+/// Must be called before any controller applies settings. If the previous
+/// boot applied risky settings and then went down without becoming stable the
+/// settings are reverted to defaults: PPT tuning groups are disabled, and the
+/// dGPU TGP and curve optimizer offsets are cleared.
+pub fn revert_after_failed_boot(config: &mut Config) {
+    let mut watchdog = BootWatchdog::new().load();
+    if !watchdog.pending || watchdog.boot_id == boot_id() {
+        return;
+    }
+    error!(
+        "BootWatchdog: the last boot went down shortly after tuning was applied, reverting PPT, \
+         TGP, and undervolt settings to defaults"
+    );
+    for tuning in config
+        .ac_profile_tunings
+        .values_mut()
+        .chain(config.dc_profile_tunings.values_mut())
+    {
+        tuning.enabled = false;
+    }
+    config.armoury_settings.retain(|name, _| !is_risky(*name));
+    config.curve_optimizer_on_profile.clear();
+    config.write();
+
+    watchdog.pending = false;
+    watchdog.reverted = true;
+    watchdog.write();
+}
+
+/// Clears the pending flag once a boot with risky settings applied has been
+/// stable long enough, and reports if settings were reverted
+#[derive(Clone)]
+pub struct CtrlBootWatchdog {
+    watchdog: Arc<Mutex<BootWatchdog>>,
+}
+
+impl CtrlBootWatchdog {
+    pub fn new() -> Self {
+        Self {
+            watchdog: Arc::new(Mutex::new(BootWatchdog::new().load())),
+        }
+    }
+
+    /// Reload from disk, the file is also written by `mark_risky_applied()`
+    async fn refresh(&self) {
+        *self.watchdog.lock().await = BootWatchdog::new().load();
+    }
+
+    async fn clear_pending(&self) {
+        let mut watchdog = self.watchdog.lock().await;
+        *watchdog = BootWatchdog::new().load();
+        if watchdog.pending {
+            watchdog.pending = false;
+            watchdog.write();
+        }
+    }
+}
+
+impl Default for CtrlBootWatchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[interface(name = "xyz.ljones.BootWatchdog")]
+impl CtrlBootWatchdog {
+    /// True while risky settings are applied and not yet confirmed stable
+    #[zbus(property)]
+    async fn pending(&self) -> bool {
+        self.refresh().await;
+        self.watchdog.lock().await.pending
+    }
+
+    /// True if risky settings were reverted after a failed boot
+    #[zbus(property)]
+    async fn reverted(&self) -> bool {
+        self.watchdog.lock().await.reverted
+    }
+
+    /// Clear the `reverted` flag
+    async fn acknowledge(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        {
+            let mut watchdog = self.watchdog.lock().await;
+            *watchdog = BootWatchdog::new().load();
+            watchdog.reverted = false;
+            watchdog.write();
+        }
+        self.reverted_changed(&ctxt).await.ok();
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/boot_watchdog.rs"));
+
+impl crate::ZbusRun for CtrlBootWatchdog {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlBootWatchdog {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        self.refresh().await;
+        if self.watchdog.lock().await.reverted {
+            warn!("BootWatchdog: tuning was reverted after a failed boot");
+        }
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlBootWatchdog {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        // A clean shutdown is not a failed boot
+        let ctrl = self.clone();
+        self.create_sys_event_tasks(
+            move |shutting_down| {
+                let ctrl = ctrl.clone();
+                async move {
+                    if shutting_down {
+                        debug!("BootWatchdog: clean shutdown, clearing pending");
+                        ctrl.clear_pending().await;
+                    }
+                }
+            },
+            move |_lid_closed| async move {},
+            move |_power_plugged| async move {},
+        )
+        .await;
+
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            loop {
+                sleep(CHECK_INTERVAL).await;
+                ctrl.refresh().await;
+                let (pending, marked) = {
+                    let watchdog = ctrl.watchdog.lock().await;
+                    (watchdog.pending, watchdog.marked_uptime)
+                };
+                if pending && uptime_secs().saturating_sub(marked) >= STABLE_AFTER.as_secs() {
+                    info!("BootWatchdog: tuning has been stable, clearing pending");
+                    ctrl.clear_pending().await;
+                    ctrl.pending_changed(&signal_ctxt).await.ok();
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -3,9 +3,11 @@ use std::collections::HashMap;
 use config_traits::{StdConfig, StdConfigLoad2};
 use rog_platform::asus_armoury::FirmwareAttribute;
 use rog_platform::cpu::CPUEPP;
-use rog_platform::platform::PlatformProfile;
+use rog_platform::platform::{GpuMode, PlatformProfile};
 use serde::{Deserialize, Serialize};
 
+use crate::dgpu_sleep::DgpuSleepGuard;
+
 const CONFIG_FILE: &str = "asusd.ron";
 
 #[derive(Default, Clone, Deserialize, Serialize, PartialEq)]
@@ -15,6 +17,50 @@ pub struct Tuning {
 }
 type Tunings = HashMap<PlatformProfile, Tuning>;
 
+fn default_panel_od_auto_min_refresh() -> u32 {
+    144
+}
+
+fn default_xg_mobile_lights() -> bool {
+    true
+}
+
+fn default_log_level() -> String {
+    "info".to_owned()
+}
+
+/// Parts of asusd which can be turned off, for example to leave the lighting
+/// to another program. A disabled part doesn't touch the hardware and its
+/// DBus interfaces are not added. Changes are applied when asusd restarts.
+/// A part left out of the config stays on.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct Subsystems {
+    /// Keyboard and other Aura lighting, including SCSI drives
+    pub aura: bool,
+    /// The AniMe matrix display
+    pub anime: bool,
+    /// The Slash lightbar
+    pub slash: bool,
+    /// Platform profile fan curves
+    pub fan_curves: bool,
+    /// Firmware attributes, and the dGPU TGP and GPU thermal policy which are
+    /// set through them
+    pub firmware_attributes: bool,
+}
+
+impl Default for Subsystems {
+    fn default() -> Self {
+        Self {
+            aura: true,
+            anime: true,
+            slash: true,
+            fan_curves: true,
+            firmware_attributes: true,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq)]
 pub struct Config {
     // The current charge limit applied
@@ -53,11 +99,69 @@ pub struct Config {
     pub screenpad_gamma: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub screenpad_sync_primary: Option<bool>,
+    /// The `mini_led_mode` to set when a platform profile becomes active.
+    /// Profiles without an entry fall back to the AC/battery binding
+    #[serde(default)]
+    pub mini_led_mode_on_profile: HashMap<PlatformProfile, i32>,
+    /// The `mini_led_mode` to set when on AC power, if any
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mini_led_mode_on_ac: Option<i32>,
+    /// The `mini_led_mode` to set when on battery power, if any
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mini_led_mode_on_battery: Option<i32>,
+    /// Toggle `panel_od` automatically with the internal panel refresh rate
+    #[serde(default)]
+    pub panel_od_auto: bool,
+    /// The refresh rate at or above which `panel_od` is enabled when
+    /// `panel_od_auto` is set
+    #[serde(default = "default_panel_od_auto_min_refresh")]
+    pub panel_od_auto_min_refresh: u32,
+    /// The AMD all-core curve optimizer offset to apply for each platform
+    /// profile. Profiles without an entry use `0`
+    #[serde(default)]
+    pub curve_optimizer_on_profile: HashMap<PlatformProfile, i32>,
+    /// The level to log at: `off`, `error`, `warn`, `info`, `debug` or `trace`
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+    /// Log levels for single modules such as `zbus` or
+    /// `asusd::ctrl_fancurves`, these take precedence over `log_level`
+    #[serde(default = "crate::logging::default_log_filters")]
+    pub log_filters: HashMap<String, String>,
+    /// Log `tracing` span enter and exit, these are many per second
+    #[serde(default)]
+    pub log_spans: bool,
+    /// Which parts of asusd are enabled
+    #[serde(default)]
+    pub subsystems: Subsystems,
+    /// The charge limit of each battery other than the main one, by its
+    /// `power_supply` name such as `BAT1`
+    #[serde(default)]
+    pub battery_charge_limits: HashMap<String, u8>,
+    /// Wait for the dGPU to power down before suspending
+    #[serde(default)]
+    pub dgpu_sleep_guard: DgpuSleepGuard,
+    /// A GPU MUX switch to make on the next shutdown or reboot
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub gpu_mux_queued: Option<QueuedMuxSwitch>,
+    /// If the XG Mobile enclosure lights are on
+    #[serde(default = "default_xg_mobile_lights")]
+    pub xg_mobile_lights: bool,
     /// Temporary state for AC/Batt
     #[serde(skip)]
     pub last_power_plugged: u8,
 }
 
+/// A GPU MUX switch waiting for the next shutdown or reboot, rather than
+/// being written to the firmware straight away
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueuedMuxSwitch {
+    /// `Ultimate` or `Optimus`
+    pub mode: GpuMode,
+    /// A client asks the user to confirm at shutdown and applies it. Without
+    /// this asusd applies it on shutdown
+    pub confirm: bool,
+}
+
 impl Config {
     pub fn select_tunings(&mut self, power_plugged: bool, profile: PlatformProfile) -> &mut Tuning {
         let config = if power_plugged {
@@ -67,6 +171,19 @@ impl Config {
         };
         config.entry(profile).or_insert_with(Tuning::default)
     }
+
+    /// The `mini_led_mode` bound to this profile or power state, if any. A
+    /// profile binding takes precedence over the AC/battery binding.
+    pub fn mini_led_mode_for(&self, power_plugged: bool, profile: PlatformProfile) -> Option<i32> {
+        self.mini_led_mode_on_profile
+            .get(&profile)
+            .copied()
+            .or(if power_plugged {
+                self.mini_led_mode_on_ac
+            } else {
+                self.mini_led_mode_on_battery
+            })
+    }
 }
 
 impl Default for Config {
@@ -92,6 +209,20 @@ impl Default for Config {
             last_power_plugged: Default::default(),
             screenpad_gamma: Default::default(),
             screenpad_sync_primary: Default::default(),
+            mini_led_mode_on_profile: HashMap::default(),
+            mini_led_mode_on_ac: None,
+            mini_led_mode_on_battery: None,
+            panel_od_auto: false,
+            panel_od_auto_min_refresh: default_panel_od_auto_min_refresh(),
+            curve_optimizer_on_profile: HashMap::default(),
+            log_level: default_log_level(),
+            log_filters: crate::logging::default_log_filters(),
+            log_spans: false,
+            subsystems: Subsystems::default(),
+            battery_charge_limits: HashMap::default(),
+            dgpu_sleep_guard: DgpuSleepGuard::default(),
+            gpu_mux_queued: None,
+            xg_mobile_lights: true,
         }
     }
 }
@@ -168,6 +299,20 @@ impl From<Config611> for Config {
             armoury_settings: HashMap::default(),
             screenpad_gamma: None,
             screenpad_sync_primary: Default::default(),
+            mini_led_mode_on_profile: HashMap::default(),
+            mini_led_mode_on_ac: None,
+            mini_led_mode_on_battery: None,
+            panel_od_auto: false,
+            panel_od_auto_min_refresh: default_panel_od_auto_min_refresh(),
+            curve_optimizer_on_profile: HashMap::default(),
+            log_level: default_log_level(),
+            log_filters: crate::logging::default_log_filters(),
+            log_spans: false,
+            subsystems: Subsystems::default(),
+            battery_charge_limits: HashMap::default(),
+            dgpu_sleep_guard: DgpuSleepGuard::default(),
+            gpu_mux_queued: None,
+            xg_mobile_lights: true,
         }
     }
 }
@@ -235,6 +380,34 @@ impl From<Config601> for Config {
             armoury_settings: HashMap::default(),
             screenpad_gamma: None,
             screenpad_sync_primary: Default::default(),
+            mini_led_mode_on_profile: HashMap::default(),
+            mini_led_mode_on_ac: None,
+            mini_led_mode_on_battery: None,
+            panel_od_auto: false,
+            panel_od_auto_min_refresh: default_panel_od_auto_min_refresh(),
+            curve_optimizer_on_profile: HashMap::default(),
+            log_level: default_log_level(),
+            log_filters: crate::logging::default_log_filters(),
+            log_spans: false,
+            subsystems: Subsystems::default(),
+            battery_charge_limits: HashMap::default(),
+            dgpu_sleep_guard: DgpuSleepGuard::default(),
+            gpu_mux_queued: None,
+            xg_mobile_lights: true,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Subsystems;
+
+    #[test]
+    fn partial_subsystems() {
+        let subsystems: Subsystems = ron::from_str("(aura: false)").unwrap();
+        assert_eq!(subsystems, Subsystems {
+            aura: false,
+            ..Subsystems::default()
+        });
+    }
+}
@@ -5,12 +5,16 @@ use config_traits::StdConfig;
 use futures_util::lock::Mutex;
 use log::{info, warn};
 use rog_platform::backlight::{Backlight, BacklightType};
+use rog_platform::screenpad::{self, GAMMA_MAX, GAMMA_MIN};
 use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
 use zbus::object_server::SignalEmitter;
 use zbus::{interface, Connection};
 
+use crate::authorization::{authorize_interactive, Action};
 use crate::config::Config;
 use crate::error::RogError;
+use crate::supervisor::Supervisor;
 use crate::ASUS_ZBUS_PATH;
 
 #[derive(Debug, Clone)]
@@ -77,9 +81,7 @@ impl CtrlBacklight {
             let gamma = self.config.lock().await.screenpad_gamma.unwrap_or(1.0);
             let scaled = if *device_type == BacklightType::Screenpad {
                 // Apply non-linear scaling with the configurable gamma value only for Screenpad
-                let normalized_level = level as f32 / 100.0;
-                let gamma_corrected = normalized_level.powf(gamma);
-                (gamma_corrected * max as f32) as i32
+                screenpad::level_to_raw(level, max, gamma)
             } else {
                 // Linear scaling for other devices
                 level * max / 100
@@ -101,9 +103,7 @@ impl CtrlBacklight {
                     if let Ok(other_max) = other.get_max_brightness() {
                         let other_scaled = if other.device_type() == &BacklightType::Screenpad {
                             // Apply gamma only to Screenpad
-                            let normalized_level = level as f32 / 100.0;
-                            let gamma_corrected = normalized_level.powf(gamma);
-                            (gamma_corrected * other_max as f32) as i32
+                            screenpad::level_to_raw(level, other_max, gamma)
                         } else {
                             // Linear scaling for other devices
                             level * other_max / 100
@@ -136,9 +136,7 @@ impl CtrlBacklight {
 
             if *device_type == BacklightType::Screenpad {
                 let gamma = self.config.lock().await.screenpad_gamma.unwrap_or(1.0);
-                let normalized = brightness as f32 / max as f32;
-                let corrected = normalized.powf(1.0 / gamma);
-                Ok((corrected * 100.0).round() as i32)
+                Ok(screenpad::raw_to_level(brightness, max, gamma))
             } else {
                 Ok(brightness * 100 / max)
             }
@@ -150,7 +148,7 @@ impl CtrlBacklight {
         }
     }
 
-    pub async fn start_watch_primary(&self) -> Result<(), RogError> {
+    pub async fn start_watch_primary(&self, supervisor: &Supervisor) -> Result<(), RogError> {
         if self.get_backlight(&BacklightType::Screenpad).is_none() {
             return Ok(());
         }
@@ -162,29 +160,23 @@ impl CtrlBacklight {
         }
 
         if let Some(backlight) = self.get_backlight(&BacklightType::Primary) {
-            let watch = backlight.monitor_brightness()?;
+            backlight.monitor_brightness()?;
 
             let backlights = self.clone();
-            tokio::spawn(async move {
-                let mut last_level = 0;
-                let mut buffer = [0; 32];
-                use futures_lite::StreamExt;
-                if let Ok(mut stream) = watch.into_event_stream(&mut buffer) {
-                    loop {
-                        let _ = stream.next().await;
-
+            supervisor.spawn("Backlight: primary watch", move || {
+                let backlights = backlights.clone();
+                async move {
+                    let Some(backlight) = backlights.get_backlight(&BacklightType::Primary) else {
+                        return Ok(());
+                    };
+                    let watch = backlight.monitor_brightness()?;
+                    let mut last_level = 0;
+                    let mut buffer = [0; 32];
+                    use futures_lite::StreamExt;
+                    let mut stream = watch.into_event_stream(&mut buffer)?;
+                    while stream.next().await.is_some() {
                         let sync = backlights.config.lock().await.screenpad_sync_primary;
-                        if let Some(sync) = sync {
-                            if !sync {
-                                continue;
-                            }
-                        } else if backlights
-                            .config
-                            .lock()
-                            .await
-                            .screenpad_sync_primary
-                            .is_none()
-                        {
+                        if sync != Some(true) {
                             continue;
                         }
 
@@ -203,11 +195,7 @@ impl CtrlBacklight {
                         // other processes cause "MODIFY" event and make this spin 100%, so sleep
                         tokio::time::sleep(Duration::from_millis(300)).await;
                     }
-                    // watch
-                    //     .into_event_stream(&mut buffer)
-                    //     .unwrap()
-                    //     .for_each(|_| async {})
-                    //     .await;
+                    Ok::<(), RogError>(())
                 }
             });
         }
@@ -228,7 +216,13 @@ impl CtrlBacklight {
     }
 
     #[zbus(property)]
-    async fn set_screenpad_sync_with_primary(&self, sync: bool) -> Result<(), zbus::Error> {
+    async fn set_screenpad_sync_with_primary(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        sync: bool,
+    ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         self.config.lock().await.screenpad_sync_primary = Some(sync);
         self.config.lock().await.write();
         Ok(())
@@ -240,16 +234,22 @@ impl CtrlBacklight {
     }
 
     #[zbus(property)]
-    async fn set_screenpad_gamma(&self, value: &str) -> Result<(), zbus::Error> {
+    async fn set_screenpad_gamma(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        value: &str,
+    ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         let gamma: f32 = value
             .parse()
             .map_err(|_| FdoErr::Failed("Invalid gamma value, must be a valid number".into()))?;
 
-        if gamma < 0.1 {
-            return Err(FdoErr::Failed("Gamma value must be greater than 0".into()).into());
+        if gamma < GAMMA_MIN {
+            return Err(FdoErr::Failed(format!("Gamma value must be {GAMMA_MIN} or more")).into());
         }
-        if gamma > 2.0 {
-            return Err(FdoErr::Failed("Gamma value must be 2.0 or less".into()).into());
+        if gamma > GAMMA_MAX {
+            return Err(FdoErr::Failed(format!("Gamma value must be {GAMMA_MAX} or less")).into());
         }
         self.config.lock().await.screenpad_gamma = Some(gamma);
         self.config.lock().await.write();
@@ -264,9 +264,12 @@ impl CtrlBacklight {
     #[zbus(property)]
     async fn set_primary_brightness(
         &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
         #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
         level: i32,
     ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         if level > 100 {
             return Err(FdoErr::Failed("Brightness level must be 0-100".into()).into());
         }
@@ -286,16 +289,19 @@ impl CtrlBacklight {
     #[zbus(property)]
     async fn set_screenpad_brightness(
         &self,
-        // #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
         level: i32,
     ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         if level > 100 {
             return Err(FdoErr::Failed("Brightness level must be 0-100".into()).into());
         }
 
         self.set_brightness_with_sync(&BacklightType::Screenpad, level)
             .await?;
-        // self.screenpad_brightness_changed(&ctxt).await?;
+        self.screenpad_brightness_changed(&ctxt).await?;
 
         Ok(())
     }
@@ -303,11 +309,10 @@ impl CtrlBacklight {
     #[zbus(property)]
     async fn screenpad_power(&self) -> Result<bool, FdoErr> {
         if let Some(backlight) = self.get_backlight(&BacklightType::Screenpad) {
-            let power = backlight.get_bl_power().map_err(|e| {
+            backlight.is_powered().map_err(|e| {
                 warn!("Failed to get backlight power: {}", e);
                 FdoErr::Failed(format!("Failed to get backlight power: {}", e))
-            })?;
-            Ok(power == 0)
+            })
         } else {
             Err(FdoErr::NotSupported("Screenpad backlight not found".into()))
         }
@@ -316,16 +321,17 @@ impl CtrlBacklight {
     #[zbus(property)]
     async fn set_screenpad_power(
         &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
         #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
         power: bool,
     ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
         if let Some(backlight) = self.get_backlight(&BacklightType::Screenpad) {
-            backlight
-                .set_bl_power(if power { 0 } else { 1 })
-                .map_err(|e| {
-                    warn!("Failed to set backlight power: {}", e);
-                    FdoErr::Failed(format!("Failed to set backlight power: {}", e))
-                })?;
+            backlight.set_powered(power).map_err(|e| {
+                warn!("Failed to set backlight power: {}", e);
+                FdoErr::Failed(format!("Failed to set backlight power: {}", e))
+            })?;
             self.screenpad_power_changed(&ctxt).await?;
             Ok(())
         } else {
@@ -334,6 +340,9 @@ impl CtrlBacklight {
     }
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_backlight.rs"));
+
 impl crate::ZbusRun for CtrlBacklight {
     async fn add_to_server(self, server: &mut Connection) {
         Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
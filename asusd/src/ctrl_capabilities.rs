@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use rog_platform::asus_armoury::{FirmwareAttribute, FirmwareAttributes};
+use rog_platform::platform::{Properties, RogPlatform};
+use rog_platform::power::AsusPower;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::ctrl_platform::supported_properties;
+use crate::error::RogError;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+/// The version of the DBus API as a whole. Increased when an interface or
+/// method is removed or changes in a way old clients can't handle
+pub const API_VERSION: u32 = 1;
+
+/// The version of each interface. Increased when a method, property, or
+/// signal is added, removed, or changed on that interface
+pub const INTERFACE_VERSIONS: &[(&str, u32)] = &[
+    ("xyz.ljones.Anime", 5),
+    ("xyz.ljones.AsusArmoury", 1),
+    ("xyz.ljones.Aura", 14),
+    ("xyz.ljones.AuraJson", 1),
+    ("xyz.ljones.AuraSync", 1),
+    ("xyz.ljones.Backlight", 1),
+    ("xyz.ljones.BootWatchdog", 1),
+    ("xyz.ljones.Capabilities", 1),
+    ("xyz.ljones.CurveOptimizer", 1),
+    ("xyz.ljones.DgpuTgp", 1),
+    ("xyz.ljones.FanCurves", 4),
+    ("xyz.ljones.GpuThermalPolicy", 1),
+    ("xyz.ljones.Health", 3),
+    ("xyz.ljones.Input", 1),
+    ("xyz.ljones.Platform", 7),
+    ("xyz.ljones.Privacy", 1),
+    ("xyz.ljones.Scenes", 1),
+    ("xyz.ljones.ScsiAura", 1),
+    ("xyz.ljones.Slash", 1),
+    ("xyz.ljones.Snapshot", 2),
+    ("xyz.ljones.UsageStats", 1),
+    ("xyz.ljones.XgMobile", 1),
+];
+
+/// Interface name and object path of each controller added at startup
+static CONTROLLERS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Record a controller which was added to the object server
+pub fn register_controller(interface: &str, path: &str) {
+    if let Ok(mut controllers) = CONTROLLERS.lock() {
+        controllers.push((interface.to_owned(), path.to_owned()));
+    }
+}
+
+/// Reports what this asusd provides so that clients can check for a feature
+/// instead of failing on a missing method. Aura, AniMe, Slash, and SCSI
+/// devices can be hotplugged so are found with `ObjectManager` instead.
+#[derive(Clone)]
+pub struct CtrlCapabilities {
+    platform: RogPlatform,
+    power: AsusPower,
+    attributes: FirmwareAttributes,
+}
+
+impl CtrlCapabilities {
+    pub fn new(platform: RogPlatform, power: AsusPower, attributes: FirmwareAttributes) -> Self {
+        Self {
+            platform,
+            power,
+            attributes,
+        }
+    }
+}
+
+#[interface(name = "xyz.ljones.Capabilities")]
+impl CtrlCapabilities {
+    /// The asusd version
+    #[zbus(property)]
+    async fn daemon_version(&self) -> String {
+        crate::VERSION.to_owned()
+    }
+
+    /// The version of the DBus API as a whole
+    #[zbus(property)]
+    async fn api_version(&self) -> u32 {
+        API_VERSION
+    }
+
+    /// The version of each interface asusd may provide
+    #[zbus(property)]
+    async fn interface_versions(&self) -> HashMap<String, u32> {
+        INTERFACE_VERSIONS
+            .iter()
+            .map(|(name, version)| (name.to_string(), *version))
+            .collect()
+    }
+
+    /// The interface name and object path of each controller loaded
+    #[zbus(property)]
+    async fn controllers(&self) -> Vec<(String, String)> {
+        CONTROLLERS.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// The properties on `xyz.ljones.Platform` which this laptop supports
+    #[zbus(property)]
+    async fn platform_properties(&self) -> Vec<Properties> {
+        supported_properties(&self.platform, &self.power)
+    }
+
+    /// The firmware attributes this laptop has, each is an
+    /// `xyz.ljones.AsusArmoury` object
+    #[zbus(property)]
+    async fn firmware_attributes(&self) -> Vec<FirmwareAttribute> {
+        self.attributes
+            .attributes()
+            .iter()
+            .map(|a| FirmwareAttribute::from(a.name()))
+            .collect()
+    }
+
+    /// The version of `interface`, or 0 if this asusd doesn't know it
+    async fn interface_version(&self, interface: &str) -> u32 {
+        INTERFACE_VERSIONS
+            .iter()
+            .find(|(name, _)| *name == interface)
+            .map_or(0, |(_, version)| *version)
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_capabilities.rs"));
+
+impl crate::ZbusRun for CtrlCapabilities {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlCapabilities {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlCapabilities {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use futures_util::lock::Mutex;
+use log::{debug, error, info, warn};
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_platform::ryzen_smu::{RyzenSmu, CURVE_OPTIMIZER_MAX, CURVE_OPTIMIZER_MIN};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize, Action};
+use crate::boot_watchdog::{boot_id, mark_risky_applied};
+use crate::config::Config;
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+use crate::sleep_hooks::{HookOrder, SleepHooks};
+use crate::supervisor::Supervisor;
+use crate::{CtrlTask, ASUS_ZBUS_PATH, CONFIG_PATH_BASE};
+
+/// A boot with offsets applied is considered stable once the daemon has been
+/// running this long
+const STABLE_AFTER: Duration = Duration::from_secs(600);
+/// Offsets are no longer applied after this many consecutive unstable boots
+const MAX_UNSTABLE_BOOTS: u32 = 3;
+
+/// Tracks boots which did not stay up for `STABLE_AFTER` while curve
+/// optimizer offsets were applied, so that an unstable undervolt can't leave
+/// the machine in a crash loop.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct CurveWatchdog {
+    /// The boot the counter was last updated in
+    pub boot_id: String,
+    /// Consecutive boots with offsets applied which did not become stable
+    pub unstable_boots: u32,
+    /// Set when `MAX_UNSTABLE_BOOTS` was exceeded. No offsets are applied
+    /// until this is cleared
+    pub tripped: bool,
+}
+
+impl StdConfig for CurveWatchdog {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "curve_optimizer_watchdog.ron".to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for CurveWatchdog {}
+
+/// The offset to write for a profile with `configured` set, if the SMU does
+/// not already have it. `applied` is the offset last written, `None` if
+/// nothing was written since asusd started
+fn offset_to_write(configured: Option<i32>, applied: Option<i32>) -> Option<i32> {
+    match (configured, applied) {
+        (None, None | Some(0)) => None,
+        (configured, applied) if configured == applied => None,
+        (configured, _) => Some(configured.unwrap_or_default()),
+    }
+}
+
+/// AMD curve optimizer offsets per platform profile
+#[derive(Clone)]
+pub struct CtrlCurveOptimizer {
+    smu: RyzenSmu,
+    platform: RogPlatform,
+    config: Arc<Mutex<Config>>,
+    watchdog: Arc<Mutex<CurveWatchdog>>,
+    /// The offset last written to the SMU
+    applied: Arc<Mutex<Option<i32>>>,
+    supervisor: Supervisor,
+    sleep_hooks: SleepHooks,
+}
+
+impl CtrlCurveOptimizer {
+    pub fn new(
+        platform: RogPlatform,
+        config: Arc<Mutex<Config>>,
+        supervisor: Supervisor,
+        sleep_hooks: SleepHooks,
+    ) -> Result<Self, RogError> {
+        Ok(Self {
+            smu: RyzenSmu::new()?,
+            platform,
+            config,
+            watchdog: Arc::new(Mutex::new(CurveWatchdog::new().load())),
+            applied: Arc::new(Mutex::new(None)),
+            supervisor,
+            sleep_hooks,
+        })
+    }
+
+    /// Count this boot against the watchdog if any offsets are set. Returns
+    /// false if offsets must not be applied.
+    async fn check_watchdog(&self) -> bool {
+        let has_offsets = self
+            .config
+            .lock()
+            .await
+            .curve_optimizer_on_profile
+            .values()
+            .any(|o| *o != 0);
+        let mut watchdog = self.watchdog.lock().await;
+        if watchdog.tripped {
+            warn!("CurveOptimizer: watchdog was tripped, not applying offsets");
+            return false;
+        }
+        let boot_id = boot_id();
+        // A restart of the daemon in the same boot is not a new boot
+        if !has_offsets || watchdog.boot_id == boot_id {
+            return true;
+        }
+        watchdog.boot_id = boot_id;
+        watchdog.unstable_boots += 1;
+        if watchdog.unstable_boots > MAX_UNSTABLE_BOOTS {
+            error!(
+                "CurveOptimizer: {MAX_UNSTABLE_BOOTS} boots in a row did not become stable, \
+                 offsets are disabled until the watchdog is cleared"
+            );
+            watchdog.tripped = true;
+            watchdog.unstable_boots = 0;
+        }
+        watchdog.write();
+        !watchdog.tripped
+    }
+
+    async fn apply_profile(&self, profile: PlatformProfile) -> Result<(), RogError> {
+        if self.watchdog.lock().await.tripped {
+            return Ok(());
+        }
+        let configured = self
+            .config
+            .lock()
+            .await
+            .curve_optimizer_on_profile
+            .get(&profile)
+            .copied();
+        let mut applied = self.applied.lock().await;
+        let Some(offset) = offset_to_write(configured, *applied) else {
+            debug!("CurveOptimizer: offset for {profile:?} is unchanged");
+            return Ok(());
+        };
+        info!("CurveOptimizer: applying offset {offset} for {profile:?}");
+        self.smu.set_all_core_offset(offset)?;
+        *applied = Some(offset);
+        if offset != 0 {
+            mark_risky_applied();
+        }
+        Ok(())
+    }
+
+    async fn apply_current(&self) -> Result<(), RogError> {
+        let profile = self.platform.get_platform_profile()?.into();
+        self.apply_profile(profile).await
+    }
+}
+
+#[interface(name = "xyz.ljones.CurveOptimizer")]
+impl CtrlCurveOptimizer {
+    /// The `(min, max)` offsets accepted
+    #[zbus(property)]
+    fn offset_range(&self) -> (i32, i32) {
+        (CURVE_OPTIMIZER_MIN, CURVE_OPTIMIZER_MAX)
+    }
+
+    /// True if offsets were disabled after repeated unstable boots
+    #[zbus(property)]
+    async fn watchdog_tripped(&self) -> bool {
+        self.watchdog.lock().await.tripped
+    }
+
+    /// The all-core offset applied when `profile` is active
+    async fn offset_for_profile(&self, profile: PlatformProfile) -> i32 {
+        self.config
+            .lock()
+            .await
+            .curve_optimizer_on_profile
+            .get(&profile)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Set the all-core offset for `profile`. Negative values undervolt. This
+    /// is applied immediately if `profile` is active
+    async fn set_offset_for_profile(
+        &mut self,
+        profile: PlatformProfile,
+        offset: i32,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        if !(CURVE_OPTIMIZER_MIN..=CURVE_OPTIMIZER_MAX).contains(&offset) {
+            return Err(FdoErr::InvalidArgs(format!(
+                "CurveOptimizer: offset must be within \
+                 {CURVE_OPTIMIZER_MIN}..={CURVE_OPTIMIZER_MAX}, got {offset}"
+            )));
+        }
+        if self.watchdog.lock().await.tripped {
+            return Err(FdoErr::Failed(
+                "CurveOptimizer: the watchdog was tripped, clear it first".to_owned(),
+            ));
+        }
+        {
+            let mut config = self.config.lock().await;
+            config.curve_optimizer_on_profile.insert(profile, offset);
+            config.write();
+        }
+        if PlatformProfile::from(self.platform.get_platform_profile()?) == profile {
+            self.apply_profile(profile).await?;
+        }
+        Ok(())
+    }
+
+    /// Re-enable offsets after the watchdog was tripped
+    async fn clear_watchdog(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        {
+            let mut watchdog = self.watchdog.lock().await;
+            watchdog.tripped = false;
+            watchdog.unstable_boots = 0;
+            watchdog.boot_id = boot_id();
+            watchdog.write();
+        }
+        info!("CurveOptimizer: watchdog cleared");
+        self.watchdog_tripped_changed(&ctxt).await.ok();
+        self.apply_current().await?;
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_curve_optimizer.rs"));
+
+impl crate::ZbusRun for CtrlCurveOptimizer {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlCurveOptimizer {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        if self.check_watchdog().await {
+            self.apply_current().await?;
+        }
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlCurveOptimizer {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        use futures_util::StreamExt;
+
+        // The SMU does not keep the offsets across suspend
+        let ctrl = self.clone();
+        self.sleep_hooks
+            .on_post_resume("CurveOptimizer", HookOrder::Cpu, move || {
+                let ctrl = ctrl.clone();
+                async move {
+                    *ctrl.applied.lock().await = Some(0);
+                    ctrl.apply_current()
+                        .await
+                        .map_err(|e| error!("CurveOptimizer: reapply failed: {e}"))
+                        .ok();
+                }
+            })
+            .await;
+
+        let ctrl = self.clone();
+        self.supervisor
+            .spawn("CurveOptimizer: platform_profile watch", move || {
+                let ctrl = ctrl.clone();
+                async move {
+                    let watch = ctrl.platform.monitor_platform_profile()?;
+                    let mut buffer = [0; 32];
+                    watch
+                        .into_event_stream(&mut buffer)?
+                        .for_each(|_| async {
+                            debug!("CurveOptimizer: platform_profile changed");
+                            ctrl.apply_current()
+                                .await
+                                .map_err(|e| error!("CurveOptimizer: apply failed: {e}"))
+                                .ok();
+                        })
+                        .await;
+                    Ok::<(), RogError>(())
+                }
+            });
+
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            sleep(STABLE_AFTER).await;
+            let mut watchdog = ctrl.watchdog.lock().await;
+            if watchdog.unstable_boots > 0 {
+                debug!("CurveOptimizer: boot is stable, resetting watchdog");
+                watchdog.unstable_boots = 0;
+                watchdog.write();
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::offset_to_write;
+
+    #[test]
+    fn skip_unchanged_offsets() {
+        // Nothing configured and nothing written, or the SMU is back at 0
+        assert_eq!(offset_to_write(None, None), None);
+        assert_eq!(offset_to_write(None, Some(0)), None);
+        assert_eq!(offset_to_write(Some(-10), Some(-10)), None);
+        // Another profile's offset is undone
+        assert_eq!(offset_to_write(None, Some(-10)), Some(0));
+        assert_eq!(offset_to_write(Some(-10), None), Some(-10));
+        assert_eq!(offset_to_write(Some(0), None), Some(0));
+        assert_eq!(offset_to_write(Some(-5), Some(-10)), Some(-5));
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use config_traits::StdConfig;
+use futures_util::lock::Mutex;
+use log::{debug, error, info, warn};
+use rog_platform::asus_armoury::{AttrValue, Attribute, FirmwareAttribute, FirmwareAttributes};
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize, Action};
+use crate::boot_watchdog::mark_risky_applied;
+use crate::config::Config;
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+use crate::supervisor::Supervisor;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+fn int_or_neg(value: &AttrValue) -> i32 {
+    match value {
+        AttrValue::Integer(i) => *i,
+        _ => -1,
+    }
+}
+
+/// Guarded control of `dgpu_tgp`. Values are checked against the firmware
+/// limits, and if the firmware rejects a write the previous value is put back.
+#[derive(Clone)]
+pub struct CtrlDgpuTgp {
+    tgp: Attribute,
+    base_tgp: Option<Attribute>,
+    config: Arc<Mutex<Config>>,
+    supervisor: Supervisor,
+}
+
+impl CtrlDgpuTgp {
+    pub fn new(
+        attributes: &FirmwareAttributes,
+        config: Arc<Mutex<Config>>,
+        supervisor: Supervisor,
+    ) -> Result<Self, RogError> {
+        let Some(tgp) = attributes.dgpu_tgp() else {
+            return Err(RogError::MissingFunction("DgpuTgp: no dgpu_tgp".into()));
+        };
+        Ok(Self {
+            tgp: tgp.clone(),
+            base_tgp: attributes.dgpu_base_tgp().cloned(),
+            config,
+            supervisor,
+        })
+    }
+
+    fn current(&self) -> i32 {
+        self.tgp
+            .current_value()
+            .map(|v| int_or_neg(&v))
+            .unwrap_or(-1)
+    }
+
+    fn check(&self, value: i32) -> Result<(), FdoErr> {
+        let min = int_or_neg(self.tgp.min_value());
+        let max = int_or_neg(self.tgp.max_value());
+        if (min != -1 && value < min) || (max != -1 && value > max) {
+            return Err(FdoErr::InvalidArgs(format!(
+                "DgpuTgp: {value} is outside of {min}..={max}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Write the value and confirm the firmware took it, reverting to the
+    /// previous value if not
+    fn write_guarded(&self, value: i32) -> Result<(), FdoErr> {
+        self.check(value)?;
+        Policy::load().check_attribute(FirmwareAttribute::DgpuTgp, value)?;
+        let previous = self.current();
+        let result = self
+            .tgp
+            .set_current_value(&AttrValue::Integer(value))
+            .map_err(|e| FdoErr::Failed(format!("DgpuTgp: write rejected: {e}")))
+            .and_then(|_| match self.current() {
+                v if v == value => Ok(()),
+                v => Err(FdoErr::Failed(format!(
+                    "DgpuTgp: firmware reports {v} after writing {value}"
+                ))),
+            });
+
+        if result.is_ok() {
+            mark_risky_applied();
+        }
+        if let Err(e) = &result {
+            error!("{e}");
+            if previous != -1 && previous != value {
+                warn!("DgpuTgp: reverting to {previous}");
+                self.tgp
+                    .set_current_value(&AttrValue::Integer(previous))
+                    .map_err(|e| error!("DgpuTgp: revert failed: {e}"))
+                    .ok();
+            }
+        }
+        result
+    }
+}
+
+#[interface(name = "xyz.ljones.DgpuTgp")]
+impl CtrlDgpuTgp {
+    /// The configurable TGP on top of the base TGP, in watts
+    #[zbus(property)]
+    fn tgp(&self) -> i32 {
+        self.current()
+    }
+
+    #[zbus(property)]
+    async fn set_tgp(
+        &mut self,
+        value: i32,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        self.write_guarded(value)?;
+        let mut config = self.config.lock().await;
+        config
+            .armoury_settings
+            .insert(FirmwareAttribute::DgpuTgp, value);
+        config.write();
+        info!("DgpuTgp: set to {value}");
+        Ok(())
+    }
+
+    /// `-1` if there is no limit
+    #[zbus(property)]
+    fn tgp_min(&self) -> i32 {
+        int_or_neg(self.tgp.min_value())
+    }
+
+    /// `-1` if there is no limit
+    #[zbus(property)]
+    fn tgp_max(&self) -> i32 {
+        int_or_neg(self.tgp.max_value())
+    }
+
+    /// `-1` if there is no default
+    #[zbus(property)]
+    fn tgp_default(&self) -> i32 {
+        int_or_neg(self.tgp.default_value())
+    }
+
+    /// The fixed base TGP, `-1` if not available
+    #[zbus(property)]
+    fn base_tgp(&self) -> i32 {
+        self.base_tgp
+            .as_ref()
+            .and_then(|a| a.current_value().ok())
+            .map(|v| int_or_neg(&v))
+            .unwrap_or(-1)
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_dgpu_tgp.rs"));
+
+impl crate::ZbusRun for CtrlDgpuTgp {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlDgpuTgp {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        let stored = self
+            .config
+            .lock()
+            .await
+            .armoury_settings
+            .get(&FirmwareAttribute::DgpuTgp)
+            .copied();
+        if let Some(value) = stored {
+            if value != self.current() {
+                info!("Reloading dgpu_tgp to {value}");
+                self.write_guarded(value)
+                    .map_err(|e| RogError::ReloadFail(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlDgpuTgp {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        use futures_util::StreamExt;
+
+        let ctrl = self.clone();
+        self.supervisor.spawn("DgpuTgp: dgpu_tgp watch", move || {
+            let ctrl = ctrl.clone();
+            let signal_ctxt = signal_ctxt.clone();
+            async move {
+                let watch = ctrl.tgp.get_watcher("current_value")?;
+                let mut buffer = [0; 32];
+                watch
+                    .into_event_stream(&mut buffer)?
+                    .for_each(|_| async {
+                        debug!("DgpuTgp: dgpu_tgp changed");
+                        ctrl.tgp_changed(&signal_ctxt).await.ok();
+                    })
+                    .await;
+                Ok::<(), RogError>(())
+            }
+        });
+        Ok(())
+    }
+}
@@ -1,29 +1,50 @@
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use config_traits::{StdConfig, StdConfigLoad};
 use futures_lite::StreamExt;
 use futures_util::lock::Mutex;
 use log::{debug, error, info, warn};
 use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_profiles::acoustic::AcousticPreset;
 use rog_profiles::error::ProfileError;
 use rog_profiles::fan_curve_set::CurveData;
 use rog_profiles::{find_fan_curve_node, FanCurvePU, FanCurveProfiles};
 use serde::{Deserialize, Serialize};
+use zbus::message::Header;
+use zbus::names::BusName;
 use zbus::object_server::SignalEmitter;
 use zbus::{interface, Connection};
 
+use crate::authorization::{authorize, authorize_interactive, Action};
 use crate::error::RogError;
+use crate::fan_boost::{BoostState, CpuSample, ThrottleBoost, AGGRESSIVENESS_MAX, SAMPLE_INTERVAL};
+use crate::hwmon::{fan_speeds, hwmon_temp, CPU_HWMON, GPU_HWMON};
+use crate::policy::{Lockdown, Policy};
+use crate::supervisor::Supervisor;
 use crate::{CtrlTask, CONFIG_PATH_BASE};
 
 pub const FAN_CURVE_ZBUS_NAME: &str = "FanCurves";
 pub const FAN_CURVE_ZBUS_PATH: &str = "/xyz/ljones";
+/// The longest a curve can be tested for before it is reverted
+const FAN_TEST_MAX_SECS: u32 = 120;
 
 #[derive(Deserialize, Serialize, Debug, Default)]
 pub struct FanCurveConfig {
     pub profiles: FanCurveProfiles,
+    #[serde(default)]
+    pub throttle_boost: ThrottleBoost,
     #[serde(skip)]
     pub current: PlatformProfile,
+    /// Set while the boosted curves are written in place of the stored ones
+    #[serde(skip)]
+    pub boost_active: bool,
+    /// The id of the curve test running, if any
+    #[serde(skip)]
+    pub test: Option<u64>,
+    #[serde(skip)]
+    pub tests_started: u64,
 }
 
 impl StdConfig for FanCurveConfig {
@@ -48,11 +69,12 @@ impl StdConfigLoad for FanCurveConfig {}
 pub struct CtrlFanCurveZbus {
     config: Arc<Mutex<FanCurveConfig>>,
     platform: RogPlatform,
+    supervisor: Supervisor,
 }
 
 // Non-zbus-derive impl
 impl CtrlFanCurveZbus {
-    pub fn new() -> Result<Self, RogError> {
+    pub fn new(supervisor: Supervisor) -> Result<Self, RogError> {
         let platform = RogPlatform::new()?;
         if platform.has_platform_profile() {
             info!("Device has profile control available");
@@ -91,11 +113,175 @@ impl CtrlFanCurveZbus {
             return Ok(Self {
                 config: Arc::new(Mutex::new(config)),
                 platform,
+                supervisor,
             });
         }
 
         Err(ProfileError::NotSupported.into())
     }
+
+    /// The stored curves of every profile
+    pub async fn profiles(&self) -> FanCurveProfiles {
+        self.config.lock().await.profiles.clone()
+    }
+
+    /// Replace the curves of every profile and write those of the active
+    /// profile
+    pub async fn restore_profiles(&self, profiles: FanCurveProfiles) -> Result<(), RogError> {
+        let active = self.platform.get_platform_profile()?.into();
+        let mut config = self.config.lock().await;
+        config.profiles = profiles;
+        config
+            .profiles
+            .write_profile_curve_to_platform(active, &mut find_fan_curve_node()?)?;
+        config.write();
+        Ok(())
+    }
+
+    /// Reset the curves of every profile to the platform defaults. Each
+    /// profile is switched to in turn to read its defaults, then the active
+    /// profile is put back.
+    pub async fn reset_to_defaults(&self) -> Result<(), RogError> {
+        let active = self.platform.get_platform_profile()?;
+        let mut config = self.config.lock().await;
+        for profile in self.platform.get_platform_profile_choices()? {
+            self.platform.set_platform_profile(profile.into())?;
+            config
+                .profiles
+                .set_active_curve_to_defaults(profile, &mut find_fan_curve_node()?)?;
+        }
+        self.platform.set_platform_profile(active.as_str())?;
+        config.write();
+        Ok(())
+    }
+
+    /// Write the boosted curves of the active profile, or the stored curves
+    /// if `boost` is false
+    fn write_boost(
+        config: &mut FanCurveConfig,
+        profile: PlatformProfile,
+        boost: bool,
+    ) -> Result<(), RogError> {
+        let mut device = find_fan_curve_node()?;
+        if !boost {
+            config
+                .profiles
+                .write_profile_curve_to_platform(profile, &mut device)?;
+            return Ok(());
+        }
+        for curve in config.profiles.get_fan_curves_for(profile) {
+            config
+                .throttle_boost
+                .boosted(curve)
+                .write_to_device(&mut device)?;
+        }
+        Ok(())
+    }
+
+    /// Put back the curves a test replaced, if test `id` is still running.
+    /// Returns false if it was stopped or replaced
+    async fn end_test(&self, id: u64) -> bool {
+        let mut config = self.config.lock().await;
+        if config.test != Some(id) {
+            return false;
+        }
+        config.test = None;
+        let boost = config.boost_active;
+        match self.platform.get_platform_profile() {
+            Ok(profile) => Self::write_boost(&mut config, profile.into(), boost)
+                .map_err(|e| warn!("Fan curve test: could not put the curves back: {e}"))
+                .ok(),
+            Err(e) => {
+                warn!("Fan curve test: could not read the platform profile: {e}");
+                None
+            }
+        };
+        true
+    }
+
+    /// Send a sample every second for `seconds` to `emitter`, then end test
+    /// `id`
+    fn start_test_task(&self, id: u64, seconds: u32, emitter: SignalEmitter<'static>) {
+        let ctrl = self.clone();
+        tokio::spawn(async move {
+            for remaining in (0..seconds).rev() {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                if ctrl.config.lock().await.test != Some(id) {
+                    return;
+                }
+                let temps: Vec<(String, f64)> = [
+                    ("cpu", hwmon_temp(CPU_HWMON)),
+                    ("gpu", hwmon_temp(GPU_HWMON)),
+                ]
+                .into_iter()
+                .filter_map(|(name, temp)| Some((name.to_owned(), f64::from(temp?))))
+                .collect();
+                Self::fan_curve_test_sample(&emitter, remaining, fan_speeds(), temps)
+                    .await
+                    .ok();
+            }
+            if ctrl.end_test(id).await {
+                info!("Fan curve test {id} finished, curves put back");
+                Self::fan_curve_test_ended(&emitter).await.ok();
+            }
+        });
+    }
+
+    /// Check the CPU every `SAMPLE_INTERVAL` while the throttle boost is
+    /// enabled, boosting the fans when it throttles for a while
+    fn start_throttle_boost_task(&self, signal_ctxt: SignalEmitter<'static>) {
+        let ctrl = self.clone();
+        self.supervisor.spawn("FanCurves: throttle boost", move || {
+            let ctrl = ctrl.clone();
+            let signal_ctxt = signal_ctxt.clone();
+            async move {
+                let mut state = BoostState::default();
+                // The profile the boost was written for, to write it again after
+                // a profile change writes the stored curves
+                let mut boosted_profile = None;
+                loop {
+                    tokio::time::sleep(SAMPLE_INTERVAL).await;
+                    let mut config = ctrl.config.lock().await;
+                    if (!config.throttle_boost.enabled && !state.active()) || config.test.is_some()
+                    {
+                        continue;
+                    }
+                    let Ok(profile) = ctrl
+                        .platform
+                        .get_platform_profile()
+                        .map(PlatformProfile::from)
+                    else {
+                        continue;
+                    };
+                    let sample = CpuSample::read();
+                    let change = state.update(&config.throttle_boost, sample);
+                    let rewrite = state.active() && boosted_profile != Some(profile);
+                    if change.is_none() && !rewrite {
+                        continue;
+                    }
+                    if change == Some(true) {
+                        info!(
+                            "CPU is thermal throttling at {:?}°C, boosting the fans",
+                            sample.temp
+                        );
+                    } else if change == Some(false) {
+                        info!("CPU has cooled, putting the fan curves back");
+                    }
+                    match Self::write_boost(&mut config, profile, state.active()) {
+                        Ok(()) => {
+                            boosted_profile = state.active().then_some(profile);
+                        }
+                        Err(e) => warn!("Throttle boost: could not write the fan curves: {e}"),
+                    }
+                    config.boost_active = state.active();
+                    drop(config);
+                    if change.is_some() {
+                        ctrl.throttle_boost_active_changed(&signal_ctxt).await.ok();
+                    }
+                }
+            }
+        });
+    }
 }
 
 #[interface(name = "xyz.ljones.FanCurves")]
@@ -106,7 +292,11 @@ impl CtrlFanCurveZbus {
         &mut self,
         profile: PlatformProfile,
         enabled: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
     ) -> zbus::fdo::Result<()> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
         self.config
             .lock()
             .await
@@ -128,7 +318,11 @@ impl CtrlFanCurveZbus {
         profile: PlatformProfile,
         fan: FanCurvePU,
         enabled: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
     ) -> zbus::fdo::Result<()> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
         self.config
             .lock()
             .await
@@ -164,7 +358,13 @@ impl CtrlFanCurveZbus {
         &mut self,
         profile: PlatformProfile,
         curve: CurveData,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
     ) -> zbus::fdo::Result<()> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
+        let acoustic = self.config.lock().await.profiles.acoustic;
+        acoustic.check(&curve)?;
         self.config
             .lock()
             .await
@@ -187,7 +387,14 @@ impl CtrlFanCurveZbus {
     ///
     /// Each platform_profile has a different default and the default can be
     /// read only for the currently active profile.
-    async fn set_curves_to_defaults(&mut self, profile: PlatformProfile) -> zbus::fdo::Result<()> {
+    async fn set_curves_to_defaults(
+        &mut self,
+        profile: PlatformProfile,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
         let active = self.platform.get_platform_profile()?;
         self.platform.set_platform_profile(profile.into())?;
         self.config
@@ -200,12 +407,88 @@ impl CtrlFanCurveZbus {
         Ok(())
     }
 
+    /// Write `curve` to its fan for `seconds`, at most 120, without storing
+    /// it, then put the stored curves back. While it runs the fan speeds and
+    /// temperatures are sent to the caller each second in
+    /// `FanCurveTestSample`, and `FanCurveTestEnded` once the curves are put
+    /// back. A new test replaces one which is running.
+    async fn test_fan_curve(
+        &self,
+        mut curve: CurveData,
+        seconds: u32,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
+        if !(1..=FAN_TEST_MAX_SECS).contains(&seconds) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "A test runs for 1 to {FAN_TEST_MAX_SECS} seconds"
+            )));
+        }
+        let emitter = SignalEmitter::new(conn, FAN_CURVE_ZBUS_PATH)?;
+        let emitter = match header.sender() {
+            Some(sender) => emitter.set_destination(BusName::Unique(sender.to_owned())),
+            None => emitter,
+        };
+
+        let mut config = self.config.lock().await;
+        curve.enabled = true;
+        curve
+            .write_to_device(&mut find_fan_curve_node()?)
+            .map_err(ProfileError::from)?;
+        config.tests_started += 1;
+        let id = config.tests_started;
+        config.test = Some(id);
+        info!("Testing fan curve {} for {seconds}s", String::from(&curve));
+        drop(config);
+        self.start_test_task(id, seconds, emitter);
+        Ok(())
+    }
+
+    /// End the running curve test now and put the stored curves back
+    async fn stop_fan_curve_test(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Power).await?;
+        let Some(id) = self.config.lock().await.test else {
+            return Ok(());
+        };
+        if self.end_test(id).await {
+            Self::fan_curve_test_ended(&emitter).await?;
+        }
+        Ok(())
+    }
+
+    /// Sent to the caller of `TestFanCurve` each second with the seconds
+    /// left, the speed of each fan in RPM, and the CPU and GPU temperatures
+    #[zbus(signal)]
+    async fn fan_curve_test_sample(
+        emitter: &SignalEmitter<'_>,
+        remaining: u32,
+        fans: Vec<(String, u32)>,
+        temps: Vec<(String, f64)>,
+    ) -> zbus::Result<()>;
+
+    /// The test curve was replaced by the stored curves again
+    #[zbus(signal)]
+    async fn fan_curve_test_ended(emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
     /// Reset the stored (self) and device curve to the defaults of the
     /// platform.
     ///
     /// Each platform_profile has a different default and the defualt can be
     /// read only for the currently active profile.
-    async fn reset_profile_curves(&self, profile: PlatformProfile) -> zbus::fdo::Result<()> {
+    async fn reset_profile_curves(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        profile: PlatformProfile,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Power).await?;
         let active = self.platform.get_platform_profile()?;
 
         self.platform.set_platform_profile(profile.into())?;
@@ -219,8 +502,131 @@ impl CtrlFanCurveZbus {
         self.config.lock().await.write();
         Ok(())
     }
+
+    /// Raise the fan curves of the active profile while the CPU is thermal
+    /// throttling, and put them back once it has cooled
+    #[zbus(property)]
+    async fn throttle_boost_enabled(&self) -> bool {
+        self.config.lock().await.throttle_boost.enabled
+    }
+
+    #[zbus(property)]
+    async fn set_throttle_boost_enabled(
+        &mut self,
+        enabled: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
+        let mut config = self.config.lock().await;
+        config.throttle_boost.enabled = enabled;
+        config.write();
+        Ok(())
+    }
+
+    /// 1 to 5, each step adds about 10% fan speed to every point of the
+    /// boosted curves
+    #[zbus(property)]
+    async fn throttle_boost_aggressiveness(&self) -> u8 {
+        self.config.lock().await.throttle_boost.aggressiveness
+    }
+
+    #[zbus(property)]
+    async fn set_throttle_boost_aggressiveness(
+        &mut self,
+        aggressiveness: u8,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if !(1..=AGGRESSIVENESS_MAX).contains(&aggressiveness) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Aggressiveness must be 1 to {AGGRESSIVENESS_MAX}"
+            )));
+        }
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
+        let mut config = self.config.lock().await;
+        config.throttle_boost.aggressiveness = aggressiveness;
+        config.write();
+        Ok(())
+    }
+
+    /// The highest fan speed in percent the boost raises a curve point to
+    #[zbus(property)]
+    async fn throttle_boost_cap(&self) -> u8 {
+        self.config.lock().await.throttle_boost.cap
+    }
+
+    #[zbus(property)]
+    async fn set_throttle_boost_cap(
+        &mut self,
+        cap: u8,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if cap > 100 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "The cap is a percentage, 0 to 100".to_owned(),
+            ));
+        }
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
+        let mut config = self.config.lock().await;
+        config.throttle_boost.cap = cap;
+        config.write();
+        Ok(())
+    }
+
+    /// If the boosted curves are written now
+    #[zbus(property)]
+    async fn throttle_boost_active(&self) -> bool {
+        self.config.lock().await.boost_active
+    }
+
+    /// Caps the fan duty of the curves below 85°C to roughly reach a noise
+    /// level: `Off`, `Silent` under about 30dBA, `Balanced` under about
+    /// 40dBA, or `Max` with no cap. The throttle boost still raises the
+    /// curves above the cap while it is active.
+    #[zbus(property)]
+    async fn acoustic_preset(&self) -> AcousticPreset {
+        self.config.lock().await.profiles.acoustic
+    }
+
+    #[zbus(property)]
+    async fn set_acoustic_preset(
+        &mut self,
+        preset: AcousticPreset,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::FanCurves)?;
+        let mut config = self.config.lock().await;
+        for profile in [
+            PlatformProfile::Balanced,
+            PlatformProfile::Performance,
+            PlatformProfile::Quiet,
+            PlatformProfile::Custom,
+        ] {
+            for curve in config.profiles.get_fan_curves_for(profile) {
+                preset.check(curve)?;
+            }
+        }
+        config.profiles.acoustic = preset;
+        if config.test.is_none() {
+            let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
+            let boost = config.boost_active;
+            Self::write_boost(&mut config, profile, boost)?;
+        }
+        config.write();
+        Ok(())
+    }
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_fancurves.rs"));
+
 impl crate::ZbusRun for CtrlFanCurveZbus {
     async fn add_to_server(self, server: &mut Connection) {
         Self::add_to_server_helper(self, FAN_CURVE_ZBUS_PATH, server).await;
@@ -232,42 +638,46 @@ impl CtrlTask for CtrlFanCurveZbus {
         FAN_CURVE_ZBUS_PATH
     }
 
-    async fn create_tasks(&self, _signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
-        let watch_platform_profile = self.platform.monitor_platform_profile()?;
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        self.start_throttle_boost_task(signal_ctxt);
+
+        // Fail here rather than restart the watch forever
+        self.platform.monitor_platform_profile()?;
         let platform = self.platform.clone();
         let config = self.config.clone();
-        let fan_curves = self.config.clone();
-
-        tokio::spawn(async move {
-            let mut buffer = [0; 32];
-            if let Ok(mut stream) = watch_platform_profile.into_event_stream(&mut buffer) {
-                while (stream.next().await).is_some() {
-                    debug!("watch_platform_profile changed");
-                    if let Ok(profile) =
-                        platform
+        self.supervisor
+            .spawn("FanCurves: platform_profile watch", move || {
+                let platform = platform.clone();
+                let config = config.clone();
+                async move {
+                    let watch_platform_profile = platform.monitor_platform_profile()?;
+                    let mut buffer = [0; 32];
+                    let mut stream = watch_platform_profile.into_event_stream(&mut buffer)?;
+                    while (stream.next().await).is_some() {
+                        debug!("watch_platform_profile changed");
+                        let Ok(profile) = platform
                             .get_platform_profile()
-                            .map(|p| p.into())
-                            .map_err(|e| {
-                                error!("get_platform_profile error: {e}");
-                            })
-                    {
-                        if profile != config.lock().await.current {
-                            fan_curves
-                                .lock()
-                                .await
+                            .map(PlatformProfile::from)
+                            .map_err(|e| error!("get_platform_profile error: {e}"))
+                        else {
+                            continue;
+                        };
+                        let mut config = config.lock().await;
+                        if profile != config.current {
+                            config
                                 .profiles
                                 .write_profile_curve_to_platform(
                                     profile,
-                                    &mut find_fan_curve_node().unwrap(),
+                                    &mut find_fan_curve_node()?,
                                 )
                                 .map_err(|e| warn!("write_profile_curve_to_platform, {}", e))
                                 .ok();
-                            config.lock().await.current = profile;
+                            config.current = profile;
                         }
                     }
+                    Ok::<(), RogError>(())
                 }
-            }
-        });
+            });
 
         Ok(())
     }
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use config_traits::StdConfig;
+use futures_util::lock::Mutex;
+use log::{debug, error, info};
+use rog_platform::asus_armoury::{
+    AttrValue, Attribute, FirmwareAttribute, FirmwareAttributes, GpuThermalPreset,
+};
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize_interactive, Action};
+use crate::config::Config;
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+use crate::supervisor::Supervisor;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+/// The presets which can be applied directly, in the order they are matched
+const PRESETS: [GpuThermalPreset; 3] = [
+    GpuThermalPreset::Cool,
+    GpuThermalPreset::Balanced,
+    GpuThermalPreset::Max,
+];
+
+fn int_of(value: &AttrValue) -> Option<i32> {
+    match value {
+        AttrValue::Integer(i) => Some(*i),
+        _ => None,
+    }
+}
+
+/// The value an attribute should take for a preset, or `-1` if the attribute
+/// is not available
+fn preset_value(attr: Option<&Attribute>, preset: GpuThermalPreset) -> i32 {
+    let Some(attr) = attr else {
+        return -1;
+    };
+    let min = int_of(attr.min_value());
+    let max = int_of(attr.max_value());
+    match preset {
+        GpuThermalPreset::Cool => min,
+        GpuThermalPreset::Max => max,
+        GpuThermalPreset::Balanced => int_of(attr.default_value()).or_else(|| {
+            let (min, max) = (min?, max?);
+            Some(min + (max - min) / 2)
+        }),
+        GpuThermalPreset::Custom => None,
+    }
+    .unwrap_or(-1)
+}
+
+fn current_value(attr: Option<&Attribute>) -> i32 {
+    attr.and_then(|a| a.current_value().ok().as_ref().and_then(int_of))
+        .unwrap_or(-1)
+}
+
+/// Check a single value against the attribute limits. A missing attribute
+/// only accepts `-1`
+fn check_value(attr: Option<&Attribute>, value: i32) -> Result<(), FdoErr> {
+    let Some(attr) = attr else {
+        if value == -1 {
+            return Ok(());
+        }
+        return Err(FdoErr::NotSupported(
+            "GpuThermalPolicy: attribute is not available".to_string(),
+        ));
+    };
+    let min = int_of(attr.min_value()).unwrap_or(i32::MIN);
+    let max = int_of(attr.max_value()).unwrap_or(i32::MAX);
+    if !(min..=max).contains(&value) {
+        return Err(FdoErr::InvalidArgs(format!(
+            "GpuThermalPolicy: {} must be within {min}..={max}, got {value}",
+            attr.name()
+        )));
+    }
+    Ok(())
+}
+
+/// Bundles `nv_dynamic_boost` and `nv_temp_target` in to a single policy so
+/// that both are validated and applied together
+#[derive(Clone)]
+pub struct CtrlGpuThermalPolicy {
+    attributes: FirmwareAttributes,
+    config: Arc<Mutex<Config>>,
+    supervisor: Supervisor,
+}
+
+impl CtrlGpuThermalPolicy {
+    pub fn new(
+        attributes: FirmwareAttributes,
+        config: Arc<Mutex<Config>>,
+        supervisor: Supervisor,
+    ) -> Result<Self, RogError> {
+        if attributes.nv_dynamic_boost().is_none() && attributes.nv_temp_target().is_none() {
+            return Err(RogError::MissingFunction(
+                "GpuThermalPolicy: no nv_dynamic_boost or nv_temp_target".into(),
+            ));
+        }
+        Ok(Self {
+            attributes,
+            config,
+            supervisor,
+        })
+    }
+
+    fn values_for(&self, preset: GpuThermalPreset) -> (i32, i32) {
+        (
+            preset_value(self.attributes.nv_dynamic_boost(), preset),
+            preset_value(self.attributes.nv_temp_target(), preset),
+        )
+    }
+
+    fn current_values(&self) -> (i32, i32) {
+        (
+            current_value(self.attributes.nv_dynamic_boost()),
+            current_value(self.attributes.nv_temp_target()),
+        )
+    }
+
+    /// Validate the pair before anything is written, then write both. If the
+    /// second write fails the first is reverted so the policy is never left
+    /// half applied.
+    async fn apply(&self, dynamic_boost: i32, temp_target: i32) -> Result<(), FdoErr> {
+        let boost_attr = self.attributes.nv_dynamic_boost();
+        let temp_attr = self.attributes.nv_temp_target();
+        check_value(boost_attr, dynamic_boost)?;
+        check_value(temp_attr, temp_target)?;
+        let policy = Policy::load();
+        policy.check_attribute(FirmwareAttribute::NvDynamicBoost, dynamic_boost)?;
+        policy.check_attribute(FirmwareAttribute::NvTempTarget, temp_target)?;
+
+        let (old_boost, _) = self.current_values();
+        if let Some(attr) = boost_attr {
+            attr.set_current_value(&AttrValue::Integer(dynamic_boost))
+                .map_err(|e| {
+                    error!("GpuThermalPolicy: could not set nv_dynamic_boost: {e:?}");
+                    e
+                })?;
+        }
+        if let Some(attr) = temp_attr {
+            if let Err(e) = attr.set_current_value(&AttrValue::Integer(temp_target)) {
+                error!("GpuThermalPolicy: could not set nv_temp_target: {e:?}");
+                if let Some(boost) = boost_attr.filter(|_| old_boost != -1) {
+                    boost
+                        .set_current_value(&AttrValue::Integer(old_boost))
+                        .map_err(|e| error!("GpuThermalPolicy: rollback failed: {e:?}"))
+                        .ok();
+                }
+                return Err(e.into());
+            }
+        }
+
+        let mut config = self.config.lock().await;
+        if boost_attr.is_some() {
+            config
+                .armoury_settings
+                .insert(FirmwareAttribute::NvDynamicBoost, dynamic_boost);
+        }
+        if temp_attr.is_some() {
+            config
+                .armoury_settings
+                .insert(FirmwareAttribute::NvTempTarget, temp_target);
+        }
+        config.write();
+        info!("GpuThermalPolicy: set dynamic boost {dynamic_boost}, temp target {temp_target}");
+        Ok(())
+    }
+
+    async fn notify_all(&self, ctxt: &SignalEmitter<'_>) {
+        self.preset_changed(ctxt).await.ok();
+        self.dynamic_boost_changed(ctxt).await.ok();
+        self.temp_target_changed(ctxt).await.ok();
+    }
+}
+
+#[interface(name = "xyz.ljones.GpuThermalPolicy")]
+impl CtrlGpuThermalPolicy {
+    /// The preset matching the current values, or `Custom` if none match
+    #[zbus(property)]
+    fn preset(&self) -> GpuThermalPreset {
+        let current = self.current_values();
+        PRESETS
+            .into_iter()
+            .find(|p| self.values_for(*p) == current)
+            .unwrap_or(GpuThermalPreset::Custom)
+    }
+
+    /// The current `nv_dynamic_boost`, `-1` if not available
+    #[zbus(property)]
+    fn dynamic_boost(&self) -> i32 {
+        current_value(self.attributes.nv_dynamic_boost())
+    }
+
+    /// The current `nv_temp_target`, `-1` if not available
+    #[zbus(property)]
+    fn temp_target(&self) -> i32 {
+        current_value(self.attributes.nv_temp_target())
+    }
+
+    /// The `(dynamic_boost, temp_target)` pair a preset resolves to on this
+    /// machine. `-1` for an attribute that is not available
+    fn preset_values(&self, preset: GpuThermalPreset) -> Result<(i32, i32), FdoErr> {
+        if preset == GpuThermalPreset::Custom {
+            return Err(FdoErr::InvalidArgs(
+                "GpuThermalPolicy: Custom has no values".to_string(),
+            ));
+        }
+        Ok(self.values_for(preset))
+    }
+
+    /// Apply one of the named presets
+    async fn apply_preset(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        preset: GpuThermalPreset,
+        #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        let (boost, temp) = self.preset_values(preset)?;
+        self.apply(boost, temp).await?;
+        self.notify_all(&ctxt).await;
+        Ok(())
+    }
+
+    /// Apply both values together. Use `-1` for an attribute that is not
+    /// available
+    async fn set_policy(
+        &self,
+        dynamic_boost: i32,
+        temp_target: i32,
+        #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        self.apply(dynamic_boost, temp_target).await?;
+        self.notify_all(&ctxt).await;
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_gpu_policy.rs"));
+
+impl crate::ZbusRun for CtrlGpuThermalPolicy {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlGpuThermalPolicy {
+    /// Restore the last applied policy
+    async fn reload(&mut self) -> Result<(), RogError> {
+        let (boost, temp) = {
+            let config = self.config.lock().await;
+            let (cur_boost, cur_temp) = self.current_values();
+            (
+                config
+                    .armoury_settings
+                    .get(&FirmwareAttribute::NvDynamicBoost)
+                    .copied()
+                    .unwrap_or(cur_boost),
+                config
+                    .armoury_settings
+                    .get(&FirmwareAttribute::NvTempTarget)
+                    .copied()
+                    .unwrap_or(cur_temp),
+            )
+        };
+        if (boost, temp) != self.current_values() {
+            info!("Reloading GPU thermal policy");
+            self.apply(boost, temp)
+                .await
+                .map_err(|e| RogError::ReloadFail(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlGpuThermalPolicy {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        use futures_util::StreamExt;
+
+        // Values may also be changed through the individual asus_armoury
+        // attributes, keep the preset in sync with those
+        for attr in [
+            self.attributes.nv_dynamic_boost(),
+            self.attributes.nv_temp_target(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let attr = attr.clone();
+            let ctrl = self.clone();
+            let sig = signal_ctxt.clone();
+            let name = attr.name().to_string();
+            self.supervisor
+                .spawn(&format!("GpuThermalPolicy: {name} watch"), move || {
+                    let attr = attr.clone();
+                    let ctrl = ctrl.clone();
+                    let sig = sig.clone();
+                    let name = name.clone();
+                    async move {
+                        let watch = attr.get_watcher("current_value")?;
+                        let mut buffer = [0; 32];
+                        watch
+                            .into_event_stream(&mut buffer)?
+                            .for_each(|_| async {
+                                debug!("GpuThermalPolicy: {name} changed");
+                                ctrl.notify_all(&sig).await;
+                            })
+                            .await;
+                        Ok::<(), RogError>(())
+                    }
+                });
+        }
+        Ok(())
+    }
+}
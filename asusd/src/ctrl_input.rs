@@ -0,0 +1,111 @@
+use log::{debug, info, warn};
+use rog_platform::hotkeys::{
+    hotkey_devices, hotkey_name, key_press, HotkeyDevice, HotkeySource, HOTKEY_CODES,
+    INPUT_EVENT_SIZE,
+};
+use tokio::io::AsyncReadExt;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::error::RogError;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+/// Relays the ASUS hotkeys, such as the ROG key, the Aura keys, and the fan
+/// mode key, as one signal so clients don't each need to read the input
+/// devices. Only the ASUS hotkeys are sent, never other keys. Loaded if the
+/// laptop has `asus-nb-wmi` hotkeys or an ASUS USB keyboard.
+#[derive(Clone)]
+pub struct CtrlInput {
+    devices: Vec<HotkeyDevice>,
+}
+
+impl CtrlInput {
+    pub fn new() -> Result<Self, RogError> {
+        Ok(Self {
+            devices: hotkey_devices()?,
+        })
+    }
+
+    fn sources(&self) -> impl Iterator<Item = HotkeySource> + '_ {
+        self.devices.iter().map(|device| device.source)
+    }
+}
+
+#[interface(name = "xyz.ljones.Input")]
+impl CtrlInput {
+    /// The names of the hotkeys this laptop can send in `Hotkey`
+    #[zbus(property)]
+    fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self
+            .sources()
+            .flat_map(|source| {
+                HOTKEY_CODES
+                    .iter()
+                    .filter_map(move |code| hotkey_name(source, *code))
+            })
+            .map(str::to_owned)
+            .collect();
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    /// An ASUS hotkey was pressed. `name` is one of `Keys` and `code` is the
+    /// Linux key code it was reported with
+    #[zbus(signal)]
+    async fn hotkey(emitter: &SignalEmitter<'_>, name: &str, code: u16) -> zbus::Result<()>;
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_input.rs"));
+
+impl crate::ZbusRun for CtrlInput {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlInput {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlInput {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    /// Read each hotkey device and send the named key presses
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        for device in self.devices.clone() {
+            let signal_ctxt = signal_ctxt.clone();
+            tokio::spawn(async move {
+                let mut file = match tokio::fs::File::open(&device.node).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        warn!("Input: could not open {:?}: {e}", device.node);
+                        return;
+                    }
+                };
+                info!("Input: relaying hotkeys from {:?}", device.node);
+                let mut event = [0; INPUT_EVENT_SIZE];
+                while file.read_exact(&mut event).await.is_ok() {
+                    let Some(code) = key_press(&event) else {
+                        continue;
+                    };
+                    let Some(name) = hotkey_name(device.source, code) else {
+                        continue;
+                    };
+                    debug!("Input: hotkey {name} ({code})");
+                    Self::hotkey(&signal_ctxt, name, code)
+                        .await
+                        .map_err(|e| warn!("Input: could not send {name}: {e}"))
+                        .ok();
+                }
+                info!("Input: {:?} was removed", device.node);
+            });
+        }
+        Ok(())
+    }
+}
@@ -1,24 +1,42 @@
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use config_traits::StdConfig;
 use futures_util::lock::Mutex;
 use log::{debug, error, info, warn};
-use rog_platform::asus_armoury::{AttrValue, FirmwareAttribute, FirmwareAttributes};
+use rog_platform::asus_armoury::{AttrValue, Attribute, FirmwareAttribute, FirmwareAttributes};
 use rog_platform::cpu::{CPUControl, CPUGovernor, CPUEPP};
-use rog_platform::platform::{PlatformProfile, Properties, RogPlatform};
+use rog_platform::dgpu::{dgpu_power, is_discrete_gpu};
+use rog_platform::display::{internal_panel_gpu, internal_refresh_rate};
+use rog_platform::platform::{GpuMode, PlatformProfile, Properties, RogPlatform};
 use rog_platform::power::AsusPower;
+use tokio::time::{sleep, sleep_until, Instant};
 use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::names::BusName;
 use zbus::object_server::SignalEmitter;
 use zbus::{interface, Connection};
 
 use crate::asus_armoury::set_config_or_default;
-use crate::config::Config;
+use crate::authorization::{authorize, authorize_interactive, Action};
+use crate::boot_watchdog::{is_risky, mark_risky_applied};
+use crate::config::{Config, QueuedMuxSwitch};
 use crate::error::RogError;
+use crate::hooks::{run_hooks, HookEvent};
+use crate::logging::{self, LogFilter};
+use crate::policy::{Lockdown, Policy};
+use crate::sessions::{self, SeatProxy};
+use crate::sleep_hooks::{HookOrder, SleepHooks};
+use crate::supervisor::Supervisor;
 use crate::{task_watch_item, CtrlTask, ReloadAndNotify};
 
 const PLATFORM_ZBUS_PATH: &str = "/xyz/ljones";
+/// GPU modes are left to supergfxd when it is running
+pub(crate) const SUPERGFXD_NAME: &str = "org.supergfxctl.Daemon";
 
 macro_rules! platform_get_value {
     ($self:ident, $property:tt, $prop_name:literal) => {
@@ -39,6 +57,18 @@ macro_rules! platform_get_value {
     }
 }
 
+/// Attribute writes on trial, reverted unless confirmed before the deadline
+#[derive(Debug, Clone)]
+struct AttributeTrial {
+    deadline: Instant,
+    /// The power source and profile the values were tried on, whose tuning
+    /// group they are confirmed into. The trial is reverted if either changes
+    on_ac: bool,
+    profile: PlatformProfile,
+    /// Every write made, in order, as `(attribute, previous, new)`
+    writes: Vec<(FirmwareAttribute, i32, i32)>,
+}
+
 #[derive(Clone)]
 pub struct CtrlPlatform {
     power: AsusPower,
@@ -46,6 +76,11 @@ pub struct CtrlPlatform {
     attributes: FirmwareAttributes,
     cpu_control: Option<CPUControl>,
     config: Arc<Mutex<Config>>,
+    supervisor: Supervisor,
+    sleep_hooks: SleepHooks,
+    attribute_trial: Arc<Mutex<Option<AttributeTrial>>>,
+    /// Set while the `panel_od_auto` task is running
+    panel_od_auto_running: Arc<AtomicBool>,
 }
 
 impl CtrlPlatform {
@@ -56,6 +91,8 @@ impl CtrlPlatform {
         config: Arc<Mutex<Config>>,
         config_path: &Path,
         signal_context: SignalEmitter<'static>,
+        supervisor: Supervisor,
+        sleep_hooks: SleepHooks,
     ) -> Result<Self, RogError> {
         let config1 = config.clone();
         let config_path = config_path.to_owned();
@@ -68,61 +105,140 @@ impl CtrlPlatform {
             cpu_control: CPUControl::new()
                 .map_err(|e| error!("Couldn't get CPU control sysfs: {e}"))
                 .ok(),
+            supervisor,
+            sleep_hooks,
+            attribute_trial: Arc::new(Mutex::new(None)),
+            panel_od_auto_running: Arc::new(AtomicBool::new(false)),
         };
-        let mut inotify_self = ret_self.clone();
-
-        tokio::spawn(async move {
-            use futures_util::StreamExt;
-            info!("Starting inotify watch for asusd config file");
+        let inotify_self = ret_self.clone();
+
+        ret_self
+            .supervisor
+            .spawn("Platform: config watch", move || {
+                let config1 = config1.clone();
+                let config_path = config_path.clone();
+                let mut inotify_self = inotify_self.clone();
+                let signal_context = signal_context.clone();
+                async move {
+                    use futures_util::StreamExt;
+                    info!("Starting inotify watch for asusd config file");
+
+                    let mut buffer = [0; 32];
+                    loop {
+                        // vi and vim do stupid shit causing the file watch to be
+                        // removed
+                        let inotify = inotify::Inotify::init()?;
+                        inotify
+                            .watches()
+                            .add(
+                                &config_path,
+                                inotify::WatchMask::MODIFY
+                                    | inotify::WatchMask::CLOSE_WRITE
+                                    | inotify::WatchMask::ATTRIB
+                                    | inotify::WatchMask::CREATE,
+                            )
+                            .inspect_err(|e| {
+                                if e.kind() == std::io::ErrorKind::NotFound {
+                                    error!("Not found: {:?}", config_path);
+                                } else {
+                                    error!("Could not set asusd config inotify: {:?}", config_path);
+                                }
+                            })
+                            .ok();
+                        let mut events = inotify.into_event_stream(&mut buffer)?;
+
+                        while let Some(ev) = events.next().await {
+                            if let Ok(ev) = ev {
+                                if ev.mask == inotify::EventMask::IGNORED {
+                                    warn!(
+                                        "Something modified asusd.ron vi/vim style. Now need to \
+                                         reload inotify watch"
+                                    );
+                                    break;
+                                }
+                            }
 
-            let mut buffer = [0; 32];
-            loop {
-                // vi and vim do stupid shit causing the file watch to be removed
-                let inotify = inotify::Inotify::init().unwrap();
-                inotify
-                    .watches()
-                    .add(
-                        &config_path,
-                        inotify::WatchMask::MODIFY
-                            | inotify::WatchMask::CLOSE_WRITE
-                            | inotify::WatchMask::ATTRIB
-                            | inotify::WatchMask::CREATE,
-                    )
-                    .inspect_err(|e| {
-                        if e.kind() == std::io::ErrorKind::NotFound {
-                            error!("Not found: {:?}", config_path);
-                        } else {
-                            error!("Could not set asusd config inotify: {:?}", config_path);
-                        }
-                    })
-                    .ok();
-                let mut events = inotify.into_event_stream(&mut buffer).unwrap();
-
-                while let Some(ev) = events.next().await {
-                    if let Ok(ev) = ev {
-                        if ev.mask == inotify::EventMask::IGNORED {
-                            warn!(
-                                "Something modified asusd.ron vi/vim style. Now need to reload \
-                                 inotify watch"
-                            );
-                            break;
+                            let res = config1.lock().await.read_new();
+                            if let Some(mut new_cfg) = res {
+                                Policy::load().apply_to_config(&mut new_cfg);
+                                inotify_self
+                                    .reload_and_notify(&signal_context, new_cfg)
+                                    .await?;
+                            }
                         }
                     }
-
-                    let res = config1.lock().await.read_new();
-                    if let Some(new_cfg) = res {
-                        inotify_self
-                            .reload_and_notify(&signal_context, new_cfg)
-                            .await
-                            .unwrap();
-                    }
+                    #[allow(unreachable_code)]
+                    Ok::<(), RogError>(())
                 }
-            }
-        });
+            });
 
         Ok(ret_self)
     }
 
+    /// Write `mode` to `gpu_mux_mode`. The firmware switches the MUX on the
+    /// next boot
+    async fn write_gpu_mux(&self, mode: GpuMode) -> Result<(), RogError> {
+        self.write_gpu_attrs(&[(FirmwareAttribute::GpuMuxMode, mode.to_mux_attr() as i32)])
+            .await
+    }
+
+    async fn write_gpu_attrs(&self, writes: &[(FirmwareAttribute, i32)]) -> Result<(), RogError> {
+        write_gpu_attrs(&self.attributes, &self.config, writes).await
+    }
+
+    /// Make the queued MUX switch on shutdown, unless a client is to confirm
+    /// it. A switch the policy no longer allows is dropped.
+    async fn apply_queued_mux_on_shutdown(&self, signal_ctxt: &SignalEmitter<'_>) {
+        let Some(queued) = self.config.lock().await.gpu_mux_queued else {
+            return;
+        };
+        if queued.confirm {
+            info!(
+                "Platform: the GPU MUX switch to {} was not confirmed, keeping it queued",
+                queued.mode
+            );
+            return;
+        }
+        if let Err(e) = check_mux_policy(&Policy::load(), queued.mode) {
+            warn!(
+                "Platform: dropping the queued GPU MUX switch to {}: {e}",
+                queued.mode
+            );
+        } else if let Err(e) = self.write_gpu_mux(queued.mode).await {
+            error!("Platform: could not make the queued GPU MUX switch: {e}");
+            return;
+        } else {
+            info!(
+                "Platform: switched the GPU MUX to {} for the next boot",
+                queued.mode
+            );
+            self.notify_gpu_modes(signal_ctxt).await;
+        }
+        {
+            let mut config = self.config.lock().await;
+            config.gpu_mux_queued = None;
+            config.write();
+        }
+        self.gpu_mode_queued_changed(signal_ctxt).await.ok();
+    }
+
+    /// Set the stored limit of each battery other than the main one
+    async fn restore_battery_charge_limits(&self) {
+        let limits = self.config.lock().await.battery_charge_limits.clone();
+        for (name, limit) in limits {
+            match self.power.battery(&name) {
+                Ok(battery) if battery.has_charge_control_end_threshold() => {
+                    info!("reloading charge_control_end_threshold of {name} to {limit}");
+                    battery
+                        .set_charge_control_end_threshold(limit)
+                        .map_err(|e| warn!("Couldn't restore the charge limit of {name}: {e}"))
+                        .ok();
+                }
+                _ => debug!("Battery {name} is not present, not restoring its charge limit"),
+            }
+        }
+    }
+
     async fn restore_charge_limit(&self) {
         let limit = self.config.lock().await.base_charge_control_end_threshold;
         if limit > 0
@@ -142,6 +258,9 @@ impl CtrlPlatform {
     }
 
     async fn run_ac_or_bat_cmd(&self, power_plugged: bool) {
+        run_hooks(HookEvent::PowerSource {
+            plugged: power_plugged,
+        });
         let prog: Vec<String> = if power_plugged {
             // AC ONLINE
             self.config
@@ -242,6 +361,529 @@ impl CtrlPlatform {
         self.platform.set_platform_profile(throttle.into()).ok();
         self.check_and_set_epp(epp, change_epp);
     }
+
+    /// Set the `mini_led_mode` bound to the profile or power state, if any
+    async fn apply_mini_led_binding(&self, power_plugged: bool, profile: PlatformProfile) {
+        let Some(mode) = self
+            .config
+            .lock()
+            .await
+            .mini_led_mode_for(power_plugged, profile)
+        else {
+            return;
+        };
+        if let Some(attr) = self.attributes.mini_led_mode() {
+            let value = AttrValue::Integer(mode);
+            if attr.current_value().is_ok_and(|v| v == value) {
+                return;
+            }
+            debug!("Setting mini_led_mode to {mode} for {profile:?}");
+            attr.set_current_value(&value)
+                .map_err(|e| error!("Could not set mini_led_mode: {e}"))
+                .ok();
+        }
+    }
+
+    /// Check the mode is one the `mini_led_mode` attribute accepts. A negative
+    /// mode is always valid as it removes a binding.
+    fn check_mini_led_mode(&self, mode: i32) -> Result<(), FdoErr> {
+        if mode < 0 {
+            return Ok(());
+        }
+        let Some(attr) = self.attributes.mini_led_mode() else {
+            return Err(FdoErr::NotSupported(
+                "RogPlatform: mini_led_mode not supported".to_owned(),
+            ));
+        };
+        if let AttrValue::EnumInt(values) = attr.possible_values() {
+            if !values.contains(&mode) {
+                return Err(FdoErr::InvalidArgs(format!(
+                    "RogPlatform: mini_led_mode {mode} not in {values:?}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_current_mini_led_binding(&self) -> Result<(), FdoErr> {
+        let power_plugged = self
+            .power
+            .get_online()
+            .map_err(|e| {
+                error!("Could not get power status: {e:?}");
+                e
+            })
+            .unwrap_or_default();
+        let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
+        self.apply_mini_led_binding(power_plugged == 1, profile)
+            .await;
+        Ok(())
+    }
+
+    /// Validate a set of PPT values and work out a safe order to write them
+    /// in. Returns each attribute with its previous and new value. The tier
+    /// order is checked on the tuning group `stored` merged with `values`, so
+    /// a disabled group, whose values are stored without being written, can
+    /// be enabled later.
+    fn plan_ppt_values(
+        &self,
+        values: &[(FirmwareAttribute, i32)],
+        stored: &HashMap<FirmwareAttribute, i32>,
+    ) -> Result<Vec<(&Attribute, i32, i32)>, FdoErr> {
+        if let Some((name, _)) = values.iter().find(|(name, _)| !name.is_ppt()) {
+            return Err(FdoErr::InvalidArgs(format!(
+                "RogPlatform: {} is not a PPT attribute",
+                <&str>::from(*name)
+            )));
+        }
+        self.plan_attribute_values(values, stored)
+    }
+
+    /// Validate a set of attribute values and work out a safe order to write
+    /// them in. Returns each attribute with its previous and new value.
+    ///
+    /// The PPT limits must stay ordered as [`check_ppt_order`] checks, using
+    /// the current value, or the value in `stored`, of any limit not in the
+    /// set. The writes are ordered by [`sort_ppt_writes`].
+    fn plan_attribute_values(
+        &self,
+        values: &[(FirmwareAttribute, i32)],
+        stored: &HashMap<FirmwareAttribute, i32>,
+    ) -> Result<Vec<(&Attribute, i32, i32)>, FdoErr> {
+        let policy = Policy::load();
+        let mut plan = Vec::new();
+        for (name, value) in values {
+            if plan
+                .iter()
+                .any(|(a, ..): &(&Attribute, i32, i32)| FirmwareAttribute::from(a.name()) == *name)
+            {
+                return Err(FdoErr::InvalidArgs(format!(
+                    "RogPlatform: {} given more than once",
+                    <&str>::from(*name)
+                )));
+            }
+            let Some(attr) = self
+                .attributes
+                .attributes()
+                .iter()
+                .find(|a| FirmwareAttribute::from(a.name()) == *name)
+            else {
+                return Err(FdoErr::NotSupported(format!(
+                    "RogPlatform: {} not supported",
+                    <&str>::from(*name)
+                )));
+            };
+            if let (AttrValue::Integer(min), AttrValue::Integer(max)) =
+                (attr.min_value(), attr.max_value())
+            {
+                if !(*min..=*max).contains(value) {
+                    return Err(FdoErr::InvalidArgs(format!(
+                        "RogPlatform: {} must be within {min}..={max}, got {value}",
+                        attr.name()
+                    )));
+                }
+            }
+            policy.check_attribute(*name, *value)?;
+            let AttrValue::Integer(previous) = attr.current_value()? else {
+                return Err(FdoErr::Failed(format!(
+                    "RogPlatform: could not read {}",
+                    attr.name()
+                )));
+            };
+            plan.push((attr, previous, *value));
+        }
+
+        let mut merged: Vec<(FirmwareAttribute, i32)> = self
+            .attributes
+            .attributes()
+            .iter()
+            .filter_map(|attr| match attr.current_value() {
+                Ok(AttrValue::Integer(value)) => Some((attr.name().into(), value)),
+                _ => None,
+            })
+            .collect();
+        merged.extend(stored.iter().map(|(name, value)| (*name, *value)));
+        merged.extend(values.iter().copied());
+        check_ppt_order(&merged).map_err(FdoErr::InvalidArgs)?;
+
+        plan.retain(|(_, previous, new)| previous != new);
+        sort_ppt_writes(&mut plan, |(attr, previous, new)| {
+            (attr.name().into(), *previous, *new)
+        });
+        Ok(plan)
+    }
+
+    /// Write a planned set of attribute values, restoring everything already
+    /// written if any write fails
+    fn apply_attribute_plan(plan: &[(&Attribute, i32, i32)]) -> Result<(), FdoErr> {
+        for (done, (attr, _, new)) in plan.iter().enumerate() {
+            if let Err(e) = attr.set_current_value(&AttrValue::Integer(*new)) {
+                error!("Could not set {} to {new}: {e}, rolling back", attr.name());
+                for (attr, previous, _) in plan[..done].iter().rev() {
+                    attr.set_current_value(&AttrValue::Integer(*previous))
+                        .map_err(|e| error!("Could not restore {}: {e}", attr.name()))
+                        .ok();
+                }
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Undo attribute trial writes, newest first
+    fn revert_attribute_writes(&self, writes: &[(FirmwareAttribute, i32, i32)]) {
+        for (name, previous, _) in writes.iter().rev() {
+            if let Some(attr) = self
+                .attributes
+                .attributes()
+                .iter()
+                .find(|a| FirmwareAttribute::from(a.name()) == *name)
+            {
+                attr.set_current_value(&AttrValue::Integer(*previous))
+                    .map_err(|e| error!("Could not restore {}: {e}", attr.name()))
+                    .ok();
+            }
+        }
+    }
+
+    /// Whether the laptop is on AC, and the platform profile
+    fn power_and_profile(&self) -> Result<(bool, PlatformProfile), FdoErr> {
+        let power_plugged = self
+            .power
+            .get_online()
+            .map_err(|e| {
+                error!("Could not get power status: {e:?}");
+                e
+            })
+            .unwrap_or_default();
+        Ok((
+            power_plugged == 1,
+            self.platform.get_platform_profile()?.into(),
+        ))
+    }
+
+    /// Revert the attribute values on trial if they were tried on another
+    /// power source or profile, before the tuning of the new one is applied
+    async fn end_attribute_trial_for(
+        &self,
+        on_ac: bool,
+        profile: PlatformProfile,
+        signal_ctxt: &SignalEmitter<'_>,
+    ) {
+        let mut trial = self.attribute_trial.lock().await;
+        if !trial
+            .as_ref()
+            .is_some_and(|t| t.on_ac != on_ac || t.profile != profile)
+        {
+            return;
+        }
+        if let Some(trial) = trial.take() {
+            warn!("The power source or profile changed, reverting the attribute values on trial");
+            self.revert_attribute_writes(&trial.writes);
+        }
+        drop(trial);
+        self.attribute_trial_pending_changed(signal_ctxt)
+            .await
+            .map_err(|e| error!("Could not notify attribute_trial_pending: {e}"))
+            .ok();
+    }
+
+    /// The GPU mode in use, the mode after the next reboot, and the mode last
+    /// requested through asusd. `dgpu_disable` and `egpu_enable` apply
+    /// immediately, only a MUX change waits for a reboot.
+    async fn gpu_modes(&self) -> (GpuMode, GpuMode, GpuMode) {
+        let (mux, dgpu, egpu) = self.attributes.gpu_attr_values();
+        let mode = |mux, dgpu, egpu| {
+            GpuMode::from_attr_values(mux, dgpu, egpu).unwrap_or(GpuMode::NotSupported)
+        };
+        let active = mode(active_gpu_mux(&self.attributes), dgpu, egpu);
+        let pending = mode(mux, dgpu, egpu);
+
+        let config = self.config.lock().await;
+        let requested_value = |attr: FirmwareAttribute, current: Option<u8>| {
+            current.map(|c| config.armoury_settings.get(&attr).map_or(c, |v| *v as u8))
+        };
+        let requested = mode(
+            requested_value(FirmwareAttribute::GpuMuxMode, mux),
+            requested_value(FirmwareAttribute::DgpuDisable, dgpu),
+            requested_value(FirmwareAttribute::EgpuEnable, egpu),
+        );
+        (active, pending, requested)
+    }
+
+    async fn notify_gpu_modes(&self, signal_ctxt: &SignalEmitter<'_>) {
+        self.gpu_mode_active_changed(signal_ctxt).await.ok();
+        self.gpu_mode_pending_changed(signal_ctxt).await.ok();
+        self.gpu_mode_requested_changed(signal_ctxt).await.ok();
+    }
+
+    /// Set `panel_od` to match the internal panel refresh rate if
+    /// `panel_od_auto` is enabled
+    async fn apply_panel_od_auto(&self) {
+        let (enabled, min_refresh) = {
+            let config = self.config.lock().await;
+            (config.panel_od_auto, config.panel_od_auto_min_refresh)
+        };
+        if !enabled {
+            return;
+        }
+        let Some(attr) = self.attributes.panel_od() else {
+            return;
+        };
+        let rate = match internal_refresh_rate() {
+            Ok(rate) => rate,
+            Err(e) => {
+                debug!("Could not read internal panel refresh rate: {e}");
+                return;
+            }
+        };
+        let value = AttrValue::Integer((rate >= min_refresh) as i32);
+        if attr.current_value().is_ok_and(|v| v == value) {
+            return;
+        }
+        debug!("Refresh rate is {rate}Hz, setting panel_od to {value:?}");
+        attr.set_current_value(&value)
+            .map_err(|e| error!("Could not set panel_od: {e}"))
+            .ok();
+    }
+
+    /// Poll the refresh rate for `panel_od_auto` while it is enabled, as it
+    /// has no change notification. Does nothing if the task is already
+    /// running or `panel_od` is not supported.
+    async fn start_panel_od_auto(&self) {
+        if self.attributes.panel_od().is_none()
+            || !self.config.lock().await.panel_od_auto
+            || self.panel_od_auto_running.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        let ctrl = self.clone();
+        self.supervisor.spawn("Platform: panel_od auto", move || {
+            let ctrl = ctrl.clone();
+            async move {
+                loop {
+                    {
+                        // Under the lock so that enabling it again either
+                        // sees the task stopped or keeps it running
+                        let config = ctrl.config.lock().await;
+                        if !config.panel_od_auto {
+                            ctrl.panel_od_auto_running.store(false, Ordering::SeqCst);
+                            return Ok::<(), RogError>(());
+                        }
+                    }
+                    ctrl.apply_panel_od_auto().await;
+                    sleep(Duration::from_secs(2)).await;
+                }
+            }
+        });
+    }
+}
+
+/// The `xyz.ljones.Platform` properties this laptop supports
+pub fn supported_properties(platform: &RogPlatform, power: &AsusPower) -> Vec<Properties> {
+    let mut supported = Vec::new();
+
+    macro_rules! platform_name {
+        ($property:tt, $prop_name:ty) => {
+            concat_idents::concat_idents!(has = has_, $property {
+                if platform.has() {
+                    supported.push($prop_name.to_owned());
+                }
+            })
+        }
+    }
+
+    macro_rules! power_name {
+        ($property:tt, $prop_name:ty) => {
+            concat_idents::concat_idents!(has = has_, $property {
+                if power.has() {
+                    supported.push($prop_name.to_owned());
+                }
+            })
+        }
+    }
+
+    // TODO: automate this
+    power_name!(
+        charge_control_end_threshold,
+        Properties::ChargeControlEndThreshold
+    );
+
+    platform_name!(platform_profile, Properties::ThrottlePolicy);
+
+    supported
+}
+
+/// Write GPU mode attributes in order, storing each written value
+pub(crate) async fn write_gpu_attrs(
+    attributes: &FirmwareAttributes,
+    config: &Mutex<Config>,
+    writes: &[(FirmwareAttribute, i32)],
+) -> Result<(), RogError> {
+    for (name, value) in writes {
+        let Some(attr) = attributes
+            .attributes()
+            .iter()
+            .find(|a| a.name() == <&str>::from(*name))
+        else {
+            return Err(RogError::MissingFunction(format!(
+                "No {}",
+                <&str>::from(*name)
+            )));
+        };
+        info!("Setting {} to {value}", attr.name());
+        attr.set_current_value(&AttrValue::Integer(*value))?;
+        config.lock().await.armoury_settings.insert(*name, *value);
+    }
+    config.lock().await.write();
+    if let Some(mode) = attributes.gpu_mode() {
+        run_hooks(HookEvent::GpuMode {
+            mode,
+            reboot_pending: FirmwareAttributes::pending_reboot(),
+        });
+    }
+    Ok(())
+}
+
+/// The tier of a PPT limit in `spl <= sppt <= fppt`. The fast limit is
+/// `ppt_pl3_fppt` or `ppt_fppt` depending on the generation. Each must be at
+/// least the lower tiers, but they are not compared with each other.
+fn ppt_tier(name: FirmwareAttribute) -> Option<u8> {
+    match name {
+        FirmwareAttribute::PptPl1Spl => Some(0),
+        FirmwareAttribute::PptPl2Sppt => Some(1),
+        FirmwareAttribute::PptPl3Fppt | FirmwareAttribute::PptFppt => Some(2),
+        _ => None,
+    }
+}
+
+/// Check that `values`, where a later value of an attribute replaces an
+/// earlier one, keep the PPT limits ordered `spl <= sppt <= fppt`
+pub(crate) fn check_ppt_order(values: &[(FirmwareAttribute, i32)]) -> Result<(), String> {
+    let mut limits = BTreeMap::new();
+    for (name, value) in values {
+        if let Some(tier) = ppt_tier(*name) {
+            limits.insert(*name, (tier, *value));
+        }
+    }
+    for (low, (low_tier, low_value)) in &limits {
+        for (high, (high_tier, high_value)) in &limits {
+            if low_tier < high_tier && low_value > high_value {
+                return Err(format!(
+                    "RogPlatform: {} ({low_value}) must not be above {} ({high_value}), PPT \
+                     limits must be ordered spl <= sppt <= fppt",
+                    <&str>::from(*low),
+                    <&str>::from(*high)
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sort `writes`, which `change` gives the attribute, previous, and new
+/// value of, so the PPT limits stay ordered after each one. Firmware may
+/// reject a write which breaks the ordering even if the full set is valid, so
+/// lowered values are written from the lowest tier up, then raised values from
+/// the highest tier down.
+pub(crate) fn sort_ppt_writes<T>(
+    writes: &mut [T],
+    change: impl Fn(&T) -> (FirmwareAttribute, i32, i32),
+) {
+    writes.sort_by_key(|write| {
+        let (name, previous, new) = change(write);
+        if new < previous {
+            (0, ppt_tier(name).map_or(i16::MAX, i16::from))
+        } else {
+            (1, -ppt_tier(name).map_or(0, i16::from))
+        }
+    });
+}
+
+/// Check that `policy` allows switching the MUX to `mode`
+fn check_mux_policy(policy: &Policy, mode: GpuMode) -> Result<(), RogError> {
+    policy.check_writable(Lockdown::FirmwareAttributes)?;
+    policy.check_attribute(FirmwareAttribute::GpuMuxMode, mode.to_mux_attr() as i32)
+}
+
+/// The MUX position in use. `gpu_mux_mode` reads as the position after the
+/// next reboot, so while a reboot is pending ask the kernel which GPU drives
+/// the internal panel.
+pub(crate) fn active_gpu_mux(attributes: &FirmwareAttributes) -> Option<u8> {
+    let mux = attributes.gpu_attr_values().0?;
+    if !FirmwareAttributes::pending_reboot() {
+        return Some(mux);
+    }
+    match internal_panel_gpu().and_then(|gpu| is_discrete_gpu(&gpu)) {
+        Some(true) => Some(0),
+        Some(false) => Some(1),
+        None => Some(mux),
+    }
+}
+
+/// The GPU modes the `gpu_mux_mode`, `dgpu_disable`, and `egpu_enable` values
+/// allow, from the values as read
+fn supported_gpu_modes((mux, dgpu, egpu): (Option<u8>, Option<u8>, Option<u8>)) -> Vec<GpuMode> {
+    let mut modes = Vec::new();
+    if mux.is_some() || dgpu.is_some() || egpu.is_some() {
+        modes.push(GpuMode::Optimus);
+    }
+    if dgpu.is_some() {
+        modes.push(GpuMode::Integrated);
+    }
+    if egpu.is_some() {
+        modes.push(GpuMode::Egpu);
+    }
+    if mux.is_some() {
+        modes.push(GpuMode::Ultimate);
+    }
+    modes
+}
+
+/// The attributes to write, in order, to switch from the current values to
+/// `mode`. The dGPU is enabled before the MUX is set to it, and other modes
+/// are turned off before `dgpu_disable` or `egpu_enable` is set.
+pub(crate) fn gpu_mode_writes(
+    mode: GpuMode,
+    (mux, dgpu, egpu): (Option<u8>, Option<u8>, Option<u8>),
+) -> Result<Vec<(FirmwareAttribute, i32)>, FdoErr> {
+    if mux == Some(0) && matches!(mode, GpuMode::Integrated | GpuMode::Egpu) {
+        return Err(FdoErr::Failed(format!(
+            "The GPU MUX is set to Ultimate, switch to Optimus before {mode}"
+        )));
+    }
+    let mut writes = Vec::new();
+    let mut set = |name, current: Option<u8>, value: u8| {
+        if current.is_some_and(|c| c != value) {
+            writes.push((name, value as i32));
+        }
+    };
+    match mode {
+        GpuMode::Optimus => {
+            set(FirmwareAttribute::DgpuDisable, dgpu, 0);
+            set(FirmwareAttribute::EgpuEnable, egpu, 0);
+            set(FirmwareAttribute::GpuMuxMode, mux, 1);
+        }
+        GpuMode::Ultimate => {
+            set(FirmwareAttribute::DgpuDisable, dgpu, 0);
+            set(FirmwareAttribute::EgpuEnable, egpu, 0);
+            set(FirmwareAttribute::GpuMuxMode, mux, 0);
+        }
+        GpuMode::Integrated => {
+            set(FirmwareAttribute::EgpuEnable, egpu, 0);
+            set(FirmwareAttribute::DgpuDisable, dgpu, 1);
+        }
+        GpuMode::Egpu => {
+            set(FirmwareAttribute::DgpuDisable, dgpu, 0);
+            set(FirmwareAttribute::EgpuEnable, egpu, 1);
+        }
+        _ => {
+            return Err(FdoErr::NotSupported(format!(
+                "{mode} can't be set with the firmware attributes"
+            )))
+        }
+    }
+    Ok(writes)
 }
 
 #[interface(name = "xyz.ljones.Platform")]
@@ -251,39 +893,231 @@ impl CtrlPlatform {
         crate::VERSION.to_string()
     }
 
-    /// Returns a list of property names that this system supports
-    async fn supported_properties(&self) -> Vec<Properties> {
-        let mut supported = Vec::new();
+    /// The GPU mode in use now
+    #[zbus(property)]
+    async fn gpu_mode_active(&self) -> GpuMode {
+        self.gpu_modes().await.0
+    }
 
-        macro_rules! platform_name {
-            ($property:tt, $prop_name:ty) => {
-                concat_idents::concat_idents!(has = has_, $property {
-                    if self.platform.has() {
-                        supported.push($prop_name.to_owned());
-                    }
-                })
+    /// The GPU mode after the next reboot. This differs from
+    /// `GpuModeActive` after a MUX change
+    #[zbus(property)]
+    async fn gpu_mode_pending(&self) -> GpuMode {
+        self.gpu_modes().await.1
+    }
+
+    /// The GPU mode last requested through asusd, which may not be applied
+    /// if the firmware refused it
+    #[zbus(property)]
+    async fn gpu_mode_requested(&self) -> GpuMode {
+        self.gpu_modes().await.2
+    }
+
+    /// The GPU modes `SetGpuMode` can switch to on this laptop
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn supported_gpu_modes(&self) -> Vec<GpuMode> {
+        supported_gpu_modes(self.attributes.gpu_attr_values())
+    }
+
+    /// Switch the GPU mode with the firmware attributes, for laptops without
+    /// supergfxd. Refused while supergfxd is running, while the dGPU is in use
+    /// for `Integrated`, and for `Integrated` or `Egpu` while the MUX is set
+    /// to Ultimate. A MUX change takes effect after a reboot.
+    async fn set_gpu_mode(
+        &self,
+        mode: GpuMode,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] signal_ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        if !supported_gpu_modes(self.attributes.gpu_attr_values()).contains(&mode) {
+            return Err(FdoErr::NotSupported(format!(
+                "{mode} is not supported on this laptop"
+            )));
+        }
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::FirmwareAttributes)?;
+        let supergfxd = zbus::fdo::DBusProxy::new(conn)
+            .await?
+            .name_has_owner(BusName::from_static_str_unchecked(SUPERGFXD_NAME))
+            .await?;
+        if supergfxd {
+            return Err(FdoErr::Failed(
+                "supergfxd is managing the GPU, use supergfxctl to change the mode".to_owned(),
+            ));
+        }
+        if active_gpu_mux(&self.attributes) == Some(0)
+            && matches!(mode, GpuMode::Integrated | GpuMode::Egpu)
+        {
+            return Err(FdoErr::Failed(format!(
+                "Switch to Optimus and reboot before switching to {mode}"
+            )));
+        }
+        if mode == GpuMode::Integrated && !dgpu_power().settled() {
+            return Err(FdoErr::Failed(
+                "The dGPU is in use, close the programs using it first".to_owned(),
+            ));
+        }
+        let writes = gpu_mode_writes(mode, self.attributes.gpu_attr_values())?;
+        for (name, value) in &writes {
+            policy.check_attribute(*name, *value)?;
+        }
+        self.write_gpu_attrs(&writes).await?;
+        self.notify_gpu_modes(&signal_ctxt).await;
+        Ok(())
+    }
+
+    /// The GPU MUX switch queued for the next shutdown or reboot, or
+    /// `NotSupported` if there is none
+    #[zbus(property)]
+    async fn gpu_mode_queued(&self) -> GpuMode {
+        self.config
+            .lock()
+            .await
+            .gpu_mux_queued
+            .map_or(GpuMode::NotSupported, |queued| queued.mode)
+    }
+
+    /// The GPU mode in use, the mode the firmware switches to on the next
+    /// boot, the queued MUX switch or `NotSupported`, and if the queued switch
+    /// waits for a client to confirm it
+    async fn pending_gpu_mux_changes(&self) -> (GpuMode, GpuMode, GpuMode, bool) {
+        let (active, pending, _) = self.gpu_modes().await;
+        let queued = self.config.lock().await.gpu_mux_queued;
+        (
+            active,
+            pending,
+            queued.map_or(GpuMode::NotSupported, |queued| queued.mode),
+            queued.is_some_and(|queued| queued.confirm),
+        )
+    }
+
+    /// Queue a GPU MUX switch to `Ultimate` or `Optimus` for the next
+    /// shutdown or reboot. With `confirm` a client such as ROG Control Center
+    /// asks at shutdown and calls `ApplyQueuedGpuMux`, otherwise asusd makes
+    /// the switch on shutdown. Queuing the mode the MUX is already set to
+    /// clears the queue.
+    async fn queue_gpu_mux(
+        &self,
+        mode: GpuMode,
+        confirm: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        if !matches!(mode, GpuMode::Ultimate | GpuMode::Optimus) {
+            return Err(FdoErr::InvalidArgs(format!("{mode} is not a GPU MUX mode")));
+        }
+        let Some(mux) = self.attributes.gpu_attr_values().0 else {
+            return Err(FdoErr::NotSupported(
+                "This laptop has no GPU MUX".to_owned(),
+            ));
+        };
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        check_mux_policy(&Policy::load(), mode)?;
+        {
+            let mut config = self.config.lock().await;
+            if GpuMode::from_mux(mux) == mode {
+                info!("Platform: the GPU MUX is already set to {mode}, clearing the queue");
+                config.gpu_mux_queued = None;
+            } else {
+                info!("Platform: queued a GPU MUX switch to {mode}, confirm: {confirm}");
+                config.gpu_mux_queued = Some(QueuedMuxSwitch { mode, confirm });
             }
+            config.write();
         }
+        self.gpu_mode_queued_changed(&ctxt).await?;
+        Ok(())
+    }
 
-        macro_rules! power_name {
-            ($property:tt, $prop_name:ty) => {
-                concat_idents::concat_idents!(has = has_, $property {
-                    if self.power.has() {
-                        supported.push($prop_name.to_owned());
-                    }
-                })
+    /// Drop the queued GPU MUX switch
+    async fn cancel_queued_gpu_mux(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        {
+            let mut config = self.config.lock().await;
+            if config.gpu_mux_queued.take().is_none() {
+                return Ok(());
             }
+            config.write();
         }
+        info!("Platform: cancelled the queued GPU MUX switch");
+        self.gpu_mode_queued_changed(&ctxt).await?;
+        Ok(())
+    }
 
-        // TODO: automate this
-        power_name!(
-            charge_control_end_threshold,
-            Properties::ChargeControlEndThreshold
+    /// Make the queued GPU MUX switch now. The MUX still only switches on the
+    /// next boot
+    async fn apply_queued_gpu_mux(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        let Some(queued) = self.config.lock().await.gpu_mux_queued else {
+            return Err(FdoErr::Failed("No GPU MUX switch is queued".to_owned()));
+        };
+        // The policy may have changed since the switch was queued
+        check_mux_policy(&Policy::load(), queued.mode)?;
+        self.write_gpu_mux(queued.mode).await?;
+        self.notify_gpu_modes(&ctxt).await;
+        {
+            let mut config = self.config.lock().await;
+            config.gpu_mux_queued = None;
+            config.write();
+        }
+        info!(
+            "Platform: switched the GPU MUX to {} for the next boot",
+            queued.mode
         );
+        self.gpu_mode_queued_changed(&ctxt).await?;
+        Ok(())
+    }
 
-        platform_name!(platform_profile, Properties::ThrottlePolicy);
+    /// The log filter in use, such as `info,zbus=warn`
+    #[zbus(property)]
+    async fn log_filter(&self) -> String {
+        logging::current_filter()
+    }
 
-        supported
+    /// Change the log levels until asusd restarts, using the `RUST_LOG`
+    /// format such as `debug,zbus=warn,asusd::aura_anime=off`
+    async fn set_log_filter(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        filter: String,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Power).await?;
+        let filter: LogFilter = filter.parse().map_err(FdoErr::InvalidArgs)?;
+        info!("Log filter set to {filter}");
+        logging::set_filter(filter);
+        self.log_filter_changed(&ctxt).await?;
+        Ok(())
+    }
+
+    /// The uid of the user whose session is in the foreground on the first
+    /// seat, or `u32::MAX` if there is none. With several users logged in,
+    /// the clients of users in the background can use this to hold back
+    /// notifications and automatic changes.
+    #[zbus(property)]
+    async fn active_user(&self) -> u32 {
+        match Connection::system().await {
+            Ok(conn) => sessions::active_user(&conn).await,
+            Err(_) => sessions::NO_ACTIVE_USER,
+        }
+    }
+
+    /// Returns a list of property names that this system supports
+    async fn supported_properties(&self) -> Vec<Properties> {
+        supported_properties(&self.platform, &self.power)
     }
 
     #[zbus(property)]
@@ -293,10 +1127,18 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_charge_control_end_threshold(&mut self, limit: u8) -> Result<(), FdoErr> {
+    async fn set_charge_control_end_threshold(
+        &mut self,
+        limit: u8,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::ChargeLimit)?;
         if !(20..=100).contains(&limit) {
             return Err(RogError::ChargeLimit(limit))?;
         }
+        Policy::load().check_charge_limit(limit)?;
         self.power.set_charge_control_end_threshold(limit)?;
         self.config.lock().await.charge_control_end_threshold = limit;
         self.config.lock().await.base_charge_control_end_threshold = limit;
@@ -304,7 +1146,90 @@ impl CtrlPlatform {
         Ok(())
     }
 
-    async fn one_shot_full_charge(&self) -> Result<(), FdoErr> {
+    /// The power negotiated by the USB-C charger in watts, or 0 if no USB-C
+    /// charger is connected
+    #[zbus(property)]
+    fn usb_pd_watts(&self) -> u32 {
+        self.power.usb_pd_watts().unwrap_or_default()
+    }
+
+    /// If the USB-C charger is too weak to charge the battery: it negotiated
+    /// less than 45W, or the battery is discharging while it is connected
+    #[zbus(property)]
+    fn slow_charger(&self) -> bool {
+        self.power.slow_charger()
+    }
+
+    /// The `power_supply` names of the batteries, the main one first. The
+    /// main battery is the one `ChargeControlEndThreshold` sets
+    #[zbus(property)]
+    fn batteries(&self) -> Vec<String> {
+        self.power
+            .batteries()
+            .iter()
+            .map(|battery| battery.name())
+            .collect()
+    }
+
+    /// The charge limit of one of `Batteries`. Fails with `NotSupported` if
+    /// that battery has no `charge_control_end_threshold`
+    async fn battery_charge_limit(&self, battery: String) -> Result<u8, FdoErr> {
+        let battery = self.power.battery(&battery)?;
+        if !battery.has_charge_control_end_threshold() {
+            return Err(FdoErr::NotSupported(format!(
+                "Battery {} has no charge_control_end_threshold",
+                battery.name()
+            )));
+        }
+        Ok(battery.get_charge_control_end_threshold()?)
+    }
+
+    /// Set the charge limit of one of `Batteries`. For the main battery this
+    /// is the same as setting `ChargeControlEndThreshold`
+    async fn set_battery_charge_limit(
+        &self,
+        battery: String,
+        limit: u8,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Power).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::ChargeLimit)?;
+        if !(20..=100).contains(&limit) {
+            return Err(RogError::ChargeLimit(limit))?;
+        }
+        policy.check_charge_limit(limit)?;
+        let battery = self.power.battery(&battery)?;
+        if !battery.has_charge_control_end_threshold() {
+            return Err(FdoErr::NotSupported(format!(
+                "Battery {} has no charge_control_end_threshold",
+                battery.name()
+            )));
+        }
+        battery.set_charge_control_end_threshold(limit)?;
+        let name = battery.name();
+        let mut config = self.config.lock().await;
+        if name == self.power.main_battery_name() {
+            config.charge_control_end_threshold = limit;
+            config.base_charge_control_end_threshold = limit;
+        } else {
+            config.battery_charge_limits.insert(name.clone(), limit);
+        }
+        config.write();
+        info!("Charge limit of {name} set to {limit}");
+        Ok(())
+    }
+
+    async fn one_shot_full_charge(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Power).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::ChargeLimit)?;
+        policy.check_charge_limit(100)?;
         let base_limit = std::mem::replace(
             &mut self.config.lock().await.charge_control_end_threshold,
             100,
@@ -321,8 +1246,12 @@ impl CtrlPlatform {
     /// If fan-curves are supported will also activate a fan curve for profile.
     async fn next_platform_profile(
         &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
         #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
     ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::PlatformProfile)?;
         let policy: PlatformProfile =
             platform_get_value!(self, platform_profile, "platform_profile").map(|n| n.into())?;
         let choices =
@@ -364,7 +1293,11 @@ impl CtrlPlatform {
         &mut self,
         #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
         policy: PlatformProfile,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
     ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::PlatformProfile)?;
         // TODO: watch for external changes
         if self.platform.has_platform_profile() {
             let change_epp = self.config.lock().await.platform_profile_linked_epp;
@@ -402,7 +1335,13 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_platform_profile_linked_epp(&self, linked: bool) -> Result<(), zbus::Error> {
+    async fn set_platform_profile_linked_epp(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        linked: bool,
+    ) -> Result<(), zbus::Error> {
+        authorize_interactive(conn, &header, Action::Power).await?;
         self.config.lock().await.platform_profile_linked_epp = linked;
         self.config.lock().await.write();
         Ok(())
@@ -418,9 +1357,14 @@ impl CtrlPlatform {
         &mut self,
         #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
         policy: PlatformProfile,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
     ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::PlatformProfile)?;
         self.config.lock().await.platform_profile_on_battery = policy;
-        self.set_platform_profile(ctxt, policy).await?;
+        self.set_platform_profile(ctxt, policy, conn, header)
+            .await?;
         self.config.lock().await.write();
         Ok(())
     }
@@ -431,7 +1375,13 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_change_platform_profile_on_battery(&mut self, change: bool) -> Result<(), FdoErr> {
+    async fn set_change_platform_profile_on_battery(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        change: bool,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
         self.config.lock().await.change_platform_profile_on_battery = change;
         self.config.lock().await.write();
         Ok(())
@@ -447,9 +1397,14 @@ impl CtrlPlatform {
         &mut self,
         #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
         policy: PlatformProfile,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
     ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
+        Policy::load().check_writable(Lockdown::PlatformProfile)?;
         self.config.lock().await.platform_profile_on_ac = policy;
-        self.set_platform_profile(ctxt, policy).await?;
+        self.set_platform_profile(ctxt, policy, conn, header)
+            .await?;
         self.config.lock().await.write();
         Ok(())
     }
@@ -460,7 +1415,13 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_change_platform_profile_on_ac(&mut self, change: bool) -> Result<(), FdoErr> {
+    async fn set_change_platform_profile_on_ac(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        change: bool,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
         self.config.lock().await.change_platform_profile_on_ac = change;
         self.config.lock().await.write();
         Ok(())
@@ -474,7 +1435,13 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_profile_quiet_epp(&mut self, epp: CPUEPP) -> Result<(), FdoErr> {
+    async fn set_profile_quiet_epp(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        epp: CPUEPP,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
         let change_pp = self.config.lock().await.platform_profile_linked_epp;
         self.config.lock().await.profile_quiet_epp = epp;
         self.check_and_set_epp(epp, change_pp);
@@ -490,7 +1457,13 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_profile_balanced_epp(&mut self, epp: CPUEPP) -> Result<(), FdoErr> {
+    async fn set_profile_balanced_epp(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        epp: CPUEPP,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
         let change_pp = self.config.lock().await.platform_profile_linked_epp;
         self.config.lock().await.profile_balanced_epp = epp;
         self.check_and_set_epp(epp, change_pp);
@@ -506,7 +1479,13 @@ impl CtrlPlatform {
     }
 
     #[zbus(property)]
-    async fn set_profile_performance_epp(&mut self, epp: CPUEPP) -> Result<(), FdoErr> {
+    async fn set_profile_performance_epp(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        epp: CPUEPP,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Power).await?;
         let change_pp = self.config.lock().await.platform_profile_linked_epp;
         self.config.lock().await.profile_performance_epp = epp;
         self.check_and_set_epp(epp, change_pp);
@@ -537,7 +1516,15 @@ impl CtrlPlatform {
 
     /// Set if the PPT tuning group for the current profile is enabled
     #[zbus(property)]
-    async fn set_enable_ppt_group(&mut self, enable: bool) -> Result<(), FdoErr> {
+    async fn set_enable_ppt_group(
+        &mut self,
+        enable: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::FirmwareAttributes)?;
         let power_plugged = self
             .power
             .get_online()
@@ -569,11 +1556,15 @@ impl CtrlPlatform {
                         .group
                         .get_mut(&name)
                     {
-                        let value = tuning
-                            .group
-                            .get(&name)
-                            .map(|v| AttrValue::Integer(*v))
-                            .unwrap_or_else(|| attr.default_value().clone());
+                        let value = match tuning.group.get(&name) {
+                            Some(v) => Some(*v),
+                            None => match attr.default_value() {
+                                AttrValue::Integer(i) => Some(*i),
+                                _ => None,
+                            },
+                        }
+                        .map(|v| AttrValue::Integer(policy.enforce_attribute(name, v)))
+                        .unwrap_or_else(|| attr.default_value().clone());
                         // restore default
                         attr.set_current_value(&value)?;
                         if let AttrValue::Integer(i) = value {
@@ -582,6 +1573,7 @@ impl CtrlPlatform {
                     }
                 }
             }
+            mark_risky_applied();
         } else {
             // finally, reapply the profile to ensure acpi does the thingy
             self.platform.set_platform_profile(profile.into())?;
@@ -596,8 +1588,296 @@ impl CtrlPlatform {
 
         Ok(())
     }
+
+    /// Validate and apply a whole set of PPT values at once. Nothing is
+    /// changed if any value is invalid, and if a write fails every value
+    /// already written is restored. The values are stored in the tuning group
+    /// for the current profile and power state, and only written to the
+    /// device if that group is enabled.
+    async fn apply_ppt_preset(
+        &mut self,
+        values: Vec<(FirmwareAttribute, i32)>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        let power_plugged = self
+            .power
+            .get_online()
+            .map_err(|e| {
+                error!("Could not get power status: {e:?}");
+                e
+            })
+            .unwrap_or_default();
+        let profile: PlatformProfile = self.platform.get_platform_profile()?.into();
+
+        let mut config = self.config.lock().await;
+        let tuning = config.select_tunings(power_plugged == 1, profile);
+        let plan = self.plan_ppt_values(&values, &tuning.group)?;
+        if tuning.enabled {
+            Self::apply_attribute_plan(&plan)?;
+            mark_risky_applied();
+        }
+        for (name, value) in values {
+            tuning.group.insert(name, value);
+        }
+        config.write();
+        info!("Applied PPT preset for {profile:?}");
+        Ok(())
+    }
+
+    /// Write a batch of attribute values, such as the PPT limits with the dGPU
+    /// TGP and core counts, on trial. The values are checked and written as
+    /// for `apply_ppt_preset` but are not stored, and are reverted after
+    /// `timeout_secs` unless `confirm_attributes` is called. A batch tried
+    /// while another is still on trial joins it and restarts the timeout.
+    async fn try_attributes(
+        &self,
+        values: Vec<(FirmwareAttribute, i32)>,
+        timeout_secs: u32,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        Policy::load().check_writable(Lockdown::FirmwareAttributes)?;
+        if timeout_secs == 0 {
+            return Err(FdoErr::InvalidArgs(
+                "RogPlatform: the trial timeout must be at least one second".to_owned(),
+            ));
+        }
+        if values.iter().any(|(name, _)| name.is_ppt()) && !self.enable_ppt_group().await? {
+            return Err(FdoErr::Failed(
+                "RogPlatform: the PPT group for this profile must be enabled to try PPT values"
+                    .to_owned(),
+            ));
+        }
+
+        // A trial from another power source or profile is reverted first
+        let (on_ac, profile) = self.power_and_profile()?;
+        self.end_attribute_trial_for(on_ac, profile, &ctxt).await;
+        let plan = self.plan_attribute_values(&values, &HashMap::new())?;
+        let deadline = Instant::now() + Duration::from_secs(timeout_secs.into());
+        let mut trial = self.attribute_trial.lock().await;
+        Self::apply_attribute_plan(&plan)?;
+        let writes = plan
+            .iter()
+            .map(|(attr, previous, new)| (attr.name().into(), *previous, *new));
+        match trial.as_mut() {
+            Some(trial) => {
+                trial.writes.extend(writes);
+                trial.deadline = deadline;
+            }
+            None => {
+                *trial = Some(AttributeTrial {
+                    deadline,
+                    on_ac,
+                    profile,
+                    writes: writes.collect(),
+                })
+            }
+        }
+        drop(trial);
+        info!("Trying attribute values {values:?}, reverting in {timeout_secs}s unless confirmed");
+        self.attribute_trial_pending_changed(&ctxt).await?;
+
+        let ctrl = self.clone();
+        let ctxt = ctxt.to_owned();
+        tokio::spawn(async move {
+            sleep_until(deadline).await;
+            let mut trial = ctrl.attribute_trial.lock().await;
+            // A later batch moves the deadline on, its own timer handles it
+            if !trial.as_ref().is_some_and(|t| t.deadline <= Instant::now()) {
+                return;
+            }
+            if let Some(trial) = trial.take() {
+                warn!("Attribute values were not confirmed in time, reverting");
+                ctrl.revert_attribute_writes(&trial.writes);
+            }
+            drop(trial);
+            ctrl.attribute_trial_pending_changed(&ctxt)
+                .await
+                .map_err(|e| error!("Could not notify attribute_trial_pending: {e}"))
+                .ok();
+        });
+        Ok(())
+    }
+
+    /// Keep the attribute values on trial. PPT values are stored in the
+    /// tuning group of the profile and power state they were tried on, and
+    /// everything else in the attribute settings.
+    async fn confirm_attributes(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        let Some(trial) = self.attribute_trial.lock().await.take() else {
+            return Err(FdoErr::Failed(
+                "RogPlatform: no attribute values are on trial".to_owned(),
+            ));
+        };
+        let profile = trial.profile;
+
+        let mut config = self.config.lock().await;
+        for (name, _, value) in &trial.writes {
+            if name.is_ppt() {
+                config
+                    .select_tunings(trial.on_ac, profile)
+                    .group
+                    .insert(*name, *value);
+            } else {
+                config.armoury_settings.insert(*name, *value);
+            }
+        }
+        config.write();
+        drop(config);
+        if trial
+            .writes
+            .iter()
+            .any(|(name, ..)| name.is_ppt() || is_risky(*name))
+        {
+            mark_risky_applied();
+        }
+        info!("Confirmed attribute values for {profile:?}");
+        Ok(self.attribute_trial_pending_changed(&ctxt).await?)
+    }
+
+    /// Revert the attribute values on trial now instead of waiting for the
+    /// timeout
+    async fn revert_attributes(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::FirmwareAttributes).await?;
+        let Some(trial) = self.attribute_trial.lock().await.take() else {
+            return Err(FdoErr::Failed(
+                "RogPlatform: no attribute values are on trial".to_owned(),
+            ));
+        };
+        self.revert_attribute_writes(&trial.writes);
+        info!("Reverted attribute values on trial");
+        Ok(self.attribute_trial_pending_changed(&ctxt).await?)
+    }
+
+    /// Set while attribute values from `try_attributes` wait to be confirmed
+    #[zbus(property)]
+    async fn attribute_trial_pending(&self) -> bool {
+        self.attribute_trial.lock().await.is_some()
+    }
+
+    /// Toggle `panel_od` automatically: on at high refresh rates, off at low
+    #[zbus(property)]
+    async fn panel_od_auto(&self) -> bool {
+        self.config.lock().await.panel_od_auto
+    }
+
+    #[zbus(property)]
+    async fn set_panel_od_auto(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        enabled: bool,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        if enabled && self.attributes.panel_od().is_none() {
+            return Err(FdoErr::NotSupported(
+                "RogPlatform: panel_od not supported".to_owned(),
+            ));
+        }
+        self.config.lock().await.panel_od_auto = enabled;
+        self.config.lock().await.write();
+        self.apply_panel_od_auto().await;
+        self.start_panel_od_auto().await;
+        Ok(())
+    }
+
+    /// The `mini_led_mode` set when on AC power. `-1` if unbound
+    #[zbus(property)]
+    async fn mini_led_mode_on_ac(&self) -> i32 {
+        self.config.lock().await.mini_led_mode_on_ac.unwrap_or(-1)
+    }
+
+    #[zbus(property)]
+    async fn set_mini_led_mode_on_ac(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        mode: i32,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        self.check_mini_led_mode(mode)?;
+        self.config.lock().await.mini_led_mode_on_ac = (mode >= 0).then_some(mode);
+        self.config.lock().await.write();
+        self.apply_current_mini_led_binding().await
+    }
+
+    /// The `mini_led_mode` set when on battery power. `-1` if unbound
+    #[zbus(property)]
+    async fn mini_led_mode_on_battery(&self) -> i32 {
+        self.config
+            .lock()
+            .await
+            .mini_led_mode_on_battery
+            .unwrap_or(-1)
+    }
+
+    #[zbus(property)]
+    async fn set_mini_led_mode_on_battery(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        mode: i32,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        self.check_mini_led_mode(mode)?;
+        self.config.lock().await.mini_led_mode_on_battery = (mode >= 0).then_some(mode);
+        self.config.lock().await.write();
+        self.apply_current_mini_led_binding().await
+    }
+
+    /// The `mini_led_mode` set when `profile` becomes active. `-1` if unbound
+    async fn mini_led_mode_for_profile(&self, profile: PlatformProfile) -> i32 {
+        self.config
+            .lock()
+            .await
+            .mini_led_mode_on_profile
+            .get(&profile)
+            .copied()
+            .unwrap_or(-1)
+    }
+
+    /// Bind a `mini_led_mode` to `profile`. Use `-1` to remove the binding.
+    /// A profile binding takes precedence over the AC/battery binding
+    async fn set_mini_led_mode_for_profile(
+        &mut self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        profile: PlatformProfile,
+        mode: i32,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        self.check_mini_led_mode(mode)?;
+        {
+            let mut config = self.config.lock().await;
+            if mode >= 0 {
+                config.mini_led_mode_on_profile.insert(profile, mode);
+            } else {
+                config.mini_led_mode_on_profile.remove(&profile);
+            }
+            config.write();
+        }
+        self.apply_current_mini_led_binding().await
+    }
 }
 
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_platform.rs"));
+
 impl crate::ZbusRun for CtrlPlatform {
     async fn add_to_server(self, server: &mut Connection) {
         Self::add_to_server_helper(self, PLATFORM_ZBUS_PATH, server).await;
@@ -668,6 +1948,7 @@ impl crate::Reloadable for CtrlPlatform {
         } else {
             warn!("No charge_control_end_threshold found")
         }
+        self.restore_battery_charge_limits().await;
 
         if let Ok(power_plugged) = self.power.get_online() {
             self.config.lock().await.last_power_plugged = power_plugged;
@@ -677,6 +1958,10 @@ impl crate::Reloadable for CtrlPlatform {
                     .await;
             }
             self.run_ac_or_bat_cmd(power_plugged > 0).await;
+            if let Ok(profile) = self.platform.get_platform_profile() {
+                self.apply_mini_led_binding(power_plugged > 0, profile.into())
+                    .await;
+            }
         }
 
         Ok(())
@@ -685,6 +1970,41 @@ impl crate::Reloadable for CtrlPlatform {
 
 impl CtrlPlatform {
     task_watch_item!(charge_control_end_threshold "charge_control_end_threshold" power);
+
+    async fn on_sleep_change(&self, sleeping: bool) {
+        // This block is commented out due to some kind of issue reported. Maybe the
+        // desktops used were storing a value whcih was then read here.
+        // Don't store it on suspend, assume that the current config setting is desired
+        // if sleeping && self.power.has_charge_control_end_threshold() {
+        //     self.config.lock().await.charge_control_end_threshold = self
+        //         .power
+        //         .get_charge_control_end_threshold()
+        //         .unwrap_or(100);
+        // } else
+        if !sleeping && self.power.has_charge_control_end_threshold() {
+            self.power
+                .set_charge_control_end_threshold(
+                    self.config.lock().await.charge_control_end_threshold,
+                )
+                .ok();
+        }
+        if !sleeping {
+            self.restore_battery_charge_limits().await;
+        }
+        if let Ok(power_plugged) = self.power.get_online() {
+            if self.config.lock().await.last_power_plugged != power_plugged {
+                if !sleeping && self.platform.has_platform_profile() {
+                    let change_epp = self.config.lock().await.platform_profile_linked_epp;
+                    self.update_policy_ac_or_bat(power_plugged > 0, change_epp)
+                        .await;
+                }
+                if !sleeping {
+                    self.run_ac_or_bat_cmd(power_plugged > 0).await;
+                }
+                self.config.lock().await.last_power_plugged = power_plugged;
+            }
+        }
+    }
 }
 
 impl CtrlTask for CtrlPlatform {
@@ -693,51 +2013,35 @@ impl CtrlTask for CtrlPlatform {
     }
 
     async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
-        let platform1 = self.clone();
+        let platform = self.clone();
+        self.sleep_hooks
+            .on_pre_sleep("Platform", HookOrder::Platform, move || {
+                let platform = platform.clone();
+                async move { platform.on_sleep_change(true).await }
+            })
+            .await;
+        let platform = self.clone();
+        self.sleep_hooks
+            .on_post_resume("Platform", HookOrder::Platform, move || {
+                let platform = platform.clone();
+                async move { platform.on_sleep_change(false).await }
+            })
+            .await;
+
         let platform2 = self.clone();
         let platform3 = self.clone();
         let signal_ctxt_copy = signal_ctxt.clone();
+        let signal_ctxt_mux = signal_ctxt.clone();
         self.create_sys_event_tasks(
-            move |sleeping| {
-                let platform1 = platform1.clone();
-                async move {
-                    // This block is commented out due to some kind of issue reported. Maybe the
-                    // desktops used were storing a value whcih was then read here.
-                    // Don't store it on suspend, assume that the current config setting is desired
-                    // if sleeping && platform1.power.has_charge_control_end_threshold() {
-                    //     platform1.config.lock().await.charge_control_end_threshold = platform1
-                    //         .power
-                    //         .get_charge_control_end_threshold()
-                    //         .unwrap_or(100);
-                    // } else
-                    if !sleeping && platform1.power.has_charge_control_end_threshold() {
-                        platform1
-                            .power
-                            .set_charge_control_end_threshold(
-                                platform1.config.lock().await.charge_control_end_threshold,
-                            )
-                            .ok();
-                    }
-                    if let Ok(power_plugged) = platform1.power.get_online() {
-                        if platform1.config.lock().await.last_power_plugged != power_plugged {
-                            if !sleeping && platform1.platform.has_platform_profile() {
-                                let change_epp =
-                                    platform1.config.lock().await.platform_profile_linked_epp;
-                                platform1
-                                    .update_policy_ac_or_bat(power_plugged > 0, change_epp)
-                                    .await;
-                            }
-                            if !sleeping {
-                                platform1.run_ac_or_bat_cmd(power_plugged > 0).await;
-                            }
-                            platform1.config.lock().await.last_power_plugged = power_plugged;
-                        }
-                    }
-                }
-            },
             move |shutting_down| {
                 let platform2 = platform2.clone();
+                let signal_ctxt_mux = signal_ctxt_mux.clone();
                 async move {
+                    if shutting_down {
+                        platform2
+                            .apply_queued_mux_on_shutdown(&signal_ctxt_mux)
+                            .await;
+                    }
                     info!("RogPlatform reloading panel_od");
                     let lock = platform2.config.lock().await;
                     if shutting_down
@@ -787,6 +2091,9 @@ impl CtrlTask for CtrlPlatform {
                             error!("Platform: get_platform_profile error: {e}");
                         })
                     {
+                        platform3
+                            .end_attribute_trial_for(power_plugged, profile, &signal_ctxt_copy)
+                            .await;
                         // TODO: manage this better, shouldn't need to create every time
                         let attrs = FirmwareAttributes::new();
                         set_config_or_default(
@@ -796,6 +2103,9 @@ impl CtrlTask for CtrlPlatform {
                             profile,
                         )
                         .await;
+                        platform3
+                            .apply_mini_led_binding(power_plugged, profile)
+                            .await;
                         platform3
                             .enable_ppt_group_changed(&signal_ctxt_copy)
                             .await
@@ -811,6 +2121,94 @@ impl CtrlTask for CtrlPlatform {
         self.watch_charge_control_end_threshold(signal_ctxt_copy.clone())
             .await?;
 
+        self.start_panel_od_auto().await;
+
+        // The GPU modes follow the firmware attributes, which can also be
+        // changed through xyz.ljones.AsusArmoury
+        for name in [
+            FirmwareAttribute::GpuMuxMode,
+            FirmwareAttribute::DgpuDisable,
+            FirmwareAttribute::EgpuEnable,
+        ] {
+            let Some(attr) = self
+                .attributes
+                .attributes()
+                .iter()
+                .find(|a| FirmwareAttribute::from(a.name()) == name)
+                .cloned()
+            else {
+                continue;
+            };
+            let ctrl = self.clone();
+            let signal_ctxt = signal_ctxt_copy.clone();
+            self.supervisor
+                .spawn(&format!("Platform: {} watch", attr.name()), move || {
+                    let ctrl = ctrl.clone();
+                    let signal_ctxt = signal_ctxt.clone();
+                    let attr = attr.clone();
+                    async move {
+                        use futures_lite::StreamExt;
+                        let mut buffer = [0; 32];
+                        let mut events = attr
+                            .get_watcher("current_value")?
+                            .into_event_stream(&mut buffer)?;
+                        while events.next().await.is_some() {
+                            ctrl.notify_gpu_modes(&signal_ctxt).await;
+                        }
+                        Ok::<(), RogError>(())
+                    }
+                });
+        }
+
+        // USB-C power_supply devices send no uevent when the battery starts
+        // discharging under load, so poll for it
+        if self.power.has_usb_c() {
+            let ctrl = self.clone();
+            let signal_ctxt_usb = signal_ctxt_copy.clone();
+            self.supervisor.spawn("Platform: USB-C charger", move || {
+                let ctrl = ctrl.clone();
+                let signal_ctxt = signal_ctxt_usb.clone();
+                async move {
+                    let mut last = (ctrl.power.usb_pd_watts(), ctrl.power.slow_charger());
+                    loop {
+                        sleep(Duration::from_secs(5)).await;
+                        let now = (ctrl.power.usb_pd_watts(), ctrl.power.slow_charger());
+                        if now.0 != last.0 {
+                            debug!("Platform: USB-C charger now {:?}W", now.0);
+                            ctrl.usb_pd_watts_changed(&signal_ctxt).await?;
+                        }
+                        if now.1 != last.1 {
+                            if now.1 {
+                                info!("Platform: the USB-C charger is too slow to charge");
+                            }
+                            ctrl.slow_charger_changed(&signal_ctxt).await?;
+                        }
+                        last = now;
+                    }
+                    #[allow(unreachable_code)]
+                    Ok::<(), RogError>(())
+                }
+            });
+        }
+
+        let ctrl = self.clone();
+        let signal_ctxt_session = signal_ctxt_copy.clone();
+        self.supervisor.spawn("Platform: active session", move || {
+            let ctrl = ctrl.clone();
+            let signal_ctxt = signal_ctxt_session.clone();
+            async move {
+                use futures_util::StreamExt;
+                let conn = Connection::system().await?;
+                let seat = SeatProxy::new(&conn).await?;
+                let mut changes = seat.receive_active_session_changed().await;
+                while changes.next().await.is_some() {
+                    debug!("Platform: active session changed");
+                    ctrl.active_user_changed(&signal_ctxt).await?;
+                }
+                Ok::<(), RogError>(())
+            }
+        });
+
         let watch_platform_profile = self.platform.monitor_platform_profile()?;
         let ctrl = self.clone();
 
@@ -836,6 +2234,7 @@ impl CtrlTask for CtrlPlatform {
                         let epp = ctrl.get_config_epp_for_throttle(profile).await;
                         ctrl.check_and_set_epp(epp, change_epp);
                         ctrl.platform_profile_changed(&signal_ctxt_copy).await.ok();
+                        run_hooks(HookEvent::PlatformProfile(profile));
                         ctrl.enable_ppt_group_changed(&signal_ctxt_copy).await.ok();
                         let power_plugged = ctrl
                             .power
@@ -845,6 +2244,12 @@ impl CtrlTask for CtrlPlatform {
                                 e
                             })
                             .unwrap_or_default();
+                        ctrl.end_attribute_trial_for(
+                            power_plugged == 1,
+                            profile,
+                            &signal_ctxt_copy,
+                        )
+                        .await;
                         set_config_or_default(
                             &attrs,
                             &mut *ctrl.config.lock().await,
@@ -852,6 +2257,8 @@ impl CtrlTask for CtrlPlatform {
                             profile,
                         )
                         .await;
+                        ctrl.apply_mini_led_binding(power_plugged == 1, profile)
+                            .await;
                     }
                 }
             }
@@ -860,3 +2267,108 @@ impl CtrlTask for CtrlPlatform {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rog_platform::asus_armoury::FirmwareAttribute;
+    use rog_platform::platform::GpuMode;
+
+    use super::{check_ppt_order, gpu_mode_writes, sort_ppt_writes, supported_gpu_modes};
+
+    #[test]
+    fn supported_modes() {
+        assert!(supported_gpu_modes((None, None, None)).is_empty());
+        assert_eq!(supported_gpu_modes((None, Some(0), None)), vec![
+            GpuMode::Optimus,
+            GpuMode::Integrated
+        ]);
+        assert_eq!(supported_gpu_modes((Some(1), Some(0), Some(0))).len(), 4);
+    }
+
+    #[test]
+    fn mode_writes() {
+        // Optimus to Ultimate enables the dGPU before the MUX
+        assert_eq!(
+            gpu_mode_writes(GpuMode::Ultimate, (Some(1), Some(1), None)).unwrap(),
+            vec![
+                (FirmwareAttribute::DgpuDisable, 0),
+                (FirmwareAttribute::GpuMuxMode, 0)
+            ]
+        );
+        assert_eq!(
+            gpu_mode_writes(GpuMode::Integrated, (Some(1), Some(0), Some(1))).unwrap(),
+            vec![
+                (FirmwareAttribute::EgpuEnable, 0),
+                (FirmwareAttribute::DgpuDisable, 1)
+            ]
+        );
+        assert!(
+            gpu_mode_writes(GpuMode::Optimus, (Some(1), Some(0), Some(0)))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(gpu_mode_writes(GpuMode::Integrated, (Some(0), Some(0), None)).is_err());
+        assert!(gpu_mode_writes(GpuMode::Vfio, (Some(1), Some(0), None)).is_err());
+    }
+
+    #[test]
+    fn ppt_order() {
+        use FirmwareAttribute::*;
+        assert!(check_ppt_order(&[
+            (PptPl1Spl, 45),
+            (PptPl2Sppt, 65),
+            (PptFppt, 80)
+        ])
+        .is_ok());
+        assert!(check_ppt_order(&[
+            (PptPl1Spl, 70),
+            (PptPl2Sppt, 65)
+        ])
+        .is_err());
+        // A later value replaces the stored one
+        assert!(check_ppt_order(&[
+            (PptPl2Sppt, 40),
+            (PptPl1Spl, 45),
+            (PptPl2Sppt, 50)
+        ])
+        .is_ok());
+        // Both fast limits are checked against sppt, not against each other
+        assert!(check_ppt_order(&[
+            (PptPl2Sppt, 65),
+            (PptPl3Fppt, 80),
+            (PptFppt, 70)
+        ])
+        .is_ok());
+        assert!(check_ppt_order(&[
+            (PptPl2Sppt, 65),
+            (PptPl3Fppt, 80),
+            (PptFppt, 60)
+        ])
+        .is_err());
+        assert!(check_ppt_order(&[
+            (PptPl1Spl, 90),
+            (NvTempTarget, 80)
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn ppt_write_order() {
+        use FirmwareAttribute::*;
+        let mut writes = vec![
+            (PptFppt, 80, 100),
+            (PptPl1Spl, 60, 40),
+            (PptPl2Sppt, 70, 90),
+            (PptPl2Sppt, 70, 50),
+            (PptFppt, 80, 60),
+        ];
+        sort_ppt_writes(&mut writes, |w| *w);
+        assert_eq!(writes, vec![
+            (PptPl1Spl, 60, 40),
+            (PptPl2Sppt, 70, 50),
+            (PptFppt, 80, 60),
+            (PptFppt, 80, 100),
+            (PptPl2Sppt, 70, 90),
+        ]);
+    }
+}
@@ -0,0 +1,225 @@
+use log::{debug, info};
+use rog_platform::platform::RogPlatform;
+use rog_platform::privacy::{PrivacyLed, PrivacyLedType};
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize, Action};
+use crate::error::RogError;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+/// The webcam kill switch and the mic-mute and camera LEDs. Only loaded if the
+/// laptop has at least one of them, and each property fails with
+/// `NotSupported` if that one is missing.
+#[derive(Clone)]
+pub struct CtrlPrivacy {
+    platform: RogPlatform,
+    camera: bool,
+    mic_mute_led: Option<PrivacyLed>,
+    camera_led: Option<PrivacyLed>,
+}
+
+impl CtrlPrivacy {
+    pub fn new(platform: RogPlatform) -> Result<Self, RogError> {
+        let camera = platform.has_camera();
+        let mic_mute_led = PrivacyLed::new(PrivacyLedType::MicMute).ok();
+        let camera_led = PrivacyLed::new(PrivacyLedType::Camera).ok();
+        if !camera && mic_mute_led.is_none() && camera_led.is_none() {
+            return Err(RogError::MissingFunction(
+                "Privacy: no camera switch or privacy LEDs".into(),
+            ));
+        }
+        Ok(Self {
+            platform,
+            camera,
+            mic_mute_led,
+            camera_led,
+        })
+    }
+
+    fn led(led: &Option<PrivacyLed>, led_type: PrivacyLedType) -> Result<&PrivacyLed, FdoErr> {
+        led.as_ref()
+            .ok_or_else(|| FdoErr::NotSupported(format!("Privacy: no {led_type:?} LED")))
+    }
+
+    fn check_camera(&self) -> Result<(), FdoErr> {
+        if !self.camera {
+            return Err(FdoErr::NotSupported("Privacy: no camera switch".to_owned()));
+        }
+        Ok(())
+    }
+}
+
+#[interface(name = "xyz.ljones.Privacy")]
+impl CtrlPrivacy {
+    /// If the webcam is switched on. When false the firmware disconnects it
+    #[zbus(property)]
+    fn camera_enabled(&self) -> Result<bool, FdoErr> {
+        self.check_camera()?;
+        Ok(self.platform.get_camera().map_err(RogError::from)?)
+    }
+
+    #[zbus(property)]
+    async fn set_camera_enabled(
+        &mut self,
+        enabled: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        self.check_camera()?;
+        authorize(conn, &header, Action::Power).await?;
+        self.platform.set_camera(enabled).map_err(RogError::from)?;
+        info!("Privacy: camera enabled set to {enabled}");
+        Ok(())
+    }
+
+    /// If the mic-mute LED is lit. It normally follows the audio mute state,
+    /// and a change made here lasts until the next mute change
+    #[zbus(property)]
+    fn mic_mute_led(&self) -> Result<bool, FdoErr> {
+        let led = Self::led(&self.mic_mute_led, PrivacyLedType::MicMute)?;
+        Ok(led.is_on().map_err(RogError::from)?)
+    }
+
+    #[zbus(property)]
+    async fn set_mic_mute_led(
+        &mut self,
+        on: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        let led = Self::led(&self.mic_mute_led, PrivacyLedType::MicMute)?;
+        authorize(conn, &header, Action::Lighting).await?;
+        led.set_on(on).map_err(RogError::from)?;
+        Ok(())
+    }
+
+    /// If the camera LED is lit
+    #[zbus(property)]
+    fn camera_led(&self) -> Result<bool, FdoErr> {
+        let led = Self::led(&self.camera_led, PrivacyLedType::Camera)?;
+        Ok(led.is_on().map_err(RogError::from)?)
+    }
+
+    #[zbus(property)]
+    async fn set_camera_led(
+        &mut self,
+        on: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        let led = Self::led(&self.camera_led, PrivacyLedType::Camera)?;
+        authorize(conn, &header, Action::Lighting).await?;
+        led.set_on(on).map_err(RogError::from)?;
+        Ok(())
+    }
+
+    /// The names of the properties this laptop has, so clients can leave out
+    /// the others
+    #[zbus(property)]
+    fn supported(&self) -> Vec<String> {
+        let mut supported = Vec::new();
+        if self.camera {
+            supported.push("CameraEnabled".to_owned());
+        }
+        if self.mic_mute_led.is_some() {
+            supported.push("MicMuteLed".to_owned());
+        }
+        if self.camera_led.is_some() {
+            supported.push("CameraLed".to_owned());
+        }
+        supported
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_privacy.rs"));
+
+impl crate::ZbusRun for CtrlPrivacy {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlPrivacy {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlPrivacy {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    /// Send property changes made outside asusd, such as by the mic-mute key
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        use futures_util::StreamExt;
+
+        let watches = [
+            (
+                PrivacyLedType::MicMute,
+                self.mic_mute_led
+                    .as_ref()
+                    .map(|led| led.monitor_brightness()),
+            ),
+            (
+                PrivacyLedType::Camera,
+                self.camera_led.as_ref().map(|led| led.monitor_brightness()),
+            ),
+        ];
+        for (led_type, watch) in watches {
+            let watch = match watch {
+                Some(Ok(watch)) => watch,
+                Some(Err(e)) => {
+                    info!("Privacy: could not watch the {led_type:?} LED: {e}");
+                    continue;
+                }
+                None => continue,
+            };
+            let ctrl = self.clone();
+            let signal_ctxt = signal_ctxt.clone();
+            tokio::spawn(async move {
+                let mut buffer = [0; 32];
+                if let Ok(stream) = watch.into_event_stream(&mut buffer) {
+                    stream
+                        .for_each(|_| async {
+                            debug!("Privacy: {led_type:?} LED changed");
+                            match led_type {
+                                PrivacyLedType::MicMute => {
+                                    ctrl.mic_mute_led_changed(&signal_ctxt).await.ok()
+                                }
+                                PrivacyLedType::Camera => {
+                                    ctrl.camera_led_changed(&signal_ctxt).await.ok()
+                                }
+                            };
+                        })
+                        .await;
+                }
+            });
+        }
+
+        if self.camera {
+            match self.platform.monitor_camera() {
+                Ok(watch) => {
+                    let ctrl = self.clone();
+                    tokio::spawn(async move {
+                        let mut buffer = [0; 32];
+                        if let Ok(stream) = watch.into_event_stream(&mut buffer) {
+                            stream
+                                .for_each(|_| async {
+                                    debug!("Privacy: camera switch changed");
+                                    ctrl.camera_enabled_changed(&signal_ctxt).await.ok();
+                                })
+                                .await;
+                        }
+                    });
+                }
+                Err(e) => info!("Privacy: could not watch the camera switch: {e}"),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use config_traits::{StdConfig, StdConfigLoad};
+use futures_util::lock::Mutex;
+use log::{debug, info};
+use rog_platform::asus_armoury::FirmwareAttributes;
+use rog_platform::platform::{GpuMode, PlatformProfile, RogPlatform};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize_interactive, Action};
+use crate::error::RogError;
+use crate::sleep_hooks::{HookOrder, SleepHooks};
+use crate::supervisor::Supervisor;
+use crate::{CtrlTask, ASUS_ZBUS_PATH, CONFIG_PATH_BASE};
+
+/// How often the active profile and GPU mode are sampled
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// Write the counters every `n` samples to limit disk writes
+const WRITE_EVERY: u32 = 5;
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Seconds spent in each platform profile and GPU mode
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct UsageStats {
+    pub profile_secs: HashMap<PlatformProfile, u64>,
+    pub gpu_mode_secs: HashMap<GpuMode, u64>,
+    /// Unix time the counters were started or last reset
+    pub since: u64,
+}
+
+impl StdConfig for UsageStats {
+    fn new() -> Self {
+        Self {
+            since: now_secs(),
+            ..Default::default()
+        }
+    }
+
+    fn file_name(&self) -> String {
+        "usage_stats.ron".to_owned()
+    }
+
+    fn config_dir() -> std::path::PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for UsageStats {}
+
+#[derive(Clone)]
+pub struct CtrlUsageStats {
+    stats: Arc<Mutex<UsageStats>>,
+    platform: RogPlatform,
+    attributes: FirmwareAttributes,
+    supervisor: Supervisor,
+    sleep_hooks: SleepHooks,
+}
+
+impl CtrlUsageStats {
+    pub fn new(
+        platform: RogPlatform,
+        attributes: FirmwareAttributes,
+        supervisor: Supervisor,
+        sleep_hooks: SleepHooks,
+    ) -> Self {
+        Self {
+            stats: Arc::new(Mutex::new(UsageStats::new().load())),
+            platform,
+            attributes,
+            supervisor,
+            sleep_hooks,
+        }
+    }
+
+    /// Add `elapsed` to the counters for whatever is currently active
+    async fn record(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs();
+        let profile = self.platform.get_platform_profile().ok();
+        let gpu_mode = self.attributes.gpu_mode();
+        let mut stats = self.stats.lock().await;
+        if let Some(profile) = profile {
+            *stats.profile_secs.entry(profile.into()).or_default() += secs;
+        }
+        if let Some(mode) = gpu_mode {
+            *stats.gpu_mode_secs.entry(mode).or_default() += secs;
+        }
+    }
+}
+
+#[interface(name = "xyz.ljones.UsageStats")]
+impl CtrlUsageStats {
+    /// Seconds spent in each platform profile
+    #[zbus(property)]
+    async fn profile_seconds(&self) -> Vec<(PlatformProfile, u64)> {
+        let mut secs: Vec<(PlatformProfile, u64)> = self
+            .stats
+            .lock()
+            .await
+            .profile_secs
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        secs.sort();
+        secs
+    }
+
+    /// Seconds spent in each GPU mode
+    #[zbus(property)]
+    async fn gpu_mode_seconds(&self) -> Vec<(GpuMode, u64)> {
+        let mut secs: Vec<(GpuMode, u64)> = self
+            .stats
+            .lock()
+            .await
+            .gpu_mode_secs
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        secs.sort_by_key(|(k, _)| u8::from(*k));
+        secs
+    }
+
+    /// Unix time the counters were started or last reset
+    #[zbus(property)]
+    async fn since(&self) -> u64 {
+        self.stats.lock().await.since
+    }
+
+    /// Clear all counters
+    async fn reset(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalEmitter<'_>,
+    ) -> zbus::fdo::Result<()> {
+        authorize_interactive(conn, &header, Action::Power).await?;
+        {
+            let mut stats = self.stats.lock().await;
+            *stats = UsageStats::new();
+            stats.write();
+        }
+        info!("Usage stats were reset");
+        self.profile_seconds_changed(&ctxt).await.ok();
+        self.gpu_mode_seconds_changed(&ctxt).await.ok();
+        self.since_changed(&ctxt).await.ok();
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_stats.rs"));
+
+impl crate::ZbusRun for CtrlUsageStats {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlUsageStats {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlUsageStats {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        let ctrl1 = self.clone();
+        self.sleep_hooks
+            .on_pre_sleep("UsageStats", HookOrder::Stats, move || {
+                let ctrl1 = ctrl1.clone();
+                async move {
+                    ctrl1.stats.lock().await.write();
+                }
+            })
+            .await;
+
+        let ctrl2 = self.clone();
+        self.create_sys_event_tasks(
+            move |shutting_down| {
+                let ctrl2 = ctrl2.clone();
+                async move {
+                    if shutting_down {
+                        ctrl2.stats.lock().await.write();
+                    }
+                }
+            },
+            move |_lid_closed| async move {},
+            move |_power_plugged| async move {},
+        )
+        .await;
+
+        // `Instant` does not advance while suspended, so time asleep is not
+        // counted against whatever profile was active
+        let ctrl = self.clone();
+        self.supervisor.spawn("UsageStats: sampler", move || {
+            let ctrl = ctrl.clone();
+            async move {
+                let mut last = Instant::now();
+                let mut samples = 0;
+                loop {
+                    sleep(SAMPLE_INTERVAL).await;
+                    let now = Instant::now();
+                    ctrl.record(now - last).await;
+                    last = now;
+                    samples += 1;
+                    if samples >= WRITE_EVERY {
+                        debug!("Writing usage stats");
+                        ctrl.stats.lock().await.write();
+                        samples = 0;
+                    }
+                }
+                #[allow(unreachable_code)]
+                Ok::<(), RogError>(())
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_traits::StdConfig;
+use futures_util::lock::Mutex;
+use log::{debug, info, warn};
+use rog_aura::usb::{laptop_effect_packets, AURA_LAPTOP_LED_INIT};
+use rog_aura::{AuraEffect, Colour};
+use rog_platform::asus_armoury::{AttrValue, Attribute, FirmwareAttribute, FirmwareAttributes};
+use rog_platform::dgpu::dgpu_power;
+use rog_platform::platform::GpuMode;
+use rog_platform::xg_mobile::{xg_mobile_leds, XgMobileState};
+use tokio::time::sleep;
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::authorization::{authorize, Action};
+use crate::config::Config;
+use crate::ctrl_platform::{active_gpu_mux, gpu_mode_writes, write_gpu_attrs};
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+use crate::supervisor::Supervisor;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+/// How often `egpu_connected` is read, the firmware doesn't notify changes
+const CONNECTION_POLL: Duration = Duration::from_secs(2);
+/// The enclosure's USB devices take a moment to appear after it is connected
+const LIGHTS_DELAY: Duration = Duration::from_secs(3);
+
+/// The ROG XG Mobile eGPU. Reports when it is connected or removed, enables
+/// and disables it in a safe order, and turns the enclosure lights on or off.
+/// Loaded if the laptop has the `egpu_connected` and `egpu_enable`
+/// attributes.
+#[derive(Clone)]
+pub struct CtrlXgMobile {
+    attributes: FirmwareAttributes,
+    config: Arc<Mutex<Config>>,
+    supervisor: Supervisor,
+}
+
+impl CtrlXgMobile {
+    pub fn new(
+        attributes: FirmwareAttributes,
+        config: Arc<Mutex<Config>>,
+        supervisor: Supervisor,
+    ) -> Result<Self, RogError> {
+        if attributes.egpu_connected().is_none() || attributes.egpu_enable().is_none() {
+            return Err(RogError::MissingFunction(
+                "XgMobile: no egpu_connected or egpu_enable".into(),
+            ));
+        }
+        Ok(Self {
+            attributes,
+            config,
+            supervisor,
+        })
+    }
+
+    fn state(&self) -> XgMobileState {
+        let read = |attr: Option<&Attribute>| {
+            attr.and_then(|a| match a.current_value() {
+                Ok(AttrValue::Integer(i)) => Some(i as u8),
+                _ => None,
+            })
+        };
+        XgMobileState::from_attr_values(
+            read(self.attributes.egpu_connected()),
+            read(self.attributes.egpu_enable()),
+        )
+    }
+
+    /// Set the enclosure lights on or off, if it is connected
+    fn apply_lights(on: bool) -> Result<(), RogError> {
+        let leds = xg_mobile_leds()?;
+        let effect = if on {
+            AuraEffect::default()
+        } else {
+            AuraEffect {
+                colour1: Colour { r: 0, g: 0, b: 0 },
+                ..Default::default()
+            }
+        };
+        for packet in AURA_LAPTOP_LED_INIT {
+            leds.write_bytes(packet)?;
+        }
+        for packet in laptop_effect_packets(&effect) {
+            leds.write_bytes(&packet)?;
+        }
+        debug!("XgMobile: lights set {}", if on { "on" } else { "off" });
+        Ok(())
+    }
+}
+
+#[interface(name = "xyz.ljones.XgMobile")]
+impl CtrlXgMobile {
+    /// If an XG Mobile is plugged in and locked
+    #[zbus(property)]
+    fn connected(&self) -> bool {
+        self.state() != XgMobileState::Disconnected
+    }
+
+    /// If the XG Mobile is in use. It must be disabled before the lock is
+    /// opened
+    #[zbus(property)]
+    fn enabled(&self) -> bool {
+        self.state() == XgMobileState::Enabled
+    }
+
+    /// Switch to the XG Mobile. The dGPU is enabled first if it was disabled,
+    /// and this is refused while the MUX is in Ultimate
+    async fn enable(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::FirmwareAttributes)?;
+        match self.state() {
+            XgMobileState::Disconnected => {
+                return Err(FdoErr::Failed(
+                    "Connect the XG Mobile and close its lock first".to_owned(),
+                ))
+            }
+            XgMobileState::Enabled => return Ok(()),
+            XgMobileState::Connected => {}
+        }
+        // The eGPU can't be enabled while the MUX is in Ultimate
+        if active_gpu_mux(&self.attributes) == Some(0) {
+            return Err(FdoErr::Failed(
+                "Switch the GPU MUX to Optimus and reboot before using the XG Mobile".to_owned(),
+            ));
+        }
+        let writes = gpu_mode_writes(GpuMode::Egpu, self.attributes.gpu_attr_values())?;
+        for (name, value) in &writes {
+            policy.check_attribute(*name, *value)?;
+        }
+        write_gpu_attrs(&self.attributes, &self.config, &writes).await?;
+        info!("XgMobile: enabled");
+        self.enabled_changed(&ctxt).await?;
+        Ok(())
+    }
+
+    /// Stop using the XG Mobile so it can be unlocked and removed. Refused
+    /// while programs are still using its GPU
+    async fn disable(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::FirmwareAttributes).await?;
+        let policy = Policy::load();
+        policy.check_writable(Lockdown::FirmwareAttributes)?;
+        if self.state() != XgMobileState::Enabled {
+            return Ok(());
+        }
+        if !dgpu_power().settled() {
+            return Err(FdoErr::Failed(
+                "The XG Mobile GPU is in use, close the programs using it first".to_owned(),
+            ));
+        }
+        policy.check_attribute(FirmwareAttribute::EgpuEnable, 0)?;
+        write_gpu_attrs(&self.attributes, &self.config, &[(
+            FirmwareAttribute::EgpuEnable,
+            0,
+        )])
+        .await?;
+        info!("XgMobile: disabled, it can be unlocked and removed");
+        self.enabled_changed(&ctxt).await?;
+        Ok(())
+    }
+
+    /// If the enclosure lights are on. Stored and applied each time the XG
+    /// Mobile is connected
+    #[zbus(property)]
+    async fn lights(&self) -> bool {
+        self.config.lock().await.xg_mobile_lights
+    }
+
+    #[zbus(property)]
+    async fn set_lights(
+        &mut self,
+        on: bool,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        {
+            let mut config = self.config.lock().await;
+            config.xg_mobile_lights = on;
+            config.write();
+        }
+        if self.state() != XgMobileState::Disconnected {
+            Self::apply_lights(on)?;
+        }
+        Ok(())
+    }
+
+    /// An XG Mobile was connected or removed
+    #[zbus(signal)]
+    async fn connection_changed(emitter: &SignalEmitter<'_>, connected: bool) -> zbus::Result<()>;
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/ctrl_xg_mobile.rs"));
+
+impl crate::ZbusRun for CtrlXgMobile {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for CtrlXgMobile {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for CtrlXgMobile {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    /// Watch for the XG Mobile being connected or removed, setting the lights
+    /// when it is connected
+    async fn create_tasks(&self, signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        let ctrl = self.clone();
+        self.supervisor.spawn("XgMobile: connection", move || {
+            let ctrl = ctrl.clone();
+            let signal_ctxt = signal_ctxt.clone();
+            async move {
+                let mut last = ctrl.state();
+                loop {
+                    sleep(CONNECTION_POLL).await;
+                    let state = ctrl.state();
+                    if state == last {
+                        continue;
+                    }
+                    let connected = state != XgMobileState::Disconnected;
+                    if connected != (last != XgMobileState::Disconnected) {
+                        info!(
+                            "XgMobile: {}",
+                            if connected { "connected" } else { "removed" }
+                        );
+                        Self::connection_changed(&signal_ctxt, connected).await?;
+                        ctrl.connected_changed(&signal_ctxt).await?;
+                        if connected {
+                            sleep(LIGHTS_DELAY).await;
+                            let on = ctrl.config.lock().await.xg_mobile_lights;
+                            Self::apply_lights(on)
+                                .map_err(|e| warn!("XgMobile: could not set the lights: {e}"))
+                                .ok();
+                        }
+                    }
+                    ctrl.enabled_changed(&signal_ctxt).await?;
+                    last = state;
+                }
+                #[allow(unreachable_code)]
+                Ok::<(), RogError>(())
+            }
+        });
+        Ok(())
+    }
+}
@@ -2,32 +2,59 @@ use std::env;
 use std::error::Error;
 use std::sync::Arc;
 
+#[cfg(not(feature = "socket-api"))]
 use ::zbus::Connection;
+use asusd::api_dump::print_api;
 use asusd::asus_armoury::start_attributes_zbus;
+use asusd::aura_laptop::boot::apply_boot_lighting;
 use asusd::aura_manager::DeviceManager;
+use asusd::boot_watchdog::{revert_after_failed_boot, CtrlBootWatchdog};
 use asusd::config::Config;
 use asusd::ctrl_backlight::CtrlBacklight;
+use asusd::ctrl_capabilities::CtrlCapabilities;
+use asusd::ctrl_curve_optimizer::CtrlCurveOptimizer;
+use asusd::ctrl_dgpu_tgp::CtrlDgpuTgp;
 use asusd::ctrl_fancurves::CtrlFanCurveZbus;
+use asusd::ctrl_gpu_policy::CtrlGpuThermalPolicy;
+use asusd::ctrl_input::CtrlInput;
 use asusd::ctrl_platform::CtrlPlatform;
-use asusd::{print_board_info, start_tasks, CtrlTask, ZbusRun, DBUS_NAME};
+use asusd::ctrl_privacy::CtrlPrivacy;
+use asusd::ctrl_stats::CtrlUsageStats;
+use asusd::ctrl_xg_mobile::CtrlXgMobile;
+use asusd::dgpu_sleep::start_dgpu_sleep_guard;
+use asusd::metrics::start_metrics;
+use asusd::policy::Policy;
+use asusd::sleep_hooks::SleepHooks;
+use asusd::snapshot::CtrlSnapshot;
+use asusd::supervisor::Supervisor;
+use asusd::{logging, print_board_info, sd_notify, start_tasks, CtrlTask, ZbusRun, DBUS_NAME};
 use config_traits::{StdConfig, StdConfigLoad2};
 use futures_util::lock::Mutex;
-use log::{error, info};
+use log::{error, info, warn};
 use rog_platform::asus_armoury::FirmwareAttributes;
 use rog_platform::platform::RogPlatform;
 use rog_platform::power::AsusPower;
+use rog_platform::simulate;
 use zbus::fdo::ObjectManager;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // console_subscriber::init();
-    let mut logger = env_logger::Builder::new();
-    logger
-        .parse_default_env()
-        .target(env_logger::Target::Stdout)
-        .format_timestamp(None)
-        .filter_level(log::LevelFilter::Debug)
-        .init();
+    // Before the logger starts so that only the dump is on stdout
+    let args: Vec<String> = env::args().collect();
+    if let Some(pos) = args.iter().position(|arg| arg == "--dump-api") {
+        let format = args.get(pos + 1).map_or("json", String::as_str);
+        print_api(format).await?;
+        return Ok(());
+    }
+
+    logging::init();
+
+    // Run by asusd-boot-lighting.service before the full daemon is started
+    if env::args().any(|arg| arg == "--boot-lighting") {
+        apply_boot_lighting().await?;
+        return Ok(());
+    }
 
     let is_service = match env::var_os("IS_SERVICE") {
         Some(val) => val == "1",
@@ -59,19 +86,57 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
     // let supported = SupportedFunctions::get_supported();
     print_board_info();
     // println!("{:?}", supported.supported_functions());
+    if let Some(root) = simulate::root() {
+        warn!("Simulating the hardware in {root:?}, no devices will be changed");
+        simulate::populate(root)?;
+    }
 
-    // Start zbus server
+    // Start zbus server. With the socket API asusd also runs without a system
+    // bus, serving its interfaces to the socket alone
+    #[cfg(feature = "socket-api")]
+    let (mut server, socket_peer) = asusd::socket_api::server_connection().await?;
+    #[cfg(not(feature = "socket-api"))]
     let mut server = Connection::system().await?;
     server.object_server().at("/", ObjectManager).await.unwrap();
 
-    let config = Config::new().load();
+    let mut config = Config::new().load();
+    logging::apply_config(&config);
+    // Must run before anything applies the tuning in the config
+    revert_after_failed_boot(&mut config);
+    // Settings pinned by the administrator win over anything stored
+    Policy::load().apply_to_config(&mut config);
     let cfg_path = config.file_path();
+    let subsystems = config.subsystems;
+    let dgpu_sleep_guard = config.dgpu_sleep_guard;
     let config = Arc::new(Mutex::new(config));
 
     // supported.add_to_server(&mut connection).await;
     let platform = RogPlatform::new()?; // TODO: maybe needs async mutex?
     let power = AsusPower::new()?; // TODO: maybe needs async mutex?
-    let attributes = FirmwareAttributes::new();
+    let attributes = if subsystems.firmware_attributes {
+        FirmwareAttributes::new()
+    } else {
+        info!("Firmware attributes are disabled in asusd.ron");
+        FirmwareAttributes::empty()
+    };
+
+    // Runs the long-lived tasks of the controllers below, restarting any that
+    // fail
+    let sig_ctx = Supervisor::signal_context(&server)?;
+    let supervisor = Supervisor::new(sig_ctx.clone());
+    start_tasks(supervisor.clone(), &mut server, sig_ctx).await?;
+
+    // Suspend and resume handling for all controllers and devices, run in a
+    // fixed order
+    let sleep_hooks = SleepHooks::new();
+    sleep_hooks.start(&supervisor);
+    if let Err(e) = start_dgpu_sleep_guard(dgpu_sleep_guard, &sleep_hooks, &supervisor).await {
+        warn!("DgpuSleep: could not take a logind inhibitor: {e}");
+    }
+
+    let sig_ctx = CtrlBootWatchdog::signal_context(&server)?;
+    start_tasks(CtrlBootWatchdog::new(), &mut server, sig_ctx).await?;
+
     start_attributes_zbus(
         &server,
         platform.clone(),
@@ -81,33 +146,131 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
     )
     .await?;
 
-    match CtrlFanCurveZbus::new() {
-        Ok(ctrl) => {
-            let sig_ctx = CtrlFanCurveZbus::signal_context(&server)?;
-            start_tasks(ctrl, &mut server, sig_ctx).await?;
-        }
-        Err(err) => {
-            error!("FanCurves: {}", err);
+    if subsystems.fan_curves {
+        match CtrlFanCurveZbus::new(supervisor.clone()) {
+            Ok(ctrl) => {
+                let sig_ctx = CtrlFanCurveZbus::signal_context(&server)?;
+                start_tasks(ctrl, &mut server, sig_ctx).await?;
+            }
+            Err(err) => {
+                error!("FanCurves: {}", err);
+                supervisor
+                    .set_unavailable("FanCurves", err.to_string())
+                    .await;
+            }
         }
+    } else {
+        info!("Fan curves are disabled in asusd.ron");
     }
 
     match CtrlBacklight::new(config.clone()) {
         Ok(backlight) => {
-            backlight.start_watch_primary().await?;
+            backlight.start_watch_primary(&supervisor).await?;
             backlight.add_to_server(&mut server).await;
         }
         Err(err) => {
             error!("Backlight: {}", err);
+            supervisor
+                .set_unavailable("Backlight", err.to_string())
+                .await;
+        }
+    }
+
+    match CtrlPrivacy::new(platform.clone()) {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlPrivacy::signal_context(&server)?;
+            start_tasks(ctrl, &mut server, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("Privacy: {}", err);
+            supervisor.set_unavailable("Privacy", err.to_string()).await;
+        }
+    }
+
+    match CtrlInput::new() {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlInput::signal_context(&server)?;
+            start_tasks(ctrl, &mut server, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("Input: {}", err);
+            supervisor.set_unavailable("Input", err.to_string()).await;
+        }
+    }
+
+    match CtrlDgpuTgp::new(&attributes, config.clone(), supervisor.clone()) {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlDgpuTgp::signal_context(&server)?;
+            start_tasks(ctrl, &mut server, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("DgpuTgp: {}", err);
+            supervisor.set_unavailable("DgpuTgp", err.to_string()).await;
+        }
+    }
+
+    match CtrlGpuThermalPolicy::new(attributes.clone(), config.clone(), supervisor.clone()) {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlGpuThermalPolicy::signal_context(&server)?;
+            start_tasks(ctrl, &mut server, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("GpuThermalPolicy: {}", err);
+            supervisor
+                .set_unavailable("GpuThermalPolicy", err.to_string())
+                .await;
+        }
+    }
+
+    match CtrlXgMobile::new(attributes.clone(), config.clone(), supervisor.clone()) {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlXgMobile::signal_context(&server)?;
+            start_tasks(ctrl, &mut server, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("XgMobile: {}", err);
+            supervisor
+                .set_unavailable("XgMobile", err.to_string())
+                .await;
+        }
+    }
+
+    match CtrlCurveOptimizer::new(
+        platform.clone(),
+        config.clone(),
+        supervisor.clone(),
+        sleep_hooks.clone(),
+    ) {
+        Ok(ctrl) => {
+            let sig_ctx = CtrlCurveOptimizer::signal_context(&server)?;
+            start_tasks(ctrl, &mut server, sig_ctx).await?;
+        }
+        Err(err) => {
+            info!("CurveOptimizer: {}", err);
+            supervisor
+                .set_unavailable("CurveOptimizer", err.to_string())
+                .await;
         }
     }
 
+    let stats = CtrlUsageStats::new(
+        platform.clone(),
+        attributes.clone(),
+        supervisor.clone(),
+        sleep_hooks.clone(),
+    );
+    let sig_ctx = CtrlUsageStats::signal_context(&server)?;
+    start_tasks(stats, &mut server, sig_ctx).await?;
+
     match CtrlPlatform::new(
-        platform,
-        power,
-        attributes,
+        platform.clone(),
+        power.clone(),
+        attributes.clone(),
         config.clone(),
         &cfg_path,
         CtrlPlatform::signal_context(&server)?,
+        supervisor.clone(),
+        sleep_hooks.clone(),
     ) {
         Ok(ctrl) => {
             let sig_ctx = CtrlPlatform::signal_context(&server)?;
@@ -115,13 +278,48 @@ async fn start_daemon() -> Result<(), Box<dyn Error>> {
         }
         Err(err) => {
             error!("CtrlPlatform: {}", err);
+            supervisor
+                .set_unavailable("CtrlPlatform", err.to_string())
+                .await;
         }
     }
 
-    let _ = DeviceManager::new(server.clone()).await?;
+    #[cfg(feature = "mqtt")]
+    asusd::mqtt::start_mqtt(platform.clone(), power.clone(), &supervisor);
+    start_metrics(
+        platform.clone(),
+        power.clone(),
+        attributes.clone(),
+        &supervisor,
+    );
+
+    let manager =
+        DeviceManager::new(server.clone(), sleep_hooks, subsystems, supervisor.clone()).await?;
+    CtrlSnapshot::new(
+        platform.clone(),
+        power.clone(),
+        attributes.clone(),
+        config.clone(),
+        manager.devices(),
+    )
+    .start(&server)
+    .await?;
+
+    // Last so that every controller above is listed
+    let capabilities = CtrlCapabilities::new(platform, power, attributes);
+    let sig_ctx = CtrlCapabilities::signal_context(&server)?;
+    start_tasks(capabilities, &mut server, sig_ctx).await?;
 
-    // Request dbus name after finishing initalizing all functions
-    server.request_name(DBUS_NAME).await?;
+    // Request dbus name after finishing initalizing all functions. There is no
+    // bus to take it on when only the socket is served
+    if server.unique_name().is_some() {
+        server.request_name(DBUS_NAME).await?;
+    }
+    #[cfg(feature = "socket-api")]
+    asusd::socket_api::start_socket_api(socket_peer, &supervisor);
+    // Every controller is up, so systemd can start what is ordered after asusd
+    sd_notify::notify_ready();
+    sd_notify::start_watchdog(server.clone(), config, &supervisor);
 
     info!("Startup success, begining dbus server loop");
     loop {
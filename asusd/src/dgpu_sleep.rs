@@ -0,0 +1,215 @@
+//! Optionally hold off suspend until the dGPU has powered down, as some
+//! laptops hang on resume if they go to sleep while the dGPU is still active.
+//! This uses logind inhibitors: in `Delay` mode a delay inhibitor is released
+//! once the dGPU is suspended or the timeout passes, and in `Block` mode a
+//! block inhibitor is held for as long as the dGPU is active.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::lock::Mutex;
+use log::{debug, info, warn};
+use rog_platform::dgpu::{dgpu_power, Dgpu};
+use serde::{Deserialize, Serialize};
+use tokio::time::{sleep, Instant};
+use zbus::Connection;
+
+use crate::error::RogError;
+use crate::sleep_hooks::{HookOrder, Login1ManagerProxy, SleepHooks, INHIBITOR_WHO};
+use crate::supervisor::Supervisor;
+
+/// How often the dGPU is checked while suspend is delayed
+const DELAY_POLL: Duration = Duration::from_millis(100);
+/// How often the dGPU is checked in `Block` mode
+const BLOCK_POLL: Duration = Duration::from_secs(2);
+/// The delay inhibitor is released this long before logind's own limit, so
+/// that the timeout is logged here
+const LOGIND_MARGIN: Duration = Duration::from_millis(250);
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DgpuSleepMode {
+    /// Suspend whatever the dGPU is doing
+    #[default]
+    Off,
+    /// Delay suspend until the dGPU has powered down, up to the timeout
+    Delay,
+    /// Refuse to suspend while the dGPU is active
+    Block,
+}
+
+/// How suspend waits for the dGPU, stored as `dgpu_sleep_guard` in
+/// `asusd.ron` and read when asusd starts
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct DgpuSleepGuard {
+    pub mode: DgpuSleepMode,
+    /// The longest suspend is delayed for in `Delay` mode. logind's
+    /// `InhibitDelayMaxSec`, 5 seconds by default, is also a limit
+    pub timeout_ms: u64,
+}
+
+impl Default for DgpuSleepGuard {
+    fn default() -> Self {
+        Self {
+            mode: DgpuSleepMode::Off,
+            timeout_ms: 4000,
+        }
+    }
+}
+
+/// How long to wait for the dGPU: the configured timeout, cut short to end
+/// before logind stops waiting
+fn delay_budget(timeout: Duration, logind_max: Duration) -> Duration {
+    timeout.min(logind_max.saturating_sub(LOGIND_MARGIN))
+}
+
+/// Wait up to `budget` for the dGPU to power down. Returns how long that
+/// took, or `None` if it was still active at the end
+async fn wait_for_dgpu(budget: Duration) -> Option<Duration> {
+    let start = Instant::now();
+    loop {
+        if dgpu_power().settled() {
+            return Some(start.elapsed());
+        }
+        if start.elapsed() >= budget {
+            return None;
+        }
+        sleep(DELAY_POLL).await;
+    }
+}
+
+async fn start_delay(guard: DgpuSleepGuard, sleep_hooks: &SleepHooks) -> Result<(), RogError> {
+    let conn = Connection::system().await?;
+    let manager = Login1ManagerProxy::new(&conn).await?;
+    let timeout = Duration::from_millis(guard.timeout_ms);
+    let logind_max = manager
+        .inhibit_delay_max_usec()
+        .await
+        .map(Duration::from_micros)
+        .unwrap_or(Duration::from_secs(5));
+    let budget = delay_budget(timeout, logind_max);
+    if budget < timeout {
+        warn!(
+            "DgpuSleep: waiting at most {budget:?} for the dGPU, logind's InhibitDelayMaxSec is \
+             {logind_max:?}"
+        );
+    }
+
+    let why = "Waiting for the dGPU to power down";
+    let inhibitor = Arc::new(Mutex::new(Some(
+        manager
+            .inhibit("sleep", INHIBITOR_WHO, why, "delay")
+            .await?,
+    )));
+    info!("DgpuSleep: suspend waits up to {budget:?} for the dGPU to power down");
+
+    let inhibitor_copy = inhibitor.clone();
+    sleep_hooks
+        .on_pre_sleep("DgpuSleep", HookOrder::Inhibitor, move || {
+            let inhibitor = inhibitor_copy.clone();
+            async move {
+                match wait_for_dgpu(budget).await {
+                    Some(waited) => info!("DgpuSleep: the dGPU powered down after {waited:?}"),
+                    None => warn!(
+                        "DgpuSleep: the dGPU is still {:?} after {budget:?}, suspending anyway",
+                        dgpu_power()
+                    ),
+                }
+                // Dropping the fd releases the inhibitor
+                inhibitor.lock().await.take();
+            }
+        })
+        .await;
+    sleep_hooks
+        .on_post_resume("DgpuSleep", HookOrder::Inhibitor, move || {
+            let inhibitor = inhibitor.clone();
+            let manager = manager.clone();
+            async move {
+                match manager.inhibit("sleep", INHIBITOR_WHO, why, "delay").await {
+                    Ok(fd) => *inhibitor.lock().await = Some(fd),
+                    Err(e) => warn!("DgpuSleep: could not delay the next suspend: {e}"),
+                }
+            }
+        })
+        .await;
+    Ok(())
+}
+
+fn start_block(supervisor: &Supervisor) {
+    supervisor.spawn("DgpuSleep: block", move || async move {
+        let conn = Connection::system().await?;
+        let manager = Login1ManagerProxy::new(&conn).await?;
+        let mut inhibitor = None;
+        loop {
+            let power = dgpu_power();
+            match (power.settled(), inhibitor.is_some()) {
+                (false, false) => {
+                    info!("DgpuSleep: the dGPU is {power:?}, blocking suspend");
+                    inhibitor = Some(
+                        manager
+                            .inhibit("sleep", INHIBITOR_WHO, "The dGPU is active", "block")
+                            .await?,
+                    );
+                }
+                (true, true) => {
+                    info!("DgpuSleep: the dGPU powered down, allowing suspend");
+                    inhibitor = None;
+                }
+                _ => {}
+            }
+            sleep(BLOCK_POLL).await;
+        }
+        #[allow(unreachable_code)]
+        Ok::<(), RogError>(())
+    });
+}
+
+/// Start waiting for the dGPU on suspend as `guard` asks, if the laptop has
+/// a dGPU
+pub async fn start_dgpu_sleep_guard(
+    guard: DgpuSleepGuard,
+    sleep_hooks: &SleepHooks,
+    supervisor: &Supervisor,
+) -> Result<(), RogError> {
+    if guard.mode == DgpuSleepMode::Off {
+        return Ok(());
+    }
+    if let Err(e) = Dgpu::find() {
+        debug!("DgpuSleep: {e}, not waiting for it on suspend");
+        return Ok(());
+    }
+    match guard.mode {
+        DgpuSleepMode::Off => {}
+        DgpuSleepMode::Delay => start_delay(guard, sleep_hooks).await?,
+        DgpuSleepMode::Block => {
+            info!("DgpuSleep: suspend is blocked while the dGPU is active");
+            start_block(supervisor);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use config_traits::ron;
+
+    use super::{delay_budget, DgpuSleepGuard, DgpuSleepMode};
+
+    #[test]
+    fn budget_within_logind_limit() {
+        let secs = Duration::from_secs;
+        assert_eq!(delay_budget(secs(4), secs(5)), secs(4));
+        assert_eq!(delay_budget(secs(10), secs(5)), Duration::from_millis(4750));
+        assert_eq!(delay_budget(secs(4), Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn serde_defaults() {
+        let guard: DgpuSleepGuard = ron::from_str("(mode: Delay)").unwrap();
+        assert_eq!(guard.mode, DgpuSleepMode::Delay);
+        assert_eq!(guard.timeout_ms, 4000);
+        assert_eq!(DgpuSleepGuard::default().mode, DgpuSleepMode::Off);
+    }
+}
@@ -38,6 +38,8 @@ pub enum RogError {
     SystemdUnitWaitTimeout(String),
     Command(String, std::io::Error),
     ParseRon(ron::Error),
+    /// A request conflicts with the administrator's policy
+    PolicyDenied(String),
 }
 
 impl fmt::Display for RogError {
@@ -88,6 +90,7 @@ impl fmt::Display for RogError {
             }
             RogError::Command(func, error) => write!(f, "Command exec error: {}: {}", func, error),
             RogError::ParseRon(error) => write!(f, "Parse config error: {}", error),
+            RogError::PolicyDenied(detail) => write!(f, "Denied by system policy: {}", detail),
         }
     }
 }
@@ -139,13 +142,19 @@ impl From<ron::Error> for RogError {
 impl From<RogError> for zbus::fdo::Error {
     #[inline]
     fn from(err: RogError) -> Self {
-        zbus::fdo::Error::Failed(format!("{}", err))
+        match err {
+            RogError::PolicyDenied(_) => zbus::fdo::Error::AccessDenied(format!("{}", err)),
+            _ => zbus::fdo::Error::Failed(format!("{}", err)),
+        }
     }
 }
 
 impl From<RogError> for zbus::Error {
     #[inline]
     fn from(err: RogError) -> Self {
-        zbus::Error::Failure(format!("{}", err))
+        match err {
+            RogError::PolicyDenied(_) => zbus::fdo::Error::from(err).into(),
+            _ => zbus::Error::Failure(format!("{}", err)),
+        }
     }
 }
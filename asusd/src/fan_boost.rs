@@ -0,0 +1,214 @@
+//! Raise the fan curves of the active profile while the CPU is thermal
+//! throttling, see [`ThrottleBoost`]. The stored curves are not changed, the
+//! boosted ones are only written to the device and the stored ones are written
+//! back once the CPU has cooled.
+
+use std::path::Path;
+use std::time::Duration;
+
+use rog_profiles::fan_curve_set::CurveData;
+use serde::{Deserialize, Serialize};
+
+use crate::hwmon::{hwmon_temp, read_trimmed, CPU_HWMON};
+
+/// How often the CPU is checked while the boost is enabled
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+/// Samples in a row which must be throttling before the boost starts, so a
+/// short spike doesn't spin the fans up
+const SUSTAIN_SAMPLES: u32 = 3;
+/// Samples in a row which must be cool before the boost ends
+const RELEASE_SAMPLES: u32 = 15;
+/// Degrees under `throttle_temp` the CPU must be at to count as cool
+const HYSTERESIS: f32 = 10.0;
+/// The fan speed added for each step of `aggressiveness`, in pwm 0-255
+const PWM_PER_STEP: u8 = 26;
+pub const AGGRESSIVENESS_MAX: u8 = 5;
+
+/// The Intel package throttle counter, which counts each time the package
+/// went over its thermal limit. AMD has no counter so only the temperature is
+/// used there
+const PACKAGE_THROTTLE_COUNT: &str =
+    "/sys/devices/system/cpu/cpu0/thermal_throttle/package_throttle_count";
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ThrottleBoost {
+    pub enabled: bool,
+    /// 1 to `AGGRESSIVENESS_MAX`, each step adds about 10% fan speed to every
+    /// point of the curve
+    pub aggressiveness: u8,
+    /// The highest fan speed in percent the boost may raise a point to. Points
+    /// already above it in the stored curve are left as they are
+    pub cap: u8,
+    /// The CPU temperature in °C taken as throttling, for CPUs without a
+    /// throttle counter
+    pub throttle_temp: u8,
+}
+
+impl Default for ThrottleBoost {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            aggressiveness: 2,
+            cap: 100,
+            throttle_temp: 95,
+        }
+    }
+}
+
+impl ThrottleBoost {
+    /// `curve` raised by the aggressiveness and limited to the cap. The boosted
+    /// curve is always enabled, or the firmware would ignore it
+    pub fn boosted(&self, curve: &CurveData) -> CurveData {
+        let add = PWM_PER_STEP.saturating_mul(self.aggressiveness.min(AGGRESSIVENESS_MAX));
+        let cap = (u32::from(self.cap.min(100)) * 255 / 100) as u8;
+        let mut boosted = curve.clone();
+        for pwm in boosted.pwm.iter_mut() {
+            *pwm = (*pwm).max(pwm.saturating_add(add).min(cap));
+        }
+        boosted.enabled = true;
+        boosted
+    }
+}
+
+/// One reading of the CPU
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuSample {
+    /// Package temperature in °C
+    pub temp: Option<f32>,
+    /// The package throttle counter, if the CPU has one
+    pub throttle_count: Option<u64>,
+}
+
+impl CpuSample {
+    pub fn read() -> Self {
+        Self {
+            temp: hwmon_temp(CPU_HWMON),
+            throttle_count: read_trimmed(Path::new(PACKAGE_THROTTLE_COUNT))
+                .and_then(|count| count.parse().ok()),
+        }
+    }
+}
+
+/// Decides when the boost starts and ends from a run of samples
+#[derive(Debug, Default)]
+pub struct BoostState {
+    active: bool,
+    hot: u32,
+    cool: u32,
+    last_count: Option<u64>,
+}
+
+impl BoostState {
+    pub fn active(&self) -> bool {
+        self.active
+    }
+
+    /// Take a sample, returning the new state if the boost starts or ends
+    pub fn update(&mut self, config: &ThrottleBoost, sample: CpuSample) -> Option<bool> {
+        let counted = match (self.last_count, sample.throttle_count) {
+            (Some(last), Some(count)) => count > last,
+            _ => false,
+        };
+        self.last_count = sample.throttle_count.or(self.last_count);
+        let limit = f32::from(config.throttle_temp);
+        let throttling = counted || sample.temp.is_some_and(|t| t >= limit);
+        let cool = !counted && sample.temp.is_some_and(|t| t <= limit - HYSTERESIS);
+
+        self.hot = if throttling { self.hot + 1 } else { 0 };
+        self.cool = if cool { self.cool + 1 } else { 0 };
+
+        if !self.active && config.enabled && self.hot >= SUSTAIN_SAMPLES {
+            self.active = true;
+            return Some(true);
+        }
+        if self.active && (!config.enabled || self.cool >= RELEASE_SAMPLES) {
+            self.active = false;
+            self.hot = 0;
+            return Some(false);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rog_profiles::fan_curve_set::CurveData;
+
+    use super::{BoostState, CpuSample, ThrottleBoost, RELEASE_SAMPLES, SUSTAIN_SAMPLES};
+
+    fn temp(t: f32) -> CpuSample {
+        CpuSample {
+            temp: Some(t),
+            throttle_count: None,
+        }
+    }
+
+    #[test]
+    fn boosted_curve_is_capped() {
+        let curve = CurveData {
+            pwm: [
+                0, 20, 40, 80, 120, 160, 200, 250,
+            ],
+            ..Default::default()
+        };
+        let boost = ThrottleBoost {
+            aggressiveness: 2,
+            cap: 80,
+            ..Default::default()
+        };
+        let boosted = boost.boosted(&curve);
+        assert!(boosted.enabled);
+        assert_eq!(boosted.pwm, [52, 72, 92, 132, 172, 204, 204, 250]);
+        assert!(boosted.pwm.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn boost_needs_sustained_throttling() {
+        let config = ThrottleBoost {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut state = BoostState::default();
+        for _ in 1..SUSTAIN_SAMPLES {
+            assert_eq!(state.update(&config, temp(97.0)), None);
+        }
+        // A dip resets the count
+        assert_eq!(state.update(&config, temp(90.0)), None);
+        for _ in 1..SUSTAIN_SAMPLES {
+            assert_eq!(state.update(&config, temp(97.0)), None);
+        }
+        assert_eq!(state.update(&config, temp(97.0)), Some(true));
+
+        // Warm but not cool enough keeps it going
+        for _ in 0..RELEASE_SAMPLES * 2 {
+            assert_eq!(state.update(&config, temp(90.0)), None);
+        }
+        for _ in 1..RELEASE_SAMPLES {
+            assert_eq!(state.update(&config, temp(80.0)), None);
+        }
+        assert_eq!(state.update(&config, temp(80.0)), Some(false));
+        assert!(!state.active());
+    }
+
+    #[test]
+    fn boost_from_throttle_counter() {
+        let mut config = ThrottleBoost {
+            enabled: true,
+            ..Default::default()
+        };
+        let mut state = BoostState::default();
+        let count = |n| CpuSample {
+            temp: Some(70.0),
+            throttle_count: Some(n),
+        };
+        assert_eq!(state.update(&config, count(5)), None);
+        for n in 1..SUSTAIN_SAMPLES {
+            assert_eq!(state.update(&config, count(5 + u64::from(n))), None);
+        }
+        assert_eq!(state.update(&config, count(20)), Some(true));
+
+        config.enabled = false;
+        assert_eq!(state.update(&config, count(20)), Some(false));
+    }
+}
@@ -0,0 +1,233 @@
+//! The client a device's `Frame` signal is streamed for. Frames show the
+//! reactive effects of keys as they are typed, so streaming is only started
+//! for root or the user of the foreground session, the signal is sent to that
+//! client alone, and streaming stops when the client leaves the bus.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use futures_util::StreamExt;
+use log::{info, warn};
+use zbus::fdo::{self, DBusProxy};
+use zbus::message::Header;
+use zbus::names::{BusName, OwnedUniqueName};
+use zbus::object_server::SignalEmitter;
+use zbus::Connection;
+
+use crate::authorization::{authorize_interactive, Action};
+use crate::sessions::active_user;
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameStream {
+    /// The unique bus name of the client frames are streamed for
+    client: Arc<Mutex<Option<OwnedUniqueName>>>,
+}
+
+impl FrameStream {
+    fn client(&self) -> MutexGuard<'_, Option<OwnedUniqueName>> {
+        self.client.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.client().is_some()
+    }
+
+    /// `signal_ctxt` addressed to the client alone, as frames must not be
+    /// broadcast to every connection on the bus
+    pub fn emitter(&self, signal_ctxt: &SignalEmitter<'static>) -> Option<SignalEmitter<'static>> {
+        let client = self.client().clone()?;
+        Some(
+            signal_ctxt
+                .clone()
+                .set_destination(BusName::Unique(client.into_inner())),
+        )
+    }
+
+    /// Start streaming for the sender of `header`, or stop it. Starting needs
+    /// the polkit check for lighting, and the sender to be root or the user of
+    /// the foreground session.
+    pub async fn set_enabled(
+        &self,
+        conn: &Connection,
+        header: &Header<'_>,
+        enabled: bool,
+    ) -> fdo::Result<()> {
+        authorize_interactive(conn, header, Action::Lighting).await?;
+        if !enabled {
+            *self.client() = None;
+            return Ok(());
+        }
+        let Some(sender) = header.sender() else {
+            return Err(fdo::Error::AccessDenied(
+                "Frames are only streamed to a bus client".to_string(),
+            ));
+        };
+        let dbus = DBusProxy::new(conn).await?;
+        let uid = dbus
+            .get_connection_unix_user(BusName::Unique(sender.to_owned()))
+            .await?;
+        if uid != 0 && uid != active_user(conn).await {
+            return Err(fdo::Error::AccessDenied(
+                "Only the user of the foreground session may stream frames".to_string(),
+            ));
+        }
+        let client = OwnedUniqueName::from(sender.to_owned());
+        if self.client().replace(client.clone()).as_ref() != Some(&client) {
+            self.stop_when_gone(dbus, client);
+        }
+        Ok(())
+    }
+
+    /// Stop streaming once `client` leaves the bus, unless another client
+    /// started it since
+    fn stop_when_gone(&self, dbus: DBusProxy<'static>, client: OwnedUniqueName) {
+        let stream = self.clone();
+        tokio::spawn(async move {
+            match dbus
+                .receive_name_owner_changed_with_args(&[(0, client.as_str())])
+                .await
+            {
+                Ok(mut changes) => {
+                    // It may have left before the match rule was added
+                    if dbus
+                        .name_has_owner(BusName::Unique(client.clone().into_inner()))
+                        .await
+                        .unwrap_or(false)
+                    {
+                        while let Some(change) = changes.next().await {
+                            if change.args().is_ok_and(|args| args.new_owner().is_none()) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => warn!("Could not watch {client} for the frame stream: {e}"),
+            }
+            let mut current = stream.client();
+            if current.as_ref() == Some(&client) {
+                info!("{client} left the bus, stopping its frame stream");
+                *current = None;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use zbus::message::Type;
+    use zbus::object_server::SignalEmitter;
+    use zbus::{connection, Connection, MatchRule, MessageStream};
+
+    use super::FrameStream;
+
+    const BUS_CONFIG: &str = r#"<busconfig>
+  <type>session</type>
+  <listen>unix:tmpdir=/tmp</listen>
+  <policy context="default">
+    <allow send_destination="*"/>
+    <allow receive_sender="*"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>"#;
+
+    /// Stops the bus when the test ends, even by a panic
+    struct Daemon(Child);
+
+    impl Drop for Daemon {
+        fn drop(&mut self) {
+            self.0.kill().ok();
+            self.0.wait().ok();
+        }
+    }
+
+    /// A private bus and its address, or `None` if `dbus-daemon` is missing
+    fn private_bus() -> Option<(Daemon, String)> {
+        let config = std::env::temp_dir().join(format!("asusd-frames-{}.conf", std::process::id()));
+        std::fs::write(&config, BUS_CONFIG).ok()?;
+        let mut daemon = Command::new("dbus-daemon")
+            .arg(format!("--config-file={}", config.display()))
+            .args([
+                "--print-address", "--nofork",
+            ])
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        let stdout = daemon.stdout.take();
+        let daemon = Daemon(daemon);
+        let mut address = String::new();
+        BufReader::new(stdout?).read_line(&mut address).ok()?;
+        std::fs::remove_file(config).ok();
+        Some((daemon, address.trim().to_string()))
+    }
+
+    async fn connect(address: &str) -> Connection {
+        connection::Builder::address(address)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+
+    async fn next_member(msgs: &mut MessageStream) -> String {
+        let msg = tokio::time::timeout(Duration::from_secs(5), msgs.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        msg.header().member().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn frames_only_reach_the_client() {
+        let Some((_daemon, address)) = private_bus() else {
+            return;
+        };
+        let server = connect(&address).await;
+        let client = connect(&address).await;
+        let other = connect(&address).await;
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface("xyz.ljones.Test")
+            .unwrap()
+            .build();
+        let mut client_msgs = MessageStream::for_match_rule(rule.clone(), &client, None)
+            .await
+            .unwrap();
+        let mut other_msgs = MessageStream::for_match_rule(rule, &other, None)
+            .await
+            .unwrap();
+
+        let stream = FrameStream::default();
+        *stream.client() = Some(client.unique_name().unwrap().to_owned());
+        let signal_ctxt = SignalEmitter::new(&server, "/xyz/ljones/Test")
+            .unwrap()
+            .into_owned();
+        let emitter = stream.emitter(&signal_ctxt).unwrap();
+        emitter
+            .emit(
+                "xyz.ljones.Test",
+                "Frame",
+                &(vec![
+                    1u8, 2, 3,
+                ],),
+            )
+            .await
+            .unwrap();
+        // Broadcast after the frame, so it is the first signal the other
+        // connection sees if the frame was not delivered to it
+        signal_ctxt
+            .emit("xyz.ljones.Test", "Marker", &())
+            .await
+            .unwrap();
+
+        assert_eq!(next_member(&mut client_msgs).await, "Frame");
+        assert_eq!(next_member(&mut other_msgs).await, "Marker");
+
+        *stream.client() = None;
+        assert!(stream.emitter(&signal_ctxt).is_none());
+    }
+}
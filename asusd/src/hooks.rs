@@ -0,0 +1,250 @@
+use std::fs::Metadata;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+use rog_aura::AuraEffect;
+use rog_platform::platform::{GpuMode, PlatformProfile};
+
+use crate::CONFIG_PATH_BASE;
+
+/// A hook still running after this long is killed, so that it doesn't hold up
+/// the hooks of later events
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often a running hook is checked on
+const HOOK_POLL: Duration = Duration::from_millis(50);
+
+/// Something which happened in asusd that hook scripts are run for
+#[derive(Debug, Clone)]
+pub enum HookEvent {
+    PlatformProfile(PlatformProfile),
+    PowerSource {
+        plugged: bool,
+    },
+    /// `reboot_pending` is set if the new mode only applies after a reboot,
+    /// such as a MUX change
+    GpuMode {
+        mode: GpuMode,
+        reboot_pending: bool,
+    },
+    Aura(AuraEffect),
+}
+
+impl HookEvent {
+    /// The name passed as the first argument and in `ASUSD_EVENT`
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::PlatformProfile(_) => "platform-profile",
+            HookEvent::PowerSource { .. } => "power-source",
+            HookEvent::GpuMode { .. } => "gpu-mode",
+            HookEvent::Aura(_) => "aura",
+        }
+    }
+
+    /// The environment variables describing the event
+    pub fn env(&self) -> Vec<(&'static str, String)> {
+        let mut env = vec![("ASUSD_EVENT", self.name().to_owned())];
+        match self {
+            HookEvent::PlatformProfile(profile) => {
+                env.push(("ASUSD_PLATFORM_PROFILE", profile.to_string()));
+            }
+            HookEvent::PowerSource { plugged } => {
+                let source = if *plugged { "ac" } else { "battery" };
+                env.push(("ASUSD_POWER_SOURCE", source.to_owned()));
+            }
+            HookEvent::GpuMode {
+                mode,
+                reboot_pending,
+            } => {
+                env.push(("ASUSD_GPU_MODE", mode.to_string()));
+                env.push(("ASUSD_REBOOT_PENDING", (*reboot_pending as u8).to_string()));
+            }
+            HookEvent::Aura(effect) => {
+                let hex = |c: rog_aura::Colour| format!("{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+                env.push(("ASUSD_AURA_MODE", effect.mode.to_string()));
+                env.push(("ASUSD_AURA_ZONE", format!("{:?}", effect.zone)));
+                env.push(("ASUSD_AURA_COLOUR1", hex(effect.colour1)));
+                env.push(("ASUSD_AURA_COLOUR2", hex(effect.colour2)));
+            }
+        }
+        env
+    }
+}
+
+pub fn hooks_dir() -> PathBuf {
+    PathBuf::from(CONFIG_PATH_BASE).join("hooks.d")
+}
+
+/// Only root may be able to change what asusd runs as root
+fn is_secure(metadata: &Metadata, owner: u32) -> bool {
+    metadata.uid() == owner && metadata.mode() & 0o022 == 0
+}
+
+/// The executable files in `dir`, in name order. Hidden files are skipped so
+/// editor backups and the like aren't run. The directory and each file must be
+/// owned by root and not writable by the group or others.
+pub fn find_hooks(dir: &Path) -> Vec<PathBuf> {
+    find_hooks_owned_by(dir, 0)
+}
+
+fn find_hooks_owned_by(dir: &Path, owner: u32) -> Vec<PathBuf> {
+    let Ok(metadata) = std::fs::metadata(dir) else {
+        return Vec::new();
+    };
+    if !is_secure(&metadata, owner) {
+        warn!("Hooks: {dir:?} must be owned by root and only writable by it, not running hooks");
+        return Vec::new();
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut hooks: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .filter(|e| {
+            let Ok(metadata) = e.metadata() else {
+                return false;
+            };
+            if !metadata.is_file() || metadata.permissions().mode() & 0o111 == 0 {
+                return false;
+            }
+            if !is_secure(&metadata, owner) {
+                warn!(
+                    "Hooks: {:?} must be owned by root and only writable by it, skipping it",
+                    e.path()
+                );
+                return false;
+            }
+            true
+        })
+        .map(|e| e.path())
+        .collect();
+    hooks.sort();
+    hooks
+}
+
+/// Wait for `child` to exit, killing it if it is still running at `timeout`.
+/// Gives `None` if it was killed
+fn wait_or_kill(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(HOOK_POLL);
+    }
+}
+
+fn run_hooks_for(event: &HookEvent) {
+    let hooks = find_hooks(&hooks_dir());
+    let env = event.env();
+    for hook in hooks {
+        debug!("Hooks: running {hook:?} for {}", event.name());
+        let status = Command::new(&hook)
+            .arg(event.name())
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .spawn()
+            .and_then(|mut child| wait_or_kill(&mut child, HOOK_TIMEOUT));
+        match status {
+            Ok(Some(status)) if !status.success() => {
+                warn!("Hooks: {hook:?} for {} exited with {status}", event.name())
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => warn!(
+                "Hooks: {hook:?} for {} ran for more than {}s and was killed",
+                event.name(),
+                HOOK_TIMEOUT.as_secs()
+            ),
+            Err(e) => warn!("Hooks: could not run {hook:?}: {e}"),
+        }
+    }
+}
+
+/// Run every hook in `/etc/asusd/hooks.d` for `event`. Each is given the event
+/// name as its first argument and the details in `ASUSD_*` environment
+/// variables. Events are queued for one thread which runs the hooks of each in
+/// turn, so a slow hook doesn't hold up the daemon and hooks see the events in
+/// the order they happened. Their exit status is only logged.
+pub fn run_hooks(event: HookEvent) {
+    static QUEUE: OnceLock<Sender<HookEvent>> = OnceLock::new();
+    let queue = QUEUE.get_or_init(|| {
+        let (send, recv) = channel::<HookEvent>();
+        std::thread::spawn(move || {
+            for event in recv {
+                run_hooks_for(&event);
+            }
+        });
+        send
+    });
+    queue.send(event).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::process::Command;
+    use std::time::Duration;
+
+    use super::{find_hooks_owned_by, wait_or_kill, HookEvent};
+
+    #[test]
+    fn hooks_in_order() {
+        let dir = std::env::temp_dir().join(format!("asusd-hooks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        for (name, mode) in [
+            ("20-b", 0o755),
+            ("10-a", 0o755),
+            ("30-not-exec", 0o644),
+            ("40-group-writable", 0o775),
+            (".hidden", 0o755),
+        ] {
+            let path = dir.join(name);
+            fs::write(&path, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        let owner = fs::metadata(&dir).unwrap().uid();
+        let hooks = find_hooks_owned_by(&dir, owner);
+        let other_owner = find_hooks_owned_by(&dir, owner + 1);
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        let writable_dir = find_hooks_owned_by(&dir, owner);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(other_owner.is_empty());
+        assert!(writable_dir.is_empty());
+        assert_eq!(hooks, vec![
+            dir.join("10-a"),
+            dir.join("20-b")
+        ]);
+    }
+
+    #[test]
+    fn power_env() {
+        let env = HookEvent::PowerSource { plugged: false }.env();
+        assert_eq!(env, vec![
+            ("ASUSD_EVENT", "power-source".to_owned()),
+            ("ASUSD_POWER_SOURCE", "battery".to_owned())
+        ]);
+    }
+
+    #[test]
+    fn hung_hook_killed() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        assert!(wait_or_kill(&mut child, Duration::from_millis(100))
+            .unwrap()
+            .is_none());
+        let mut child = Command::new("true").spawn().unwrap();
+        assert!(wait_or_kill(&mut child, Duration::from_secs(10))
+            .unwrap()
+            .is_some_and(|status| status.success()));
+    }
+}
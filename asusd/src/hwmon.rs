@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+/// hwmon drivers for the CPU and GPU temperatures, the first found is used
+pub const CPU_HWMON: &[&str] = &[
+    "k10temp", "coretemp", "zenpower",
+];
+pub const GPU_HWMON: &[&str] = &[
+    "amdgpu", "nouveau",
+];
+/// The hwmon device of `asus-nb-wmi`, which has the fans
+const FAN_HWMON: &str = "asus";
+
+pub fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_owned())
+}
+
+fn hwmon_dirs() -> Vec<PathBuf> {
+    std::fs::read_dir("/sys/class/hwmon")
+        .map(|entries| entries.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default()
+}
+
+fn find_hwmon(dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    dirs.iter()
+        .find(|dir| read_trimmed(&dir.join("name")).is_some_and(|n| n == name))
+        .cloned()
+}
+
+/// The temperature in °C from `temp1_input` of the first hwmon device with
+/// one of `names`
+pub fn hwmon_temp(names: &[&str]) -> Option<f32> {
+    let dirs = hwmon_dirs();
+    names.iter().find_map(|name| {
+        find_hwmon(&dirs, name)
+            .and_then(|dir| read_trimmed(&dir.join("temp1_input")))
+            .and_then(|t| t.parse::<f32>().ok())
+            .map(|t| t / 1000.0)
+    })
+}
+
+/// The speed in RPM of each fan, named by its `fan<n>_label` such as
+/// `cpu_fan`, or `fan<n>` if it has no label
+pub fn fan_speeds() -> Vec<(String, u32)> {
+    let Some(dir) = find_hwmon(&hwmon_dirs(), FAN_HWMON) else {
+        return Vec::new();
+    };
+    (1..=8)
+        .filter_map(|n| {
+            let rpm = read_trimmed(&dir.join(format!("fan{n}_input")))?
+                .parse()
+                .ok()?;
+            let label = read_trimmed(&dir.join(format!("fan{n}_label")))
+                .filter(|l| !l.is_empty())
+                .unwrap_or_else(|| format!("fan{n}"));
+            Some((label, rpm))
+        })
+        .collect()
+}
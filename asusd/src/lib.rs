@@ -1,11 +1,33 @@
 #![deny(unused_must_use)]
+/// Print the DBus API as JSON for generating bindings
+pub mod api_dump;
+/// Revert risky tuning if a boot fails after it was applied
+pub mod boot_watchdog;
 /// Configuration loading, saving
 pub mod config;
 pub mod ctrl_backlight;
+/// DBus API versions and what this daemon provides
+pub mod ctrl_capabilities;
+/// AMD curve optimizer offsets per platform profile
+pub mod ctrl_curve_optimizer;
+/// Guarded control of the dGPU TGP
+pub mod ctrl_dgpu_tgp;
 /// Control platform profiles + fan-curves if available
 pub mod ctrl_fancurves;
+/// Combined NVIDIA dynamic boost and temperature target policy
+pub mod ctrl_gpu_policy;
+/// The ASUS hotkeys as one DBus signal
+pub mod ctrl_input;
 /// Control ASUS bios function such as boot sound, Optimus/Dedicated gfx mode
 pub mod ctrl_platform;
+/// Webcam kill switch and privacy LEDs
+pub mod ctrl_privacy;
+/// Time spent in each platform profile and GPU mode
+pub mod ctrl_stats;
+/// The XG Mobile eGPU, its connection, and its lights
+pub mod ctrl_xg_mobile;
+/// Holding off suspend until the dGPU has powered down
+pub mod dgpu_sleep;
 
 pub mod asus_armoury;
 pub mod aura_anime;
@@ -13,8 +35,43 @@ pub mod aura_laptop;
 pub mod aura_manager;
 pub mod aura_scsi;
 pub mod aura_slash;
+pub mod aura_sync;
 pub mod aura_types;
+/// Polkit checks on changes by lighting, power, and firmware tuning
+pub mod authorization;
 pub mod error;
+/// Raising the fan curves while the CPU is thermal throttling
+pub mod fan_boost;
+/// The client the `Frame` signals of a device are streamed for
+pub mod frame_stream;
+/// User scripts in `/etc/asusd/hooks.d` run on daemon events
+pub mod hooks;
+/// Temperatures and fan speeds from hwmon
+pub mod hwmon;
+/// The local time of day, for schedules
+pub mod local_time;
+/// Log levels from the config and changing them at runtime
+pub mod logging;
+/// Prometheus metrics on a local HTTP endpoint
+pub mod metrics;
+/// Telemetry and control over MQTT for Home Assistant
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+/// Settings pinned by an administrator in `/etc/asusd/policy.d`
+pub mod policy;
+/// Keyboard, lightbar, and AniMe settings applied together by name
+pub mod scenes;
+/// Startup and watchdog notifications to systemd
+pub mod sd_notify;
+/// The user whose login session is in the foreground
+pub mod sessions;
+pub mod sleep_hooks;
+/// Every controllable setting saved to and restored from one document
+pub mod snapshot;
+/// The DBus interfaces as JSON-RPC on a unix socket
+#[cfg(feature = "socket-api")]
+pub mod socket_api;
+pub mod supervisor;
 
 use std::future::Future;
 use std::time::Duration;
@@ -157,21 +214,23 @@ pub trait ReloadAndNotify {
 pub trait ZbusRun {
     fn add_to_server(self, server: &mut Connection) -> impl Future<Output = ()> + Send;
 
-    fn add_to_server_helper(
-        iface: impl Interface,
+    fn add_to_server_helper<I: Interface>(
+        iface: I,
         path: &str,
         server: &mut Connection,
     ) -> impl Future<Output = ()> + Send {
         async move {
-            server
+            let added = server
                 .object_server()
                 .at(&ObjectPath::from_str_unchecked(path), iface)
                 .await
                 .map_err(|err| {
                     warn!("{}: add_to_server {}", path, err);
                     err
-                })
-                .ok();
+                });
+            if added.is_ok() {
+                ctrl_capabilities::register_controller(I::name().as_str(), path);
+            }
         }
     }
 }
@@ -203,27 +262,25 @@ pub trait CtrlTask {
     //     });
     // }
 
-    /// Free helper method to create tasks to run on: sleep, wake, shutdown,
-    /// boot
+    /// Free helper method to create tasks to run on: shutdown, boot, lid and
+    /// external power changes. Suspend and resume are handled by
+    /// `sleep_hooks::SleepHooks`.
     ///
     /// The closures can potentially block, so execution time should be the
     /// minimal possible such as save a variable.
-    fn create_sys_event_tasks<Fut1, Fut2, Fut3, Fut4, F1, F2, F3, F4>(
+    fn create_sys_event_tasks<Fut1, Fut2, Fut3, F1, F2, F3>(
         &self,
-        mut on_prepare_for_sleep: F1,
-        mut on_prepare_for_shutdown: F2,
-        mut on_lid_change: F3,
-        mut on_external_power_change: F4,
+        mut on_prepare_for_shutdown: F1,
+        mut on_lid_change: F2,
+        mut on_external_power_change: F3,
     ) -> impl Future<Output = ()> + Send
     where
         F1: FnMut(bool) -> Fut1 + Send + 'static,
         F2: FnMut(bool) -> Fut2 + Send + 'static,
         F3: FnMut(bool) -> Fut3 + Send + 'static,
-        F4: FnMut(bool) -> Fut4 + Send + 'static,
         Fut1: Future<Output = ()> + Send,
         Fut2: Future<Output = ()> + Send,
         Fut3: Future<Output = ()> + Send,
-        Fut4: Future<Output = ()> + Send,
     {
         async {
             let connection = Connection::system()
@@ -251,23 +308,10 @@ pub trait CtrlTask {
 
             let manager2 = manager.clone();
             tokio::spawn(async move {
-                if let Ok(mut notif) = manager2.receive_prepare_for_sleep().await {
-                    while let Some(event) = notif.next().await {
-                        // blocks thread :|
-                        if let Ok(args) = event.args() {
-                            debug!("Doing on_prepare_for_sleep({})", args.start);
-                            on_prepare_for_sleep(args.start).await;
-                        }
-                    }
-                }
-            });
-
-            let manager3 = manager.clone();
-            tokio::spawn(async move {
-                let mut last_power = manager3.on_external_power().await.unwrap_or_default();
+                let mut last_power = manager2.on_external_power().await.unwrap_or_default();
 
                 loop {
-                    if let Ok(next) = manager3.on_external_power().await {
+                    if let Ok(next) = manager2.on_external_power().await {
                         if next != last_power {
                             last_power = next;
                             on_external_power_change(next).await;
@@ -0,0 +1,106 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOCALTIME: &str = "/etc/localtime";
+
+/// Minutes since local midnight, 0 to 1439. The UTC offset is read from
+/// `/etc/localtime` and UTC is used if that fails.
+pub fn minutes_since_midnight() -> u16 {
+    (local_secs().rem_euclid(86400) / 60) as u16
+}
+
+/// The local day of the week, 0 for Monday to 6 for Sunday
+pub fn weekday() -> u8 {
+    weekday_of(local_secs())
+}
+
+/// Seconds since the epoch, moved by the local UTC offset
+fn local_secs() -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let offset = std::fs::read(LOCALTIME)
+        .ok()
+        .and_then(|tzif| utc_offset(&tzif, now))
+        .unwrap_or_default();
+    now + offset as i64
+}
+
+fn weekday_of(secs: i64) -> u8 {
+    // The epoch was a Thursday
+    (secs.div_euclid(86400) + 3).rem_euclid(7) as u8
+}
+
+fn be_i32(data: &[u8], at: usize) -> Option<i32> {
+    Some(i32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The offset from UTC in seconds at `now` from the version 1 data of a TZif
+/// file, which covers up to 2037
+fn utc_offset(tzif: &[u8], now: i64) -> Option<i32> {
+    if tzif.get(..4)? != b"TZif" {
+        return None;
+    }
+    let count = |n: usize| be_i32(tzif, 20 + n * 4).map(|c| c as usize);
+    let (timecnt, typecnt) = (count(3)?, count(4)?);
+    if typecnt == 0 {
+        return None;
+    }
+    let times = 44;
+    let indices = times + timecnt * 4;
+    let types = indices + timecnt;
+    // Without transitions, or before the first, the first type is used
+    let mut index = 0;
+    for n in 0..timecnt {
+        if i64::from(be_i32(tzif, times + n * 4)?) > now {
+            break;
+        }
+        index = *tzif.get(indices + n)? as usize;
+    }
+    if index >= typecnt {
+        return None;
+    }
+    be_i32(tzif, types + index * 6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{utc_offset, weekday_of};
+
+    /// A TZif with UTC+1 until `change` and UTC+2 after
+    fn tzif(change: i32) -> Vec<u8> {
+        let mut data = b"TZif2".to_vec();
+        data.resize(20, 0);
+        for count in [
+            0, 0, 0, 1, 2, 0,
+        ] {
+            data.extend_from_slice(&(count as i32).to_be_bytes());
+        }
+        data.extend_from_slice(&change.to_be_bytes());
+        data.push(1);
+        for offset in [
+            3600i32, 7200,
+        ] {
+            data.extend_from_slice(&offset.to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+        }
+        data
+    }
+
+    #[test]
+    fn offset_from_transitions() {
+        let data = tzif(1000);
+        assert_eq!(utc_offset(&data, 999), Some(3600));
+        assert_eq!(utc_offset(&data, 1000), Some(7200));
+        assert_eq!(utc_offset(&data, 5000), Some(7200));
+        assert_eq!(utc_offset(b"not a tzif", 0), None);
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(weekday_of(0), 3);
+        // Monday 5 January 1970
+        assert_eq!(weekday_of(4 * 86400), 0);
+        assert_eq!(weekday_of(4 * 86400 - 1), 6);
+    }
+}
@@ -0,0 +1,226 @@
+//! The daemon logger. Levels come from `log_level` and `log_filters` in
+//! `asusd.ron`, or from `RUST_LOG` if that is set, and can be changed while
+//! running with the `SetLogFilter` method on `xyz.ljones.Platform`.
+
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::config::Config;
+
+/// The log target the `tracing` crate uses for span enter and exit events.
+/// zbus opens a span for every message it reads off the socket, so these are
+/// many per second and rarely useful.
+const SPAN_TARGET: &str = "tracing::span";
+
+/// A default level plus levels for individual modules, parsed from and
+/// printed as `RUST_LOG` style text such as
+/// `info,zbus=warn,asusd::ctrl_fancurves=trace`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    level: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Build the filter from the config. Module names not in the config keep
+    /// the default level, except for `tracing` spans which are off unless
+    /// `log_spans` is set.
+    pub fn from_config(config: &Config) -> Self {
+        let mut filter = Self {
+            level: LevelFilter::from_str(&config.log_level).unwrap_or(LevelFilter::Info),
+            modules: Vec::new(),
+        };
+        if !config.log_spans {
+            filter.set_module(SPAN_TARGET, LevelFilter::Off);
+        }
+        for (module, level) in &config.log_filters {
+            match LevelFilter::from_str(level) {
+                Ok(level) => filter.set_module(module, level),
+                Err(_) => eprintln!("asusd.ron: invalid log level {level} for {module}"),
+            }
+        }
+        filter
+    }
+
+    fn set_module(&mut self, module: &str, level: LevelFilter) {
+        self.modules.retain(|(m, _)| m != module);
+        self.modules.push((module.to_owned(), level));
+        // Longest first so the most specific module wins
+        self.modules.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    }
+
+    /// The level for a log target, from the longest module name which is the
+    /// target or a parent of it
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| {
+                target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.level, |(_, level)| *level)
+    }
+
+    /// The most verbose level of any module
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, LevelFilter::max)
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Self {
+            level: LevelFilter::Info,
+            modules: Vec::new(),
+        };
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let parse = |level: &str| {
+                LevelFilter::from_str(level.trim())
+                    .map_err(|_| format!("invalid log level {level}"))
+            };
+            match part.split_once('=') {
+                Some((module, level)) => filter.set_module(module.trim(), parse(level)?),
+                None => filter.level = parse(part)?,
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl std::fmt::Display for LogFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.level.as_str().to_lowercase())?;
+        for (module, level) in &self.modules {
+            write!(f, ",{module}={}", level.as_str().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+struct DaemonLogger {
+    inner: env_logger::Logger,
+    filter: RwLock<LogFilter>,
+}
+
+impl Log for DaemonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter
+            .read()
+            .is_ok_and(|f| metadata.level() <= f.level_for(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+static LOGGER: OnceLock<DaemonLogger> = OnceLock::new();
+
+/// `RUST_LOG` overrides the config when it is set and valid
+fn env_filter() -> Option<LogFilter> {
+    let spec = env::var("RUST_LOG").ok()?;
+    spec.parse().map_err(|e| eprintln!("RUST_LOG: {e}")).ok()
+}
+
+/// Install the logger. Until [`apply_config`] is called everything at `info`
+/// and above is logged, apart from `tracing` spans.
+pub fn init() {
+    let mut filter = LogFilter {
+        level: LevelFilter::Info,
+        modules: Vec::new(),
+    };
+    filter.set_module(SPAN_TARGET, LevelFilter::Off);
+    let filter = env_filter().unwrap_or(filter);
+    let max_level = filter.max_level();
+
+    let inner = env_logger::Builder::new()
+        .target(env_logger::Target::Stdout)
+        .format_timestamp(None)
+        .filter_level(LevelFilter::Trace)
+        .build();
+    let logger = LOGGER.get_or_init(|| DaemonLogger {
+        inner,
+        filter: RwLock::new(filter),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// Set the levels from the loaded config, unless `RUST_LOG` is set
+pub fn apply_config(config: &Config) {
+    if env_filter().is_none() {
+        set_filter(LogFilter::from_config(config));
+    }
+}
+
+/// Replace the filter in use. This is not saved to the config.
+pub fn set_filter(filter: LogFilter) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    log::set_max_level(filter.max_level());
+    if let Ok(mut lock) = logger.filter.write() {
+        *lock = filter;
+    }
+}
+
+/// The filter in use, as `RUST_LOG` style text
+pub fn current_filter() -> String {
+    LOGGER
+        .get()
+        .and_then(|l| l.filter.read().ok().map(|f| f.to_string()))
+        .unwrap_or_default()
+}
+
+/// Default module levels for `asusd.ron`. zbus logs every message it reads at
+/// debug level.
+pub fn default_log_filters() -> HashMap<String, String> {
+    HashMap::from([("zbus".to_owned(), "warn".to_owned())])
+}
+
+#[cfg(test)]
+mod tests {
+    use log::LevelFilter;
+
+    use super::LogFilter;
+
+    #[test]
+    fn most_specific_module_wins() {
+        let filter: LogFilter = "info,zbus=warn,zbus::connection=trace".parse().unwrap();
+        assert_eq!(filter.level_for("asusd::ctrl_platform"), LevelFilter::Info);
+        assert_eq!(filter.level_for("zbus::proxy"), LevelFilter::Warn);
+        assert_eq!(
+            filter.level_for("zbus::connection::socket"),
+            LevelFilter::Trace
+        );
+        // A module name is not a plain prefix
+        assert_eq!(filter.level_for("zbus_macros"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn parse_and_print() {
+        let filter: LogFilter = "debug, asusd::aura_anime=off".parse().unwrap();
+        assert_eq!(filter.to_string(), "debug,asusd::aura_anime=off");
+        assert_eq!(filter.to_string().parse::<LogFilter>().unwrap(), filter);
+        assert!("loud".parse::<LogFilter>().is_err());
+        assert!("zbus=loud".parse::<LogFilter>().is_err());
+    }
+}
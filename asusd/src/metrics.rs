@@ -0,0 +1,351 @@
+//! Serves fan speeds, temperatures, battery power, the charge limit, the
+//! platform profile, and the GPU mode as Prometheus metrics over HTTP, for
+//! scraping alongside node_exporter. Configured in `/etc/asusd/metrics.ron`
+//! and off by default. The endpoint is not authenticated so it listens on
+//! localhost unless set otherwise.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use log::{debug, info, warn};
+use rog_platform::asus_armoury::{AttrValue, FirmwareAttributes};
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_platform::power::AsusPower;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+
+use crate::error::RogError;
+use crate::hwmon::{fan_speeds, hwmon_temp, CPU_HWMON, GPU_HWMON};
+use crate::supervisor::Supervisor;
+use crate::CONFIG_PATH_BASE;
+
+/// Requests larger than this are refused, a scrape is a few hundred bytes
+const MAX_REQUEST: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections answered at once
+const MAX_CONNECTIONS: usize = 8;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Address and port to serve `/metrics` on
+    pub listen: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:9811".to_owned(),
+        }
+    }
+}
+
+impl StdConfig for MetricsConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "metrics.ron".to_owned()
+    }
+
+    fn config_dir() -> PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for MetricsConfig {}
+
+/// Metrics in the Prometheus text format. Every metric is a gauge.
+#[derive(Debug, Default)]
+struct Exposition(String);
+
+impl Exposition {
+    /// Add a metric with a sample for each set of labels. Nothing is added if
+    /// there are no samples, such as for a sensor the laptop doesn't have.
+    fn gauge(&mut self, name: &str, help: &str, samples: &[(Vec<(&str, String)>, f64)]) {
+        if samples.is_empty() {
+            return;
+        }
+        let out = &mut self.0;
+        writeln!(out, "# HELP {name} {help}").ok();
+        writeln!(out, "# TYPE {name} gauge").ok();
+        for (labels, value) in samples {
+            out.push_str(name);
+            if !labels.is_empty() {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|(k, v)| format!("{k}=\"{}\"", escape_label(v)))
+                    .collect();
+                write!(out, "{{{}}}", labels.join(",")).ok();
+            }
+            writeln!(out, " {value}").ok();
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A single unlabelled sample, if there is a value
+fn single(value: Option<f64>) -> Vec<(Vec<(&'static str, String)>, f64)> {
+    value.map(|v| (Vec::new(), v)).into_iter().collect()
+}
+
+fn gather(platform: &RogPlatform, power: &AsusPower, attributes: &FirmwareAttributes) -> String {
+    let mut out = Exposition::default();
+
+    let fans: Vec<_> = fan_speeds()
+        .into_iter()
+        .map(|(fan, rpm)| (vec![("fan", fan)], rpm as f64))
+        .collect();
+    out.gauge("asusd_fan_speed_rpm", "Fan speed", &fans);
+
+    let temps: Vec<_> = [
+        ("cpu", CPU_HWMON),
+        ("gpu", GPU_HWMON),
+    ]
+    .into_iter()
+    .filter_map(|(sensor, names)| {
+        hwmon_temp(names).map(|t| (vec![("sensor", sensor.to_owned())], t as f64))
+    })
+    .collect();
+    out.gauge("asusd_temperature_celsius", "Temperature", &temps);
+
+    out.gauge(
+        "asusd_ac_power",
+        "1 if on AC power",
+        &single(power.get_online().ok().map(f64::from)),
+    );
+    out.gauge(
+        "asusd_battery_capacity_percent",
+        "Battery charge",
+        &single(power.get_capacity().ok().map(f64::from)),
+    );
+    out.gauge(
+        "asusd_battery_power_watts",
+        "Battery charge or discharge power",
+        &single(power.get_power_now().ok().map(|p| p as f64 / 1_000_000.0)),
+    );
+    out.gauge(
+        "asusd_charge_control_end_threshold_percent",
+        "Charge limit",
+        &single(power.get_charge_control_end_threshold().ok().map(f64::from)),
+    );
+
+    let current = platform
+        .get_platform_profile()
+        .ok()
+        .map(|p| PlatformProfile::from(p.as_str()));
+    let profiles: Vec<_> = platform
+        .get_platform_profile_choices()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|p| {
+            let value = if Some(p) == current { 1.0 } else { 0.0 };
+            (vec![("profile", <&str>::from(&p).to_owned())], value)
+        })
+        .collect();
+    out.gauge(
+        "asusd_platform_profile",
+        "1 for the active platform profile",
+        &profiles,
+    );
+
+    let gpu_mode: Vec<_> = attributes
+        .gpu_mode()
+        .map(|mode| (vec![("mode", mode.to_string())], 1.0))
+        .into_iter()
+        .collect();
+    out.gauge(
+        "asusd_gpu_mode",
+        "The GPU mode the firmware is set to",
+        &gpu_mode,
+    );
+    out.gauge(
+        "asusd_gpu_reboot_pending",
+        "1 if a GPU mode change waits for a reboot",
+        &single(Some(FirmwareAttributes::pending_reboot() as u8 as f64)),
+    );
+    let tgp = attributes.dgpu_tgp().and_then(|a| match a.current_value() {
+        Ok(AttrValue::Integer(i)) => Some(i as f64),
+        _ => None,
+    });
+    out.gauge("asusd_dgpu_tgp_watts", "dGPU TGP", &single(tgp));
+
+    out.0
+}
+
+/// The request line of a complete HTTP request
+#[derive(Debug, PartialEq)]
+enum Request<'a> {
+    /// A `GET` of the path
+    Get(&'a str),
+    /// Any other method, which is refused
+    Other,
+}
+
+/// Parse `request`, `None` until the headers are complete
+fn parse_request(request: &str) -> Option<Request<'_>> {
+    if !request.contains("\r\n\r\n") {
+        return None;
+    }
+    let mut parts = request.lines().next()?.split(' ');
+    if parts.next()? != "GET" {
+        return Some(Request::Other);
+    }
+    Some(Request::Get(parts.next().unwrap_or_default()))
+}
+
+/// Read the request from `stream`, `None` if it is too large. Closing the
+/// connection first is an `UnexpectedEof` error.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<String>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.len() > MAX_REQUEST {
+            return Ok(None);
+        }
+        if buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            return Ok(Some(String::from_utf8_lossy(&buf).into_owned()));
+        }
+    }
+}
+
+async fn respond(
+    mut stream: TcpStream,
+    platform: &RogPlatform,
+    power: &AsusPower,
+    attributes: &FirmwareAttributes,
+) -> std::io::Result<()> {
+    // The whole request must arrive in time, not each read of it
+    let request = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+        Ok(request) => request?,
+        Err(_) => return Err(std::io::ErrorKind::TimedOut.into()),
+    };
+
+    let (status, headers, body) = match request.as_deref().and_then(parse_request) {
+        Some(Request::Get("/metrics")) => ("200 OK", "", gather(platform, power, attributes)),
+        Some(Request::Get(_)) => ("404 Not Found", "", "Metrics are at /metrics\n".to_owned()),
+        Some(Request::Other) => (
+            "405 Method Not Allowed",
+            "Allow: GET\r\n",
+            "Only GET is supported\n".to_owned(),
+        ),
+        None => (
+            "413 Content Too Large",
+            "",
+            "The request is too large\n".to_owned(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; \
+         charset=utf-8\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn serve(
+    listen: String,
+    platform: RogPlatform,
+    power: AsusPower,
+    attributes: FirmwareAttributes,
+) -> Result<(), RogError> {
+    let listener = TcpListener::bind(&listen).await?;
+    info!("Metrics: serving on http://{listen}/metrics");
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        // Further connections wait in the listen backlog until one is answered
+        let Ok(permit) = connections.clone().acquire_owned().await else {
+            return Ok(());
+        };
+        let (stream, peer) = listener.accept().await?;
+        debug!("Metrics: request from {peer}");
+        let platform = platform.clone();
+        let power = power.clone();
+        let attributes = attributes.clone();
+        tokio::spawn(async move {
+            respond(stream, &platform, &power, &attributes)
+                .await
+                .map_err(|e| warn!("Metrics: could not answer {peer}: {e}"))
+                .ok();
+            drop(permit);
+        });
+    }
+}
+
+/// Start serving metrics if enabled in `/etc/asusd/metrics.ron`
+pub fn start_metrics(
+    platform: RogPlatform,
+    power: AsusPower,
+    attributes: FirmwareAttributes,
+    supervisor: &Supervisor,
+) {
+    let config = MetricsConfig::new().load();
+    if !config.enabled {
+        return;
+    }
+    supervisor.spawn("Metrics", move || {
+        serve(
+            config.listen.clone(),
+            platform.clone(),
+            power.clone(),
+            attributes.clone(),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_request, Exposition, Request};
+
+    #[test]
+    fn exposition_format() {
+        let mut out = Exposition::default();
+        out.gauge("asusd_fan_speed_rpm", "Fan speed", &[
+            (vec![("fan", "cpu_fan".to_owned())], 2400.0),
+            (vec![("fan", "gpu \"2\"".to_owned())], 0.0),
+        ]);
+        out.gauge("asusd_missing", "Not on this laptop", &[]);
+        assert_eq!(
+            out.0,
+            r#"# HELP asusd_fan_speed_rpm Fan speed
+# TYPE asusd_fan_speed_rpm gauge
+asusd_fan_speed_rpm{fan="cpu_fan"} 2400
+asusd_fan_speed_rpm{fan="gpu \"2\""} 0
+"#
+        );
+    }
+
+    #[test]
+    fn request_paths() {
+        assert_eq!(
+            parse_request("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some(Request::Get("/metrics"))
+        );
+        assert_eq!(parse_request("GET /metrics HTTP/1.1\r\nHost: loc"), None);
+        assert_eq!(
+            parse_request("POST /metrics HTTP/1.1\r\n\r\n"),
+            Some(Request::Other)
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Publishes the platform profile, power, and temperatures to an MQTT broker
+//! and takes commands to set the profile and turn the keyboard LEDs on or off,
+//! for Home Assistant and similar. Entities are announced with Home Assistant
+//! MQTT discovery. Built with the `mqtt` feature and configured in
+//! `/etc/asusd/mqtt.ron`.
+//!
+//! Topics are under `<topic_prefix>/<host name>/`:
+//! - `state`, JSON with `profile`, `ac_power`, `battery`, `charge_limit`,
+//!   `cpu_temp`, `gpu_temp`, and `leds`
+//! - `availability`, `online` or `offline`
+//! - `profile/set`, a profile name such as `performance`
+//! - `leds/set`, `ON` or `OFF`
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use dmi_id::DMIID;
+use log::{debug, info, warn};
+use rog_aura::LedBrightness;
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_platform::power::AsusPower;
+use rumqttc::{
+    AsyncClient, Event, LastWill, MqttOptions, Packet, QoS, TlsConfiguration, Transport,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{Connection, Proxy};
+
+use crate::error::RogError;
+use crate::hwmon::{hwmon_temp, read_trimmed, CPU_HWMON, GPU_HWMON};
+use crate::supervisor::Supervisor;
+use crate::{ASUS_ZBUS_PATH, CONFIG_PATH_BASE, DBUS_NAME};
+
+const AURA_IFACE: &str = "xyz.ljones.Aura";
+const PLATFORM_IFACE: &str = "xyz.ljones.Platform";
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Connect with TLS, checking the broker's certificate against the
+    /// system's CA certificates or `ca_file`
+    pub tls: bool,
+    /// A PEM CA certificate to check the broker's certificate with
+    pub ca_file: Option<PathBuf>,
+    pub topic_prefix: String,
+    /// The Home Assistant discovery prefix
+    pub discovery_prefix: String,
+    /// Seconds between state updates. State is also sent after a command
+    pub interval_secs: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".to_owned(),
+            port: 1883,
+            username: None,
+            password: None,
+            tls: false,
+            ca_file: None,
+            topic_prefix: "asusd".to_owned(),
+            discovery_prefix: "homeassistant".to_owned(),
+            interval_secs: 30,
+        }
+    }
+}
+
+impl StdConfig for MqttConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "mqtt.ron".to_owned()
+    }
+
+    fn config_dir() -> PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+
+    /// It holds the broker password
+    fn file_mode(&self) -> u32 {
+        0o600
+    }
+}
+
+impl StdConfigLoad for MqttConfig {}
+
+#[derive(Serialize, Debug, Default)]
+struct Telemetry {
+    profile: Option<String>,
+    ac_power: Option<bool>,
+    battery: Option<u8>,
+    charge_limit: Option<u8>,
+    cpu_temp: Option<f32>,
+    gpu_temp: Option<f32>,
+    leds: Option<bool>,
+}
+
+/// The host name with anything but ASCII letters and digits replaced, for
+/// use in topics and entity IDs
+fn device_id() -> String {
+    let host = read_trimmed(Path::new("/proc/sys/kernel/hostname"))
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "asus".to_owned());
+    host.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+struct Mqtt {
+    config: MqttConfig,
+    platform: RogPlatform,
+    power: AsusPower,
+    id: String,
+    /// Restored when the LEDs are turned back on
+    last_brightness: AtomicU32,
+}
+
+impl Mqtt {
+    fn topic(&self, suffix: &str) -> String {
+        format!("{}/{}/{suffix}", self.config.topic_prefix, self.id)
+    }
+
+    /// The object paths of the Aura devices and their `Brightness`
+    async fn aura_brightness(dbus: &Connection) -> Result<Vec<(OwnedObjectPath, u32)>, RogError> {
+        let objects = ObjectManagerProxy::builder(dbus)
+            .destination(DBUS_NAME)?
+            .path("/")?
+            .build()
+            .await?
+            .get_managed_objects()
+            .await
+            .map_err(zbus::Error::from)?;
+        Ok(objects
+            .into_iter()
+            .filter_map(|(path, ifaces)| {
+                let props: &HashMap<String, OwnedValue> = ifaces
+                    .iter()
+                    .find(|(name, _)| name.as_str() == AURA_IFACE)
+                    .map(|(_, props)| props)?;
+                let brightness = props
+                    .get("Brightness")
+                    .and_then(|v| v.downcast_ref::<u32>().ok())
+                    .unwrap_or_default();
+                Some((path, brightness))
+            })
+            .collect())
+    }
+
+    async fn telemetry(&self, dbus: &Connection) -> Telemetry {
+        let leds = Self::aura_brightness(dbus)
+            .await
+            .map_err(|e| debug!("MQTT: could not read Aura brightness: {e}"))
+            .ok()
+            .filter(|auras| !auras.is_empty())
+            .map(|auras| {
+                if let Some((_, b)) = auras.iter().find(|(_, b)| *b != 0) {
+                    self.last_brightness.store(*b, Ordering::Relaxed);
+                }
+                auras.iter().any(|(_, b)| *b != 0)
+            });
+        Telemetry {
+            profile: self
+                .platform
+                .get_platform_profile()
+                .ok()
+                .map(|p| <&str>::from(&PlatformProfile::from(p.as_str())).to_owned()),
+            ac_power: self.power.get_online().ok().map(|o| o == 1),
+            battery: self.power.get_capacity().ok(),
+            charge_limit: self.power.get_charge_control_end_threshold().ok(),
+            cpu_temp: hwmon_temp(CPU_HWMON),
+            gpu_temp: hwmon_temp(GPU_HWMON),
+            leds,
+        }
+    }
+
+    async fn publish_state(&self, client: &AsyncClient, dbus: &Connection) {
+        let state = self.telemetry(dbus).await;
+        match serde_json::to_string(&state) {
+            Ok(payload) => {
+                client
+                    .publish(self.topic("state"), QoS::AtMostOnce, false, payload)
+                    .await
+                    .map_err(|e| warn!("MQTT: could not publish state: {e}"))
+                    .ok();
+            }
+            Err(e) => warn!("MQTT: could not serialise state: {e}"),
+        }
+    }
+
+    /// Home Assistant discovery topics and their config
+    fn discovery(&self) -> Vec<(String, Value)> {
+        let dmi = DMIID::new().unwrap_or_default();
+        let device = json!({
+            "identifiers": [format!("asusd_{}", self.id)],
+            "name": if dmi.product_name.is_empty() { self.id.clone() } else { dmi.product_name },
+            "manufacturer": "ASUS",
+            "model": dmi.board_name,
+            "sw_version": crate::VERSION,
+        });
+        let state = self.topic("state");
+        let availability = self.topic("availability");
+        let entity = |component: &str, key: &str, name: &str, extra: Value| {
+            let mut config = json!({
+                "name": name,
+                "unique_id": format!("asusd_{}_{key}", self.id),
+                "state_topic": state,
+                "availability_topic": availability,
+                "device": device,
+            });
+            if let (Some(config), Value::Object(extra)) = (config.as_object_mut(), extra) {
+                config.extend(extra);
+            }
+            (
+                format!(
+                    "{}/{component}/asusd_{}/{key}/config",
+                    self.config.discovery_prefix, self.id
+                ),
+                config,
+            )
+        };
+        let profiles: Vec<&str> = self
+            .platform
+            .get_platform_profile_choices()
+            .unwrap_or_default()
+            .iter()
+            .map(<&str>::from)
+            .collect();
+
+        vec![
+            entity(
+                "select",
+                "profile",
+                "Platform profile",
+                json!({
+                    "command_topic": self.topic("profile/set"),
+                    "options": profiles,
+                    "value_template": "{{ value_json.profile }}",
+                }),
+            ),
+            entity(
+                "switch",
+                "leds",
+                "Keyboard LEDs",
+                json!({
+                    "command_topic": self.topic("leds/set"),
+                    "value_template": "{{ 'ON' if value_json.leds else 'OFF' }}",
+                }),
+            ),
+            entity(
+                "binary_sensor",
+                "ac_power",
+                "AC power",
+                json!({
+                    "device_class": "plug",
+                    "value_template": "{{ 'ON' if value_json.ac_power else 'OFF' }}",
+                }),
+            ),
+            entity(
+                "sensor",
+                "battery",
+                "Battery",
+                json!({
+                    "device_class": "battery",
+                    "unit_of_measurement": "%",
+                    "value_template": "{{ value_json.battery }}",
+                }),
+            ),
+            entity(
+                "sensor",
+                "charge_limit",
+                "Charge limit",
+                json!({
+                    "unit_of_measurement": "%",
+                    "value_template": "{{ value_json.charge_limit }}",
+                }),
+            ),
+            entity(
+                "sensor",
+                "cpu_temp",
+                "CPU temperature",
+                json!({
+                    "device_class": "temperature",
+                    "unit_of_measurement": "°C",
+                    "value_template": "{{ value_json.cpu_temp }}",
+                }),
+            ),
+            entity(
+                "sensor",
+                "gpu_temp",
+                "GPU temperature",
+                json!({
+                    "device_class": "temperature",
+                    "unit_of_measurement": "°C",
+                    "value_template": "{{ value_json.gpu_temp }}",
+                }),
+            ),
+        ]
+    }
+
+    /// Announce the entities and subscribe to commands. Run on each
+    /// (re)connect since the broker may have lost them
+    async fn on_connect(&self, client: &AsyncClient) -> Result<(), rumqttc::ClientError> {
+        info!(
+            "MQTT: connected to {}:{}",
+            self.config.host, self.config.port
+        );
+        client
+            .publish(self.topic("availability"), QoS::AtLeastOnce, true, "online")
+            .await?;
+        for (topic, config) in self.discovery() {
+            client
+                .publish(topic, QoS::AtLeastOnce, true, config.to_string())
+                .await?;
+        }
+        client
+            .subscribe(self.topic("+/set"), QoS::AtLeastOnce)
+            .await
+    }
+
+    /// Commands go through asusd's own DBus interfaces so that they are
+    /// checked and saved the same as any other client's
+    async fn handle_command(
+        &self,
+        dbus: &Connection,
+        topic: &str,
+        payload: &str,
+    ) -> Result<(), RogError> {
+        if topic == self.topic("profile/set") {
+            let profile = PlatformProfile::from_str(payload)?;
+            Proxy::new(dbus, DBUS_NAME, ASUS_ZBUS_PATH, PLATFORM_IFACE)
+                .await?
+                .set_property("PlatformProfile", profile)
+                .await
+                .map_err(zbus::Error::from)?;
+        } else if topic == self.topic("leds/set") {
+            let brightness = match payload.to_ascii_uppercase().as_str() {
+                "ON" => self.last_brightness.load(Ordering::Relaxed),
+                "OFF" => LedBrightness::Off as u32,
+                _ => return Err(RogError::NotSupported),
+            };
+            for (path, _) in Self::aura_brightness(dbus).await? {
+                Proxy::new(dbus, DBUS_NAME, path, AURA_IFACE)
+                    .await?
+                    .set_property("Brightness", brightness)
+                    .await
+                    .map_err(zbus::Error::from)?;
+            }
+        } else {
+            debug!("MQTT: ignoring {topic}");
+        }
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), RogError> {
+        let mut options = MqttOptions::new(
+            format!("asusd-{}", self.id),
+            &self.config.host,
+            self.config.port,
+        );
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &self.config.username {
+            options.set_credentials(username, self.config.password.clone().unwrap_or_default());
+        }
+        if self.config.tls {
+            options.set_transport(match &self.config.ca_file {
+                Some(ca_file) => Transport::tls_with_config(TlsConfiguration::Simple {
+                    ca: fs::read(ca_file)?,
+                    alpn: None,
+                    client_auth: None,
+                }),
+                None => Transport::tls_with_default_config(),
+            });
+        }
+        options.set_last_will(LastWill::new(
+            self.topic("availability"),
+            "offline",
+            QoS::AtLeastOnce,
+            true,
+        ));
+        let (client, mut eventloop) = AsyncClient::new(options, 16);
+        let dbus = Connection::system().await?;
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.interval_secs.max(5)));
+
+        loop {
+            tokio::select! {
+                event = eventloop.poll() => match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        if let Err(e) = self.on_connect(&client).await {
+                            warn!("MQTT: could not set up topics: {e}");
+                        }
+                        self.publish_state(&client, &dbus).await;
+                    }
+                    Ok(Event::Incoming(Packet::Publish(msg))) => {
+                        let payload = String::from_utf8_lossy(&msg.payload);
+                        match self.handle_command(&dbus, &msg.topic, payload.trim()).await {
+                            Ok(()) => self.publish_state(&client, &dbus).await,
+                            Err(e) => warn!("MQTT: {} {payload}: {e}", msg.topic),
+                        }
+                    }
+                    Ok(_) => {}
+                    // The supervisor reconnects after a backoff
+                    Err(e) => return Err(RogError::DoTask(format!("MQTT connection: {e}"))),
+                },
+                _ = interval.tick() => self.publish_state(&client, &dbus).await,
+            }
+        }
+    }
+}
+
+/// Start the MQTT client if it is enabled in `/etc/asusd/mqtt.ron`
+pub fn start_mqtt(platform: RogPlatform, power: AsusPower, supervisor: &Supervisor) {
+    let config = MqttConfig::new().load();
+    if !config.enabled {
+        return;
+    }
+    let id = device_id();
+    supervisor.spawn("MQTT", move || {
+        let mqtt = Mqtt {
+            config: config.clone(),
+            platform: platform.clone(),
+            power: power.clone(),
+            id: id.clone(),
+            last_brightness: AtomicU32::new(LedBrightness::Med as u32),
+        };
+        mqtt.run()
+    });
+}
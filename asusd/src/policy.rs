@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use config_traits::ron;
+use log::{info, warn};
+use rog_platform::asus_armoury::FirmwareAttribute;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::RogError;
+use crate::CONFIG_PATH_BASE;
+
+/// Settings pinned by an administrator, such as on a company issued laptop.
+/// Every `*.ron` file in `/etc/asusd/policy.d` is read in name order, with
+/// later files overriding earlier ones. Requests which conflict with the
+/// policy fail with `org.freedesktop.DBus.Error.AccessDenied`.
+///
+/// ```ron
+/// (
+///     charge_limit: Some(80),
+///     attribute_max: { PptPl1Spl: 45, PptPl2Sppt: 55 },
+///     read_only: [FirmwareAttributes, FanCurves],
+/// )
+/// ```
+#[derive(Deserialize, Serialize, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct Policy {
+    /// The charge limit is fixed at this value, and one-shot full charges are
+    /// denied
+    pub charge_limit: Option<u8>,
+    /// Firmware attributes fixed at a value
+    pub attributes: HashMap<FirmwareAttribute, i32>,
+    /// The highest value a firmware attribute may be set to, such as PPT
+    /// limits
+    pub attribute_max: HashMap<FirmwareAttribute, i32>,
+    /// Groups of settings which can be read but not changed over DBus
+    pub read_only: HashSet<Lockdown>,
+}
+
+/// A group of settings which a policy can make read only
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lockdown {
+    /// Keyboard brightness, modes, and LED power
+    Lighting,
+    /// The platform profile and the profiles used on AC and battery
+    PlatformProfile,
+    FanCurves,
+    /// The charge limit and one-shot full charges
+    ChargeLimit,
+    /// PPT, TGP, curve optimizer, and other firmware attributes
+    FirmwareAttributes,
+}
+
+impl fmt::Display for Lockdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Lighting => "lighting",
+            Self::PlatformProfile => "the platform profile",
+            Self::FanCurves => "fan curves",
+            Self::ChargeLimit => "the charge limit",
+            Self::FirmwareAttributes => "firmware attributes",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Policy {
+    pub fn policy_dir() -> PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE).join("policy.d")
+    }
+
+    /// The policy in effect. This is read on every use so changes by an
+    /// administrator apply without restarting asusd.
+    pub fn load() -> Self {
+        Self::load_dir(&Self::policy_dir())
+    }
+
+    /// Read and merge every `*.ron` file in `dir`. Files which fail to parse
+    /// are skipped.
+    pub fn load_dir(dir: &Path) -> Self {
+        let mut policy = Policy::default();
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return policy;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "ron"))
+            .collect();
+        files.sort();
+        for file in files {
+            match std::fs::read_to_string(&file)
+                .map_err(RogError::from)
+                .and_then(|s| ron::from_str::<Policy>(&s).map_err(|e| RogError::ParseRon(e.code)))
+            {
+                Ok(p) => policy.merge(p),
+                Err(e) => warn!("Policy: could not read {file:?}: {e}"),
+            }
+        }
+        policy
+    }
+
+    fn merge(&mut self, other: Policy) {
+        if other.charge_limit.is_some() {
+            self.charge_limit = other.charge_limit;
+        }
+        self.attributes.extend(other.attributes);
+        self.attribute_max.extend(other.attribute_max);
+        self.read_only.extend(other.read_only);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Policy::default()
+    }
+
+    /// Deny changes to a group of settings made read only
+    pub fn check_writable(&self, group: Lockdown) -> Result<(), RogError> {
+        if self.read_only.contains(&group) {
+            return Err(RogError::PolicyDenied(format!(
+                "{group} is locked by the administrator"
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn check_charge_limit(&self, limit: u8) -> Result<(), RogError> {
+        match self.charge_limit {
+            Some(pinned) if pinned != limit => Err(RogError::PolicyDenied(format!(
+                "the charge limit is pinned to {pinned}%"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_attribute(&self, name: FirmwareAttribute, value: i32) -> Result<(), RogError> {
+        let attr = <&str>::from(name);
+        if let Some(pinned) = self.attributes.get(&name) {
+            if *pinned != value {
+                return Err(RogError::PolicyDenied(format!(
+                    "{attr} is pinned to {pinned}"
+                )));
+            }
+        }
+        if let Some(max) = self.attribute_max.get(&name) {
+            if value > *max {
+                return Err(RogError::PolicyDenied(format!(
+                    "{attr} may not be set above {max}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The value to use for an attribute in place of `value`: the pinned
+    /// value if there is one, lowered to the maximum
+    pub fn enforce_attribute(&self, name: FirmwareAttribute, value: i32) -> i32 {
+        let value = self.attributes.get(&name).copied().unwrap_or(value);
+        self.attribute_max
+            .get(&name)
+            .map_or(value, |max| value.min(*max))
+    }
+
+    /// Change any stored settings which conflict with the policy, so that
+    /// they are not applied when asusd starts or the config is edited by hand
+    pub fn apply_to_config(&self, config: &mut Config) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(limit) = self.charge_limit {
+            config.charge_control_end_threshold = limit;
+            config.base_charge_control_end_threshold = limit;
+            for battery_limit in config.battery_charge_limits.values_mut() {
+                *battery_limit = limit;
+            }
+        }
+        let tunings = config
+            .ac_profile_tunings
+            .values_mut()
+            .chain(config.dc_profile_tunings.values_mut())
+            .map(|t| &mut t.group);
+        for values in tunings.chain([&mut config.armoury_settings]) {
+            for (name, value) in values.iter_mut() {
+                *value = self.enforce_attribute(*name, *value);
+            }
+        }
+        for (name, pinned) in &self.attributes {
+            if !name.is_ppt() {
+                config.armoury_settings.insert(*name, *pinned);
+            }
+        }
+        info!("Policy: enforcing settings from {:?}", Self::policy_dir());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rog_platform::asus_armoury::FirmwareAttribute;
+    use rog_platform::platform::PlatformProfile;
+
+    use super::{Lockdown, Policy};
+    use crate::config::{Config, Tuning};
+    use crate::error::RogError;
+
+    fn policy() -> Policy {
+        Policy {
+            charge_limit: Some(80),
+            attributes: HashMap::from([(FirmwareAttribute::BootSound, 0)]),
+            attribute_max: HashMap::from([(FirmwareAttribute::PptPl1Spl, 45)]),
+            read_only: [Lockdown::FanCurves].into(),
+        }
+    }
+
+    #[test]
+    fn checks() {
+        let policy = policy();
+        assert!(policy.check_charge_limit(80).is_ok());
+        assert!(matches!(
+            policy.check_charge_limit(100),
+            Err(RogError::PolicyDenied(_))
+        ));
+        assert!(policy
+            .check_attribute(FirmwareAttribute::BootSound, 0)
+            .is_ok());
+        assert!(policy
+            .check_attribute(FirmwareAttribute::BootSound, 1)
+            .is_err());
+        assert!(policy
+            .check_attribute(FirmwareAttribute::PptPl1Spl, 45)
+            .is_ok());
+        assert!(policy
+            .check_attribute(FirmwareAttribute::PptPl1Spl, 46)
+            .is_err());
+        assert!(policy
+            .check_attribute(FirmwareAttribute::PptPl2Sppt, 90)
+            .is_ok());
+        assert!(Policy::default().check_charge_limit(100).is_ok());
+        assert!(matches!(
+            policy.check_writable(Lockdown::FanCurves),
+            Err(RogError::PolicyDenied(_))
+        ));
+        assert!(policy.check_writable(Lockdown::Lighting).is_ok());
+    }
+
+    #[test]
+    fn merge_and_apply() {
+        let mut merged = policy();
+        merged.merge(Policy {
+            charge_limit: Some(60),
+            read_only: [Lockdown::ChargeLimit].into(),
+            ..Default::default()
+        });
+        assert_eq!(merged.charge_limit, Some(60));
+        assert_eq!(merged.attribute_max.len(), 1);
+        assert_eq!(merged.read_only.len(), 2);
+
+        let mut config = Config {
+            charge_control_end_threshold: 100,
+            battery_charge_limits: HashMap::from([("BAT1".to_owned(), 90)]),
+            ..Default::default()
+        };
+        config
+            .ac_profile_tunings
+            .insert(PlatformProfile::Balanced, Tuning {
+                enabled: true,
+                group: HashMap::from([(FirmwareAttribute::PptPl1Spl, 80)]),
+            });
+        merged.apply_to_config(&mut config);
+        assert_eq!(config.charge_control_end_threshold, 60);
+        assert_eq!(config.battery_charge_limits["BAT1"], 60);
+        let group = &config.ac_profile_tunings[&PlatformProfile::Balanced].group;
+        assert_eq!(group[&FirmwareAttribute::PptPl1Spl], 45);
+        assert_eq!(
+            config.armoury_settings.get(&FirmwareAttribute::BootSound),
+            Some(&0)
+        );
+    }
+
+    #[test]
+    fn enforce_attribute() {
+        let mut policy = policy();
+        assert_eq!(
+            policy.enforce_attribute(FirmwareAttribute::PptPl1Spl, 80),
+            45
+        );
+        assert_eq!(
+            policy.enforce_attribute(FirmwareAttribute::PptPl1Spl, 30),
+            30
+        );
+        assert_eq!(
+            policy.enforce_attribute(FirmwareAttribute::PptPl2Sppt, 80),
+            80
+        );
+        policy.attributes.insert(FirmwareAttribute::PptPl2Sppt, 50);
+        assert_eq!(
+            policy.enforce_attribute(FirmwareAttribute::PptPl2Sppt, 80),
+            50
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Scenes: a keyboard effect, lightbar colour, and AniMe animation saved
+//! together under a name and applied as one. Every part of a scene is checked
+//! against the devices before anything is written, and if a write fails the
+//! parts already written are put back, so a scene is applied whole or not at
+//! all. Scenes are stored in `/etc/asusd/scenes.ron`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use futures_util::lock::Mutex;
+use log::{info, warn};
+use rog_anime::Animations;
+use rog_aura::{AuraEffect, AuraZone, Colour};
+use serde::{Deserialize, Serialize};
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::aura_anime::AniMe;
+use crate::aura_laptop::Aura;
+use crate::aura_manager::AsusDevice;
+use crate::aura_types::DeviceHandle;
+use crate::authorization::{authorize_interactive, Action};
+use crate::ctrl_capabilities::register_controller;
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+use crate::CONFIG_PATH_BASE;
+
+pub const SCENES_PATH: &str = "/xyz/ljones/scenes";
+
+/// The names of the parts a scene can have, as used over DBus
+pub const PART_KEYBOARD: &str = "keyboard";
+pub const PART_LIGHTBAR: &str = "lightbar";
+pub const PART_ANIME: &str = "anime";
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub name: String,
+    /// The keyboard mode and colours, for the whole keyboard
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyboard: Option<AuraEffect>,
+    /// A static colour for the lightbar zones of zoned keyboards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lightbar: Option<Colour>,
+    /// The AniMe builtin animations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anime: Option<Animations>,
+}
+
+impl Scene {
+    pub fn parts(&self) -> Vec<String> {
+        let mut parts = Vec::new();
+        if self.keyboard.is_some() {
+            parts.push(PART_KEYBOARD.to_owned());
+        }
+        if self.lightbar.is_some() {
+            parts.push(PART_LIGHTBAR.to_owned());
+        }
+        if self.anime.is_some() {
+            parts.push(PART_ANIME.to_owned());
+        }
+        parts
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct ScenesConfig {
+    pub scenes: Vec<Scene>,
+    /// The scene applied last, empty if none
+    pub active: String,
+}
+
+impl StdConfig for ScenesConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "scenes.ron".to_owned()
+    }
+
+    fn config_dir() -> PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for ScenesConfig {}
+
+#[derive(Clone)]
+pub struct Scenes {
+    devices: Arc<Mutex<Vec<AsusDevice>>>,
+    config: Arc<Mutex<ScenesConfig>>,
+}
+
+impl Scenes {
+    pub fn new(devices: Arc<Mutex<Vec<AsusDevice>>>) -> Self {
+        Self {
+            devices,
+            config: Arc::new(Mutex::new(ScenesConfig::new().load())),
+        }
+    }
+
+    pub async fn start(self, connection: &Connection) -> Result<(), RogError> {
+        connection.object_server().at(SCENES_PATH, self).await?;
+        register_controller("xyz.ljones.Scenes", SCENES_PATH);
+        Ok(())
+    }
+
+    /// The laptop keyboard and AniMe display, if there are any
+    async fn targets(&self) -> (Option<Aura>, Option<AniMe>) {
+        let mut aura = None;
+        let mut anime = None;
+        for dev in self.devices.lock().await.iter() {
+            match &dev.device {
+                DeviceHandle::Aura(a) if aura.is_none() => aura = Some(a.clone()),
+                DeviceHandle::AniMe(a) if anime.is_none() => anime = Some(a.clone()),
+                _ => {}
+            }
+        }
+        (aura, anime)
+    }
+
+    /// Write every part of `scene`, or none of it
+    async fn apply_scene(&self, scene: &Scene) -> Result<(), RogError> {
+        let (aura, anime) = self.targets().await;
+        let wants_aura = scene.keyboard.is_some() || scene.lightbar.is_some();
+        let aura = match aura {
+            Some(aura) if wants_aura => Some(aura),
+            None if wants_aura => return Err(RogError::NoAuraKeyboard),
+            _ => None,
+        };
+        let anime = match (anime, scene.anime) {
+            (Some(anime), Some(animations)) => Some((anime, animations)),
+            (None, Some(_)) => {
+                return Err(RogError::MissingFunction("No AniMe display".to_owned()));
+            }
+            _ => None,
+        };
+
+        // The keyboard config is held until the end so nothing else can write
+        // the keyboard between checking the scene and putting it back
+        let mut keyboard = match &aura {
+            Some(aura) => Some((aura, aura.config.lock().await)),
+            None => None,
+        };
+        let mut previous = None;
+        if let Some((aura, config)) = keyboard.as_mut() {
+            if let Some(effect) = &scene.keyboard {
+                if !config.support_data.basic_modes.contains(&effect.mode) {
+                    return Err(RogError::AuraEffectNotSupported);
+                }
+            }
+            if scene.lightbar.is_some() && config.lightbar_zones().is_empty() {
+                return Err(RogError::MissingFunction(
+                    "This keyboard has no separate lightbar".to_owned(),
+                ));
+            }
+
+            previous = Some((**config).clone());
+            if let Some(effect) = &scene.keyboard {
+                config.set_builtin(AuraEffect {
+                    zone: AuraZone::None,
+                    ..effect.clone()
+                });
+            }
+            if let Some(colour) = scene.lightbar {
+                config.set_lightbar(colour);
+            }
+            if let Err(e) = aura.write_current_config_mode(config).await {
+                if let Some(previous) = previous {
+                    **config = previous;
+                    aura.write_current_config_mode(config).await.ok();
+                }
+                return Err(e);
+            }
+        }
+
+        if let Some((anime, animations)) = &anime {
+            let previous_anims = anime.builtin_animations().await;
+            if let Err(e) = anime.set_builtin_animations(*animations).await {
+                warn!("Scene {}: {e}, putting the keyboard back", scene.name);
+                anime.set_builtin_animations(previous_anims).await.ok();
+                if let (Some((aura, config)), Some(previous)) = (keyboard.as_mut(), previous) {
+                    **config = previous;
+                    aura.write_current_config_mode(config).await.ok();
+                }
+                return Err(e);
+            }
+        }
+
+        if let Some((_, config)) = keyboard.as_mut() {
+            config.write();
+        }
+        Ok(())
+    }
+
+    /// A scene from the current state of the devices, with only the parts
+    /// asked for
+    async fn capture(
+        &self,
+        name: String,
+        keyboard: bool,
+        lightbar: bool,
+        anime: bool,
+    ) -> Result<Scene, RogError> {
+        let (aura, anime_dev) = self.targets().await;
+        let mut scene = Scene {
+            name,
+            keyboard: None,
+            lightbar: None,
+            anime: None,
+        };
+        if keyboard || lightbar {
+            let aura = aura.ok_or(RogError::NoAuraKeyboard)?;
+            let config = aura.config.lock().await;
+            if keyboard {
+                scene.keyboard = config.builtins.get(&config.current_mode).cloned();
+            }
+            if lightbar {
+                scene.lightbar = Some(config.lightbar_colour().ok_or_else(|| {
+                    RogError::MissingFunction("This keyboard has no separate lightbar".to_owned())
+                })?);
+            }
+        }
+        if anime {
+            let anime = anime_dev
+                .ok_or_else(|| RogError::MissingFunction("No AniMe display".to_owned()))?;
+            scene.anime = Some(anime.builtin_animations().await);
+        }
+        Ok(scene)
+    }
+}
+
+#[interface(name = "xyz.ljones.Scenes")]
+impl Scenes {
+    /// The names of the saved scenes
+    #[zbus(property)]
+    async fn scenes(&self) -> Vec<String> {
+        self.config
+            .lock()
+            .await
+            .scenes
+            .iter()
+            .map(|s| s.name.clone())
+            .collect()
+    }
+
+    /// The scene applied last, empty if none
+    #[zbus(property)]
+    async fn active(&self) -> String {
+        self.config.lock().await.active.clone()
+    }
+
+    /// The parts which scenes can have on this laptop, of `keyboard`,
+    /// `lightbar`, and `anime`
+    #[zbus(property)]
+    async fn supported_parts(&self) -> Vec<String> {
+        let (aura, anime) = self.targets().await;
+        let mut parts = Vec::new();
+        if let Some(aura) = aura {
+            parts.push(PART_KEYBOARD.to_owned());
+            if !aura.config.lock().await.lightbar_zones().is_empty() {
+                parts.push(PART_LIGHTBAR.to_owned());
+            }
+        }
+        if anime.is_some() {
+            parts.push(PART_ANIME.to_owned());
+        }
+        parts
+    }
+
+    /// The parts saved in the scene `name`
+    async fn scene_parts(&self, name: String) -> Result<Vec<String>, FdoErr> {
+        let config = self.config.lock().await;
+        let scene = config
+            .scenes
+            .iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| FdoErr::InvalidArgs(format!("No scene named {name}")))?;
+        Ok(scene.parts())
+    }
+
+    /// Apply every part of the scene `name`. If any part can't be applied
+    /// nothing is changed.
+    async fn apply(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: String,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        let scene = self
+            .config
+            .lock()
+            .await
+            .scenes
+            .iter()
+            .find(|s| s.name == name)
+            .cloned()
+            .ok_or_else(|| FdoErr::InvalidArgs(format!("No scene named {name}")))?;
+        info!("Applying scene {name}");
+        self.apply_scene(&scene).await?;
+        {
+            let mut config = self.config.lock().await;
+            config.active = name;
+            config.write();
+        }
+        self.active_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
+    /// Save the current keyboard effect, lightbar colour, and AniMe
+    /// animations as the scene `name`, replacing any scene of that name. Only
+    /// the parts given as true are saved, and applying the scene leaves the
+    /// others alone.
+    async fn capture_scene(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: String,
+        keyboard: bool,
+        lightbar: bool,
+        anime: bool,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        if name.trim().is_empty() {
+            return Err(FdoErr::InvalidArgs("The scene needs a name".to_owned()));
+        }
+        if !(keyboard || lightbar || anime) {
+            return Err(FdoErr::InvalidArgs(
+                "The scene needs at least one part".to_owned(),
+            ));
+        }
+        let scene = self.capture(name, keyboard, lightbar, anime).await?;
+        {
+            let mut config = self.config.lock().await;
+            config.scenes.retain(|s| s.name != scene.name);
+            config.scenes.push(scene);
+            config.write();
+        }
+        self.scenes_changed(&ctxt).await.ok();
+        Ok(())
+    }
+
+    /// Delete the scene `name`
+    async fn remove_scene(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+        name: String,
+        #[zbus(signal_emitter)] ctxt: SignalEmitter<'_>,
+    ) -> Result<(), FdoErr> {
+        authorize_interactive(conn, &header, Action::Lighting).await?;
+        Policy::load().check_writable(Lockdown::Lighting)?;
+        {
+            let mut config = self.config.lock().await;
+            let len = config.scenes.len();
+            config.scenes.retain(|s| s.name != name);
+            if config.scenes.len() == len {
+                return Err(FdoErr::InvalidArgs(format!("No scene named {name}")));
+            }
+            if config.active == name {
+                config.active.clear();
+            }
+            config.write();
+        }
+        self.scenes_changed(&ctxt).await.ok();
+        self.active_changed(&ctxt).await.ok();
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/scenes.rs"));
+
+#[cfg(test)]
+mod tests {
+    use rog_aura::{AuraEffect, Colour};
+
+    use super::{Scene, PART_KEYBOARD, PART_LIGHTBAR};
+
+    #[test]
+    fn scene_parts() {
+        let scene = Scene {
+            name: "Night".to_owned(),
+            keyboard: Some(AuraEffect::default()),
+            lightbar: Some(Colour { r: 0, g: 0, b: 255 }),
+            anime: None,
+        };
+        assert_eq!(scene.parts(), vec![PART_KEYBOARD, PART_LIGHTBAR]);
+
+        let text = ron::to_string(&scene).unwrap();
+        assert!(!text.contains("anime"));
+        assert_eq!(ron::from_str::<Scene>(&text).unwrap(), scene);
+    }
+}
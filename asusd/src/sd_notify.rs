@@ -0,0 +1,162 @@
+//! Startup and liveness reporting to systemd through `$NOTIFY_SOCKET`, for
+//! `Type=notify` and `WatchdogSec=` in asusd.service. Outside of systemd
+//! nothing is sent.
+
+use std::env;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::lock::Mutex;
+use log::{debug, info, warn};
+use tokio::time::{sleep, timeout};
+use zbus::fdo::PeerProxy;
+use zbus::Connection;
+
+use crate::config::Config;
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+
+/// Send `state`, such as `READY=1`, to the socket systemd gave. Returns
+/// `false` if asusd was not started by systemd with a notify socket
+pub fn notify(state: &str) -> std::io::Result<bool> {
+    match env::var("NOTIFY_SOCKET") {
+        Ok(socket) => notify_to(&socket, state).map(|_| true),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Send `state` to `socket`, a path or an abstract name starting with `@`
+pub fn notify_to(socket: &str, state: &str) -> std::io::Result<()> {
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+/// The interval systemd expects `WATCHDOG=1` in, from `WATCHDOG_USEC`. `None`
+/// if the watchdog is off, or is for another process than `pid`
+pub fn watchdog_interval(
+    usec: Option<&str>,
+    watchdog_pid: Option<&str>,
+    pid: u32,
+) -> Option<Duration> {
+    if let Some(watchdog_pid) = watchdog_pid {
+        if watchdog_pid.trim().parse::<u32>().ok()? != pid {
+            return None;
+        }
+    }
+    match usec?.trim().parse::<u64>().ok()? {
+        0 => None,
+        usec => Some(Duration::from_micros(usec)),
+    }
+}
+
+/// Tell systemd that every controller is started and the interfaces are up
+pub fn notify_ready() {
+    match notify("READY=1\nSTATUS=Running") {
+        Ok(true) => debug!("Notified systemd that asusd is ready"),
+        Ok(false) => {}
+        Err(e) => warn!("Could not notify systemd: {e}"),
+    }
+}
+
+/// Check that the DBus object server answers and the config isn't held
+/// forever, which are what a hung asusd stops doing
+async fn alive(server: &Connection, config: &Arc<Mutex<Config>>) -> Result<(), String> {
+    if let Some(name) = server.unique_name() {
+        PeerProxy::builder(server)
+            .destination(name.as_str())
+            .and_then(|b| b.path("/"))
+            .map_err(|e| e.to_string())?
+            .build()
+            .await
+            .map_err(|e| e.to_string())?
+            .ping()
+            .await
+            .map_err(|e| format!("the DBus interfaces did not answer: {e}"))?;
+    }
+    drop(config.lock().await);
+    Ok(())
+}
+
+async fn keep_alive(
+    server: Connection,
+    config: Arc<Mutex<Config>>,
+    interval: Duration,
+) -> Result<(), RogError> {
+    loop {
+        sleep(interval / 2).await;
+        match timeout(interval / 2, alive(&server, &config)).await {
+            Ok(Ok(())) => {
+                notify("WATCHDOG=1")?;
+            }
+            Ok(Err(e)) => warn!("Watchdog: not sending a keep-alive, {e}"),
+            Err(_) => warn!("Watchdog: not sending a keep-alive, asusd did not answer"),
+        }
+    }
+}
+
+/// Send `WATCHDOG=1` at half the interval systemd asked for, but only while
+/// asusd is still answering. A hung asusd stops sending and is restarted by
+/// systemd.
+pub fn start_watchdog(server: Connection, config: Arc<Mutex<Config>>, supervisor: &Supervisor) {
+    let Some(interval) = watchdog_interval(
+        env::var("WATCHDOG_USEC").ok().as_deref(),
+        env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    ) else {
+        return;
+    };
+    info!("Sending the systemd watchdog every {:?}", interval / 2);
+    supervisor.spawn("Watchdog", move || {
+        keep_alive(server.clone(), config.clone(), interval)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+    use std::time::Duration;
+
+    use super::{notify_to, watchdog_interval};
+
+    #[test]
+    fn parse_watchdog() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 10),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("10"), 10),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("11"), 10), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 10), None);
+        assert_eq!(watchdog_interval(Some("soon"), None, 10), None);
+        assert_eq!(watchdog_interval(None, None, 10), None);
+    }
+
+    #[test]
+    fn notify_socket() {
+        let path = std::env::temp_dir().join(format!("asusd-notify-{}", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let socket = UnixDatagram::bind(&path).unwrap();
+        notify_to(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0u8; 16];
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        std::fs::remove_file(&path).ok();
+
+        let name = format!("@asusd-notify-{}", std::process::id());
+        let addr = SocketAddr::from_abstract_name(&name.as_bytes()[1..]).unwrap();
+        let socket = UnixDatagram::bind_addr(&addr).unwrap();
+        notify_to(&name, "WATCHDOG=1").unwrap();
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"WATCHDOG=1");
+    }
+}
@@ -0,0 +1,48 @@
+//! The user whose session is in the foreground, so that the clients run by
+//! each logged in user can tell which of them is being used.
+
+use log::debug;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{proxy, Connection};
+
+/// The active user when no session is in the foreground
+pub const NO_ACTIVE_USER: u32 = u32::MAX;
+
+#[proxy(
+    interface = "org.freedesktop.login1.Seat",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/seat/seat0"
+)]
+pub trait Seat {
+    /// ActiveSession property
+    #[zbus(property)]
+    fn active_session(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1"
+)]
+trait Session {
+    /// User property
+    #[zbus(property)]
+    fn user(&self) -> zbus::Result<(u32, OwnedObjectPath)>;
+}
+
+/// The uid of the user with the foreground session on `seat0`, or
+/// [`NO_ACTIVE_USER`]
+pub async fn active_user(conn: &Connection) -> u32 {
+    let user = async {
+        let seat = SeatProxy::new(conn).await?;
+        let (_, path) = seat.active_session().await?;
+        let session = SessionProxy::builder(conn).path(path)?.build().await?;
+        session.user().await
+    };
+    match user.await {
+        Ok((uid, _)) => uid,
+        Err(e) => {
+            debug!("No active session on seat0: {e}");
+            NO_ACTIVE_USER
+        }
+    }
+}
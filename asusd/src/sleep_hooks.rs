@@ -0,0 +1,418 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_lite::stream::StreamExt;
+use futures_util::lock::Mutex;
+use log::{debug, info, warn};
+use logind_zbus::manager::ManagerProxy;
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedFd;
+use zbus::{proxy, Connection};
+
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+
+pub(crate) const INHIBITOR_WHO: &str = "asusd";
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub(crate) trait Login1Manager {
+    /// Inhibit method
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    /// InhibitDelayMaxUSec property
+    #[zbus(property, name = "InhibitDelayMaxUSec")]
+    fn inhibit_delay_max_usec(&self) -> zbus::Result<u64>;
+}
+
+/// Where the system is in a suspend cycle as seen through logind's
+/// `PrepareForSleep` signal
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SleepState {
+    #[default]
+    Awake,
+    /// Pre-sleep hooks are running
+    Suspending,
+    Asleep,
+    /// Post-resume hooks are running
+    Resuming,
+}
+
+/// What to do with a `PrepareForSleep` signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// Run the hooks, moving to this state
+    Run(SleepState),
+    /// Run the hooks once those running now have finished, such as a resume
+    /// which arrives while the pre-sleep hooks run
+    Queue,
+    /// A repeat of the last signal
+    Ignore,
+}
+
+impl SleepState {
+    /// What to do for a `PrepareForSleep(start)` signal
+    pub fn on_prepare_for_sleep(self, start: bool) -> Transition {
+        match (self, start) {
+            (SleepState::Awake, true) => Transition::Run(SleepState::Suspending),
+            (SleepState::Asleep, false) => Transition::Run(SleepState::Resuming),
+            (SleepState::Suspending, false) | (SleepState::Resuming, true) => Transition::Queue,
+            _ => Transition::Ignore,
+        }
+    }
+}
+
+/// Pre-sleep hooks run in ascending order and post-resume hooks in descending
+/// order, so whatever is put to sleep last is woken first. Hooks with the same
+/// order run in the order they were registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HookOrder {
+    /// Saving state to disk
+    Stats,
+    /// LEDs and displays: Aura, AniMe, Slash
+    Devices,
+    /// Charge limit and AC/battery policies
+    Platform,
+    /// CPU settings which are lost in suspend
+    Cpu,
+    /// Releasing the delay inhibitor which holds off suspend, once every
+    /// other hook has run
+    Inhibitor,
+}
+
+type HookFn = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+#[derive(Clone)]
+struct Hook {
+    owner: String,
+    order: HookOrder,
+    run: HookFn,
+}
+
+#[derive(Default)]
+struct Hooks {
+    state: SleepState,
+    /// The signal which arrived while hooks were running
+    queued: Option<bool>,
+    pre_sleep: Vec<Hook>,
+    post_resume: Vec<Hook>,
+    logind: Option<Login1ManagerProxy<'static>>,
+    /// Held while awake so that suspend waits for the pre-sleep hooks
+    inhibitor: Option<OwnedFd>,
+}
+
+/// The one place suspend and resume are handled. Controllers register
+/// callbacks to run before sleep and after resume, which are then run one
+/// at a time in a fixed order.
+#[derive(Clone, Default)]
+pub struct SleepHooks {
+    inner: Arc<Mutex<Hooks>>,
+}
+
+impl SleepHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn hook<F, Fut>(owner: &str, order: HookOrder, hook: F) -> Hook
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Hook {
+            owner: owner.to_owned(),
+            order,
+            run: Arc::new(move || Box::pin(hook())),
+        }
+    }
+
+    /// Register a callback to run before the system sleeps. `owner` is used
+    /// for logging and for `remove()`.
+    pub async fn on_pre_sleep<F, Fut>(&self, owner: &str, order: HookOrder, hook: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let hook = Self::hook(owner, order, hook);
+        self.inner.lock().await.pre_sleep.push(hook);
+    }
+
+    /// Register a callback to run after the system resumes. `owner` is used
+    /// for logging and for `remove()`.
+    pub async fn on_post_resume<F, Fut>(&self, owner: &str, order: HookOrder, hook: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let hook = Self::hook(owner, order, hook);
+        self.inner.lock().await.post_resume.push(hook);
+    }
+
+    /// Remove all hooks registered by `owner`, such as a device which was
+    /// unplugged
+    pub async fn remove(&self, owner: &str) {
+        let mut hooks = self.inner.lock().await;
+        hooks.pre_sleep.retain(|h| h.owner != owner);
+        hooks.post_resume.retain(|h| h.owner != owner);
+    }
+
+    pub async fn state(&self) -> SleepState {
+        self.inner.lock().await.state
+    }
+
+    /// Take a delay inhibitor from logind, which is released once the
+    /// pre-sleep hooks have run
+    async fn inhibit(&self) {
+        let Some(logind) = self.inner.lock().await.logind.clone() else {
+            return;
+        };
+        match logind
+            .inhibit("sleep", INHIBITOR_WHO, "Running suspend hooks", "delay")
+            .await
+        {
+            Ok(fd) => self.inner.lock().await.inhibitor = Some(fd),
+            Err(e) => warn!("SleepHooks: could not delay suspend for the hooks: {e}"),
+        }
+    }
+
+    /// Step the state machine for a `PrepareForSleep(start)` signal and run
+    /// the hooks for the transition, then any queued while they ran
+    pub async fn prepare_for_sleep(&self, start: bool) {
+        if let Some(hooks) = self.begin(start).await {
+            self.run(start, hooks).await;
+        }
+    }
+
+    /// Step the state machine for a `PrepareForSleep(start)` signal. Returns
+    /// the hooks to run, or `None` if the signal was ignored or queued
+    async fn begin(&self, start: bool) -> Option<Vec<Hook>> {
+        let mut inner = self.inner.lock().await;
+        let transition = match inner.queued {
+            // Suspending again before the queued resume ran cancels it
+            Some(queued) if queued != start => {
+                debug!("SleepHooks: PrepareForSleep({start}) cancels the queued one");
+                inner.queued = None;
+                return None;
+            }
+            Some(_) => Transition::Ignore,
+            None => inner.state.on_prepare_for_sleep(start),
+        };
+        match transition {
+            Transition::Run(next) => inner.state = next,
+            Transition::Queue => {
+                debug!(
+                    "SleepHooks: PrepareForSleep({start}) while {:?}, running it next",
+                    inner.state
+                );
+                inner.queued = Some(start);
+                return None;
+            }
+            Transition::Ignore => {
+                debug!(
+                    "SleepHooks: ignoring PrepareForSleep({start}) while {:?}",
+                    inner.state
+                );
+                return None;
+            }
+        }
+        let mut hooks = if start {
+            inner.pre_sleep.clone()
+        } else {
+            inner.post_resume.clone()
+        };
+        // Stable sort, so registration order is kept within an order
+        if start {
+            hooks.sort_by(|a, b| a.order.cmp(&b.order));
+        } else {
+            hooks.sort_by(|a, b| b.order.cmp(&a.order));
+        }
+        Some(hooks)
+    }
+
+    /// Run `hooks` for the transition `begin()` started, then those of the
+    /// signal queued meanwhile
+    async fn run(&self, mut start: bool, mut hooks: Vec<Hook>) {
+        loop {
+            // The lock is not held while running so hooks may register or
+            // remove other hooks
+            for hook in hooks {
+                debug!("SleepHooks: {:?} hook for {}", hook.order, hook.owner);
+                (hook.run)().await;
+            }
+
+            if start {
+                // Dropping the fd lets the suspend go ahead
+                self.inner.lock().await.inhibitor.take();
+            } else {
+                self.inhibit().await;
+            }
+
+            let queued = {
+                let mut inner = self.inner.lock().await;
+                inner.state = if start {
+                    SleepState::Asleep
+                } else {
+                    SleepState::Awake
+                };
+                info!("SleepHooks: now {:?}", inner.state);
+                inner.queued.take()
+            };
+            let Some(queued) = queued else {
+                return;
+            };
+            let Some(next) = self.begin(queued).await else {
+                return;
+            };
+            (start, hooks) = (queued, next);
+        }
+    }
+
+    /// Run the hooks on logind's `PrepareForSleep` signal, holding a delay
+    /// inhibitor so that suspend waits for them
+    pub fn start(&self, supervisor: &Supervisor) {
+        let hooks = self.clone();
+        supervisor.spawn("SleepHooks: PrepareForSleep", move || {
+            let hooks = hooks.clone();
+            async move {
+                let connection = Connection::system().await?;
+                let manager = ManagerProxy::builder(&connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await?;
+                let mut notif = manager.receive_prepare_for_sleep().await?;
+                hooks.inner.lock().await.logind = Some(Login1ManagerProxy::new(&connection).await?);
+                if hooks.state().await == SleepState::Awake {
+                    hooks.inhibit().await;
+                }
+                while let Some(event) = notif.next().await {
+                    let Ok(start) = event.args().map(|args| args.start) else {
+                        continue;
+                    };
+                    // The hooks run in their own task, so that a signal
+                    // arriving meanwhile is queued rather than left unread
+                    if let Some(run) = hooks.begin(start).await {
+                        let hooks = hooks.clone();
+                        tokio::spawn(async move { hooks.run(start, run).await });
+                    }
+                }
+                Err(RogError::Io(std::io::Error::other(
+                    "PrepareForSleep stream ended",
+                )))
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_lite::future::block_on;
+    use futures_util::lock::Mutex;
+
+    use super::{HookOrder, SleepHooks, SleepState, Transition};
+
+    #[test]
+    fn state_transitions() {
+        assert_eq!(
+            SleepState::Awake.on_prepare_for_sleep(true),
+            Transition::Run(SleepState::Suspending)
+        );
+        assert_eq!(
+            SleepState::Asleep.on_prepare_for_sleep(false),
+            Transition::Run(SleepState::Resuming)
+        );
+        assert_eq!(
+            SleepState::Awake.on_prepare_for_sleep(false),
+            Transition::Ignore
+        );
+        assert_eq!(
+            SleepState::Asleep.on_prepare_for_sleep(true),
+            Transition::Ignore
+        );
+        assert_eq!(
+            SleepState::Suspending.on_prepare_for_sleep(false),
+            Transition::Queue
+        );
+        assert_eq!(
+            SleepState::Resuming.on_prepare_for_sleep(true),
+            Transition::Queue
+        );
+    }
+
+    #[test]
+    fn hook_order() {
+        block_on(async {
+            let hooks = SleepHooks::new();
+            let log = Arc::new(Mutex::new(Vec::new()));
+            for (name, order) in [
+                ("cpu", HookOrder::Cpu),
+                ("aura", HookOrder::Devices),
+                ("stats", HookOrder::Stats),
+                ("anime", HookOrder::Devices),
+            ] {
+                let l = log.clone();
+                hooks
+                    .on_pre_sleep(name, order, move || {
+                        let l = l.clone();
+                        async move { l.lock().await.push(format!("sleep {name}")) }
+                    })
+                    .await;
+                let l = log.clone();
+                hooks
+                    .on_post_resume(name, order, move || {
+                        let l = l.clone();
+                        async move { l.lock().await.push(format!("resume {name}")) }
+                    })
+                    .await;
+            }
+            hooks.remove("anime").await;
+
+            hooks.prepare_for_sleep(true).await;
+            assert_eq!(hooks.state().await, SleepState::Asleep);
+            // A repeated signal must not run the hooks again
+            hooks.prepare_for_sleep(true).await;
+            hooks.prepare_for_sleep(false).await;
+            assert_eq!(hooks.state().await, SleepState::Awake);
+
+            assert_eq!(*log.lock().await, [
+                "sleep stats", "sleep aura", "sleep cpu", "resume cpu", "resume aura",
+                "resume stats",
+            ]);
+        });
+    }
+
+    #[test]
+    fn resume_while_suspending() {
+        block_on(async {
+            let hooks = SleepHooks::new();
+            let log = Arc::new(Mutex::new(Vec::new()));
+            let (h, l) = (hooks.clone(), log.clone());
+            hooks
+                .on_pre_sleep("aura", HookOrder::Devices, move || {
+                    let (h, l) = (h.clone(), l.clone());
+                    async move {
+                        // logind resumes before the pre-sleep hooks finish
+                        h.prepare_for_sleep(false).await;
+                        l.lock().await.push("sleep");
+                    }
+                })
+                .await;
+            let l = log.clone();
+            hooks
+                .on_post_resume("aura", HookOrder::Devices, move || {
+                    let l = l.clone();
+                    async move { l.lock().await.push("resume") }
+                })
+                .await;
+
+            hooks.prepare_for_sleep(true).await;
+            assert_eq!(hooks.state().await, SleepState::Awake);
+            assert_eq!(*log.lock().await, ["sleep", "resume"]);
+        });
+    }
+}
@@ -0,0 +1,593 @@
+//! Snapshots of every setting asusd controls, taken as one RON document so
+//! they can be kept in a file and restored later, such as before trying new
+//! tuning or after reinstalling. A snapshot has the platform profile, charge
+//! limit, firmware attributes other than the GPU mode, fan curves, and laptop
+//! keyboard lighting. Parts
+//! the laptop doesn't have are left out, and restoring skips parts which are
+//! missing from either the snapshot or the laptop.
+//!
+//! The same interface resets settings to their defaults, for the firmware
+//! attributes the `default_value` the firmware gives, in place of deleting
+//! the files in `/etc/asusd`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use config_traits::StdConfig;
+use futures_util::lock::Mutex;
+use log::{info, warn};
+use rog_aura::keyboard::LaptopAuraPower;
+use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+use rog_platform::asus_armoury::{AttrValue, FirmwareAttribute, FirmwareAttributes};
+use rog_platform::platform::{PlatformProfile, RogPlatform};
+use rog_platform::power::AsusPower;
+use rog_profiles::FanCurveProfiles;
+use serde::{Deserialize, Serialize};
+use zbus::fdo::Error as FdoErr;
+use zbus::message::Header;
+use zbus::{interface, Connection};
+
+use crate::asus_armoury::{dbus_path_for_attr, AsusArmouryAttribute};
+use crate::aura_laptop::Aura;
+use crate::aura_manager::AsusDevice;
+use crate::aura_types::DeviceHandle;
+use crate::authorization::{authorize_interactive, Action};
+use crate::config::Config;
+use crate::ctrl_capabilities::register_controller;
+use crate::ctrl_fancurves::{CtrlFanCurveZbus, FAN_CURVE_ZBUS_PATH};
+use crate::ctrl_platform::{check_ppt_order, sort_ppt_writes};
+use crate::error::RogError;
+use crate::policy::{Lockdown, Policy};
+
+pub const SNAPSHOT_PATH: &str = "/xyz/ljones/snapshot";
+
+/// The groups of settings `ResetToDefaults` can reset, as used over DBus
+pub const PART_AURA: &str = "aura";
+pub const PART_PROFILES: &str = "profiles";
+pub const PART_ATTRIBUTES: &str = "attributes";
+pub const PART_CHARGE_LIMIT: &str = "charge_limit";
+pub const RESET_PARTS: [&str; 4] = [
+    PART_AURA, PART_PROFILES, PART_ATTRIBUTES, PART_CHARGE_LIMIT,
+];
+
+/// The laptop keyboard lighting
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct AuraSnapshot {
+    pub brightness: LedBrightness,
+    pub current_mode: AuraModeNum,
+    /// The settings of every builtin mode, not only the current one
+    pub builtins: BTreeMap<AuraModeNum, AuraEffect>,
+    pub enabled: LaptopAuraPower,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, PartialEq)]
+pub struct Snapshot {
+    /// The asusd version which took the snapshot
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform_profile: Option<PlatformProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charge_limit: Option<u8>,
+    /// PPT values are those stored for the platform profile and power source
+    /// in use when the snapshot was taken
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<(FirmwareAttribute, i32)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fan_curves: Option<FanCurveProfiles>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aura: Option<AuraSnapshot>,
+}
+
+impl Snapshot {
+    pub fn from_ron(text: &str) -> Result<Self, RogError> {
+        ron::from_str(text).map_err(|e| RogError::ParseRon(e.code))
+    }
+
+    pub fn to_ron(&self) -> Result<String, RogError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::new().depth_limit(4),
+        )?)
+    }
+}
+
+#[derive(Clone)]
+pub struct CtrlSnapshot {
+    platform: RogPlatform,
+    power: AsusPower,
+    attributes: FirmwareAttributes,
+    config: Arc<Mutex<Config>>,
+    devices: Arc<Mutex<Vec<AsusDevice>>>,
+}
+
+impl CtrlSnapshot {
+    pub fn new(
+        platform: RogPlatform,
+        power: AsusPower,
+        attributes: FirmwareAttributes,
+        config: Arc<Mutex<Config>>,
+        devices: Arc<Mutex<Vec<AsusDevice>>>,
+    ) -> Self {
+        Self {
+            platform,
+            power,
+            attributes,
+            config,
+            devices,
+        }
+    }
+
+    pub async fn start(self, connection: &Connection) -> Result<(), RogError> {
+        connection.object_server().at(SNAPSHOT_PATH, self).await?;
+        register_controller("xyz.ljones.Snapshot", SNAPSHOT_PATH);
+        Ok(())
+    }
+
+    /// The laptop keyboard, if there is one
+    async fn aura(&self) -> Option<Aura> {
+        self.devices
+            .lock()
+            .await
+            .iter()
+            .find_map(|dev| match &dev.device {
+                DeviceHandle::Aura(aura) => Some(aura.clone()),
+                _ => None,
+            })
+    }
+
+    async fn fan_curves(&self, conn: &Connection) -> Option<CtrlFanCurveZbus> {
+        let iface = conn
+            .object_server()
+            .interface::<_, CtrlFanCurveZbus>(FAN_CURVE_ZBUS_PATH)
+            .await
+            .ok()?;
+        let ctrl = iface.get().await.clone();
+        Some(ctrl)
+    }
+
+    /// The current value of each integer attribute
+    fn attribute_values(&self) -> Vec<(FirmwareAttribute, i32)> {
+        self.attributes
+            .attributes()
+            .iter()
+            .filter_map(|attr| match attr.current_value() {
+                Ok(AttrValue::Integer(value)) => Some((attr.name().into(), value)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    async fn take(&self, conn: &Connection) -> Snapshot {
+        let mut snapshot = Snapshot {
+            version: crate::VERSION.to_owned(),
+            ..Default::default()
+        };
+        if self.platform.has_platform_profile() {
+            snapshot.platform_profile = self.platform.get_platform_profile().map(|p| p.into()).ok();
+        }
+        if self.power.has_charge_control_end_threshold() {
+            snapshot.charge_limit = self.power.get_charge_control_end_threshold().ok();
+        }
+
+        for attr in self.attributes.attributes() {
+            if FirmwareAttribute::from(attr.name()).is_gpu_mode() {
+                continue;
+            }
+            let Ok(iface) = conn
+                .object_server()
+                .interface::<_, AsusArmouryAttribute>(dbus_path_for_attr(attr.name()))
+                .await
+            else {
+                continue;
+            };
+            match iface.get().await.value().await {
+                Ok(value) => snapshot.attributes.push((attr.name().into(), value)),
+                Err(e) => warn!("Snapshot: couldn't read {}: {e}", attr.name()),
+            }
+        }
+
+        if let Some(ctrl) = self.fan_curves(conn).await {
+            snapshot.fan_curves = Some(ctrl.profiles().await);
+        }
+
+        if let Some(aura) = self.aura().await {
+            let config = aura.config.lock().await;
+            snapshot.aura = Some(AuraSnapshot {
+                brightness: config.brightness,
+                current_mode: config.current_mode,
+                builtins: config.builtins.clone(),
+                enabled: config.enabled.clone(),
+            });
+        }
+        snapshot
+    }
+
+    /// Check that the sender may change every part of `snapshot` before
+    /// anything is written
+    async fn check_restore(
+        &self,
+        snapshot: &Snapshot,
+        conn: &Connection,
+        header: &Header<'_>,
+    ) -> Result<(), FdoErr> {
+        let policy = Policy::load();
+        if snapshot.platform_profile.is_some()
+            || snapshot.charge_limit.is_some()
+            || snapshot.fan_curves.is_some()
+        {
+            authorize_interactive(conn, header, Action::Power).await?;
+        }
+        if snapshot.platform_profile.is_some() {
+            policy.check_writable(Lockdown::PlatformProfile)?;
+        }
+        if let Some(limit) = snapshot.charge_limit {
+            policy.check_writable(Lockdown::ChargeLimit)?;
+            if !(20..=100).contains(&limit) {
+                return Err(RogError::ChargeLimit(limit))?;
+            }
+            policy.check_charge_limit(limit)?;
+        }
+        if snapshot.fan_curves.is_some() {
+            policy.check_writable(Lockdown::FanCurves)?;
+        }
+        let writes = attribute_writes(&snapshot.attributes, &self.attribute_values())
+            .map_err(FdoErr::InvalidArgs)?;
+        if !writes.is_empty() {
+            authorize_interactive(conn, header, Action::FirmwareAttributes).await?;
+            policy.check_writable(Lockdown::FirmwareAttributes)?;
+            for (name, value) in &writes {
+                policy.check_attribute(*name, *value)?;
+            }
+        }
+        if snapshot.aura.is_some() {
+            authorize_interactive(conn, header, Action::Lighting).await?;
+            policy.check_writable(Lockdown::Lighting)?;
+        }
+        Ok(())
+    }
+
+    async fn restore(&self, snapshot: Snapshot, conn: &Connection) -> Result<(), FdoErr> {
+        // The profile goes first as the fan curves and PPT values written
+        // after it depend on it
+        if let Some(profile) = snapshot.platform_profile {
+            if self.platform.has_platform_profile() {
+                self.platform.set_platform_profile(profile.into())?;
+            }
+        }
+
+        if let Some(limit) = snapshot.charge_limit {
+            if self.power.has_charge_control_end_threshold() {
+                self.power.set_charge_control_end_threshold(limit)?;
+                let mut config = self.config.lock().await;
+                config.charge_control_end_threshold = limit;
+                config.base_charge_control_end_threshold = limit;
+                config.write();
+            }
+        }
+
+        if let Some(profiles) = snapshot.fan_curves {
+            match self.fan_curves(conn).await {
+                Some(ctrl) => ctrl.restore_profiles(profiles).await?,
+                None => info!("Snapshot: no fan curves on this laptop, skipping"),
+            }
+        }
+
+        // Ordered against the values after the profile change
+        let writes = attribute_writes(&snapshot.attributes, &self.attribute_values())
+            .map_err(FdoErr::InvalidArgs)?;
+        for (name, value) in writes {
+            let path = dbus_path_for_attr(name.into());
+            match conn
+                .object_server()
+                .interface::<_, AsusArmouryAttribute>(path)
+                .await
+            {
+                Ok(iface) => iface.get_mut().await.write_value(value).await?,
+                Err(_) => info!(
+                    "Snapshot: no {} on this laptop, skipping",
+                    <&str>::from(name)
+                ),
+            }
+        }
+
+        if let Some(state) = snapshot.aura {
+            match self.aura().await {
+                Some(aura) => {
+                    let mut config = aura.config.lock().await;
+                    config.current_mode = state.current_mode;
+                    for (mode, effect) in state.builtins {
+                        if config.builtins.contains_key(&mode) {
+                            config.builtins.insert(mode, effect);
+                        }
+                    }
+                    config.enabled = state.enabled;
+                    config.brightness = state.brightness;
+                    aura.write_current_config_mode(&mut config).await?;
+                    aura.set_power_states(&config).await?;
+                    if aura.backlight.is_some() {
+                        aura.set_brightness(state.brightness.into()).await?;
+                    }
+                    config.write();
+                }
+                None => info!("Snapshot: no laptop keyboard, skipping lighting"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the sender may reset every part in `parts` before anything
+    /// is reset
+    async fn check_reset(
+        &self,
+        parts: &[String],
+        conn: &Connection,
+        header: &Header<'_>,
+    ) -> Result<(), FdoErr> {
+        let policy = Policy::load();
+        for part in parts {
+            match part.as_str() {
+                PART_AURA => {
+                    authorize_interactive(conn, header, Action::Lighting).await?;
+                    policy.check_writable(Lockdown::Lighting)?;
+                }
+                PART_PROFILES => {
+                    authorize_interactive(conn, header, Action::Power).await?;
+                    policy.check_writable(Lockdown::PlatformProfile)?;
+                    policy.check_writable(Lockdown::FanCurves)?;
+                }
+                PART_ATTRIBUTES => {
+                    authorize_interactive(conn, header, Action::FirmwareAttributes).await?;
+                    policy.check_writable(Lockdown::FirmwareAttributes)?;
+                }
+                PART_CHARGE_LIMIT => {
+                    authorize_interactive(conn, header, Action::Power).await?;
+                    policy.check_writable(Lockdown::ChargeLimit)?;
+                    policy.check_charge_limit(100)?;
+                }
+                _ => {
+                    return Err(FdoErr::InvalidArgs(format!(
+                        "Unknown part {part}, expected one of {}",
+                        RESET_PARTS.join(", ")
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn reset_aura(&self) -> Result<(), RogError> {
+        let Some(aura) = self.aura().await else {
+            info!("Reset: no laptop keyboard, skipping lighting");
+            return Ok(());
+        };
+        let mut config = aura.config.lock().await;
+        config.reset_to_defaults();
+        aura.write_current_config_mode(&mut config).await?;
+        aura.set_power_states(&config).await?;
+        if aura.backlight.is_some() {
+            aura.set_brightness(config.brightness.into()).await?;
+        }
+        config.write();
+        Ok(())
+    }
+
+    /// The platform profile settings in `asusd.ron`, the active profile, and
+    /// the fan curves of every profile
+    async fn reset_profiles(&self, conn: &Connection) -> Result<(), RogError> {
+        {
+            let defaults = Config::default();
+            let mut config = self.config.lock().await;
+            config.platform_profile_linked_epp = defaults.platform_profile_linked_epp;
+            config.platform_profile_on_battery = defaults.platform_profile_on_battery;
+            config.change_platform_profile_on_battery = defaults.change_platform_profile_on_battery;
+            config.platform_profile_on_ac = defaults.platform_profile_on_ac;
+            config.change_platform_profile_on_ac = defaults.change_platform_profile_on_ac;
+            config.profile_quiet_epp = defaults.profile_quiet_epp;
+            config.profile_balanced_epp = defaults.profile_balanced_epp;
+            config.profile_custom_epp = defaults.profile_custom_epp;
+            config.profile_performance_epp = defaults.profile_performance_epp;
+            config.write();
+        }
+        if let Some(ctrl) = self.fan_curves(conn).await {
+            ctrl.reset_to_defaults().await?;
+        }
+        if self.platform.has_platform_profile() {
+            self.platform
+                .set_platform_profile(PlatformProfile::default().into())?;
+        }
+        Ok(())
+    }
+
+    /// Clear the stored PPT tunings and attribute settings, then write the
+    /// firmware default of each attribute which has one. The GPU MUX, dGPU,
+    /// and eGPU attributes are left alone as changing them needs a reboot and
+    /// can leave the laptop without a display. Values pinned by the fleet
+    /// policy are kept.
+    async fn reset_attributes(&self) -> Result<(), RogError> {
+        let policy = Policy::load();
+        {
+            let mut config = self.config.lock().await;
+            config.ac_profile_tunings.clear();
+            config.dc_profile_tunings.clear();
+            config.armoury_settings.clear();
+            policy.apply_to_config(&mut config);
+            config.write();
+        }
+        for attr in self.attributes.attributes() {
+            let name: FirmwareAttribute = attr.name().into();
+            if name.is_gpu_mode() {
+                continue;
+            }
+            let AttrValue::Integer(default) = attr.default_value() else {
+                continue;
+            };
+            if policy.check_attribute(name, *default).is_err() {
+                info!("Reset: {} is pinned by the fleet policy", attr.name());
+                continue;
+            }
+            attr.restore_default()?;
+        }
+        Ok(())
+    }
+
+    async fn reset_charge_limit(&self) -> Result<(), RogError> {
+        if !self.power.has_charge_control_end_threshold() {
+            return Ok(());
+        }
+        self.power.set_charge_control_end_threshold(100)?;
+        let mut config = self.config.lock().await;
+        config.charge_control_end_threshold = 100;
+        config.base_charge_control_end_threshold = 100;
+        config.write();
+        Ok(())
+    }
+}
+
+#[interface(name = "xyz.ljones.Snapshot")]
+impl CtrlSnapshot {
+    /// Every setting asusd controls as a RON document, to be given to
+    /// `RestoreSnapshot` later
+    async fn snapshot(&self, #[zbus(connection)] conn: &Connection) -> Result<String, FdoErr> {
+        Ok(self.take(conn).await.to_ron()?)
+    }
+
+    /// Restore the settings of a document from `Snapshot`. Every part is
+    /// checked against polkit and the fleet policy before anything is
+    /// written.
+    async fn restore_snapshot(
+        &self,
+        snapshot: String,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        let snapshot = Snapshot::from_ron(&snapshot)
+            .map_err(|e| FdoErr::InvalidArgs(format!("Not a snapshot: {e}")))?;
+        self.check_restore(&snapshot, conn, &header).await?;
+        info!("Restoring a snapshot taken by asusd {}", snapshot.version);
+        self.restore(snapshot, conn).await
+    }
+
+    /// The parts `ResetToDefaults` accepts
+    #[zbus(property)]
+    async fn reset_parts(&self) -> Vec<String> {
+        RESET_PARTS.iter().map(|p| (*p).to_owned()).collect()
+    }
+
+    /// Reset each of `parts` to the defaults. `aura` is the laptop keyboard
+    /// lighting, `profiles` the platform profile settings and fan curves,
+    /// `attributes` the firmware attributes, and `charge_limit` the charge
+    /// limit.
+    async fn reset_to_defaults(
+        &self,
+        parts: Vec<String>,
+        #[zbus(connection)] conn: &Connection,
+        #[zbus(header)] header: Header<'_>,
+    ) -> Result<(), FdoErr> {
+        if parts.is_empty() {
+            return Err(FdoErr::InvalidArgs("Nothing to reset".to_owned()));
+        }
+        self.check_reset(&parts, conn, &header).await?;
+        for part in &parts {
+            info!("Resetting {part} to defaults");
+            match part.as_str() {
+                PART_AURA => self.reset_aura().await?,
+                PART_PROFILES => self.reset_profiles(conn).await?,
+                PART_ATTRIBUTES => self.reset_attributes().await?,
+                PART_CHARGE_LIMIT => self.reset_charge_limit().await?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+/// The attributes of a snapshot to write, in the order to write them over
+/// the `current` values. The GPU mode attributes are left out, as switching
+/// them needs the checks of `SetGpuMode`, and the PPT limits must be in order
+/// once all are written.
+fn attribute_writes(
+    attributes: &[(FirmwareAttribute, i32)],
+    current: &[(FirmwareAttribute, i32)],
+) -> Result<Vec<(FirmwareAttribute, i32)>, String> {
+    let mut writes: Vec<(FirmwareAttribute, i32, i32)> = attributes
+        .iter()
+        .filter(|(name, _)| !name.is_gpu_mode())
+        .map(|(name, value)| {
+            let previous = current
+                .iter()
+                .find(|(c, _)| c == name)
+                .map_or(*value, |(_, v)| *v);
+            (*name, previous, *value)
+        })
+        .collect();
+    let mut merged = current.to_vec();
+    merged.extend(writes.iter().map(|(name, _, value)| (*name, *value)));
+    check_ppt_order(&merged)?;
+    sort_ppt_writes(&mut writes, |write| *write);
+    Ok(writes
+        .into_iter()
+        .map(|(name, _, value)| (name, value))
+        .collect())
+}
+
+include!(concat!(env!("OUT_DIR"), "/api/snapshot.rs"));
+
+#[cfg(test)]
+mod tests {
+    use rog_aura::keyboard::LaptopAuraPower;
+    use rog_aura::{AuraEffect, AuraModeNum, LedBrightness};
+    use rog_platform::asus_armoury::FirmwareAttribute;
+    use rog_platform::platform::PlatformProfile;
+
+    use super::{attribute_writes, AuraSnapshot, Snapshot};
+
+    #[test]
+    fn snapshot_round_trip() {
+        let snapshot = Snapshot {
+            version: "6.1.0".to_owned(),
+            platform_profile: Some(PlatformProfile::Quiet),
+            charge_limit: Some(80),
+            attributes: vec![(FirmwareAttribute::PptPl1Spl, 45)],
+            fan_curves: None,
+            aura: Some(AuraSnapshot {
+                brightness: LedBrightness::Med,
+                current_mode: AuraModeNum::Static,
+                builtins: [(AuraModeNum::Static, AuraEffect::default())].into(),
+                enabled: LaptopAuraPower::default(),
+            }),
+        };
+        let text = snapshot.to_ron().unwrap();
+        assert!(!text.contains("fan_curves"));
+        assert!(Snapshot::from_ron(&text).unwrap() == snapshot);
+    }
+
+    #[test]
+    fn attribute_writes_checked_and_ordered() {
+        use FirmwareAttribute::*;
+        let current = [
+            (PptPl1Spl, 80),
+            (PptPl2Sppt, 90),
+            (PptFppt, 100),
+        ];
+        // Lowered from the lowest tier up, and the GPU mode left alone
+        let snapshot = [
+            (GpuMuxMode, 0),
+            (PptFppt, 60),
+            (PptPl2Sppt, 50),
+            (PptPl1Spl, 40),
+            (NvTempTarget, 80),
+        ];
+        assert_eq!(attribute_writes(&snapshot, &current).unwrap(), vec![
+            (PptPl1Spl, 40),
+            (PptPl2Sppt, 50),
+            (PptFppt, 60),
+            (NvTempTarget, 80),
+        ]);
+        // Out of order with the fast limit the snapshot doesn't have
+        assert!(attribute_writes(&[(PptPl2Sppt, 120)], &current).is_err());
+        assert!(attribute_writes(&[(DgpuDisable, 1)], &current)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,1006 @@
+//! JSON-RPC 2.0 over a unix socket, for systems and containers without a
+//! system DBus. Built with the `socket-api` feature and configured in
+//! `/etc/asusd/socket_api.ron`. Every request goes through asusd's own DBus
+//! interfaces, so the API and its checks are the same as over DBus. With no
+//! system bus asusd serves its interfaces on an in-process connection which
+//! only the socket talks to.
+//!
+//! Each line on the socket is one request, answered with one line:
+//! - `Call`, `{"path", "interface", "method", "args": [..]}`. The result is
+//!   `null` with no outputs, the output with one, and an array with more
+//! - `Get`, `{"path", "interface", "property"}`
+//! - `GetAll`, `{"path", "interface"}`, an object of the properties
+//! - `Set`, `{"path", "interface", "property", "value"}`
+//! - `Introspect`, `{"path"}`, the DBus introspection XML
+//!
+//! Values are converted by their DBus type: structures are arrays, dicts are
+//! objects, and a variant is `{"signature": "u", "value": 1}` or a plain
+//! value whose type is guessed. File descriptors can't be sent.
+//!
+//! The interfaces see every request as coming from asusd itself, so `Call`
+//! and `Set` from a client other than root are checked with polkit here
+//! first, by the client's process. Without a system bus there is no polkit,
+//! and only root may use them. Settings tied to the caller's own bus
+//! connection, such as `StreamFrames`, can't be changed over the socket.
+//!
+//! A request is at most [`MAX_REQUEST_LEN`] bytes, and at most
+//! [`MAX_CLIENTS`] clients are served at once.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use config_traits::{StdConfig, StdConfigLoad};
+use log::{debug, info, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Semaphore;
+use zbus::message::Message;
+use zbus::zvariant::{
+    Array, Dict, DynamicType, ObjectPath, OwnedValue, Signature, Structure, StructureBuilder, Value,
+};
+use zbus::{Connection, DBusError, Guid};
+use zbus_xml::{ArgDirection, Node};
+
+use crate::authorization::{authorize_process, Action};
+use crate::error::RogError;
+use crate::supervisor::Supervisor;
+use crate::{CONFIG_PATH_BASE, DBUS_NAME};
+
+/// The code of errors returned by asusd's interfaces, with the DBus error
+/// name in `data.name`
+pub const DBUS_ERROR: i64 = -32000;
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+const FAILED: &str = "org.freedesktop.DBus.Error.Failed";
+
+/// The longest request line read, in bytes
+pub const MAX_REQUEST_LEN: usize = 64 * 1024;
+/// Clients served at once. Further clients wait in the listen backlog.
+pub const MAX_CLIENTS: usize = 8;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SocketApiConfig {
+    pub enabled: bool,
+    pub path: PathBuf,
+    /// Members of this group may use the socket as well as root. Their
+    /// changes are checked with polkit as over DBus
+    pub group: Option<String>,
+}
+
+impl Default for SocketApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: PathBuf::from("/run/asusd/asusd.sock"),
+            group: None,
+        }
+    }
+}
+
+impl StdConfig for SocketApiConfig {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn file_name(&self) -> String {
+        "socket_api.ron".to_owned()
+    }
+
+    fn config_dir() -> PathBuf {
+        PathBuf::from(CONFIG_PATH_BASE)
+    }
+}
+
+impl StdConfigLoad for SocketApiConfig {}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    /// Requests without an id are not answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Json>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Json,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Response {
+    pub jsonrpc: String,
+    pub id: Json,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Json>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Json>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// An error named like a DBus error, such as
+    /// `org.freedesktop.DBus.Error.InvalidArgs`
+    pub fn dbus(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            code: DBUS_ERROR,
+            message: message.into(),
+            data: Some(json!({ "name": name })),
+        }
+    }
+
+    /// The DBus error name, `org.freedesktop.DBus.Error.Failed` for errors
+    /// which aren't from asusd's interfaces
+    pub fn dbus_name(&self) -> &str {
+        self.data
+            .as_ref()
+            .and_then(|data| data["name"].as_str())
+            .unwrap_or(FAILED)
+    }
+}
+
+impl From<zbus::Error> for RpcError {
+    fn from(err: zbus::Error) -> Self {
+        match err {
+            zbus::Error::MethodError(name, description, _) => {
+                Self::dbus(name.as_str(), description.unwrap_or_default())
+            }
+            e => Self::dbus(FAILED, e.to_string()),
+        }
+    }
+}
+
+impl From<zbus::fdo::Error> for RpcError {
+    fn from(err: zbus::fdo::Error) -> Self {
+        Self::dbus(err.name().as_str(), err.description().unwrap_or_default())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CallParams {
+    pub path: String,
+    pub interface: String,
+    pub method: String,
+    #[serde(default)]
+    pub args: Vec<Json>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct PropertyParams {
+    pub path: String,
+    pub interface: String,
+    /// Left out for `GetAll`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub property: String,
+    /// Only for `Set`
+    #[serde(default, skip_serializing_if = "Json::is_null")]
+    pub value: Json,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct IntrospectParams {
+    pub path: String,
+}
+
+/// A JSON integer as `T`, if it fits
+fn int<T>(json: &Json) -> Option<T>
+where
+    T: TryFrom<u64> + TryFrom<i64>,
+{
+    match json.as_u64() {
+        Some(v) => T::try_from(v).ok(),
+        None => T::try_from(json.as_i64()?).ok(),
+    }
+}
+
+fn parse_signature(s: &str) -> Result<Signature, String> {
+    Signature::try_from(s).map_err(|e| format!("{s} is not a DBus type: {e}"))
+}
+
+/// A variant's value, `{"signature": "u", "value": 1}` or a plain value
+/// taken as a bool, `x`, `d`, `s`, `av`, or `a{sv}`
+fn variant_value(json: &Json) -> Result<Value<'static>, String> {
+    if let Some(object) = json.as_object() {
+        if let (Some(Json::String(signature)), Some(value), 2) =
+            (object.get("signature"), object.get("value"), object.len())
+        {
+            return to_value(value, &parse_signature(signature)?);
+        }
+    }
+    let signature = match json {
+        Json::Bool(_) => "b",
+        Json::Number(n) if n.is_i64() || n.is_u64() => "x",
+        Json::Number(_) => "d",
+        Json::String(_) => "s",
+        Json::Array(_) => "av",
+        Json::Object(_) => "a{sv}",
+        Json::Null => return Err("a variant can't be null".to_owned()),
+    };
+    to_value(json, &parse_signature(signature)?)
+}
+
+/// Convert JSON to a DBus value of type `signature`
+pub fn to_value(json: &Json, signature: &Signature) -> Result<Value<'static>, String> {
+    let mismatch = || format!("{json} is not a {signature}");
+    Ok(match signature {
+        Signature::Bool => Value::from(json.as_bool().ok_or_else(mismatch)?),
+        Signature::U8 => Value::from(int::<u8>(json).ok_or_else(mismatch)?),
+        Signature::I16 => Value::from(int::<i16>(json).ok_or_else(mismatch)?),
+        Signature::U16 => Value::from(int::<u16>(json).ok_or_else(mismatch)?),
+        Signature::I32 => Value::from(int::<i32>(json).ok_or_else(mismatch)?),
+        Signature::U32 => Value::from(int::<u32>(json).ok_or_else(mismatch)?),
+        Signature::I64 => Value::from(int::<i64>(json).ok_or_else(mismatch)?),
+        Signature::U64 => Value::from(int::<u64>(json).ok_or_else(mismatch)?),
+        Signature::F64 => Value::from(json.as_f64().ok_or_else(mismatch)?),
+        Signature::Str => Value::from(json.as_str().ok_or_else(mismatch)?.to_owned()),
+        Signature::ObjectPath => {
+            let path = json.as_str().ok_or_else(mismatch)?;
+            Value::ObjectPath(ObjectPath::try_from(path.to_owned()).map_err(|e| e.to_string())?)
+        }
+        Signature::Signature => {
+            Value::Signature(parse_signature(json.as_str().ok_or_else(mismatch)?)?)
+        }
+        Signature::Variant => Value::Value(Box::new(variant_value(json)?)),
+        Signature::Array(child) => {
+            let mut array = Array::new(child);
+            for item in json.as_array().ok_or_else(mismatch)? {
+                array
+                    .append(to_value(item, child)?)
+                    .map_err(|e| e.to_string())?;
+            }
+            Value::Array(array)
+        }
+        Signature::Dict { key, value } => {
+            let mut dict = Dict::new(key, value);
+            for (k, v) in json.as_object().ok_or_else(mismatch)? {
+                // Keys are always strings in JSON, numbers are written out
+                let k = match **key {
+                    Signature::Str | Signature::ObjectPath | Signature::Signature => {
+                        Json::String(k.clone())
+                    }
+                    _ => k.parse().map_err(|_| format!("{k} is not a {}", **key))?,
+                };
+                dict.append(to_value(&k, key)?, to_value(v, value)?)
+                    .map_err(|e| e.to_string())?;
+            }
+            Value::Dict(dict)
+        }
+        Signature::Structure(fields) => {
+            let items = json.as_array().ok_or_else(mismatch)?;
+            if items.len() != fields.iter().count() {
+                return Err(mismatch());
+            }
+            let mut builder = StructureBuilder::new();
+            for (item, field) in items.iter().zip(fields.iter()) {
+                builder = builder.append_field(to_value(item, field)?);
+            }
+            Value::Structure(builder.build().map_err(|e| e.to_string())?)
+        }
+        _ => return Err(format!("{signature} values can't be sent over the socket")),
+    })
+}
+
+/// Convert a DBus value to JSON. File descriptors are `null`
+pub fn to_json(value: &Value<'_>) -> Json {
+    match value {
+        Value::U8(v) => json!(v),
+        Value::Bool(v) => json!(v),
+        Value::I16(v) => json!(v),
+        Value::U16(v) => json!(v),
+        Value::I32(v) => json!(v),
+        Value::U32(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::U64(v) => json!(v),
+        Value::F64(v) => json!(v),
+        Value::Str(v) => json!(v.as_str()),
+        Value::Signature(v) => json!(v.to_string()),
+        Value::ObjectPath(v) => json!(v.as_str()),
+        Value::Value(v) => json!({
+            "signature": v.value_signature().to_string(),
+            "value": to_json(v),
+        }),
+        Value::Array(v) => Json::Array(v.iter().map(to_json).collect()),
+        Value::Dict(v) => Json::Object(
+            v.iter()
+                .map(|(k, v)| {
+                    let k = match to_json(k) {
+                        Json::String(k) => k,
+                        k => k.to_string(),
+                    };
+                    (k, to_json(v))
+                })
+                .collect(),
+        ),
+        Value::Structure(v) => Json::Array(v.fields().iter().map(to_json).collect()),
+        _ => Json::Null,
+    }
+}
+
+fn find_interface<'a>(
+    node: &'a Node<'a>,
+    interface: &str,
+) -> Result<&'a zbus_xml::Interface<'a>, RpcError> {
+    node.interfaces()
+        .iter()
+        .find(|iface| iface.name() == interface)
+        .ok_or_else(|| {
+            RpcError::dbus(
+                "org.freedesktop.DBus.Error.UnknownInterface",
+                format!("There is no {interface}"),
+            )
+        })
+}
+
+fn parse_xml(xml: &str) -> Result<Node<'_>, RpcError> {
+    Node::from_reader(xml.as_bytes())
+        .map_err(|e| RpcError::dbus(FAILED, format!("Bad introspection data: {e}")))
+}
+
+/// The input or output types of `method` on `interface`, from the
+/// introspection `xml` of its object
+pub fn method_signatures(
+    xml: &str,
+    interface: &str,
+    method: &str,
+    direction: ArgDirection,
+) -> Result<Vec<Signature>, RpcError> {
+    let node = parse_xml(xml)?;
+    let method = find_interface(&node, interface)?
+        .methods()
+        .iter()
+        .find(|m| m.name() == method)
+        .ok_or_else(|| {
+            RpcError::dbus(
+                "org.freedesktop.DBus.Error.UnknownMethod",
+                format!("{interface} has no method {method}"),
+            )
+        })?;
+    Ok(method
+        .args()
+        .iter()
+        .filter(|arg| {
+            (arg.direction() == Some(ArgDirection::Out)) == (direction == ArgDirection::Out)
+        })
+        .map(|arg| arg.ty().clone())
+        .collect())
+}
+
+/// The types of the properties of `interface`, from the introspection `xml`
+/// of its object
+pub fn property_signatures(
+    xml: &str,
+    interface: &str,
+) -> Result<HashMap<String, Signature>, RpcError> {
+    let node = parse_xml(xml)?;
+    Ok(find_interface(&node, interface)?
+        .properties()
+        .iter()
+        .map(|p| (p.name().to_string(), p.ty().clone()))
+        .collect())
+}
+
+/// The type of `property` on `interface`
+pub fn property_signature(
+    xml: &str,
+    interface: &str,
+    property: &str,
+) -> Result<Signature, RpcError> {
+    property_signatures(xml, interface)?
+        .remove(property)
+        .ok_or_else(|| {
+            RpcError::dbus(
+                "org.freedesktop.DBus.Error.UnknownProperty",
+                format!("{interface} has no property {property}"),
+            )
+        })
+}
+
+fn params<T: DeserializeOwned>(params: Json) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn invalid_args(e: String) -> RpcError {
+    RpcError::dbus("org.freedesktop.DBus.Error.InvalidArgs", e)
+}
+
+/// The kinds of change `interface` can make, for polkit. `None` for an
+/// interface whose changes aren't known, which only root may call
+pub fn interface_actions(interface: &str) -> Option<&'static [Action]> {
+    Some(match interface {
+        "xyz.ljones.Aura"
+        | "xyz.ljones.AuraJson"
+        | "xyz.ljones.AuraSync"
+        | "xyz.ljones.Anime"
+        | "xyz.ljones.Slash"
+        | "xyz.ljones.ScsiAura"
+        | "xyz.ljones.Scenes"
+        | "xyz.ljones.Backlight" => &[Action::Lighting],
+        "xyz.ljones.FanCurves" | "xyz.ljones.UsageStats" => &[Action::Power],
+        "xyz.ljones.Platform" => &[
+            Action::Power,
+            Action::FirmwareAttributes,
+        ],
+        "xyz.ljones.Privacy" => &[
+            Action::Power,
+            Action::Lighting,
+        ],
+        "xyz.ljones.AsusArmoury"
+        | "xyz.ljones.DgpuTgp"
+        | "xyz.ljones.CurveOptimizer"
+        | "xyz.ljones.GpuThermalPolicy"
+        | "xyz.ljones.BootWatchdog" => &[Action::FirmwareAttributes],
+        "xyz.ljones.XgMobile" => &[
+            Action::FirmwareAttributes,
+            Action::Lighting,
+        ],
+        "xyz.ljones.Snapshot" => &[
+            Action::Power,
+            Action::FirmwareAttributes,
+            Action::Lighting,
+        ],
+        "xyz.ljones.Health"
+        | "xyz.ljones.Capabilities"
+        | "xyz.ljones.Input"
+        | "org.freedesktop.DBus.Introspectable"
+        | "org.freedesktop.DBus.Peer"
+        | "org.freedesktop.DBus.ObjectManager" => &[],
+        _ => return None,
+    })
+}
+
+/// Properties tied to the bus connection which sets them. Over the socket
+/// that is asusd's own, which the caller can't stand in for.
+const CALLER_BOUND: [(&str, &str); 2] = [
+    ("xyz.ljones.Aura", "StreamFrames"),
+    ("xyz.ljones.Anime", "StreamFrames"),
+];
+
+fn refuse_caller_bound(interface: &str, property: &str) -> Result<(), RpcError> {
+    if CALLER_BOUND.contains(&(interface, property)) {
+        return Err(RpcError::dbus(
+            "org.freedesktop.DBus.Error.AccessDenied",
+            format!("{interface}.{property} can only be set over DBus"),
+        ));
+    }
+    Ok(())
+}
+
+/// The start time of a process, field 22 of `/proc/<pid>/stat`
+fn stat_start_time(stat: &str) -> Option<u64> {
+    // The name before it is in brackets, and may itself hold spaces
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// The client on the other end of a socket connection
+#[derive(Debug, Clone, Copy)]
+struct Client {
+    uid: u32,
+    /// The pid and start time of the client's process
+    process: Option<(u32, u64)>,
+}
+
+impl Client {
+    fn of(stream: &UnixStream) -> std::io::Result<Self> {
+        let cred = stream.peer_cred()?;
+        let process = cred
+            .pid()
+            .and_then(|pid| u32::try_from(pid).ok())
+            .and_then(|pid| {
+                let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+                Some((pid, stat_start_time(&stat)?))
+            });
+        Ok(Self {
+            uid: cred.uid(),
+            process,
+        })
+    }
+}
+
+/// Answers requests with calls to asusd's interfaces
+struct Rpc {
+    conn: Connection,
+    /// `None` on the in-process connection, which has no bus names
+    destination: Option<&'static str>,
+}
+
+impl Rpc {
+    async fn call<B>(
+        &self,
+        path: &str,
+        interface: &str,
+        method: &str,
+        body: &B,
+    ) -> Result<Message, RpcError>
+    where
+        B: Serialize + DynamicType,
+    {
+        Ok(self
+            .conn
+            .call_method(self.destination, path, Some(interface), method, body)
+            .await?)
+    }
+
+    async fn introspect(&self, path: &str) -> Result<String, RpcError> {
+        let reply = self
+            .call(
+                path,
+                "org.freedesktop.DBus.Introspectable",
+                "Introspect",
+                &(),
+            )
+            .await?;
+        Ok(reply.body().deserialize::<String>()?)
+    }
+
+    async fn call_method(&self, call: CallParams) -> Result<Json, RpcError> {
+        let xml = self.introspect(&call.path).await?;
+        let inputs = method_signatures(&xml, &call.interface, &call.method, ArgDirection::In)?;
+        if inputs.len() != call.args.len() {
+            return Err(invalid_args(format!(
+                "{} takes {} arguments, not {}",
+                call.method,
+                inputs.len(),
+                call.args.len()
+            )));
+        }
+        let reply = if inputs.is_empty() {
+            self.call(&call.path, &call.interface, &call.method, &())
+                .await?
+        } else {
+            let mut args = StructureBuilder::new();
+            for (arg, signature) in call.args.iter().zip(&inputs) {
+                args = args.append_field(to_value(arg, signature).map_err(invalid_args)?);
+            }
+            let args = args.build().map_err(|e| invalid_args(e.to_string()))?;
+            self.call(&call.path, &call.interface, &call.method, &args)
+                .await?
+        };
+
+        let body = reply.body();
+        if matches!(body.signature(), Signature::Unit) {
+            return Ok(Json::Null);
+        }
+        let outputs = body.deserialize::<Structure<'_>>()?;
+        Ok(match outputs.fields() {
+            [output] => to_json(output),
+            outputs => Json::Array(outputs.iter().map(to_json).collect()),
+        })
+    }
+
+    async fn get(&self, get: PropertyParams) -> Result<Json, RpcError> {
+        let reply = self
+            .call(
+                &get.path,
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                &(get.interface.as_str(), get.property.as_str()),
+            )
+            .await?;
+        Ok(to_json(&reply.body().deserialize::<OwnedValue>()?))
+    }
+
+    async fn get_all(&self, get: PropertyParams) -> Result<Json, RpcError> {
+        let reply = self
+            .call(
+                &get.path,
+                "org.freedesktop.DBus.Properties",
+                "GetAll",
+                &(get.interface.as_str(),),
+            )
+            .await?;
+        let properties = reply.body().deserialize::<HashMap<String, OwnedValue>>()?;
+        Ok(Json::Object(
+            properties
+                .iter()
+                .map(|(name, value)| (name.clone(), to_json(value)))
+                .collect(),
+        ))
+    }
+
+    async fn set(&self, set: PropertyParams) -> Result<Json, RpcError> {
+        let xml = self.introspect(&set.path).await?;
+        let signature = property_signature(&xml, &set.interface, &set.property)?;
+        let value = to_value(&set.value, &signature).map_err(invalid_args)?;
+        self.call(
+            &set.path,
+            "org.freedesktop.DBus.Properties",
+            "Set",
+            &(set.interface.as_str(), set.property.as_str(), value),
+        )
+        .await?;
+        Ok(Json::Null)
+    }
+
+    /// Check that `client` may make the changes of `interface`
+    async fn authorize(&self, client: Client, interface: &str) -> Result<(), RpcError> {
+        if client.uid == 0 {
+            return Ok(());
+        }
+        let denied = |why: String| {
+            warn!("SocketApi: uid {}: {why}", client.uid);
+            RpcError::dbus("org.freedesktop.DBus.Error.AccessDenied", why)
+        };
+        let Some(actions) = interface_actions(interface) else {
+            return Err(denied(format!("Only root may call {interface}")));
+        };
+        if actions.is_empty() {
+            return Ok(());
+        }
+        // The in-process connection has no polkit to ask
+        let (Some((pid, start_time)), Some(_)) = (client.process, self.destination) else {
+            return Err(denied(format!(
+                "Only root may change {interface} without polkit"
+            )));
+        };
+        for action in actions {
+            authorize_process(&self.conn, pid, start_time, client.uid, *action).await?;
+        }
+        Ok(())
+    }
+
+    async fn dispatch(&self, client: Client, method: &str, params: Json) -> Result<Json, RpcError> {
+        match method {
+            "Call" => {
+                let call: CallParams = self::params(params)?;
+                match (call.interface.as_str(), call.method.as_str()) {
+                    ("org.freedesktop.DBus.Properties", "Get" | "GetAll") => {}
+                    ("org.freedesktop.DBus.Properties", _) => {
+                        let target = call.args.first().and_then(Json::as_str).unwrap_or("");
+                        let property = call.args.get(1).and_then(Json::as_str).unwrap_or("");
+                        refuse_caller_bound(target, property)?;
+                        self.authorize(client, target).await?;
+                    }
+                    (interface, _) => self.authorize(client, interface).await?,
+                }
+                self.call_method(call).await
+            }
+            "Get" => self.get(self::params(params)?).await,
+            "GetAll" => self.get_all(self::params(params)?).await,
+            "Set" => {
+                let set: PropertyParams = self::params(params)?;
+                refuse_caller_bound(&set.interface, &set.property)?;
+                self.authorize(client, &set.interface).await?;
+                self.set(set).await
+            }
+            "Introspect" => {
+                let IntrospectParams { path } = self::params(params)?;
+                Ok(Json::String(self.introspect(&path).await?))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("There is no method {method}"),
+            )),
+        }
+    }
+
+    /// The response to one line, `None` for requests without an id
+    async fn respond(&self, client: Client, line: &str) -> Option<Response> {
+        let (id, result) = match serde_json::from_str::<Request>(line) {
+            Ok(request) if request.jsonrpc != "2.0" => (
+                request.id?,
+                Err(RpcError::new(
+                    INVALID_REQUEST,
+                    "Only JSON-RPC 2.0 is served",
+                )),
+            ),
+            Ok(request) => (
+                request.id?,
+                self.dispatch(client, &request.method, request.params).await,
+            ),
+            Err(e) if e.is_data() => (
+                Json::Null,
+                Err(RpcError::new(INVALID_REQUEST, e.to_string())),
+            ),
+            Err(e) => (Json::Null, Err(RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Some(Response {
+            jsonrpc: "2.0".to_owned(),
+            id,
+            result,
+            error,
+        })
+    }
+
+    async fn serve_client(&self, client: Client, stream: UnixStream) -> std::io::Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut read = BufReader::new(read);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let len = (&mut read)
+                .take(MAX_REQUEST_LEN as u64 + 1)
+                .read_until(b'\n', &mut line)
+                .await?;
+            if len == 0 {
+                return Ok(());
+            }
+            if len > MAX_REQUEST_LEN && line.last() != Some(&b'\n') {
+                let error = RpcError::new(
+                    INVALID_REQUEST,
+                    format!("A request is at most {MAX_REQUEST_LEN} bytes"),
+                );
+                let response = Response {
+                    jsonrpc: "2.0".to_owned(),
+                    id: Json::Null,
+                    result: None,
+                    error: Some(error),
+                };
+                write_response(&mut write, &response).await?;
+                return Ok(());
+            }
+            let line = std::str::from_utf8(&line)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.respond(client, line).await {
+                write_response(&mut write, &response).await?;
+            }
+        }
+    }
+}
+
+async fn write_response(write: &mut OwnedWriteHalf, response: &Response) -> std::io::Result<()> {
+    let mut out = serde_json::to_string(response)?;
+    out.push('\n');
+    write.write_all(out.as_bytes()).await
+}
+
+/// The group ID of `name` from `/etc/group`
+fn group_id(name: &str) -> Option<u32> {
+    std::fs::read_to_string("/etc/group")
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            let mut fields = line.split(':');
+            (fields.next()? == name).then(|| fields.nth(1)?.parse().ok())?
+        })
+}
+
+/// Bind the socket, readable by root and the configured group only
+fn bind(config: &SocketApiConfig) -> Result<UnixListener, RogError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path: &Path = &config.path;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // Left behind if asusd didn't stop cleanly
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let mut mode = 0o600;
+    if let Some(group) = &config.group {
+        match group_id(group) {
+            Some(gid) => {
+                std::os::unix::fs::chown(path, None, Some(gid))?;
+                mode = 0o660;
+            }
+            None => warn!("SocketApi: there is no group {group}, the socket is for root only"),
+        }
+    }
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+async fn serve(config: SocketApiConfig, peer: Option<Connection>) -> Result<(), RogError> {
+    let rpc = match peer {
+        Some(conn) => Rpc {
+            conn,
+            destination: None,
+        },
+        None => Rpc {
+            conn: Connection::system().await?,
+            destination: Some(DBUS_NAME),
+        },
+    };
+    let rpc = Arc::new(rpc);
+    let listener = bind(&config)?;
+    info!("SocketApi: serving on {:?}", config.path);
+    let clients = Arc::new(Semaphore::new(MAX_CLIENTS));
+    loop {
+        // Further clients wait in the listen backlog until one leaves
+        let Ok(permit) = clients.clone().acquire_owned().await else {
+            return Ok(());
+        };
+        let (stream, _) = listener.accept().await?;
+        // Read now, while the client is connected, so the process is the one
+        // polkit is later asked about
+        let client = match Client::of(&stream) {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("SocketApi: could not identify a client, closing it: {e}");
+                continue;
+            }
+        };
+        let uid = client.uid;
+        debug!("SocketApi: connection from uid {uid}");
+        let rpc = rpc.clone();
+        tokio::spawn(async move {
+            rpc.serve_client(client, stream)
+                .await
+                .map_err(|e| warn!("SocketApi: connection from uid {uid}: {e}"))
+                .ok();
+            drop(permit);
+        });
+    }
+}
+
+/// The connection asusd serves its interfaces on. This is the system bus if
+/// there is one, otherwise an in-process connection with the other end
+/// returned for the socket to call through
+pub async fn server_connection() -> Result<(Connection, Option<Connection>), RogError> {
+    match Connection::system().await {
+        Ok(conn) => Ok((conn, None)),
+        Err(e) => {
+            warn!("No system bus ({e}), asusd is only served on its socket");
+            let (ours, theirs) = std::os::unix::net::UnixStream::pair()?;
+            let server = zbus::connection::Builder::unix_stream(ours)
+                .server(Guid::generate())?
+                .p2p()
+                .build();
+            let client = zbus::connection::Builder::unix_stream(theirs).p2p().build();
+            let (server, client) = futures_util::try_join!(server, client)?;
+            Ok((server, Some(client)))
+        }
+    }
+}
+
+/// Start serving the socket if it is enabled in `/etc/asusd/socket_api.ron`.
+/// `peer` is the in-process connection from [`server_connection`], if asusd
+/// isn't on the system bus
+pub fn start_socket_api(peer: Option<Connection>, supervisor: &Supervisor) {
+    let config = SocketApiConfig::new().load();
+    if !config.enabled {
+        return;
+    }
+    supervisor.spawn("SocketApi", move || serve(config.clone(), peer.clone()));
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use zbus::zvariant::{Signature, Value};
+    use zbus_xml::ArgDirection;
+
+    use super::{
+        interface_actions, method_signatures, property_signature, refuse_caller_bound,
+        stat_start_time, to_json, to_value,
+    };
+    use crate::authorization::Action;
+
+    fn round_trip(json: serde_json::Value, signature: &str) -> serde_json::Value {
+        let signature = Signature::try_from(signature).unwrap();
+        let value = to_value(&json, &signature).unwrap();
+        assert_eq!(value.value_signature(), &signature);
+        to_json(&value)
+    }
+
+    #[test]
+    fn convert_values() {
+        assert_eq!(round_trip(json!(3), "u"), json!(3));
+        assert_eq!(round_trip(json!(-1), "i"), json!(-1));
+        assert_eq!(round_trip(json!("Quiet"), "s"), json!("Quiet"));
+        assert_eq!(
+            round_trip(
+                json!([1, [
+                    2, 3
+                ]]),
+                "(u(yy))"
+            ),
+            json!([1, [
+                2, 3
+            ]])
+        );
+        assert_eq!(
+            round_trip(json!({"1": [true], "2": []}), "a{uab}"),
+            json!({"1": [true], "2": []})
+        );
+        assert_eq!(
+            round_trip(
+                json!({"a": {"signature": "q", "value": 7}, "b": "x"}),
+                "a{sv}"
+            ),
+            json!({"a": {"signature": "q", "value": 7}, "b": {"signature": "s", "value": "x"}})
+        );
+
+        let u8_sig = Signature::try_from("y").unwrap();
+        assert!(to_value(&json!(256), &u8_sig).is_err());
+        assert!(to_value(&json!("1"), &u8_sig).is_err());
+        assert!(to_value(&json!([1]), &Signature::try_from("(uu)").unwrap()).is_err());
+        assert!(to_value(&json!(0), &Signature::try_from("h").unwrap()).is_err());
+        assert_eq!(to_json(&Value::from(1.5)), json!(1.5));
+    }
+
+    #[test]
+    fn signatures_from_xml() {
+        let xml = r#"<node>
+  <interface name="xyz.ljones.Aura">
+    <method name="SetLogo">
+      <arg name="mode" type="u" direction="in"/>
+      <arg name="colour" type="(yyy)" direction="in"/>
+    </method>
+    <method name="KeyboardLayout">
+      <arg type="(sa(sdddd))" direction="out"/>
+    </method>
+    <property name="Brightness" type="u" access="readwrite"/>
+  </interface>
+</node>"#;
+        let sig = |s| Signature::try_from(s).unwrap();
+        assert_eq!(
+            method_signatures(xml, "xyz.ljones.Aura", "SetLogo", ArgDirection::In).unwrap(),
+            vec![
+                sig("u"),
+                sig("(yyy)")
+            ]
+        );
+        assert!(
+            method_signatures(xml, "xyz.ljones.Aura", "SetLogo", ArgDirection::Out)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            method_signatures(xml, "xyz.ljones.Aura", "KeyboardLayout", ArgDirection::Out).unwrap(),
+            vec![sig(
+                "(sa(sdddd))"
+            )]
+        );
+        assert_eq!(
+            property_signature(xml, "xyz.ljones.Aura", "Brightness").unwrap(),
+            sig("u")
+        );
+        assert!(method_signatures(xml, "xyz.ljones.Anime", "SetLogo", ArgDirection::In).is_err());
+        assert!(property_signature(xml, "xyz.ljones.Aura", "Missing").is_err());
+    }
+
+    #[test]
+    fn actions_of_interfaces() {
+        assert_eq!(
+            interface_actions("xyz.ljones.Aura"),
+            Some(&[Action::Lighting][..])
+        );
+        assert_eq!(interface_actions("xyz.ljones.Health"), Some(&[][..]));
+        assert_eq!(interface_actions("xyz.ljones.Unknown"), None);
+    }
+
+    #[test]
+    fn start_time_of_process() {
+        let stat = "4242 (a) b (c)) S 1 4242 4242 0 -1 4194560 300 0 0 0 2 1 0 0 20 0 1 0 987654 \
+                    10000 100";
+        assert_eq!(stat_start_time(stat), Some(987654));
+        assert_eq!(stat_start_time("4242 (short) S 1"), None);
+    }
+
+    #[test]
+    fn caller_bound_properties() {
+        assert!(refuse_caller_bound("xyz.ljones.Aura", "StreamFrames").is_err());
+        assert!(refuse_caller_bound("xyz.ljones.Anime", "StreamFrames").is_err());
+        assert!(refuse_caller_bound("xyz.ljones.Aura", "Brightness").is_ok());
+    }
+}
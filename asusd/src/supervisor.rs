@@ -0,0 +1,271 @@
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::lock::Mutex;
+use log::{debug, info, warn};
+use tokio::time::sleep;
+use zbus::object_server::SignalEmitter;
+use zbus::{interface, Connection};
+
+use crate::error::RogError;
+use crate::{CtrlTask, ASUS_ZBUS_PATH};
+
+/// Delay before the first restart of a failed task
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+/// The backoff doubles on each consecutive failure up to this
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// A task which ran at least this long before failing starts again from
+/// `MIN_BACKOFF`
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+/// What a task or controller is doing, as given in the `Status` property
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum TaskState {
+    #[default]
+    Running,
+    /// Failed and waiting for the backoff before starting again
+    Restarting,
+    Finished,
+    /// A controller which could not be started, such as for missing hardware
+    Unavailable,
+}
+
+impl TaskState {
+    fn as_str(self) -> &'static str {
+        match self {
+            TaskState::Running => "running",
+            TaskState::Restarting => "restarting",
+            TaskState::Finished => "finished",
+            TaskState::Unavailable => "unavailable",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct TaskHealth {
+    state: TaskState,
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        format!("panicked: {s}")
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        format!("panicked: {s}")
+    } else {
+        "panicked".to_owned()
+    }
+}
+
+/// Runs long-lived daemon tasks so that a panic or error in one of them is
+/// contained. A failed task is restarted with an increasing delay, and the
+/// restart counts are reported on the `xyz.ljones.Health` interface.
+#[derive(Clone)]
+pub struct Supervisor {
+    tasks: Arc<Mutex<BTreeMap<String, TaskHealth>>>,
+    /// Problems which need the user to fix something, keyed by what they
+    /// are about
+    warnings: Arc<Mutex<BTreeMap<String, String>>>,
+    signal_ctxt: SignalEmitter<'static>,
+}
+
+impl Supervisor {
+    pub fn new(signal_ctxt: SignalEmitter<'static>) -> Self {
+        Self {
+            tasks: Default::default(),
+            warnings: Default::default(),
+            signal_ctxt,
+        }
+    }
+
+    /// Spawn a supervised task. `task` is called to create the future each
+    /// time the task is (re)started. A task which returns `Ok(())` is
+    /// considered finished and is not restarted.
+    pub fn spawn<F, Fut>(&self, name: &str, mut task: F)
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), RogError>> + Send + 'static,
+    {
+        let supervisor = self.clone();
+        let name = name.to_owned();
+        tokio::spawn(async move {
+            let mut backoff = MIN_BACKOFF;
+            loop {
+                supervisor.set_state(&name, TaskState::Running).await;
+                let started = Instant::now();
+                let error = match tokio::spawn(task()).await {
+                    Ok(Ok(())) => {
+                        debug!("Supervisor: {name} finished");
+                        supervisor.set_state(&name, TaskState::Finished).await;
+                        return;
+                    }
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) if e.is_panic() => panic_message(e.into_panic()),
+                    Err(e) => {
+                        debug!("Supervisor: {name} was cancelled: {e}");
+                        supervisor.set_state(&name, TaskState::Finished).await;
+                        return;
+                    }
+                };
+
+                if started.elapsed() >= STABLE_AFTER {
+                    backoff = MIN_BACKOFF;
+                }
+                warn!(
+                    "Supervisor: {name} failed: {error}. Restarting in {}s",
+                    backoff.as_secs()
+                );
+                supervisor.record_failure(&name, error).await;
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+                info!("Supervisor: restarting {name}");
+            }
+        });
+    }
+
+    async fn set_state(&self, name: &str, state: TaskState) {
+        let changed = {
+            let mut tasks = self.tasks.lock().await;
+            let health = tasks.entry(name.to_owned()).or_default();
+            std::mem::replace(&mut health.state, state) != state
+        };
+        if changed {
+            self.status_changed(&self.signal_ctxt).await.ok();
+        }
+    }
+
+    async fn record_failure(&self, name: &str, error: String) {
+        {
+            let mut tasks = self.tasks.lock().await;
+            let health = tasks.entry(name.to_owned()).or_default();
+            health.state = TaskState::Restarting;
+            health.restarts += 1;
+            health.last_error = Some(error);
+        }
+        self.restart_counts_changed(&self.signal_ctxt).await.ok();
+        self.last_errors_changed(&self.signal_ctxt).await.ok();
+        self.status_changed(&self.signal_ctxt).await.ok();
+    }
+
+    /// Report a controller which could not be started, and why, in `Status`
+    pub async fn set_unavailable(&self, name: &str, reason: String) {
+        {
+            let mut tasks = self.tasks.lock().await;
+            let health = tasks.entry(name.to_owned()).or_default();
+            health.state = TaskState::Unavailable;
+            health.last_error = Some(reason);
+        }
+        self.status_changed(&self.signal_ctxt).await.ok();
+    }
+
+    /// Report a problem with `key`, or clear it with `None`
+    pub async fn set_warning(&self, key: &str, warning: Option<String>) {
+        let changed = {
+            let mut warnings = self.warnings.lock().await;
+            match warning {
+                Some(warning) => warnings.insert(key.to_owned(), warning.clone()) != Some(warning),
+                None => warnings.remove(key).is_some(),
+            }
+        };
+        if changed {
+            self.warnings_changed(&self.signal_ctxt).await.ok();
+        }
+    }
+
+    pub async fn has_warning(&self, key: &str) -> bool {
+        self.warnings.lock().await.contains_key(key)
+    }
+}
+
+#[interface(name = "xyz.ljones.Health")]
+impl Supervisor {
+    /// Every supervised task and the number of times it has been restarted
+    #[zbus(property)]
+    async fn restart_counts(&self) -> Vec<(String, u32)> {
+        self.tasks
+            .lock()
+            .await
+            .iter()
+            .map(|(name, health)| (name.clone(), health.restarts))
+            .collect()
+    }
+
+    /// The most recent error or panic message of each task which has failed
+    #[zbus(property)]
+    async fn last_errors(&self) -> Vec<(String, String)> {
+        self.tasks
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, health)| health.state != TaskState::Unavailable)
+            .filter_map(|(name, health)| {
+                health
+                    .last_error
+                    .as_ref()
+                    .map(|e| (name.clone(), e.clone()))
+            })
+            .collect()
+    }
+
+    /// The state of every supervised task and of each controller which could
+    /// not be started: its name, `running`, `restarting`, `finished`, or
+    /// `unavailable`, the restart count, and the last error or the reason it
+    /// is unavailable, empty if there is none
+    #[zbus(property)]
+    async fn status(&self) -> Vec<(String, String, u32, String)> {
+        self.tasks
+            .lock()
+            .await
+            .iter()
+            .map(|(name, health)| {
+                (
+                    name.clone(),
+                    health.state.as_str().to_owned(),
+                    health.restarts,
+                    health.last_error.clone().unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    /// Problems found which need fixing by the user, such as missing device
+    /// permissions, and how to fix them. Cleared once resolved
+    #[zbus(property)]
+    async fn warnings(&self) -> Vec<(String, String)> {
+        self.warnings
+            .lock()
+            .await
+            .iter()
+            .map(|(key, warning)| (key.clone(), warning.clone()))
+            .collect()
+    }
+}
+
+// `api_description()` for `asusd --dump-api`
+include!(concat!(env!("OUT_DIR"), "/api/supervisor.rs"));
+
+impl crate::ZbusRun for Supervisor {
+    async fn add_to_server(self, server: &mut Connection) {
+        Self::add_to_server_helper(self, ASUS_ZBUS_PATH, server).await;
+    }
+}
+
+impl crate::Reloadable for Supervisor {
+    async fn reload(&mut self) -> Result<(), RogError> {
+        Ok(())
+    }
+}
+
+impl CtrlTask for Supervisor {
+    fn zbus_path() -> &'static str {
+        ASUS_ZBUS_PATH
+    }
+
+    async fn create_tasks(&self, _signal_ctxt: SignalEmitter<'static>) -> Result<(), RogError> {
+        Ok(())
+    }
+}
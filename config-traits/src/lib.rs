@@ -6,8 +6,9 @@
 //! The end canonical file format is `.ron` as this supports rust types well
 
 use std::fs::{self, create_dir, File, OpenOptions};
-use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 
 use log::{error, warn};
 pub use ron;
@@ -15,6 +16,59 @@ use ron::ser::PrettyConfig;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+/// The path of the one backup kept of `path`, `<file>.bak`
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Replace the file at `path` with `data` so that a crash or power loss leaves
+/// either the old or the new file, never an empty or partial one. The data is
+/// written to `<file>.tmp`, synced, and renamed over `path`. The file being
+/// replaced is kept as the [`backup_path`], unless it is empty. A new file is
+/// created with `mode`, and a replaced one keeps its permissions without any
+/// not in `mode`, as does the backup.
+pub fn write_atomic(path: &Path, data: &[u8], mode: u32) -> std::io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let existing = fs::metadata(path).ok();
+    let permissions =
+        |existing: &fs::Metadata| fs::Permissions::from_mode(existing.permissions().mode() & mode);
+
+    // A left over file would keep its permissions, so start afresh
+    fs::remove_file(&tmp).ok();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(&tmp)?;
+    if let Some(existing) = &existing {
+        file.set_permissions(permissions(existing))?;
+    }
+    file.write_all(data)?;
+    file.sync_all()?;
+    drop(file);
+
+    if let Some(existing) = existing.filter(|m| m.len() > 0) {
+        let backup = backup_path(path);
+        fs::remove_file(&backup).ok();
+        if fs::hard_link(path, &backup).is_err() {
+            fs::copy(path, &backup)?;
+        }
+        fs::set_permissions(&backup, permissions(&existing))?;
+    }
+    fs::rename(&tmp, path)?;
+
+    // The rename is only durable once the directory is synced
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
 /// Config file helper traits. Only `new()` and `file_name()` are required to be
 /// implemented, the rest are intended to be free methods.
 pub trait StdConfig
@@ -30,6 +84,12 @@ where
     /// Return the full path to the directory the config file resides in
     fn config_dir() -> PathBuf;
 
+    /// The permissions the config file is created with. Configs holding
+    /// passwords or keys should return `0o600`
+    fn file_mode(&self) -> u32 {
+        0o644
+    }
+
     /// Return the full path to the config file
     fn file_path(&self) -> PathBuf {
         let mut config = Self::config_dir();
@@ -121,18 +181,15 @@ where
         None
     }
 
-    /// Write the config file data to pretty ron format
+    /// Read the backup kept by [`write_atomic`], if there is one
+    fn read_backup(&self) -> Option<String> {
+        fs::read_to_string(backup_path(&self.file_path()))
+            .ok()
+            .filter(|data| !data.is_empty())
+    }
+
+    /// Write the config file data to pretty ron format, see [`write_atomic`]
     fn write(&self) {
-        let mut file = match File::create(self.file_path()) {
-            Ok(data) => data,
-            Err(e) => {
-                error!(
-                    "Couldn't overwrite config {:?}, error: {e}",
-                    self.file_path()
-                );
-                return;
-            }
-        };
         let ron = match ron::ser::to_string_pretty(&self, PrettyConfig::new().depth_limit(4)) {
             Ok(data) => data,
             Err(e) => {
@@ -140,8 +197,12 @@ where
                 return;
             }
         };
-        file.write_all(ron.as_bytes())
-            .unwrap_or_else(|err| error!("Could not write config: {}", err));
+        write_atomic(&self.file_path(), ron.as_bytes(), self.file_mode()).unwrap_or_else(|e| {
+            error!(
+                "Couldn't overwrite config {:?}, error: {e}",
+                self.file_path()
+            )
+        });
     }
 
     /// Renames the existing file to `<file>-old`
@@ -199,30 +260,51 @@ macro_rules! std_config_load {
         /// impl StdConfigLoad2<FanCurveConfigOld, FanCurveConfigOlder> for FanCurveConfig {}
         /// ```
         ///
-        /// If all of the generics fails to parse, then the old config is renamed and a
+        /// If all of the generics fails to parse, or the file is empty, then the
+        /// backup is tried. If that fails too the old config is renamed and a
         /// new one created
         pub trait $trait_name<$($generic),*>
         where
             Self: $crate::StdConfig + DeserializeOwned + Serialize,
             $($generic: DeserializeOwned + Into<Self>),*
         {
+            /// Parse `buf` as this version or any of the previous ones
+            fn parse_any_version(buf: &str) -> Option<Self> {
+                if let Ok(data) = ron::from_str(buf) {
+                    log::info!("Parsed RON for {:?}", std::any::type_name::<Self>());
+                    return Some(data);
+                }
+                $(if let Ok(data) = ron::from_str::<$generic>(buf) {
+                    log::info!("New version failed, trying previous: Parsed RON for {:?}", std::any::type_name::<$generic>());
+                    return Some(data.into());
+                })*
+                None
+            }
+
             fn load(mut self) -> Self {
-                let mut file = self.file_open();
-                let mut buf = String::new();
-                if let Ok(read_len) = file.read_to_string(&mut buf) {
-                    if read_len != 0 {
-                        if let Ok(data) = ron::from_str(&buf) {
-                            self = data;
-                            log::info!("Parsed RON for {:?}", std::any::type_name::<Self>());
-                        }  $(else if let Ok(data) = ron::from_str::<$generic>(&buf) {
-                            self = data.into();
-                            log::info!("New version failed, trying previous: Parsed RON for {:?}", std::any::type_name::<$generic>());
-                        })* else {
+                // A missing file is left for `write` to create with `file_mode`
+                let buf = match std::fs::read_to_string(self.file_path()) {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+                    buf => buf,
+                };
+                if let Ok(buf) = buf {
+                    let read_len = buf.len();
+                    if read_len == 0 {
+                        error!("Config file {} zero read length", self.file_name());
+                    }
+                    if let Some(data) = Self::parse_any_version(&buf) {
+                        self = data;
+                    } else if let Some(data) = self.read_backup().and_then(|b| Self::parse_any_version(&b)) {
+                        log::warn!("Config file {} restored from its backup", self.file_name());
+                        // Keep the bad file out of the way so that it doesn't
+                        // replace the backup on the next write
+                        if read_len != 0 {
                             self.rename_file_old();
-                            self = Self::new();
                         }
-                    } else {
-                        error!("Config file {} zero read length", self.file_name());
+                        self = data;
+                    } else if read_len != 0 {
+                        self.rename_file_old();
+                        self = Self::new();
                     }
                 }
                 self.write();
@@ -240,8 +322,137 @@ std_config_load!(StdConfigLoad4: T1, T2, T3, T4);
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
     use std::path::PathBuf;
 
+    use crate::{backup_path, write_atomic};
+
+    #[test]
+    fn write_keeps_backup() {
+        let dir = std::env::temp_dir().join(format!("config-traits-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("test.ron");
+
+        write_atomic(&file, b"one", 0o644).unwrap();
+        assert!(!backup_path(&file).exists());
+        write_atomic(&file, b"two", 0o644).unwrap();
+        write_atomic(&file, b"three", 0o644).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "three");
+        assert_eq!(fs::read_to_string(backup_path(&file)).unwrap(), "two");
+
+        // An emptied file doesn't replace the backup
+        fs::write(&file, b"").unwrap();
+        write_atomic(&file, b"four", 0o644).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&file)).unwrap(), "two");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_private() {
+        let dir = std::env::temp_dir().join(format!("config-traits-mode-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("secret.ron");
+        let mode = |path: &PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        write_atomic(&file, b"one", 0o600).unwrap();
+        assert_eq!(mode(&file), 0o600);
+        // A replaced file keeps the permissions it was given, within `mode`
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o400)).unwrap();
+        write_atomic(&file, b"two", 0o600).unwrap();
+        assert_eq!(mode(&file), 0o400);
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        write_atomic(&file, b"three", 0o600).unwrap();
+        assert_eq!(mode(&file), 0o600);
+        assert_eq!(mode(&backup_path(&file)), 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_private() {
+        #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+        struct Secret {
+            password: String,
+        }
+
+        impl crate::StdConfig for Secret {
+            fn new() -> Self {
+                Self {
+                    password: "hunter2".to_owned(),
+                }
+            }
+
+            fn file_name(&self) -> String {
+                "secret.ron".to_owned()
+            }
+
+            fn config_dir() -> PathBuf {
+                std::env::temp_dir().join(format!("config-traits-private-{}", std::process::id()))
+            }
+
+            fn file_mode(&self) -> u32 {
+                0o600
+            }
+        }
+
+        impl crate::StdConfigLoad for Secret {}
+
+        use crate::{StdConfig, StdConfigLoad};
+        let dir = Secret::config_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("secret.ron");
+        let mode = |path: &PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // Created by loading
+        Secret::new().load();
+        assert_eq!(mode(&file), 0o600);
+
+        // Written before configs could be created private
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(Secret::new().load(), Secret::new());
+        assert_eq!(mode(&file), 0o600);
+        assert_eq!(mode(&backup_path(&file)), 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_from_backup() {
+        #[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq)]
+        struct Test {
+            value: u32,
+        }
+
+        impl crate::StdConfig for Test {
+            fn new() -> Self {
+                Self { value: 0 }
+            }
+
+            fn file_name(&self) -> String {
+                "test.ron".to_owned()
+            }
+
+            fn config_dir() -> PathBuf {
+                std::env::temp_dir().join(format!("config-traits-load-{}", std::process::id()))
+            }
+        }
+
+        impl crate::StdConfigLoad for Test {}
+
+        use crate::{StdConfig, StdConfigLoad};
+        let dir = Test::config_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("test.ron");
+        fs::write(&file, b"").unwrap();
+        fs::write(backup_path(&file), b"(value: 7)").unwrap();
+        assert_eq!(Test::new().load(), Test { value: 7 });
+        assert_eq!(Test::new().load(), Test { value: 7 });
+
+        fs::write(&file, b"(value: ").unwrap();
+        assert_eq!(Test::new().load(), Test { value: 7 });
+        assert!(dir.join("test.ron-old").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn check_macro_from_1() {
         #[derive(serde::Deserialize, serde::Serialize, Debug)]
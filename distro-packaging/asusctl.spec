@@ -90,10 +90,16 @@ EOF
 
 %build
 export RUSTFLAGS="%{rustflags}"
+# rog-control-center is a workspace of its own, built into the same target
+export CARGO_TARGET_DIR="$PWD/target"
 %if %{defined fedora}
 %cargo_build
+cd rog-control-center
+%cargo_build
+cd ..
 %else
 /usr/bin/cargo auditable build --release
+/usr/bin/cargo auditable build --release --manifest-path rog-control-center/Cargo.toml
 %endif
 
 %install
@@ -118,6 +124,7 @@ desktop-file-validate %{buildroot}/%{_datadir}/applications/rog-control-center.d
 #%dir %{_sysconfdir}/asusd/
 %{_datadir}/asusd/aura_support.ron
 %{_datadir}/dbus-1/system.d/asusd.conf
+%{_datadir}/polkit-1/actions/xyz.ljones.asusd.policy
 %{_datadir}/icons/hicolor/512x512/apps/asus_notif_yellow.png
 %{_datadir}/icons/hicolor/512x512/apps/asus_notif_green.png
 %{_datadir}/icons/hicolor/512x512/apps/asus_notif_red.png
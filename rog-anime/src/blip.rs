@@ -0,0 +1,294 @@
+use crate::data::{AnimeDataBuffer, AnimeType};
+use crate::error::Result;
+use crate::image::{AnimeImage, Led};
+
+/// The longest a blip may be shown for, in milliseconds
+pub const BLIP_MAX_MS: u32 = 10_000;
+
+/// Small icons drawn for the named blips, `#` is lit
+#[rustfmt::skip]
+const ICONS: [(&str, [&str; 7]); 5] = [
+    ("mail", [
+        "#########",
+        "##.....##",
+        "#.#...#.#",
+        "#..#.#..#",
+        "#...#...#",
+        "#.......#",
+        "#########",
+    ]),
+    ("chat", [
+        ".#######.",
+        "#.......#",
+        "#.#.#.#.#",
+        "#.......#",
+        ".###.###.",
+        "...#.#...",
+        "....#....",
+    ]),
+    ("alert", [
+        "....#....",
+        "...#.#...",
+        "..#.#.#..",
+        "..#.#.#..",
+        ".#.....#.",
+        "#...#...#",
+        "#########",
+    ]),
+    ("bell", [
+        "....#....",
+        "..#####..",
+        ".#.....#.",
+        ".#.....#.",
+        ".#.....#.",
+        "#########",
+        "...###...",
+    ]),
+    ("battery", [
+        ".........",
+        "########.",
+        "#.......#",
+        "##......#",
+        "#.......#",
+        "########.",
+        ".........",
+    ]),
+];
+
+/// Other names notification daemons use for the icons
+const ICON_ALIASES: [(&str, &str); 3] = [
+    ("message", "chat"),
+    ("battery-low", "battery"),
+    ("battery_low", "battery"),
+];
+
+/// The names accepted by [`blip_buffer`] as icons rather than text
+pub fn blip_icons() -> Vec<&'static str> {
+    ICONS
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(ICON_ALIASES.iter().map(|(alias, _)| *alias))
+        .collect()
+}
+
+/// A 3x5 font for short text blips such as an unread count
+#[rustfmt::skip]
+const FONT: [(char, [&str; 5]); 42] = [
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["###", "..#", "###", "#..", "###"]),
+    ('3', ["###", "..#", ".##", "..#", "###"]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "###", "..#", "###"]),
+    ('6', ["###", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", ".#.", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "###"]),
+    ('A', [".#.", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', [".##", "#..", "#..", "#..", ".##"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', [".##", "#..", "#.#", "#.#", ".##"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", ".#."]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#.#", "###", "###", "#.#", "#.#"]),
+    ('N', ["##.", "#.#", "#.#", "#.#", "#.#"]),
+    ('O', [".#.", "#.#", "#.#", "#.#", ".#."]),
+    ('P', ["##.", "#.#", "##.", "#..", "#.."]),
+    ('Q', [".#.", "#.#", "#.#", "##.", ".##"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', [".##", "#..", ".#.", "..#", "##."]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", "#.#", ".#."]),
+    ('W', ["#.#", "#.#", "###", "###", "#.#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    ('!', [".#.", ".#.", ".#.", "...", ".#."]),
+    ('?', ["##.", "..#", ".#.", "...", ".#."]),
+    ('+', ["...", ".#.", "###", ".#.", "..."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    (':', ["...", ".#.", "...", ".#.", "..."]),
+    ('.', ["...", "...", "...", "...", ".#."]),
+];
+
+/// The top LED row of the blip. The rows near the top of every panel are the
+/// widest
+const TOP_ROW: f32 = 2.0;
+
+/// A blip bitmap, one `Vec<bool>` per row
+type Bitmap = Vec<Vec<bool>>;
+
+fn parse(rows: &[&str]) -> Bitmap {
+    rows.iter()
+        .map(|row| row.chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+/// Lay out `text` in the 3x5 font. Characters not in the font are blank, and
+/// the text is cut to `max_width` columns.
+fn text_bitmap(text: &str, max_width: usize) -> Bitmap {
+    let mut bitmap = vec![Vec::new(); 5];
+    for c in text.chars().map(|c| c.to_ascii_uppercase()) {
+        let glyph = FONT
+            .iter()
+            .find(|(g, _)| *g == c)
+            .map_or(["..."; 5], |(_, rows)| *rows);
+        let spacing = usize::from(!bitmap[0].is_empty());
+        if bitmap[0].len() + spacing + 3 > max_width {
+            break;
+        }
+        for (row, glyph_row) in bitmap.iter_mut().zip(glyph) {
+            if spacing > 0 {
+                row.push(false);
+            }
+            row.extend(glyph_row.chars().map(|c| c == '#'));
+        }
+    }
+    bitmap
+}
+
+/// The first and last LED column of the row the top of a blip is drawn on
+fn blip_row(positions: &[Option<Led>]) -> (f32, f32) {
+    positions
+        .iter()
+        .flatten()
+        .filter(|led| led.y() == TOP_ROW + 6.0)
+        .fold((f32::MAX, f32::MIN), |(min, max), led| {
+            (min.min(led.x()), max.max(led.x()))
+        })
+}
+
+/// Draw `bitmap` with its left edge at LED column `left`. Each bitmap pixel is
+/// one LED wide and two rows high since alternate rows are offset by half an
+/// LED
+fn draw_bitmap(
+    anime_type: AnimeType,
+    positions: &[Option<Led>],
+    bitmap: &Bitmap,
+    left: f32,
+) -> Result<AnimeDataBuffer> {
+    let mut data: Vec<u8> = positions
+        .iter()
+        .map(|led| {
+            let Some(led) = led else {
+                return 0;
+            };
+            let x = (led.x() - left).round();
+            let y = ((led.y() - TOP_ROW) / 2.0).floor();
+            if x < 0.0 || y < 0.0 {
+                return 0;
+            }
+            let lit = bitmap
+                .get(y as usize)
+                .and_then(|row| row.get(x as usize))
+                .copied()
+                .unwrap_or_default();
+            if lit {
+                255
+            } else {
+                0
+            }
+        })
+        .collect();
+    let mut buf = Vec::with_capacity(anime_type.data_length());
+    if anime_type == AnimeType::GA401 {
+        buf.push(0);
+    }
+    buf.append(&mut data);
+    buf.resize(anime_type.data_length(), 0);
+    AnimeDataBuffer::from_vec(anime_type, buf)
+}
+
+/// Draw a notification blip. `icon_or_text` is one of the [`blip_icons`],
+/// such as `mail` or `battery-low`, or else a short text such as an unread
+/// count.
+pub fn blip_buffer(anime_type: AnimeType, icon_or_text: &str) -> Result<AnimeDataBuffer> {
+    let positions = AnimeImage::generate_image_positioning(anime_type);
+    let (row_min, row_max) = blip_row(&positions);
+    let row_width = (row_max - row_min).max(0.0) as usize;
+
+    let icon = ICON_ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(icon_or_text.trim()))
+        .map_or(icon_or_text.trim(), |(_, name)| *name);
+    let bitmap = ICONS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(icon))
+        .map_or_else(
+            || text_bitmap(icon_or_text.trim(), row_width),
+            |(_, rows)| parse(rows),
+        );
+    let width = bitmap.first().map_or(0, Vec::len);
+    let left = row_min + (row_width.saturating_sub(width) / 2) as f32;
+    draw_bitmap(anime_type, &positions, &bitmap, left)
+}
+
+/// The frames of `text` scrolling right to left across the blip row, one LED
+/// column per frame. The text enters from the right edge and the last frame is
+/// blank.
+pub fn scroll_text_buffers(anime_type: AnimeType, text: &str) -> Result<Vec<AnimeDataBuffer>> {
+    let positions = AnimeImage::generate_image_positioning(anime_type);
+    let (row_min, row_max) = blip_row(&positions);
+    let bitmap = text_bitmap(text.trim(), usize::MAX);
+    let width = bitmap.first().map_or(0, Vec::len);
+    let steps = (row_max - row_min).max(0.0) as usize + width + 1;
+    (0..=steps)
+        .map(|step| draw_bitmap(anime_type, &positions, &bitmap, row_max - step as f32))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blip_buffer, blip_icons, scroll_text_buffers, text_bitmap};
+    use crate::data::AnimeType;
+
+    #[test]
+    fn text_is_cut_to_width() {
+        let bitmap = text_bitmap("12", 20);
+        assert_eq!(bitmap.len(), 5);
+        assert_eq!(bitmap[0].len(), 7);
+        assert_eq!(text_bitmap("abcdefgh", 9)[0].len(), 7);
+    }
+
+    #[test]
+    fn blips_light_some_leds() {
+        for anime_type in [
+            AnimeType::GA401,
+            AnimeType::GA402,
+            AnimeType::GU604,
+        ] {
+            for blip in [
+                "mail", "Chat", "3", "hi!", "battery", "message",
+            ] {
+                let buf = blip_buffer(anime_type, blip).unwrap();
+                assert_eq!(buf.data().len(), anime_type.data_length());
+                assert!(buf.data().iter().any(|b| *b == 255), "{blip}");
+            }
+        }
+    }
+
+    #[test]
+    fn aliases_draw_their_icon() {
+        let chat = blip_buffer(AnimeType::GA402, "chat").unwrap();
+        let message = blip_buffer(AnimeType::GA402, "Message").unwrap();
+        assert_eq!(chat.data(), message.data());
+        let battery = blip_buffer(AnimeType::GA402, "battery-low").unwrap();
+        assert_ne!(battery.data(), chat.data());
+        assert!(blip_icons().contains(&"battery-low"));
+    }
+
+    #[test]
+    fn scroll_ends_blank() {
+        let frames = scroll_text_buffers(AnimeType::GA402, "abc - def").unwrap();
+        assert!(frames.iter().any(|f| f.data().iter().any(|b| *b == 255)));
+        let last = frames.last().unwrap();
+        assert!(last.data().iter().all(|b| *b == 0));
+    }
+}
@@ -237,10 +237,10 @@ impl AnimeGif {
                     }
                     let pos =
                         (x + frame.left as usize) + ((y + frame.top as usize) * width as usize);
-                    image.get_mut()[pos] = Pixel {
+                    image.set_pixel(pos, Pixel {
                         color: ((px[0] as u32 + px[1] as u32 + px[2] as u32) / 3),
                         alpha: 1.0,
-                    };
+                    });
                 }
             }
             image.update();
@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 use std::path::Path;
 
 pub use glam::Vec2;
-use glam::{Mat3, Vec3};
+use glam::{Mat3, Vec3, Vec4};
 use log::error;
 
 use crate::data::AnimeDataBuffer;
@@ -39,11 +39,11 @@ impl Led {
         Led(x, y, 0)
     }
 
-    pub(crate) const fn x(&self) -> f32 {
+    pub const fn x(&self) -> f32 {
         self.0
     }
 
-    pub(crate) const fn y(&self) -> f32 {
+    pub const fn y(&self) -> f32 {
         self.1
     }
 
@@ -56,6 +56,16 @@ impl Led {
     }
 }
 
+/// A pixel as sampled by `AnimeImage::update()`: the colour, the alpha, and
+/// `1.0` if it is inside the image. Summing texels gives the colour and alpha
+/// sums and the sample count together, in one SIMD add.
+fn texel(p: Pixel) -> Vec4 {
+    Vec4::new(p.color as f32, p.alpha, 1.0, 0.0)
+}
+
+/// The scale, angle, translation, and brightness an image was last sampled at
+type Sampled = (Vec2, f32, Vec2, f32);
+
 /// Container of `Led`, each of which specifies a position within the image
 /// The main use of this is to position and sample colours for the final image
 /// to show on `AniMe`
@@ -69,10 +79,19 @@ pub struct AnimeImage {
     pub bright: f32,
     /// Positions of all the LEDs
     led_pos: Vec<Option<Led>>,
-    /// THe image data for sampling
-    img_pixels: Vec<Pixel>,
+    /// The image data for sampling, see [`texel`]. There is a border of empty
+    /// texels one wide, so samples outside the image are clamped on to it
+    /// instead of checked
+    texels: Vec<Vec4>,
     /// width of the image
     width: u32,
+    height: u32,
+    /// Centimetres on the display to LED positions
+    led_from_cm: Mat3,
+    /// Image pixels to centimetres, centred and scaled to fit the display
+    cm_from_px: Mat3,
+    /// `None` if the LEDs need sampling on the next `update()`
+    sampled: Option<Sampled>,
     /// The type of the display. The GA401 and GA402 use the same controller and
     /// therefore same ID, so the identifier must be by laptop model in
     /// `AnimeType`.
@@ -94,14 +113,29 @@ impl AnimeImage {
             return Err(AnimeError::InvalidBrightness(bright));
         }
 
+        let height = pixels.len() as u32 / width;
+        let stride = width as usize + 2;
+        let mut texels = vec![Vec4::ZERO; stride * (height as usize + 2)];
+        for (row, pixels) in pixels.chunks_exact(width as usize).enumerate() {
+            let start = (row + 1) * stride + 1;
+            for (t, p) in texels[start..start + width as usize].iter_mut().zip(pixels) {
+                *t = texel(*p);
+            }
+        }
+        let (led_from_cm, cm_from_px) = Self::put(anime_type, width as f32, height as f32);
+
         Ok(Self {
             scale,
             angle,
             translation,
             bright,
             led_pos: Self::generate_image_positioning(anime_type),
-            img_pixels: pixels,
+            texels,
             width,
+            height,
+            led_from_cm,
+            cm_from_px,
+            sampled: None,
             anime_type,
         })
     }
@@ -275,8 +309,28 @@ impl AnimeImage {
         }
     }
 
-    pub(crate) fn get_mut(&mut self) -> &mut [Pixel] {
-        &mut self.img_pixels
+    /// Set the pixel at `pos`, counted along the rows of the image
+    pub(crate) fn set_pixel(&mut self, pos: usize, pixel: Pixel) {
+        let width = self.width as usize;
+        let (x, y) = (pos % width, pos / width);
+        self.texels[(y + 1) * (width + 2) + x + 1] = texel(pixel);
+        self.sampled = None;
+    }
+
+    /// Turn the image by `angle` radians from where it is now. Call `update()`
+    /// after the changes for a frame are made
+    pub fn rotate(&mut self, angle: f32) {
+        self.angle = (self.angle + angle) % std::f32::consts::TAU;
+    }
+
+    /// Move the image by `by` from where it is now
+    pub fn translate(&mut self, by: Vec2) {
+        self.translation += by;
+    }
+
+    /// Multiply the scale of the image by `factor`
+    pub fn zoom(&mut self, factor: f32) {
+        self.scale *= factor;
     }
 
     /// Generate a list of LED positions. These are then used to sample the
@@ -300,49 +354,55 @@ impl AnimeImage {
 
     /// Called after setting new angle, position, or scale to refresh the image
     /// samples, the result can then been transformed to the appropriate data
-    /// for displaying.
+    /// for displaying. Does nothing if none of them or the image changed since
+    /// the last update.
     ///
     /// The internal for loop iterates over the LED positions, skipping the
     /// blank/dead pixels if any.
     #[inline]
     pub fn update(&mut self) {
+        let state = (self.scale, self.angle, self.translation, self.bright);
+        if self.sampled == Some(state) {
+            return;
+        }
+        self.sampled = Some(state);
+
         let width = self.width as i32;
-        let height = self.img_pixels.len() as i32 / width;
-        let led_from_px = self.put(width as f32, height as f32);
+        let height = self.height as i32;
+        let stride = width + 2;
+        let transform =
+            Mat3::from_scale_angle_translation(self.scale, self.angle, self.translation);
+        // Get LED-to-image coords
+        let led_from_px = (self.led_from_cm * transform * self.cm_from_px).inverse();
         // Steps should be configurable as "sharpness"
         let du = led_from_px * Vec3::new(-0.5, 0.5, 0.0);
         let dv = led_from_px * Vec3::new(0.5, 0.5, 0.0);
 
         for led in self.led_pos.iter_mut().flatten() {
-            let mut sum = 0.0;
-            let mut alpha = 0.0;
-            let mut count = 0;
-
             let pos = Vec3::new(led.x(), led.y(), 1.0);
             let x0 = led_from_px.mul_vec3(pos + Vec3::new(0.0, -0.5, 0.0));
 
             const GROUP: [f32; 4] = [
                 0.0, 0.5, 1.0, 1.5,
             ];
+            // Colour, alpha, and the count of samples inside the image
+            let mut sum = Vec4::ZERO;
             for u in &GROUP {
                 for v in &GROUP {
                     let sample = x0 + *u * du + *v * dv;
-
-                    let x = sample.x as i32;
-                    let y = sample.y as i32;
-                    if x > width - 1 || y > height - 1 || x < 0 || y < 0 {
-                        continue;
-                    }
-
-                    let p = self.img_pixels[(x + (y * width)) as usize];
-                    sum += p.color as f32;
-                    alpha += p.alpha;
-                    count += 1;
+                    // Outside the image lands on the empty border
+                    let x = (sample.x as i32).clamp(-1, width) + 1;
+                    let y = (sample.y as i32).clamp(-1, height) + 1;
+                    sum += self.texels[(x + y * stride) as usize];
                 }
             }
-            alpha /= count as f32;
-            sum /= count as f32;
-            led.set_bright((sum * self.bright * alpha) as u8);
+            let bright = if sum.z > 0.0 {
+                let (colour, alpha) = (sum.x / sum.z, sum.y / sum.z);
+                colour * self.bright * alpha
+            } else {
+                0.0
+            };
+            led.set_bright(bright as u8);
         }
     }
 
@@ -384,13 +444,15 @@ impl AnimeImage {
         }
     }
 
-    /// Put the render window in place on the image
-    fn put(&self, bmp_w: f32, bmp_h: f32) -> Mat3 {
+    /// Put the render window in place on the image. These are the parts of
+    /// the LED-to-image transform either side of the image's own scale, angle,
+    /// and translation, which only change with the image size.
+    fn put(anime_type: AnimeType, bmp_w: f32, bmp_h: f32) -> (Mat3, Mat3) {
         // Center of image
         let center = Mat3::from_translation(Vec2::new(-0.5 * bmp_w, -0.5 * bmp_h));
         // Find the scale required for cleanly showing the image
-        let h = AnimeImage::phys_height(self.anime_type) / bmp_h;
-        let mut base_scale = AnimeImage::phys_width(self.anime_type) / bmp_w;
+        let h = AnimeImage::phys_height(anime_type) / bmp_h;
+        let mut base_scale = AnimeImage::phys_width(anime_type) / bmp_w;
         if base_scale > h {
             base_scale = h;
         }
@@ -398,18 +460,12 @@ impl AnimeImage {
         let cm_from_px = Mat3::from_scale(Vec2::new(base_scale, base_scale));
 
         let led_from_cm = Mat3::from_scale(Vec2::new(
-            1.0 / AnimeImage::scale_x(self.anime_type),
-            1.0 / AnimeImage::scale_y(self.anime_type),
+            1.0 / AnimeImage::scale_x(anime_type),
+            1.0 / AnimeImage::scale_y(anime_type),
         ));
 
-        let transform =
-            Mat3::from_scale_angle_translation(self.scale, self.angle, self.translation);
-
         let pos_in_leds = Mat3::from_translation(Vec2::new(20.0, 20.0));
-        // Get LED-to-image coords
-        let led_from_px = pos_in_leds * led_from_cm * transform * cm_from_px * center;
-
-        led_from_px.inverse()
+        (pos_in_leds * led_from_cm, cm_from_px * center)
     }
 
     /// Generate the base image from inputs. The result can be displayed as is
@@ -640,6 +696,99 @@ mod tests {
         assert_eq!(AnimeImage::pitch(a, 14), 29);
     }
 
+    fn test_image() -> AnimeImage {
+        // A bright square in the middle of a dark image
+        let pixels = (0..64 * 64)
+            .map(|i| {
+                let (x, y) = (i % 64, i / 64);
+                Pixel {
+                    color: if (16..48).contains(&x) && (16..48).contains(&y) {
+                        255
+                    } else {
+                        20
+                    },
+                    alpha: 1.0,
+                }
+            })
+            .collect();
+        AnimeImage::new(
+            Vec2::new(0.5, 0.5),
+            0.0,
+            Vec2::default(),
+            1.0,
+            pixels,
+            64,
+            AnimeType::GA402,
+        )
+        .unwrap()
+    }
+
+    fn brightness(image: &AnimeImage) -> Vec<u8> {
+        image
+            .led_pos
+            .iter()
+            .map(|l| l.map(|l| l.bright()).unwrap_or(0))
+            .collect()
+    }
+
+    #[test]
+    fn incremental_transform() {
+        let mut image = test_image();
+        for _ in 0..10 {
+            image.rotate(0.1);
+            image.translate(Vec2::new(0.5, 0.0));
+            image.zoom(1.1);
+            image.update();
+        }
+
+        let mut expected = test_image();
+        expected.angle = image.angle;
+        expected.translation = image.translation;
+        expected.scale = image.scale;
+        expected.update();
+        assert_eq!(brightness(&image), brightness(&expected));
+        assert!(brightness(&image).iter().any(|b| *b > 0));
+    }
+
+    #[test]
+    fn update_only_when_changed() {
+        let mut image = test_image();
+        image.update();
+        let before = brightness(&image);
+        image.edge_outline();
+        image.update();
+        assert_ne!(
+            brightness(&image),
+            before,
+            "an unchanged image is not sampled again"
+        );
+
+        // Setting a pixel samples again, with the same pixel here
+        image.set_pixel(0, Pixel {
+            color: 20,
+            alpha: 1.0,
+        });
+        image.update();
+        assert_eq!(brightness(&image), before);
+    }
+
+    #[test]
+    #[ignore = "Timing, run with --release --ignored --nocapture"]
+    fn update_timing() {
+        let mut image = test_image();
+        let frames = 10_000;
+        let start = std::time::Instant::now();
+        for _ in 0..frames {
+            image.rotate(0.05);
+            image.update();
+        }
+        let elapsed = start.elapsed();
+        println!(
+            "{frames} rotated frames in {elapsed:?}, {:?} per frame",
+            elapsed / frames
+        );
+    }
+
     #[test]
     #[ignore = "Just to inspect image packet"]
     fn ga402_image_packet_check() {
@@ -26,6 +26,11 @@ pub use crate::gif::*;
 mod sequencer;
 pub use sequencer::*;
 
+/// Small icons and text shown briefly over the current content, such as for
+/// desktop notifications
+mod blip;
+pub use blip::*;
+
 /// Base errors that are possible
 pub mod error;
 
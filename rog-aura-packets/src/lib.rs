@@ -0,0 +1,163 @@
+//! The raw packets for ASUS ROG laptop keyboard LEDs, without dependencies.
+//! Every packet is written as is to the keyboard's hidraw device, except on
+//! TUF laptops which take [`tuf_effect_bytes`] through sysfs.
+#![no_std]
+
+/// The length of the effect, set, and apply packets
+pub const LED_MSG_LEN: usize = 17;
+
+// Only these two packets must be 17 bytes
+pub const LED_APPLY: [u8; LED_MSG_LEN] = [
+    0x5d, 0xb4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+pub const LED_SET: [u8; LED_MSG_LEN] = [
+    0x5d, 0xb5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+/// The handshake Armoury Crate sends when it takes the keyboard, "ASUS
+/// Tech.Inc." on the LED report IDs. Used to bring back a controller which
+/// stopped responding, such as after a bad resume.
+pub const LED_INIT: [&[u8]; 5] = [
+    &[
+        0x5d, 0xb9,
+    ],
+    b"]ASUS Tech.Inc.",
+    &[
+        0x5d, 0x05, 0x20, 0x31, 0, 0x08,
+    ],
+    b"^ASUS Tech.Inc.",
+    &[
+        0x5e, 0x05, 0x20, 0x31, 0, 0x08,
+    ],
+];
+
+/// Power states with every zone on, the base an Ally is reset to
+pub const LED_POWER_ALL_ON: [u8; 7] = power_packet([0xff; 4]);
+
+/// A builtin effect as the bytes the keyboard takes. rog-aura's `AuraEffect`
+/// converts to this
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectBytes {
+    /// 0 for the whole keyboard, 1-4 for the keyboard zones, 5 the logo, 6 and
+    /// 7 the left and right lightbar
+    pub zone: u8,
+    pub mode: u8,
+    pub colour1: [u8; 3],
+    /// 0xe1 low, 0xeb medium, 0xf5 high
+    pub speed: u8,
+    /// 0 right, 1 left, 2 up, 3 down
+    pub direction: u8,
+    pub colour2: [u8; 3],
+}
+
+impl Default for EffectBytes {
+    /// Static red at medium speed on the whole keyboard
+    fn default() -> Self {
+        Self {
+            zone: 0,
+            mode: 0,
+            colour1: [
+                0xa6, 0, 0,
+            ],
+            speed: 0xeb,
+            direction: 0,
+            colour2: [0; 3],
+        }
+    }
+}
+
+/// The packet which sets an effect
+///
+/// Byte structure where colour is RGB, one byte per R, G, B:
+/// ```ignore
+/// | 0 | 1 | 2   | 3   | 4, 5, 6 | 7    | 8        | 9 | 10, 11, 12|
+/// |---|---|-----|-----|---------|------|----------|---|-----------|
+/// |5d |b3 |Zone |Mode |Colour 1 |Speed |Direction |00 |Colour 2   |
+/// ```
+pub const fn effect_packet(effect: &EffectBytes) -> [u8; LED_MSG_LEN] {
+    let mut msg = [0u8; LED_MSG_LEN];
+    msg[0] = 0x5d;
+    msg[1] = 0xb3;
+    msg[2] = effect.zone;
+    msg[3] = effect.mode;
+    msg[4] = effect.colour1[0];
+    msg[5] = effect.colour1[1];
+    msg[6] = effect.colour1[2];
+    msg[7] = effect.speed;
+    msg[8] = effect.direction;
+    msg[10] = effect.colour2[0];
+    msg[11] = effect.colour2[1];
+    msg[12] = effect.colour2[2];
+    msg
+}
+
+/// The packets which set an effect, in the order they are written. The last
+/// one keeps the effect after a reboot.
+pub const fn effect_packets(effect: &EffectBytes) -> [[u8; LED_MSG_LEN]; 3] {
+    [
+        effect_packet(effect),
+        LED_SET,
+        LED_APPLY,
+    ]
+}
+
+/// The bytes a TUF laptop takes for an effect through the `kbd_rgb_mode`
+/// sysfs attribute. TUF keyboards have no zones or second colour
+pub const fn tuf_effect_bytes(effect: &EffectBytes) -> [u8; 6] {
+    [
+        1, effect.mode, effect.colour1[0], effect.colour1[1], effect.colour1[2], effect.speed,
+    ]
+}
+
+/// The packet which sets the power states, from the four bytes of power state
+/// bits. rog-aura's `LaptopAuraPower::to_bytes` makes these bytes for each
+/// keyboard type
+pub const fn power_packet(bits: [u8; 4]) -> [u8; 7] {
+    [
+        0x5d, 0xbd, 0x01, bits[0], bits[1], bits[2], bits[3],
+    ]
+}
+
+/// The packet which sets the power states of an Ally, from its single byte of
+/// power state bits
+pub const fn ally_power_packet(bits: u8) -> [u8; 7] {
+    [
+        0x5d, 0xd1, 0x09, 0x01, bits, 0, 0,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effect() {
+        let effect = EffectBytes {
+            zone: 1,
+            mode: 1,
+            colour1: [
+                0xff, 0x11, 0xdd,
+            ],
+            speed: 0xe1,
+            direction: 2,
+            colour2: [
+                0xa6, 0, 0x01,
+            ],
+        };
+        assert_eq!(effect_packet(&effect), [
+            0x5d, 0xb3, 0x01, 0x01, 0xff, 0x11, 0xdd, 0xe1, 0x02, 0x0, 0xa6, 0x0, 0x01, 0x0, 0x0,
+            0x0, 0x0,
+        ]);
+        let packets = effect_packets(&effect);
+        assert_eq!(packets[1], LED_SET);
+        assert_eq!(packets[2], LED_APPLY);
+        assert_eq!(tuf_effect_bytes(&effect), [1, 1, 0xff, 0x11, 0xdd, 0xe1]);
+    }
+
+    #[test]
+    fn power() {
+        assert_eq!(power_packet([1, 2, 3, 4]), [0x5d, 0xbd, 0x01, 1, 2, 3, 4]);
+        assert_eq!(LED_POWER_ALL_ON[3..], [0xff; 4]);
+        assert_eq!(ally_power_packet(0x0f)[4], 0x0f);
+    }
+}
@@ -4,13 +4,68 @@ use dmi_id::DMIID;
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
 use crate::keyboard::AdvancedAuraType;
-use crate::{AuraModeNum, AuraZone, PowerZones};
+use crate::{AuraEffect, AuraModeNum, AuraZone, PowerZones};
 
 pub const ASUS_LED_MODE_CONF: &str = "/usr/share/asusd/aura_support.ron";
 pub const ASUS_LED_MODE_USER_CONF: &str = "/etc/asusd/asusd_user_ledmodes.ron";
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+/// Maps the brightness shown to the user, in percent, to the raw level of
+/// the controller. Perceived brightness is not linear in the light output, so
+/// the levels are spread along a gamma curve: 50% is half as bright to the
+/// eye, not half the light.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BrightnessCurve {
+    /// 1.0 is linear. Most keyboards look right around 2.2, lower it for
+    /// controllers whose low levels are already dim.
+    pub gamma: f32,
+    /// The highest raw level, if it differs from what the driver reports
+    pub max_level: Option<u8>,
+}
+
+impl Default for BrightnessCurve {
+    fn default() -> Self {
+        Self {
+            gamma: 2.2,
+            max_level: None,
+        }
+    }
+}
+
+impl BrightnessCurve {
+    fn gamma(&self) -> f32 {
+        if self.gamma.is_finite() && self.gamma > 0.0 {
+            self.gamma
+        } else {
+            1.0
+        }
+    }
+
+    /// The raw level for `percent`. Any non-zero percentage is at least the
+    /// lowest level so the lights never turn off until the slider is at 0.
+    pub fn level(&self, percent: u8, max_level: u8) -> u8 {
+        let max_level = self.max_level.unwrap_or(max_level);
+        if percent == 0 || max_level == 0 {
+            return 0;
+        }
+        let light = (percent.min(100) as f32 / 100.0).powf(self.gamma());
+        ((light * max_level as f32).round() as u8).clamp(1, max_level)
+    }
+
+    /// The percentage shown for a raw level, the inverse of [`Self::level`]
+    pub fn percent(&self, level: u8, max_level: u8) -> u8 {
+        let max_level = self.max_level.unwrap_or(max_level);
+        if max_level == 0 {
+            return 0;
+        }
+        let light = level.min(max_level) as f32 / max_level as f32;
+        (light.powf(1.0 / self.gamma()) * 100.0).round() as u8
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct LedSupportData {
     /// This can be many different types of name:
     /// - `/sys/class/dmi/id/board_name` (must use for laptops)
@@ -55,6 +110,14 @@ pub struct LedSupportData {
     pub advanced_type: AdvancedAuraType,
     /// If empty will default to `Keyboard` power zone
     pub power_zones: Vec<PowerZones>,
+    /// Override of the default perceptual brightness curve
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness_curve: Option<BrightnessCurve>,
+    /// The `basic_zones` can each have a different builtin mode, as on some
+    /// 2022 and later 4-zone keyboards. Otherwise the zones differ only in
+    /// colour and every zone shows the same mode
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub per_zone_modes: bool,
 }
 
 impl LedSupportData {
@@ -77,9 +140,49 @@ impl LedSupportData {
         data.power_zones.push(PowerZones::Keyboard);
         data
     }
+
+    /// Check that `effects` can be set together on the zones: each is for a
+    /// different zone in `basic_zones` with a mode in `basic_modes`, and they
+    /// have the same mode unless `per_zone_modes` is set
+    pub fn check_zone_effects(&self, effects: &[AuraEffect]) -> Result<(), Error> {
+        if effects.is_empty() {
+            return Err(Error::NotSupported("No zone effects were given".into()));
+        }
+        for (i, effect) in effects.iter().enumerate() {
+            if !self.basic_zones.contains(&effect.zone) {
+                return Err(Error::NotSupported(format!(
+                    "The keyboard has no zone {:?}",
+                    effect.zone
+                )));
+            }
+            if !self.basic_modes.contains(&effect.mode) {
+                return Err(Error::NotSupported(format!(
+                    "The Aura mode is not supported: {:?}",
+                    effect.mode
+                )));
+            }
+            if effects[..i].iter().any(|fx| fx.zone == effect.zone) {
+                return Err(Error::NotSupported(format!(
+                    "The zone {:?} was given twice",
+                    effect.zone
+                )));
+            }
+        }
+        if !self.per_zone_modes && effects.iter().any(|fx| fx.mode != effects[0].mode) {
+            return Err(Error::NotSupported(
+                "The keyboard zones can't have different modes".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The brightness curve from the support data, or the default
+    pub fn brightness_curve(&self) -> BrightnessCurve {
+        self.brightness_curve.unwrap_or_default()
+    }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 pub struct LedSupportFile(Vec<LedSupportData>);
 
 impl LedSupportFile {
@@ -117,6 +220,8 @@ impl LedSupportFile {
             basic_zones: vec![],
             advanced_type: AdvancedAuraType::None,
             power_zones: vec![PowerZones::Keyboard],
+            brightness_curve: None,
+            per_zone_modes: false,
         }
     }
 
@@ -178,11 +283,11 @@ mod tests {
 
     use ron::ser::PrettyConfig;
 
-    use super::LedSupportData;
+    use super::{BrightnessCurve, LedSupportData};
     use crate::aura_detection::{LedSupportFile, PowerZones};
     use crate::keyboard::{AdvancedAuraType, LedCode};
     // use crate::zoned::Zone;
-    use crate::{AuraModeNum, AuraZone};
+    use crate::{AuraEffect, AuraModeNum, AuraZone};
 
     #[test]
     fn check_data_parse() {
@@ -201,12 +306,87 @@ mod tests {
                 PowerZones::Keyboard,
                 PowerZones::RearGlow,
             ],
+            brightness_curve: None,
+            per_zone_modes: false,
         };
 
         assert!(ron::to_string(&led).is_ok());
         // assert_eq!(json, String::new());
     }
 
+    #[test]
+    fn check_zone_effects() {
+        let mut led = LedSupportData {
+            basic_modes: vec![
+                AuraModeNum::Static,
+                AuraModeNum::Breathe,
+            ],
+            basic_zones: vec![
+                AuraZone::Key1,
+                AuraZone::Key2,
+            ],
+            ..Default::default()
+        };
+        let effect = |zone, mode| AuraEffect {
+            zone,
+            mode,
+            ..Default::default()
+        };
+
+        let same = [
+            effect(AuraZone::Key1, AuraModeNum::Breathe),
+            effect(AuraZone::Key2, AuraModeNum::Breathe),
+        ];
+        let mixed = [
+            effect(AuraZone::Key1, AuraModeNum::Static),
+            effect(AuraZone::Key2, AuraModeNum::Breathe),
+        ];
+        assert!(led.check_zone_effects(&same).is_ok());
+        assert!(led.check_zone_effects(&mixed).is_err());
+        led.per_zone_modes = true;
+        assert!(led.check_zone_effects(&mixed).is_ok());
+
+        assert!(led.check_zone_effects(&[]).is_err());
+        assert!(led
+            .check_zone_effects(&[effect(
+                AuraZone::Logo,
+                AuraModeNum::Static
+            )])
+            .is_err());
+        assert!(led
+            .check_zone_effects(&[effect(
+                AuraZone::Key1,
+                AuraModeNum::Pulse
+            )])
+            .is_err());
+        assert!(led
+            .check_zone_effects(&[
+                effect(AuraZone::Key1, AuraModeNum::Static),
+                effect(AuraZone::Key1, AuraModeNum::Static)
+            ])
+            .is_err());
+    }
+
+    #[test]
+    fn brightness_curve() {
+        let curve = BrightnessCurve::default();
+        assert_eq!(curve.level(0, 3), 0);
+        assert_eq!(curve.level(1, 3), 1);
+        assert_eq!(curve.level(50, 3), 1);
+        assert_eq!(curve.level(80, 3), 2);
+        assert_eq!(curve.level(100, 3), 3);
+        for level in 0..=3 {
+            assert_eq!(curve.level(curve.percent(level, 3), 3), level);
+        }
+
+        let linear = BrightnessCurve {
+            gamma: 1.0,
+            max_level: Some(100),
+        };
+        assert_eq!(linear.level(50, 3), 50);
+        assert_eq!(linear.percent(25, 3), 25);
+    }
+
     #[test]
     fn check_data_file_parse() {
         let mut data = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -1,6 +1,7 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
+use rog_aura_packets::{effect_packet, EffectBytes};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "dbus")]
 use zbus::zvariant::{OwnedValue, Type, Value};
@@ -77,6 +78,95 @@ impl From<i32> for LedBrightness {
     }
 }
 
+/// The keyboard brightness set when asusd starts and after resume
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(
+    feature = "dbus",
+    derive(Type, Value, OwnedValue),
+    zvariant(signature = "u")
+)]
+pub enum BrightnessRestore {
+    /// The brightness last set through asusd
+    Saved = 0,
+    /// Always off
+    Off = 1,
+    /// The brightness before suspend or shutdown, including changes made
+    /// with the brightness keys
+    #[default]
+    Hardware = 2,
+}
+
+/// Give the current mode a new random colour when asusd starts, after resume,
+/// and optionally on an interval
+#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuraShuffle {
+    pub enabled: bool,
+    /// Also pick a new colour every `interval_secs`, 0 for only on start and
+    /// resume
+    pub interval_secs: u32,
+    /// The colours to pick from, any colour if empty
+    pub palette: Vec<Colour>,
+}
+
+/// Show `effect` in place of the current mode while on battery with the charge
+/// at or below `threshold` percent. The current mode is put back once charging
+/// or above the threshold again.
+#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuraLowBattery {
+    pub enabled: bool,
+    pub threshold: u8,
+    pub effect: AuraEffect,
+}
+
+impl Default for AuraLowBattery {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 15,
+            effect: AuraEffect {
+                mode: AuraModeNum::Breathe,
+                colour1: Colour { r: 255, g: 0, b: 0 },
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl AuraLowBattery {
+    /// If the warning should be shown for this power state
+    pub fn should_warn(&self, on_ac: bool, capacity: u8) -> bool {
+        self.enabled && !on_ac && capacity <= self.threshold
+    }
+}
+
+/// The mode, colour, and brightness set when the platform profile changes to
+/// the profile this is bound to. It becomes the saved mode, so it stays after
+/// changing to a profile without one.
+#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuraProfileLighting {
+    pub enabled: bool,
+    pub effect: AuraEffect,
+    pub brightness: LedBrightness,
+}
+
+/// A step of the lighting schedule, setting `lighting` when `when` comes
+/// around. `when` is a local time such as `18:30`, optionally after the days
+/// it applies on such as `mon-fri 09:00` or `sat,sun 10:00`, or an event:
+/// `@ac` or `@battery` for when the laptop changes to that power source.
+#[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AuraScheduleStep {
+    pub when: String,
+    pub lighting: AuraProfileLighting,
+}
+
 #[cfg_attr(feature = "dbus", derive(Type, Value, OwnedValue))]
 #[derive(Debug, Clone, PartialEq, Eq, Copy, Deserialize, Serialize)]
 pub struct Colour {
@@ -365,7 +455,9 @@ impl From<AuraEffect> for AuraModeNum {
     derive(Type, Value, OwnedValue),
     zvariant(signature = "u")
 )]
-#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
 pub enum AuraZone {
     /// Used if keyboard has no zones, or if setting all
     #[default]
@@ -471,6 +563,22 @@ impl AuraEffect {
     pub fn zone(&self) -> AuraZone {
         self.zone
     }
+
+    /// A copy with both colours dimmed to `percent` of their brightness. Modes
+    /// which make their own colours, such as rainbow, are not dimmed.
+    pub fn scaled(&self, percent: u8) -> Self {
+        let percent = percent.min(100) as u16;
+        let scale = |c: Colour| Colour {
+            r: (c.r as u16 * percent / 100) as u8,
+            g: (c.g as u16 * percent / 100) as u8,
+            b: (c.b as u16 * percent / 100) as u8,
+        };
+        Self {
+            colour1: scale(self.colour1),
+            colour2: scale(self.colour2),
+            ..self.clone()
+        }
+    }
 }
 
 impl Default for AuraEffect {
@@ -492,56 +600,39 @@ impl Display for AuraEffect {
     }
 }
 
-/// Parses `AuraEffect` in to packet data for writing to the USB interface
-///
-/// Byte structure where colour is RGB, one byte per R, G, B:
-/// ```ignore
-/// | 0 | 1 | 2   | 3   | 4, 5, 6 | 7    | 8        | 9 | 10, 11, 12|
-/// |---|---|-----|-----|---------|------|----------|---|-----------|
-/// |5d |b3 |Zone |Mode |Colour 1 |Speed |Direction |00 |Colour 2   |
-/// ```
+/// The bytes of an effect for [`rog_aura_packets`]
+impl From<&AuraEffect> for EffectBytes {
+    fn from(aura: &AuraEffect) -> Self {
+        Self {
+            zone: aura.zone as u8,
+            mode: aura.mode as u8,
+            colour1: aura.colour1.into(),
+            speed: aura.speed as u8,
+            direction: aura.direction as u8,
+            colour2: aura.colour2.into(),
+        }
+    }
+}
+
+/// Parses `AuraEffect` in to packet data for writing to the USB interface,
+/// see [`rog_aura_packets::effect_packet`] for the bytes
 impl From<&AuraEffect> for [u8; AURA_LAPTOP_LED_MSG_LEN] {
     fn from(aura: &AuraEffect) -> Self {
-        let mut msg = [0u8; AURA_LAPTOP_LED_MSG_LEN];
-        msg[0] = 0x5d;
-        msg[1] = 0xb3;
-        msg[2] = aura.zone as u8;
-        msg[3] = aura.mode as u8;
-        msg[4] = aura.colour1.r;
-        msg[5] = aura.colour1.g;
-        msg[6] = aura.colour1.b;
-        msg[7] = aura.speed as u8;
-        msg[8] = aura.direction as u8;
-        msg[10] = aura.colour2.r;
-        msg[11] = aura.colour2.g;
-        msg[12] = aura.colour2.b;
-        msg
+        effect_packet(&aura.into())
     }
 }
 
 impl From<&AuraEffect> for Vec<u8> {
     fn from(aura: &AuraEffect) -> Self {
-        let mut msg = vec![0u8; AURA_LAPTOP_LED_MSG_LEN];
-        msg[0] = 0x5d;
-        msg[1] = 0xb3;
-        msg[2] = aura.zone as u8;
-        msg[3] = aura.mode as u8;
-        msg[4] = aura.colour1.r;
-        msg[5] = aura.colour1.g;
-        msg[6] = aura.colour1.b;
-        msg[7] = aura.speed as u8;
-        msg[8] = aura.direction as u8;
-        msg[10] = aura.colour2.r;
-        msg[11] = aura.colour2.g;
-        msg[12] = aura.colour2.b;
-        msg
+        <[u8; AURA_LAPTOP_LED_MSG_LEN]>::from(aura).to_vec()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        AuraEffect, AuraModeNum, AuraZone, Colour, Direction, Speed, AURA_LAPTOP_LED_MSG_LEN,
+        AuraEffect, AuraLowBattery, AuraModeNum, AuraZone, Colour, Direction, Speed,
+        AURA_LAPTOP_LED_MSG_LEN,
     };
 
     #[test]
@@ -691,4 +782,44 @@ mod tests {
             capture[..9]
         );
     }
+
+    #[test]
+    fn low_battery_warning() {
+        let mut low = AuraLowBattery::default();
+        assert!(!low.should_warn(false, 5));
+
+        low.enabled = true;
+        assert!(low.should_warn(false, 15));
+        assert!(low.should_warn(false, 0));
+        assert!(!low.should_warn(false, 16));
+        assert!(!low.should_warn(true, 5));
+    }
+
+    #[test]
+    fn scaled_effect() {
+        let effect = AuraEffect {
+            colour1: Colour {
+                r: 0xff,
+                g: 0x80,
+                b: 0,
+            },
+            colour2: Colour {
+                r: 10,
+                g: 20,
+                b: 30,
+            },
+            ..Default::default()
+        };
+        let half = effect.scaled(50);
+        assert_eq!(half.colour1, Colour {
+            r: 0x7f,
+            g: 0x40,
+            b: 0
+        });
+        assert_eq!(half.colour2, Colour { r: 5, g: 10, b: 15 });
+        assert_eq!(half.mode, effect.mode);
+        assert_eq!(effect.scaled(100), effect);
+        assert_eq!(effect.scaled(200), effect);
+        assert_eq!(effect.scaled(0).colour1, Colour { r: 0, g: 0, b: 0 });
+    }
 }
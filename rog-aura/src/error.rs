@@ -9,6 +9,7 @@ pub enum Error {
     IoPath(String, std::io::Error),
     Ron(ron::Error),
     RonParse(ron::error::SpannedError),
+    NotSupported(String),
 }
 
 impl fmt::Display for Error {
@@ -22,6 +23,7 @@ impl fmt::Display for Error {
             Error::IoPath(path, io) => write!(f, "IO Error: {path}, {io}"),
             Error::Ron(e) => write!(f, "RON Parse Error: {e}"),
             Error::RonParse(e) => write!(f, "RON Parse Error: {e}"),
+            Error::NotSupported(e) => write!(f, "{e}"),
         }
     }
 }
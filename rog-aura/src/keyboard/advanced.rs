@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "dbus")]
 use zbus::zvariant::Type;
 
+use crate::Colour;
+
 // TODO: GZ301Z
 // 5dbcd0010300000a00ff0000ff0000ff0000ff0000ff0000ff0000ff0000ff0000ff0000ff0000
 
@@ -288,6 +290,20 @@ impl LedUsbPackets {
         init
     }
 
+    /// Wrap packets such as those given to `DirectAddressingRaw`, which are
+    /// zoned if the first has the zoned starting bytes
+    pub fn from_packets(usb_packets: AuraLaptopUsbPackets) -> Self {
+        let zoned = usb_packets
+            .first()
+            .is_some_and(|row| row.get(2) == Some(&0x01));
+        Self { usb_packets, zoned }
+    }
+
+    /// If the packets are for a zoned keyboard
+    pub fn zoned(&self) -> bool {
+        self.zoned
+    }
+
     /// Set the RGB colour of an `LedCode`
     #[inline]
     pub fn set(&mut self, key: LedCode, r: u8, g: u8, b: u8) {
@@ -298,12 +314,31 @@ impl LedUsbPackets {
         }
     }
 
+    /// The RGB colour of an `LedCode`, if it has one in these packets
+    pub fn colour(&self, key: LedCode) -> Option<Colour> {
+        let (row, col) = self.index_of(key)?;
+        match self.usb_packets.get(row)?.get(col..=col + 2)? {
+            [r, g, b] => Some(Colour {
+                r: *r,
+                g: *g,
+                b: *b,
+            }),
+            _ => None,
+        }
+    }
+
     /// Indexes in to `UsbPackets` at the correct row and column
     /// to set a series of three bytes to the chosen R,G,B values
+    fn rgb_for_led_code(&mut self, led_code: LedCode) -> Option<&mut [u8]> {
+        let (row, col) = self.index_of(led_code)?;
+        self.usb_packets.get_mut(row)?.get_mut(col..=col + 2)
+    }
+
+    /// The row and column of the R byte of `led_code`
     ///
     /// Indexing is different for `zoned` and assumes that only one packet is
     /// generated for all the zones
-    fn rgb_for_led_code(&mut self, led_code: LedCode) -> Option<&mut [u8]> {
+    fn index_of(&self, led_code: LedCode) -> Option<(usize, usize)> {
         let zoned = self.zoned;
         // Tuples are indexes in to array
         #[allow(clippy::match_same_arms)]
@@ -465,7 +500,7 @@ impl LedUsbPackets {
             return None;
         }
 
-        Some(&mut self.usb_packets[row][col..=col + 2])
+        Some((row, col))
     }
 
     #[inline]
@@ -641,6 +676,7 @@ impl From<&LedCode> for &str {
 #[cfg(test)]
 mod tests {
     use crate::keyboard::{AuraLaptopUsbPackets, LedCode, LedUsbPackets};
+    use crate::Colour;
 
     macro_rules! colour_check_zoned {
         ($zone:expr, $pkt_idx_start:expr) => {
@@ -725,4 +761,25 @@ mod tests {
         assert_eq!(pkt[7][14], 0xff); // M
         assert_eq!(pkt[7][15], 0x00); // M
     }
+
+    #[test]
+    fn colours_from_packets() {
+        let mut per_key = LedUsbPackets::new_per_key();
+        per_key.set(LedCode::D, 1, 2, 3);
+        let per_key = LedUsbPackets::from_packets(per_key.into());
+        assert_eq!(
+            per_key.colour(LedCode::D),
+            Some(Colour { r: 1, g: 2, b: 3 })
+        );
+        assert_eq!(per_key.colour(LedCode::LidLogo), None);
+
+        let mut zoned = LedUsbPackets::new_zoned(true);
+        zoned.set(LedCode::LightbarLeft, 4, 5, 6);
+        let zoned = LedUsbPackets::from_packets(zoned.into());
+        assert_eq!(
+            zoned.colour(LedCode::LightbarLeft),
+            Some(Colour { r: 4, g: 5, b: 6 })
+        );
+        assert_eq!(zoned.colour(LedCode::D), None);
+    }
 }
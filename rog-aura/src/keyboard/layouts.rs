@@ -4,9 +4,14 @@
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::slice::Iter;
+use std::time::SystemTime;
 
-use log::warn;
+use log::{info, warn};
+use serde::de::value::StrDeserializer;
+use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "dbus")]
+use zbus::zvariant::Type;
 
 use crate::aura_detection::LedSupportData;
 use crate::error::Error;
@@ -188,8 +193,13 @@ pub struct KeyLayout {
     advanced_type: AdvancedAuraType,
 }
 
+/// Set to a directory of `<layout_name>_US.ron` files to use them in place of
+/// the installed layouts. This is for writing layouts for unsupported models
+/// without rebuilding or reinstalling.
+pub const LAYOUT_DIR_ENV: &str = "ROG_AURA_LAYOUT_DIR";
+
 impl KeyLayout {
-    fn from_file(path: &Path) -> Result<Self, Error> {
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
         let buf: String = std::fs::read_to_string(path)
             .map_err(|e| Error::IoPath(path.to_string_lossy().to_string(), e))?;
         if buf.is_empty() {
@@ -277,8 +287,63 @@ impl KeyLayout {
         width
     }
 
+    /// Problems which would make the layout draw or address keys wrongly. An
+    /// empty list means the layout is fine.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.key_rows.is_empty() {
+            problems.push("The layout has no key rows".to_owned());
+        }
+
+        let mut unused: HashSet<&String> = self.key_shapes.keys().collect();
+        for (n, row) in self.key_rows.iter().enumerate() {
+            if row.row.is_empty() {
+                problems.push(format!("Row {n} has no keys"));
+            }
+            for (led, shape) in &row.row {
+                if self.key_shapes.contains_key(shape) {
+                    unused.remove(shape);
+                } else {
+                    problems.push(format!("Key {led:?} in row {n} uses missing shape {shape}"));
+                }
+            }
+        }
+        let mut unused: Vec<&String> = unused.into_iter().collect();
+        unused.sort();
+        for shape in unused {
+            problems.push(format!("Shape {shape} is not used"));
+        }
+
+        let mut shapes: Vec<(&String, &KeyShape)> = self.key_shapes.iter().collect();
+        shapes.sort_by_key(|(name, _)| *name);
+        for (name, shape) in shapes {
+            if let KeyShape::Led { width, height, .. } = shape {
+                if *width <= 0.0 || *height <= 0.0 {
+                    problems.push(format!("LED shape {name} has no width or height"));
+                }
+            }
+        }
+        problems
+    }
+
+    /// The path of the layout file for `layout_name`. A file in
+    /// `$ROG_AURA_LAYOUT_DIR` is used before the one in `data_path/layouts`.
+    pub fn layout_path(layout_name: &str, mut data_path: PathBuf) -> PathBuf {
+        let layout_file = format!("{layout_name}_US.ron");
+        if let Some(dir) = std::env::var_os(LAYOUT_DIR_ENV) {
+            let path = PathBuf::from(dir).join(&layout_file);
+            if path.exists() {
+                info!("Using development layout {path:?}");
+                return path;
+            }
+        }
+        data_path.push("layouts");
+        data_path.push(layout_file);
+        data_path
+    }
+
     /// Find a layout matching the name in `LaptopLedData` in the provided dir
-    pub fn find_layout(led_data: LedSupportData, mut data_path: PathBuf) -> Result<Self, Error> {
+    pub fn find_layout(led_data: LedSupportData, data_path: PathBuf) -> Result<Self, Error> {
         // TODO: locales
         let layout_name = if led_data.layout_name.is_empty() {
             "ga401q".to_owned() // Need some sort of default here due to ROGCC
@@ -286,12 +351,9 @@ impl KeyLayout {
         } else {
             led_data.layout_name
         };
-        let layout_file = format!("{layout_name}_US.ron");
-        data_path.push("layouts");
-        data_path.push(layout_file);
-        let path = data_path.as_path();
+        let path = Self::layout_path(&layout_name, data_path);
 
-        let mut tmp = KeyLayout::from_file(path)?;
+        let mut tmp = KeyLayout::from_file(&path)?;
         tmp.basic_modes = led_data.basic_modes;
         tmp.basic_zones = led_data.basic_zones;
         tmp.advanced_type = led_data.advanced_type;
@@ -319,6 +381,146 @@ impl KeyLayout {
     }
 }
 
+/// The position and size of one LED in a `LayoutInfo`, in the same units as
+/// the layout files where a typical key is 1.0 wide
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LayoutKey {
+    /// The `LedCode` name, such as `Esc` or `LightbarLeft`
+    pub led: String,
+    /// The `LedCode` as a number, its position in the `LedCode` enum
+    pub code: u32,
+    /// The row of the layout this key is in
+    pub row: u32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl LayoutKey {
+    /// Parse the `led` name back into a `LedCode`
+    pub fn led_code(&self) -> Option<LedCode> {
+        let de: StrDeserializer<'_, serde::de::value::Error> =
+            self.led.as_str().into_deserializer();
+        LedCode::deserialize(de).ok()
+    }
+}
+
+/// A `KeyLayout` flattened to absolute key positions, for clients which want
+/// to draw the keyboard without reading the layout files
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LayoutInfo {
+    /// The layout file name without the locale, such as `g513i-per-key`
+    pub name: String,
+    pub locale: String,
+    /// The LED names of each zone if the keyboard is zoned. Empty if it is
+    /// per-key or single zone
+    pub zones: Vec<String>,
+    pub per_key: bool,
+    pub width: f64,
+    pub height: f64,
+    pub keys: Vec<LayoutKey>,
+}
+
+impl KeyLayout {
+    /// Flatten the layout to absolute positions. Blank shapes and placeholder
+    /// keys are skipped.
+    pub fn layout_info(&self, name: &str) -> LayoutInfo {
+        let mut keys = Vec::new();
+        let mut y = 0.0;
+        for (row_num, row) in self.key_rows.iter().enumerate() {
+            y += row.pad_top;
+            let mut x = row.pad_left;
+            let mut row_height: f32 = 0.0;
+            for (led, shape_name) in &row.row {
+                let Some(shape) = self.key_shapes.get(shape_name) else {
+                    continue;
+                };
+                match shape {
+                    KeyShape::Led {
+                        width,
+                        height,
+                        pad_left,
+                        pad_right,
+                        pad_top,
+                        pad_bottom,
+                    } => {
+                        if !led.is_placeholder() {
+                            keys.push(LayoutKey {
+                                led: format!("{led:?}"),
+                                code: *led as u32,
+                                row: row_num as u32,
+                                x: (x + pad_left) as f64,
+                                y: (y + pad_top) as f64,
+                                width: *width as f64,
+                                height: *height as f64,
+                            });
+                        }
+                        x += pad_left + width + pad_right;
+                        row_height = row_height.max(pad_top + height + pad_bottom);
+                    }
+                    KeyShape::Blank { width, height } => {
+                        x += width;
+                        row_height = row_height.max(*height);
+                    }
+                }
+            }
+            y += row_height;
+        }
+
+        let zones = match &self.advanced_type {
+            AdvancedAuraType::Zoned(zones) => zones.iter().map(|z| format!("{z:?}")).collect(),
+            _ => Vec::new(),
+        };
+        LayoutInfo {
+            name: name.to_owned(),
+            locale: self.locale.clone(),
+            zones,
+            per_key: self.advanced_type == AdvancedAuraType::PerKey,
+            width: keys.iter().map(|k| k.x + k.width).fold(0.0, f64::max),
+            height: y as f64,
+            keys,
+        }
+    }
+}
+
+/// Reloads a layout file when it changes on disk, so that a layout can be
+/// edited while effects are running on it
+pub struct LayoutReloader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl LayoutReloader {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Self::modified(&path);
+        Self { path, modified }
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Returns the reloaded layout if the file changed since the last call.
+    /// The modes, zones and type are kept from `current` as they come from the
+    /// support data rather than the file.
+    pub fn poll(&mut self, current: &KeyLayout) -> Option<Result<KeyLayout, Error>> {
+        let modified = Self::modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(KeyLayout::from_file(&self.path).map(|mut layout| {
+            layout.basic_modes = current.basic_modes.clone();
+            layout.basic_zones = current.basic_zones.clone();
+            layout.advanced_type = current.advanced_type.clone();
+            layout
+        }))
+    }
+}
+
 impl KeyLayout {
     pub fn default_layout() -> Self {
         Self {
@@ -435,7 +637,7 @@ mod tests {
     use std::path::PathBuf;
 
     use crate::aura_detection::LedSupportFile;
-    use crate::keyboard::KeyLayout;
+    use crate::keyboard::{KeyLayout, KeyShape, LedCode};
 
     #[test]
     fn check_parse_all() {
@@ -539,4 +741,51 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn layout_info_positions() {
+        let info = KeyLayout::default_layout().layout_info("default");
+        assert_eq!(info.name, "default");
+        assert!(!info.per_key);
+        assert!(info.zones.is_empty());
+
+        let esc = &info.keys[0];
+        assert_eq!(esc.led, "Esc");
+        assert_eq!(esc.row, 0);
+        assert!((esc.x - 0.2).abs() < 0.001);
+        assert!((esc.y - 0.2).abs() < 0.001);
+
+        // Each key is 1.0 wide with 0.1 padding either side
+        let f1 = &info.keys[1];
+        assert_eq!(f1.led, "F1");
+        assert_eq!(f1.led_code(), Some(LedCode::F1));
+        assert_eq!(f1.code, LedCode::F1 as u32);
+        assert!((f1.x - 1.4).abs() < 0.001);
+
+        assert!(info.keys.iter().all(|k| k.x + k.width <= info.width));
+        assert!(info.keys.iter().all(|k| k.y + k.height <= info.height));
+    }
+
+    #[test]
+    fn validate_layouts() {
+        const DATA_DIR: &str = env!("CARGO_MANIFEST_DIR");
+        let mut data_path = PathBuf::from(DATA_DIR);
+        data_path.push("data");
+        data_path.push("layouts");
+        for p in fs::read_dir(&data_path).unwrap() {
+            let path = p.unwrap().path();
+            let layout = KeyLayout::from_file(&path).unwrap();
+            let problems = layout.validate();
+            assert!(problems.is_empty(), "{path:?}: {problems:?}");
+        }
+
+        let mut layout = KeyLayout::default_layout();
+        assert!(layout.validate().is_empty());
+        let flat = KeyShape::new_led(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        layout.key_shapes.insert("flat".to_owned(), flat);
+        assert_eq!(layout.validate(), [
+            "Shape flat is not used",
+            "LED shape flat has no width or height",
+        ]);
+    }
 }
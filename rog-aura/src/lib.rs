@@ -19,11 +19,18 @@ pub use builtin_modes::*;
 /// Helper for detecting what is available
 pub mod aura_detection;
 pub mod error;
+/// Translated names of the modes and power zones
+pub mod names;
+/// Colours saved by name
+pub mod palette;
 pub mod usb;
+/// The raw packets without rog-aura's types, for projects which can't take
+/// zbus or serde
+pub use rog_aura_packets as packets;
 
 pub mod keyboard;
 
-pub const AURA_LAPTOP_LED_MSG_LEN: usize = 17;
+pub const AURA_LAPTOP_LED_MSG_LEN: usize = rog_aura_packets::LED_MSG_LEN;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub const RED: Colour = Colour {
@@ -92,6 +99,11 @@ impl AuraDeviceType {
         *self == Self::LaptopKeyboard2021
     }
 
+    /// Any of the built-in laptop keyboards
+    pub fn is_laptop_keyboard(&self) -> bool {
+        self.is_old_laptop() || self.is_tuf_laptop() || self.is_new_laptop()
+    }
+
     pub fn is_ally(&self) -> bool {
         *self == Self::Ally
     }
@@ -99,6 +111,41 @@ impl AuraDeviceType {
     pub fn is_scsi(&self) -> bool {
         *self == Self::ScsiExtDisk
     }
+
+    /// The packet protocol revision the device takes. Laptop keyboards from
+    /// 2021 on take a longer power state packet than those before
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            Self::LaptopKeyboard2021 => "laptop-2021",
+            Self::LaptopKeyboardPre2021 => "laptop-pre-2021",
+            Self::LaptopKeyboardTuf => "tuf-sysfs",
+            Self::ScsiExtDisk => "scsi",
+            Self::Ally => "ally",
+            Self::AnimeOrSlash => "anime-slash",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// The identity of an Aura device, for triaging model specific issues. Any
+/// which could not be read, such as the USB IDs of a TUF keyboard, are empty
+#[cfg_attr(feature = "dbus", derive(Type))]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuraDeviceInfo {
+    /// USB `idVendor`, such as `0b05`
+    pub vendor_id: String,
+    /// USB `idProduct`, such as `19b6`
+    pub product_id: String,
+    pub manufacturer: String,
+    pub product: String,
+    /// The USB device release, which ASUS keyboards set to their firmware
+    /// version, such as `3.05`
+    pub firmware_version: String,
+    pub device_type: AuraDeviceType,
+    /// See [`AuraDeviceType::protocol`]
+    pub protocol: String,
+    /// The hidraw node, or the sysfs attribute for TUF keyboards
+    pub path: String,
 }
 
 impl From<&str> for AuraDeviceType {
@@ -0,0 +1,124 @@
+//! Display names of the builtin modes and power zones, one `<lang>.ron` file
+//! per language in `data/names`. The English names are built in and used for
+//! any name a language file doesn't have.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{AuraModeNum, PowerZones};
+
+/// Where the language files are installed
+pub const NAMES_DIR: &str = "/usr/share/rog-gui/names";
+/// Used in place of `NAMES_DIR` if set, for working on a language file
+pub const NAMES_DIR_ENV: &str = "ROG_AURA_NAMES_DIR";
+
+const ENGLISH: &str = include_str!("../data/names/en.ron");
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct AuraNames {
+    pub modes: HashMap<AuraModeNum, String>,
+    pub power_zones: HashMap<PowerZones, String>,
+}
+
+impl AuraNames {
+    pub fn english() -> Self {
+        ron::from_str(ENGLISH).unwrap_or_default()
+    }
+
+    /// The names for `lang`, such as `pt_BR`, from `dir`. Falls back to the
+    /// language without its region, such as `pt`, then to English for any
+    /// missing name.
+    pub fn load(dir: &Path, lang: &str) -> Self {
+        let mut names = Self::english();
+        let base = lang.split('_').next().unwrap_or(lang);
+        let path = [
+            lang, base,
+        ]
+        .iter()
+        .map(|l| dir.join(format!("{l}.ron")))
+        .find(|p| p.exists());
+        if let Some(path) = path {
+            match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|s| ron::from_str::<Self>(&s).map_err(|e| e.to_string()))
+            {
+                Ok(local) => {
+                    names.modes.extend(local.modes);
+                    names.power_zones.extend(local.power_zones);
+                }
+                Err(e) => warn!("Could not load Aura names from {path:?}: {e}"),
+            }
+        }
+        names
+    }
+
+    /// The names for the language of the current locale
+    pub fn for_locale() -> Self {
+        let dir = std::env::var_os(NAMES_DIR_ENV)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(NAMES_DIR));
+        Self::load(&dir, &locale_lang())
+    }
+
+    pub fn mode(&self, mode: AuraModeNum) -> String {
+        self.modes
+            .get(&mode)
+            .cloned()
+            .unwrap_or_else(|| mode.to_string())
+    }
+
+    pub fn power_zone(&self, zone: PowerZones) -> String {
+        self.power_zones
+            .get(&zone)
+            .cloned()
+            .unwrap_or_else(|| format!("{zone:?}"))
+    }
+}
+
+/// The language of the current locale from `LC_ALL`, `LC_MESSAGES`, or
+/// `LANG`, without the encoding, e.g. `pt_BR` from `pt_BR.UTF-8`
+pub fn locale_lang() -> String {
+    [
+        "LC_ALL", "LC_MESSAGES", "LANG",
+    ]
+    .iter()
+    .filter_map(|var| std::env::var(var).ok())
+    .find(|v| !v.is_empty())
+    .and_then(|v| v.split(['.', '@']).next().map(str::to_owned))
+    .unwrap_or_else(|| "en".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::AuraNames;
+    use crate::{AuraModeNum, PowerZones};
+
+    #[test]
+    fn names_files_parse() {
+        let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        dir.push("data/names");
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let file = std::fs::read_to_string(&path).unwrap();
+            assert!(
+                ron::from_str::<AuraNames>(&file).is_ok(),
+                "{path:?} did not parse"
+            );
+        }
+
+        let english = AuraNames::english();
+        assert_eq!(english.mode(AuraModeNum::RainbowCycle), "Strobe");
+        assert_eq!(english.power_zone(PowerZones::RearGlow), "Rear Glow");
+
+        let russian = AuraNames::load(&dir, "ru_RU");
+        assert_eq!(russian.mode(AuraModeNum::Static), "Статический");
+        let turkish = AuraNames::load(&dir, "tr");
+        assert_eq!(turkish.mode(AuraModeNum::Comet), "Comet");
+    }
+}
@@ -0,0 +1,153 @@
+//! Colours saved by the user under a name, stored in `palettes.ron` in the
+//! user's `rog` config dir and shared by asusctl and ROG Control Center. A
+//! colour is either on its own, used as `name`, or in a named set, used as
+//! `set.name`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::Colour;
+
+/// The file name in the user's `rog` config dir
+pub const PALETTE_FILE: &str = "palettes.ron";
+
+/// Named colours, and sets of named colours
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Palettes {
+    pub colours: BTreeMap<String, Colour>,
+    pub sets: BTreeMap<String, BTreeMap<String, Colour>>,
+}
+
+impl Palettes {
+    /// Read the palettes from `path`, empty if there is no file yet
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(data) => Ok(ron::from_str(&data)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::IoPath(path.to_string_lossy().into(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| Error::IoPath(dir.to_string_lossy().into(), e))?;
+        }
+        let data = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        std::fs::write(path, data).map_err(|e| Error::IoPath(path.to_string_lossy().into(), e))
+    }
+
+    /// The colour saved as `name` or `set.name`
+    pub fn get(&self, name: &str) -> Option<Colour> {
+        match name.split_once('.') {
+            Some((set, name)) => self.sets.get(set)?.get(name).copied(),
+            None => self.colours.get(name).copied(),
+        }
+    }
+
+    /// Save `colour` as `name` or `set.name`, replacing any colour of that
+    /// name
+    pub fn insert(&mut self, name: &str, colour: Colour) {
+        match name.split_once('.') {
+            Some((set, name)) => {
+                self.sets
+                    .entry(set.to_owned())
+                    .or_default()
+                    .insert(name.to_owned(), colour);
+            }
+            None => {
+                self.colours.insert(name.to_owned(), colour);
+            }
+        }
+    }
+
+    /// Remove the colour saved as `name` or `set.name`, or a whole set by its
+    /// name. `false` if there was nothing of that name
+    pub fn remove(&mut self, name: &str) -> bool {
+        match name.split_once('.') {
+            Some((set, name)) => {
+                let Some(colours) = self.sets.get_mut(set) else {
+                    return false;
+                };
+                let removed = colours.remove(name).is_some();
+                if colours.is_empty() {
+                    self.sets.remove(set);
+                }
+                removed
+            }
+            None => self.colours.remove(name).is_some() || self.sets.remove(name).is_some(),
+        }
+    }
+
+    /// Every saved colour with its full name, sets after the single colours
+    pub fn entries(&self) -> Vec<(String, Colour)> {
+        let mut entries: Vec<(String, Colour)> = self
+            .colours
+            .iter()
+            .map(|(name, colour)| (name.clone(), *colour))
+            .collect();
+        for (set, colours) in &self.sets {
+            entries.extend(
+                colours
+                    .iter()
+                    .map(|(name, colour)| (format!("{set}.{name}"), *colour)),
+            );
+        }
+        entries
+    }
+
+    /// A colour given as hex, such as `ff00ff`, or by a saved name
+    pub fn parse_colour(&self, s: &str) -> Result<Colour, Error> {
+        let hex = s.trim_start_matches('#');
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Colour::from_str(hex);
+        }
+        self.get(s).ok_or(Error::ParseColour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Palettes;
+    use crate::Colour;
+
+    #[test]
+    fn names_and_sets() {
+        let accent = Colour {
+            r: 0xff,
+            g: 0x00,
+            b: 0x80,
+        };
+        let red = Colour {
+            r: 0xff,
+            g: 0x00,
+            b: 0x00,
+        };
+        let mut palettes = Palettes::default();
+        palettes.insert("mine.accent", accent);
+        palettes.insert("warning", red);
+
+        assert_eq!(palettes.get("mine.accent"), Some(accent));
+        assert_eq!(palettes.get("warning"), Some(red));
+        assert_eq!(palettes.get("mine.other"), None);
+        assert_eq!(palettes.parse_colour("mine.accent").unwrap(), accent);
+        assert_eq!(palettes.parse_colour("#ff0000").unwrap(), red);
+        assert!(palettes.parse_colour("missing").is_err());
+        assert_eq!(palettes.entries(), vec![
+            ("warning".to_owned(), red),
+            ("mine.accent".to_owned(), accent),
+        ]);
+
+        assert!(palettes.remove("mine.accent"));
+        assert!(palettes.sets.is_empty());
+        assert!(!palettes.remove("mine.accent"));
+
+        let ron = ron::to_string(&palettes).unwrap();
+        assert_eq!(ron::from_str::<Palettes>(&ron).unwrap(), palettes);
+    }
+}
@@ -1,7 +1,54 @@
-// Only these two packets must be 17 bytes
-pub const AURA_LAPTOP_LED_APPLY: [u8; 17] = [
-    0x5d, 0xb4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
-pub const AURA_LAPTOP_LED_SET: [u8; 17] = [
-    0x5d, 0xb5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-];
+//! The laptop keyboard packets for rog-aura's types. The bytes are built by
+//! the dependency free `rog_aura_packets` crate, re-exported as
+//! [`crate::packets`].
+
+use rog_aura_packets::{effect_packets, power_packet, tuf_effect_bytes};
+pub use rog_aura_packets::{
+    LED_APPLY as AURA_LAPTOP_LED_APPLY, LED_INIT as AURA_LAPTOP_LED_INIT,
+    LED_SET as AURA_LAPTOP_LED_SET,
+};
+
+use crate::keyboard::LaptopAuraPower;
+use crate::{AuraDeviceType, AuraEffect, AURA_LAPTOP_LED_MSG_LEN};
+
+/// The packets which set `effect` on a laptop keyboard, in the order they are
+/// written. The last one keeps the effect after a reboot.
+pub fn laptop_effect_packets(effect: &AuraEffect) -> [[u8; AURA_LAPTOP_LED_MSG_LEN]; 3] {
+    effect_packets(&effect.into())
+}
+
+/// The bytes which set `effect` on a TUF laptop through sysfs
+pub fn tuf_effect(effect: &AuraEffect) -> [u8; 6] {
+    tuf_effect_bytes(&effect.into())
+}
+
+/// The packet which sets the power states of a laptop keyboard. Not for TUF
+/// laptops, which take the bytes of [`LaptopAuraPower::to_bytes`] through
+/// sysfs.
+pub fn laptop_power_packet(power: &LaptopAuraPower, aura_type: AuraDeviceType) -> Vec<u8> {
+    let bytes = power.to_bytes(aura_type);
+    power_packet([
+        bytes[0], bytes[1], bytes[2], bytes[3],
+    ])
+    .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::laptop_power_packet;
+    use crate::keyboard::{AuraPowerState, LaptopAuraPower};
+    use crate::AuraDeviceType;
+
+    #[test]
+    fn power_packet_len() {
+        let power = LaptopAuraPower {
+            states: vec![AuraPowerState::default()],
+        };
+        for dev_type in [
+            AuraDeviceType::LaptopKeyboard2021,
+            AuraDeviceType::LaptopKeyboardPre2021,
+        ] {
+            assert_eq!(laptop_power_packet(&power, dev_type).len(), 7);
+        }
+    }
+}
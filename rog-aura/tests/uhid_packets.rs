@@ -0,0 +1,109 @@
+//! Checks the exact packets sent to laptop keyboards by writing them to a
+//! virtual keyboard made with uhid. Needs write access to `/dev/uhid`, run
+//! with `sudo -E cargo test -p rog_aura --test uhid_packets -- --ignored`.
+
+#[cfg(test)]
+mod tests {
+    use rog_aura::keyboard::{AuraPowerState, LaptopAuraPower};
+    use rog_aura::usb::{laptop_effect_packets, laptop_power_packet};
+    use rog_aura::{AuraDeviceType, AuraEffect, AuraModeNum, PowerZones, AURA_LAPTOP_LED_MSG_LEN};
+    use rog_platform::hid_raw::HidRaw;
+    use rog_platform::uhid::{UhidDevice, AURA_REPORT_DESCRIPTOR};
+
+    const MODES: [AuraModeNum; 12] = [
+        AuraModeNum::Static,
+        AuraModeNum::Breathe,
+        AuraModeNum::RainbowCycle,
+        AuraModeNum::RainbowWave,
+        AuraModeNum::Star,
+        AuraModeNum::Rain,
+        AuraModeNum::Highlight,
+        AuraModeNum::Laser,
+        AuraModeNum::Ripple,
+        AuraModeNum::Pulse,
+        AuraModeNum::Comet,
+        AuraModeNum::Flash,
+    ];
+
+    /// The product ID of each USB laptop keyboard type
+    const KEYBOARDS: [(u32, AuraDeviceType); 2] = [
+        (0x19b6, AuraDeviceType::LaptopKeyboard2021),
+        (0x1866, AuraDeviceType::LaptopKeyboardPre2021),
+    ];
+
+    fn keyboard(product: u32) -> (UhidDevice, HidRaw) {
+        let name = format!("rog-aura test {product:04x} {}", std::process::id());
+        let uhid = UhidDevice::create(&name, 0x0b05, product, AURA_REPORT_DESCRIPTOR).unwrap();
+        let node = uhid.hidraw_node().unwrap();
+        let hidraw = HidRaw::from_dev_node(&node, &format!("{product:04x}")).unwrap();
+        (uhid, hidraw)
+    }
+
+    /// A full length laptop packet starting with `start`
+    fn packet(start: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0u8; AURA_LAPTOP_LED_MSG_LEN];
+        packet[..start.len()].copy_from_slice(start);
+        packet
+    }
+
+    #[test]
+    #[ignore = "Needs write access to /dev/uhid"]
+    fn builtin_mode_packets() {
+        for (product, dev_type) in KEYBOARDS {
+            let (mut uhid, hidraw) = keyboard(product);
+            for mode in MODES {
+                let effect = AuraEffect::default_with_mode(mode);
+                for packet in laptop_effect_packets(&effect) {
+                    hidraw.write_bytes(&packet).unwrap();
+                }
+
+                let expected = [
+                    packet(&[
+                        0x5d, 0xb3, 0x00, mode as u8, 0xa6, 0x00, 0x00, 0xeb, 0x00,
+                    ]),
+                    packet(&[
+                        0x5d, 0xb5,
+                    ]),
+                    packet(&[
+                        0x5d, 0xb4,
+                    ]),
+                ];
+                for expected in expected {
+                    assert_eq!(
+                        uhid.read_output().unwrap(),
+                        expected,
+                        "{dev_type:?} {mode:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "Needs write access to /dev/uhid"]
+    fn power_state_packets() {
+        let power = LaptopAuraPower {
+            states: vec![
+                AuraPowerState {
+                    zone: PowerZones::Keyboard,
+                    ..Default::default()
+                },
+            ],
+        };
+        let expected: [&[u8]; 2] = [
+            &[
+                0x5d, 0xbd, 0x01, 0xaa, 0x00, 0x00, 0x00,
+            ],
+            &[
+                0x5d, 0xbd, 0x01, 0xfb, 0x1a, 0x0f, 0x00,
+            ],
+        ];
+        for ((product, dev_type), expected) in KEYBOARDS.into_iter().zip(expected) {
+            let (mut uhid, hidraw) = keyboard(product);
+            hidraw
+                .write_bytes(&laptop_power_packet(&power, dev_type))
+                .unwrap();
+            assert_eq!(uhid.read_output().unwrap(), expected, "{dev_type:?}");
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Keyboard colour from the desktop accent colour. GNOME and KDE publish the
+//! accent colour through the XDG desktop portal `Settings` interface, and when
+//! enabled in the config the keyboard is set to a static mode of that colour
+//! at start and each time the accent colour changes.
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use rog_aura::{AuraEffect, AuraModeNum, AuraZone, Colour};
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{proxy, Connection};
+
+use crate::config::Config;
+use crate::session::is_foreground_user;
+use crate::ui::setup_aura::find_aura_iface;
+
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const ACCENT_COLOUR_KEY: &str = "accent-color";
+
+#[proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait Settings {
+    /// ReadOne method
+    fn read_one(&self, namespace: &str, key: &str) -> zbus::Result<OwnedValue>;
+
+    /// SettingChanged signal
+    #[zbus(signal)]
+    fn setting_changed(&self, namespace: &str, key: &str, value: Value<'_>) -> zbus::Result<()>;
+}
+
+/// The accent colour is red, green, and blue from 0.0 to 1.0. Values outside
+/// that range mean the desktop has no accent colour set.
+fn accent_colour(value: Value<'_>) -> Option<Colour> {
+    let (r, g, b) = <(f64, f64, f64)>::try_from(value).ok()?;
+    if [r, g, b].iter().any(|c| !(0.0..=1.0).contains(c)) {
+        return None;
+    }
+    let to_u8 = |c: f64| (c * 255.0).round() as u8;
+    Some(Colour {
+        r: to_u8(r),
+        g: to_u8(g),
+        b: to_u8(b),
+    })
+}
+
+async fn set_keyboard_colour(colour: Colour) {
+    let Ok(aura) = find_aura_iface().await else {
+        debug!("No Aura keyboard for the accent colour");
+        return;
+    };
+    // Keep the speed and second colour set for other modes
+    let mut effect = aura.led_mode_data().await.unwrap_or_default();
+    if effect.mode == AuraModeNum::Static && effect.colour1 == colour {
+        return;
+    }
+    effect = AuraEffect {
+        mode: AuraModeNum::Static,
+        zone: AuraZone::None,
+        colour1: colour,
+        ..effect
+    };
+    info!("Setting the keyboard to the accent colour {colour:?}");
+    aura.set_led_mode_data(effect)
+        .await
+        .map_err(|e| error!("Could not set the accent colour: {e}"))
+        .ok();
+}
+
+/// Read the accent colour now and set the keyboard to it, such as when the
+/// option is turned on
+pub async fn apply_accent_colour() -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let settings = SettingsProxy::new(&conn).await?;
+    let value = settings
+        .read_one(APPEARANCE_NAMESPACE, ACCENT_COLOUR_KEY)
+        .await?;
+    match accent_colour(value.into()) {
+        Some(colour) => set_keyboard_colour(colour).await,
+        None => debug!("The desktop has no accent colour set"),
+    }
+    Ok(())
+}
+
+async fn watch_accent_colour(config: Arc<Mutex<Config>>) -> zbus::Result<()> {
+    let enabled = || config.lock().is_ok_and(|c| c.accent_keyboard_colour);
+    let conn = Connection::session().await?;
+    let settings = SettingsProxy::new(&conn).await?;
+    let mut changes = settings
+        .receive_setting_changed_with_args(&[
+            (0, APPEARANCE_NAMESPACE),
+            (1, ACCENT_COLOUR_KEY),
+        ])
+        .await?;
+    if enabled() {
+        apply_accent_colour()
+            .await
+            .map_err(|e| warn!("Could not read the accent colour: {e}"))
+            .ok();
+    }
+    while let Some(change) = changes.next().await {
+        if !enabled() || !is_foreground_user().await {
+            continue;
+        }
+        let Ok(args) = change.args() else {
+            continue;
+        };
+        if let Some(colour) = args.value().try_clone().ok().and_then(accent_colour) {
+            set_keyboard_colour(colour).await;
+        }
+    }
+    Ok(())
+}
+
+/// Follow the desktop accent colour with the keyboard while enabled in the
+/// config. Does nothing if the desktop has no `Settings` portal.
+pub fn start_accent_colour(config: Arc<Mutex<Config>>) {
+    tokio::spawn(async move {
+        watch_accent_colour(config)
+            .await
+            .map_err(|e| warn!("Accent colour keyboard stopped: {e}"))
+            .ok();
+    });
+}
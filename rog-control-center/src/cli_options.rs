@@ -24,4 +24,10 @@ pub struct CliStart {
                 that might match your laptop"
     )]
     pub layout_viewing: bool,
+    #[options(
+        meta = "",
+        help = "control asusd on another machine through this DBus address, such as its system \
+                bus forwarded over SSH with unix:path=/tmp/asusd.sock"
+    )]
+    pub dbus_address: Option<String>,
 }
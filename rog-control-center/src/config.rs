@@ -3,7 +3,9 @@ use std::fs::create_dir;
 use config_traits::{StdConfig, StdConfigLoad1};
 use serde::{Deserialize, Serialize};
 
+use crate::games::GameProfile;
 use crate::notify::EnabledNotifications;
+use crate::tray::TrayIconScheme;
 
 const CFG_DIR: &str = "rog";
 const CFG_FILE_NAME: &str = "rog-control-center.cfg";
@@ -13,6 +15,9 @@ pub struct Config {
     pub run_in_background: bool,
     pub startup_in_background: bool,
     pub enable_tray_icon: bool,
+    /// What the tray icon shows
+    #[serde(default)]
+    pub tray_icon_scheme: TrayIconScheme,
     pub ac_command: String,
     pub bat_command: String,
     pub dark_mode: bool,
@@ -20,6 +25,15 @@ pub struct Config {
     pub start_fullscreen: bool,
     pub fullscreen_width: u32,
     pub fullscreen_height: u32,
+    /// Platform profiles used while a Steam game runs
+    #[serde(default)]
+    pub game_profiles: Vec<GameProfile>,
+    /// Set the keyboard to a static colour matching the desktop accent colour
+    #[serde(default)]
+    pub accent_keyboard_colour: bool,
+    /// Set once the first-run wizard is finished or skipped
+    #[serde(default)]
+    pub first_run_done: bool,
     // This field must be last
     pub notifications: EnabledNotifications,
 }
@@ -30,10 +44,14 @@ impl Default for Config {
             run_in_background: true,
             startup_in_background: false,
             enable_tray_icon: true,
+            tray_icon_scheme: TrayIconScheme::default(),
             dark_mode: true,
             start_fullscreen: false,
             fullscreen_width: 1920,
             fullscreen_height: 1080,
+            game_profiles: Vec::new(),
+            accent_keyboard_colour: false,
+            first_run_done: false,
             notifications: EnabledNotifications::default(),
             ac_command: String::new(),
             bat_command: String::new(),
@@ -86,12 +104,16 @@ impl From<Config461> for Config {
             run_in_background: c.run_in_background,
             startup_in_background: c.startup_in_background,
             enable_tray_icon: true,
+            tray_icon_scheme: TrayIconScheme::default(),
             ac_command: c.ac_command,
             bat_command: c.bat_command,
             dark_mode: true,
             start_fullscreen: false,
             fullscreen_width: 1920,
             fullscreen_height: 1080,
+            game_profiles: Vec::new(),
+            accent_keyboard_colour: false,
+            first_run_done: true,
             notifications: c.enabled_notifications,
         }
     }
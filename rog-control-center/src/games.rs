@@ -0,0 +1,180 @@
+//! Per-game platform profiles. Running Steam games are found by their AppID,
+//! from the `AppId=` argument Steam starts its `reaper` launcher with, or
+//! from `RunningAppID` in Steam's registry file. While a game with a profile
+//! in the config is running that profile is used, and the previous one is
+//! put back once the game exits.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, error, info, warn};
+use rog_dbus::zbus_platform::PlatformProxy;
+use rog_platform::platform::PlatformProfile;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::session::is_foreground_user;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Steam install locations relative to the home directory
+const STEAM_DIRS: &[&str] = &[
+    ".local/share/Steam",
+    ".steam/steam",
+    ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+];
+const STEAM_REGISTRY: &str = ".steam/registry.vdf";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct GameProfile {
+    pub app_id: u32,
+    /// From the Steam app manifest when the profile was added, only shown
+    #[serde(default)]
+    pub name: String,
+    pub platform_profile: PlatformProfile,
+}
+
+/// The AppID in the arguments of Steam's game launcher, such as
+/// `reaper SteamLaunch AppId=1091500 -- ...`
+fn cmdline_app_id(cmdline: &[u8]) -> Option<u32> {
+    let args: Vec<&[u8]> = cmdline.split(|b| *b == 0).collect();
+    if !args.iter().any(|a| *a == b"SteamLaunch") {
+        return None;
+    }
+    args.iter()
+        .find_map(|a| a.strip_prefix(b"AppId="))
+        .and_then(|id| std::str::from_utf8(id).ok())
+        .and_then(|id| id.parse().ok())
+        .filter(|id| *id != 0)
+}
+
+/// The value of a `"key" "value"` line of a Valve KeyValues file
+fn vdf_value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let key = format!("\"{key}\"");
+    text.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix(key.as_str())?;
+        Some(rest.trim().trim_matches('"'))
+    })
+}
+
+fn home_path(relative: &str) -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(relative))
+}
+
+/// The AppIDs of the Steam games that are running
+pub fn running_app_ids() -> Vec<u32> {
+    let cmdlines: Vec<Vec<u8>> = std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| {
+                    e.file_name()
+                        .to_string_lossy()
+                        .bytes()
+                        .all(|b| b.is_ascii_digit())
+                })
+                .filter_map(|e| std::fs::read(e.path().join("cmdline")).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut ids: Vec<u32> = cmdlines.iter().filter_map(|c| cmdline_app_id(c)).collect();
+
+    // Steam records the game it started too, but the value is stale if Steam
+    // exited while the game ran, so it is only used while Steam runs
+    let steam_running = cmdlines.iter().any(|c| {
+        let exe = c.split(|b| *b == 0).next().unwrap_or_default();
+        exe.rsplit(|b| *b == b'/').next() == Some(&b"steam"[..])
+    });
+    if ids.is_empty() && steam_running {
+        if let Some(id) = home_path(STEAM_REGISTRY)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| vdf_value(&text, "RunningAppID").and_then(|v| v.parse().ok()))
+            .filter(|id| *id != 0)
+        {
+            ids.push(id);
+        }
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// The name of an installed game from its Steam app manifest
+pub fn game_name(app_id: u32) -> Option<String> {
+    STEAM_DIRS.iter().find_map(|dir| {
+        let path = home_path(dir)?.join(format!("steamapps/appmanifest_{app_id}.acf"));
+        let text = std::fs::read_to_string(path).ok()?;
+        vdf_value(&text, "name").map(str::to_owned)
+    })
+}
+
+async fn watch_games(config: Arc<Mutex<Config>>) -> zbus::Result<()> {
+    let conn = rog_dbus::system_connection().await?;
+    let platform = PlatformProxy::new(&conn).await?;
+    // The game whose profile is in use, and the profile to put back
+    let mut active: Option<(u32, PlatformProfile)> = None;
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let profiles = config
+            .lock()
+            .map(|c| c.game_profiles.clone())
+            .unwrap_or_default();
+        if profiles.is_empty() && active.is_none() {
+            continue;
+        }
+        // Leave the profile to the user in the foreground, but still put
+        // back the profile this user changed
+        if active.is_none() && !is_foreground_user().await {
+            continue;
+        }
+        let running = running_app_ids();
+        let game = profiles.iter().find(|g| running.contains(&g.app_id));
+
+        match (game, active) {
+            (Some(game), Some((app_id, _))) if game.app_id == app_id => {}
+            (Some(game), _) => {
+                let previous = match active {
+                    Some((_, previous)) => previous,
+                    None => match platform.platform_profile().await {
+                        Ok(profile) => profile,
+                        Err(e) => {
+                            error!("Could not read the platform profile: {e}");
+                            continue;
+                        }
+                    },
+                };
+                info!(
+                    "Steam game {} started, using the {:?} profile",
+                    game.app_id, game.platform_profile
+                );
+                platform
+                    .set_platform_profile(game.platform_profile)
+                    .await
+                    .map_err(|e| error!("Could not set the game profile: {e}"))
+                    .ok();
+                active = Some((game.app_id, previous));
+            }
+            (None, Some((app_id, previous))) => {
+                info!("Steam game {app_id} exited, going back to the {previous:?} profile");
+                platform
+                    .set_platform_profile(previous)
+                    .await
+                    .map_err(|e| error!("Could not restore the platform profile: {e}"))
+                    .ok();
+                active = None;
+            }
+            (None, None) => {}
+        }
+        debug!("Running Steam games: {running:?}");
+    }
+}
+
+/// Watch for Steam games with a profile set in the config
+pub fn start_game_profiles(config: Arc<Mutex<Config>>) {
+    tokio::spawn(async move {
+        watch_games(config)
+            .await
+            .map_err(|e| warn!("Per-game profiles stopped: {e}"))
+            .ok();
+    });
+}
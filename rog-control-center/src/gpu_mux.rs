@@ -0,0 +1,148 @@
+//! Confirm a queued GPU MUX switch at shutdown. While asusd has a switch
+//! queued with `confirm`, a logind delay inhibitor holds off shutdown long
+//! enough to ask whether to make the switch. The switch is made if the
+//! notification is not answered in time, as the user asked for it.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use notify_rust::{Hint, Notification, Timeout, Urgency};
+use rog_dbus::zbus_platform::PlatformProxy;
+use rog_platform::platform::GpuMode;
+use zbus::zvariant::OwnedFd;
+use zbus::{proxy, Connection};
+
+const ACTION_SWITCH: &str = "gpu-mux-switch";
+const ACTION_NOT_NOW: &str = "gpu-mux-not-now";
+/// The notification is given up on this long before logind stops waiting,
+/// so there is time to make the switch
+const LOGIND_MARGIN: Duration = Duration::from_millis(500);
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    /// Inhibit method
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    /// InhibitDelayMaxUSec property
+    #[zbus(property, name = "InhibitDelayMaxUSec")]
+    fn inhibit_delay_max_usec(&self) -> zbus::Result<u64>;
+
+    /// PrepareForShutdown signal
+    #[zbus(signal)]
+    fn prepare_for_shutdown(&self, start: bool) -> zbus::Result<()>;
+}
+
+fn do_mux_confirm_notif(mode: GpuMode) -> Notification {
+    let mut notif = Notification::new();
+    notif
+        .appname("ROG Control")
+        .summary(&format!("Switch the GPU MUX to {mode}?"))
+        .body("The switch was queued for this shutdown and takes effect on the next boot")
+        .icon("system-reboot-symbolic")
+        .action(ACTION_SWITCH, "Switch")
+        .action(ACTION_NOT_NOW, "Not now")
+        .urgency(Urgency::Critical)
+        .timeout(Timeout::Never)
+        .hint(Hint::Category("device".into()));
+    notif
+}
+
+/// Ask whether to make the queued switch, waiting up to `budget`. `true` if
+/// the switch should be made
+async fn confirm_switch(mode: GpuMode, budget: Duration) -> bool {
+    let answer = tokio::task::spawn_blocking(move || {
+        let handle = do_mux_confirm_notif(mode).show().ok()?;
+        let mut switch = None;
+        handle.wait_for_action(|id| match id {
+            ACTION_SWITCH => switch = Some(true),
+            ACTION_NOT_NOW => switch = Some(false),
+            _ => {}
+        });
+        switch
+    });
+    match tokio::time::timeout(budget, answer).await {
+        Ok(Ok(Some(switch))) => switch,
+        Ok(_) => {
+            debug!("The GPU MUX notification was closed, switching");
+            true
+        }
+        Err(_) => {
+            info!("The GPU MUX switch was not answered in {budget:?}, switching");
+            true
+        }
+    }
+}
+
+async fn run_mux_confirm() -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let platform = PlatformProxy::new(&conn).await?;
+    let manager = Login1ManagerProxy::new(&conn).await?;
+    let budget = manager
+        .inhibit_delay_max_usec()
+        .await
+        .map(Duration::from_micros)
+        .unwrap_or(Duration::from_secs(5))
+        .saturating_sub(LOGIND_MARGIN);
+
+    let mut queued_changes = platform.receive_gpu_mode_queued_changed().await;
+    let mut shutdowns = manager.receive_prepare_for_shutdown().await?;
+    let mut inhibitor: Option<OwnedFd> = None;
+    info!("Started the GPU MUX shutdown confirmation");
+    loop {
+        let (_, _, queued, confirm) = platform.pending_gpu_mux_changes().await?;
+        let wanted = queued != GpuMode::NotSupported && confirm;
+        if wanted && inhibitor.is_none() {
+            inhibitor = Some(
+                manager
+                    .inhibit(
+                        "shutdown",
+                        "ROG Control Center",
+                        "Confirming the queued GPU MUX switch",
+                        "delay",
+                    )
+                    .await?,
+            );
+        } else if !wanted {
+            // Dropping the fd releases the inhibitor
+            inhibitor = None;
+        }
+
+        tokio::select! {
+            Some(_) = queued_changes.next() => {}
+            Some(signal) = shutdowns.next() => {
+                let Ok(args) = signal.args() else {
+                    continue;
+                };
+                if !*args.start() || inhibitor.is_none() {
+                    continue;
+                }
+                let result = if confirm_switch(queued, budget).await {
+                    platform.apply_queued_gpu_mux().await
+                } else {
+                    platform.cancel_queued_gpu_mux().await
+                };
+                result
+                    .map_err(|e| warn!("Could not finish the queued GPU MUX switch: {e}"))
+                    .ok();
+                inhibitor = None;
+            }
+            else => break,
+        }
+    }
+    Ok(())
+}
+
+/// Confirm queued GPU MUX switches at shutdown, on the tokio runtime
+pub fn start_gpu_mux_confirm() {
+    tokio::spawn(async move {
+        run_mux_confirm()
+            .await
+            .map_err(|e| warn!("Confirming GPU MUX switches at shutdown is not available: {e}"))
+            .ok();
+    });
+}
@@ -6,12 +6,19 @@ slint::include_modules!();
 /// `Cargo.toml`
 pub use slint;
 
+pub mod accent;
 pub mod cli_options;
 pub mod config;
 pub mod error;
+pub mod games;
+pub mod gpu_mux;
 #[cfg(feature = "mocking")]
 pub mod mocking;
 pub mod notify;
+pub mod osd;
+pub mod portal;
+pub mod session;
+pub mod shortcuts;
 pub mod tray;
 pub mod types;
 pub mod ui;
@@ -9,10 +9,15 @@ use config_traits::{StdConfig, StdConfigLoad1};
 use dmi_id::DMIID;
 use gumdrop::Options;
 use log::{debug, info, warn, LevelFilter};
+use rog_control_center::accent::start_accent_colour;
 use rog_control_center::cli_options::CliStart;
 use rog_control_center::config::Config;
 use rog_control_center::error::Result;
+use rog_control_center::games::start_game_profiles;
+use rog_control_center::gpu_mux::start_gpu_mux_confirm;
 use rog_control_center::notify::start_notifications;
+use rog_control_center::osd::start_osd;
+use rog_control_center::shortcuts::start_global_shortcuts;
 use rog_control_center::slint::ComponentHandle;
 use rog_control_center::tray::init_tray;
 use rog_control_center::ui::setup_window;
@@ -63,9 +68,23 @@ async fn main() -> Result<()> {
         }
     }
 
+    let args: Vec<String> = args().skip(1).collect();
+
+    let cli_parsed = match CliStart::parse_args_default(&args) {
+        Ok(p) => p,
+        Err(err) => {
+            panic!("source {}", err);
+        }
+    };
+
+    if let Some(address) = &cli_parsed.dbus_address {
+        info!("Connecting to asusd at {address}");
+        rog_dbus::set_system_address(address);
+    }
+
     // version checks
     let self_version = env!("CARGO_PKG_VERSION");
-    let zbus_con = zbus::blocking::Connection::system()?;
+    let zbus_con = rog_dbus::system_connection_blocking()?;
     let platform_proxy = rog_dbus::zbus_platform::PlatformProxyBlocking::new(&zbus_con)?;
     let asusd_version = platform_proxy
         .version()
@@ -104,15 +123,6 @@ async fn main() -> Result<()> {
     info!("Running on {board_name}, product: {prod_family}");
     let is_rog_ally = board_name == "RC71L" || board_name == "RC72L" || prod_family == "ROG Ally";
 
-    let args: Vec<String> = args().skip(1).collect();
-
-    let cli_parsed = match CliStart::parse_args_default(&args) {
-        Ok(p) => p,
-        Err(err) => {
-            panic!("source {}", err);
-        }
-    };
-
     if do_cli_help(&cli_parsed) {
         return Ok(());
     }
@@ -147,6 +157,11 @@ async fn main() -> Result<()> {
     let config = Arc::new(Mutex::new(config));
 
     start_notifications(config.clone(), &rt)?;
+    start_global_shortcuts();
+    start_osd(config.clone());
+    start_gpu_mux_confirm();
+    start_game_profiles(config.clone());
+    start_accent_colour(config.clone());
 
     if enable_tray_icon {
         init_tray(supported_properties, config.clone());
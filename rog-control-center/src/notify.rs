@@ -11,7 +11,9 @@ use std::time::Duration;
 
 use futures_util::StreamExt;
 use log::{debug, error, info, warn};
-use notify_rust::{Hint, Notification, Timeout, Urgency};
+use notify_rust::{Hint, Notification, NotificationHandle, Timeout, Urgency};
+use rog_dbus::zbus_platform::PlatformProxy;
+use rog_dbus::zbus_xg_mobile::XgMobileProxy;
 use rog_platform::platform::GpuMode;
 use rog_platform::power::AsusPower;
 use serde::{Deserialize, Serialize};
@@ -23,15 +25,69 @@ use tokio::task::JoinHandle;
 
 use crate::config::Config;
 use crate::error::Result;
+use crate::session::is_foreground_user_blocking;
 
 const NOTIF_HEADER: &str = "ROG Control";
 
+/// Remembers the server assigned ID of the last notification shown for a
+/// status so that the next one replaces it in place, rather than filling the
+/// notification shade with stale entries
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct ReplaceId(Option<u32>);
+
+impl ReplaceId {
+    pub(crate) fn show(&mut self, notif: &mut Notification) -> Result<NotificationHandle> {
+        if let Some(id) = self.0 {
+            notif.id(id);
+        }
+        let handle = notif.show()?;
+        self.0 = Some(handle.id());
+        Ok(handle)
+    }
+
+    pub(crate) async fn show_async(
+        &mut self,
+        notif: &mut Notification,
+    ) -> Result<NotificationHandle> {
+        if let Some(id) = self.0 {
+            notif.id(id);
+        }
+        let handle = notif.show_async().await?;
+        self.0 = Some(handle.id());
+        Ok(handle)
+    }
+}
+
+/// The kinds of notification which can be turned off separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyEvent {
+    /// A graphics mode change which needs a logout or reboot
+    GfxAction,
+    /// The dGPU power status changed
+    GfxStatus,
+    /// The platform profile OSD
+    PlatformProfile,
+    /// The keyboard brightness OSD for the brightness keys
+    KeyboardBrightness,
+    /// A USB-C charger too weak to charge the battery was connected
+    SlowCharger,
+    /// An XG Mobile was connected or removed
+    XgMobile,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default)]
 pub struct EnabledNotifications {
     pub enabled: bool,
     pub receive_notify_gfx: bool,
     pub receive_notify_gfx_status: bool,
+    pub receive_notify_platform_profile: bool,
+    pub receive_notify_kbd_brightness: bool,
+    pub receive_notify_slow_charger: bool,
+    pub receive_notify_xg_mobile: bool,
+    /// Hold back notifications which need no action while the desktop is in
+    /// do not disturb mode
+    pub respect_do_not_disturb: bool,
 }
 
 impl Default for EnabledNotifications {
@@ -40,10 +96,95 @@ impl Default for EnabledNotifications {
             enabled: true,
             receive_notify_gfx: true,
             receive_notify_gfx_status: true,
+            receive_notify_platform_profile: true,
+            receive_notify_kbd_brightness: true,
+            receive_notify_slow_charger: true,
+            receive_notify_xg_mobile: true,
+            respect_do_not_disturb: true,
         }
     }
 }
 
+impl EnabledNotifications {
+    /// If notifications of `event` are turned on, not counting do not disturb
+    pub fn event_enabled(&self, event: NotifyEvent) -> bool {
+        self.enabled
+            && match event {
+                NotifyEvent::GfxAction => self.receive_notify_gfx,
+                NotifyEvent::GfxStatus => self.receive_notify_gfx_status,
+                NotifyEvent::PlatformProfile => self.receive_notify_platform_profile,
+                NotifyEvent::KeyboardBrightness => self.receive_notify_kbd_brightness,
+                NotifyEvent::SlowCharger => self.receive_notify_slow_charger,
+                NotifyEvent::XgMobile => self.receive_notify_xg_mobile,
+            }
+    }
+
+    /// If `event` is held back while do not disturb is on. A graphics change
+    /// asking for a logout or reboot is always shown.
+    pub fn quiet_in_dnd(&self, event: NotifyEvent) -> bool {
+        self.respect_do_not_disturb && event != NotifyEvent::GfxAction
+    }
+}
+
+/// If the desktop is in do not disturb mode. KDE and others report this as
+/// `Inhibited` on the notification server, GNOME as its `show-banners`
+/// setting. Blocking, as it may start `gsettings`.
+pub fn do_not_disturb() -> bool {
+    let inhibited = zbus::blocking::Connection::session().and_then(|conn| {
+        let proxy = zbus::blocking::Proxy::new(
+            &conn,
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+        )?;
+        proxy.get_property::<bool>("Inhibited")
+    });
+    if let Ok(inhibited) = inhibited {
+        return inhibited;
+    }
+    Command::new("gsettings")
+        .args([
+            "get",
+            "org.gnome.desktop.notifications",
+            "show-banners",
+        ])
+        .output()
+        .is_ok_and(|out| out.status.success() && out.stdout.trim_ascii() == b"false")
+}
+
+/// If a notification of `event` should be shown now, from the config, if this
+/// user's session is in the foreground, and the desktop's do not disturb state
+pub fn should_notify(config: &Arc<Mutex<Config>>, event: NotifyEvent) -> bool {
+    let Ok((enabled, quiet)) = config.lock().map(|c| {
+        (
+            c.notifications.event_enabled(event),
+            c.notifications.quiet_in_dnd(event),
+        )
+    }) else {
+        return true;
+    };
+    if !enabled {
+        return false;
+    }
+    if !is_foreground_user_blocking() {
+        debug!("Another user is in the foreground, not showing the {event:?} notification");
+        return false;
+    }
+    if quiet && do_not_disturb() {
+        debug!("Do not disturb is on, not showing the {event:?} notification");
+        return false;
+    }
+    true
+}
+
+/// [`should_notify`] for async tasks
+pub async fn should_notify_async(config: &Arc<Mutex<Config>>, event: NotifyEvent) -> bool {
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || should_notify(&config, event))
+        .await
+        .unwrap_or(true)
+}
+
 fn start_dpu_status_mon(config: Arc<Mutex<Config>>) {
     use supergfxctl::pci_device::Device;
     let dev = Device::find().unwrap_or_default();
@@ -58,23 +199,20 @@ fn start_dpu_status_mon(config: Arc<Mutex<Config>>) {
             // Plain old thread is perfectly fine since most of this is potentially blocking
             std::thread::spawn(move || {
                 let mut last_status = GfxPower::Unknown;
+                let mut notif_id = ReplaceId::default();
                 loop {
                     std::thread::sleep(Duration::from_millis(1500));
                     if let Ok(status) = dev.get_runtime_status() {
                         if status != GfxPower::Unknown && status != last_status {
-                            if let Ok(config) = enabled_notifications_copy.lock() {
-                                if !config.notifications.receive_notify_gfx_status
-                                    || !config.notifications.enabled
-                                {
-                                    continue;
-                                }
+                            if !should_notify(&enabled_notifications_copy, NotifyEvent::GfxStatus) {
+                                continue;
                             }
                             // Required check because status cycles through
                             // active/unknown/suspended
-                            do_gpu_status_notif("dGPU status changed:", &status)
-                                .show()
-                                .unwrap()
-                                .on_close(|_| ());
+                            notif_id
+                                .show(&mut do_gpu_status_notif("dGPU status changed:", &status))
+                                .map_err(|e| error!("dGPU status notification: {e}"))
+                                .ok();
                             debug!("dGPU status changed: {:?}", &status);
                         }
                         last_status = status;
@@ -145,6 +283,73 @@ pub fn start_notifications(
         }
     });
 
+    // Slow USB-C charger notif
+    let slow_charger_config = config.clone();
+    tokio::spawn(async move {
+        let conn = rog_dbus::system_connection().await?;
+        let proxy = PlatformProxy::new(&conn).await?;
+        let mut changes = proxy.receive_slow_charger_changed().await;
+        info!("Started zbus signal thread: receive_slow_charger_changed");
+        let mut notif_id = ReplaceId::default();
+        while let Some(change) = changes.next().await {
+            let Ok(slow) = change.get().await else {
+                continue;
+            };
+            if !slow || !should_notify_async(&slow_charger_config, NotifyEvent::SlowCharger).await {
+                continue;
+            }
+            let watts = proxy.usb_pd_watts().await.unwrap_or_default();
+            notif_id
+                .show_async(&mut do_slow_charger_notif(watts))
+                .await
+                .map_err(|e| error!("Slow charger notification: {e}"))
+                .ok();
+        }
+        Ok::<(), zbus::Error>(())
+    });
+
+    // XG Mobile connected or removed notif
+    let xg_mobile_config = config.clone();
+    tokio::spawn(async move {
+        let conn = rog_dbus::system_connection().await?;
+        let proxy = XgMobileProxy::new(&conn).await?;
+        let mut changes = proxy.receive_connection_changed().await?;
+        info!("Started zbus signal thread: receive_connection_changed");
+        let mut notif_id = ReplaceId::default();
+        while let Some(change) = changes.next().await {
+            let Ok(args) = change.args() else {
+                continue;
+            };
+            if !should_notify_async(&xg_mobile_config, NotifyEvent::XgMobile).await {
+                continue;
+            }
+            let connected = *args.connected();
+            let enabled = proxy.enabled().await.unwrap_or_default();
+            let handle = notif_id
+                .show_async(&mut do_xg_mobile_notif(connected, enabled))
+                .await
+                .map_err(|e| error!("XG Mobile notification: {e}"))
+                .ok();
+            if let Some(handle) = handle.filter(|_| connected && !enabled) {
+                let proxy = proxy.clone();
+                tokio::task::spawn_blocking(move || {
+                    let mut enable = false;
+                    handle.wait_for_action(|id| enable = id == "xg-mobile-enable");
+                    if enable {
+                        tokio::runtime::Handle::current().block_on(async {
+                            proxy
+                                .enable()
+                                .await
+                                .map_err(|e| warn!("Could not enable the XG Mobile: {e}"))
+                                .ok();
+                        });
+                    }
+                });
+            }
+        }
+        Ok::<(), zbus::Error>(())
+    });
+
     let enabled_notifications_copy = config.clone();
     let no_supergfx = move |e: &zbus::Error| {
         error!("zbus signal: receive_notify_gfx_status: {e}");
@@ -192,7 +397,7 @@ pub fn start_notifications(
     let enabled_notifications_copy = config.clone();
     // GPU Mode change/action notif
     tokio::spawn(async move {
-        let conn = zbus::Connection::system().await.inspect_err(|e| {
+        let conn = rog_dbus::system_connection().await.inspect_err(|e| {
             no_supergfx(e);
         })?;
         let proxy = SuperProxy::builder(&conn).build().await.inspect_err(|e| {
@@ -204,17 +409,26 @@ pub fn start_notifications(
 
         let proxy_copy = proxy.clone();
         let mut p = proxy.receive_notify_action().await?;
+        let action_config = enabled_notifications_copy.clone();
         tokio::spawn(async move {
             info!("Started zbus signal thread: receive_notify_action");
+            let mut notif_id = ReplaceId::default();
             while let Some(e) = p.next().await {
+                if !should_notify_async(&action_config, NotifyEvent::GfxAction).await {
+                    continue;
+                }
                 if let Ok(out) = e.args() {
                     let action = out.action();
                     let mode = convert_gfx_mode(proxy.mode().await.unwrap_or_default());
                     match action {
-                        supergfxctl::actions::UserActionRequired::Reboot => {
-                            do_mux_notification("Graphics mode change requires reboot", &mode)
+                        supergfxctl::actions::UserActionRequired::Reboot => do_mux_notification(
+                            "Graphics mode change requires reboot",
+                            &mode,
+                            &mut notif_id,
+                        ),
+                        _ => {
+                            do_gfx_action_notif(<&str>::from(action), *action, mode, &mut notif_id)
                         }
-                        _ => do_gfx_action_notif(<&str>::from(action), *action, mode),
                     }
                     .map_err(|e| {
                         error!("zbus signal: do_gfx_action_notif: {e}");
@@ -229,24 +443,23 @@ pub fn start_notifications(
         tokio::spawn(async move {
             info!("Started zbus signal thread: receive_notify_gfx_status");
             let mut last_status = GfxPower::Unknown;
+            let mut notif_id = ReplaceId::default();
             while let Some(e) = p.next().await {
                 if let Ok(out) = e.args() {
                     let status = out.status;
                     if status != GfxPower::Unknown && status != last_status {
-                        if let Ok(config) = enabled_notifications_copy.lock() {
-                            if !config.notifications.receive_notify_gfx_status
-                                || !config.notifications.enabled
-                            {
-                                continue;
-                            }
+                        if !should_notify_async(&enabled_notifications_copy, NotifyEvent::GfxStatus)
+                            .await
+                        {
+                            continue;
                         }
                         // Required check because status cycles through
                         // active/unknown/suspended
-                        do_gpu_status_notif("dGPU status changed:", &status)
-                            .show_async()
+                        notif_id
+                            .show_async(&mut do_gpu_status_notif("dGPU status changed:", &status))
                             .await
-                            .unwrap()
-                            .on_close(|_| ());
+                            .map_err(|e| error!("dGPU status notification: {e}"))
+                            .ok();
                     }
                     last_status = status;
                 }
@@ -283,6 +496,41 @@ where
     notif
 }
 
+fn do_slow_charger_notif(watts: u32) -> Notification {
+    let mut notif = Notification::new();
+    notif
+        .appname(NOTIF_HEADER)
+        .summary("Slow charger")
+        .body(&format!(
+            "The USB-C charger gives {watts}W, which is not enough to charge the battery while in \
+             use"
+        ))
+        .icon("battery-caution-charging-symbolic")
+        .timeout(Timeout::Milliseconds(6000))
+        .hint(Hint::Category("device".into()));
+    notif
+}
+
+fn do_xg_mobile_notif(connected: bool, enabled: bool) -> Notification {
+    let mut notif = Notification::new();
+    notif
+        .appname(NOTIF_HEADER)
+        .icon("video-display-symbolic")
+        .timeout(Timeout::Milliseconds(6000))
+        .hint(Hint::Category("device".into()));
+    if !connected {
+        notif.summary("XG Mobile removed");
+    } else if enabled {
+        notif.summary("XG Mobile connected and in use");
+    } else {
+        notif
+            .summary("XG Mobile connected")
+            .body("Enable it to use its GPU. Disable it again before opening the lock")
+            .action("xg-mobile-enable", "Enable");
+    }
+    notif
+}
+
 fn do_gpu_status_notif(message: &str, data: &GfxPower) -> Notification {
     let mut notif = base_notification(message, &<&str>::from(data).to_owned());
     let icon = match data {
@@ -296,9 +544,14 @@ fn do_gpu_status_notif(message: &str, data: &GfxPower) -> Notification {
     notif
 }
 
-fn do_gfx_action_notif(message: &str, action: GfxUserAction, mode: GpuMode) -> Result<()> {
+fn do_gfx_action_notif(
+    message: &str,
+    action: GfxUserAction,
+    mode: GpuMode,
+    notif_id: &mut ReplaceId,
+) -> Result<()> {
     if matches!(action, GfxUserAction::Reboot) {
-        do_mux_notification("Graphics mode change requires reboot", &mode).ok();
+        do_mux_notification("Graphics mode change requires reboot", &mode, notif_id).ok();
         return Ok(());
     }
 
@@ -315,7 +568,7 @@ fn do_gfx_action_notif(message: &str, action: GfxUserAction, mode: GpuMode) -> R
 
     if matches!(action, GfxUserAction::Logout) {
         notif.action("gfx-mode-session-action", "Logout");
-        let handle = notif.show()?;
+        let handle = notif_id.show(&mut notif)?;
         if let Ok(desktop) = std::env::var("XDG_CURRENT_DESKTOP") {
             if desktop.to_lowercase() == "gnome" {
                 handle.wait_for_action(|id| {
@@ -343,20 +596,20 @@ fn do_gfx_action_notif(message: &str, action: GfxUserAction, mode: GpuMode) -> R
             }
         }
     } else {
-        notif.show()?;
+        notif_id.show(&mut notif)?;
     }
     Ok(())
 }
 
 /// Actual `GpuMode` unused as data is never correct until switched by reboot
-fn do_mux_notification(message: &str, m: &GpuMode) -> Result<()> {
+fn do_mux_notification(message: &str, m: &GpuMode, notif_id: &mut ReplaceId) -> Result<()> {
     let mut notif = base_notification(message, &m.to_string());
     notif
         .action("gfx-mode-session-action", "Reboot")
         .urgency(Urgency::Critical)
         .icon("system-reboot-symbolic")
         .hint(Hint::Transient(true));
-    let handle = notif.show()?;
+    let handle = notif_id.show(&mut notif)?;
 
     std::thread::spawn(|| {
         if let Ok(desktop) = std::env::var("XDG_CURRENT_DESKTOP") {
@@ -0,0 +1,160 @@
+//! On-screen display for changes made with the Fn keys: the platform profile,
+//! which is the fan mode on Fn+F5, and the keyboard brightness. The OSD is a
+//! short lived notification with the synchronous hint, which notification
+//! servers that support it show in place of the previous one as an overlay,
+//! on X11 and Wayland alike.
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use notify_rust::{Hint, Notification, Timeout};
+use rog_dbus::zbus_platform::PlatformProxy;
+use rog_platform::platform::PlatformProfile;
+use zbus::{proxy, Connection};
+
+use crate::config::Config;
+use crate::notify::{should_notify_async, NotifyEvent, ReplaceId};
+
+const OSD_APP_NAME: &str = "ROG Control";
+const OSD_TIMEOUT_MS: u32 = 1500;
+/// The `BrightnessChangedWithSource` source for a change made by the
+/// firmware, which is a brightness key
+const SOURCE_HOTKEY: &str = "internal";
+
+#[proxy(
+    interface = "org.freedesktop.UPower.KbdBacklight",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/KbdBacklight"
+)]
+trait KbdBacklight {
+    /// GetMaxBrightness method
+    fn get_max_brightness(&self) -> zbus::Result<i32>;
+
+    /// BrightnessChangedWithSource signal
+    #[zbus(signal)]
+    fn brightness_changed_with_source(&self, value: i32, source: &str) -> zbus::Result<()>;
+}
+
+fn osd_notification(summary: &str, icon: &str, synchronous: &str) -> Notification {
+    let mut notif = Notification::new();
+    notif
+        .appname(OSD_APP_NAME)
+        .summary(summary)
+        .icon(icon)
+        .timeout(Timeout::Milliseconds(OSD_TIMEOUT_MS))
+        .hint(Hint::Category("device".into()))
+        .hint(Hint::Transient(true))
+        // Asks notification servers which support it to show this as an OSD
+        .hint(Hint::Custom(
+            "x-canonical-private-synchronous".into(),
+            synchronous.into(),
+        ));
+    notif
+}
+
+/// The OSD shown when the platform profile is changed
+pub(crate) fn do_profile_osd(profile: PlatformProfile) -> Notification {
+    let icon = match profile {
+        PlatformProfile::Performance => "power-profile-performance-symbolic",
+        PlatformProfile::Quiet | PlatformProfile::LowPower => "power-profile-power-saver-symbolic",
+        PlatformProfile::Balanced | PlatformProfile::Custom => "power-profile-balanced-symbolic",
+    };
+    osd_notification(
+        &format!("Platform profile: {}", <&str>::from(profile)),
+        icon,
+        "rog-platform-profile",
+    )
+}
+
+/// The OSD shown when the keyboard brightness is changed, with a bar of
+/// `value` out of `max`
+pub(crate) fn do_kbd_brightness_osd(value: i32, max: i32) -> Notification {
+    let percent = if max > 0 {
+        (value.clamp(0, max) * 100) / max
+    } else {
+        0
+    };
+    let icon = if value > 0 {
+        "keyboard-brightness-symbolic"
+    } else {
+        "keyboard-brightness-off-symbolic"
+    };
+    let mut notif = osd_notification(
+        &format!("Keyboard brightness: {value}/{max}"),
+        icon,
+        "rog-kbd-brightness",
+    );
+    notif.hint(Hint::CustomInt("value".into(), percent));
+    notif
+}
+
+async fn run_profile_osd(config: Arc<Mutex<Config>>) -> zbus::Result<()> {
+    let conn = rog_dbus::system_connection().await?;
+    let platform = PlatformProxy::new(&conn).await?;
+    let mut last = platform.platform_profile().await.ok();
+    let mut changes = platform.receive_platform_profile_changed().await;
+    let mut osd = ReplaceId::default();
+    info!("Started the platform profile OSD");
+    while let Some(change) = changes.next().await {
+        let Ok(profile) = change.get().await else {
+            continue;
+        };
+        if last.replace(profile) == Some(profile) {
+            continue;
+        }
+        if !should_notify_async(&config, NotifyEvent::PlatformProfile).await {
+            continue;
+        }
+        osd.show_async(&mut do_profile_osd(profile))
+            .await
+            .map_err(|e| error!("Profile OSD: {e}"))
+            .ok();
+    }
+    Ok(())
+}
+
+async fn run_kbd_brightness_osd(config: Arc<Mutex<Config>>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let backlight = KbdBacklightProxy::new(&conn).await?;
+    let max = backlight.get_max_brightness().await?;
+    let mut changes = backlight.receive_brightness_changed_with_source().await?;
+    let mut osd = ReplaceId::default();
+    info!("Started the keyboard brightness OSD");
+    while let Some(change) = changes.next().await {
+        let Ok(args) = change.args() else {
+            continue;
+        };
+        // Changes made by asusd or the desktop show their own feedback
+        if *args.source() != SOURCE_HOTKEY {
+            debug!("Keyboard brightness set by {}, no OSD", args.source());
+            continue;
+        }
+        if !should_notify_async(&config, NotifyEvent::KeyboardBrightness).await {
+            continue;
+        }
+        osd.show_async(&mut do_kbd_brightness_osd(*args.value(), max))
+            .await
+            .map_err(|e| error!("Keyboard brightness OSD: {e}"))
+            .ok();
+    }
+    Ok(())
+}
+
+/// Show the OSD for profile and keyboard brightness changes on the tokio
+/// runtime. The keyboard brightness OSD needs UPower.
+pub fn start_osd(config: Arc<Mutex<Config>>) {
+    let config_copy = config.clone();
+    tokio::spawn(async move {
+        run_profile_osd(config_copy)
+            .await
+            .map_err(|e| warn!("The platform profile OSD is not available: {e}"))
+            .ok();
+    });
+    tokio::spawn(async move {
+        run_kbd_brightness_osd(config)
+            .await
+            .map_err(|e| warn!("The keyboard brightness OSD is not available: {e}"))
+            .ok();
+    });
+}
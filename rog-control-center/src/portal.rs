@@ -0,0 +1,120 @@
+//! Helpers for calling the XDG desktop portal, which answers most calls
+//! through a `Response` signal on a request object rather than directly.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use futures_util::StreamExt;
+use slint::Color;
+use zbus::zvariant::{OwnedObjectPath, Value};
+use zbus::{proxy, Connection};
+
+#[proxy(
+    interface = "org.freedesktop.portal.Request",
+    default_service = "org.freedesktop.portal.Desktop"
+)]
+pub trait Request {
+    /// Response signal
+    #[zbus(signal)]
+    fn response(&self, response: u32, results: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.portal.Screenshot",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait Screenshot {
+    /// PickColor method
+    fn pick_color(
+        &self,
+        parent_window: &str,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    /// version property
+    #[zbus(property, name = "version")]
+    fn version(&self) -> zbus::Result<u32>;
+}
+
+/// The portal names request and session objects after the caller's unique
+/// bus name and a token of our choosing
+pub fn portal_path(conn: &Connection, kind: &str, token: &str) -> String {
+    let sender = conn
+        .unique_name()
+        .map(|n| n.trim_start_matches(':').replace('.', "_"))
+        .unwrap_or_default();
+    format!("/org/freedesktop/portal/desktop/{kind}/{sender}/{token}")
+}
+
+/// Make a portal call and wait for its reply. The listener is set up before
+/// the call is made so the reply can't be missed. Returns the `Response`
+/// signal so that callers can read the results.
+pub async fn portal_request(
+    conn: &Connection,
+    token: &str,
+    call: impl Future<Output = zbus::Result<OwnedObjectPath>>,
+) -> zbus::Result<Response> {
+    let request = RequestProxy::builder(conn)
+        .path(portal_path(conn, "request", token))?
+        .build()
+        .await?;
+    let mut responses = request.receive_response().await?;
+    call.await?;
+    let response = responses
+        .next()
+        .await
+        .ok_or_else(|| zbus::Error::Failure("portal request closed".into()))?;
+    match *response.args()?.response() {
+        0 => Ok(response),
+        r => Err(zbus::Error::Failure(format!(
+            "portal request was cancelled or denied ({r})"
+        ))),
+    }
+}
+
+/// Each pick needs its own request object, so each gets its own token
+static PICK_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// If the desktop has a `Screenshot` portal new enough to pick colours
+pub async fn can_pick_colour() -> bool {
+    let Ok(conn) = Connection::session().await else {
+        return false;
+    };
+    let Ok(portal) = ScreenshotProxy::new(&conn).await else {
+        return false;
+    };
+    // PickColor was added in version 2
+    portal.version().await.is_ok_and(|v| v >= 2)
+}
+
+/// Let the user pick a colour from anywhere on screen, such as the wallpaper.
+/// This works on Wayland where clients can't read the screen themselves, and
+/// fails if the desktop has no `Screenshot` portal or the pick is cancelled.
+pub async fn pick_colour() -> zbus::Result<Color> {
+    let conn = Connection::session().await?;
+    let portal = ScreenshotProxy::new(&conn).await?;
+    let token = format!(
+        "rogcc_pick_colour_{}",
+        PICK_COUNT.fetch_add(1, Ordering::Relaxed)
+    );
+    let response = portal_request(
+        &conn,
+        &token,
+        portal.pick_color(
+            "",
+            HashMap::from([("handle_token", Value::from(token.as_str()))]),
+        ),
+    )
+    .await?;
+    let args = response.args()?;
+    let colour = args
+        .results()
+        .get("color")
+        .ok_or_else(|| zbus::Error::Failure("portal gave no colour".into()))?;
+    // The colour is given as red, green, and blue from 0.0 to 1.0
+    let (r, g, b) = <(f64, f64, f64)>::try_from(colour.try_clone()?)?;
+    let to_u8 = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Ok(Color::from_rgb_u8(to_u8(r), to_u8(g), to_u8(b)))
+}
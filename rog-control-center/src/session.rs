@@ -0,0 +1,49 @@
+//! Each logged in user can run their own rog-control-center. Only the one
+//! whose session is in the foreground, as told by asusd's `ActiveUser`, shows
+//! notifications and makes automatic changes such as game profiles.
+
+use std::os::unix::fs::MetadataExt;
+
+use log::debug;
+use rog_dbus::zbus_platform::{PlatformProxy, PlatformProxyBlocking};
+
+fn own_uid() -> Option<u32> {
+    std::fs::metadata("/proc/self").ok().map(|m| m.uid())
+}
+
+fn is_active(active: zbus::Result<u32>) -> bool {
+    // The foreground session of a remote asusd is nothing to do with us, and
+    // an older asusd can't say
+    if rog_dbus::system_address().is_some() {
+        return true;
+    }
+    match (active, own_uid()) {
+        (Ok(active), Some(uid)) => {
+            if active != uid {
+                debug!("User {active} is in the foreground, not {uid}");
+            }
+            active == uid
+        }
+        _ => true,
+    }
+}
+
+/// If this user's session is in the foreground
+pub async fn is_foreground_user() -> bool {
+    let active = match rog_dbus::system_connection().await {
+        Ok(conn) => match PlatformProxy::new(&conn).await {
+            Ok(platform) => platform.active_user().await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    is_active(active)
+}
+
+/// [`is_foreground_user`] for blocking callers
+pub fn is_foreground_user_blocking() -> bool {
+    let active = rog_dbus::system_connection_blocking()
+        .and_then(|conn| PlatformProxyBlocking::new(&conn))
+        .and_then(|platform| platform.active_user());
+    is_active(active)
+}
@@ -0,0 +1,115 @@
+//! Global shortcuts registered through the XDG desktop portal. Some
+//! compositors never deliver the Fn+F5 key event, so this gives the same
+//! profile cycling on any desktop with a `GlobalShortcuts` portal.
+
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use rog_dbus::zbus_platform::PlatformProxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, Value};
+use zbus::{proxy, Connection};
+
+use crate::portal::{portal_path, portal_request};
+
+const CYCLE_PROFILE_ID: &str = "cycle-platform-profile";
+const SESSION_TOKEN: &str = "rogcc_shortcuts";
+const CREATE_TOKEN: &str = "rogcc_create_session";
+const BIND_TOKEN: &str = "rogcc_bind_shortcuts";
+
+#[proxy(
+    interface = "org.freedesktop.portal.GlobalShortcuts",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait GlobalShortcuts {
+    /// CreateSession method
+    fn create_session(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>;
+
+    /// BindShortcuts method
+    fn bind_shortcuts(
+        &self,
+        session_handle: &ObjectPath<'_>,
+        shortcuts: &[(&str, HashMap<&str, Value<'_>>)],
+        parent_window: &str,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    /// Activated signal
+    #[zbus(signal)]
+    fn activated(
+        &self,
+        session_handle: ObjectPath<'_>,
+        shortcut_id: &str,
+        timestamp: u64,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+async fn run_global_shortcuts() -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let portal = GlobalShortcutsProxy::new(&conn).await?;
+
+    portal_request(
+        &conn,
+        CREATE_TOKEN,
+        portal.create_session(HashMap::from([
+            ("handle_token", Value::from(CREATE_TOKEN)),
+            ("session_handle_token", Value::from(SESSION_TOKEN)),
+        ])),
+    )
+    .await?;
+    let session_path = portal_path(&conn, "session", SESSION_TOKEN);
+    let session = ObjectPath::try_from(session_path.as_str())?;
+
+    let shortcuts = [(
+        CYCLE_PROFILE_ID,
+        HashMap::from([
+            ("description", Value::from("Cycle platform profile")),
+            ("preferred_trigger", Value::from("LOGO+F5")),
+        ]),
+    )];
+    portal_request(
+        &conn,
+        BIND_TOKEN,
+        portal.bind_shortcuts(
+            &session,
+            &shortcuts,
+            "",
+            HashMap::from([("handle_token", Value::from(BIND_TOKEN))]),
+        ),
+    )
+    .await?;
+    info!("Registered global shortcut: {CYCLE_PROFILE_ID}");
+
+    let system = rog_dbus::system_connection().await?;
+    let platform = PlatformProxy::new(&system).await?;
+    let mut activated = portal.receive_activated().await?;
+    while let Some(event) = activated.next().await {
+        let Ok(args) = event.args() else {
+            continue;
+        };
+        if *args.shortcut_id() != CYCLE_PROFILE_ID {
+            continue;
+        }
+        debug!("Global shortcut: {CYCLE_PROFILE_ID}");
+        // The profile OSD is shown for the change, see `osd`
+        platform
+            .next_platform_profile()
+            .await
+            .map_err(|e| error!("Could not change platform profile: {e}"))
+            .ok();
+    }
+    Ok(())
+}
+
+/// Register the global shortcuts and handle them on the tokio runtime. Does
+/// nothing if the desktop has no `GlobalShortcuts` portal.
+pub fn start_global_shortcuts() {
+    tokio::spawn(async move {
+        run_global_shortcuts()
+            .await
+            .map_err(|e| warn!("Global shortcuts are not available: {e}"))
+            .ok();
+    });
+}
@@ -6,18 +6,31 @@ use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Duration;
 
+use futures_util::{stream, StreamExt};
 use ksni::{Handle, Icon, TrayMethods};
-use log::{info, warn};
-use rog_platform::platform::Properties;
+use log::{debug, info, warn};
+use rog_aura::LedBrightness;
+use rog_dbus::asus_armoury::{AsusArmouryProxy, AsusArmouryProxyBlocking};
+use rog_dbus::zbus_aura::{AuraProxy, AuraProxyBlocking};
+use rog_dbus::zbus_platform::{PlatformProxy, PlatformProxyBlocking};
+use rog_dbus::zbus_privacy::{PrivacyProxy, PrivacyProxyBlocking};
+use rog_platform::asus_armoury::FirmwareAttribute;
+use rog_platform::platform::{GpuMode, PlatformProfile, Properties};
+use serde::{Deserialize, Serialize};
 use supergfxctl::pci_device::{Device, GfxMode, GfxPower};
-use supergfxctl::zbus_proxy::DaemonProxy as GfxProxy;
+use supergfxctl::zbus_proxy::{DaemonProxy as GfxProxy, DaemonProxyBlocking as GfxProxyBlocking};
+use tokio::sync::Notify;
 use versions::Versioning;
 
 use crate::config::Config;
-use crate::zbus_proxies::{AppState, ROGCCZbusProxyBlocking};
+use crate::zbus_proxies::{find_iface, find_iface_async, AppState, ROGCCZbusProxyBlocking};
 
 const TRAY_LABEL: &str = "ROG Control Center";
 const TRAY_ICON_PATH: &str = "/usr/share/icons/hicolor/512x512/apps/";
+/// How often the dGPU status is read when there are no supergfxd signals
+const FALLBACK_POLL: Duration = Duration::from_secs(5);
+/// How often the tray setting is checked if no change to it is signalled
+const ENABLED_CHECK: Duration = Duration::from_secs(30);
 
 struct Icons {
     rog_blue: Icon,
@@ -55,10 +68,494 @@ fn read_icon(file: &Path) -> Icon {
     }
 }
 
+/// Settings that can be changed from the tray menu. Each is `None` if the
+/// laptop or the running daemons don't have it, and the menu leaves it out.
+struct QuickActions {
+    platform: Option<PlatformProxyBlocking<'static>>,
+    profile: PlatformProfile,
+    profile_choices: Vec<PlatformProfile>,
+    aura: Option<AuraProxyBlocking<'static>>,
+    brightness: LedBrightness,
+    panel_od: Option<AsusArmouryProxyBlocking<'static>>,
+    panel_od_enabled: bool,
+    gfx: Option<GfxProxyBlocking<'static>>,
+    gfx_mode: GfxMode,
+    gfx_modes: Vec<GfxMode>,
+    /// GPU modes set through asusd's firmware attributes when supergfxd is
+    /// not running
+    gpu_mode: GpuMode,
+    gpu_modes: Vec<GpuMode>,
+    privacy: Option<PrivacyProxyBlocking<'static>>,
+    /// The `Privacy` properties this laptop has, with their current values
+    privacy_toggles: Vec<(String, bool)>,
+}
+
+impl QuickActions {
+    fn new() -> Self {
+        let platform = rog_dbus::system_connection_blocking()
+            .and_then(|conn| PlatformProxyBlocking::new(&conn))
+            .ok()
+            .filter(|p| p.platform_profile().is_ok());
+        let profile_choices = platform
+            .as_ref()
+            .and_then(|p| p.platform_profile_choices().ok())
+            .unwrap_or_default();
+        let panel_od = find_iface::<AsusArmouryProxyBlocking>("xyz.ljones.AsusArmoury")
+            .ok()
+            .and_then(|attrs| {
+                attrs.into_iter().find(|a| {
+                    a.name()
+                        .is_ok_and(|n| n == FirmwareAttribute::PanelOverdrive)
+                })
+            });
+        let privacy = rog_dbus::system_connection_blocking()
+            .and_then(|conn| PrivacyProxyBlocking::new(&conn))
+            .ok();
+        let privacy_toggles = privacy
+            .as_ref()
+            .and_then(|p| p.supported().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|name| (name, false))
+            .collect();
+        let mut quick = Self {
+            platform,
+            profile: PlatformProfile::default(),
+            profile_choices,
+            aura: find_iface::<AuraProxyBlocking>("xyz.ljones.Aura")
+                .ok()
+                .and_then(|aura| aura.into_iter().next()),
+            brightness: LedBrightness::default(),
+            panel_od,
+            panel_od_enabled: false,
+            gfx: None,
+            gfx_mode: GfxMode::None,
+            gfx_modes: Vec::new(),
+            gpu_mode: GpuMode::NotSupported,
+            gpu_modes: Vec::new(),
+            privacy,
+            privacy_toggles,
+        };
+        quick.refresh();
+        quick
+    }
+
+    /// GPU modes are only offered once supergfxd is found to be new enough
+    fn add_gfx(&mut self) {
+        self.gfx = rog_dbus::system_connection_blocking()
+            .and_then(|conn| GfxProxyBlocking::new(&conn))
+            .ok();
+        if let Some(gfx) = &self.gfx {
+            self.gfx_modes = gfx.supported().unwrap_or_default();
+        }
+        self.refresh();
+    }
+
+    /// Without supergfxd the basic GPU modes are set through asusd
+    fn add_platform_gpu(&mut self) {
+        if let Some(platform) = &self.platform {
+            self.gpu_modes = platform.supported_gpu_modes().unwrap_or_default();
+        }
+        self.refresh();
+    }
+
+    /// Read the current values so the menu shows changes made elsewhere
+    fn refresh(&mut self) {
+        if let Some(profile) = self
+            .platform
+            .as_ref()
+            .and_then(|p| p.platform_profile().ok())
+        {
+            self.profile = profile;
+        }
+        if let Some(brightness) = self.aura.as_ref().and_then(|a| a.brightness().ok()) {
+            self.brightness = brightness;
+        }
+        if let Some(value) = self.panel_od.as_ref().and_then(|a| a.current_value().ok()) {
+            self.panel_od_enabled = value == 1;
+        }
+        if let Some(mode) = self.gfx.as_ref().and_then(|g| g.mode().ok()) {
+            self.gfx_mode = mode;
+        }
+        if !self.gpu_modes.is_empty() {
+            if let Some(mode) = self
+                .platform
+                .as_ref()
+                .and_then(|p| p.gpu_mode_pending().ok())
+            {
+                self.gpu_mode = mode;
+            }
+        }
+        if let Some(privacy) = &self.privacy {
+            for (name, value) in &mut self.privacy_toggles {
+                let current = match name.as_str() {
+                    "CameraEnabled" => privacy.camera_enabled(),
+                    "MicMuteLed" => privacy.mic_mute_led(),
+                    "CameraLed" => privacy.camera_led(),
+                    _ => continue,
+                };
+                if let Ok(current) = current {
+                    *value = current;
+                }
+            }
+        }
+    }
+
+    fn set_profile(&mut self, profile: PlatformProfile) {
+        if let Some(platform) = &self.platform {
+            match platform.set_platform_profile(profile) {
+                Ok(()) => self.profile = profile,
+                Err(e) => warn!("Tray could not set the platform profile: {e}"),
+            }
+        }
+    }
+
+    fn next_profile(&mut self) {
+        if let Some(platform) = &self.platform {
+            platform
+                .next_platform_profile()
+                .map_err(|e| warn!("Tray could not change the platform profile: {e}"))
+                .ok();
+        }
+        self.refresh();
+    }
+
+    fn set_brightness(&mut self, brightness: LedBrightness) {
+        if let Some(aura) = &self.aura {
+            match aura.set_brightness(brightness) {
+                Ok(()) => self.brightness = brightness,
+                Err(e) => warn!("Tray could not set the keyboard brightness: {e}"),
+            }
+        }
+    }
+
+    fn toggle_panel_od(&mut self) {
+        if let Some(attr) = &self.panel_od {
+            let enable = !self.panel_od_enabled;
+            match attr.set_current_value(enable as i32) {
+                Ok(()) => self.panel_od_enabled = enable,
+                Err(e) => warn!("Tray could not set panel overdrive: {e}"),
+            }
+        }
+    }
+
+    fn set_privacy(&mut self, name: &str, value: bool) {
+        if let Some(privacy) = &self.privacy {
+            let result = match name {
+                "CameraEnabled" => privacy.set_camera_enabled(value),
+                "MicMuteLed" => privacy.set_mic_mute_led(value),
+                "CameraLed" => privacy.set_camera_led(value),
+                _ => return,
+            };
+            result
+                .map_err(|e| warn!("Tray could not set {name}: {e}"))
+                .ok();
+        }
+        self.refresh();
+    }
+
+    fn set_gfx_mode(&mut self, mode: GfxMode) {
+        if let Some(gfx) = &self.gfx {
+            // Any logout or reboot needed is shown by the notifications
+            match gfx.set_mode(&mode) {
+                Ok(action) => {
+                    debug!("Tray set GPU mode {mode:?}, user action {action:?}");
+                    self.gfx_mode = mode;
+                }
+                Err(e) => warn!("Tray could not set the GPU mode: {e}"),
+            }
+        }
+    }
+
+    fn set_gpu_mode(&mut self, mode: GpuMode) {
+        if let Some(platform) = &self.platform {
+            match platform.set_gpu_mode(mode) {
+                Ok(()) => self.gpu_mode = mode,
+                Err(e) => warn!("Tray could not set the GPU mode: {e}"),
+            }
+        }
+    }
+
+    fn menu(&self) -> Vec<ksni::MenuItem<AsusTray>> {
+        use ksni::menu::*;
+        let mut items = Vec::new();
+        if self.platform.is_some() && !self.profile_choices.is_empty() {
+            let choices = self.profile_choices.clone();
+            items.push(
+                SubMenu {
+                    label: "Platform profile".into(),
+                    submenu: vec![
+                        RadioGroup {
+                            selected: choices
+                                .iter()
+                                .position(|p| *p == self.profile)
+                                .unwrap_or_default(),
+                            select: Box::new(move |s: &mut AsusTray, i| {
+                                if let Some(profile) = choices.get(i) {
+                                    s.quick.set_profile(*profile);
+                                }
+                            }),
+                            options: self
+                                .profile_choices
+                                .iter()
+                                .map(|p| RadioItem {
+                                    label: p.to_string(),
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        }
+                        .into(),
+                        MenuItem::Separator,
+                        StandardItem {
+                            label: "Next profile".into(),
+                            activate: Box::new(|s: &mut AsusTray| s.quick.next_profile()),
+                            ..Default::default()
+                        }
+                        .into(),
+                    ],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        if self.aura.is_some() {
+            let levels = [
+                LedBrightness::Off,
+                LedBrightness::Low,
+                LedBrightness::Med,
+                LedBrightness::High,
+            ];
+            items.push(
+                SubMenu {
+                    label: "Keyboard brightness".into(),
+                    submenu: vec![
+                        RadioGroup {
+                            selected: levels
+                                .iter()
+                                .position(|b| *b == self.brightness)
+                                .unwrap_or_default(),
+                            select: Box::new(move |s: &mut AsusTray, i| {
+                                if let Some(brightness) = levels.get(i) {
+                                    s.quick.set_brightness(*brightness);
+                                }
+                            }),
+                            options: levels
+                                .iter()
+                                .map(|b| RadioItem {
+                                    label: format!("{b:?}"),
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        }
+                        .into(),
+                    ],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        if self.panel_od.is_some() {
+            items.push(
+                CheckmarkItem {
+                    label: "Panel overdrive".into(),
+                    checked: self.panel_od_enabled,
+                    activate: Box::new(|s: &mut AsusTray| s.quick.toggle_panel_od()),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        if self.gfx.is_some() && !self.gfx_modes.is_empty() {
+            let modes = self.gfx_modes.clone();
+            items.push(
+                SubMenu {
+                    label: "GPU mode".into(),
+                    submenu: vec![
+                        RadioGroup {
+                            selected: modes
+                                .iter()
+                                .position(|m| *m == self.gfx_mode)
+                                .unwrap_or_default(),
+                            select: Box::new(move |s: &mut AsusTray, i| {
+                                if let Some(mode) = modes.get(i) {
+                                    s.quick.set_gfx_mode(*mode);
+                                }
+                            }),
+                            options: self
+                                .gfx_modes
+                                .iter()
+                                .map(|m| RadioItem {
+                                    label: format!("{m:?}"),
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        }
+                        .into(),
+                    ],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        if self.gfx.is_none() && !self.gpu_modes.is_empty() {
+            let modes = self.gpu_modes.clone();
+            items.push(
+                SubMenu {
+                    label: "GPU mode".into(),
+                    submenu: vec![
+                        RadioGroup {
+                            selected: modes
+                                .iter()
+                                .position(|m| *m == self.gpu_mode)
+                                .unwrap_or_default(),
+                            select: Box::new(move |s: &mut AsusTray, i| {
+                                if let Some(mode) = modes.get(i) {
+                                    s.quick.set_gpu_mode(*mode);
+                                }
+                            }),
+                            options: self
+                                .gpu_modes
+                                .iter()
+                                .map(|m| RadioItem {
+                                    label: m.to_string(),
+                                    ..Default::default()
+                                })
+                                .collect(),
+                        }
+                        .into(),
+                    ],
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        for (name, value) in &self.privacy_toggles {
+            let label = match name.as_str() {
+                "CameraEnabled" => "Camera",
+                "MicMuteLed" => "Mic mute LED",
+                "CameraLed" => "Camera LED",
+                _ => continue,
+            };
+            let (name, value) = (name.clone(), *value);
+            items.push(
+                CheckmarkItem {
+                    label: label.into(),
+                    checked: value,
+                    activate: Box::new(move |s: &mut AsusTray| s.quick.set_privacy(&name, !value)),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+        if !items.is_empty() {
+            items.push(MenuItem::Separator);
+        }
+        items
+    }
+}
+
+/// What the tray icon shows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum TrayIconScheme {
+    /// The dGPU power state, or the GPU mode from supergfxd
+    #[default]
+    GpuPower,
+    PlatformProfile,
+    /// If the battery charge is limited below 100%
+    ChargeLimit,
+}
+
+impl From<i32> for TrayIconScheme {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => Self::PlatformProfile,
+            2 => Self::ChargeLimit,
+            _ => Self::GpuPower,
+        }
+    }
+}
+
 struct AsusTray {
     current_title: String,
     current_icon: Icon,
     proxy: ROGCCZbusProxyBlocking<'static>,
+    quick: QuickActions,
+    scheme: TrayIconScheme,
+    /// GPU mode, dGPU power, and if supergfxd is in use
+    gfx_status: (GfxMode, GfxPower, bool),
+    charge_limit: Option<u8>,
+}
+
+impl AsusTray {
+    /// Read the current values shown by the menu and icon
+    fn refresh(&mut self) {
+        self.quick.refresh();
+        if let Some(limit) = self
+            .quick
+            .platform
+            .as_ref()
+            .and_then(|p| p.charge_control_end_threshold().ok())
+        {
+            self.charge_limit = Some(limit);
+        }
+        self.update_icon();
+    }
+
+    /// Set the icon and title from the current state for the chosen scheme
+    fn update_icon(&mut self) {
+        let Some(icons) = ICONS.get() else {
+            return;
+        };
+        let (mode, power, supergfx_active) = self.gfx_status;
+        let (icon, title) = match self.scheme {
+            TrayIconScheme::GpuPower => {
+                let icon = match power {
+                    GfxPower::Suspended => &icons.rog_blue,
+                    GfxPower::Off => {
+                        if mode == GfxMode::Vfio {
+                            &icons.rog_red
+                        } else {
+                            &icons.rog_green
+                        }
+                    }
+                    GfxPower::AsusDisabled => &icons.rog_white,
+                    GfxPower::AsusMuxDiscreet | GfxPower::Active => &icons.rog_red,
+                    GfxPower::Unknown => {
+                        if supergfx_active {
+                            &icons.gpu_integrated
+                        } else {
+                            &icons.rog_red
+                        }
+                    }
+                };
+                (
+                    icon,
+                    format!("ROG: gpu mode = {mode:?}, gpu power = {power:?}"),
+                )
+            }
+            TrayIconScheme::PlatformProfile if self.quick.platform.is_some() => {
+                let profile = self.quick.profile;
+                let icon = match profile {
+                    PlatformProfile::Quiet | PlatformProfile::LowPower => &icons.rog_blue,
+                    PlatformProfile::Balanced => &icons.rog_white,
+                    PlatformProfile::Performance => &icons.rog_red,
+                    PlatformProfile::Custom => &icons.rog_green,
+                };
+                (icon, format!("ROG: platform profile = {profile}"))
+            }
+            TrayIconScheme::ChargeLimit if self.charge_limit.is_some() => {
+                let limit = self.charge_limit.unwrap_or(100);
+                if limit < 100 {
+                    (&icons.rog_green, format!("ROG: charge limited to {limit}%"))
+                } else {
+                    (&icons.rog_white, "ROG: charge not limited".to_string())
+                }
+            }
+            _ => (&icons.gpu_integrated, TRAY_LABEL.to_string()),
+        };
+        self.current_icon = icon.clone();
+        self.current_title = title;
+    }
 }
 
 impl ksni::Tray for AsusTray {
@@ -91,6 +588,10 @@ impl ksni::Tray for AsusTray {
             }
             .into(),
             MenuItem::Separator,
+        ]
+        .into_iter()
+        .chain(self.quick.menu())
+        .chain([
             StandardItem {
                 label: "Quit ROGCC".into(),
                 icon_name: "application-exit".into(),
@@ -98,7 +599,8 @@ impl ksni::Tray for AsusTray {
                 ..Default::default()
             }
             .into(),
-        ]
+        ])
+        .collect()
     }
 }
 
@@ -108,36 +610,12 @@ async fn set_tray_icon_and_tip(
     tray: &mut Handle<AsusTray>,
     supergfx_active: bool,
 ) {
-    if let Some(icons) = ICONS.get() {
-        let icon = match power {
-            GfxPower::Suspended => icons.rog_blue.clone(),
-            GfxPower::Off => {
-                if mode == GfxMode::Vfio {
-                    icons.rog_red.clone()
-                } else {
-                    icons.rog_green.clone()
-                }
-            }
-            GfxPower::AsusDisabled => icons.rog_white.clone(),
-            GfxPower::AsusMuxDiscreet | GfxPower::Active => icons.rog_red.clone(),
-            GfxPower::Unknown => {
-                if supergfx_active {
-                    icons.gpu_integrated.clone()
-                } else {
-                    icons.rog_red.clone()
-                }
-            }
-        };
-
-        tray.update(|tray: &mut AsusTray| {
-            tray.current_icon = icon;
-            tray.current_title = format!(
-                "ROG: gpu mode = {mode:?}, gpu power =
-            {power:?}"
-            );
-        })
-        .await;
-    }
+    tray.update(|tray: &mut AsusTray| {
+        tray.gfx_status = (mode, power, supergfx_active);
+        tray.quick.gfx_mode = mode;
+        tray.update_icon();
+    })
+    .await;
 }
 
 fn find_dgpu() -> Option<Device> {
@@ -154,6 +632,136 @@ fn find_dgpu() -> Option<Device> {
     None
 }
 
+fn tray_enabled(config: &Arc<Mutex<Config>>) -> bool {
+    config.try_lock().map_or(true, |lock| lock.enable_tray_icon)
+}
+
+fn tray_enabled_notify() -> &'static Notify {
+    static NOTIFY: OnceLock<Notify> = OnceLock::new();
+    NOTIFY.get_or_init(Notify::new)
+}
+
+/// Wake the tray to check `enable_tray_icon`, after it is changed in the config
+pub fn tray_enabled_changed() {
+    tray_enabled_notify().notify_one();
+}
+
+/// Update the tray when supergfxd signals a mode or dGPU power change. Returns
+/// `Ok` once the tray icon is disabled, which is checked when
+/// `tray_enabled_changed` is called, or an error if the signals can't be
+/// received.
+async fn watch_supergfx(
+    gfx_proxy: &GfxProxy<'_>,
+    tray: &mut Handle<AsusTray>,
+    config: &Arc<Mutex<Config>>,
+    last: &mut (GfxMode, GfxPower),
+) -> zbus::Result<()> {
+    let modes = gfx_proxy.receive_notify_gfx().await?.map(|_| ());
+    let powers = gfx_proxy.receive_notify_gfx_status().await?.map(|_| ());
+    let mut changes = stream::select(modes, powers);
+    // The signals only say something changed, the current state is read so
+    // that a missed signal is caught up on at the next one
+    loop {
+        if !tray_enabled(config) {
+            return Ok(());
+        }
+        let status = (gfx_proxy.mode().await?, gfx_proxy.power().await?);
+        if status != *last {
+            set_tray_icon_and_tip(status.0, status.1, tray, true).await;
+            *last = status;
+        }
+        tokio::select! {
+            change = changes.next() => {
+                if change.is_none() {
+                    return Err(zbus::Error::Failure("supergfxd signal stream ended".into()));
+                }
+            }
+            _ = tray_enabled_notify().notified() => {}
+            _ = tokio::time::sleep(ENABLED_CHECK) => {}
+        }
+    }
+}
+
+/// Refresh the quick actions and icon when they are changed outside the tray,
+/// such as by a hotkey or from asusctl
+async fn watch_quick_actions(tray: Handle<AsusTray>) -> zbus::Result<()> {
+    let conn = rog_dbus::system_connection().await?;
+    let mut changes = Vec::new();
+    if let Ok(platform) = PlatformProxy::new(&conn).await {
+        let profiles = platform.receive_platform_profile_changed().await;
+        changes.push(profiles.map(|_| ()).boxed());
+        let limits = platform
+            .receive_charge_control_end_threshold_changed()
+            .await;
+        changes.push(limits.map(|_| ()).boxed());
+    }
+    // The lookup errors aren't `Send` so are dropped before the next await
+    let aura = find_iface_async::<AuraProxy>("xyz.ljones.Aura")
+        .await
+        .unwrap_or_default();
+    if let Some(aura) = aura.into_iter().next() {
+        let brightness = aura.receive_brightness_changed().await;
+        changes.push(brightness.map(|_| ()).boxed());
+    }
+    let attrs = find_iface_async::<AsusArmouryProxy>("xyz.ljones.AsusArmoury")
+        .await
+        .unwrap_or_default();
+    for attr in attrs {
+        if attr
+            .name()
+            .await
+            .is_ok_and(|n| n == FirmwareAttribute::PanelOverdrive)
+        {
+            let values = attr.receive_current_value_changed().await;
+            changes.push(values.map(|_| ()).boxed());
+        }
+    }
+    if let Ok(privacy) = PrivacyProxy::new(&conn).await {
+        if privacy.supported().await.is_ok() {
+            changes.push(
+                privacy
+                    .receive_camera_enabled_changed()
+                    .await
+                    .map(|_| ())
+                    .boxed(),
+            );
+            changes.push(
+                privacy
+                    .receive_mic_mute_led_changed()
+                    .await
+                    .map(|_| ())
+                    .boxed(),
+            );
+            changes.push(
+                privacy
+                    .receive_camera_led_changed()
+                    .await
+                    .map(|_| ())
+                    .boxed(),
+            );
+        }
+    }
+    let mut changes = stream::select_all(changes);
+    while changes.next().await.is_some() {
+        tray.update(|tray: &mut AsusTray| tray.refresh()).await;
+    }
+    Ok(())
+}
+
+/// The running tray, so that settings changed in the app reach it
+static TRAY: OnceLock<Handle<AsusTray>> = OnceLock::new();
+
+/// Switch what the tray icon shows, if the tray is running
+pub async fn set_tray_icon_scheme(scheme: TrayIconScheme) {
+    if let Some(tray) = TRAY.get() {
+        tray.update(|tray: &mut AsusTray| {
+            tray.scheme = scheme;
+            tray.update_icon();
+        })
+        .await;
+    }
+}
+
 /// The tray is controlled somewhat by `Arc<Mutex<SystemState>>`
 pub fn init_tray(_supported_properties: Vec<Properties>, config: Arc<Mutex<Config>>) {
     tokio::spawn(async move {
@@ -166,6 +774,13 @@ pub fn init_tray(_supported_properties: Vec<Properties>, config: Arc<Mutex<Confi
             current_title: TRAY_LABEL.to_string(),
             current_icon: rog_red.clone(),
             proxy,
+            quick: QuickActions::new(),
+            scheme: config
+                .lock()
+                .map(|c| c.tray_icon_scheme)
+                .unwrap_or_default(),
+            gfx_status: (GfxMode::None, GfxPower::Unknown, false),
+            charge_limit: None,
         };
 
         // TODO: return an error to the UI
@@ -181,6 +796,13 @@ pub fn init_tray(_supported_properties: Vec<Properties>, config: Arc<Mutex<Confi
         }
 
         info!("Tray started");
+        let watched = tray.clone();
+        tokio::spawn(async move {
+            watch_quick_actions(watched)
+                .await
+                .map_err(|e| warn!("Tray quick actions won't follow changes: {e}"))
+                .ok();
+        });
         let rog_blue = read_icon(&PathBuf::from("asus_notif_blue.png"));
         let rog_green = read_icon(&PathBuf::from("asus_notif_green.png"));
         let rog_white = read_icon(&PathBuf::from("asus_notif_white.png"));
@@ -192,9 +814,11 @@ pub fn init_tray(_supported_properties: Vec<Properties>, config: Arc<Mutex<Confi
             rog_white,
             gpu_integrated,
         });
+        tray.update(|tray: &mut AsusTray| tray.refresh()).await;
+        TRAY.set(tray.clone()).ok();
 
         let mut has_supergfx = false;
-        let conn = zbus::Connection::system().await.unwrap();
+        let conn = rog_dbus::system_connection().await.unwrap();
         if let Ok(gfx_proxy) = GfxProxy::new(&conn).await {
             match gfx_proxy.mode().await {
                 Ok(_) => {
@@ -222,33 +846,40 @@ pub fn init_tray(_supported_properties: Vec<Properties>, config: Arc<Mutex<Confi
                 },
             }
 
+            if has_supergfx {
+                tray.update(|tray: &mut AsusTray| tray.quick.add_gfx())
+                    .await;
+            } else {
+                tray.update(|tray: &mut AsusTray| tray.quick.add_platform_gpu())
+                    .await;
+            }
             info!("Started ROGTray");
-            let mut last_power = GfxPower::Unknown;
+            let mut last = (GfxMode::None, GfxPower::Unknown);
+            if has_supergfx {
+                match watch_supergfx(&gfx_proxy, &mut tray, &config, &mut last).await {
+                    Ok(()) => return,
+                    Err(e) => warn!("No supergfxd signals, polling the dGPU status: {e}"),
+                }
+            }
             let dev = find_dgpu();
             loop {
-                tokio::time::sleep(Duration::from_millis(1000)).await;
-                if let Ok(lock) = config.try_lock() {
-                    if !lock.enable_tray_icon {
-                        return;
-                    }
+                tokio::time::sleep(FALLBACK_POLL).await;
+                if !tray_enabled(&config) {
+                    return;
                 }
-                if has_supergfx {
-                    if let Ok(mode) = gfx_proxy.mode().await {
-                        if let Ok(power) = gfx_proxy.power().await {
-                            if last_power != power {
-                                set_tray_icon_and_tip(mode, power, &mut tray, has_supergfx).await;
-                                last_power = power;
-                            }
-                        }
-                    }
-                } else if let Some(dev) = dev.as_ref() {
-                    if let Ok(power) = dev.get_runtime_status() {
-                        if last_power != power {
-                            set_tray_icon_and_tip(GfxMode::Hybrid, power, &mut tray, has_supergfx)
-                                .await;
-                            last_power = power;
-                        }
+                let status = if has_supergfx {
+                    match (gfx_proxy.mode().await, gfx_proxy.power().await) {
+                        (Ok(mode), Ok(power)) => Some((mode, power)),
+                        _ => None,
                     }
+                } else {
+                    dev.as_ref()
+                        .and_then(|dev| dev.get_runtime_status().ok())
+                        .map(|power| (GfxMode::Hybrid, power))
+                };
+                if let Some(status) = status.filter(|s| *s != last) {
+                    set_tray_icon_and_tip(status.0, status.1, &mut tray, has_supergfx).await;
+                    last = status;
                 }
             }
         }
@@ -1,6 +1,9 @@
 pub mod setup_anime;
 pub mod setup_aura;
+pub mod setup_diagnostics;
 pub mod setup_fans;
+pub mod setup_first_run;
+pub mod setup_scenes;
 pub mod setup_system;
 
 use std::sync::{Arc, Mutex};
@@ -10,12 +13,20 @@ use log::warn;
 use rog_dbus::list_iface_blocking;
 use slint::{ComponentHandle, SharedString, Weak};
 
+use crate::accent::apply_accent_colour;
 use crate::config::Config;
+use crate::games::{game_name, running_app_ids, GameProfile};
+use crate::tray::{set_tray_icon_scheme, tray_enabled_changed, TrayIconScheme};
 use crate::ui::setup_anime::setup_anime_page;
 use crate::ui::setup_aura::setup_aura_page;
+use crate::ui::setup_diagnostics::{missing_page, setup_diagnostics};
 use crate::ui::setup_fans::setup_fan_curve_page;
+use crate::ui::setup_first_run::setup_first_run;
+use crate::ui::setup_scenes::setup_scenes_page;
 use crate::ui::setup_system::{setup_system_page, setup_system_page_callbacks};
-use crate::{AppSettingsPageData, MainWindow};
+use crate::{
+    AnimePageData, AppSettingsPageData, AuraPageData, GameProfileEntry, MainWindow, SystemPageData,
+};
 
 // this macro sets up:
 // - a link from UI callback -> dbus proxy property
@@ -97,11 +108,13 @@ pub fn setup_window(config: Arc<Mutex<Config>>) -> MainWindow {
     let available = list_iface_blocking().unwrap_or_default();
     ui.set_sidebar_items_avilable(
         [
-            // Needs to match the order of slint sidebar items
-            available.contains(&"xyz.ljones.Platform".to_string()),
-            available.contains(&"xyz.ljones.Aura".to_string()),
-            available.contains(&"xyz.ljones.Anime".to_string()),
+            // Needs to match the order of slint sidebar items. The System,
+            // Aura, and AniMe pages explain what is missing instead
+            true,
+            true,
+            true,
             available.contains(&"xyz.ljones.FanCurves".to_string()),
+            available.contains(&"xyz.ljones.Scenes".to_string()),
             true,
             true,
         ]
@@ -113,23 +126,53 @@ pub fn setup_window(config: Arc<Mutex<Config>>) -> MainWindow {
     });
 
     setup_app_settings_page(&ui, config.clone());
+    setup_diagnostics(&ui);
     if available.contains(&"xyz.ljones.Platform".to_string()) {
         setup_system_page(&ui, config.clone());
         setup_system_page_callbacks(&ui, config.clone());
+    } else {
+        let data = ui.global::<SystemPageData>();
+        data.set_available(false);
+        data.set_unsupported(missing_page("xyz.ljones.Platform"));
     }
     if available.contains(&"xyz.ljones.Aura".to_string()) {
         setup_aura_page(&ui, config.clone());
+    } else {
+        let data = ui.global::<AuraPageData>();
+        data.set_available(false);
+        data.set_unsupported(missing_page("xyz.ljones.Aura"));
     }
     if available.contains(&"xyz.ljones.Anime".to_string()) {
         setup_anime_page(&ui, config.clone());
+    } else {
+        let data = ui.global::<AnimePageData>();
+        data.set_available(false);
+        data.set_unsupported(missing_page("xyz.ljones.Anime"));
+    }
+    if available.contains(&"xyz.ljones.Scenes".to_string()) {
+        setup_scenes_page(&ui, config.clone());
     }
     if available.contains(&"xyz.ljones.FanCurves".to_string()) {
-        setup_fan_curve_page(&ui, config);
+        setup_fan_curve_page(&ui, config.clone());
     }
+    setup_first_run(&ui, config, &available);
 
     ui
 }
 
+fn set_game_profiles(ui: &MainWindow, profiles: &[GameProfile]) {
+    let entries: Vec<GameProfileEntry> = profiles
+        .iter()
+        .map(|g| GameProfileEntry {
+            app_id: g.app_id.to_string().into(),
+            name: g.name.as_str().into(),
+            profile: g.platform_profile as i32,
+        })
+        .collect();
+    ui.global::<AppSettingsPageData>()
+        .set_game_profiles(entries.as_slice().into());
+}
+
 pub fn setup_app_settings_page(ui: &MainWindow, config: Arc<Mutex<Config>>) {
     let config_copy = config.clone();
     let global = ui.global::<AppSettingsPageData>();
@@ -152,6 +195,16 @@ pub fn setup_app_settings_page(ui: &MainWindow, config: Arc<Mutex<Config>>) {
             lock.enable_tray_icon = enable;
             lock.write();
         }
+        tray_enabled_changed();
+    });
+    let config_copy = config.clone();
+    global.on_set_tray_icon_scheme(move |index| {
+        let scheme = TrayIconScheme::from(index);
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.tray_icon_scheme = scheme;
+            lock.write();
+        }
+        tokio::spawn(set_tray_icon_scheme(scheme));
     });
     let config_copy = config.clone();
     global.on_set_enable_dgpu_notifications(move |enable| {
@@ -160,11 +213,110 @@ pub fn setup_app_settings_page(ui: &MainWindow, config: Arc<Mutex<Config>>) {
             lock.write();
         }
     });
+    let config_copy = config.clone();
+    global.on_set_enable_profile_notifications(move |enable| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.notifications.receive_notify_platform_profile = enable;
+            lock.write();
+        }
+    });
+    let config_copy = config.clone();
+    global.on_set_enable_kbd_brightness_osd(move |enable| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.notifications.receive_notify_kbd_brightness = enable;
+            lock.write();
+        }
+    });
+    let config_copy = config.clone();
+    global.on_set_enable_slow_charger_notifications(move |enable| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.notifications.receive_notify_slow_charger = enable;
+            lock.write();
+        }
+    });
+    let config_copy = config.clone();
+    global.on_set_enable_xg_mobile_notifications(move |enable| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.notifications.receive_notify_xg_mobile = enable;
+            lock.write();
+        }
+    });
+    let config_copy = config.clone();
+    global.on_set_respect_do_not_disturb(move |enable| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.notifications.respect_do_not_disturb = enable;
+            lock.write();
+        }
+    });
+    let config_copy = config.clone();
+    global.on_set_accent_keyboard_colour(move |enable| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.accent_keyboard_colour = enable;
+            lock.write();
+        }
+        if enable {
+            tokio::spawn(async {
+                apply_accent_colour()
+                    .await
+                    .map_err(|e| warn!("Could not read the accent colour: {e}"))
+                    .ok();
+            });
+        }
+    });
+
+    let config_copy = config.clone();
+    let handle = ui.as_weak();
+    global.on_add_game_profile(move |app_id, profile| {
+        let Ok(app_id) = app_id.trim().parse::<u32>() else {
+            warn!("Not a Steam AppID: {app_id}");
+            return;
+        };
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.game_profiles.retain(|g| g.app_id != app_id);
+            lock.game_profiles.push(GameProfile {
+                app_id,
+                name: game_name(app_id).unwrap_or_default(),
+                platform_profile: profile.into(),
+            });
+            lock.write();
+            if let Some(handle) = handle.upgrade() {
+                set_game_profiles(&handle, &lock.game_profiles);
+            }
+        }
+    });
+    let config_copy = config.clone();
+    let handle = ui.as_weak();
+    global.on_remove_game_profile(move |index| {
+        if let Ok(mut lock) = config_copy.try_lock() {
+            if (index as usize) < lock.game_profiles.len() {
+                lock.game_profiles.remove(index as usize);
+                lock.write();
+            }
+            if let Some(handle) = handle.upgrade() {
+                set_game_profiles(&handle, &lock.game_profiles);
+            }
+        }
+    });
+    global.on_detect_running_game(|| {
+        running_app_ids()
+            .first()
+            .map(|id| id.to_string().into())
+            .unwrap_or_default()
+    });
 
     if let Ok(lock) = config.try_lock() {
+        set_game_profiles(ui, &lock.game_profiles);
         global.set_run_in_background(lock.run_in_background);
         global.set_startup_in_background(lock.startup_in_background);
         global.set_enable_tray_icon(lock.enable_tray_icon);
+        global.set_tray_icon_scheme(lock.tray_icon_scheme as i32);
         global.set_enable_dgpu_notifications(lock.notifications.enabled);
+        global.set_enable_profile_notifications(lock.notifications.receive_notify_platform_profile);
+        global.set_enable_kbd_brightness_osd(lock.notifications.receive_notify_kbd_brightness);
+        global
+            .set_enable_slow_charger_notifications(lock.notifications.receive_notify_slow_charger);
+        global.set_enable_xg_mobile_notifications(lock.notifications.receive_notify_xg_mobile);
+        global.set_respect_do_not_disturb(lock.notifications.respect_do_not_disturb);
+        global.set_accent_keyboard_colour(lock.accent_keyboard_colour);
     }
 }
@@ -7,6 +7,7 @@ use rog_dbus::zbus_anime::AnimeProxy;
 use slint::ComponentHandle;
 
 use crate::config::Config;
+use crate::ui::setup_diagnostics::{push_unsupported, unsupported};
 use crate::ui::show_toast;
 use crate::{set_ui_callbacks, set_ui_props_async, AnimePageData, MainWindow};
 
@@ -26,11 +27,24 @@ pub fn setup_anime_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
             set_ui_props_async!(handle, anime, AnimePageData, off_when_suspended);
             set_ui_props_async!(handle, anime, AnimePageData, off_when_unplugged);
 
-            let builtins = anime.builtin_animations().await.unwrap_or_default();
+            let builtins = anime.builtin_animations().await;
+            let builtins_available = builtins.is_ok();
+            let builtins = builtins.unwrap_or_default();
             handle
                 .upgrade_in_event_loop(move |handle| {
                     {
                         let global = handle.global::<AnimePageData>();
+                        if !builtins_available {
+                            global.set_builtins_available(false);
+                            global.set_unsupported(push_unsupported(
+                                global.get_unsupported(),
+                                unsupported(
+                                    "Built-in animations",
+                                    "The display did not report its built-in animations. Older \
+                                     AniMe firmware may not have them.",
+                                ),
+                            ));
+                        }
                         global.set_boot_anim(builtins.boot as i32);
                         global.set_awake_anim(builtins.awake as i32);
                         global.set_sleep_anim(builtins.sleep as i32);
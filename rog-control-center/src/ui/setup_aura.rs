@@ -1,17 +1,74 @@
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
-use log::{debug, error, info};
-use rog_aura::keyboard::LaptopAuraPower;
-use rog_aura::{AuraDeviceType, PowerZones};
+use config_traits::StdConfig;
+use log::{debug, error, info, warn};
+use rog_aura::keyboard::{LaptopAuraPower, LayoutInfo, LedCode, LedUsbPackets};
+use rog_aura::names::AuraNames;
+use rog_aura::palette::{Palettes, PALETTE_FILE};
+use rog_aura::{
+    AuraDeviceType, AuraEffect, AuraModeNum, AuraProfileLighting, AuraShuffle, AuraZone, Colour,
+    LedBrightness, PowerZones,
+};
 use rog_dbus::zbus_aura::AuraProxy;
-use slint::{ComponentHandle, Model, RgbaColor, SharedString};
+use rog_dbus::{get_all, system_connection};
+use rog_platform::platform::PlatformProfile;
+use slint::{ComponentHandle, Model, ModelRc, RgbaColor, SharedString, VecModel};
 
 use crate::config::Config;
+use crate::portal::{can_pick_colour, pick_colour};
+use crate::ui::setup_diagnostics::{push_unsupported, unsupported};
 use crate::ui::show_toast;
 use crate::{
-    set_ui_callbacks, set_ui_props_async, AuraPageData, MainWindow, PowerZones as SlintPowerZones,
+    set_ui_callbacks, AuraKey, AuraPageData, MainWindow, PowerZones as SlintPowerZones,
+    SavedColour, ZoneBrightness,
 };
 
+/// The order of `profile_lighting` in `AuraPageData`
+const LIGHTING_PROFILES: [PlatformProfile; 3] = [
+    PlatformProfile::Quiet,
+    PlatformProfile::Balanced,
+    PlatformProfile::Performance,
+];
+
+/// The saved colours, shared with asusctl
+fn palette_path() -> PathBuf {
+    Config::config_dir().join(PALETTE_FILE)
+}
+
+fn set_saved_colours(ui: &MainWindow, palettes: &Palettes) {
+    let saved: Vec<SavedColour> = palettes
+        .entries()
+        .into_iter()
+        .map(|(name, c)| SavedColour {
+            name: name.into(),
+            colour: slint::Color::from_rgb_u8(c.r, c.g, c.b),
+        })
+        .collect();
+    ui.global::<AuraPageData>()
+        .set_saved_colours(saved.as_slice().into());
+}
+
+/// Bind the current mode and brightness to `profile`, or stop setting
+/// lighting for it
+async fn set_profile_lighting(
+    aura: &AuraProxy<'static>,
+    profile: PlatformProfile,
+    enabled: bool,
+) -> zbus::Result<()> {
+    let mut bindings = aura.profile_lighting().await?;
+    if enabled {
+        bindings.insert(profile, AuraProfileLighting {
+            enabled,
+            effect: aura.led_mode_data().await?,
+            brightness: aura.brightness().await?,
+        });
+    } else if let Some(lighting) = bindings.get_mut(&profile) {
+        lighting.enabled = false;
+    }
+    aura.set_profile_lighting(bindings).await
+}
+
 fn decode_hex(s: &str) -> RgbaColor<u8> {
     let s = s.trim_start_matches('#');
     if s.len() < 6 {
@@ -34,10 +91,75 @@ fn decode_hex(s: &str) -> RgbaColor<u8> {
     }
 }
 
+/// Fill the keyboard picture from the layout and set up editing of the key
+/// colours
+fn setup_keyboard_layout(handle: &MainWindow, aura: AuraProxy<'static>, layout: LayoutInfo) {
+    let data = handle.global::<AuraPageData>();
+    let colour = data.get_color1();
+    let codes: Vec<Option<LedCode>> = layout.keys.iter().map(|k| k.led_code()).collect();
+    let keys: Vec<AuraKey> = layout
+        .keys
+        .iter()
+        .zip(&codes)
+        .map(|(k, code)| AuraKey {
+            label: code.map(<&str>::from).unwrap_or(&k.led).into(),
+            x: k.x as f32,
+            y: k.y as f32,
+            width: k.width as f32,
+            height: k.height as f32,
+            colour,
+        })
+        .collect();
+    data.set_layout_width(layout.width as f32);
+    data.set_layout_height(layout.height as f32);
+    data.set_direct_supported(layout.per_key || !layout.zones.is_empty());
+    data.set_layout_keys(ModelRc::new(VecModel::from(keys)));
+
+    let handle_copy = handle.as_weak();
+    data.on_cb_fill_key_colours(move |colour| {
+        let Some(handle) = handle_copy.upgrade() else {
+            return;
+        };
+        let keys = handle.global::<AuraPageData>().get_layout_keys();
+        for i in 0..keys.row_count() {
+            if let Some(mut key) = keys.row_data(i) {
+                key.colour = colour;
+                keys.set_row_data(i, key);
+            }
+        }
+    });
+
+    let handle_copy = handle.as_weak();
+    let zoned = !layout.zones.is_empty();
+    data.on_cb_apply_key_colours(move |keys| {
+        let mut packets = if zoned {
+            LedUsbPackets::new_zoned(true)
+        } else {
+            LedUsbPackets::new_per_key()
+        };
+        for (key, code) in keys.iter().zip(&codes) {
+            if let Some(code) = code {
+                let c = key.colour;
+                packets.set(*code, c.red(), c.green(), c.blue());
+            }
+        }
+        let handle_copy = handle_copy.clone();
+        let aura = aura.clone();
+        tokio::spawn(async move {
+            show_toast(
+                "Key colours applied".into(),
+                "Failed to apply key colours".into(),
+                handle_copy,
+                aura.direct_addressing_raw(packets.into()).await,
+            );
+        });
+    });
+}
+
 /// Returns the first available Aura interface
 // TODO: return all
-async fn find_aura_iface() -> Result<AuraProxy<'static>, Box<dyn std::error::Error>> {
-    let conn = zbus::Connection::system().await?;
+pub async fn find_aura_iface() -> Result<AuraProxy<'static>, Box<dyn std::error::Error>> {
+    let conn = system_connection().await?;
     let f = zbus::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/").await?;
     let interfaces = f.get_managed_objects().await?;
     let mut aura_paths = Vec::new();
@@ -72,6 +194,93 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
     ui.global::<AuraPageData>()
         .on_cb_hex_to_colour(|s| decode_hex(s.as_str()).into());
 
+    let handle = ui.as_weak();
+    ui.global::<AuraPageData>().on_cb_pick_colour(move |index| {
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            let colour = match pick_colour().await {
+                Ok(colour) => colour,
+                Err(e) => {
+                    warn!("Could not pick a colour: {e}");
+                    return;
+                }
+            };
+            handle
+                .upgrade_in_event_loop(move |handle| {
+                    let data = handle.global::<AuraPageData>();
+                    let mut effect = data.get_led_mode_data();
+                    if index == 2 {
+                        effect.colour2 = colour;
+                    } else {
+                        effect.colour1 = colour;
+                    }
+                    data.invoke_update_led_mode_data(effect.clone());
+                    handle.invoke_external_colour_change();
+                    data.invoke_cb_led_mode_data(effect);
+                })
+                .map_err(|e| error!("{e:}"))
+                .ok();
+        });
+    });
+
+    match Palettes::load(&palette_path()) {
+        Ok(palettes) => set_saved_colours(ui, &palettes),
+        Err(e) => warn!("Could not load the saved colours: {e}"),
+    }
+    let handle = ui.as_weak();
+    ui.global::<AuraPageData>()
+        .on_cb_save_colour(move |name, colour| {
+            let Some(ui) = handle.upgrade() else {
+                return;
+            };
+            let path = palette_path();
+            // Not saved over a file which didn't load, so it isn't lost
+            let result = Palettes::load(&path).and_then(|mut palettes| {
+                palettes.insert(name.as_str(), Colour {
+                    r: colour.red(),
+                    g: colour.green(),
+                    b: colour.blue(),
+                });
+                palettes.save(&path)?;
+                Ok(palettes)
+            });
+            match result {
+                Ok(palettes) => set_saved_colours(&ui, &palettes),
+                Err(e) => {
+                    error!("Could not save the colour {name}: {e}");
+                    ui.invoke_show_toast(format!("Could not save the colour: {e}").into());
+                }
+            }
+        });
+
+    let handle = ui.as_weak();
+    tokio::spawn(async move {
+        let can_pick = can_pick_colour().await;
+        handle
+            .upgrade_in_event_loop(move |handle| {
+                handle
+                    .global::<AuraPageData>()
+                    .set_can_pick_colour(can_pick);
+            })
+            .ok();
+    });
+
+    let names = AuraNames::for_locale();
+    let power_zone_names: Vec<SharedString> = [
+        PowerZones::Logo,
+        PowerZones::Keyboard,
+        PowerZones::Lightbar,
+        PowerZones::Lid,
+        PowerZones::RearGlow,
+        PowerZones::KeyboardAndLightbar,
+        PowerZones::Ally,
+    ]
+    .into_iter()
+    .map(|zone| names.power_zone(zone).into())
+    .collect();
+    ui.global::<AuraPageData>()
+        .set_power_zone_names(power_zone_names.as_slice().into());
+
     let handle = ui.as_weak();
     tokio::spawn(async move {
         let Ok(aura) = find_aura_iface().await else {
@@ -79,17 +288,64 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
             return Ok::<(), zbus::Error>(());
         };
 
-        set_ui_props_async!(handle, aura, AuraPageData, brightness);
-        set_ui_props_async!(handle, aura, AuraPageData, led_mode);
-        set_ui_props_async!(handle, aura, AuraPageData, led_mode_data);
-        set_ui_props_async!(handle, aura, AuraPageData, led_power);
-        set_ui_props_async!(handle, aura, AuraPageData, device_type);
-
-        if let Ok(mut pow3r) = aura.supported_power_zones().await {
-            let dev_type = aura
-                .device_type()
-                .await
-                .unwrap_or(AuraDeviceType::LaptopKeyboard2021);
+        // One GetAll instead of a call per property
+        let props = get_all(aura.inner().path(), "xyz.ljones.Aura")
+            .await
+            .map_err(|e| error!("Could not read Aura properties: {e}"))
+            .unwrap_or_default();
+        let brightness: Option<LedBrightness> = props.get("Brightness");
+        let brightness_percent: Option<u8> = props.get("BrightnessPercent");
+        let led_mode: Option<AuraModeNum> = props.get("LedMode");
+        let led_mode_data: Option<AuraEffect> = props.get("LedModeData");
+        let led_power: Option<LaptopAuraPower> = props.get("LedPower");
+        let device_type: Option<AuraDeviceType> = props.get("DeviceType");
+        let shuffle: Option<AuraShuffle> = props.get("Shuffle");
+        let zone_brightness: Option<Vec<(AuraZone, u8)>> = props.get("ZoneBrightness");
+        let per_zone_modes: Option<bool> = props.get("PerZoneModes");
+        handle
+            .upgrade_in_event_loop(move |handle| {
+                let data = handle.global::<AuraPageData>();
+                if let Some(value) = brightness {
+                    data.set_brightness(value.into());
+                }
+                if let Some(value) = brightness_percent {
+                    data.set_brightness_percent(value as i32);
+                }
+                if let Some(value) = led_mode {
+                    data.set_led_mode(value.into());
+                }
+                if let Some(value) = led_mode_data {
+                    data.set_led_mode_data(value.into());
+                }
+                if let Some(value) = led_power {
+                    data.set_led_power(value.into());
+                }
+                if let Some(value) = device_type {
+                    data.set_device_type(value.into());
+                }
+                if let Some(value) = shuffle {
+                    data.set_shuffle_enabled(value.enabled);
+                }
+                data.set_per_zone_modes(per_zone_modes.unwrap_or_default());
+                if let Some(zones) = zone_brightness {
+                    let zones: Vec<ZoneBrightness> = zones
+                        .into_iter()
+                        .map(|(zone, percent)| ZoneBrightness {
+                            zone: zone.into(),
+                            percent: percent as i32,
+                        })
+                        .collect();
+                    data.set_zone_brightness(zones.as_slice().into());
+                }
+            })
+            .map_err(|e| error!("{e:}"))
+            .ok();
+
+        if let Some(mut pow3r) = props
+            .get::<Vec<PowerZones>>("SupportedPowerZones")
+            .filter(|zones| !zones.is_empty())
+        {
+            let dev_type = device_type.unwrap_or(AuraDeviceType::LaptopKeyboard2021);
             log::debug!("Available LED power modes {pow3r:?}");
             handle
                 .upgrade_in_event_loop(move |handle| {
@@ -121,9 +377,24 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
                     }
                 })
                 .ok();
+        } else {
+            handle
+                .upgrade_in_event_loop(|handle| {
+                    let data = handle.global::<AuraPageData>();
+                    data.set_supported_power_zones(ModelRc::default());
+                    data.set_unsupported(push_unsupported(
+                        data.get_unsupported(),
+                        unsupported(
+                            "Power settings",
+                            "asusd knows of no LED power zones for this keyboard, so when it is \
+                             lit while booting, awake, or asleep can't be set.",
+                        ),
+                    ));
+                })
+                .ok();
         }
 
-        if let Ok(modes) = aura.supported_basic_modes().await {
+        if let Some(modes) = props.get::<Vec<AuraModeNum>>("SupportedBasicModes") {
             log::debug!("Available LED modes {modes:?}");
             handle
                 .upgrade_in_event_loop(move |handle| {
@@ -131,15 +402,8 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
                     handle
                         .global::<AuraPageData>()
                         .set_supported_basic_modes(m.as_slice().into());
-                    // Get the translated names
-                    let names = handle.global::<AuraPageData>().get_mode_names();
-
-                    let res: Vec<SharedString> = names
-                        .iter()
-                        .enumerate()
-                        .filter(|(n, _)| modes.contains(&(*n as i32).into()) && *n != 9)
-                        .map(|(_, i)| i)
-                        .collect();
+                    let res: Vec<SharedString> =
+                        modes.iter().map(|m| names.mode(*m).into()).collect();
                     handle
                         .global::<AuraPageData>()
                         .set_available_mode_names(res.as_slice().into());
@@ -148,6 +412,34 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
                 .ok();
         }
 
+        match aura.keyboard_layout().await {
+            Ok(layout) => {
+                let proxy_copy = aura.clone();
+                handle
+                    .upgrade_in_event_loop(move |handle| {
+                        setup_keyboard_layout(&handle, proxy_copy, layout);
+                    })
+                    .map_err(|e| error!("{e:}"))
+                    .ok();
+            }
+            Err(e) => {
+                info!("No keyboard layout available: {e}");
+                handle
+                    .upgrade_in_event_loop(|handle| {
+                        let data = handle.global::<AuraPageData>();
+                        data.set_unsupported(push_unsupported(
+                            data.get_unsupported(),
+                            unsupported(
+                                "Keyboard layout",
+                                "No layout matches this laptop's board name, so keys can't be \
+                                 shown or coloured one at a time.",
+                            ),
+                        ));
+                    })
+                    .ok();
+            }
+        }
+
         let proxy_copy = aura.clone();
         handle
             .upgrade_in_event_loop(move |handle| {
@@ -158,6 +450,13 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
                     "Setting keyboard LED brightness failed"
                 );
 
+                set_ui_callbacks!(handle,
+                    AuraPageData(as i32),
+                    proxy_copy.brightness_percent(as u8),
+                    "Keyboard LED brightness successfully set to {}%",
+                    "Setting keyboard LED brightness failed"
+                );
+
                 set_ui_callbacks!(handle,
                     AuraPageData(.into()),
                     proxy_copy.led_mode(.into()),
@@ -183,6 +482,85 @@ pub fn setup_aura_page(ui: &MainWindow, _states: Arc<Mutex<Config>>) {
             })
             .ok();
 
+        let bindings = aura.profile_lighting().await.unwrap_or_default();
+        let bound: Vec<bool> = LIGHTING_PROFILES
+            .iter()
+            .map(|profile| bindings.get(profile).is_some_and(|l| l.enabled))
+            .collect();
+        let handle_copy = handle.clone();
+        let proxy_copy = aura.clone();
+        handle
+            .upgrade_in_event_loop(move |handle| {
+                let data = handle.global::<AuraPageData>();
+                data.set_profile_lighting(bound.as_slice().into());
+                let handle_lighting = handle_copy.clone();
+                let proxy_lighting = proxy_copy.clone();
+                data.on_cb_profile_lighting(move |index, enabled| {
+                    let Some(profile) = LIGHTING_PROFILES.get(index as usize).copied() else {
+                        return;
+                    };
+                    let handle_copy = handle_lighting.clone();
+                    let proxy_copy = proxy_lighting.clone();
+                    tokio::spawn(async move {
+                        show_toast(
+                            "Aura profile lighting changed".into(),
+                            "Failed to set the Aura profile lighting".into(),
+                            handle_copy,
+                            set_profile_lighting(&proxy_copy, profile, enabled).await,
+                        );
+                    });
+                });
+                let handle_shuffle = handle_copy.clone();
+                let proxy_shuffle = proxy_copy.clone();
+                data.on_cb_shuffle_enabled(move |enabled| {
+                    let handle_copy = handle_shuffle.clone();
+                    let proxy_copy = proxy_shuffle.clone();
+                    tokio::spawn(async move {
+                        // Keep the interval and palette set with asusctl
+                        let mut shuffle = proxy_copy.shuffle().await.unwrap_or_default();
+                        shuffle.enabled = enabled;
+                        show_toast(
+                            "Aura colour shuffle changed".into(),
+                            "Failed to set the Aura colour shuffle".into(),
+                            handle_copy,
+                            proxy_copy.set_shuffle(shuffle).await,
+                        );
+                    });
+                });
+                let handle_zones = handle_copy.clone();
+                let proxy_zones = proxy_copy.clone();
+                data.on_cb_zone_brightness(move |zones| {
+                    let handle_copy = handle_zones.clone();
+                    let proxy_copy = proxy_zones.clone();
+                    let zones: Vec<(AuraZone, u8)> = zones
+                        .iter()
+                        .map(|z| (AuraZone::from(z.zone), z.percent as u8))
+                        .collect();
+                    tokio::spawn(async move {
+                        show_toast(
+                            "Aura zone brightness changed".into(),
+                            "Failed to set the Aura zone brightness".into(),
+                            handle_copy,
+                            proxy_copy.set_zone_brightness(zones).await,
+                        );
+                    });
+                });
+                data.on_cb_shuffle_colour(move || {
+                    let handle_copy = handle_copy.clone();
+                    let proxy_copy = proxy_copy.clone();
+                    tokio::spawn(async move {
+                        show_toast(
+                            "Picked a random colour".into(),
+                            "Failed to pick a random colour".into(),
+                            handle_copy,
+                            proxy_copy.shuffle_colour().await,
+                        );
+                    });
+                });
+            })
+            .map_err(|e| error!("{e:}"))
+            .ok();
+
         let handle_copy = handle.clone();
         let proxy_copy = aura.clone();
         handle
@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use log::error;
+use rog_aura::AuraDeviceInfo;
+use rog_dbus::zbus_aura::AuraProxy;
+use rog_dbus::zbus_health::HealthProxy;
+use slint::{ComponentHandle, Model, ModelRc, SharedString};
+
+use crate::ui::setup_first_run::FEATURES;
+use crate::{DiagnosticsData, MainWindow, UnsupportedSection};
+
+/// Kernel modules asusd relies on, and what each one provides
+const MODULES: [(&str, &str); 3] = [
+    (
+        "asus_nb_wmi",
+        "platform profiles, charge limit, and keyboard brightness",
+    ),
+    (
+        "asus_armoury",
+        "firmware attributes such as PPT limits and panel overdrive",
+    ),
+    ("hid_asus", "USB keyboards and the AniMe Matrix"),
+];
+
+pub fn unsupported(title: &str, reason: &str) -> UnsupportedSection {
+    UnsupportedSection {
+        title: title.into(),
+        reason: reason.into(),
+    }
+}
+
+/// Add a section to the unsupported list of a page
+pub fn push_unsupported(
+    list: ModelRc<UnsupportedSection>,
+    section: UnsupportedSection,
+) -> ModelRc<UnsupportedSection> {
+    let mut sections: Vec<UnsupportedSection> = list.iter().collect();
+    sections.push(section);
+    sections.as_slice().into()
+}
+
+/// The explanation for a page whose asusd interface is missing, using the
+/// same reasons as the first-run wizard
+pub fn missing_page(iface: &str) -> ModelRc<UnsupportedSection> {
+    let sections: Vec<UnsupportedSection> = FEATURES
+        .iter()
+        .filter(|(_, feature, ..)| *feature == iface)
+        .map(|(name, _, _, missing)| unsupported(name, missing))
+        .collect();
+    sections.as_slice().into()
+}
+
+/// Fill the diagnostics view each time it is opened from an unsupported
+/// section
+pub fn setup_diagnostics(ui: &MainWindow) {
+    let handle = ui.as_weak();
+    ui.global::<DiagnosticsData>().on_open(move || {
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            let lines = collect_diagnostics().await;
+            handle
+                .upgrade_in_event_loop(move |handle| {
+                    let data = handle.global::<DiagnosticsData>();
+                    data.set_lines(lines.as_slice().into());
+                    data.set_show(true);
+                })
+                .map_err(|e| error!("{e:}"))
+                .ok();
+        });
+    });
+}
+
+async fn collect_diagnostics() -> Vec<SharedString> {
+    let mut lines = Vec::new();
+    let kernel = std::fs::read_to_string("/proc/sys/kernel/osrelease").unwrap_or_default();
+    lines.push(format!("Kernel: {}", kernel.trim()).into());
+
+    for (module, provides) in MODULES {
+        let state = if Path::new("/sys/module").join(module).exists() {
+            "loaded"
+        } else {
+            "not loaded"
+        };
+        lines.push(format!("Module {module} is {state}, it provides {provides}").into());
+    }
+
+    match asusd_lines().await {
+        Ok(asusd) => lines.extend(asusd),
+        Err(e) => lines.push(format!("Could not reach asusd: {e}").into()),
+    }
+    lines.push("The daemon log has more detail: journalctl -b -u asusd".into());
+    lines
+}
+
+/// One line naming an Aura device, leaving out what asusd could not read
+fn aura_device_line(info: &AuraDeviceInfo) -> String {
+    let mut line = format!("Aura device {:?}", info.device_type);
+    let name = format!("{} {}", info.manufacturer, info.product);
+    if !name.trim().is_empty() {
+        line += &format!(", {}", name.trim());
+    }
+    if !info.product_id.is_empty() {
+        line += &format!(", USB {}:{}", info.vendor_id, info.product_id);
+    }
+    if !info.firmware_version.is_empty() {
+        line += &format!(", firmware {}", info.firmware_version);
+    }
+    line += &format!(", protocol {}", info.protocol);
+    if !info.path.is_empty() {
+        line += &format!(", at {}", info.path);
+    }
+    line
+}
+
+/// The interfaces asusd has, the Aura devices, the controllers it couldn't
+/// start or is restarting, and the warnings, errors, and restarts it reports
+async fn asusd_lines() -> zbus::Result<Vec<SharedString>> {
+    let conn = rog_dbus::system_connection().await?;
+    let objects = zbus::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/")
+        .await?
+        .get_managed_objects()
+        .await?;
+    let mut lines = Vec::new();
+    for (name, iface, ..) in FEATURES {
+        let found = objects
+            .values()
+            .any(|ifaces| ifaces.keys().any(|k| k.as_str() == iface));
+        let state = if found { "found" } else { "missing" };
+        lines.push(format!("{name} ({iface}) is {state}").into());
+    }
+
+    let mut auras: Vec<_> = objects
+        .iter()
+        .filter(|(_, ifaces)| ifaces.keys().any(|k| k.as_str() == "xyz.ljones.Aura"))
+        .map(|(path, _)| path.clone())
+        .collect();
+    auras.sort();
+    for path in auras {
+        let aura = AuraProxy::builder(&conn)
+            .path(path.clone())?
+            .build()
+            .await?;
+        match aura.device_info().await {
+            Ok(info) => lines.push(aura_device_line(&info).into()),
+            Err(e) => lines.push(format!("Could not identify the Aura device {path}: {e}").into()),
+        }
+    }
+
+    let health = HealthProxy::builder(&conn).build().await?;
+    for (name, state, _, reason) in health.status().await? {
+        match state.as_str() {
+            "running" | "finished" => {}
+            "unavailable" => lines.push(format!("{name} is unavailable: {reason}").into()),
+            _ => lines.push(format!("{name} is {state}").into()),
+        }
+    }
+    let mut reported = false;
+    for (source, warning) in health.warnings().await? {
+        lines.push(format!("Warning from {source}: {warning}").into());
+        reported = true;
+    }
+    for (source, err) in health.last_errors().await? {
+        lines.push(format!("Last error from {source}: {err}").into());
+        reported = true;
+    }
+    for (task, count) in health.restart_counts().await? {
+        if count > 0 {
+            lines.push(format!("{task} restarted {count} times").into());
+            reported = true;
+        }
+    }
+    if !reported {
+        lines.push("asusd reports no warnings or errors".into());
+    }
+    Ok(lines)
+}
@@ -1,5 +1,6 @@
 use std::sync::{Arc, Mutex};
 
+use futures_util::StreamExt;
 use log::error;
 use rog_dbus::zbus_fan_curves::FanCurvesProxy;
 use rog_dbus::zbus_platform::PlatformProxy;
@@ -10,6 +11,9 @@ use slint::{ComponentHandle, Model, Weak};
 use crate::config::Config;
 use crate::{FanPageData, FanType, MainWindow, Node};
 
+/// How long the fan page tests a curve for
+const FAN_TEST_SECS: u32 = 30;
+
 pub fn update_fan_data(
     handle: Weak<MainWindow>,
     bal: Vec<CurveData>,
@@ -101,7 +105,7 @@ pub fn setup_fan_curve_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
 
     tokio::spawn(async move {
         // Create the connections/proxies here to prevent future delays in process
-        let conn = match zbus::Connection::system().await {
+        let conn = match rog_dbus::system_connection().await {
             Ok(conn) => conn,
             Err(e) => {
                 error!("{e:}");
@@ -167,6 +171,7 @@ pub fn setup_fan_curve_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
         };
 
         update_fan_data(handle, balanced, perf, quiet);
+        watch_fan_test(handle_copy.clone(), fans.clone());
 
         let handle_next1 = handle_copy.clone();
         if let Err(e) = handle_copy.upgrade_in_event_loop(move |handle| {
@@ -203,6 +208,28 @@ pub fn setup_fan_curve_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
                     update_fan_data(handle_next, balanced, perf, quiet);
                 });
             });
+            let fans1 = fans.clone();
+            global.on_test_fan_data(move |fan, data| {
+                let fans = fans1.clone();
+                let data: Vec<Node> = data.iter().collect();
+                let data = fan_data_for(fan, true, data);
+                tokio::spawn(async move {
+                    fans.test_fan_curve(data, FAN_TEST_SECS)
+                        .await
+                        .map_err(|e| error!("{e:}"))
+                        .ok()
+                });
+            });
+            let fans1 = fans.clone();
+            global.on_stop_fan_test(move || {
+                let fans = fans1.clone();
+                tokio::spawn(async move {
+                    fans.stop_fan_curve_test()
+                        .await
+                        .map_err(|e| error!("{e:}"))
+                        .ok()
+                });
+            });
             global.on_set_fan_data(move |fan, profile, enabled, data| {
                 let fans = fans.clone();
                 let data: Vec<Node> = data.iter().collect();
@@ -220,6 +247,45 @@ pub fn setup_fan_curve_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
     });
 }
 
+/// Show the samples of a running fan curve test, and clear them when it ends
+fn watch_fan_test(handle: Weak<MainWindow>, fans: FanCurvesProxy<'static>) {
+    tokio::spawn(async move {
+        let (Ok(mut samples), Ok(mut ended)) = (
+            fans.receive_fan_curve_test_sample().await,
+            fans.receive_fan_curve_test_ended().await,
+        ) else {
+            error!("Could not watch for fan curve test signals");
+            return;
+        };
+        loop {
+            let status = tokio::select! {
+                Some(sample) = samples.next() => {
+                    let Ok(args) = sample.args() else {
+                        continue;
+                    };
+                    let mut status = format!("{}s left", args.remaining());
+                    for (fan, rpm) in args.fans() {
+                        status.push_str(&format!(", {fan} {rpm} RPM"));
+                    }
+                    for (sensor, temp) in args.temps() {
+                        status.push_str(&format!(", {sensor} {temp:.0}°C"));
+                    }
+                    Some(status)
+                }
+                Some(_) = ended.next() => None,
+                else => return,
+            };
+            handle
+                .upgrade_in_event_loop(move |handle| {
+                    let global = handle.global::<FanPageData>();
+                    global.set_testing(status.is_some());
+                    global.set_test_status(status.unwrap_or_default().into());
+                })
+                .ok();
+        }
+    });
+}
+
 fn fan_data_for(fan: FanType, enabled: bool, data: Vec<Node>) -> CurveData {
     let mut temp = [0u8; 8];
     let mut pwm = [0u8; 8];
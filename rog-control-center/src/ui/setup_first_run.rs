@@ -0,0 +1,267 @@
+use std::sync::{Arc, Mutex};
+
+use config_traits::StdConfig;
+use log::{error, info};
+use rog_dbus::asus_armoury::AsusArmouryProxy;
+use rog_dbus::zbus_capabilities::CapabilitiesProxy;
+use rog_dbus::zbus_platform::PlatformProxy;
+use rog_platform::asus_armoury::FirmwareAttribute;
+use rog_platform::platform::Properties;
+use slint::{ComponentHandle, Model, Weak};
+
+use crate::config::Config;
+use crate::tray::tray_enabled_changed;
+use crate::ui::show_toast;
+use crate::zbus_proxies::find_iface_async;
+use crate::{FeatureEntry, FirstRunData, MainWindow};
+
+/// The wizard steps, matching `FirstRunData.steps`
+const STEP_FEATURES: i32 = 0;
+const STEP_CHARGE_LIMIT: i32 = 1;
+const STEP_PANEL_OD: i32 = 2;
+const STEP_GPU_MUX: i32 = 3;
+const STEP_APP: i32 = 4;
+
+/// Each page of the app with the interface it needs, what it does, and why it
+/// might be missing
+pub(crate) const FEATURES: [(&str, &str, &str, &str); 6] = [
+    (
+        "System Control",
+        "xyz.ljones.Platform",
+        "Platform profiles, charge limit, and power settings",
+        "asusd is not running or this laptop has no asus-nb-wmi platform device",
+    ),
+    (
+        "Firmware attributes",
+        "xyz.ljones.AsusArmoury",
+        "PPT limits, panel overdrive, GPU MUX, and other firmware settings",
+        "Needs a kernel with the asus-armoury driver",
+    ),
+    (
+        "Keyboard Aura",
+        "xyz.ljones.Aura",
+        "Keyboard lighting modes and colours",
+        "No RGB keyboard was found, or it is not supported yet",
+    ),
+    (
+        "AniMe Matrix",
+        "xyz.ljones.Anime",
+        "The LED matrix display on the lid",
+        "This laptop has no AniMe Matrix display",
+    ),
+    (
+        "Fan Curves",
+        "xyz.ljones.FanCurves",
+        "Custom fan curves for each platform profile",
+        "This laptop's firmware does not allow custom fan curves",
+    ),
+    (
+        "Scenes",
+        "xyz.ljones.Scenes",
+        "Lighting saved together and applied by name",
+        "Needs a keyboard with Aura or an AniMe display",
+    ),
+];
+
+/// Show the first-run wizard if it has not been finished or skipped
+pub fn setup_first_run(ui: &MainWindow, config: Arc<Mutex<Config>>, available: &[String]) {
+    let data = ui.global::<FirstRunData>();
+    if let Ok(lock) = config.try_lock() {
+        if lock.first_run_done {
+            return;
+        }
+        data.set_run_in_background(lock.run_in_background);
+        data.set_enable_tray_icon(lock.enable_tray_icon);
+    }
+
+    let features: Vec<FeatureEntry> = FEATURES
+        .iter()
+        .map(|(name, iface, detail, missing)| {
+            let found = available.iter().any(|a| a == iface);
+            FeatureEntry {
+                name: (*name).into(),
+                available: found,
+                detail: if found { *detail } else { *missing }.into(),
+            }
+        })
+        .collect();
+    data.set_features(features.as_slice().into());
+    data.set_show(true);
+
+    let config_copy = config.clone();
+    let handle = ui.as_weak();
+    data.on_skip(move || {
+        info!("First-run wizard skipped");
+        if let Ok(mut lock) = config_copy.try_lock() {
+            lock.first_run_done = true;
+            lock.write();
+        }
+        if let Some(handle) = handle.upgrade() {
+            handle.global::<FirstRunData>().set_show(false);
+        }
+    });
+
+    let handle = ui.as_weak();
+    data.on_finish(move || {
+        let Some(ui) = handle.upgrade() else {
+            return;
+        };
+        let data = ui.global::<FirstRunData>();
+        data.set_show(false);
+        if let Ok(mut lock) = config.try_lock() {
+            lock.first_run_done = true;
+            lock.run_in_background = data.get_run_in_background();
+            lock.enable_tray_icon = data.get_enable_tray_icon();
+            lock.write();
+        }
+        tray_enabled_changed();
+
+        let steps: Vec<i32> = data.get_steps().iter().collect();
+        let charge_limit = steps
+            .contains(&STEP_CHARGE_LIMIT)
+            .then(|| data.get_charge_limit() as u8);
+        let panel_od = steps
+            .contains(&STEP_PANEL_OD)
+            .then(|| data.get_panel_od() as i32);
+        let gpu_mux_mode = steps
+            .contains(&STEP_GPU_MUX)
+            .then(|| data.get_gpu_mux_mode());
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            apply_choices(handle, charge_limit, panel_od, gpu_mux_mode).await;
+        });
+    });
+
+    let handle = ui.as_weak();
+    tokio::spawn(async move {
+        if let Err(e) = read_capabilities(handle).await {
+            error!("First-run wizard: {e}");
+        }
+    });
+}
+
+/// Add the steps for what this laptop supports, starting from its current
+/// settings
+async fn read_capabilities(handle: Weak<MainWindow>) -> zbus::Result<()> {
+    let conn = rog_dbus::system_connection().await?;
+    let capabilities = CapabilitiesProxy::builder(&conn).build().await?;
+    let properties = capabilities.platform_properties().await.unwrap_or_default();
+    let attributes = capabilities.firmware_attributes().await.unwrap_or_default();
+
+    let mut steps = vec![STEP_FEATURES];
+    let mut charge_limit = None;
+    if properties.contains(&Properties::ChargeControlEndThreshold) {
+        steps.push(STEP_CHARGE_LIMIT);
+        let platform = PlatformProxy::builder(&conn).build().await?;
+        // Suggest 80% unless a limit was already set
+        charge_limit = platform
+            .charge_control_end_threshold()
+            .await
+            .ok()
+            .filter(|limit| *limit < 100);
+    }
+    let mut panel_od = None;
+    let mut gpu_mux_mode = None;
+    for attr in find_iface_async::<AsusArmouryProxy>("xyz.ljones.AsusArmoury")
+        .await
+        .unwrap_or_default()
+    {
+        match attr.name().await {
+            Ok(FirmwareAttribute::PanelOd) if attributes.contains(&FirmwareAttribute::PanelOd) => {
+                panel_od = attr.current_value().await.ok();
+            }
+            Ok(FirmwareAttribute::GpuMuxMode)
+                if attributes.contains(&FirmwareAttribute::GpuMuxMode) =>
+            {
+                gpu_mux_mode = attr.current_value().await.ok();
+            }
+            _ => {}
+        }
+    }
+    if panel_od.is_some() {
+        steps.push(STEP_PANEL_OD);
+    }
+    if gpu_mux_mode.is_some() {
+        steps.push(STEP_GPU_MUX);
+    }
+    steps.push(STEP_APP);
+
+    handle
+        .upgrade_in_event_loop(move |handle| {
+            let data = handle.global::<FirstRunData>();
+            data.set_steps(steps.as_slice().into());
+            if let Some(limit) = charge_limit {
+                data.set_charge_limit(limit as i32);
+            }
+            if let Some(od) = panel_od {
+                data.set_panel_od(od == 1);
+            }
+            if let Some(mode) = gpu_mux_mode {
+                data.set_gpu_mux_mode(mode);
+            }
+        })
+        .map_err(|e| error!("{e:}"))
+        .ok();
+    Ok(())
+}
+
+/// Write the settings chosen in the wizard through asusd. Firmware attributes
+/// which already have the chosen value are left alone.
+async fn apply_choices(
+    handle: Weak<MainWindow>,
+    charge_limit: Option<u8>,
+    panel_od: Option<i32>,
+    gpu_mux_mode: Option<i32>,
+) {
+    let Ok(conn) = rog_dbus::system_connection().await else {
+        return;
+    };
+    if let Some(limit) = charge_limit {
+        if let Ok(platform) = PlatformProxy::builder(&conn).build().await {
+            show_toast(
+                format!("Charge limit set to {limit}%").into(),
+                "Could not set the charge limit".into(),
+                handle.clone(),
+                platform.set_charge_control_end_threshold(limit).await,
+            );
+        }
+    }
+
+    let attrs = find_iface_async::<AsusArmouryProxy>("xyz.ljones.AsusArmoury")
+        .await
+        .unwrap_or_default();
+    for attr in attrs {
+        let (value, success, fail) = match attr.name().await {
+            Ok(FirmwareAttribute::PanelOd) => match panel_od {
+                Some(value) => (
+                    value,
+                    "Panel overdrive set",
+                    "Could not set panel overdrive",
+                ),
+                None => continue,
+            },
+            Ok(FirmwareAttribute::GpuMuxMode) => match gpu_mux_mode {
+                Some(value) => (
+                    value,
+                    "GPU mode set, reboot to use it",
+                    "Could not set the GPU mode",
+                ),
+                None => continue,
+            },
+            _ => continue,
+        };
+        if attr
+            .current_value()
+            .await
+            .is_ok_and(|current| current == value)
+        {
+            continue;
+        }
+        show_toast(
+            success.into(),
+            fail.into(),
+            handle.clone(),
+            attr.set_current_value(value).await,
+        );
+    }
+}
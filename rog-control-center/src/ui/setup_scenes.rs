@@ -0,0 +1,122 @@
+use std::sync::{Arc, Mutex};
+
+use futures_util::StreamExt;
+use log::error;
+use rog_dbus::zbus_scenes::ScenesProxy;
+use slint::{ComponentHandle, Weak};
+
+use crate::config::Config;
+use crate::ui::show_toast;
+use crate::{MainWindow, SceneEntry, ScenesPageData};
+
+/// Read the scene list and active scene from asusd in to the page
+async fn refresh_scenes(handle: Weak<MainWindow>, scenes: ScenesProxy<'static>) {
+    let names = scenes.scenes().await.unwrap_or_default();
+    let mut entries = Vec::new();
+    for name in names {
+        let parts = scenes.scene_parts(&name).await.unwrap_or_default();
+        entries.push((name, parts.join(", ")));
+    }
+    let active = scenes.active().await.unwrap_or_default();
+    let supported = scenes.supported_parts().await.unwrap_or_default();
+    handle
+        .upgrade_in_event_loop(move |handle| {
+            let entries: Vec<SceneEntry> = entries
+                .into_iter()
+                .map(|(name, parts)| SceneEntry {
+                    name: name.into(),
+                    parts: parts.into(),
+                })
+                .collect();
+            let data = handle.global::<ScenesPageData>();
+            data.set_scenes(entries.as_slice().into());
+            data.set_active(active.into());
+            let has = |part: &str| supported.iter().any(|p| p == part);
+            data.set_has_keyboard(has("keyboard"));
+            data.set_has_lightbar(has("lightbar"));
+            data.set_has_anime(has("anime"));
+        })
+        .map_err(|e| error!("{e:}"))
+        .ok();
+}
+
+pub fn setup_scenes_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
+    let handle = ui.as_weak();
+    tokio::spawn(async move {
+        if let Err(e) = setup_scenes(handle).await {
+            error!("Scenes page failed: {e}");
+        }
+    });
+}
+
+async fn setup_scenes(handle: Weak<MainWindow>) -> zbus::Result<()> {
+    let conn = rog_dbus::system_connection().await?;
+    let scenes = ScenesProxy::builder(&conn).build().await?;
+    refresh_scenes(handle.clone(), scenes.clone()).await;
+
+    let scenes_copy = scenes.clone();
+    handle
+        .upgrade_in_event_loop(move |handle| {
+            let data = handle.global::<ScenesPageData>();
+
+            let handle_copy = handle.as_weak();
+            let proxy = scenes_copy.clone();
+            data.on_apply_scene(move |name| {
+                let handle_copy = handle_copy.clone();
+                let proxy = proxy.clone();
+                tokio::spawn(async move {
+                    show_toast(
+                        format!("Applied scene {name}").into(),
+                        format!("Could not apply scene {name}").into(),
+                        handle_copy,
+                        proxy.apply(&name).await,
+                    );
+                });
+            });
+
+            let handle_copy = handle.as_weak();
+            let proxy = scenes_copy.clone();
+            data.on_remove_scene(move |name| {
+                let handle_copy = handle_copy.clone();
+                let proxy = proxy.clone();
+                tokio::spawn(async move {
+                    show_toast(
+                        format!("Removed scene {name}").into(),
+                        format!("Could not remove scene {name}").into(),
+                        handle_copy,
+                        proxy.remove_scene(&name).await,
+                    );
+                });
+            });
+
+            let handle_copy = handle.as_weak();
+            let proxy = scenes_copy.clone();
+            data.on_capture_scene(move |name, keyboard, lightbar, anime| {
+                let handle_copy = handle_copy.clone();
+                let proxy = proxy.clone();
+                tokio::spawn(async move {
+                    show_toast(
+                        format!("Saved scene {name}").into(),
+                        format!("Could not save scene {name}").into(),
+                        handle_copy,
+                        proxy.capture_scene(&name, keyboard, lightbar, anime).await,
+                    );
+                });
+            });
+        })
+        .map_err(|e| error!("{e:}"))
+        .ok();
+
+    // Scenes can also be changed from asusctl
+    let mut scenes_changed = scenes.receive_scenes_changed().await;
+    let mut active_changed = scenes.receive_active_changed().await;
+    loop {
+        tokio::select! {
+            Some(_) = scenes_changed.next() => {}
+            Some(_) = active_changed.next() => {}
+            else => break,
+        }
+        refresh_scenes(handle.clone(), scenes.clone()).await;
+    }
+    Ok(())
+}
@@ -4,11 +4,15 @@ use concat_idents::concat_idents;
 use log::{debug, error};
 use rog_dbus::asus_armoury::AsusArmouryProxy;
 use rog_dbus::zbus_backlight::BacklightProxy;
+use rog_dbus::zbus_dgpu_tgp::DgpuTgpProxy;
+use rog_dbus::zbus_gpu_policy::GpuThermalPolicyProxy;
 use rog_dbus::zbus_platform::{PlatformProxy, PlatformProxyBlocking};
-use rog_platform::asus_armoury::FirmwareAttribute;
-use rog_platform::platform::Properties;
-use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel};
+use rog_platform::asus_armoury::{FirmwareAttribute, GpuThermalPreset};
+use rog_platform::platform::{GpuMode, Properties};
+use slint::{ComponentHandle, Model, ModelRc, SharedString, VecModel, Weak};
+use zbus::names::BusName;
 
+use super::setup_diagnostics::{push_unsupported, unsupported};
 use super::show_toast;
 use crate::config::Config;
 use crate::zbus_proxies::find_iface_async;
@@ -20,8 +24,14 @@ const MINMAX: AttrMinMax = AttrMinMax {
     current: -1.0,
 };
 
+/// Seconds to keep tuning changes before they are reverted if
+/// `confirm_tuning` is on
+const TUNING_TRIAL_SECS: u32 = 15;
+/// GPU modes are left to supergfxd's own controls when it is running
+const SUPERGFXD_NAME: &str = "org.supergfxctl.Daemon";
+
 pub fn setup_system_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
-    let conn = zbus::blocking::Connection::system()
+    let conn = rog_dbus::system_connection_blocking()
         .map_err(|e| error!("DBus system connection failed: {e:?}"))
         .unwrap();
     let platform = PlatformProxyBlocking::builder(&conn)
@@ -50,6 +60,9 @@ pub fn setup_system_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
     ui.global::<SystemPageData>().set_ppt_platform_sppt(MINMAX);
     ui.global::<SystemPageData>().set_nv_dynamic_boost(MINMAX);
     ui.global::<SystemPageData>().set_nv_temp_target(MINMAX);
+    ui.global::<SystemPageData>().set_gpu_thermal_preset(-1);
+    ui.global::<SystemPageData>().set_gpu_mode(-1);
+    ui.global::<SystemPageData>().set_dgpu_tgp(MINMAX);
     ui.global::<SystemPageData>().set_ppt_enabled(false);
     ui.global::<SystemPageData>()
         .set_ppt_enabled_available(false);
@@ -59,12 +72,28 @@ pub fn setup_system_page(ui: &MainWindow, _config: Arc<Mutex<Config>>) {
         .map_err(|e| log::error!("Failed to get supported properties: {}", e))
     {
         log::debug!("Available system properties: {:?}", &sys_props);
+        let mut sections = Vec::new();
         if sys_props.contains(&Properties::ChargeControlEndThreshold) {
             ui.global::<SystemPageData>()
                 .set_charge_control_end_threshold(60.0);
             ui.global::<SystemPageData>()
                 .set_charge_control_enabled(true);
+        } else {
+            sections.push(unsupported(
+                "Charge limit",
+                "The battery has no charge_control_end_threshold. This needs the asus-nb-wmi \
+                 driver and a battery which supports a limit.",
+            ));
+        }
+        if !sys_props.contains(&Properties::ThrottlePolicy) {
+            sections.push(unsupported(
+                "Platform profile",
+                "The kernel has no platform_profile for this laptop. This needs the asus-nb-wmi \
+                 driver with platform profile support.",
+            ));
         }
+        ui.global::<SystemPageData>()
+            .set_unsupported(sections.as_slice().into());
     }
 }
 
@@ -150,6 +179,58 @@ macro_rules! setup_callback {
     };
 }
 
+// For handling callbacks from UI value changes of tuning attributes. These are
+// tried through the platform interface and reverted unless kept in time if
+// `confirm_tuning` is on
+macro_rules! setup_trial_callback {
+    ($property:ident, $handle:expr, $attr:expr, $platform:expr, $name:expr) => {
+        let handle_copy = $handle.as_weak();
+        let proxy_copy = $attr.clone();
+        let platform_copy = $platform.clone();
+        concat_idents!(on_callback = on_cb_, $property {
+            $handle
+                .global::<SystemPageData>()
+                .on_callback(move |v| {
+                    let confirm = handle_copy
+                        .upgrade()
+                        .is_some_and(|h| h.global::<SystemPageData>().get_confirm_tuning());
+                    let handle_copy = handle_copy.clone();
+                    let proxy_copy = proxy_copy.clone();
+                    let platform_copy = platform_copy.clone();
+                    tokio::spawn(async move {
+                        if !confirm {
+                            show_toast(
+                                format!("{} successfully set to {}", stringify!($property), v).into(),
+                                format!("Setting {} failed", stringify!($property)).into(),
+                                handle_copy,
+                                proxy_copy.set_current_value(v).await,
+                            );
+                            return;
+                        }
+                        let result = platform_copy
+                            .try_attributes(&[($name, v)], TUNING_TRIAL_SECS)
+                            .await;
+                        if result.is_ok() {
+                            handle_copy
+                                .upgrade_in_event_loop(|handle| {
+                                    handle
+                                        .global::<SystemPageData>()
+                                        .set_tuning_trial_secs(TUNING_TRIAL_SECS as i32);
+                                })
+                                .ok();
+                        }
+                        show_toast(
+                            format!("{} set to {} on trial", stringify!($property), v).into(),
+                            format!("Setting {} failed", stringify!($property)).into(),
+                            handle_copy,
+                            result,
+                        );
+                    });
+                });
+        });
+    };
+}
+
 // For handling callbacks from UI value changes
 macro_rules! setup_callback_restore_default {
     ($property:ident, $handle:expr, $attr:expr) => {
@@ -277,7 +358,7 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
 
     tokio::spawn(async move {
         // Create the connections/proxies here to prevent future delays in process
-        let conn = zbus::Connection::system()
+        let conn = rog_dbus::system_connection()
             .await
             .map_err(|e| {
                 log::error!("Failed to connect to system bus: {}", e);
@@ -407,6 +488,7 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
             SystemPageData,
             screenpad_sync_with_primary
         );
+        set_ui_props_async!(handle, backlight, SystemPageData, screenpad_power);
 
         let platform_copy = platform.clone();
         handle
@@ -467,6 +549,57 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
                     "Setting PPT group settings failed"
                 );
 
+                let handle_copy = handle.as_weak();
+                let proxy_copy = platform_copy.clone();
+                handle
+                    .global::<SystemPageData>()
+                    .on_cb_keep_tuning(move || {
+                        let proxy_copy = proxy_copy.clone();
+                        let handle_copy = handle_copy.clone();
+                        tokio::spawn(async move {
+                            show_toast(
+                                "Kept tuning changes".into(),
+                                "Keeping tuning changes failed".into(),
+                                handle_copy,
+                                proxy_copy.confirm_attributes().await,
+                            );
+                        });
+                    });
+
+                let handle_copy = handle.as_weak();
+                let proxy_copy = platform_copy.clone();
+                handle
+                    .global::<SystemPageData>()
+                    .on_cb_revert_tuning(move || {
+                        let proxy_copy = proxy_copy.clone();
+                        let handle_copy = handle_copy.clone();
+                        tokio::spawn(async move {
+                            show_toast(
+                                "Reverted tuning changes".into(),
+                                "Reverting tuning changes failed".into(),
+                                handle_copy,
+                                proxy_copy.revert_attributes().await,
+                            );
+                        });
+                    });
+
+                // Hide the countdown if the trial ended some other way
+                let handle_copy = handle.as_weak();
+                let proxy_copy = platform_copy.clone();
+                tokio::spawn(async move {
+                    let mut x = proxy_copy.receive_attribute_trial_pending_changed().await;
+                    use futures_util::StreamExt;
+                    while let Some(e) = x.next().await {
+                        if let Ok(false) = e.get().await {
+                            handle_copy
+                                .upgrade_in_event_loop(|handle| {
+                                    handle.global::<SystemPageData>().set_tuning_trial_secs(0);
+                                })
+                                .ok();
+                        }
+                    }
+                });
+
                 set_ui_callbacks!(handle,
                     SystemPageData(as f32),
                     platform_copy.charge_control_end_threshold(as u8),
@@ -543,6 +676,13 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
                     "Setting screenpad brightness failed"
                 );
 
+                set_ui_callbacks!(handle,
+                    SystemPageData(as bool),
+                    backlight.screenpad_power(as bool),
+                    "Screenpad power set to {}",
+                    "Setting screenpad power failed"
+                );
+
                 set_ui_callbacks!(handle,
                     SystemPageData(.parse().unwrap_or(1.0)),
                     backlight.screenpad_gamma(.to_string().as_str()),
@@ -552,6 +692,234 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
             })
             .ok();
 
+        // supergfxd has its own controls, so the basic modes are only offered
+        // without it
+        let supergfxd = match zbus::fdo::DBusProxy::new(&conn).await {
+            Ok(dbus) => dbus
+                .name_has_owner(BusName::from_static_str_unchecked(SUPERGFXD_NAME))
+                .await
+                .unwrap_or(false),
+            Err(_) => false,
+        };
+        let gpu_modes = platform.supported_gpu_modes().await.unwrap_or_default();
+        if !supergfxd && !gpu_modes.is_empty() {
+            let pending = platform.gpu_mode_pending().await.unwrap_or_default();
+            let index = gpu_modes.iter().position(|m| *m == pending).unwrap_or(0) as i32;
+            let choices: Vec<SharedString> =
+                gpu_modes.iter().map(|m| m.to_string().into()).collect();
+            let handle_copy = handle.clone();
+            let proxy_copy = platform.clone();
+            handle
+                .upgrade_in_event_loop(move |handle| {
+                    let data = handle.global::<SystemPageData>();
+                    data.set_gpu_mode_choices(ModelRc::new(VecModel::from(choices)));
+                    data.set_gpu_mode(index);
+                    data.on_cb_gpu_mode(move |index| {
+                        let Some(mode) = gpu_modes.get(index as usize).copied() else {
+                            return;
+                        };
+                        let proxy_copy = proxy_copy.clone();
+                        let handle_copy = handle_copy.clone();
+                        tokio::spawn(async move {
+                            let message = if mode == GpuMode::Ultimate {
+                                format!("GPU mode set to {mode}, reboot to apply")
+                            } else {
+                                format!("GPU mode set to {mode}")
+                            };
+                            show_toast(
+                                message.into(),
+                                "Setting the GPU mode failed".into(),
+                                handle_copy,
+                                proxy_copy.set_gpu_mode(mode).await,
+                            );
+                        });
+                    });
+                })
+                .ok();
+        }
+
+        if let Ok(gpu_policy) = GpuThermalPolicyProxy::builder(&conn).build().await {
+            if let Ok(value) = gpu_policy.preset().await {
+                handle
+                    .upgrade_in_event_loop(move |handle| {
+                        handle
+                            .global::<SystemPageData>()
+                            .set_gpu_thermal_preset(i32::from(value));
+                    })
+                    .ok();
+
+                let handle_copy = handle.clone();
+                let proxy_copy = gpu_policy.clone();
+                handle
+                    .upgrade_in_event_loop(move |handle| {
+                        handle
+                            .global::<SystemPageData>()
+                            .on_cb_gpu_thermal_preset(move |value| {
+                                let preset = GpuThermalPreset::from(value);
+                                if preset == GpuThermalPreset::Custom {
+                                    return;
+                                }
+                                let proxy_copy = proxy_copy.clone();
+                                let handle_copy = handle_copy.clone();
+                                tokio::spawn(async move {
+                                    show_toast(
+                                        format!(
+                                            "GPU thermal policy set to {}",
+                                            <&str>::from(preset)
+                                        )
+                                        .into(),
+                                        "Setting GPU thermal policy failed".into(),
+                                        handle_copy,
+                                        proxy_copy.apply_preset(preset).await,
+                                    );
+                                });
+                            });
+                    })
+                    .ok();
+
+                let handle_copy = handle.clone();
+                tokio::spawn(async move {
+                    let mut x = gpu_policy.receive_preset_changed().await;
+                    use futures_util::StreamExt;
+                    while let Some(e) = x.next().await {
+                        if let Ok(out) = e.get().await {
+                            handle_copy
+                                .upgrade_in_event_loop(move |handle| {
+                                    handle
+                                        .global::<SystemPageData>()
+                                        .set_gpu_thermal_preset(i32::from(out));
+                                })
+                                .ok();
+                        }
+                    }
+                });
+            }
+        }
+
+        if let Ok(dgpu_tgp) = DgpuTgpProxy::builder(&conn).build().await {
+            if let Ok(current) = dgpu_tgp.tgp().await {
+                // -1 means there is no limit
+                let min = dgpu_tgp.tgp_min().await.ok().filter(|v| *v >= 0);
+                let max = dgpu_tgp.tgp_max().await.ok().filter(|v| *v >= 0);
+                let (min, max) = (min.unwrap_or(0), max.unwrap_or(current));
+                handle
+                    .upgrade_in_event_loop(move |handle| {
+                        handle.global::<SystemPageData>().set_dgpu_tgp(AttrMinMax {
+                            min,
+                            max,
+                            current: current as f32,
+                        });
+                    })
+                    .ok();
+
+                let handle_copy = handle.clone();
+                let proxy_copy = dgpu_tgp.clone();
+                let platform_copy = platform.clone();
+                handle
+                    .upgrade_in_event_loop(move |handle| {
+                        let handle_copy2 = handle_copy.clone();
+                        let proxy_copy2 = proxy_copy.clone();
+                        handle
+                            .global::<SystemPageData>()
+                            .on_cb_dgpu_tgp(move |value| {
+                                let confirm = handle_copy.upgrade().is_some_and(|h| {
+                                    h.global::<SystemPageData>().get_confirm_tuning()
+                                });
+                                let proxy_copy = proxy_copy.clone();
+                                let platform_copy = platform_copy.clone();
+                                let handle_copy = handle_copy.clone();
+                                tokio::spawn(async move {
+                                    if confirm {
+                                        let result = platform_copy
+                                            .try_attributes(
+                                                &[(FirmwareAttribute::DgpuTgp, value)],
+                                                TUNING_TRIAL_SECS,
+                                            )
+                                            .await;
+                                        if result.is_ok() {
+                                            handle_copy
+                                                .upgrade_in_event_loop(|handle| {
+                                                    handle
+                                                        .global::<SystemPageData>()
+                                                        .set_tuning_trial_secs(
+                                                            TUNING_TRIAL_SECS as i32,
+                                                        );
+                                                })
+                                                .ok();
+                                        }
+                                        show_toast(
+                                            format!("GPU TGP set to {value} on trial").into(),
+                                            "Setting GPU TGP failed".into(),
+                                            handle_copy,
+                                            result,
+                                        );
+                                        return;
+                                    }
+                                    let result = proxy_copy.set_tgp(value).await;
+                                    if result.is_err() {
+                                        // The daemon reverted the write, so show the real value
+                                        if let Ok(real) = proxy_copy.tgp().await {
+                                            handle_copy
+                                                .upgrade_in_event_loop(move |handle| {
+                                                    let mut tmp = handle
+                                                        .global::<SystemPageData>()
+                                                        .get_dgpu_tgp();
+                                                    tmp.current = real as f32;
+                                                    handle
+                                                        .global::<SystemPageData>()
+                                                        .set_dgpu_tgp(tmp);
+                                                })
+                                                .ok();
+                                        }
+                                    }
+                                    show_toast(
+                                        format!("GPU TGP set to {value}").into(),
+                                        "Setting GPU TGP failed, value reverted".into(),
+                                        handle_copy,
+                                        result,
+                                    );
+                                });
+                            });
+                        handle
+                            .global::<SystemPageData>()
+                            .on_cb_default_dgpu_tgp(move || {
+                                let proxy_copy = proxy_copy2.clone();
+                                let handle_copy = handle_copy2.clone();
+                                tokio::spawn(async move {
+                                    if let Ok(default) = proxy_copy.tgp_default().await {
+                                        if default != -1 {
+                                            show_toast(
+                                                format!("GPU TGP reset to {default}").into(),
+                                                "Resetting GPU TGP failed".into(),
+                                                handle_copy,
+                                                proxy_copy.set_tgp(default).await,
+                                            );
+                                        }
+                                    }
+                                });
+                            });
+                    })
+                    .ok();
+
+                let handle_copy = handle.clone();
+                tokio::spawn(async move {
+                    let mut x = dgpu_tgp.receive_tgp_changed().await;
+                    use futures_util::StreamExt;
+                    while let Some(e) = x.next().await {
+                        if let Ok(out) = e.get().await {
+                            handle_copy
+                                .upgrade_in_event_loop(move |handle| {
+                                    let mut tmp = handle.global::<SystemPageData>().get_dgpu_tgp();
+                                    tmp.current = out as f32;
+                                    handle.global::<SystemPageData>().set_dgpu_tgp(tmp);
+                                })
+                                .ok();
+                        }
+                    }
+                });
+            }
+        }
+
         let armoury_attrs;
         if let Ok(attrs) = find_iface_async::<AsusArmouryProxy>("xyz.ljones.AsusArmoury").await {
             debug!("Found AsusArmoury interfaces");
@@ -567,12 +935,20 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
                  need to either build or install a kernel which includes the patchwork. This \
                  driver is in process of being upstreamed"
             );
+            add_unsupported(
+                &handle,
+                "Firmware attributes",
+                "The asus-armoury driver is not loaded. PPT limits, panel overdrive, and other \
+                 firmware settings need a kernel with this driver added.",
+            );
             return;
         }
 
+        let mut names = Vec::new();
         for attr in armoury_attrs {
             if let Ok(value) = attr.current_value().await {
                 let name = attr.name().await.unwrap();
+                names.push(name);
                 debug!("Setting up {} = {value}", <&str>::from(name));
                 let platform = platform.clone();
                 handle
@@ -612,43 +988,85 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
                         }
                         FirmwareAttribute::PptPl1Spl => {
                             init_minmax_property!(ppt_pl1_spl, handle, attr);
-                            setup_callback!(ppt_pl1_spl, handle, attr, i32);
+                            setup_trial_callback!(
+                                ppt_pl1_spl,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::PptPl1Spl
+                            );
                             setup_callback_restore_default!(ppt_pl1_spl, handle, attr);
                             setup_minmax_external!(ppt_pl1_spl, handle, attr, platform);
                         }
                         FirmwareAttribute::PptPl2Sppt => {
                             init_minmax_property!(ppt_pl2_sppt, handle, attr);
-                            setup_callback!(ppt_pl2_sppt, handle, attr, i32);
+                            setup_trial_callback!(
+                                ppt_pl2_sppt,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::PptPl2Sppt
+                            );
                             setup_callback_restore_default!(ppt_pl2_sppt, handle, attr);
                             setup_minmax_external!(ppt_pl2_sppt, handle, attr, platform);
                         }
                         FirmwareAttribute::PptPl3Fppt => {
                             init_minmax_property!(ppt_pl3_fppt, handle, attr);
-                            setup_callback!(ppt_pl3_fppt, handle, attr, i32);
+                            setup_trial_callback!(
+                                ppt_pl3_fppt,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::PptPl3Fppt
+                            );
                             setup_callback_restore_default!(ppt_pl3_fppt, handle, attr);
                             setup_minmax_external!(ppt_pl3_fppt, handle, attr, platform);
                         }
                         FirmwareAttribute::PptFppt => {
                             init_minmax_property!(ppt_fppt, handle, attr);
-                            setup_callback!(ppt_fppt, handle, attr, i32);
+                            setup_trial_callback!(
+                                ppt_fppt,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::PptFppt
+                            );
                             setup_callback_restore_default!(ppt_fppt, handle, attr);
                             setup_minmax_external!(ppt_fppt, handle, attr, platform);
                         }
                         FirmwareAttribute::PptApuSppt => {
                             init_minmax_property!(ppt_apu_sppt, handle, attr);
-                            setup_callback!(ppt_apu_sppt, handle, attr, i32);
+                            setup_trial_callback!(
+                                ppt_apu_sppt,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::PptApuSppt
+                            );
                             setup_callback_restore_default!(ppt_apu_sppt, handle, attr);
                             setup_minmax_external!(ppt_apu_sppt, handle, attr, platform);
                         }
                         FirmwareAttribute::PptPlatformSppt => {
                             init_minmax_property!(ppt_platform_sppt, handle, attr);
-                            setup_callback!(ppt_platform_sppt, handle, attr, i32);
+                            setup_trial_callback!(
+                                ppt_platform_sppt,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::PptPlatformSppt
+                            );
                             setup_callback_restore_default!(ppt_platform_sppt, handle, attr);
                             setup_minmax_external!(ppt_platform_sppt, handle, attr, platform);
                         }
                         FirmwareAttribute::NvDynamicBoost => {
                             init_minmax_property!(nv_dynamic_boost, handle, attr);
-                            setup_callback!(nv_dynamic_boost, handle, attr, i32);
+                            setup_trial_callback!(
+                                nv_dynamic_boost,
+                                handle,
+                                attr,
+                                platform,
+                                FirmwareAttribute::NvDynamicBoost
+                            );
                             setup_callback_restore_default!(nv_dynamic_boost, handle, attr);
                             setup_minmax_external!(nv_dynamic_boost, handle, attr, platform);
                         }
@@ -688,6 +1106,25 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
                     .ok();
             }
         }
+        if !names.iter().any(|name| name.is_ppt()) {
+            add_unsupported(
+                &handle,
+                "Power limits",
+                "The firmware of this laptop has no adjustable CPU power limits (PPT).",
+            );
+        }
+        if !names.iter().any(|name| {
+            matches!(
+                name,
+                FirmwareAttribute::NvDynamicBoost | FirmwareAttribute::NvTempTarget
+            )
+        }) {
+            add_unsupported(
+                &handle,
+                "GPU tuning",
+                "The firmware of this laptop has no dGPU power boost or temperature limit.",
+            );
+        }
         handle
             .upgrade_in_event_loop(|ui| {
                 debug!(
@@ -702,3 +1139,15 @@ pub fn setup_system_page_callbacks(ui: &MainWindow, _states: Arc<Mutex<Config>>)
             .ok();
     });
 }
+
+fn add_unsupported(handle: &Weak<MainWindow>, title: &'static str, reason: &'static str) {
+    handle
+        .upgrade_in_event_loop(move |ui| {
+            let data = ui.global::<SystemPageData>();
+            data.set_unsupported(push_unsupported(
+                data.get_unsupported(),
+                unsupported(title, reason),
+            ));
+        })
+        .ok();
+}
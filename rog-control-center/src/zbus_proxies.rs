@@ -1,7 +1,7 @@
 use std::sync::{Arc, Mutex};
 
+use zbus::blocking::fdo;
 use zbus::blocking::proxy::ProxyImpl;
-use zbus::blocking::{fdo, Connection};
 use zbus::zvariant::{OwnedValue, Type, Value};
 use zbus::{interface, proxy};
 
@@ -74,7 +74,7 @@ pub fn find_iface<T>(iface_name: &str) -> Result<Vec<T>, Box<dyn std::error::Err
 where
     T: ProxyImpl<'static> + From<zbus::Proxy<'static>>,
 {
-    let conn = Connection::system().unwrap();
+    let conn = rog_dbus::system_connection_blocking().unwrap();
     let f = fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/").unwrap();
     let interfaces = f.get_managed_objects().unwrap();
     let mut paths = Vec::new();
@@ -112,7 +112,7 @@ pub async fn find_iface_async<T>(iface_name: &str) -> Result<Vec<T>, Box<dyn std
 where
     T: zbus::proxy::ProxyImpl<'static> + From<zbus::Proxy<'static>>,
 {
-    let conn = zbus::Connection::system().await.unwrap();
+    let conn = rog_dbus::system_connection().await.unwrap();
     let f = zbus::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/")
         .await
         .unwrap();
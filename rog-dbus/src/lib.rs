@@ -1,19 +1,42 @@
 pub use asusd::{DBUS_IFACE, DBUS_NAME, DBUS_PATH};
+pub use properties::{
+    get_all, set_system_address, system_address, system_connection, system_connection_blocking,
+    Properties,
+};
+#[cfg(feature = "socket-api")]
+pub use properties::{set_socket_path, socket_path};
 use zbus::proxy::ProxyImpl;
 
 pub mod asus_armoury;
+pub mod properties;
 pub mod scsi_aura;
+#[cfg(feature = "socket-api")]
+pub mod socket;
 pub mod zbus_anime;
 pub mod zbus_aura;
+pub mod zbus_aura_json;
+pub mod zbus_aura_sync;
 pub mod zbus_backlight;
+pub mod zbus_boot_watchdog;
+pub mod zbus_capabilities;
+pub mod zbus_curve_optimizer;
+pub mod zbus_dgpu_tgp;
 pub mod zbus_fan_curves;
+pub mod zbus_gpu_policy;
+pub mod zbus_health;
+pub mod zbus_input;
 pub mod zbus_platform;
+pub mod zbus_privacy;
+pub mod zbus_scenes;
 pub mod zbus_slash;
+pub mod zbus_snapshot;
+pub mod zbus_stats;
+pub mod zbus_xg_mobile;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn list_iface_blocking() -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let conn = zbus::blocking::Connection::system()?;
+    let conn = system_connection_blocking()?;
     let f = zbus::blocking::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/")?;
     let interfaces = f.get_managed_objects()?;
     let mut ifaces = Vec::new();
@@ -26,7 +49,7 @@ pub fn list_iface_blocking() -> Result<Vec<String>, Box<dyn std::error::Error>>
 }
 
 pub fn has_iface_blocking(iface: &str) -> Result<bool, Box<dyn std::error::Error>> {
-    let conn = zbus::blocking::Connection::system()?;
+    let conn = system_connection_blocking()?;
     let f = zbus::blocking::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/")?;
     let interfaces = f.get_managed_objects()?;
     for v in interfaces.iter() {
@@ -40,7 +63,7 @@ pub fn has_iface_blocking(iface: &str) -> Result<bool, Box<dyn std::error::Error
 }
 
 pub async fn has_iface(iface: &str) -> Result<bool, Box<dyn std::error::Error>> {
-    let conn = zbus::Connection::system().await?;
+    let conn = system_connection().await?;
     let f = zbus::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/").await?;
     let interfaces = f.get_managed_objects().await?;
     for v in interfaces.iter() {
@@ -57,7 +80,7 @@ pub async fn find_iface_async<T>(iface_name: &str) -> Result<Vec<T>, Box<dyn std
 where
     T: ProxyImpl<'static> + From<zbus::Proxy<'static>>,
 {
-    let conn = zbus::Connection::system().await?;
+    let conn = system_connection().await?;
     let f = zbus::fdo::ObjectManagerProxy::new(&conn, "xyz.ljones.Asusd", "/").await?;
     let interfaces = f.get_managed_objects().await?;
     let mut paths = Vec::new();
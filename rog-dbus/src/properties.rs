@@ -0,0 +1,107 @@
+//! Reading all the properties of an interface in one call, for clients which
+//! fill a whole page of settings at once.
+
+use std::collections::HashMap;
+#[cfg(feature = "socket-api")]
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{ObjectPath, Optional, OwnedValue};
+use zbus::Connection;
+
+use crate::DBUS_NAME;
+
+static SYSTEM_CONNECTION: OnceLock<Connection> = OnceLock::new();
+static SYSTEM_ADDRESS: OnceLock<String> = OnceLock::new();
+#[cfg(feature = "socket-api")]
+static SYSTEM_SOCKET: OnceLock<PathBuf> = OnceLock::new();
+
+/// Connect to asusd at `address` instead of the local system bus, such as
+/// `unix:path=/tmp/ally.sock` for a system bus forwarded over SSH. Must be set
+/// before the first connection is made, and can only be set once.
+pub fn set_system_address(address: &str) -> bool {
+    SYSTEM_ADDRESS.set(address.to_owned()).is_ok()
+}
+
+/// The address set with [`set_system_address`], if any
+pub fn system_address() -> Option<&'static str> {
+    SYSTEM_ADDRESS.get().map(String::as_str)
+}
+
+/// Connect to asusd through its JSON-RPC socket at `path`, for systems
+/// without a system bus. Only blocking connections go through the socket. Must
+/// be set before the first connection is made, and can only be set once.
+#[cfg(feature = "socket-api")]
+pub fn set_socket_path(path: &Path) -> bool {
+    SYSTEM_SOCKET.set(path.to_owned()).is_ok()
+}
+
+/// The socket set with [`set_socket_path`], if any
+#[cfg(feature = "socket-api")]
+pub fn socket_path() -> Option<&'static Path> {
+    SYSTEM_SOCKET.get().map(PathBuf::as_path)
+}
+
+/// A system bus connection shared by every caller in the process, so that a
+/// client making many proxies only connects once
+pub async fn system_connection() -> zbus::Result<Connection> {
+    if let Some(conn) = SYSTEM_CONNECTION.get() {
+        return Ok(conn.clone());
+    }
+    let conn = match system_address() {
+        Some(address) => zbus::connection::Builder::address(address)?.build().await?,
+        None => Connection::system().await?,
+    };
+    // If another task got there first use theirs and drop this one
+    Ok(SYSTEM_CONNECTION.get_or_init(|| conn).clone())
+}
+
+/// A new blocking system bus connection, to the address set with
+/// [`set_system_address`] if there is one, or through the socket set with
+/// `set_socket_path`
+pub fn system_connection_blocking() -> zbus::Result<zbus::blocking::Connection> {
+    #[cfg(feature = "socket-api")]
+    if let Some(path) = socket_path() {
+        return crate::socket::connect_blocking(path);
+    }
+    match system_address() {
+        Some(address) => zbus::blocking::connection::Builder::address(address)?.build(),
+        None => zbus::blocking::Connection::system(),
+    }
+}
+
+/// The properties of one interface as returned by `GetAll`
+#[derive(Debug, Default)]
+pub struct Properties(HashMap<String, OwnedValue>);
+
+impl Properties {
+    /// The value of `name`, such as `"LedMode"`. `None` if the property is
+    /// missing or is not a `T`.
+    pub fn get<T>(&self, name: &str) -> Option<T>
+    where
+        T: TryFrom<OwnedValue>,
+    {
+        let value = self.0.get(name)?.try_clone().ok()?;
+        T::try_from(value).ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Read every property of `interface` at `path` on asusd in one round trip
+/// with `org.freedesktop.DBus.Properties.GetAll`
+pub async fn get_all(path: &ObjectPath<'_>, interface: &str) -> zbus::Result<Properties> {
+    let conn = system_connection().await?;
+    let proxy = PropertiesProxy::builder(&conn)
+        .destination(DBUS_NAME)?
+        .path(path.to_owned())?
+        .build()
+        .await?;
+    let interface = InterfaceName::try_from(interface)?;
+    let values = proxy.get_all(Optional::from(Some(interface))).await?;
+    Ok(Properties(values))
+}
@@ -42,6 +42,12 @@ pub trait ScsiAura {
     #[zbus(property)]
     fn set_enabled(&self, value: bool) -> zbus::Result<()>;
 
+    /// FollowKeyboard property
+    #[zbus(property)]
+    fn follow_keyboard(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_follow_keyboard(&self, value: bool) -> zbus::Result<()>;
+
     /// LedMode property
     #[zbus(property)]
     fn led_mode(&self) -> zbus::Result<u8>;
@@ -0,0 +1,241 @@
+//! asusd's JSON-RPC socket as a DBus connection, so that the proxies in this
+//! crate work on systems without a system bus. The connection's peer is a
+//! thread which makes each call a request on the socket, see
+//! `asusd::socket_api` for the requests.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+use asusd::socket_api::{
+    method_signatures, property_signature, property_signatures, to_json, to_value, CallParams,
+    IntrospectParams, PropertyParams, Request, Response, RpcError,
+};
+use serde_json::Value as Json;
+use zbus::blocking::connection::Builder;
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::{Message, Type as MessageType};
+use zbus::zvariant::{OwnedValue, Signature, Structure, StructureBuilder, Value};
+use zbus::Guid;
+use zbus_xml::ArgDirection;
+
+const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+const INTROSPECTABLE: &str = "org.freedesktop.DBus.Introspectable";
+const PEER: &str = "org.freedesktop.DBus.Peer";
+
+fn io_error(e: impl std::fmt::Display) -> RpcError {
+    RpcError::dbus(
+        "org.freedesktop.DBus.Error.IOError",
+        format!("asusd socket: {e}"),
+    )
+}
+
+fn invalid_args(e: impl std::fmt::Display) -> RpcError {
+    RpcError::dbus("org.freedesktop.DBus.Error.InvalidArgs", e.to_string())
+}
+
+enum Reply {
+    Empty,
+    Args(Structure<'static>),
+    Variant(Value<'static>),
+    Properties(HashMap<String, Value<'static>>),
+    Xml(String),
+}
+
+/// Makes the calls on the DBus connection requests on the socket
+struct Bridge {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+    /// Introspection XML by object path, for the types of each call
+    xml: HashMap<String, String>,
+}
+
+impl Bridge {
+    fn connect(path: &Path) -> std::io::Result<Self> {
+        let writer = UnixStream::connect(path)?;
+        Ok(Self {
+            reader: BufReader::new(writer.try_clone()?),
+            writer,
+            next_id: 0,
+            xml: HashMap::new(),
+        })
+    }
+
+    fn request(&mut self, method: &str, params: Json) -> Result<Json, RpcError> {
+        self.next_id += 1;
+        let request = Request {
+            jsonrpc: "2.0".to_owned(),
+            id: Some(Json::from(self.next_id)),
+            method: method.to_owned(),
+            params,
+        };
+        let mut line = serde_json::to_string(&request).map_err(io_error)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).map_err(io_error)?;
+
+        line.clear();
+        if self.reader.read_line(&mut line).map_err(io_error)? == 0 {
+            return Err(io_error("asusd closed the connection"));
+        }
+        let response: Response = serde_json::from_str(&line).map_err(io_error)?;
+        match response.error {
+            Some(error) => Err(error),
+            None => Ok(response.result.unwrap_or_default()),
+        }
+    }
+
+    fn introspect(&mut self, path: &str) -> Result<String, RpcError> {
+        if let Some(xml) = self.xml.get(path) {
+            return Ok(xml.clone());
+        }
+        let params = IntrospectParams {
+            path: path.to_owned(),
+        };
+        let xml = self.request(
+            "Introspect",
+            serde_json::to_value(params).map_err(io_error)?,
+        )?;
+        let xml = xml
+            .as_str()
+            .ok_or_else(|| io_error("the introspection data is not a string"))?
+            .to_owned();
+        self.xml.insert(path.to_owned(), xml.clone());
+        Ok(xml)
+    }
+
+    fn property_request(
+        &mut self,
+        method: &str,
+        path: &str,
+        interface: &str,
+        property: &str,
+        value: Json,
+    ) -> Result<Json, RpcError> {
+        let params = PropertyParams {
+            path: path.to_owned(),
+            interface: interface.to_owned(),
+            property: property.to_owned(),
+            value,
+        };
+        self.request(method, serde_json::to_value(params).map_err(io_error)?)
+    }
+
+    fn handle(&mut self, msg: &Message) -> Result<Reply, RpcError> {
+        let header = msg.header();
+        let path = header.path().map(|p| p.to_string()).unwrap_or_default();
+        let interface = header
+            .interface()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        let member = header.member().map(|m| m.to_string()).unwrap_or_default();
+        let body = msg.body();
+
+        match (interface.as_str(), member.as_str()) {
+            (PEER, "Ping") => Ok(Reply::Empty),
+            (INTROSPECTABLE, "Introspect") => Ok(Reply::Xml(self.introspect(&path)?)),
+            (PROPERTIES, "Get") => {
+                let (interface, property): (String, String) = body.deserialize()?;
+                let value =
+                    self.property_request("Get", &path, &interface, &property, Json::Null)?;
+                let signature =
+                    property_signature(&self.introspect(&path)?, &interface, &property)?;
+                Ok(Reply::Variant(
+                    to_value(&value, &signature).map_err(invalid_args)?,
+                ))
+            }
+            (PROPERTIES, "GetAll") => {
+                let (interface,): (String,) = body.deserialize()?;
+                let values = self.property_request("GetAll", &path, &interface, "", Json::Null)?;
+                let signatures = property_signatures(&self.introspect(&path)?, &interface)?;
+                let mut properties = HashMap::new();
+                for (name, value) in values.as_object().into_iter().flatten() {
+                    if let Some(signature) = signatures.get(name) {
+                        properties.insert(
+                            name.clone(),
+                            to_value(value, signature).map_err(invalid_args)?,
+                        );
+                    }
+                }
+                Ok(Reply::Properties(properties))
+            }
+            (PROPERTIES, "Set") => {
+                let (interface, property, value): (String, String, OwnedValue) =
+                    body.deserialize()?;
+                self.property_request("Set", &path, &interface, &property, to_json(&value))?;
+                Ok(Reply::Empty)
+            }
+            _ => {
+                let args = if matches!(body.signature(), Signature::Unit) {
+                    Vec::new()
+                } else {
+                    let args: Structure<'_> = body.deserialize()?;
+                    args.fields().iter().map(to_json).collect()
+                };
+                let call = CallParams {
+                    path: path.clone(),
+                    interface: interface.clone(),
+                    method: member.clone(),
+                    args,
+                };
+                let result = self.request("Call", serde_json::to_value(call).map_err(io_error)?)?;
+                let outputs = method_signatures(
+                    &self.introspect(&path)?,
+                    &interface,
+                    &member,
+                    ArgDirection::Out,
+                )?;
+                let results = match outputs.len() {
+                    0 => return Ok(Reply::Empty),
+                    1 => vec![result],
+                    _ => result.as_array().cloned().unwrap_or_default(),
+                };
+                if results.len() != outputs.len() {
+                    return Err(io_error(format!("{member} returned the wrong outputs")));
+                }
+                let mut reply = StructureBuilder::new();
+                for (result, signature) in results.iter().zip(&outputs) {
+                    reply = reply.append_field(to_value(result, signature).map_err(invalid_args)?);
+                }
+                Ok(Reply::Args(reply.build().map_err(invalid_args)?))
+            }
+        }
+    }
+
+    /// Answer the method calls on `conn` until it is closed
+    fn serve(mut self, conn: &Connection) -> zbus::Result<()> {
+        for msg in MessageIterator::from(conn) {
+            let msg = msg?;
+            let header = msg.header();
+            if header.message_type() != MessageType::MethodCall {
+                continue;
+            }
+            match self.handle(&msg) {
+                Ok(Reply::Empty) => conn.reply(&header, &()),
+                Ok(Reply::Args(args)) => conn.reply(&header, &args),
+                Ok(Reply::Variant(value)) => conn.reply(&header, &value),
+                Ok(Reply::Properties(properties)) => conn.reply(&header, &properties),
+                Ok(Reply::Xml(xml)) => conn.reply(&header, &xml),
+                Err(e) => conn.reply_error(&header, e.dbus_name(), &e.message),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+/// A connection to asusd through its JSON-RPC socket at `path`, which proxies
+/// can be made on the same as a system bus connection. Signals are not sent
+/// over the socket.
+pub fn connect_blocking(path: &Path) -> zbus::Result<Connection> {
+    let bridge = Bridge::connect(path)?;
+    let (ours, theirs) = UnixStream::pair()?;
+    std::thread::spawn(move || {
+        Builder::unix_stream(ours)
+            .server(Guid::generate())
+            .and_then(|builder| builder.p2p().build())
+            .and_then(|conn| bridge.serve(&conn))
+            .ok();
+    });
+    Builder::unix_stream(theirs).p2p().build()
+}
@@ -8,9 +8,39 @@ use zbus::proxy;
     default_path = "/xyz/ljones"
 )]
 pub trait Anime {
+    /// ClearFrameCache method
+    fn clear_frame_cache(&self) -> zbus::Result<u32>;
+
     /// DeviceState method
     fn device_state(&self) -> zbus::Result<AnimeDeviceState>;
 
+    /// NotifyBlip method
+    fn notify_blip(&self, icon_or_text: &str, duration_ms: u32) -> zbus::Result<()>;
+
+    /// PlayGif method
+    #[allow(clippy::too_many_arguments)]
+    fn play_gif(
+        &self,
+        file: &str,
+        scale: f64,
+        angle: f64,
+        translation: (f64, f64),
+        brightness: f64,
+        loops: u32,
+    ) -> zbus::Result<()>;
+
+    /// Schedule method
+    fn schedule(&self) -> zbus::Result<(u16, u16, Brightness, Brightness)>;
+
+    /// SetSchedule method
+    fn set_schedule(
+        &self,
+        day_start: u16,
+        night_start: u16,
+        day: Brightness,
+        night: Brightness,
+    ) -> zbus::Result<()>;
+
     /// RunMainLoop method
     fn run_main_loop(&self, start: bool) -> zbus::Result<()>;
 
@@ -21,12 +51,54 @@ pub trait Anime {
     #[zbus(signal)]
     fn notify_device_state(&self, data: AnimeDeviceState) -> zbus::Result<()>;
 
+    /// Frame signal
+    #[zbus(signal)]
+    fn frame(&self, data: Vec<u8>) -> zbus::Result<()>;
+
+    /// StreamFrames property
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn stream_frames(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_stream_frames(&self, value: bool) -> zbus::Result<()>;
+
+    /// MaxFps property
+    #[zbus(property)]
+    fn max_fps(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn set_max_fps(&self, value: u32) -> zbus::Result<()>;
+
+    /// EffectiveFps property
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn effective_fps(&self) -> zbus::Result<u32>;
+
+    /// BlipIcons property
+    #[zbus(property)]
+    fn blip_icons(&self) -> zbus::Result<Vec<String>>;
+
     /// Brightness property
     #[zbus(property)]
     fn brightness(&self) -> zbus::Result<Brightness>;
     #[zbus(property)]
     fn set_brightness(&self, value: Brightness) -> zbus::Result<()>;
 
+    /// AutoBrightness property
+    #[zbus(property)]
+    fn auto_brightness(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_auto_brightness(&self, value: bool) -> zbus::Result<()>;
+
+    /// ScheduleEnabled property
+    #[zbus(property)]
+    fn schedule_enabled(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_schedule_enabled(&self, value: bool) -> zbus::Result<()>;
+
+    /// ScheduleOverride property
+    #[zbus(property)]
+    fn schedule_override(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_schedule_override(&self, value: bool) -> zbus::Result<()>;
+
     /// BuiltinAnimations property
     #[zbus(property)]
     fn builtin_animations(&self) -> zbus::Result<Animations>;
@@ -22,8 +22,12 @@
 
 use std::collections::BTreeMap;
 
-use rog_aura::keyboard::{AuraLaptopUsbPackets, LaptopAuraPower};
-use rog_aura::{AuraDeviceType, AuraEffect, AuraModeNum, AuraZone, LedBrightness, PowerZones};
+use rog_aura::keyboard::{AuraLaptopUsbPackets, LaptopAuraPower, LayoutInfo};
+use rog_aura::{
+    AuraDeviceInfo, AuraDeviceType, AuraEffect, AuraLowBattery, AuraModeNum, AuraProfileLighting,
+    AuraScheduleStep, AuraShuffle, AuraZone, BrightnessRestore, Colour, LedBrightness, PowerZones,
+};
+use rog_platform::platform::PlatformProfile;
 use zbus::blocking::Connection;
 use zbus::{proxy, Result};
 
@@ -38,19 +42,85 @@ pub trait Aura {
     /// AllModeData method
     fn all_mode_data(&self) -> zbus::Result<BTreeMap<AuraModeNum, AuraEffect>>;
 
+    /// DeviceInfo method
+    fn device_info(&self) -> zbus::Result<AuraDeviceInfo>;
+
     /// DirectAddressingRaw method
     fn direct_addressing_raw(&self, data: AuraLaptopUsbPackets) -> zbus::Result<()>;
 
+    /// OpenDirectStream method
+    fn open_direct_stream(&self, rows: u32) -> zbus::Result<zbus::zvariant::OwnedFd>;
+
+    /// KeyboardLayout method
+    fn keyboard_layout(&self) -> zbus::Result<LayoutInfo>;
+
+    /// RemoveScheduleStep method
+    fn remove_schedule_step(&self, when: &str) -> zbus::Result<()>;
+
+    /// ResetController method
+    fn reset_controller(&self) -> zbus::Result<()>;
+
+    /// SetLightbar method
+    fn set_lightbar(&self, mode: AuraModeNum, colour: Colour) -> zbus::Result<()>;
+
+    /// SetLightbarEnabled method
+    fn set_lightbar_enabled(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// SetLogo method
+    fn set_logo(&self, mode: AuraModeNum, colour: Colour) -> zbus::Result<()>;
+
+    /// SetLogoEnabled method
+    fn set_logo_enabled(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// SetScheduleStep method
+    fn set_schedule_step(&self, step: AuraScheduleStep) -> zbus::Result<()>;
+
+    /// SetZoneEffects method
+    fn set_zone_effects(&self, effects: Vec<AuraEffect>) -> zbus::Result<()>;
+
+    /// ShuffleColour method
+    fn shuffle_colour(&self) -> zbus::Result<()>;
+
     /// Brightness property
     #[zbus(property)]
     fn brightness(&self) -> zbus::Result<LedBrightness>;
     #[zbus(property)]
     fn set_brightness(&self, value: LedBrightness) -> zbus::Result<()>;
 
+    /// BrightnessPercent property
+    #[zbus(property)]
+    fn brightness_percent(&self) -> zbus::Result<u8>;
+    #[zbus(property)]
+    fn set_brightness_percent(&self, value: u8) -> zbus::Result<()>;
+
+    /// ZoneBrightness property
+    #[zbus(property)]
+    fn zone_brightness(&self) -> zbus::Result<Vec<(AuraZone, u8)>>;
+    #[zbus(property)]
+    fn set_zone_brightness(&self, value: Vec<(AuraZone, u8)>) -> zbus::Result<()>;
+
+    /// ZoneEffects property
+    #[zbus(property)]
+    fn zone_effects(&self) -> zbus::Result<Vec<AuraEffect>>;
+
+    /// PerZoneModes property
+    #[zbus(property)]
+    fn per_zone_modes(&self) -> zbus::Result<bool>;
+
+    /// Conflicts property
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn conflicts(&self) -> zbus::Result<Vec<String>>;
+
     /// DeviceType property
     #[zbus(property)]
     fn device_type(&self) -> zbus::Result<AuraDeviceType>;
 
+    /// ExclusiveMode property
+    #[zbus(property)]
+    fn exclusive_mode(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_exclusive_mode(&self, value: bool) -> zbus::Result<()>;
+
     /// LedMode property
     #[zbus(property)]
     fn led_mode(&self) -> zbus::Result<AuraModeNum>;
@@ -69,6 +139,52 @@ pub trait Aura {
     #[zbus(property)]
     fn set_led_power(&self, value: LaptopAuraPower) -> zbus::Result<()>;
 
+    /// LowBattery property
+    #[zbus(property)]
+    fn low_battery(&self) -> zbus::Result<AuraLowBattery>;
+    #[zbus(property)]
+    fn set_low_battery(&self, value: AuraLowBattery) -> zbus::Result<()>;
+
+    /// IdleTimeoutOnAc property
+    #[zbus(property)]
+    fn idle_timeout_on_ac(&self) -> zbus::Result<BTreeMap<PlatformProfile, u32>>;
+    #[zbus(property)]
+    fn set_idle_timeout_on_ac(&self, value: BTreeMap<PlatformProfile, u32>) -> zbus::Result<()>;
+
+    /// IdleTimeoutOnBattery property
+    #[zbus(property)]
+    fn idle_timeout_on_battery(&self) -> zbus::Result<BTreeMap<PlatformProfile, u32>>;
+    #[zbus(property)]
+    fn set_idle_timeout_on_battery(
+        &self,
+        value: BTreeMap<PlatformProfile, u32>,
+    ) -> zbus::Result<()>;
+
+    /// ProfileLighting property
+    #[zbus(property)]
+    fn profile_lighting(&self) -> zbus::Result<BTreeMap<PlatformProfile, AuraProfileLighting>>;
+    #[zbus(property)]
+    fn set_profile_lighting(
+        &self,
+        value: BTreeMap<PlatformProfile, AuraProfileLighting>,
+    ) -> zbus::Result<()>;
+
+    /// Schedule property
+    #[zbus(property)]
+    fn schedule(&self) -> zbus::Result<Vec<AuraScheduleStep>>;
+
+    /// BrightnessRestore property
+    #[zbus(property)]
+    fn brightness_restore(&self) -> zbus::Result<BrightnessRestore>;
+    #[zbus(property)]
+    fn set_brightness_restore(&self, value: BrightnessRestore) -> zbus::Result<()>;
+
+    /// Shuffle property
+    #[zbus(property)]
+    fn shuffle(&self) -> zbus::Result<AuraShuffle>;
+    #[zbus(property)]
+    fn set_shuffle(&self, value: AuraShuffle) -> zbus::Result<()>;
+
     /// SupportedBrightness property
     #[zbus(property)]
     fn supported_brightness(&self) -> zbus::Result<Vec<LedBrightness>>;
@@ -84,6 +200,16 @@ pub trait Aura {
     /// SupportedPowerZones property
     #[zbus(property)]
     fn supported_power_zones(&self) -> zbus::Result<Vec<PowerZones>>;
+
+    /// StreamFrames property
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn stream_frames(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_stream_frames(&self, value: bool) -> zbus::Result<()>;
+
+    /// Frame signal
+    #[zbus(signal)]
+    fn frame(&self, data: AuraLaptopUsbPackets) -> zbus::Result<()>;
 }
 
 pub struct AuraProxyPerkey<'a>(AuraProxyBlocking<'a>);
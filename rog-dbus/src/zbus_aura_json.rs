@@ -0,0 +1,28 @@
+//! # D-Bus interface proxy for: `xyz.ljones.AuraJson`
+//!
+//! The `xyz.ljones.Aura` effect and power states as JSON strings. Mostly of
+//! use from scripting languages, Rust clients should use
+//! [`crate::zbus_aura::AuraProxy`].
+use zbus::{proxy, Result};
+
+#[proxy(
+    interface = "xyz.ljones.AuraJson",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones/Aura"
+)]
+pub trait AuraJson {
+    /// Schema method
+    fn schema(&self, name: &str) -> Result<String>;
+
+    /// LedModeData method
+    fn led_mode_data(&self) -> Result<String>;
+
+    /// SetLedModeData method
+    fn set_led_mode_data(&self, json: &str) -> Result<()>;
+
+    /// LedPower method
+    fn led_power(&self) -> Result<String>;
+
+    /// SetLedPower method
+    fn set_led_power(&self, json: &str) -> Result<()>;
+}
@@ -0,0 +1,52 @@
+//! # D-Bus interface proxy for: `xyz.ljones.AuraSync`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones/aura_sync' from service
+//! 'xyz.ljones.Asusd' on system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use rog_aura::AuraEffect;
+use zbus::proxy;
+use zbus::zvariant::OwnedObjectPath;
+
+#[proxy(
+    interface = "xyz.ljones.AuraSync",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones/aura_sync"
+)]
+pub trait AuraSync {
+    /// Resync method
+    fn resync(&self) -> zbus::Result<()>;
+
+    /// SetEffect method
+    fn set_effect(&self, effect: AuraEffect) -> zbus::Result<()>;
+
+    /// Devices property
+    #[zbus(property)]
+    fn devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    /// Effect property
+    #[zbus(property)]
+    fn effect(&self) -> zbus::Result<AuraEffect>;
+
+    /// Members property
+    #[zbus(property)]
+    fn members(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+    #[zbus(property)]
+    fn set_members(&self, value: Vec<OwnedObjectPath>) -> zbus::Result<()>;
+}
@@ -0,0 +1,60 @@
+//! # D-Bus interface proxy for: `xyz.ljones.Capabilities`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use std::collections::HashMap;
+
+use rog_platform::asus_armoury::FirmwareAttribute;
+use rog_platform::platform::Properties;
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.Capabilities",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait Capabilities {
+    /// InterfaceVersion method
+    fn interface_version(&self, interface: &str) -> zbus::Result<u32>;
+
+    /// ApiVersion property
+    #[zbus(property)]
+    fn api_version(&self) -> zbus::Result<u32>;
+
+    /// Controllers property
+    #[zbus(property)]
+    fn controllers(&self) -> zbus::Result<Vec<(String, String)>>;
+
+    /// DaemonVersion property
+    #[zbus(property)]
+    fn daemon_version(&self) -> zbus::Result<String>;
+
+    /// FirmwareAttributes property
+    #[zbus(property)]
+    fn firmware_attributes(&self) -> zbus::Result<Vec<FirmwareAttribute>>;
+
+    /// InterfaceVersions property
+    #[zbus(property)]
+    fn interface_versions(&self) -> zbus::Result<HashMap<String, u32>>;
+
+    /// PlatformProperties property
+    #[zbus(property)]
+    fn platform_properties(&self) -> zbus::Result<Vec<Properties>>;
+}
@@ -0,0 +1,47 @@
+//! # D-Bus interface proxy for: `xyz.ljones.CurveOptimizer`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use rog_platform::platform::PlatformProfile;
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.CurveOptimizer",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait CurveOptimizer {
+    /// ClearWatchdog method
+    fn clear_watchdog(&self) -> zbus::Result<()>;
+
+    /// OffsetForProfile method
+    fn offset_for_profile(&self, profile: PlatformProfile) -> zbus::Result<i32>;
+
+    /// SetOffsetForProfile method
+    fn set_offset_for_profile(&self, profile: PlatformProfile, offset: i32) -> zbus::Result<()>;
+
+    /// OffsetRange property
+    #[zbus(property)]
+    fn offset_range(&self) -> zbus::Result<(i32, i32)>;
+
+    /// WatchdogTripped property
+    #[zbus(property)]
+    fn watchdog_tripped(&self) -> zbus::Result<bool>;
+}
@@ -0,0 +1,51 @@
+//! # D-Bus interface proxy for: `xyz.ljones.DgpuTgp`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.DgpuTgp",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait DgpuTgp {
+    /// BaseTgp property, `-1` if not available
+    #[zbus(property)]
+    fn base_tgp(&self) -> zbus::Result<i32>;
+
+    /// Tgp property
+    #[zbus(property)]
+    fn tgp(&self) -> zbus::Result<i32>;
+    #[zbus(property)]
+    fn set_tgp(&self, value: i32) -> zbus::Result<()>;
+
+    /// TgpDefault property, `-1` if there is no default
+    #[zbus(property)]
+    fn tgp_default(&self) -> zbus::Result<i32>;
+
+    /// TgpMax property, `-1` if there is no limit
+    #[zbus(property)]
+    fn tgp_max(&self) -> zbus::Result<i32>;
+
+    /// TgpMin property, `-1` if there is no limit
+    #[zbus(property)]
+    fn tgp_min(&self) -> zbus::Result<i32>;
+}
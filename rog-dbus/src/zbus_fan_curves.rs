@@ -21,6 +21,7 @@
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
 use rog_platform::platform::PlatformProfile;
+use rog_profiles::acoustic::AcousticPreset;
 use rog_profiles::fan_curve_set::CurveData;
 use rog_profiles::FanCurvePU;
 use zbus::proxy;
@@ -59,4 +60,51 @@ pub trait FanCurves {
         fan: FanCurvePU,
         enabled: bool,
     ) -> zbus::Result<()>;
+
+    /// TestFanCurve method
+    fn test_fan_curve(&self, curve: CurveData, seconds: u32) -> zbus::Result<()>;
+
+    /// StopFanCurveTest method
+    fn stop_fan_curve_test(&self) -> zbus::Result<()>;
+
+    /// FanCurveTestSample signal
+    #[zbus(signal)]
+    fn fan_curve_test_sample(
+        &self,
+        remaining: u32,
+        fans: Vec<(String, u32)>,
+        temps: Vec<(String, f64)>,
+    ) -> zbus::Result<()>;
+
+    /// FanCurveTestEnded signal
+    #[zbus(signal)]
+    fn fan_curve_test_ended(&self) -> zbus::Result<()>;
+
+    /// ThrottleBoostEnabled property
+    #[zbus(property)]
+    fn throttle_boost_enabled(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_throttle_boost_enabled(&self, value: bool) -> zbus::Result<()>;
+
+    /// ThrottleBoostAggressiveness property
+    #[zbus(property)]
+    fn throttle_boost_aggressiveness(&self) -> zbus::Result<u8>;
+    #[zbus(property)]
+    fn set_throttle_boost_aggressiveness(&self, value: u8) -> zbus::Result<()>;
+
+    /// ThrottleBoostCap property
+    #[zbus(property)]
+    fn throttle_boost_cap(&self) -> zbus::Result<u8>;
+    #[zbus(property)]
+    fn set_throttle_boost_cap(&self, value: u8) -> zbus::Result<()>;
+
+    /// ThrottleBoostActive property
+    #[zbus(property)]
+    fn throttle_boost_active(&self) -> zbus::Result<bool>;
+
+    /// AcousticPreset property
+    #[zbus(property)]
+    fn acoustic_preset(&self) -> zbus::Result<AcousticPreset>;
+    #[zbus(property)]
+    fn set_acoustic_preset(&self, value: AcousticPreset) -> zbus::Result<()>;
 }
@@ -0,0 +1,51 @@
+//! # D-Bus interface proxy for: `xyz.ljones.GpuThermalPolicy`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use rog_platform::asus_armoury::GpuThermalPreset;
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.GpuThermalPolicy",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait GpuThermalPolicy {
+    /// ApplyPreset method
+    fn apply_preset(&self, preset: GpuThermalPreset) -> zbus::Result<()>;
+
+    /// PresetValues method, returns `(dynamic_boost, temp_target)`
+    fn preset_values(&self, preset: GpuThermalPreset) -> zbus::Result<(i32, i32)>;
+
+    /// SetPolicy method
+    fn set_policy(&self, dynamic_boost: i32, temp_target: i32) -> zbus::Result<()>;
+
+    /// DynamicBoost property, `-1` if not available
+    #[zbus(property)]
+    fn dynamic_boost(&self) -> zbus::Result<i32>;
+
+    /// Preset property
+    #[zbus(property)]
+    fn preset(&self) -> zbus::Result<GpuThermalPreset>;
+
+    /// TempTarget property, `-1` if not available
+    #[zbus(property)]
+    fn temp_target(&self) -> zbus::Result<i32>;
+}
@@ -0,0 +1,45 @@
+//! # D-Bus interface proxy for: `xyz.ljones.Health`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.Health",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait Health {
+    /// LastErrors property
+    #[zbus(property)]
+    fn last_errors(&self) -> zbus::Result<Vec<(String, String)>>;
+
+    /// RestartCounts property
+    #[zbus(property)]
+    fn restart_counts(&self) -> zbus::Result<Vec<(String, u32)>>;
+
+    /// Status property
+    #[zbus(property)]
+    fn status(&self) -> zbus::Result<Vec<(String, String, u32, String)>>;
+
+    /// Warnings property
+    #[zbus(property)]
+    fn warnings(&self) -> zbus::Result<Vec<(String, String)>>;
+}
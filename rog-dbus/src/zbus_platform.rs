@@ -20,8 +20,9 @@
 //!
 //! …consequently `zbus-xmlgen` did not generate code for the above interfaces.
 
+use rog_platform::asus_armoury::FirmwareAttribute;
 use rog_platform::cpu::CPUEPP;
-use rog_platform::platform::{PlatformProfile, Properties};
+use rog_platform::platform::{GpuMode, PlatformProfile, Properties};
 use zbus::proxy;
 
 #[proxy(
@@ -33,6 +34,52 @@ pub trait Platform {
     #[zbus(property)]
     fn version(&self) -> zbus::Result<String>;
 
+    /// GpuModeActive property
+    #[zbus(property)]
+    fn gpu_mode_active(&self) -> zbus::Result<GpuMode>;
+
+    /// GpuModePending property
+    #[zbus(property)]
+    fn gpu_mode_pending(&self) -> zbus::Result<GpuMode>;
+
+    /// GpuModeRequested property
+    #[zbus(property)]
+    fn gpu_mode_requested(&self) -> zbus::Result<GpuMode>;
+
+    /// SupportedGpuModes property
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn supported_gpu_modes(&self) -> zbus::Result<Vec<GpuMode>>;
+
+    /// SetGpuMode method
+    fn set_gpu_mode(&self, mode: GpuMode) -> zbus::Result<()>;
+
+    /// GpuModeQueued property
+    #[zbus(property)]
+    fn gpu_mode_queued(&self) -> zbus::Result<GpuMode>;
+
+    /// PendingGpuMuxChanges method
+    fn pending_gpu_mux_changes(&self) -> zbus::Result<(GpuMode, GpuMode, GpuMode, bool)>;
+
+    /// QueueGpuMux method
+    fn queue_gpu_mux(&self, mode: GpuMode, confirm: bool) -> zbus::Result<()>;
+
+    /// CancelQueuedGpuMux method
+    fn cancel_queued_gpu_mux(&self) -> zbus::Result<()>;
+
+    /// ApplyQueuedGpuMux method
+    fn apply_queued_gpu_mux(&self) -> zbus::Result<()>;
+
+    /// LogFilter property
+    #[zbus(property)]
+    fn log_filter(&self) -> zbus::Result<String>;
+
+    /// SetLogFilter method
+    fn set_log_filter(&self, filter: &str) -> zbus::Result<()>;
+
+    /// ActiveUser property
+    #[zbus(property)]
+    fn active_user(&self) -> zbus::Result<u32>;
+
     /// NextThrottleThermalPolicy method
     fn next_platform_profile(&self) -> zbus::Result<()>;
 
@@ -48,6 +95,24 @@ pub trait Platform {
     // Toggle one-shot charge to 100%
     fn one_shot_full_charge(&self) -> zbus::Result<()>;
 
+    /// UsbPdWatts property
+    #[zbus(property)]
+    fn usb_pd_watts(&self) -> zbus::Result<u32>;
+
+    /// SlowCharger property
+    #[zbus(property)]
+    fn slow_charger(&self) -> zbus::Result<bool>;
+
+    /// Batteries property
+    #[zbus(property)]
+    fn batteries(&self) -> zbus::Result<Vec<String>>;
+
+    /// BatteryChargeLimit method
+    fn battery_charge_limit(&self, battery: &str) -> zbus::Result<u8>;
+
+    /// SetBatteryChargeLimit method
+    fn set_battery_charge_limit(&self, battery: &str, limit: u8) -> zbus::Result<()>;
+
     /// ThrottleBalancedEpp property
     #[zbus(property)]
     fn profile_balanced_epp(&self) -> zbus::Result<CPUEPP>;
@@ -116,4 +181,52 @@ pub trait Platform {
     /// Set if the PPT tuning group for the current profile is enabled
     #[zbus(property)]
     fn set_enable_ppt_group(&self, enable: bool) -> zbus::Result<()>;
+
+    /// ApplyPptPreset method
+    fn apply_ppt_preset(&self, values: &[(FirmwareAttribute, i32)]) -> zbus::Result<()>;
+
+    /// TryAttributes method
+    fn try_attributes(
+        &self,
+        values: &[(FirmwareAttribute, i32)],
+        timeout_secs: u32,
+    ) -> zbus::Result<()>;
+
+    /// ConfirmAttributes method
+    fn confirm_attributes(&self) -> zbus::Result<()>;
+
+    /// RevertAttributes method
+    fn revert_attributes(&self) -> zbus::Result<()>;
+
+    /// AttributeTrialPending property
+    #[zbus(property)]
+    fn attribute_trial_pending(&self) -> zbus::Result<bool>;
+
+    /// PanelOdAuto property
+    #[zbus(property)]
+    fn panel_od_auto(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_panel_od_auto(&self, enabled: bool) -> zbus::Result<()>;
+
+    /// MiniLedModeOnAc property, `-1` if unbound
+    #[zbus(property)]
+    fn mini_led_mode_on_ac(&self) -> zbus::Result<i32>;
+    #[zbus(property)]
+    fn set_mini_led_mode_on_ac(&self, mode: i32) -> zbus::Result<()>;
+
+    /// MiniLedModeOnBattery property, `-1` if unbound
+    #[zbus(property)]
+    fn mini_led_mode_on_battery(&self) -> zbus::Result<i32>;
+    #[zbus(property)]
+    fn set_mini_led_mode_on_battery(&self, mode: i32) -> zbus::Result<()>;
+
+    /// MiniLedModeForProfile method, `-1` if unbound
+    fn mini_led_mode_for_profile(&self, profile: PlatformProfile) -> zbus::Result<i32>;
+
+    /// SetMiniLedModeForProfile method, `-1` removes the binding
+    fn set_mini_led_mode_for_profile(
+        &self,
+        profile: PlatformProfile,
+        mode: i32,
+    ) -> zbus::Result<()>;
 }
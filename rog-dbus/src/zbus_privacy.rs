@@ -0,0 +1,51 @@
+//! # D-Bus interface proxy for: `xyz.ljones.Privacy`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.Privacy",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait Privacy {
+    /// CameraEnabled property
+    #[zbus(property)]
+    fn camera_enabled(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_camera_enabled(&self, value: bool) -> zbus::Result<()>;
+
+    /// CameraLed property
+    #[zbus(property)]
+    fn camera_led(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_camera_led(&self, value: bool) -> zbus::Result<()>;
+
+    /// MicMuteLed property
+    #[zbus(property)]
+    fn mic_mute_led(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_mic_mute_led(&self, value: bool) -> zbus::Result<()>;
+
+    /// Supported property, the names of the properties this laptop has
+    #[zbus(property)]
+    fn supported(&self) -> zbus::Result<Vec<String>>;
+}
@@ -0,0 +1,60 @@
+//! # D-Bus interface proxy for: `xyz.ljones.Scenes`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones/scenes' from service
+//! 'xyz.ljones.Asusd' on system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.Scenes",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones/scenes"
+)]
+pub trait Scenes {
+    /// Apply method
+    fn apply(&self, name: &str) -> zbus::Result<()>;
+
+    /// CaptureScene method
+    fn capture_scene(
+        &self,
+        name: &str,
+        keyboard: bool,
+        lightbar: bool,
+        anime: bool,
+    ) -> zbus::Result<()>;
+
+    /// RemoveScene method
+    fn remove_scene(&self, name: &str) -> zbus::Result<()>;
+
+    /// SceneParts method
+    fn scene_parts(&self, name: &str) -> zbus::Result<Vec<String>>;
+
+    /// Active property
+    #[zbus(property)]
+    fn active(&self) -> zbus::Result<String>;
+
+    /// Scenes property
+    #[zbus(property)]
+    fn scenes(&self) -> zbus::Result<Vec<String>>;
+
+    /// SupportedParts property
+    #[zbus(property)]
+    fn supported_parts(&self) -> zbus::Result<Vec<String>>;
+}
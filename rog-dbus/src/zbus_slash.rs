@@ -7,6 +7,9 @@ use zbus::proxy;
     default_path = "/xyz/ljones"
 )]
 pub trait Slash {
+    /// NotifyBlip method
+    fn notify_blip(&self, icon_or_text: &str, duration_ms: u32) -> zbus::Result<()>;
+
     /// EnableDisplay property
     #[zbus(property)]
     fn enabled(&self) -> zbus::Result<bool>;
@@ -0,0 +1,43 @@
+//! # D-Bus interface proxy for: `xyz.ljones.Snapshot`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones/snapshot' from service
+//! 'xyz.ljones.Asusd' on system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+
+#[proxy(
+    interface = "xyz.ljones.Snapshot",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones/snapshot"
+)]
+pub trait Snapshot {
+    /// ResetToDefaults method
+    fn reset_to_defaults(&self, parts: &[&str]) -> zbus::Result<()>;
+
+    /// RestoreSnapshot method
+    fn restore_snapshot(&self, snapshot: &str) -> zbus::Result<()>;
+
+    /// Snapshot method
+    fn snapshot(&self) -> zbus::Result<String>;
+
+    /// ResetParts property
+    #[zbus(property)]
+    fn reset_parts(&self) -> zbus::Result<Vec<String>>;
+}
@@ -0,0 +1,45 @@
+//! # D-Bus interface proxy for: `xyz.ljones.UsageStats`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use rog_platform::platform::{GpuMode, PlatformProfile};
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.UsageStats",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait UsageStats {
+    /// Reset method
+    fn reset(&self) -> zbus::Result<()>;
+
+    /// GpuModeSeconds property
+    #[zbus(property)]
+    fn gpu_mode_seconds(&self) -> zbus::Result<Vec<(GpuMode, u64)>>;
+
+    /// ProfileSeconds property
+    #[zbus(property)]
+    fn profile_seconds(&self) -> zbus::Result<Vec<(PlatformProfile, u64)>>;
+
+    /// Since property, unix time the counters were started or last reset
+    #[zbus(property)]
+    fn since(&self) -> zbus::Result<u64>;
+}
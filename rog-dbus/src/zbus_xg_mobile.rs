@@ -0,0 +1,53 @@
+//! # D-Bus interface proxy for: `xyz.ljones.XgMobile`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection
+//! data. Source: `Interface '/xyz/ljones' from service 'xyz.ljones.Asusd' on
+//! system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the
+//! zbus documentation.
+//!
+//! This type implements the [D-Bus standard interfaces],
+//! (`org.freedesktop.DBus.*`) for which the following zbus API can be used:
+//!
+//! * [`zbus::fdo::PeerProxy`]
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "xyz.ljones.XgMobile",
+    default_service = "xyz.ljones.Asusd",
+    default_path = "/xyz/ljones"
+)]
+pub trait XgMobile {
+    /// Connected property
+    #[zbus(property)]
+    fn connected(&self) -> zbus::Result<bool>;
+
+    /// Enabled property
+    #[zbus(property)]
+    fn enabled(&self) -> zbus::Result<bool>;
+
+    /// Enable method
+    fn enable(&self) -> zbus::Result<()>;
+
+    /// Disable method
+    fn disable(&self) -> zbus::Result<()>;
+
+    /// Lights property
+    #[zbus(property)]
+    fn lights(&self) -> zbus::Result<bool>;
+    #[zbus(property)]
+    fn set_lights(&self, value: bool) -> zbus::Result<()>;
+
+    /// ConnectionChanged signal
+    #[zbus(signal)]
+    fn connection_changed(&self, connected: bool) -> zbus::Result<()>;
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+use crate::error::{PlatformError, Result};
+use crate::sysfs::SysfsDevice;
+use crate::{has_attr, read_attr_string, to_device};
+
+/// An ambient light sensor from the IIO subsystem
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct AmbientLight {
+    path: PathBuf,
+}
+
+impl AmbientLight {
+    pub fn new() -> Result<Self> {
+        let mut enumerator = udev::Enumerator::new().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("enumerator failed".into(), err)
+        })?;
+        enumerator.match_subsystem("iio").map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("match_subsystem failed".into(), err)
+        })?;
+
+        for device in enumerator.scan_devices().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("scan_devices failed".into(), err)
+        })? {
+            let sysname = device.sysname().to_owned();
+            let device = SysfsDevice::from(device);
+            if has_attr(&device, "in_illuminance_input") || has_attr(&device, "in_illuminance_raw")
+            {
+                info!("Found ambient light sensor at {sysname:?}");
+                return Ok(Self {
+                    path: device.syspath().to_path_buf(),
+                });
+            }
+        }
+
+        Err(PlatformError::MissingFunction(
+            "Ambient light sensor not found".into(),
+        ))
+    }
+
+    fn read_f32(&self, attr_name: &str) -> Result<f32> {
+        read_attr_string(&to_device(&self.path)?, attr_name)?
+            .trim()
+            .parse()
+            .map_err(|_| PlatformError::ParseNum)
+    }
+
+    /// The light level in lux. Some drivers only provide a raw value which is
+    /// scaled to lux with `in_illuminance_scale` and `in_illuminance_offset`.
+    pub fn illuminance(&self) -> Result<f32> {
+        if let Ok(lux) = self.read_f32("in_illuminance_input") {
+            return Ok(lux);
+        }
+        let raw = self.read_f32("in_illuminance_raw")?;
+        let offset = self.read_f32("in_illuminance_offset").unwrap_or(0.0);
+        let scale = self.read_f32("in_illuminance_scale").unwrap_or(1.0);
+        Ok((raw + offset) * scale)
+    }
+}
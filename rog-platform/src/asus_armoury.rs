@@ -7,11 +7,17 @@ use serde::{Deserialize, Serialize};
 use zbus::zvariant::{OwnedValue, Type, Value};
 
 use crate::error::PlatformError;
+use crate::platform::GpuMode;
+use crate::simulate;
 
 /// The root sysfs path. This path should never change in kernel so
 /// using udev to find it *should* not be required.
 const BASE_DIR: &str = "/sys/class/firmware-attributes/asus-armoury/attributes/";
 
+fn base_dir() -> PathBuf {
+    simulate::path(simulate::FIRMWARE_ATTRIBUTES).unwrap_or_else(|| PathBuf::from(BASE_DIR))
+}
+
 fn read_i32(path: &Path) -> Result<i32, PlatformError> {
     if let Ok(mut f) = File::open(path) {
         let mut buf = String::new();
@@ -199,9 +205,14 @@ pub struct FirmwareAttributes {
 
 #[allow(clippy::new_without_default)]
 impl FirmwareAttributes {
+    /// No attributes, for when they should be left alone
+    pub fn empty() -> Self {
+        Self { attrs: Vec::new() }
+    }
+
     pub fn new() -> Self {
         let mut attrs = Vec::new();
-        if let Ok(dir) = read_dir(BASE_DIR) {
+        if let Ok(dir) = read_dir(base_dir()) {
             for entry in dir.flatten() {
                 let base_path = entry.path();
                 let name = base_path.file_name().unwrap().to_string_lossy().to_string();
@@ -235,6 +246,35 @@ impl FirmwareAttributes {
     pub fn attributes_mut(&mut self) -> &mut Vec<Attribute> {
         &mut self.attrs
     }
+
+    /// The current `gpu_mux_mode`, `dgpu_disable`, and `egpu_enable` values,
+    /// `None` for any the laptop doesn't have
+    pub fn gpu_attr_values(&self) -> (Option<u8>, Option<u8>, Option<u8>) {
+        let read = |attr: Option<&Attribute>| {
+            attr.and_then(|a| match a.current_value() {
+                Ok(AttrValue::Integer(i)) => Some(i as u8),
+                _ => None,
+            })
+        };
+        (
+            read(self.gpu_mux_mode()),
+            read(self.dgpu_disable()),
+            read(self.egpu_enable()),
+        )
+    }
+
+    /// The GPU mode the firmware attributes are set to. A MUX change only
+    /// takes effect after a reboot, so this may not be the mode in use.
+    pub fn gpu_mode(&self) -> Option<GpuMode> {
+        let (mux, dgpu, egpu) = self.gpu_attr_values();
+        GpuMode::from_attr_values(mux, dgpu, egpu)
+    }
+
+    /// If the firmware has a changed setting, such as the MUX, which only
+    /// applies after a reboot
+    pub fn pending_reboot() -> bool {
+        read_i32(&base_dir().join("pending_reboot")).is_ok_and(|v| v == 1)
+    }
 }
 
 macro_rules! define_attribute_getters {
@@ -321,6 +361,16 @@ impl FirmwareAttribute {
         )
     }
 
+    /// The attributes which together set the GPU mode
+    pub fn is_gpu_mode(&self) -> bool {
+        matches!(
+            self,
+            FirmwareAttribute::GpuMuxMode
+                | FirmwareAttribute::DgpuDisable
+                | FirmwareAttribute::EgpuEnable
+        )
+    }
+
     pub fn is_dgpu(&self) -> bool {
         matches!(
             self,
@@ -400,6 +450,80 @@ impl From<FirmwareAttribute> for &str {
     }
 }
 
+/// Named presets for the combined NVIDIA GPU thermal policy, which bundles
+/// `nv_dynamic_boost` and `nv_temp_target` together
+#[repr(u8)]
+#[derive(
+    Deserialize,
+    Serialize,
+    Default,
+    Type,
+    Value,
+    OwnedValue,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+)]
+#[zvariant(signature = "u")]
+pub enum GpuThermalPreset {
+    /// Lowest dynamic boost and temperature target
+    Cool = 0,
+    /// The firmware defaults
+    #[default]
+    Balanced = 1,
+    /// Highest dynamic boost and temperature target
+    Max = 2,
+    /// Values were set individually and match no preset
+    Custom = 3,
+}
+
+impl From<i32> for GpuThermalPreset {
+    fn from(num: i32) -> Self {
+        match num {
+            0 => Self::Cool,
+            1 => Self::Balanced,
+            2 => Self::Max,
+            _ => Self::Custom,
+        }
+    }
+}
+
+impl From<GpuThermalPreset> for i32 {
+    fn from(p: GpuThermalPreset) -> Self {
+        p as i32
+    }
+}
+
+impl From<GpuThermalPreset> for &str {
+    fn from(preset: GpuThermalPreset) -> &'static str {
+        match preset {
+            GpuThermalPreset::Cool => "cool",
+            GpuThermalPreset::Balanced => "balanced",
+            GpuThermalPreset::Max => "max",
+            GpuThermalPreset::Custom => "custom",
+        }
+    }
+}
+
+impl std::str::FromStr for GpuThermalPreset {
+    type Err = PlatformError;
+
+    fn from_str(preset: &str) -> Result<Self, Self::Err> {
+        match preset.to_ascii_lowercase().trim() {
+            "cool" => Ok(Self::Cool),
+            "balanced" => Ok(Self::Balanced),
+            "max" => Ok(Self::Max),
+            "custom" => Ok(Self::Custom),
+            _ => Err(PlatformError::NotSupported),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
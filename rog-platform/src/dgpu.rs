@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use crate::error::{PlatformError, Result};
+
+/// PCI classes of VGA and 3D controllers
+const DISPLAY_CLASSES: [&str; 2] = [
+    "0x030000", "0x030200",
+];
+
+/// The runtime power state of a PCI device, from `power/runtime_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DgpuPower {
+    Active,
+    Suspending,
+    Suspended,
+    Resuming,
+    /// Runtime power management is off for the device, so it stays active
+    Unsupported,
+}
+
+impl DgpuPower {
+    fn from_status(status: &str) -> Self {
+        match status.trim() {
+            "suspended" => Self::Suspended,
+            "suspending" => Self::Suspending,
+            "resuming" => Self::Resuming,
+            "active" => Self::Active,
+            _ => Self::Unsupported,
+        }
+    }
+
+    /// If the dGPU is powered down, or can't be, so there is nothing to wait
+    /// for before sleeping
+    pub fn settled(&self) -> bool {
+        matches!(self, Self::Suspended | Self::Unsupported)
+    }
+}
+
+/// The maker of a GPU, from its PCI vendor ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuVendor {
+    Nvidia,
+    Amd,
+    Intel,
+    Other,
+}
+
+impl GpuVendor {
+    pub(crate) fn from_pci_id(id: &str) -> Self {
+        match id.trim().to_lowercase().as_str() {
+            "0x10de" => Self::Nvidia,
+            "0x1002" => Self::Amd,
+            "0x8086" => Self::Intel,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// The discrete GPU, found as the display controller which is not the boot
+/// VGA device. It disappears while `dgpu_disable` is set, so find it again
+/// rather than keeping it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Dgpu {
+    path: PathBuf,
+}
+
+impl Dgpu {
+    pub fn find() -> Result<Self> {
+        let mut enumerator = udev::Enumerator::new().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("enumerator failed".into(), err)
+        })?;
+        enumerator.match_subsystem("pci").map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("match_subsystem failed".into(), err)
+        })?;
+
+        for device in enumerator.scan_devices().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("scan_devices failed".into(), err)
+        })? {
+            let is_display = device
+                .attribute_value("class")
+                .is_some_and(|class| DISPLAY_CLASSES.contains(&&*class.to_string_lossy()));
+            let boot_vga = device
+                .attribute_value("boot_vga")
+                .is_some_and(|v| v.to_string_lossy().trim() == "1");
+            if is_display && !boot_vga {
+                info!("Found the dGPU at {:?}", device.sysname());
+                return Ok(Self {
+                    path: device.syspath().to_path_buf(),
+                });
+            }
+        }
+        Err(PlatformError::MissingFunction("No dGPU found".into()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The PCI address, such as `0000:01:00.0`
+    pub fn slot(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn vendor(&self) -> Result<GpuVendor> {
+        let path = self.path.join("vendor");
+        let id = std::fs::read_to_string(&path)
+            .map_err(|e| PlatformError::Read(path.to_string_lossy().into(), e))?;
+        Ok(GpuVendor::from_pci_id(&id))
+    }
+
+    pub fn runtime_status(&self) -> Result<DgpuPower> {
+        let path = self.path.join("power/runtime_status");
+        let status = std::fs::read_to_string(&path)
+            .map_err(|e| PlatformError::Read(path.to_string_lossy().into(), e))?;
+        Ok(DgpuPower::from_status(&status))
+    }
+}
+
+/// If the GPU at `device`, a PCI device in sysfs, is a discrete GPU. `None`
+/// if it can't be told
+pub fn is_discrete_gpu(device: &Path) -> Option<bool> {
+    let vendor = std::fs::read_to_string(device.join("vendor")).ok()?;
+    match GpuVendor::from_pci_id(&vendor) {
+        GpuVendor::Nvidia => Some(true),
+        GpuVendor::Intel => Some(false),
+        // amdgpu only has PCIe DPM on discrete cards
+        GpuVendor::Amd => Some(device.join("pp_dpm_pcie").exists()),
+        GpuVendor::Other => None,
+    }
+}
+
+/// The power state of the dGPU now. A dGPU which is not there, such as while
+/// `dgpu_disable` is set, is [`DgpuPower::Suspended`]
+pub fn dgpu_power() -> DgpuPower {
+    Dgpu::find()
+        .and_then(|dgpu| dgpu.runtime_status())
+        .unwrap_or(DgpuPower::Suspended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DgpuPower, GpuVendor};
+
+    #[test]
+    fn runtime_status() {
+        assert_eq!(DgpuPower::from_status("suspended\n"), DgpuPower::Suspended);
+        assert_eq!(DgpuPower::from_status("active\n"), DgpuPower::Active);
+        assert_eq!(
+            DgpuPower::from_status("unsupported\n"),
+            DgpuPower::Unsupported
+        );
+        assert!(DgpuPower::Suspended.settled());
+        assert!(DgpuPower::Unsupported.settled());
+        assert!(!DgpuPower::Active.settled());
+        assert!(!DgpuPower::Suspending.settled());
+    }
+
+    #[test]
+    fn vendor_ids() {
+        assert_eq!(GpuVendor::from_pci_id("0x10de\n"), GpuVendor::Nvidia);
+        assert_eq!(GpuVendor::from_pci_id("0x1002\n"), GpuVendor::Amd);
+        assert_eq!(GpuVendor::from_pci_id("0x8086"), GpuVendor::Intel);
+        assert_eq!(GpuVendor::from_pci_id("0x1af4"), GpuVendor::Other);
+    }
+}
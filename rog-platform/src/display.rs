@@ -0,0 +1,276 @@
+use std::fs::{canonicalize, read_dir, read_to_string, File};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+
+use crate::error::{PlatformError, Result};
+
+const DRI_DIR: &str = "/dev/dri/";
+const DRM_CLASS_DIR: &str = "/sys/class/drm/";
+/// Name prefixes of the built-in panel connectors in `/sys/class/drm`
+const INTERNAL_CONNECTOR_NAMES: [&str; 3] = [
+    "LVDS", "eDP", "DSI",
+];
+
+/// `DRM_MODE_CONNECTOR_LVDS`, `_eDP`, and `_DSI`, the built-in panels
+const INTERNAL_CONNECTORS: [u32; 3] = [
+    7, 14, 16,
+];
+const DRM_MODE_FLAG_INTERLACE: u32 = 1 << 4;
+const DRM_MODE_FLAG_DBLSCAN: u32 = 1 << 5;
+
+/// `struct drm_mode_card_res`
+#[repr(C)]
+#[derive(Default)]
+struct CardRes {
+    fb_id_ptr: u64,
+    crtc_id_ptr: u64,
+    connector_id_ptr: u64,
+    encoder_id_ptr: u64,
+    count_fbs: u32,
+    count_crtcs: u32,
+    count_connectors: u32,
+    count_encoders: u32,
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+}
+
+/// `struct drm_mode_modeinfo`
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ModeInfo {
+    pub clock: u32,
+    pub hdisplay: u16,
+    pub hsync_start: u16,
+    pub hsync_end: u16,
+    pub htotal: u16,
+    pub hskew: u16,
+    pub vdisplay: u16,
+    pub vsync_start: u16,
+    pub vsync_end: u16,
+    pub vtotal: u16,
+    pub vscan: u16,
+    pub vrefresh: u32,
+    pub flags: u32,
+    pub type_: u32,
+    pub name: [u8; 32],
+}
+
+/// `struct drm_mode_get_connector`
+#[repr(C)]
+#[derive(Default)]
+struct GetConnector {
+    encoders_ptr: u64,
+    modes_ptr: u64,
+    props_ptr: u64,
+    prop_values_ptr: u64,
+    count_modes: u32,
+    count_props: u32,
+    count_encoders: u32,
+    encoder_id: u32,
+    connector_id: u32,
+    connector_type: u32,
+    connector_type_id: u32,
+    connection: u32,
+    mm_width: u32,
+    mm_height: u32,
+    subpixel: u32,
+    pad: u32,
+}
+
+/// `struct drm_mode_get_encoder`
+#[repr(C)]
+#[derive(Default)]
+struct GetEncoder {
+    encoder_id: u32,
+    encoder_type: u32,
+    crtc_id: u32,
+    possible_crtcs: u32,
+    possible_clones: u32,
+}
+
+/// `struct drm_mode_crtc`
+#[repr(C)]
+#[derive(Default)]
+struct GetCrtc {
+    set_connectors_ptr: u64,
+    count_connectors: u32,
+    crtc_id: u32,
+    fb_id: u32,
+    x: u32,
+    y: u32,
+    gamma_size: u32,
+    mode_valid: u32,
+    mode: ModeInfo,
+}
+
+/// `DRM_IOWR(nr, T)`
+const fn drm_iowr<T>(nr: u64) -> u64 {
+    (3 << 30) | ((std::mem::size_of::<T>() as u64) << 16) | ((b'd' as u64) << 8) | nr
+}
+
+// The sizes are part of the ioctl numbers
+const _: () = assert!(std::mem::size_of::<CardRes>() == 64);
+const _: () = assert!(std::mem::size_of::<ModeInfo>() == 68);
+const _: () = assert!(std::mem::size_of::<GetConnector>() == 80);
+const _: () = assert!(std::mem::size_of::<GetEncoder>() == 20);
+const _: () = assert!(std::mem::size_of::<GetCrtc>() == 104);
+
+const DRM_IOCTL_MODE_GETRESOURCES: u64 = drm_iowr::<CardRes>(0xa0);
+const DRM_IOCTL_MODE_GETCRTC: u64 = drm_iowr::<GetCrtc>(0xa1);
+const DRM_IOCTL_MODE_GETENCODER: u64 = drm_iowr::<GetEncoder>(0xa6);
+const DRM_IOCTL_MODE_GETCONNECTOR: u64 = drm_iowr::<GetConnector>(0xa7);
+
+fn drm_ioctl<T>(card: &File, request: u64, arg: &mut T) -> io::Result<()> {
+    // SAFETY: `T` is the struct the kernel takes for `request`, and the only
+    // pointers set in it are to buffers at least as long as their counts
+    if unsafe { libc::ioctl(card.as_raw_fd(), request as _, arg as *mut T) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The refresh rate of `mode` in Hz, rounded as the kernel's
+/// `drm_mode_vrefresh()` does
+pub fn mode_refresh_rate(mode: &ModeInfo) -> u32 {
+    let mut num = mode.clock as u64 * 1000;
+    let mut den = mode.htotal as u64 * mode.vtotal as u64;
+    if mode.flags & DRM_MODE_FLAG_INTERLACE != 0 {
+        num *= 2;
+    }
+    if mode.flags & DRM_MODE_FLAG_DBLSCAN != 0 {
+        den *= 2;
+    }
+    if mode.vscan > 1 {
+        den *= mode.vscan as u64;
+    }
+    if den == 0 {
+        return 0;
+    }
+    ((num + den / 2) / den) as u32
+}
+
+/// The mode of the CRTC driving an internal panel of `card`, if one is lit
+fn internal_panel_mode(card: &File) -> io::Result<Option<ModeInfo>> {
+    let mut res = CardRes::default();
+    drm_ioctl(card, DRM_IOCTL_MODE_GETRESOURCES, &mut res)?;
+    let mut connectors = vec![0u32; res.count_connectors as usize];
+    let mut res = CardRes {
+        connector_id_ptr: connectors.as_mut_ptr() as u64,
+        count_connectors: connectors.len() as u32,
+        ..Default::default()
+    };
+    drm_ioctl(card, DRM_IOCTL_MODE_GETRESOURCES, &mut res)?;
+    connectors.truncate(res.count_connectors as usize);
+
+    for connector_id in connectors {
+        // Room for one mode, as asking for none makes the DRM master probe
+        // the connector
+        let mut modes = [ModeInfo::default()];
+        let mut connector = GetConnector {
+            connector_id,
+            modes_ptr: modes.as_mut_ptr() as u64,
+            count_modes: modes.len() as u32,
+            ..Default::default()
+        };
+        drm_ioctl(card, DRM_IOCTL_MODE_GETCONNECTOR, &mut connector)?;
+        if !INTERNAL_CONNECTORS.contains(&connector.connector_type) || connector.encoder_id == 0 {
+            continue;
+        }
+
+        let mut encoder = GetEncoder {
+            encoder_id: connector.encoder_id,
+            ..Default::default()
+        };
+        drm_ioctl(card, DRM_IOCTL_MODE_GETENCODER, &mut encoder)?;
+        if encoder.crtc_id == 0 {
+            continue;
+        }
+
+        let mut crtc = GetCrtc {
+            crtc_id: encoder.crtc_id,
+            ..Default::default()
+        };
+        drm_ioctl(card, DRM_IOCTL_MODE_GETCRTC, &mut crtc)?;
+        if crtc.mode_valid != 0 {
+            return Ok(Some(crtc.mode));
+        }
+    }
+    Ok(None)
+}
+
+/// Read the current refresh rate of the internal panel from the mode of the
+/// CRTC driving it. A card which can't be read, such as a dGPU without KMS, is
+/// skipped
+pub fn internal_refresh_rate() -> Result<u32> {
+    for entry in read_dir(DRI_DIR)
+        .map_err(|e| PlatformError::IoPath(DRI_DIR.to_string(), e))?
+        .flatten()
+    {
+        if !entry.file_name().to_string_lossy().starts_with("card") {
+            continue;
+        }
+        let path = entry.path();
+        match File::open(&path).and_then(|card| internal_panel_mode(&card)) {
+            Ok(Some(mode)) => return Ok(mode_refresh_rate(&mode)),
+            Ok(None) => {}
+            Err(e) => debug!("Could not read the modes of {path:?}, trying the next card: {e}"),
+        }
+    }
+    Err(PlatformError::NotSupported)
+}
+
+/// The PCI device of the GPU driving the internal panel, from which of the
+/// panel connectors is connected. On a laptop with a MUX this is the dGPU
+/// while the MUX is switched to it.
+pub fn internal_panel_gpu() -> Option<PathBuf> {
+    for entry in read_dir(DRM_CLASS_DIR).ok()?.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // Such as `card1-eDP-1`
+        let Some((card, connector)) = name.split_once('-') else {
+            continue;
+        };
+        if !INTERNAL_CONNECTOR_NAMES
+            .iter()
+            .any(|prefix| connector.starts_with(prefix))
+        {
+            continue;
+        }
+        if read_to_string(entry.path().join("status")).is_ok_and(|s| s.trim() == "connected") {
+            return canonicalize(Path::new(DRM_CLASS_DIR).join(card).join("device")).ok();
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mode_refresh_rate, ModeInfo};
+
+    fn mode(clock: u32, htotal: u16, vtotal: u16) -> ModeInfo {
+        ModeInfo {
+            clock,
+            htotal,
+            vtotal,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn refresh_of_panel_modes() {
+        assert_eq!(mode_refresh_rate(&mode(556470, 2720, 1640)), 125);
+        assert_eq!(mode_refresh_rate(&mode(148500, 2200, 1125)), 60);
+        assert_eq!(mode_refresh_rate(&mode(0, 0, 0)), 0);
+    }
+
+    #[test]
+    fn refresh_of_interlaced_mode() {
+        let mut interlaced = mode(74250, 2200, 1125);
+        interlaced.flags = super::DRM_MODE_FLAG_INTERLACE;
+        assert_eq!(mode_refresh_rate(&interlaced), 60);
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use zbus::fdo::Error as FdoErr;
 
+use crate::hid_raw::{DeviceUser, HidPermissionProblem};
+
 pub type Result<T> = std::result::Result<T, PlatformError>;
 
 #[derive(Debug)]
@@ -23,6 +25,10 @@ pub enum PlatformError {
     NoAuraKeyboard,
     NoAuraNode,
     CPU(String),
+    /// The hidraw device node and why it couldn't be opened
+    HidPermission(String, HidPermissionProblem),
+    /// A write to the device node failed while other processes had it open
+    DeviceInUse(String, Vec<DeviceUser>, std::io::Error),
 }
 
 impl fmt::Display for PlatformError {
@@ -53,6 +59,18 @@ impl fmt::Display for PlatformError {
             PlatformError::NoAuraKeyboard => write!(f, "No supported Aura keyboard"),
             PlatformError::NoAuraNode => write!(f, "No Aura keyboard node found"),
             PlatformError::CPU(s) => write!(f, "CPU control: {s}"),
+            PlatformError::HidPermission(path, problem) => {
+                write!(f, "Permission denied opening {path}: {problem}")
+            }
+            PlatformError::DeviceInUse(path, users, error) => {
+                let users: Vec<String> = users.iter().map(|u| u.to_string()).collect();
+                write!(
+                    f,
+                    "{path} {error}. It is also open in {}, which may be controlling the LEDs \
+                     too. Close it, or turn on exclusive mode",
+                    users.join(", ")
+                )
+            }
         }
     }
 }
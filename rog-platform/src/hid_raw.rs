@@ -1,13 +1,224 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use log::{info, warn};
 use udev::Device;
 
 use crate::error::{PlatformError, Result};
 
+/// Directories searched for udev rules
+const UDEV_RULES_DIRS: [&str; 3] = [
+    "/etc/udev/rules.d",
+    "/usr/lib/udev/rules.d",
+    "/lib/udev/rules.d",
+];
+
+/// Failed writes are retried this many times in exclusive mode
+const EXCLUSIVE_RETRIES: u32 = 3;
+const EXCLUSIVE_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Why a hidraw device node could not be opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HidPermissionProblem {
+    /// The process is not in the group which owns the node
+    NotInGroup(String),
+    /// No udev rule sets the permissions of ASUS hidraw nodes
+    NoUdevRule,
+    /// Running as root, or the permissions look right, so something such as
+    /// SELinux is denying access
+    Other,
+}
+
+impl fmt::Display for HidPermissionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HidPermissionProblem::NotInGroup(group) => write!(
+                f,
+                "the user running asusd is not in the {group} group which owns the device, add it \
+                 with `usermod -aG {group} <user>`"
+            ),
+            HidPermissionProblem::NoUdevRule => write!(
+                f,
+                "no udev rule gives access to ASUS hidraw devices, install the udev rules and run \
+                 `udevadm control --reload && udevadm trigger`"
+            ),
+            HidPermissionProblem::Other => write!(
+                f,
+                "access was denied, check that asusd runs as root and is not confined by SELinux \
+                 or AppArmor"
+            ),
+        }
+    }
+}
+
+/// The effective UID and the groups of this process from `/proc/self/status`
+fn process_ids() -> (Option<u32>, Vec<u32>) {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let field = |name: &str| -> Vec<u32> {
+        status
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .map(|ids| {
+                ids.split_whitespace()
+                    .filter_map(|i| i.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let euid = field("Uid:").get(1).copied();
+    let mut groups = field("Groups:");
+    groups.extend(field("Gid:").get(1));
+    (euid, groups)
+}
+
+fn group_name(gid: u32) -> String {
+    std::fs::read_to_string("/etc/group")
+        .unwrap_or_default()
+        .lines()
+        .find_map(|l| {
+            let mut parts = l.split(':');
+            let name = parts.next()?;
+            (parts.nth(1)?.parse::<u32>().ok() == Some(gid)).then(|| name.to_owned())
+        })
+        .unwrap_or_else(|| gid.to_string())
+}
+
+fn has_udev_rule() -> bool {
+    UDEV_RULES_DIRS
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .any(|rules| rules.contains("hidraw") && rules.to_ascii_lowercase().contains("0b05"))
+}
+
+/// Work out why `dev_node` can't be opened so the fix can be reported
+pub fn diagnose_permission(dev_node: &Path) -> HidPermissionProblem {
+    let (euid, groups) = process_ids();
+    if euid == Some(0) {
+        return HidPermissionProblem::Other;
+    }
+    if let Ok(meta) = std::fs::metadata(dev_node) {
+        if meta.mode() & 0o060 == 0o060 && meta.gid() != 0 && !groups.contains(&meta.gid()) {
+            return HidPermissionProblem::NotInGroup(group_name(meta.gid()));
+        }
+    }
+    if !has_udev_rule() {
+        return HidPermissionProblem::NoUdevRule;
+    }
+    HidPermissionProblem::Other
+}
+
+/// Another process with a device node open, such as OpenRGB or rogauracore,
+/// whose writes may overwrite or interleave with ours
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceUser {
+    pub pid: u32,
+    /// The process name from `/proc/<pid>/comm`
+    pub name: String,
+}
+
+impl fmt::Display for DeviceUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (pid {})", self.name, self.pid)
+    }
+}
+
+/// Processes other than this one with any of `dev_nodes` open. Processes
+/// owned by other users can only be seen when running as root.
+pub fn device_users(dev_nodes: &[PathBuf]) -> Vec<DeviceUser> {
+    let own_pid = std::process::id();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != own_pid)
+        .filter(|pid| {
+            std::fs::read_dir(format!("/proc/{pid}/fd"))
+                .map(|fds| {
+                    fds.flatten().any(|fd| {
+                        std::fs::read_link(fd.path())
+                            .map(|target| dev_nodes.contains(&target))
+                            .unwrap_or(false)
+                    })
+                })
+                .unwrap_or(false)
+        })
+        .map(|pid| DeviceUser {
+            pid,
+            name: std::fs::read_to_string(format!("/proc/{pid}/comm"))
+                .map(|n| n.trim().to_owned())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Open `dev_node` for writing. A permission error is diagnosed
+fn open_dev_node(dev_node: &Path) -> Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .open(dev_node)
+        .map_err(|e| match e.kind() {
+            ErrorKind::PermissionDenied => PlatformError::HidPermission(
+                dev_node.to_string_lossy().to_string(),
+                diagnose_permission(dev_node),
+            ),
+            _ => e.into(),
+        })
+}
+
+/// The identity of the USB device a hidraw node belongs to, as read from
+/// sysfs. Attributes which could not be read are empty
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UsbIdentity {
+    /// `idVendor`, such as `0b05`
+    pub vendor_id: String,
+    /// `idProduct`, such as `19b6`
+    pub product_id: String,
+    pub manufacturer: String,
+    pub product: String,
+    /// `bcdDevice`, the device release, which ASUS keyboards set to their
+    /// firmware version
+    pub bcd_device: Option<u16>,
+}
+
+impl UsbIdentity {
+    fn from_usb_device(usb_device: &Device) -> Self {
+        let attribute = |name: &str| {
+            usb_device
+                .attribute_value(name)
+                .map(|v| v.to_string_lossy().trim().to_owned())
+                .unwrap_or_default()
+        };
+        Self {
+            vendor_id: attribute("idVendor"),
+            product_id: attribute("idProduct"),
+            manufacturer: attribute("manufacturer"),
+            product: attribute("product"),
+            bcd_device: parse_bcd(&attribute("bcdDevice")),
+        }
+    }
+
+    /// `bcd_device` as a version, such as `3.05` for `0305`
+    pub fn firmware_version(&self) -> Option<String> {
+        self.bcd_device
+            .map(|bcd| format!("{:x}.{:02x}", bcd >> 8, bcd & 0xff))
+    }
+}
+
+/// sysfs gives `bcdDevice` as four hex digits
+fn parse_bcd(value: &str) -> Option<u16> {
+    u16::from_str_radix(value, 16).ok()
+}
+
 /// A USB device that utilizes hidraw for I/O
 #[derive(Debug)]
 pub struct HidRaw {
@@ -15,11 +226,14 @@ pub struct HidRaw {
     devfs_path: PathBuf,
     /// The sysfs path
     syspath: PathBuf,
-    /// The product ID. The vendor ID is not kept
+    /// The product ID
     prod_id: String,
-    _device_bcd: u32,
+    usb: UsbIdentity,
     /// Retaining a handle to the file for the duration of `HidRaw`
     file: RefCell<File>,
+    /// Retry failed writes, opening the device again, see
+    /// [`HidRaw::set_exclusive`]
+    exclusive: Cell<bool>,
 }
 
 impl HidRaw {
@@ -57,16 +271,12 @@ impl HidRaw {
                             );
                         }
                         return Ok(Self {
-                            file: RefCell::new(OpenOptions::new().write(true).open(dev_node)?),
+                            file: RefCell::new(open_dev_node(dev_node)?),
+                            exclusive: Cell::new(false),
                             devfs_path: dev_node.to_owned(),
                             prod_id: this_id_product.to_string_lossy().into(),
                             syspath: endpoint.syspath().into(),
-                            _device_bcd: usb_device
-                                .attribute_value("bcdDevice")
-                                .unwrap_or_default()
-                                .to_string_lossy()
-                                .parse()
-                                .unwrap_or_default(),
+                            usb: UsbIdentity::from_usb_device(&usb_device),
                         });
                     }
                 }
@@ -89,16 +299,12 @@ impl HidRaw {
             if let Some(dev_node) = endpoint.devnode() {
                 if let Some(id_product) = parent.attribute_value("idProduct") {
                     return Ok(Self {
-                        file: RefCell::new(OpenOptions::new().write(true).open(dev_node)?),
+                        file: RefCell::new(open_dev_node(dev_node)?),
+                        exclusive: Cell::new(false),
                         devfs_path: dev_node.to_owned(),
                         prod_id: id_product.to_string_lossy().into(),
                         syspath: endpoint.syspath().into(),
-                        _device_bcd: endpoint
-                            .attribute_value("bcdDevice")
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .parse()
-                            .unwrap_or_default(),
+                        usb: UsbIdentity::from_usb_device(&parent),
                     });
                 }
             }
@@ -108,21 +314,127 @@ impl HidRaw {
         ))
     }
 
+    /// Open a hidraw node directly, such as one made by
+    /// [`crate::uhid::UhidDevice`]
+    pub fn from_dev_node(dev_node: &Path, prod_id: &str) -> Result<Self> {
+        Ok(Self {
+            file: RefCell::new(open_dev_node(dev_node)?),
+            exclusive: Cell::new(false),
+            devfs_path: dev_node.to_owned(),
+            prod_id: prod_id.to_owned(),
+            syspath: dev_node.to_owned(),
+            usb: UsbIdentity {
+                product_id: prod_id.to_owned(),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// A simulated device, see [`crate::simulate`]. Writes are appended to
+    /// the file at `path`.
+    pub fn simulated(path: &Path, prod_id: &str) -> Result<Self> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        Ok(Self {
+            file: RefCell::new(file),
+            exclusive: Cell::new(false),
+            devfs_path: path.to_owned(),
+            prod_id: prod_id.to_owned(),
+            syspath: path.to_owned(),
+            usb: UsbIdentity {
+                product_id: prod_id.to_owned(),
+                ..Default::default()
+            },
+        })
+    }
+
     pub fn prod_id(&self) -> &str {
         &self.prod_id
     }
 
-    /// Write an array of raw bytes to the device using the hidraw interface
+    /// The USB device this node belongs to. Only the product ID is known for
+    /// simulated and uhid nodes
+    pub fn usb_identity(&self) -> &UsbIdentity {
+        &self.usb
+    }
+
+    /// The `/dev` node written to
+    pub fn dev_node(&self) -> &Path {
+        &self.devfs_path
+    }
+
+    /// Write an array of raw bytes to the device using the hidraw interface.
+    /// If the write fails while another process has the device open the
+    /// error names that process.
     pub fn write_bytes(&self, message: &[u8]) -> Result<()> {
         if let Ok(mut file) = self.file.try_borrow_mut() {
-            // TODO: re-get the file if error?
-            file.write_all(message).map_err(|e| {
-                PlatformError::IoPath(self.devfs_path.to_string_lossy().to_string(), e)
+            let mut result = match file.write_all(message) {
+                // The permissions may have been fixed since the node was
+                // opened, so open it again and retry once
+                Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                    *file = open_dev_node(&self.devfs_path)?;
+                    file.write_all(message)
+                }
+                result => result,
+            };
+            let mut retries = 0;
+            while result.is_err() && self.exclusive.get() && retries < EXCLUSIVE_RETRIES {
+                retries += 1;
+                std::thread::sleep(EXCLUSIVE_RETRY_DELAY);
+                if let Ok(reopened) = open_dev_node(&self.devfs_path) {
+                    *file = reopened;
+                    result = file.write_all(message);
+                }
+            }
+            result.map_err(|e| {
+                let path = self.devfs_path.to_string_lossy().to_string();
+                let users = self.users();
+                if users.is_empty() {
+                    PlatformError::IoPath(path, e)
+                } else {
+                    PlatformError::DeviceInUse(path, users, e)
+                }
             })?;
         }
         Ok(())
     }
 
+    /// Other processes which have this device open, by its hidraw node or
+    /// the node of its USB device as used by libusb
+    pub fn users(&self) -> Vec<DeviceUser> {
+        let mut dev_nodes = vec![self.devfs_path.clone()];
+        if let Some(usb_node) = Device::from_syspath(&self.syspath)
+            .ok()
+            .and_then(|dev| {
+                dev.parent_with_subsystem_devtype("usb", "usb_device")
+                    .ok()?
+            })
+            .and_then(|usb| usb.devnode().map(Path::to_path_buf))
+        {
+            dev_nodes.push(usb_node);
+        }
+        device_users(&dev_nodes)
+    }
+
+    /// In exclusive mode a failed write is retried a few times, opening the
+    /// device again each time, for when other RGB software briefly holds or
+    /// resets it
+    pub fn set_exclusive(&self, exclusive: bool) {
+        self.exclusive.set(exclusive);
+    }
+
+    pub fn exclusive(&self) -> bool {
+        self.exclusive.get()
+    }
+
+    /// Close and open the device node again, for a handle which went stale
+    /// such as after the device was reset
+    pub fn reopen(&self) -> Result<()> {
+        if let Ok(mut file) = self.file.try_borrow_mut() {
+            *file = open_dev_node(&self.devfs_path)?;
+        }
+        Ok(())
+    }
+
     /// This method was added for certain devices like AniMe to prevent them
     /// waking the laptop
     pub fn set_wakeup_disabled(&self) -> Result<()> {
@@ -130,3 +442,20 @@ impl HidRaw {
         Ok(dev.set_attribute_value("power/wakeup", "disabled")?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bcd, UsbIdentity};
+
+    #[test]
+    fn firmware_version() {
+        assert_eq!(parse_bcd("0305"), Some(0x0305));
+        assert_eq!(parse_bcd(""), None);
+        let usb = UsbIdentity {
+            bcd_device: parse_bcd("0305"),
+            ..Default::default()
+        };
+        assert_eq!(usb.firmware_version().as_deref(), Some("3.05"));
+        assert_eq!(UsbIdentity::default().firmware_version(), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+use crate::error::{PlatformError, Result};
+
+/// The input device `asus-nb-wmi` reports its hotkeys on
+const WMI_HOTKEYS_NAME: &str = "Asus WMI hotkeys";
+/// The USB vendor ID of the keyboards `hid-asus` reports hotkeys for
+const ASUS_VENDOR_ID: &str = "0b05";
+
+/// `EV_KEY` from `linux/input-event-codes.h`
+const EV_KEY: u16 = 0x01;
+/// The size of `struct input_event`, a `struct timeval` of two longs then the
+/// type, code, and value
+pub const INPUT_EVENT_SIZE: usize = 2 * std::mem::size_of::<usize>() + 8;
+
+/// Which driver an ASUS hotkey comes from. The two give some keys different
+/// codes, so the code alone doesn't name the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeySource {
+    /// `asus-nb-wmi`, on most laptops
+    Wmi,
+    /// `hid-asus`, on laptops with a USB keyboard such as the N-KEY device
+    Keyboard,
+}
+
+/// An input device node which reports ASUS hotkeys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotkeyDevice {
+    pub node: PathBuf,
+    pub source: HotkeySource,
+}
+
+/// Every key code [`hotkey_name`] names for some source
+pub const HOTKEY_CODES: [u16; 15] = [
+    140, 148, 149, 183, 191, 202, 203, 212, 228, 229, 230, 247, 248, 470, 530,
+];
+
+/// The name of an ASUS hotkey from its Linux key code. Codes which are not
+/// ASUS hotkeys are `None`, so ordinary typing is never named.
+pub fn hotkey_name(source: HotkeySource, code: u16) -> Option<&'static str> {
+    let name = match (source, code) {
+        // KEY_KBDILLUMTOGGLE, KEY_KBDILLUMDOWN, KEY_KBDILLUMUP
+        (_, 228) => "kbd-backlight-toggle",
+        (_, 229) => "kbd-brightness-down",
+        (_, 230) => "kbd-brightness-up",
+        // KEY_MICMUTE, KEY_CAMERA, KEY_RFKILL, KEY_CALC
+        (_, 248) => "mic-mute",
+        (_, 212) => "camera",
+        (_, 247) => "rfkill",
+        (_, 140) => "calculator",
+        // KEY_F13
+        (_, 183) => "screenpad",
+        // KEY_FN_F5
+        (_, 470) => "fan-mode",
+        // KEY_PROG1, KEY_PROG3, KEY_TOUCHPAD_TOGGLE
+        (HotkeySource::Wmi, 148) => "myasus",
+        (HotkeySource::Wmi, 202) => "rog",
+        (HotkeySource::Wmi, 530) => "touchpad-toggle",
+        // KEY_PROG1 to KEY_PROG4, KEY_F21
+        (HotkeySource::Keyboard, 148) => "rog",
+        (HotkeySource::Keyboard, 149) => "aura-previous",
+        (HotkeySource::Keyboard, 202) => "aura-next",
+        (HotkeySource::Keyboard, 203) => "fan-mode",
+        (HotkeySource::Keyboard, 191) => "touchpad-toggle",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// The key code of a key press in one `struct input_event`. Releases,
+/// repeats, and other event types are `None`
+pub fn key_press(event: &[u8]) -> Option<u16> {
+    if event.len() != INPUT_EVENT_SIZE {
+        return None;
+    }
+    let data = &event[INPUT_EVENT_SIZE - 8..];
+    let event_type = u16::from_ne_bytes([
+        data[0], data[1],
+    ]);
+    let code = u16::from_ne_bytes([
+        data[2], data[3],
+    ]);
+    let value = i32::from_ne_bytes([
+        data[4], data[5], data[6], data[7],
+    ]);
+    (event_type == EV_KEY && value == 1).then_some(code)
+}
+
+/// Find the input devices which report ASUS hotkeys
+pub fn hotkey_devices() -> Result<Vec<HotkeyDevice>> {
+    let mut enumerator = udev::Enumerator::new().map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("enumerator failed".into(), err)
+    })?;
+    enumerator.match_subsystem("input").map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("match_subsystem failed".into(), err)
+    })?;
+
+    let mut devices = Vec::new();
+    for device in enumerator.scan_devices().map_err(|err| {
+        warn!("{}", err);
+        PlatformError::Udev("scan_devices failed".into(), err)
+    })? {
+        let Some(node) = device.devnode() else {
+            continue;
+        };
+        if !node.to_string_lossy().starts_with("/dev/input/event") {
+            continue;
+        }
+        let name = device
+            .parent()
+            .and_then(|parent| parent.attribute_value("name").map(|n| n.to_owned()))
+            .unwrap_or_default();
+        let source = if name == WMI_HOTKEYS_NAME {
+            HotkeySource::Wmi
+        } else if device
+            .property_value("ID_VENDOR_ID")
+            .is_some_and(|v| v == ASUS_VENDOR_ID)
+            && device
+                .property_value("ID_INPUT_KEY")
+                .is_some_and(|v| v == "1")
+        {
+            HotkeySource::Keyboard
+        } else {
+            continue;
+        };
+        info!("Found ASUS hotkeys at {node:?} ({name:?})");
+        devices.push(HotkeyDevice {
+            node: node.to_path_buf(),
+            source,
+        });
+    }
+    if devices.is_empty() {
+        return Err(PlatformError::MissingFunction(
+            "No ASUS hotkey input devices".into(),
+        ));
+    }
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hotkey_name, key_press, HotkeySource, INPUT_EVENT_SIZE};
+
+    fn event(event_type: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut event = vec![0; INPUT_EVENT_SIZE - 8];
+        event.extend(event_type.to_ne_bytes());
+        event.extend(code.to_ne_bytes());
+        event.extend(value.to_ne_bytes());
+        event
+    }
+
+    #[test]
+    fn key_presses_only() {
+        assert_eq!(key_press(&event(1, 202, 1)), Some(202));
+        assert_eq!(key_press(&event(1, 202, 0)), None);
+        assert_eq!(key_press(&event(1, 202, 2)), None);
+        // EV_SYN and EV_MSC
+        assert_eq!(key_press(&event(0, 0, 0)), None);
+        assert_eq!(key_press(&event(4, 4, 0xae)), None);
+        assert_eq!(key_press(&[0; 4]), None);
+    }
+
+    #[test]
+    fn names_by_source() {
+        assert_eq!(hotkey_name(HotkeySource::Wmi, 202), Some("rog"));
+        assert_eq!(hotkey_name(HotkeySource::Keyboard, 202), Some("aura-next"));
+        assert_eq!(hotkey_name(HotkeySource::Keyboard, 148), Some("rog"));
+        assert_eq!(hotkey_name(HotkeySource::Wmi, 470), Some("fan-mode"));
+        // KEY_A is never named
+        assert_eq!(hotkey_name(HotkeySource::Keyboard, 30), None);
+    }
+}
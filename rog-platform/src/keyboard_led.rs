@@ -1,9 +1,9 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use log::{info, warn};
 
 use crate::error::{PlatformError, Result};
-use crate::{attr_num, has_attr, set_attr_u8_array, to_device};
+use crate::{attr_num, has_attr, set_attr_u8_array, simulate, to_device};
 
 /// The sysfs control for backlight levels. This is only for the 3-step
 /// backlight setting, and for TUF laptops. It is not a hard requirement
@@ -16,6 +16,8 @@ pub struct KeyboardBacklight {
 impl KeyboardBacklight {
     attr_num!("brightness", path, u8);
 
+    attr_num!("max_brightness", path, u8);
+
     has_attr!("kbd_rgb_mode" path);
 
     set_attr_u8_array!(
@@ -33,6 +35,11 @@ impl KeyboardBacklight {
     );
 
     pub fn new() -> Result<Self> {
+        if let Some(path) = simulate::path(simulate::KEYBOARD_LED) {
+            info!("Simulating keyboard LED controls at {path:?}");
+            return Ok(Self { path });
+        }
+
         let mut enumerator = udev::Enumerator::new().map_err(|err| {
             warn!("{}", err);
             PlatformError::Udev("enumerator failed".into(), err)
@@ -59,4 +66,9 @@ impl KeyboardBacklight {
             "KeyboardLed:new(), asus::kbd_backlight not found".into(),
         ))
     }
+
+    /// The sysfs directory of the LED class device
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 }
@@ -7,7 +7,7 @@ use serde::{Deserialize, Serialize};
 use zbus::zvariant::{OwnedValue, Type, Value};
 
 use crate::error::{PlatformError, Result};
-use crate::{attr_string, attr_string_array, to_device};
+use crate::{attr_bool, attr_string, attr_string_array, simulate, to_device};
 
 /// The "platform" device provides access to things like:
 /// - `dgpu_disable`
@@ -17,6 +17,7 @@ use crate::{attr_string, attr_string_array, to_device};
 /// - various CPU an GPU tunings
 /// - `keyboard_mode`, set keyboard RGB mode and speed
 /// - `keyboard_state`, set keyboard power states
+/// - `camera`, the webcam kill switch
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
 pub struct RogPlatform {
     path: PathBuf,
@@ -36,7 +37,22 @@ impl RogPlatform {
         pp_path
     );
 
+    attr_bool!(
+        /// Switches the webcam off at the firmware when false, on laptops
+        /// with a camera kill switch
+        "camera",
+        path
+    );
+
     pub fn new() -> Result<Self> {
+        if let Some(root) = simulate::root() {
+            info!("Simulating platform support in {root:?}");
+            return Ok(Self {
+                path: root.join(simulate::PLATFORM),
+                pp_path: root.join(simulate::ACPI),
+            });
+        }
+
         let mut enumerator = udev::Enumerator::new().map_err(|err| {
             warn!("{}", err);
             PlatformError::Udev("enumerator failed".into(), err)
@@ -81,7 +97,18 @@ impl Default for RogPlatform {
 
 #[repr(u8)]
 #[derive(
-    Serialize, Deserialize, Default, Type, Value, OwnedValue, Debug, PartialEq, Eq, Clone, Copy,
+    Serialize,
+    Deserialize,
+    Default,
+    Type,
+    Value,
+    OwnedValue,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Clone,
+    Copy,
 )]
 pub enum GpuMode {
     Optimus = 0,
@@ -159,6 +186,25 @@ impl GpuMode {
         }
         Self::Optimus
     }
+
+    /// Derive the mode from the `gpu_mux_mode`, `dgpu_disable`, and
+    /// `egpu_enable` values. The MUX takes precedence, then `dgpu_disable`,
+    /// then `egpu_enable`. `None` if the laptop has none of these.
+    pub fn from_attr_values(mux: Option<u8>, dgpu: Option<u8>, egpu: Option<u8>) -> Option<Self> {
+        if mux.is_none() && dgpu.is_none() && egpu.is_none() {
+            return None;
+        }
+        if mux.is_some_and(|m| Self::from_mux(m) == Self::Ultimate) {
+            return Some(Self::Ultimate);
+        }
+        if dgpu.is_some_and(|d| Self::from_dgpu(d) == Self::Integrated) {
+            return Some(Self::Integrated);
+        }
+        if egpu.is_some_and(|e| Self::from_egpu(e) == Self::Egpu) {
+            return Some(Self::Egpu);
+        }
+        Some(Self::Optimus)
+    }
 }
 
 impl Display for GpuMode {
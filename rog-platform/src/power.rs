@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use log::{info, warn};
 
 use crate::error::{PlatformError, Result};
-use crate::{attr_num, to_device};
+use crate::{attr_num, read_attr_num, read_attr_string, simulate, to_device};
 
 /// The "platform" device provides access to things like:
 /// - `dgpu_disable`
@@ -16,7 +16,69 @@ use crate::{attr_num, to_device};
 pub struct AsusPower {
     mains: PathBuf,
     battery: PathBuf,
-    usb: Option<PathBuf>,
+    /// Every battery, the main one first
+    batteries: Vec<PathBuf>,
+    /// The USB-C ports, a `power_supply` each
+    usb: Vec<PathBuf>,
+}
+
+/// A USB-C charger which negotiates less than this is slow. The chargers ASUS
+/// ships with laptops and handhelds which charge over USB-C are 65W or more
+pub const SLOW_CHARGER_WATTS: u32 = 45;
+
+/// Watts from the `voltage_max` and `current_max` of a USB-C port, which are
+/// in µV and µA
+pub fn pd_watts(microvolts: u64, microamps: u64) -> u32 {
+    (microvolts.saturating_mul(microamps) / 1_000_000_000_000) as u32
+}
+
+/// If a USB-C charger of `watts` is too weak: it negotiated less than
+/// [`SLOW_CHARGER_WATTS`], or the battery is discharging while it is
+/// connected. No charger is never slow
+pub fn charger_is_slow(watts: Option<u32>, battery_status: &str) -> bool {
+    watts.is_some_and(|watts| {
+        watts < SLOW_CHARGER_WATTS || battery_status.trim().eq_ignore_ascii_case("discharging")
+    })
+}
+
+/// One battery `power_supply` device, such as `BAT0` or a slice battery.
+/// Each can have its own `charge_control_end_threshold`
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
+pub struct Battery {
+    path: PathBuf,
+}
+
+impl Battery {
+    attr_num!("charge_control_end_threshold", path, u8);
+
+    attr_num!("capacity", path, u8);
+
+    /// The `power_supply` name, such as `BAT1`
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// A battery found while enumerating, for choosing the main one
+struct BatteryCandidate {
+    path: PathBuf,
+    name: String,
+    has_threshold: bool,
+}
+
+/// The index of the main battery, in order of:
+/// - if attr `charge_control_end_threshold` exists
+/// - if the sysfs name starts with `BAT`
+/// - the first battery
+fn main_battery(candidates: &[BatteryCandidate]) -> Option<usize> {
+    candidates
+        .iter()
+        .position(|b| b.has_threshold)
+        .or_else(|| candidates.iter().position(|b| b.name.starts_with("BAT")))
+        .or((!candidates.is_empty()).then_some(0))
 }
 
 impl AsusPower {
@@ -24,16 +86,29 @@ impl AsusPower {
 
     attr_num!("online", mains, u8);
 
-    /// When checking for battery this will look in order:
-    /// - if attr `manufacturer` contains `asus`
-    /// - if attr `charge_control_end_threshold` exists and `energy_full_design`
-    ///   >= 50 watt
+    attr_num!("capacity", battery, u8);
+
+    attr_num!("power_now", battery, u32);
+
+    /// All batteries are kept, and the main battery used by the methods here
+    /// is chosen in order:
+    /// - if attr `charge_control_end_threshold` exists
     /// - if syspath end conatins `BAT`
     /// - if attr `type` is `battery` (last resort)
     pub fn new() -> Result<Self> {
+        if let Some(root) = simulate::root() {
+            info!("Simulating power supplies in {root:?}");
+            return Ok(Self {
+                mains: root.join(simulate::MAINS),
+                battery: root.join(simulate::BATTERY),
+                batteries: vec![root.join(simulate::BATTERY)],
+                usb: vec![root.join(simulate::USB_C)],
+            });
+        }
+
         let mut mains = PathBuf::new();
-        let mut battery = None;
-        let mut usb = None;
+        let mut batteries = Vec::new();
+        let mut usb = Vec::new();
 
         let mut enumerator = udev::Enumerator::new().map_err(|err| {
             warn!("{}", err);
@@ -56,47 +131,36 @@ impl AsusPower {
                         mains = device.syspath().to_path_buf();
                     }
                     "battery" => {
-                        // Priortised list of checks
-                        info!("Found a battery");
-                        if battery.is_none() {
-                            info!("Checking battery attributes");
-                            if let Some(current) =
-                                device.attribute_value("charge_control_end_threshold")
-                            {
-                                info!(
-                                    "Found battery power at {:?}, matched \
-                                     charge_control_end_threshold. Current level: {current:?}",
-                                    device.sysname()
-                                );
-                                battery = Some(device.syspath().to_path_buf());
-                            } else if device.sysname().to_string_lossy().starts_with("BAT") {
-                                info!(
-                                    "Found battery power at {:?}, sysfs path ended with BAT<n>",
-                                    device.sysname()
-                                );
-                                battery = Some(device.syspath().to_path_buf());
-                            } else {
-                                info!(
-                                    "Last resort: Found battery power at {:?} using type = Battery",
-                                    device.sysname()
-                                );
-                                battery = Some(device.syspath().to_path_buf());
-                            }
-                        }
+                        let threshold = device.attribute_value("charge_control_end_threshold");
+                        info!(
+                            "Found a battery at {:?}, charge_control_end_threshold: {threshold:?}",
+                            device.sysname()
+                        );
+                        batteries.push(BatteryCandidate {
+                            path: device.syspath().to_path_buf(),
+                            name: device.sysname().to_string_lossy().into_owned(),
+                            has_threshold: threshold.is_some(),
+                        });
                     }
                     "usb" => {
                         info!("Found USB-C power at {:?}", device.sysname());
-                        usb = Some(device.syspath().to_path_buf());
+                        usb.push(device.syspath().to_path_buf());
                     }
                     _ => {}
                 };
             }
         }
 
-        if let Some(battery) = battery {
+        if let Some(main) = main_battery(&batteries) {
+            let main = batteries.remove(main);
+            info!("Using {:?} as the main battery", main.name);
+            let battery = main.path;
+            let mut batteries: Vec<PathBuf> = batteries.into_iter().map(|b| b.path).collect();
+            batteries.insert(0, battery.clone());
             return Ok(Self {
                 mains,
                 battery,
+                batteries,
                 usb,
             });
         }
@@ -105,4 +169,106 @@ impl AsusPower {
             "Did not find a battery".to_owned(),
         ))
     }
+
+    /// Every battery, the main one first. Most laptops have one, some have a
+    /// second or a slice battery
+    pub fn batteries(&self) -> Vec<Battery> {
+        self.batteries
+            .iter()
+            .map(|path| Battery { path: path.clone() })
+            .collect()
+    }
+
+    /// The battery with this `power_supply` name
+    pub fn battery(&self, name: &str) -> Result<Battery> {
+        self.batteries()
+            .into_iter()
+            .find(|battery| battery.name() == name)
+            .ok_or_else(|| PlatformError::MissingFunction(format!("No battery named {name}")))
+    }
+
+    /// If the laptop has a USB-C port which reports charging
+    pub fn has_usb_c(&self) -> bool {
+        !self.usb.is_empty()
+    }
+
+    /// The power negotiated by the USB-C charger in watts, the highest if
+    /// more than one is connected. `None` if no USB-C charger is connected
+    pub fn usb_pd_watts(&self) -> Option<u32> {
+        self.usb
+            .iter()
+            .filter_map(|path| {
+                let device = to_device(path).ok()?;
+                if read_attr_num::<u8>(&device, "online").ok()? == 0 {
+                    return None;
+                }
+                let microvolts = read_attr_num::<u64>(&device, "voltage_max")
+                    .or_else(|_| read_attr_num::<u64>(&device, "voltage_now"))
+                    .ok()?;
+                let microamps = read_attr_num::<u64>(&device, "current_max").ok()?;
+                Some(pd_watts(microvolts, microamps))
+            })
+            .max()
+    }
+
+    /// If a USB-C charger is connected and is too weak to charge the battery,
+    /// see [`charger_is_slow`]
+    pub fn slow_charger(&self) -> bool {
+        let status = to_device(&self.battery)
+            .and_then(|device| read_attr_string(&device, "status"))
+            .unwrap_or_default();
+        charger_is_slow(self.usb_pd_watts(), &status)
+    }
+
+    /// The `power_supply` name of the main battery
+    pub fn main_battery_name(&self) -> String {
+        Battery {
+            path: self.battery.clone(),
+        }
+        .name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{charger_is_slow, main_battery, pd_watts, BatteryCandidate};
+
+    fn candidate(name: &str, has_threshold: bool) -> BatteryCandidate {
+        BatteryCandidate {
+            path: PathBuf::from(format!("/sys/class/power_supply/{name}")),
+            name: name.to_owned(),
+            has_threshold,
+        }
+    }
+
+    #[test]
+    fn main_battery_order() {
+        assert_eq!(main_battery(&[]), None);
+        let batteries = [
+            candidate("hid-0018", false),
+            candidate("BAT1", false),
+        ];
+        assert_eq!(main_battery(&batteries), Some(1));
+        let batteries = [
+            candidate("hid-0018", false),
+            candidate("BAT0", false),
+            candidate("BAT1", true),
+        ];
+        assert_eq!(main_battery(&batteries), Some(2));
+        assert_eq!(main_battery(&[candidate("CMB0", false)]), Some(0));
+    }
+
+    #[test]
+    fn usb_pd() {
+        assert_eq!(pd_watts(20_000_000, 3_250_000), 65);
+        assert_eq!(pd_watts(5_000_000, 3_000_000), 15);
+        assert_eq!(pd_watts(0, 3_000_000), 0);
+        assert!(!charger_is_slow(None, "Discharging"));
+        assert!(!charger_is_slow(Some(65), "Charging\n"));
+        assert!(charger_is_slow(Some(65), "Discharging\n"));
+        assert!(charger_is_slow(Some(30), "Charging"));
+        assert!(!charger_is_slow(Some(45), "Full"));
+    }
 }
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+use crate::error::{PlatformError, Result};
+use crate::{attr_num, simulate, to_device};
+
+/// The LEDs some laptops have to show the microphone is muted or the camera
+/// is off
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy)]
+pub enum PrivacyLedType {
+    MicMute,
+    Camera,
+}
+
+impl PrivacyLedType {
+    /// The LED class names used by `asus-wmi` and the generic audio triggers
+    fn sysnames(&self) -> &'static [&'static str] {
+        match self {
+            Self::MicMute => &[
+                "platform::micmute", "asus::micmute",
+            ],
+            Self::Camera => &[
+                "platform::camera", "asus::camera",
+            ],
+        }
+    }
+
+    fn simulated(&self) -> &'static str {
+        match self {
+            Self::MicMute => simulate::MICMUTE_LED,
+            Self::Camera => simulate::CAMERA_LED,
+        }
+    }
+}
+
+/// A privacy LED from the "leds" class. The mic-mute LED is normally driven
+/// by the `audio-micmute` trigger, and setting the brightness overrides it
+/// until the next mute change.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
+pub struct PrivacyLed {
+    path: PathBuf,
+    led_type: PrivacyLedType,
+}
+
+impl PrivacyLed {
+    attr_num!("brightness", path, u8);
+
+    pub fn new(led_type: PrivacyLedType) -> Result<Self> {
+        if let Some(path) = simulate::path(led_type.simulated()) {
+            if path.exists() {
+                info!("Simulating {led_type:?} LED at {path:?}");
+                return Ok(Self { path, led_type });
+            }
+        }
+
+        let mut enumerator = udev::Enumerator::new().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("enumerator failed".into(), err)
+        })?;
+        enumerator.match_subsystem("leds").map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("match_subsystem failed".into(), err)
+        })?;
+
+        for device in enumerator.scan_devices().map_err(|err| {
+            warn!("{}", err);
+            PlatformError::Udev("scan_devices failed".into(), err)
+        })? {
+            let sysname = device.sysname().to_string_lossy();
+            if led_type.sysnames().contains(&sysname.as_ref()) {
+                info!("Found {led_type:?} LED at {sysname}");
+                return Ok(Self {
+                    path: device.syspath().to_owned(),
+                    led_type,
+                });
+            }
+        }
+        Err(PlatformError::MissingFunction(format!(
+            "{led_type:?} LED not found"
+        )))
+    }
+
+    pub fn led_type(&self) -> PrivacyLedType {
+        self.led_type
+    }
+
+    /// If the LED is lit
+    pub fn is_on(&self) -> Result<bool> {
+        Ok(self.get_brightness()? > 0)
+    }
+
+    pub fn set_on(&self, on: bool) -> Result<()> {
+        self.set_brightness(u8::from(on))
+    }
+}
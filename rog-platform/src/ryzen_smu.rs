@@ -0,0 +1,117 @@
+use std::fs::{read_to_string, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use log::{debug, info};
+
+use crate::error::{PlatformError, Result};
+
+/// Provided by the out-of-tree `ryzen_smu` driver
+const RYZEN_SMU_PATH: &str = "/sys/kernel/ryzen_smu_drv/";
+
+/// The smallest and largest all-core curve optimizer offsets accepted. Mobile
+/// parts generally do not allow more than this.
+pub const CURVE_OPTIMIZER_MIN: i32 = -30;
+pub const CURVE_OPTIMIZER_MAX: i32 = 30;
+
+/// The RSMU command to set the all-core curve optimizer offset for a CPU
+/// codename, as numbered by `ryzen_smu`. Only mobile APUs are listed.
+fn all_core_offset_cmd(codename: u32) -> Option<u32> {
+    match codename {
+        // Renoir, Cezanne, Lucienne
+        2 | 14 | 17 => Some(0xb1),
+        // Rembrandt, Phoenix
+        11 | 21 => Some(0x5d),
+        _ => None,
+    }
+}
+
+/// Offsets are sent as a 20 bit two's complement value
+fn encode_offset(offset: i32) -> u32 {
+    (offset as u32) & 0xf_ffff
+}
+
+/// AMD curve optimizer control through the `ryzen_smu` driver. The SMU has no
+/// way to read back the current offset, so callers need to track what was
+/// last applied.
+#[derive(Debug, Clone)]
+pub struct RyzenSmu {
+    path: PathBuf,
+    all_core_offset_cmd: u32,
+}
+
+impl RyzenSmu {
+    pub fn new() -> Result<Self> {
+        let path = PathBuf::from(RYZEN_SMU_PATH);
+        let codename_path = path.join("codename");
+        let codename: u32 = read_to_string(&codename_path)
+            .map_err(|e| PlatformError::IoPath(codename_path.to_string_lossy().to_string(), e))?
+            .trim()
+            .parse()
+            .map_err(|_| PlatformError::ParseNum)?;
+        let Some(all_core_offset_cmd) = all_core_offset_cmd(codename) else {
+            return Err(PlatformError::MissingFunction(format!(
+                "ryzen_smu: curve optimizer not supported for codename {codename}"
+            )));
+        };
+        info!("ryzen_smu: found codename {codename}");
+        Ok(Self {
+            path,
+            all_core_offset_cmd,
+        })
+    }
+
+    fn write(path: &Path, data: &[u8]) -> Result<()> {
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|mut f| f.write_all(data))
+            .map_err(|e| PlatformError::Write(path.to_string_lossy().to_string(), e))
+    }
+
+    /// Send an RSMU command with one argument and check the response
+    fn rsmu_cmd(&self, cmd: u32, arg: u32) -> Result<()> {
+        let mut args = [0u8; 24];
+        args[..4].copy_from_slice(&arg.to_le_bytes());
+        Self::write(&self.path.join("smu_args"), &args)?;
+
+        let cmd_path = self.path.join("rsmu_cmd");
+        Self::write(&cmd_path, &cmd.to_le_bytes())?;
+
+        let mut response = [0u8; 4];
+        OpenOptions::new()
+            .read(true)
+            .open(&cmd_path)
+            .and_then(|mut f| f.read_exact(&mut response))
+            .map_err(|e| PlatformError::Read(cmd_path.to_string_lossy().to_string(), e))?;
+        match u32::from_le_bytes(response) {
+            0x01 => Ok(()),
+            r => Err(PlatformError::CPU(format!(
+                "ryzen_smu: command {cmd:#x} failed with {r:#x}"
+            ))),
+        }
+    }
+
+    /// Set the curve optimizer offset for all cores. Negative values
+    /// undervolt.
+    pub fn set_all_core_offset(&self, offset: i32) -> Result<()> {
+        if !(CURVE_OPTIMIZER_MIN..=CURVE_OPTIMIZER_MAX).contains(&offset) {
+            return Err(PlatformError::InvalidValue);
+        }
+        debug!("ryzen_smu: setting all core curve optimizer offset to {offset}");
+        self.rsmu_cmd(self.all_core_offset_cmd, encode_offset(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_offset;
+
+    #[test]
+    fn offset_encoding() {
+        assert_eq!(encode_offset(0), 0);
+        assert_eq!(encode_offset(5), 5);
+        assert_eq!(encode_offset(-1), 0xf_ffff);
+        assert_eq!(encode_offset(-20), 0x10_0000 - 20);
+    }
+}
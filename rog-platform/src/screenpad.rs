@@ -0,0 +1,80 @@
+use crate::backlight::Backlight;
+use crate::error::Result;
+
+/// The lowest gamma accepted for the screenpad brightness curve
+pub const GAMMA_MIN: f32 = 0.1;
+/// The highest gamma accepted for the screenpad brightness curve
+pub const GAMMA_MAX: f32 = 2.0;
+
+/// `bl_power` value for a backlight that is on, `FB_BLANK_UNBLANK`
+const BL_POWER_ON: i32 = 0;
+/// `bl_power` value for a backlight that is off, `FB_BLANK_POWERDOWN`
+const BL_POWER_OFF: i32 = 4;
+
+/// Convert a brightness of 0-100 to a raw value of 0-`max` using `gamma`. A
+/// gamma of 1.0 is linear, above it gives finer steps at the dim end.
+pub fn level_to_raw(level: i32, max: i32, gamma: f32) -> i32 {
+    let normalized = level.clamp(0, 100) as f32 / 100.0;
+    (normalized.powf(gamma) * max as f32) as i32
+}
+
+/// The inverse of [`level_to_raw`], rounded to the nearest percent
+pub fn raw_to_level(raw: i32, max: i32, gamma: f32) -> i32 {
+    if max <= 0 {
+        return 0;
+    }
+    let normalized = raw.clamp(0, max) as f32 / max as f32;
+    (normalized.powf(1.0 / gamma) * 100.0).round() as i32
+}
+
+// The screenpad of the ROG and Zenbook Duo laptops is driven by `asus-wmi` as a
+// second backlight device, with power and a brightness curve on top
+impl Backlight {
+    /// If the panel is powered on
+    pub fn is_powered(&self) -> Result<bool> {
+        Ok(self.get_bl_power()? == BL_POWER_ON)
+    }
+
+    /// Power the panel on or off. The brightness is kept while it is off
+    pub fn set_powered(&self, on: bool) -> Result<()> {
+        self.set_bl_power(if on { BL_POWER_ON } else { BL_POWER_OFF })
+    }
+
+    /// The brightness as 0-100 through the `gamma` curve
+    pub fn get_level(&self, gamma: f32) -> Result<i32> {
+        Ok(raw_to_level(
+            self.get_brightness()?,
+            self.get_max_brightness()?,
+            gamma,
+        ))
+    }
+
+    /// Set the brightness as 0-100 through the `gamma` curve
+    pub fn set_level(&self, level: i32, gamma: f32) -> Result<()> {
+        self.set_brightness(level_to_raw(level, self.get_max_brightness()?, gamma))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{level_to_raw, raw_to_level};
+
+    #[test]
+    fn gamma_round_trip() {
+        assert_eq!(level_to_raw(100, 255, 1.0), 255);
+        assert_eq!(level_to_raw(50, 200, 1.0), 100);
+        assert_eq!(level_to_raw(50, 200, 2.0), 50);
+        assert_eq!(level_to_raw(150, 200, 1.0), 200);
+        for gamma in [
+            0.5, 1.0, 2.0,
+        ] {
+            for level in [
+                0, 25, 60, 100,
+            ] {
+                let raw = level_to_raw(level, 10000, gamma);
+                assert_eq!(raw_to_level(raw, 10000, gamma), level);
+            }
+        }
+        assert_eq!(raw_to_level(10, 0, 1.0), 0);
+    }
+}
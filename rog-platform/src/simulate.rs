@@ -0,0 +1,177 @@
+//! A simulated laptop for developing and testing without ASUS hardware. When
+//! `ASUSD_SIMULATE` is set to a directory the platform, power, keyboard LED,
+//! firmware attribute, and fan curve devices are found there instead of
+//! through udev, and every attribute is a plain file which can be read,
+//! written, and watched. [`populate`] fills the directory with a 2021 laptop
+//! keyboard, three platform profiles, two fans, and a few PPT attributes.
+//! Files which already exist are kept, so the directory can be edited to
+//! stand in for other hardware.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The environment variable holding the directory to simulate the hardware in
+pub const SIMULATE_ENV: &str = "ASUSD_SIMULATE";
+/// The product ID of the simulated laptop keyboard
+pub const SIM_AURA_PROD_ID: &str = "19b6";
+
+/// The `asus-nb-wmi` platform device
+pub const PLATFORM: &str = "platform/asus-nb-wmi";
+/// The ACPI `platform_profile` attributes
+pub const ACPI: &str = "firmware/acpi";
+pub const MAINS: &str = "power_supply/AC0";
+pub const BATTERY: &str = "power_supply/BAT0";
+/// A USB-C port with a 65W charger
+pub const USB_C: &str = "power_supply/ucsi-source-psy-USBC000:001";
+pub const KEYBOARD_LED: &str = "leds/asus::kbd_backlight";
+pub const MICMUTE_LED: &str = "leds/platform::micmute";
+pub const CAMERA_LED: &str = "leds/platform::camera";
+pub const FIRMWARE_ATTRIBUTES: &str = "firmware-attributes/asus-armoury/attributes";
+pub const FAN_CURVES: &str = "hwmon/asus_custom_fan_curve";
+/// Writes to the simulated keyboard are appended to this file
+pub const AURA_HIDRAW: &str = "hidraw/aura";
+
+/// `(name, default, min, max)` of the simulated firmware attributes
+const ATTRIBUTES: &[(&str, i32, i32, i32)] = &[
+    ("ppt_pl1_spl", 45, 15, 80),
+    ("ppt_pl2_sppt", 65, 15, 80),
+    ("nv_dynamic_boost", 15, 5, 25),
+];
+
+/// The directory the hardware is simulated in, if `ASUSD_SIMULATE` is set
+pub fn root() -> Option<&'static Path> {
+    static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        std::env::var_os(SIMULATE_ENV)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    })
+    .as_deref()
+}
+
+/// `relative` joined to the simulation directory, if simulating
+pub fn path(relative: &str) -> Option<PathBuf> {
+    root().map(|root| root.join(relative))
+}
+
+fn write_default(dir: &Path, name: &str, value: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    if !path.exists() {
+        std::fs::write(path, value)?;
+    }
+    Ok(())
+}
+
+/// Fill `root` with the default simulated laptop
+pub fn populate(root: &Path) -> std::io::Result<()> {
+    // The `camera` attribute switches the webcam off when 0
+    write_default(&root.join(PLATFORM), "camera", "1")?;
+
+    let acpi = root.join(ACPI);
+    write_default(&acpi, "platform_profile", "balanced")?;
+    write_default(
+        &acpi,
+        "platform_profile_choices",
+        "quiet balanced performance",
+    )?;
+
+    let mains = root.join(MAINS);
+    write_default(&mains, "type", "Mains")?;
+    write_default(&mains, "online", "1")?;
+    let battery = root.join(BATTERY);
+    write_default(&battery, "type", "Battery")?;
+    write_default(&battery, "charge_control_end_threshold", "100")?;
+    write_default(&battery, "capacity", "80")?;
+    write_default(&battery, "power_now", "15000000")?;
+    write_default(&battery, "status", "Charging")?;
+    let usb_c = root.join(USB_C);
+    write_default(&usb_c, "type", "USB")?;
+    write_default(&usb_c, "online", "1")?;
+    write_default(&usb_c, "voltage_max", "20000000")?;
+    write_default(&usb_c, "current_max", "3250000")?;
+
+    let led = root.join(KEYBOARD_LED);
+    write_default(&led, "brightness", "2")?;
+    write_default(&led, "max_brightness", "3")?;
+    for privacy_led in [
+        MICMUTE_LED, CAMERA_LED,
+    ] {
+        write_default(&root.join(privacy_led), "brightness", "0")?;
+        write_default(&root.join(privacy_led), "max_brightness", "1")?;
+    }
+
+    let attributes = root.join(FIRMWARE_ATTRIBUTES);
+    write_default(&attributes, "pending_reboot", "0")?;
+    for (name, default, min, max) in ATTRIBUTES {
+        let dir = attributes.join(name);
+        write_default(&dir, "display_name", name)?;
+        write_default(&dir, "current_value", &default.to_string())?;
+        write_default(&dir, "default_value", &default.to_string())?;
+        write_default(&dir, "min_value", &min.to_string())?;
+        write_default(&dir, "max_value", &max.to_string())?;
+        write_default(&dir, "scalar_increment", "1")?;
+    }
+
+    let fans = root.join(FAN_CURVES);
+    write_default(&fans, "name", "asus_custom_fan_curve")?;
+    for fan in 1..=2 {
+        write_default(&fans, &format!("pwm{fan}_enable"), "2")?;
+        for point in 1..=8 {
+            let temp = 30 + point * 8;
+            let pwm = point * 30;
+            write_default(
+                &fans,
+                &format!("pwm{fan}_auto_point{point}_temp"),
+                &temp.to_string(),
+            )?;
+            write_default(
+                &fans,
+                &format!("pwm{fan}_auto_point{point}_pwm"),
+                &pwm.to_string(),
+            )?;
+        }
+    }
+
+    write_default(&root.join("hidraw"), "aura", "")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::SysfsDevice;
+    use crate::{read_attr_num, write_attr_num};
+
+    #[test]
+    fn populated_attributes() {
+        let root = std::env::temp_dir().join(format!("rog-simulate-{}", std::process::id()));
+        populate(&root).unwrap();
+
+        let mut battery = SysfsDevice::Simulated(root.join(BATTERY));
+        assert_eq!(
+            read_attr_num::<u8>(&battery, "charge_control_end_threshold").unwrap(),
+            100
+        );
+        write_attr_num(&mut battery, "charge_control_end_threshold", 80).unwrap();
+        assert_eq!(
+            read_attr_num::<u8>(&battery, "charge_control_end_threshold").unwrap(),
+            80
+        );
+
+        // Edits are kept
+        populate(&root).unwrap();
+        assert_eq!(
+            read_attr_num::<u8>(&battery, "charge_control_end_threshold").unwrap(),
+            80
+        );
+
+        let platform = SysfsDevice::Simulated(root.join(PLATFORM));
+        assert!(crate::read_attr_bool(&platform, "camera").unwrap());
+
+        let fans = SysfsDevice::Simulated(root.join(FAN_CURVES));
+        assert!(fans.attribute_names().contains(&"pwm2_enable".to_owned()));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
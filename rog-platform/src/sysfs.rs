@@ -0,0 +1,84 @@
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use udev::Device;
+
+use crate::error::{PlatformError, Result};
+use crate::simulate;
+
+/// A sysfs device, or a directory of plain files standing in for one when
+/// asusd simulates the hardware
+#[derive(Debug, Clone)]
+pub enum SysfsDevice {
+    Udev(Device),
+    Simulated(PathBuf),
+}
+
+impl SysfsDevice {
+    pub fn from_syspath(sys_path: &Path) -> Result<Self> {
+        if simulate::root().is_some_and(|root| sys_path.starts_with(root)) {
+            return Ok(Self::Simulated(sys_path.to_owned()));
+        }
+        Device::from_syspath(sys_path)
+            .map(Self::Udev)
+            .map_err(|e| PlatformError::Udev("Couldn't transform syspath to device".to_owned(), e))
+    }
+
+    pub fn syspath(&self) -> &Path {
+        match self {
+            Self::Udev(device) => device.syspath(),
+            Self::Simulated(path) => path,
+        }
+    }
+
+    /// The value of an attribute without the trailing newline
+    pub fn attribute_value(&self, attribute: &str) -> Option<OsString> {
+        match self {
+            Self::Udev(device) => device.attribute_value(attribute).map(OsStr::to_owned),
+            Self::Simulated(path) => std::fs::read(path.join(attribute)).ok().map(|mut v| {
+                while v.last() == Some(&b'\n') {
+                    v.pop();
+                }
+                OsStr::from_bytes(&v).to_owned()
+            }),
+        }
+    }
+
+    pub fn set_attribute_value<T: AsRef<OsStr>, U: AsRef<OsStr>>(
+        &mut self,
+        attribute: T,
+        value: U,
+    ) -> std::io::Result<()> {
+        match self {
+            Self::Udev(device) => device.set_attribute_value(attribute, value),
+            Self::Simulated(path) => {
+                std::fs::write(path.join(attribute.as_ref()), value.as_ref().as_bytes())
+            }
+        }
+    }
+
+    /// The names of all attributes of the device
+    pub fn attribute_names(&self) -> Vec<String> {
+        match self {
+            Self::Udev(device) => device
+                .attributes()
+                .map(|a| a.name().to_string_lossy().to_string())
+                .collect(),
+            Self::Simulated(path) => std::fs::read_dir(path)
+                .map(|dir| {
+                    dir.flatten()
+                        .filter(|e| e.path().is_file())
+                        .map(|e| e.file_name().to_string_lossy().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Device> for SysfsDevice {
+    fn from(device: Device) -> Self {
+        Self::Udev(device)
+    }
+}
@@ -0,0 +1,147 @@
+//! Virtual HID devices made through `/dev/uhid`, for testing the exact
+//! reports written to a hidraw node without the hardware. The kernel gives
+//! each device a hidraw node, and the reports written to it are read back
+//! with [`UhidDevice::read_output`]. Needs write access to `/dev/uhid`, which
+//! is normally root only.
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::error::{PlatformError, Result};
+
+const UHID_PATH: &str = "/dev/uhid";
+const O_NONBLOCK: i32 = 0o4000;
+
+const UHID_DESTROY: u32 = 1;
+const UHID_OUTPUT: u32 = 6;
+const UHID_CREATE2: u32 = 11;
+const BUS_USB: u16 = 0x03;
+
+/// `sizeof(struct uhid_event)`, the event type and the largest request,
+/// `struct uhid_create2_req`
+const EVENT_LEN: usize = 4 + 128 + 64 + 64 + 2 + 2 + 4 + 4 + 4 + 4 + 4096;
+/// `struct uhid_output_req` is the report data followed by its size
+const OUTPUT_SIZE_OFFSET: usize = 4 + 4096;
+
+/// How long to wait for the hidraw node or a report
+const TIMEOUT: Duration = Duration::from_secs(2);
+const POLL_DELAY: Duration = Duration::from_millis(10);
+
+/// A vendor defined report descriptor with 63 byte input and output reports
+/// on report ID `0x5d`, the ID laptop keyboards use for LED control
+pub const AURA_REPORT_DESCRIPTOR: &[u8] = &[
+    0x06, 0x31, 0xff, // Usage Page (Vendor 0xff31)
+    0x09, 0x79, // Usage (0x79)
+    0xa1, 0x01, // Collection (Application)
+    0x85, 0x5d, //   Report ID (0x5d)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x2a, 0xff, 0x00, //   Usage Maximum (255)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x26, 0xff, 0x00, //   Logical Maximum (255)
+    0x75, 0x08, //   Report Size (8)
+    0x95, 0x3f, //   Report Count (63)
+    0x81, 0x00, //   Input (Data, Array, Absolute)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x2a, 0xff, 0x00, //   Usage Maximum (255)
+    0x91, 0x00, //   Output (Data, Array, Absolute)
+    0xc0, // End Collection
+];
+
+/// A virtual USB HID device which exists until dropped
+pub struct UhidDevice {
+    file: File,
+    name: String,
+}
+
+impl UhidDevice {
+    /// Create a device with the USB `vendor` and `product` IDs. The `name`
+    /// is used to find the hidraw node so should be unique.
+    pub fn create(name: &str, vendor: u32, product: u32, descriptor: &[u8]) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(O_NONBLOCK)
+            .open(UHID_PATH)
+            .map_err(|e| PlatformError::IoPath(UHID_PATH.to_owned(), e))?;
+
+        let mut event = vec![0u8; EVENT_LEN];
+        event[..4].copy_from_slice(&UHID_CREATE2.to_ne_bytes());
+        let name_len = name.len().min(127);
+        event[4..4 + name_len].copy_from_slice(&name.as_bytes()[..name_len]);
+        event[260..262].copy_from_slice(&(descriptor.len() as u16).to_ne_bytes());
+        event[262..264].copy_from_slice(&BUS_USB.to_ne_bytes());
+        event[264..268].copy_from_slice(&vendor.to_ne_bytes());
+        event[268..272].copy_from_slice(&product.to_ne_bytes());
+        event[280..280 + descriptor.len()].copy_from_slice(descriptor);
+        file.write_all(&event)?;
+
+        Ok(Self {
+            file,
+            name: name.to_owned(),
+        })
+    }
+
+    /// The `/dev/hidraw*` node the kernel made for this device
+    pub fn hidraw_node(&self) -> Result<PathBuf> {
+        let hid_name = format!("HID_NAME={}", self.name);
+        let start = Instant::now();
+        while start.elapsed() < TIMEOUT {
+            let found = std::fs::read_dir("/sys/class/hidraw")?
+                .flatten()
+                .find(|entry| {
+                    std::fs::read_to_string(entry.path().join("device/uevent"))
+                        .is_ok_and(|uevent| uevent.lines().any(|l| l == hid_name))
+                })
+                .map(|entry| PathBuf::from("/dev").join(entry.file_name()));
+            if let Some(node) = found.filter(|node| node.exists()) {
+                return Ok(node);
+            }
+            std::thread::sleep(POLL_DELAY);
+        }
+        Err(PlatformError::MissingFunction(format!(
+            "no hidraw node for {}",
+            self.name
+        )))
+    }
+
+    /// The next report written to the hidraw node, including the report ID
+    pub fn read_output(&mut self) -> Result<Vec<u8>> {
+        let mut event = vec![0u8; EVENT_LEN];
+        let start = Instant::now();
+        while start.elapsed() < TIMEOUT {
+            match self.file.read(&mut event) {
+                Ok(len) if len >= OUTPUT_SIZE_OFFSET + 2 => {
+                    let kind = u32::from_ne_bytes([
+                        event[0], event[1], event[2], event[3],
+                    ]);
+                    if kind != UHID_OUTPUT {
+                        continue;
+                    }
+                    let size = u16::from_ne_bytes([
+                        event[OUTPUT_SIZE_OFFSET],
+                        event[OUTPUT_SIZE_OFFSET + 1],
+                    ]) as usize;
+                    return Ok(event[4..4 + size].to_vec());
+                }
+                Ok(_) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL_DELAY),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(PlatformError::MissingFunction(format!(
+            "no report written to {}",
+            self.name
+        )))
+    }
+}
+
+impl Drop for UhidDevice {
+    fn drop(&mut self) {
+        let mut event = vec![0u8; EVENT_LEN];
+        event[..4].copy_from_slice(&UHID_DESTROY.to_ne_bytes());
+        self.file.write_all(&event).ok();
+    }
+}
@@ -0,0 +1,68 @@
+use log::info;
+
+use crate::error::{PlatformError, Result};
+use crate::hid_raw::HidRaw;
+
+/// USB product IDs of the XG Mobile enclosures, the GC31 and GC32
+pub const XG_MOBILE_PROD_IDS: [&str; 2] = [
+    "1970", "1a9a",
+];
+
+/// Where the ROG XG Mobile eGPU is, from `egpu_connected` and `egpu_enable`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XgMobileState {
+    Disconnected,
+    /// Plugged in and locked, but not in use
+    Connected,
+    /// In use, so it must be disabled before the lock is opened
+    Enabled,
+}
+
+impl XgMobileState {
+    pub fn from_attr_values(connected: Option<u8>, enabled: Option<u8>) -> Self {
+        match (connected, enabled) {
+            (Some(1), Some(1)) => Self::Enabled,
+            (Some(1), _) => Self::Connected,
+            _ => Self::Disconnected,
+        }
+    }
+}
+
+/// The HID device of a connected XG Mobile, which takes the same LED packets
+/// as the Aura laptop keyboards
+pub fn xg_mobile_leds() -> Result<HidRaw> {
+    for prod_id in XG_MOBILE_PROD_IDS {
+        if let Ok(device) = HidRaw::new(prod_id) {
+            info!("Found XG Mobile LEDs at {:?}", device.prod_id());
+            return Ok(device);
+        }
+    }
+    Err(PlatformError::MissingFunction(
+        "No XG Mobile LED device".into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XgMobileState;
+
+    #[test]
+    fn state_from_attrs() {
+        assert_eq!(
+            XgMobileState::from_attr_values(None, None),
+            XgMobileState::Disconnected
+        );
+        assert_eq!(
+            XgMobileState::from_attr_values(Some(0), Some(1)),
+            XgMobileState::Disconnected
+        );
+        assert_eq!(
+            XgMobileState::from_attr_values(Some(1), Some(0)),
+            XgMobileState::Connected
+        );
+        assert_eq!(
+            XgMobileState::from_attr_values(Some(1), Some(1)),
+            XgMobileState::Enabled
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "dbus")]
+use zbus::zvariant::{OwnedValue, Type, Value};
+
+use crate::error::ProfileError;
+use crate::fan_curve_set::CurveData;
+
+/// Curve points at or above this temperature in °C are never capped, so the
+/// fans can always cool the laptop when it is hot
+pub const ACOUSTIC_SAFETY_TEMP: u8 = 85;
+
+/// Noise presets which cap the fan duty of the curves written for every
+/// profile, to roughly reach a sound level. The levels are approximate as the
+/// noise at a given duty differs between laptops. The stored curves are not
+/// changed.
+#[cfg_attr(
+    feature = "dbus",
+    derive(Type, Value, OwnedValue),
+    zvariant(signature = "s")
+)]
+#[derive(Deserialize, Serialize, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum AcousticPreset {
+    /// The curves are written as they are
+    #[default]
+    Off,
+    /// Under about 30dBA
+    Silent,
+    /// Under about 40dBA
+    Balanced,
+    /// No cap, as loud as the curves ask for
+    Max,
+}
+
+impl AcousticPreset {
+    /// The highest fan duty in percent below `ACOUSTIC_SAFETY_TEMP`, if any
+    pub fn max_duty(self) -> Option<u8> {
+        match self {
+            Self::Silent => Some(35),
+            Self::Balanced => Some(60),
+            Self::Off | Self::Max => None,
+        }
+    }
+
+    /// The rough noise level the preset aims for
+    pub fn approx_dba(self) -> Option<u8> {
+        match self {
+            Self::Silent => Some(30),
+            Self::Balanced => Some(40),
+            Self::Off | Self::Max => None,
+        }
+    }
+
+    /// `curve` with the points below `ACOUSTIC_SAFETY_TEMP` capped. The
+    /// result rises with temperature as the input does
+    pub fn apply(self, curve: &CurveData) -> CurveData {
+        let mut capped = curve.clone();
+        let Some(duty) = self.max_duty() else {
+            return capped;
+        };
+        let cap = (u32::from(duty) * 255 / 100) as u8;
+        for (pwm, temp) in capped.pwm.iter_mut().zip(curve.temp) {
+            if temp < ACOUSTIC_SAFETY_TEMP {
+                *pwm = (*pwm).min(cap);
+            }
+        }
+        capped
+    }
+
+    /// Check the preset leaves `curve` a point at or above
+    /// `ACOUSTIC_SAFETY_TEMP`, otherwise the cap would hold at any temperature
+    pub fn check(self, curve: &CurveData) -> Result<(), ProfileError> {
+        if self.max_duty().is_none() || curve.temp.iter().any(|t| *t >= ACOUSTIC_SAFETY_TEMP) {
+            return Ok(());
+        }
+        Err(ProfileError::AcousticUnsafe(curve.fan))
+    }
+}
+
+impl fmt::Display for AcousticPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.approx_dba() {
+            Some(dba) => write!(f, "{self:?} (<{dba}dBA)"),
+            None => write!(f, "{self:?}"),
+        }
+    }
+}
+
+impl std::str::FromStr for AcousticPreset {
+    type Err = ProfileError;
+
+    fn from_str(preset: &str) -> Result<Self, Self::Err> {
+        match preset.to_ascii_lowercase().trim() {
+            "off" => Ok(Self::Off),
+            "silent" => Ok(Self::Silent),
+            "balanced" => Ok(Self::Balanced),
+            "max" => Ok(Self::Max),
+            _ => Err(ProfileError::ParseProfileName),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AcousticPreset;
+    use crate::error::ProfileError;
+    use crate::fan_curve_set::CurveData;
+
+    fn curve(temp: [u8; 8]) -> CurveData {
+        CurveData {
+            pwm: [
+                10, 50, 90, 130, 170, 200, 230, 255,
+            ],
+            temp,
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn caps_below_safety_temp() {
+        let curve = curve([
+            30, 40, 50, 60, 70, 80, 90, 100,
+        ]);
+        assert_eq!(AcousticPreset::Off.apply(&curve).pwm, curve.pwm);
+        assert_eq!(AcousticPreset::Max.apply(&curve).pwm, curve.pwm);
+        let silent = AcousticPreset::Silent.apply(&curve);
+        assert_eq!(silent.pwm, [10, 50, 89, 89, 89, 89, 230, 255]);
+        assert!(silent.pwm.windows(2).all(|w| w[0] <= w[1]));
+        assert!(AcousticPreset::Silent.check(&curve).is_ok());
+    }
+
+    #[test]
+    fn rejects_curve_without_hot_point() {
+        let cool = curve([
+            30, 35, 40, 45, 50, 55, 60, 65,
+        ]);
+        assert!(matches!(
+            AcousticPreset::Balanced.check(&cool),
+            Err(ProfileError::AcousticUnsafe(_))
+        ));
+        assert!(AcousticPreset::Max.check(&cool).is_ok());
+        assert_eq!(
+            "silent".parse::<AcousticPreset>().unwrap(),
+            AcousticPreset::Silent
+        );
+    }
+}
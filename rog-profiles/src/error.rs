@@ -3,6 +3,9 @@ use std::fmt;
 use log::error;
 use zbus::fdo::Error as FdoErr;
 
+use crate::acoustic::ACOUSTIC_SAFETY_TEMP;
+use crate::FanCurvePU;
+
 #[derive(Debug)]
 pub enum ProfileError {
     Path(String, std::io::Error),
@@ -17,6 +20,8 @@ pub enum ProfileError {
     ParseFanCurvePrevHigher(&'static str, u8, u8),
     ParseFanCurvePercentOver100(u8),
     NotEnoughPoints, // Zbus(zbus::Error),
+    /// The curve of the fan has no point hot enough to escape the acoustic cap
+    AcousticUnsafe(FanCurvePU),
 }
 
 impl fmt::Display for ProfileError {
@@ -41,7 +46,12 @@ impl fmt::Display for ProfileError {
             ),
             ProfileError::ParseFanCurvePercentOver100(value) => {
                 write!(f, "Invalid percentage, {} is higher than 100", value)
-            } // Error::Zbus(detail) => write!(f, "Zbus error: {}", detail),
+            }
+            ProfileError::AcousticUnsafe(fan) => write!(
+                f,
+                "The {fan:?} fan curve needs a point at or above {ACOUSTIC_SAFETY_TEMP}°C to use \
+                 an acoustic preset"
+            ), // Error::Zbus(detail) => write!(f, "Zbus error: {}", detail),
         }
     }
 }
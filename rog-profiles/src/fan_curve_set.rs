@@ -1,6 +1,6 @@
 use log::{error, trace};
+use rog_platform::sysfs::SysfsDevice;
 use serde::{Deserialize, Serialize};
-use udev::Device;
 #[cfg(feature = "dbus")]
 use zbus::zvariant::Type;
 
@@ -136,7 +136,7 @@ impl CurveData {
         self.fan = fan;
     }
 
-    fn set_val_from_attr(tmp: &str, device: &Device, buf: &mut [u8; 8]) {
+    fn set_val_from_attr(tmp: &str, device: &SysfsDevice, buf: &mut [u8; 8]) {
         if let Some(n) = tmp.chars().nth(15) {
             let i = n.to_digit(10).unwrap() as usize;
             let d = device.attribute_value(tmp).unwrap();
@@ -145,22 +145,21 @@ impl CurveData {
         }
     }
 
-    pub fn read_from_device(&mut self, device: &Device) {
-        for attr in device.attributes() {
-            let tmp = attr.name().to_string_lossy();
+    pub fn read_from_device(&mut self, device: &SysfsDevice) {
+        for tmp in device.attribute_names() {
             let pwm_num: char = self.fan.into();
             let pwm = format!("pwm{pwm_num}");
             if tmp.starts_with(&pwm) && tmp.ends_with("_temp") {
-                Self::set_val_from_attr(tmp.as_ref(), device, &mut self.temp);
+                Self::set_val_from_attr(&tmp, device, &mut self.temp);
             }
             if tmp.starts_with(&pwm) && tmp.ends_with("_pwm") {
-                Self::set_val_from_attr(tmp.as_ref(), device, &mut self.pwm);
+                Self::set_val_from_attr(&tmp, device, &mut self.pwm);
             }
         }
     }
 
     /// Write this curve to the device fan specified by `self.fan`
-    pub fn write_to_device(&self, device: &mut Device) -> std::io::Result<()> {
+    pub fn write_to_device(&self, device: &mut SysfsDevice) -> std::io::Result<()> {
         let pwm_num: char = self.fan.into();
         let enable = if self.enabled { '1' } else { '2' };
 
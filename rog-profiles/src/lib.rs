@@ -1,12 +1,15 @@
+pub mod acoustic;
 pub mod error;
 pub mod fan_curve_set;
 
+use acoustic::AcousticPreset;
 use error::ProfileError;
 use fan_curve_set::CurveData;
 use log::debug;
 use rog_platform::platform::PlatformProfile;
+use rog_platform::simulate;
+pub use rog_platform::sysfs::SysfsDevice;
 use serde::{Deserialize, Serialize};
-pub use udev::Device;
 #[cfg(feature = "dbus")]
 use zbus::zvariant::Type;
 use zbus::zvariant::{OwnedValue, Value};
@@ -15,7 +18,11 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Use udev system to find the fan curve path/node which is labelled with
 /// "asus_custom_fan_curve" in the kernel
-pub fn find_fan_curve_node() -> Result<Device, ProfileError> {
+pub fn find_fan_curve_node() -> Result<SysfsDevice, ProfileError> {
+    if let Some(path) = simulate::path(simulate::FAN_CURVES) {
+        return Ok(SysfsDevice::Simulated(path));
+    }
+
     let mut enumerator = udev::Enumerator::new()?;
     enumerator.match_subsystem("hwmon")?;
 
@@ -23,7 +30,7 @@ pub fn find_fan_curve_node() -> Result<Device, ProfileError> {
         if device.parent_with_subsystem("platform")?.is_some() {
             if let Some(name) = device.attribute_value("name") {
                 if name == "asus_custom_fan_curve" {
-                    return Ok(device);
+                    return Ok(device.into());
                 }
             }
         }
@@ -45,7 +52,7 @@ pub enum FanCurvePU {
 }
 
 impl FanCurvePU {
-    fn which_fans(device: &Device) -> Vec<Self> {
+    fn which_fans(device: &SysfsDevice) -> Vec<Self> {
         let mut fans = Vec::with_capacity(3);
         for fan in [
             Self::CPU,
@@ -55,9 +62,8 @@ impl FanCurvePU {
             let pwm_num: char = fan.into();
             let pwm_enable = format!("pwm{pwm_num}_enable");
             debug!("Looking for {pwm_enable}");
-            for attr in device.attributes() {
-                let tmp = attr.name().to_string_lossy();
-                if tmp.contains(&pwm_enable) {
+            for name in device.attribute_names() {
+                if name.contains(&pwm_enable) {
                     debug!("Found {pwm_enable}");
                     fans.push(fan);
                 }
@@ -108,12 +114,15 @@ impl Default for FanCurvePU {
 
 /// Main purpose of `FanCurves` is to enable restoring state on system boot
 #[cfg_attr(feature = "dbus", derive(Type))]
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
 pub struct FanCurveProfiles {
     pub balanced: Vec<CurveData>,
     pub performance: Vec<CurveData>,
     pub quiet: Vec<CurveData>,
     pub custom: Vec<CurveData>,
+    /// Caps the duty of every curve as it is written, see [`AcousticPreset`]
+    #[serde(default)]
+    pub acoustic: AcousticPreset,
 }
 
 impl FanCurveProfiles {
@@ -127,7 +136,7 @@ impl FanCurveProfiles {
     pub fn read_from_dev_profile(
         &mut self,
         profile: PlatformProfile,
-        device: &Device,
+        device: &SysfsDevice,
     ) -> Result<(), ProfileError> {
         let fans = Self::supported_fans()?;
         let mut curves = Vec::with_capacity(3);
@@ -160,7 +169,7 @@ impl FanCurveProfiles {
     pub fn set_active_curve_to_defaults(
         &mut self,
         profile: PlatformProfile,
-        device: &mut Device,
+        device: &mut SysfsDevice,
     ) -> Result<(), ProfileError> {
         let fans = Self::supported_fans()?;
         // Do reset for all
@@ -174,12 +183,14 @@ impl FanCurveProfiles {
     }
 
     /// Write the curves for the selected profile to the device. If the curve is
-    /// in the enabled list it will become active.
+    /// in the enabled list it will become active. The acoustic preset is
+    /// applied to what is written, the stored curves are left as they are.
     pub fn write_profile_curve_to_platform(
         &mut self,
         profile: PlatformProfile,
-        device: &mut Device,
+        device: &mut SysfsDevice,
     ) -> Result<(), ProfileError> {
+        let acoustic = self.acoustic;
         let fans = match profile {
             PlatformProfile::Balanced => &mut self.balanced,
             PlatformProfile::Performance => &mut self.performance,
@@ -188,13 +199,13 @@ impl FanCurveProfiles {
         };
         for fan in fans.iter().filter(|f| !f.enabled) {
             debug!("write_profile_curve_to_platform: writing profile:{profile}, {fan:?}");
-            fan.write_to_device(device)?;
+            acoustic.apply(fan).write_to_device(device)?;
         }
         // Write enabled fans last because the kernel currently resets *all* if one is
         // disabled
         for fan in fans.iter().filter(|f| f.enabled) {
             debug!("write_profile_curve_to_platform: writing profile:{profile}, {fan:?}");
-            fan.write_to_device(device)?;
+            acoustic.apply(fan).write_to_device(device)?;
         }
         Ok(())
     }
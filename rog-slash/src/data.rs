@@ -135,6 +135,24 @@ impl Display for SlashMode {
 }
 
 impl SlashMode {
+    /// The mode played for a notification blip. `icon_or_text` may name a
+    /// mode, otherwise the AniMe icon names are mapped to a similar mode and
+    /// anything else is `Buzzer`.
+    pub fn for_blip(icon_or_text: &str) -> Self {
+        let name = icon_or_text.trim();
+        if let Some(mode) = SlashMode::list()
+            .iter()
+            .find(|m| m.eq_ignore_ascii_case(name))
+        {
+            return SlashMode::from_str(mode).unwrap_or_default();
+        }
+        match name.to_ascii_lowercase().as_str() {
+            "mail" | "chat" | "message" => SlashMode::Transmission,
+            "alert" | "battery" | "battery-low" | "battery_low" => SlashMode::Hazard,
+            _ => SlashMode::Buzzer,
+        }
+    }
+
     pub fn list() -> [String; 15] {
         [
             SlashMode::Bounce.to_string(),
@@ -0,0 +1,290 @@
+//! A window showing what asusd is sending to the keyboard and AniMe display,
+//! so effects can be developed without the hardware, or while it is in use.
+//!
+//! The keyboard is drawn from the layout asusd uses, and both it and the AniMe
+//! display show the frames asusd writes, which it sends while the
+//! `StreamFrames` property of each is set. The firmware animates builtin
+//! keyboard modes, so these are shown in their first colour.
+//!
+//! Run with the AniMe model, such as `rog-simulator GA402`. The model is
+//! detected from DMI if not given.
+
+use std::env;
+use std::error::Error;
+use std::str::FromStr;
+
+use futures_util::StreamExt;
+use log::{info, warn};
+use rog_anime::{AnimeImage, AnimeType};
+use rog_aura::keyboard::{LayoutInfo, LayoutKey, LedCode, LedUsbPackets};
+use rog_aura::Colour;
+use rog_dbus::find_iface_async;
+use rog_dbus::zbus_anime::AnimeProxy;
+use rog_dbus::zbus_aura::AuraProxy;
+use slint::{ComponentHandle, Image, Rgb8Pixel, SharedPixelBuffer, Weak};
+
+slint::slint! {
+    export component SimulatorWindow inherits Window {
+        title: "ROG Simulator";
+        background: #181818;
+        in property <image> keyboard;
+        in property <image> anime;
+        in property <string> keyboard-status: "No Aura keyboard found";
+        in property <string> anime-status: "No AniMe display found";
+
+        VerticalLayout {
+            padding: 12px;
+            spacing: 8px;
+            Text {
+                text: root.keyboard-status;
+                color: #e0e0e0;
+            }
+            Image {
+                source: root.keyboard;
+                image-fit: contain;
+            }
+            Text {
+                text: root.anime-status;
+                color: #e0e0e0;
+            }
+            Image {
+                source: root.anime;
+                image-fit: contain;
+            }
+        }
+    }
+}
+
+/// Pixels per key unit, a typical key such as `A` is one unit wide
+const KEY_SCALE: f64 = 32.0;
+/// Pixels per AniMe LED column
+const LED_SCALE: f32 = 10.0;
+const BACKGROUND: Rgb8Pixel = Rgb8Pixel {
+    r: 24,
+    g: 24,
+    b: 24,
+};
+
+fn fill_rect(
+    buf: &mut SharedPixelBuffer<Rgb8Pixel>,
+    (x, y): (usize, usize),
+    (w, h): (usize, usize),
+    colour: Rgb8Pixel,
+) {
+    let (width, height) = (buf.width() as usize, buf.height() as usize);
+    let pixels = buf.make_mut_slice();
+    for row in y.min(height)..(y + h).min(height) {
+        pixels[row * width + x.min(width)..row * width + (x + w).min(width)].fill(colour);
+    }
+}
+
+/// The four zones of a zoned keyboard, left to right
+const KEYBOARD_ZONES: [LedCode; 4] = [
+    LedCode::ZonedKbLeft,
+    LedCode::ZonedKbLeftMid,
+    LedCode::ZonedKbRightMid,
+    LedCode::ZonedKbRight,
+];
+const UNLIT: Colour = Colour { r: 0, g: 0, b: 0 };
+
+/// The colour of `key` in `frame`. On a zoned keyboard a key takes the colour
+/// of the quarter of the keyboard it is in, as the layouts don't say which
+/// zone each key is in.
+fn key_colour(layout: &LayoutInfo, key: &LayoutKey, frame: &LedUsbPackets) -> Colour {
+    let Some(led) = key.led_code() else {
+        return UNLIT;
+    };
+    if !frame.zoned() || led.is_lightbar_zone() {
+        return frame.colour(led).unwrap_or(UNLIT);
+    }
+    let quarter = ((key.x + key.width / 2.0) / layout.width * 4.0) as usize;
+    frame
+        .colour(KEYBOARD_ZONES[quarter.min(3)])
+        .unwrap_or(UNLIT)
+}
+
+fn draw_keyboard(layout: &LayoutInfo, frame: &LedUsbPackets) -> SharedPixelBuffer<Rgb8Pixel> {
+    let mut buf = SharedPixelBuffer::new(
+        (layout.width * KEY_SCALE).ceil().max(1.0) as u32,
+        (layout.height * KEY_SCALE).ceil().max(1.0) as u32,
+    );
+    buf.make_mut_slice().fill(BACKGROUND);
+    for key in &layout.keys {
+        let colour = key_colour(layout, key, frame);
+        // Inset each key so there is a gap between them
+        fill_rect(
+            &mut buf,
+            (
+                (key.x * KEY_SCALE) as usize + 2,
+                (key.y * KEY_SCALE) as usize + 2,
+            ),
+            (
+                ((key.width * KEY_SCALE) as usize).saturating_sub(4),
+                ((key.height * KEY_SCALE) as usize).saturating_sub(4),
+            ),
+            Rgb8Pixel {
+                r: colour.r,
+                g: colour.g,
+                b: colour.b,
+            },
+        );
+    }
+    buf
+}
+
+/// Draw a frame in the `AnimeDataBuffer` layout. Alternate rows are offset by
+/// half an LED, so rows are half as far apart as columns.
+fn draw_anime(anime_type: AnimeType, frame: &[u8]) -> SharedPixelBuffer<Rgb8Pixel> {
+    let positions = AnimeImage::generate_image_positioning(anime_type);
+    let (width, height) = positions
+        .iter()
+        .flatten()
+        .fold((0.0f32, 0.0f32), |(w, h), led| {
+            (w.max(led.x()), h.max(led.y()))
+        });
+    let mut buf = SharedPixelBuffer::new(
+        ((width + 2.0) * LED_SCALE) as u32,
+        ((height + 2.0) * LED_SCALE / 2.0) as u32,
+    );
+    buf.make_mut_slice().fill(BACKGROUND);
+    // The GA401 buffer starts with an unused byte
+    let data = if anime_type == AnimeType::GA401 {
+        frame.get(1..).unwrap_or_default()
+    } else {
+        frame
+    };
+    for (led, bright) in positions.iter().zip(data) {
+        let Some(led) = led else {
+            continue;
+        };
+        let x = ((led.x() + 1.0) * LED_SCALE) as usize;
+        let y = (led.y() * LED_SCALE / 2.0) as usize;
+        fill_rect(
+            &mut buf,
+            (x, y),
+            (LED_SCALE as usize - 2, LED_SCALE as usize / 2 - 1),
+            Rgb8Pixel {
+                r: *bright,
+                g: *bright,
+                b: *bright,
+            },
+        );
+    }
+    buf
+}
+
+async fn watch_keyboard(aura: AuraProxy<'static>, window: Weak<SimulatorWindow>) {
+    let layout = match aura.keyboard_layout().await {
+        Ok(layout) => layout,
+        Err(e) => {
+            warn!("Could not get the keyboard layout: {e}");
+            return;
+        }
+    };
+    let mut frames = match aura.receive_frame().await {
+        Ok(frames) => frames,
+        Err(e) => {
+            warn!("Could not receive keyboard frames: {e}");
+            return;
+        }
+    };
+    if let Err(e) = aura.set_stream_frames(true).await {
+        warn!("Could not start keyboard frame streaming: {e}");
+        return;
+    }
+    let status = layout.name.clone();
+    window
+        .upgrade_in_event_loop(move |w| w.set_keyboard_status(status.into()))
+        .ok();
+    while let Some(frame) = frames.next().await {
+        let Ok(args) = frame.args() else {
+            continue;
+        };
+        let frame = LedUsbPackets::from_packets(args.data().clone());
+        let buf = draw_keyboard(&layout, &frame);
+        window
+            .upgrade_in_event_loop(move |w| w.set_keyboard(Image::from_rgb8(buf)))
+            .ok();
+    }
+}
+
+async fn watch_anime(
+    anime: AnimeProxy<'static>,
+    anime_type: AnimeType,
+    window: Weak<SimulatorWindow>,
+) {
+    let mut frames = match anime.receive_frame().await {
+        Ok(frames) => frames,
+        Err(e) => {
+            warn!("Could not receive AniMe frames: {e}");
+            return;
+        }
+    };
+    if let Err(e) = anime.set_stream_frames(true).await {
+        warn!("Could not start AniMe frame streaming: {e}");
+        return;
+    }
+    let status = format!("AniMe {anime_type:?}");
+    window
+        .upgrade_in_event_loop(move |w| w.set_anime_status(status.into()))
+        .ok();
+    while let Some(frame) = frames.next().await {
+        let Ok(args) = frame.args() else {
+            continue;
+        };
+        let buf = draw_anime(anime_type, args.data());
+        window
+            .upgrade_in_event_loop(move |w| w.set_anime(Image::from_rgb8(buf)))
+            .ok();
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut logger = env_logger::Builder::new();
+    logger
+        .parse_default_env()
+        .target(env_logger::Target::Stdout)
+        .format_timestamp(None)
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let anime_type = match env::args().nth(1) {
+        Some(arg) => AnimeType::from_str(&arg)?,
+        None => AnimeType::from_dmi(),
+    };
+    let anime_type = if anime_type == AnimeType::Unsupported {
+        info!("No AniMe model given or detected, using GA402");
+        AnimeType::GA402
+    } else {
+        anime_type
+    };
+
+    let rt = tokio::runtime::Runtime::new()?;
+    let auras = rt
+        .block_on(find_iface_async::<AuraProxy>("xyz.ljones.Aura"))
+        .unwrap_or_default();
+    let animes = rt
+        .block_on(find_iface_async::<AnimeProxy>("xyz.ljones.Anime"))
+        .unwrap_or_default();
+
+    let window = SimulatorWindow::new()?;
+    if let Some(aura) = auras.first() {
+        rt.spawn(watch_keyboard(aura.clone(), window.as_weak()));
+    }
+    if let Some(anime) = animes.first() {
+        rt.spawn(watch_anime(anime.clone(), anime_type, window.as_weak()));
+    }
+    window.run()?;
+
+    if let Some(aura) = auras.first() {
+        rt.block_on(aura.set_stream_frames(false))
+            .map_err(|e| warn!("Could not stop keyboard frame streaming: {e}"))
+            .ok();
+    }
+    for anime in &animes {
+        rt.block_on(anime.set_stream_frames(false))
+            .map_err(|e| warn!("Could not stop AniMe frame streaming: {e}"))
+            .ok();
+    }
+    Ok(())
+}